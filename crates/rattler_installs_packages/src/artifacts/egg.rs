@@ -0,0 +1,470 @@
+//! Conversion of legacy `.egg` archives into wheels.
+//!
+//! Eggs predate wheels and are no longer supported by modern installers. The conversion implemented
+//! here mirrors `wheel convert` from the `wheel` package: the `EGG-INFO` directory is turned into a
+//! `.dist-info` directory and all other files are copied verbatim.
+
+use super::Wheel;
+use crate::types::{
+    ArtifactFromBytes, EggFilename, NormalizedPackageName, RFC822ish, RecordEntry, WheelFilename,
+};
+use crate::utils::ReadAndSeek;
+use data_encoding::BASE64URL_NOPAD;
+use fs_err as fs;
+use miette::IntoDiagnostic;
+use parking_lot::Mutex;
+use rattler_digest::Sha256;
+use std::ffi::OsStr;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+use std::str::FromStr;
+use thiserror::Error;
+use zip::{result::ZipError, write::FileOptions, ZipArchive, ZipWriter};
+
+/// The name of the metadata directory inside an egg.
+const EGG_INFO: &str = "EGG-INFO";
+
+/// A legacy `.egg` archive.
+///
+/// Eggs cannot be installed directly, use [`Egg::convert_to_wheel`] or [`Egg::to_wheel`] to
+/// convert the egg to a wheel first.
+pub struct Egg {
+    /// Name of the egg
+    pub name: EggFilename,
+
+    archive: Mutex<ZipArchive<Box<dyn ReadAndSeek + Send>>>,
+}
+
+/// An error that can occur when converting an egg to a wheel.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ConvertEggError {
+    #[error("failed to read {0} from the egg")]
+    ZipError(String, #[source] ZipError),
+
+    #[error("the egg does not contain {EGG_INFO}/PKG-INFO")]
+    MissingPkgInfo,
+
+    #[error("failed to parse {EGG_INFO}/PKG-INFO")]
+    InvalidPkgInfo(#[source] <RFC822ish as FromStr>::Err),
+
+    #[error("invalid requirement section '{0}' in {EGG_INFO}/requires.txt")]
+    InvalidRequiresSection(String),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+}
+
+impl Egg {
+    /// Open an egg by reading a file on disk.
+    pub fn from_path(path: &Path) -> miette::Result<Self> {
+        let file_name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .ok_or_else(|| miette::miette!("path does not contain a filename"))?;
+        let egg_name = EggFilename::from_str(file_name).into_diagnostic()?;
+        let file = fs::File::open(path).into_diagnostic()?;
+        Self::from_bytes(egg_name, Box::new(file))
+    }
+
+    /// Open an egg from its name and content.
+    pub fn from_bytes(
+        name: EggFilename,
+        bytes: Box<dyn ReadAndSeek + Send>,
+    ) -> miette::Result<Self> {
+        Ok(Self {
+            name,
+            archive: Mutex::new(ZipArchive::new(bytes).into_diagnostic()?),
+        })
+    }
+
+    /// Returns the name of the wheel that is produced when converting this egg.
+    ///
+    /// Eggs that target a specific platform are assumed to be built for CPython.
+    pub fn wheel_name(&self) -> WheelFilename {
+        let python_tag = match &self.name.python_version {
+            Some(version) => format!("py{}", version.replace('.', "")),
+            None => String::from("py2.py3"),
+        };
+        let (python_tag, arch_tag) = match &self.name.platform {
+            Some(platform) => (
+                python_tag.replacen("py", "cp", 1),
+                platform.replace(['.', '-'], "_"),
+            ),
+            None => (python_tag, String::from("any")),
+        };
+
+        WheelFilename {
+            distribution: self.name.distribution.clone(),
+            version: self.name.version.clone(),
+            build_tag: None,
+            py_tags: python_tag.split('.').map(ToOwned::to_owned).collect(),
+            abi_tags: vec![String::from("none")],
+            arch_tags: vec![arch_tag],
+        }
+    }
+
+    /// Converts the egg to a wheel and writes the wheel archive to `writer`. Returns the name of
+    /// the resulting wheel.
+    pub fn convert_to_wheel<W: Write + Seek>(
+        &self,
+        writer: W,
+    ) -> Result<WheelFilename, ConvertEggError> {
+        let wheel_name = self.wheel_name();
+        let dist_info = format!(
+            "{}-{}.dist-info",
+            wheel_name.distribution.as_str().replace('-', "_"),
+            wheel_name.version
+        );
+
+        let mut archive = self.archive.lock();
+        let mut writer = RecordingZipWriter::new(writer);
+
+        let mut pkg_info = None;
+        let mut requires = None;
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|e| ConvertEggError::ZipError(format!("<index {index}>"), e))?;
+            if entry.is_dir() {
+                continue;
+            }
+            let Some(path) = entry
+                .enclosed_name()
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+            else {
+                continue;
+            };
+
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+
+            match path
+                .strip_prefix(EGG_INFO)
+                .and_then(|p| p.strip_prefix('/'))
+            {
+                Some("PKG-INFO") => pkg_info = Some(bytes),
+                Some("requires.txt") => requires = Some(bytes),
+                // Metadata files that are also recognized in a .dist-info directory
+                Some(
+                    name @ ("entry_points.txt"
+                    | "top_level.txt"
+                    | "namespace_packages.txt"
+                    | "dependency_links.txt"),
+                ) => writer.write_file(&format!("{dist_info}/{name}"), &bytes)?,
+                // All other egg specific metadata (e.g. SOURCES.txt or zip-safe) is dropped
+                Some(_) => {}
+                None => writer.write_file(&path, &bytes)?,
+            }
+        }
+
+        let pkg_info = pkg_info.ok_or(ConvertEggError::MissingPkgInfo)?;
+        let metadata = pkg_info_to_metadata(
+            &String::from_utf8_lossy(&pkg_info),
+            requires.as_deref().map(String::from_utf8_lossy).as_deref(),
+        )?;
+        writer.write_file(&format!("{dist_info}/METADATA"), metadata.as_bytes())?;
+
+        let root_is_purelib = self.name.platform.is_none();
+        let mut wheel = format!(
+            "Wheel-Version: 1.0\nGenerator: {} ({})\nRoot-Is-Purelib: {root_is_purelib}\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+        );
+        for tag in wheel_name.all_tags_iter() {
+            wheel.push_str(&format!("Tag: {tag}\n"));
+        }
+        writer.write_file(&format!("{dist_info}/WHEEL"), wheel.as_bytes())?;
+
+        writer.finish(&format!("{dist_info}/RECORD"))?;
+
+        Ok(wheel_name)
+    }
+
+    /// Converts the egg to a wheel in memory.
+    pub fn to_wheel(&self) -> miette::Result<Wheel> {
+        let mut bytes = Cursor::new(Vec::new());
+        let wheel_name = self.convert_to_wheel(&mut bytes).into_diagnostic()?;
+        bytes.rewind().into_diagnostic()?;
+        Wheel::from_bytes(wheel_name, Box::new(bytes))
+    }
+
+    /// Converts the egg on disk to a wheel placed in `dest_dir`. Returns the wheel.
+    pub fn convert_path(path: &Path, dest_dir: &Path) -> miette::Result<Wheel> {
+        let egg = Self::from_path(path)?;
+        let wheel_path = dest_dir.join(egg.wheel_name().to_string());
+        let file = fs::File::create(&wheel_path).into_diagnostic()?;
+        egg.convert_to_wheel(file).into_diagnostic()?;
+        let normalized_name: NormalizedPackageName = egg.name.distribution.clone().into();
+        Wheel::from_path(&wheel_path, &normalized_name)
+    }
+}
+
+/// A wrapper around a [`ZipWriter`] that keeps track of all written files so a RECORD file can be
+/// written at the end.
+struct RecordingZipWriter<W: Write + Seek> {
+    writer: ZipWriter<W>,
+    records: Vec<RecordEntry>,
+}
+
+impl<W: Write + Seek> RecordingZipWriter<W> {
+    fn new(writer: W) -> Self {
+        Self {
+            writer: ZipWriter::new(writer),
+            records: Vec::new(),
+        }
+    }
+
+    fn write_file(&mut self, path: &str, bytes: &[u8]) -> Result<(), ConvertEggError> {
+        self.writer
+            .start_file(path, FileOptions::default())
+            .map_err(|e| ConvertEggError::ZipError(path.to_owned(), e))?;
+        self.writer.write_all(bytes)?;
+
+        let digest = rattler_digest::compute_bytes_digest::<Sha256>(bytes);
+        self.records.push(RecordEntry {
+            path: path.to_owned(),
+            hash: Some(format!("sha256={}", BASE64URL_NOPAD.encode(&digest))),
+            size: Some(bytes.len() as u64),
+        });
+        Ok(())
+    }
+
+    fn finish(mut self, record_path: &str) -> Result<W, ConvertEggError> {
+        self.records.push(RecordEntry {
+            path: record_path.to_owned(),
+            hash: None,
+            size: None,
+        });
+
+        let mut record = csv::WriterBuilder::new()
+            .has_headers(false)
+            .escape(b'"')
+            .from_writer(Vec::new());
+        for entry in self.records.iter() {
+            record
+                .serialize(entry)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        }
+        let record = record
+            .into_inner()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        self.writer
+            .start_file(record_path, FileOptions::default())
+            .map_err(|e| ConvertEggError::ZipError(record_path.to_owned(), e))?;
+        self.writer.write_all(&record)?;
+        self.writer
+            .finish()
+            .map_err(|e| ConvertEggError::ZipError(record_path.to_owned(), e))
+    }
+}
+
+/// Converts the contents of a `PKG-INFO` file and an optional `requires.txt` file to a `METADATA`
+/// file. The dependencies from `requires.txt` are converted to `Requires-Dist` and `Provides-Extra`
+/// fields.
+fn pkg_info_to_metadata(pkg_info: &str, requires: Option<&str>) -> Result<String, ConvertEggError> {
+    // Make sure the PKG-INFO is valid
+    let mut parsed = RFC822ish::from_str(pkg_info).map_err(ConvertEggError::InvalidPkgInfo)?;
+    let has_requires_dist = !parsed.take_all("Requires-Dist").is_empty();
+
+    let (headers, body) = match pkg_info.split_once("\n\n") {
+        Some((headers, body)) => (headers, Some(body)),
+        None => (pkg_info.trim_end(), None),
+    };
+
+    let mut metadata = String::from("Metadata-Version: 2.1\n");
+    for line in headers.lines() {
+        if !line.to_ascii_lowercase().starts_with("metadata-version:") {
+            metadata.push_str(line);
+            metadata.push('\n');
+        }
+    }
+
+    if let (Some(requires), false) = (requires, has_requires_dist) {
+        let (extras, requires_dist) = parse_requires_txt(requires)?;
+        for extra in extras {
+            metadata.push_str(&format!("Provides-Extra: {extra}\n"));
+        }
+        for requirement in requires_dist {
+            metadata.push_str(&format!("Requires-Dist: {requirement}\n"));
+        }
+    }
+
+    if let Some(body) = body {
+        metadata.push('\n');
+        metadata.push_str(body);
+    }
+
+    Ok(metadata)
+}
+
+/// Parses a setuptools `requires.txt` file. Returns the extras that are defined and the
+/// requirements in the format of a `Requires-Dist` field.
+///
+/// The file contains a list of requirements, optionally grouped in sections. A section looks like
+/// `[extra]`, `[extra:marker]` or `[:marker]`.
+fn parse_requires_txt(requires: &str) -> Result<(Vec<String>, Vec<String>), ConvertEggError> {
+    let mut extras = Vec::new();
+    let mut requirements = Vec::new();
+
+    let mut section_marker: Option<String> = None;
+    for line in requires.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[') {
+            let section = section
+                .strip_suffix(']')
+                .ok_or_else(|| ConvertEggError::InvalidRequiresSection(line.to_owned()))?;
+            let (extra, marker) = match section.split_once(':') {
+                Some((extra, marker)) => (extra.trim(), Some(marker.trim())),
+                None => (section.trim(), None),
+            };
+
+            let extra_marker = if extra.is_empty() {
+                None
+            } else {
+                if !extras.iter().any(|e| e == extra) {
+                    extras.push(extra.to_owned());
+                }
+                Some(format!("extra == \"{extra}\""))
+            };
+
+            section_marker = match (marker, extra_marker) {
+                (Some(marker), Some(extra_marker)) => {
+                    Some(format!("({marker}) and {extra_marker}"))
+                }
+                (Some(marker), None) => Some(marker.to_owned()),
+                (None, extra_marker) => extra_marker,
+            };
+            continue;
+        }
+
+        let requirement = match (&section_marker, line.split_once(';')) {
+            (None, _) => line.to_owned(),
+            (Some(section_marker), None) => format!("{line} ; {section_marker}"),
+            (Some(section_marker), Some((requirement, marker))) => format!(
+                "{} ; ({}) and {section_marker}",
+                requirement.trim(),
+                marker.trim()
+            ),
+        };
+        requirements.push(requirement);
+    }
+
+    Ok((extras, requirements))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Extra;
+
+    fn make_egg(name: &str, files: &[(&str, &str)]) -> Egg {
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = ZipWriter::new(&mut bytes);
+        for (path, content) in files {
+            writer.start_file(*path, FileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+        bytes.rewind().unwrap();
+
+        Egg::from_bytes(name.parse().unwrap(), Box::new(bytes)).unwrap()
+    }
+
+    #[test]
+    fn test_requires_txt() {
+        let (extras, requirements) = parse_requires_txt(
+            "requests>=2\n\n[security]\npyOpenSSL\n\n[:python_version < \"3\"]\nenum34\n\n[socks:sys_platform == \"win32\"]\nwin_inet_pton; python_version < \"3.8\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(extras, vec!["security", "socks"]);
+        assert_eq!(
+            requirements,
+            vec![
+                "requests>=2",
+                "pyOpenSSL ; extra == \"security\"",
+                "enum34 ; python_version < \"3\"",
+                "win_inet_pton ; (python_version < \"3.8\") and (sys_platform == \"win32\") and extra == \"socks\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_pure_egg() {
+        let egg = make_egg(
+            "foo_bar-1.0-py3.9.egg",
+            &[
+                (
+                    "EGG-INFO/PKG-INFO",
+                    "Metadata-Version: 1.1\nName: foo-bar\nVersion: 1.0\nSummary: An egg\n\nA long description\n",
+                ),
+                ("EGG-INFO/requires.txt", "six\n\n[test]\npytest\n"),
+                ("EGG-INFO/entry_points.txt", "[console_scripts]\nfoo = foo_bar:main\n"),
+                ("EGG-INFO/SOURCES.txt", "foo_bar/__init__.py\n"),
+                ("EGG-INFO/zip-safe", "\n"),
+                ("foo_bar/__init__.py", "def main(): pass\n"),
+            ],
+        );
+
+        assert_eq!(
+            egg.wheel_name().to_string(),
+            "foo_bar-1.0-py39-none-any.whl"
+        );
+
+        let wheel = egg.to_wheel().unwrap();
+        let (_, metadata) = wheel.metadata().unwrap();
+        assert_eq!(metadata.name, "foo-bar".parse().unwrap());
+        assert_eq!(metadata.version, "1.0".parse().unwrap());
+        assert_eq!(metadata.requires_dist.len(), 2);
+        assert!(metadata.extras.contains(&"test".parse::<Extra>().unwrap()));
+
+        // Make sure the wheel can actually be installed
+        let tmpdir = tempfile::tempdir().unwrap();
+        let unpacked = wheel
+            .unpack(
+                tmpdir.path(),
+                &crate::artifacts::wheel::InstallPaths::for_venv((3, 9, 0), false),
+                Path::new("/invalid"),
+                &Default::default(),
+            )
+            .unwrap();
+        assert!(unpacked.dist_info.join("entry_points.txt").is_file());
+        assert!(!unpacked.dist_info.join("SOURCES.txt").exists());
+        assert!(unpacked
+            .dist_info
+            .parent()
+            .unwrap()
+            .join("foo_bar/__init__.py")
+            .is_file());
+    }
+
+    #[test]
+    fn test_platform_egg_name() {
+        let egg = make_egg(
+            "lxml-4.9.0-py3.9-linux-x86_64.egg",
+            &[(
+                "EGG-INFO/PKG-INFO",
+                "Metadata-Version: 2.1\nName: lxml\nVersion: 4.9.0\n",
+            )],
+        );
+        assert_eq!(
+            egg.wheel_name().to_string(),
+            "lxml-4.9.0-cp39-none-linux_x86_64.whl"
+        );
+    }
+
+    #[test]
+    fn test_missing_pkg_info() {
+        let egg = make_egg("foo-1.0-py3.9.egg", &[("foo/__init__.py", "")]);
+        assert!(matches!(
+            egg.convert_to_wheel(Cursor::new(Vec::new())),
+            Err(ConvertEggError::MissingPkgInfo)
+        ));
+    }
+}
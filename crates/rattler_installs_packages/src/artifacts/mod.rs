@@ -1,10 +1,16 @@
 //! Module containing artifacts that can be resolved and installed.
+mod egg;
+
+mod registry;
+
 mod sdist;
 
 mod stree;
 /// Module for working with PyPA wheels. Contains the [`Wheel`] type, and related functionality.
 pub mod wheel;
 
+pub use egg::{ConvertEggError, Egg};
+pub use registry::{ArtifactKind, ArtifactRegistry};
 pub use sdist::SDist;
 pub use stree::STree;
 pub use wheel::Wheel;
@@ -0,0 +1,202 @@
+//! A registry of artifact kinds that are not natively supported by this crate. Consumers can
+//! register their own [`ArtifactKind`] to have artifacts of that kind participate in candidate
+//! selection, download and caching alongside wheels and sdists.
+
+use crate::artifacts::Wheel;
+use crate::python_env::WheelTags;
+use crate::types::{ArtifactName, CustomArtifactName, NormalizedPackageName, PackageName, Version};
+use crate::utils::ReadAndSeek;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// A kind of artifact that can be registered in an [`ArtifactRegistry`].
+///
+/// Artifacts of a custom kind are treated as binary artifacts. They are downloaded and cached like
+/// any other artifact after which they are converted to a [`Wheel`] to extract their metadata and
+/// to install them.
+pub trait ArtifactKind: Send + Sync {
+    /// A unique name that identifies this kind of artifact, e.g. `bundle`.
+    fn name(&self) -> &str;
+
+    /// Returns the version of the distribution if the filename refers to an artifact of this kind
+    /// for the specified package. Returns `None` if the filename is not recognized.
+    fn parse_filename(
+        &self,
+        filename: &str,
+        package_name: &NormalizedPackageName,
+    ) -> Option<Version>;
+
+    /// Returns true if the artifact can be installed in an environment that supports the specified
+    /// tags. If `compatible_tags` is `None` compatibility should not be checked.
+    fn is_compatible(
+        &self,
+        _name: &CustomArtifactName,
+        _compatible_tags: Option<&WheelTags>,
+    ) -> bool {
+        true
+    }
+
+    /// Converts the (downloaded) contents of an artifact of this kind into a wheel.
+    fn to_wheel(
+        &self,
+        name: &CustomArtifactName,
+        bytes: Box<dyn ReadAndSeek + Send>,
+    ) -> miette::Result<Wheel>;
+}
+
+/// A collection of [`ArtifactKind`]s. Kinds are consulted in the order in which they were
+/// registered and only for filenames that are not recognized as a wheel or sdist.
+#[derive(Default, Clone)]
+pub struct ArtifactRegistry {
+    kinds: Vec<Arc<dyn ArtifactKind>>,
+}
+
+impl Debug for ArtifactRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.kinds.iter().map(|kind| kind.name()))
+            .finish()
+    }
+}
+
+impl ArtifactRegistry {
+    /// Registers a new kind of artifact.
+    pub fn register(&mut self, kind: impl ArtifactKind + 'static) {
+        self.kinds.push(Arc::new(kind));
+    }
+
+    /// Registers a new kind of artifact and returns the modified registry.
+    pub fn with_kind(mut self, kind: impl ArtifactKind + 'static) -> Self {
+        self.register(kind);
+        self
+    }
+
+    /// Returns the registered kind with the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn ArtifactKind> {
+        self.kinds
+            .iter()
+            .find(|kind| kind.name() == name)
+            .map(|kind| kind.as_ref())
+    }
+
+    /// Returns true if no kinds have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.kinds.is_empty()
+    }
+
+    /// Tries to recognize the filename with any of the registered kinds.
+    pub fn parse_filename(
+        &self,
+        filename: &str,
+        package_name: &NormalizedPackageName,
+    ) -> Option<ArtifactName> {
+        self.kinds.iter().find_map(|kind| {
+            let version = kind.parse_filename(filename, package_name)?;
+            Some(ArtifactName::Custom(CustomArtifactName {
+                kind: kind.name().to_owned(),
+                distribution: PackageName::from(package_name.clone()),
+                version,
+                filename: filename.to_owned(),
+            }))
+        })
+    }
+
+    /// Returns true if the custom artifact is compatible with the specified tags. Artifacts of an
+    /// unknown kind are never compatible.
+    pub fn is_compatible(
+        &self,
+        name: &CustomArtifactName,
+        compatible_tags: Option<&WheelTags>,
+    ) -> bool {
+        self.get(&name.kind)
+            .is_some_and(|kind| kind.is_compatible(name, compatible_tags))
+    }
+
+    /// Converts the contents of a custom artifact into a wheel.
+    pub fn to_wheel(
+        &self,
+        name: &CustomArtifactName,
+        bytes: Box<dyn ReadAndSeek + Send>,
+    ) -> miette::Result<Wheel> {
+        let kind = self
+            .get(&name.kind)
+            .ok_or_else(|| miette::miette!("unknown artifact kind '{}'", name.kind))?;
+        kind.to_wheel(name, bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    struct Bundle;
+
+    impl ArtifactKind for Bundle {
+        fn name(&self) -> &str {
+            "bundle"
+        }
+
+        fn parse_filename(
+            &self,
+            filename: &str,
+            package_name: &NormalizedPackageName,
+        ) -> Option<Version> {
+            let (name, version) = filename.strip_suffix(".bundle")?.rsplit_once('-')?;
+            (NormalizedPackageName::from_str(name).ok()? == *package_name)
+                .then(|| Version::from_str(version).ok())
+                .flatten()
+        }
+
+        fn is_compatible(
+            &self,
+            name: &CustomArtifactName,
+            _compatible_tags: Option<&WheelTags>,
+        ) -> bool {
+            !name.version.any_prerelease()
+        }
+
+        fn to_wheel(
+            &self,
+            _name: &CustomArtifactName,
+            _bytes: Box<dyn ReadAndSeek + Send>,
+        ) -> miette::Result<Wheel> {
+            miette::bail!("not implemented")
+        }
+    }
+
+    #[test]
+    fn test_parse_filename() {
+        let registry = ArtifactRegistry::default().with_kind(Bundle);
+        let package_name = NormalizedPackageName::from_str("foo-bar").unwrap();
+
+        let name = registry
+            .parse_filename("Foo_Bar-1.2.3.bundle", &package_name)
+            .unwrap();
+        let custom = name.as_custom().unwrap();
+        assert_eq!(custom.kind, "bundle");
+        assert_eq!(custom.version, Version::from_str("1.2.3").unwrap());
+        assert_eq!(name.to_string(), "Foo_Bar-1.2.3.bundle");
+        assert!(registry.is_compatible(custom, None));
+
+        assert!(registry
+            .parse_filename("Foo_Bar-1.2.3.tar.gz", &package_name)
+            .is_none());
+        assert!(registry
+            .parse_filename("baz-1.2.3.bundle", &package_name)
+            .is_none());
+    }
+
+    #[test]
+    fn test_unknown_kind_is_incompatible() {
+        let registry = ArtifactRegistry::default().with_kind(Bundle);
+        let name = CustomArtifactName {
+            kind: String::from("unknown"),
+            distribution: PackageName::from_str("foo").unwrap(),
+            version: Version::from_str("1.0").unwrap(),
+            filename: String::from("foo-1.0.unknown"),
+        };
+        assert!(!registry.is_compatible(&name, None));
+        assert!(ArtifactRegistry::default().get("bundle").is_none());
+    }
+}
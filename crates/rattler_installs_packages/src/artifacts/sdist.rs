@@ -11,9 +11,9 @@ use fs_err as fs;
 use miette::IntoDiagnostic;
 
 use std::ffi::OsStr;
-use std::io::{ErrorKind, Read, Seek};
-use std::path::{Path, PathBuf};
-use tar::Archive;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, EntryType};
 use zip::ZipArchive;
 
 /// Represents a source distribution artifact.
@@ -164,6 +164,20 @@ impl ArtifactFromSource for SDist {
         Ok(vec)
     }
 
+    fn write_bytes_to(&self, writer: &mut dyn Write) -> Result<(), std::io::Error> {
+        let mut inner = self.lock_data();
+        inner.rewind()?;
+        std::io::copy(&mut *inner, writer)?;
+        Ok(())
+    }
+
+    fn archive_size(&self) -> Option<u64> {
+        let mut inner = self.lock_data();
+        let size = inner.seek(SeekFrom::End(0)).ok();
+        inner.rewind().ok()?;
+        size
+    }
+
     fn distribution_name(&self) -> String {
         self.name().distribution.as_source_str().to_owned()
     }
@@ -199,7 +213,8 @@ impl ArtifactFromSource for SDist {
         }
     }
 
-    /// Extract the contents of the sdist archive to the given directory
+    /// Extract the contents of the sdist archive to the given directory. The entries are written
+    /// to the directory while the archive is decompressed, without intermediate copies.
     fn extract_to(&self, work_dir: &Path) -> std::io::Result<()> {
         let mut lock = self.file.lock();
         let archives = generic_archive_reader(&mut lock, self.name.format)?;
@@ -210,15 +225,112 @@ impl ArtifactFromSource for SDist {
                 // happens when unpacking the `tomli-2.0.1` source distribution
                 // https://github.com/alexcrichton/tar-rs/issues/349
                 archive.set_preserve_mtime(false);
-                archive.unpack(work_dir)?;
-                Ok(())
+                unpack_tar(&mut archive, work_dir)
             }
-            Archives::Zip(mut archive) => {
-                archive.extract(work_dir)?;
-                Ok(())
+            Archives::Zip(mut archive) => unpack_zip(&mut archive, work_dir),
+        }
+    }
+}
+
+/// Streams the entries of a tar archive into `work_dir`. Fails if an entry, or the target of a
+/// link, would end up outside of `work_dir`.
+fn unpack_tar(archive: &mut Archive<impl Read>, work_dir: &Path) -> std::io::Result<()> {
+    // Like `Archive::unpack`, directories are created last so their permissions don't prevent
+    // the files in them from being written
+    let mut directories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let relative_path = enclosed_path(&path).ok_or_else(|| outside_of_work_dir(&path))?;
+
+        match entry.header().entry_type() {
+            // The target of a symbolic link is relative to the directory of the link
+            EntryType::Symlink => {
+                let target = entry.link_name()?.unwrap_or_default();
+                let parent = relative_path.parent().unwrap_or(Path::new(""));
+                if enclosed_path(&parent.join(&target)).is_none() {
+                    return Err(outside_of_work_dir(&path));
+                }
+            }
+            // The target of a hard link is relative to the root of the archive
+            EntryType::Link => {
+                let target = entry.link_name()?.unwrap_or_default();
+                if enclosed_path(&target).is_none() {
+                    return Err(outside_of_work_dir(&path));
+                }
+            }
+            EntryType::Directory => {
+                directories.push(entry);
+                continue;
+            }
+            _ => {}
+        }
+
+        entry.unpack_in(work_dir)?;
+    }
+    for mut directory in directories {
+        directory.unpack_in(work_dir)?;
+    }
+    Ok(())
+}
+
+/// Streams the entries of a zip archive into `work_dir`. Fails if an entry would end up outside
+/// of `work_dir`.
+fn unpack_zip(archive: &mut ZipArchive<impl Read + Seek>, work_dir: &Path) -> std::io::Result<()> {
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let relative_path = file
+            .enclosed_name()
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| outside_of_work_dir(Path::new(file.name())))?;
+        let destination = work_dir.join(relative_path);
+        if file.is_dir() {
+            fs::create_dir_all(&destination)?;
+            continue;
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = fs::File::create(&destination)?;
+        std::io::copy(&mut file, &mut output)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = file.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&destination, std::fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns `path` with its `.` and `..` components resolved if it is relative and does not point
+/// outside of the directory it is relative to.
+fn enclosed_path(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => enclosed.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !enclosed.pop() {
+                    return None;
+                }
             }
+            Component::RootDir | Component::Prefix(_) => return None,
         }
     }
+    Some(enclosed)
+}
+
+fn outside_of_work_dir(path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::InvalidData,
+        format!(
+            "the archive entry '{}' would be extracted outside of the target directory",
+            path.display()
+        ),
+    )
 }
 
 enum RawAndGzReader<'a> {
@@ -271,7 +383,7 @@ mod tests {
     use crate::python_env::{Pep508EnvMakers, PythonLocation, VEnv};
     use crate::resolve::solve_options::{ResolveOptions, SDistResolution};
     use crate::resolve::PypiVersion;
-    use crate::types::{ArtifactFromSource, PackageName};
+    use crate::types::{ArtifactFromBytes, ArtifactFromSource, PackageName};
     use crate::types::{
         ArtifactInfo, ArtifactName, DistInfoMetadata, Extra, NormalizedPackageName, STreeFilename,
         WheelFilename, Yanked,
@@ -872,12 +984,13 @@ mod tests {
 
         let artifact_info = vec![ArtifactInfo {
             filename: ArtifactName::STree(stree_file_name),
-            url: url,
+            url,
             is_direct_url: true,
             hashes: None,
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         }];
 
         let wheel_metadata = package_db
@@ -923,6 +1036,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (whl, _) = package_db
@@ -986,6 +1100,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (whl, _) = package_db
@@ -1029,6 +1144,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (whl, _) = package_db
@@ -1075,6 +1191,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         }];
 
         let wheel_metadata = package_db
@@ -1118,6 +1235,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         }];
 
         let wheel_metadata = package_db
@@ -1161,6 +1279,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (_, direct_url_json) = package_db
@@ -1208,6 +1327,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (_, direct_url_json) = package_db
@@ -1248,6 +1368,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (_, direct_url_json) = package_db
@@ -1288,6 +1409,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (_, direct_url_json) = package_db
@@ -1328,6 +1450,7 @@ mod tests {
             requires_python: None,
             dist_info_metadata: DistInfoMetadata::default(),
             yanked: Yanked::default(),
+            size: None,
         };
 
         let (wheel, _) = package_db
@@ -1338,4 +1461,61 @@ mod tests {
 
         assert_debug_snapshot!(wheel.metadata());
     }
+
+    #[test]
+    pub fn extract_sdists() {
+        let sdists = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/sdists");
+        for (file_name, name, extracted_file) in [
+            ("rich-13.6.0.tar.gz", "rich", "rich-13.6.0/rich/__init__.py"),
+            ("filterpy-1.4.5.zip", "filterpy", "filterpy-1.4.5/PKG-INFO"),
+        ] {
+            let sdist = SDist::from_path(&sdists.join(file_name), &name.parse().unwrap()).unwrap();
+            let work_dir = tempdir().unwrap();
+            sdist.extract_to(work_dir.path()).unwrap();
+            assert!(work_dir.path().join(extracted_file).is_file());
+
+            // The bytes are streamed unchanged
+            let mut bytes = Vec::new();
+            sdist.write_bytes_to(&mut bytes).unwrap();
+            assert_eq!(bytes, sdist.try_get_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    pub fn extract_rejects_paths_outside_of_work_dir() {
+        fn tar_gz(path: &[u8], entry_type: tar::EntryType, link_name: &str) -> Vec<u8> {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..path.len()].copy_from_slice(path);
+            header.set_entry_type(entry_type);
+            header.as_old_mut().linkname[..link_name.len()].copy_from_slice(link_name.as_bytes());
+            header.set_size(0);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::fast(),
+            ));
+            builder.append(&header, std::io::empty()).unwrap();
+            builder.into_inner().unwrap().finish().unwrap()
+        }
+
+        for (path, entry_type, link_name) in [
+            (&b"../evil.py"[..], tar::EntryType::Regular, ""),
+            (b"/tmp/evil.py", tar::EntryType::Regular, ""),
+            (b"pkg-1.0/link", tar::EntryType::Symlink, "../../outside"),
+            (b"pkg-1.0/link", tar::EntryType::Symlink, "/etc/passwd"),
+            (b"pkg-1.0/link", tar::EntryType::Link, "../outside"),
+        ] {
+            let sdist = SDist::from_bytes(
+                SDistFilename::from_filename("pkg-1.0.tar.gz", &"pkg".parse().unwrap()).unwrap(),
+                Box::new(std::io::Cursor::new(tar_gz(path, entry_type, link_name))),
+            )
+            .unwrap();
+            let work_dir = tempdir().unwrap();
+            let err = sdist.extract_to(&work_dir.path().join("work")).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(std::fs::read_dir(work_dir.path()).unwrap().next().is_none());
+        }
+    }
 }
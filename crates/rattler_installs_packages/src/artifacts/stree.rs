@@ -18,6 +18,14 @@ pub struct STree {
 }
 
 impl STree {
+    /// Create a source tree for the directory at `location`
+    pub fn new(name: STreeFilename, location: PathBuf) -> Self {
+        Self {
+            name,
+            location: parking_lot::Mutex::new(location),
+        }
+    }
+
     /// Get a lock on the inner data
     pub fn lock_data(&self) -> parking_lot::MutexGuard<PathBuf> {
         self.location.lock()
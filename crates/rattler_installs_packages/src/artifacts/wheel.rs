@@ -1,5 +1,8 @@
+use crate::install::{
+    FileBatch, InstallOptions, LinkStrategy, WheelFileCache, MAX_BATCHED_FILE_SIZE,
+};
 use crate::python_env::{ByteCodeCompiler, CompilationError};
-use crate::types::{DirectUrlJson, HasArtifactName};
+use crate::types::{DirectUrlJson, HasArtifactName, ProvenanceUrlJson};
 use crate::{
     python_env::PythonInterpreterVersion,
     types::ArtifactFromBytes,
@@ -11,7 +14,10 @@ use crate::{
     types::WheelFilename,
     types::{Record, RecordEntry},
     types::{WheelCoreMetaDataError, WheelCoreMetadata},
-    utils::ReadAndSeek,
+    utils::{
+        ensure_available_space, is_file_in_use, remove_file, retry_in_use, InsufficientDiskSpace,
+        LocalFile, ReadAndSeek,
+    },
 };
 use async_http_range_reader::AsyncHttpRangeReader;
 use async_zip::base::read::seek::ZipFileReader;
@@ -22,6 +28,7 @@ use miette::IntoDiagnostic;
 use parking_lot::Mutex;
 use pep440_rs::Version;
 use rattler_digest::Sha256;
+use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader};
 use std::sync::mpsc::channel;
 use std::{
@@ -29,14 +36,14 @@ use std::{
     collections::HashMap,
     collections::HashSet,
     ffi::OsStr,
-    io::{Read, Write},
+    io::{Read, Seek, Write},
     iter::FromIterator,
     path::{Component, Path, PathBuf},
     str::FromStr,
 };
 use thiserror::Error;
 use tokio_util::compat::TokioAsyncReadCompatExt;
-use zip::{result::ZipError, ZipArchive};
+use zip::{result::ZipError, ZipArchive, ZipWriter};
 
 use crate::win::launcher::{build_windows_launcher, LauncherType, WindowsLauncherArch};
 
@@ -68,7 +75,7 @@ impl ArtifactFromBytes for Wheel {
 }
 
 impl Wheel {
-    /// Open a wheel by reading a file on disk.
+    /// Open a wheel by reading a file on disk. Large wheels are memory-mapped.
     pub fn from_path(
         path: &Path,
         normalized_package_name: &NormalizedPackageName,
@@ -79,7 +86,7 @@ impl Wheel {
             .ok_or_else(|| miette::miette!("path does not contain a filename"))?;
         let wheel_name =
             WheelFilename::from_filename(file_name, normalized_package_name).into_diagnostic()?;
-        let file = fs::File::open(path).into_diagnostic()?;
+        let file = LocalFile::open(path).into_diagnostic()?;
         Self::from_bytes(wheel_name, Box::new(file))
     }
 
@@ -238,7 +245,7 @@ impl Wheel {
         Ok((contents, metadata))
     }
 
-    fn get_vitals(&self) -> Result<WheelVitals, WheelVitalsError> {
+    pub(crate) fn get_vitals(&self) -> Result<WheelVitals, WheelVitalsError> {
         let mut archive = self.archive.lock();
 
         // Determine the top level filenames in the wheel
@@ -323,6 +330,16 @@ impl Wheel {
         })
     }
 
+    /// Writes the wheel archive to the file at `path`, e.g. to keep a wheel that was built from
+    /// an sdist.
+    pub fn write_to(self, path: &Path) -> std::io::Result<()> {
+        let mut bytes = self.archive.into_inner().into_inner();
+        bytes.rewind()?;
+        let mut file = fs::File::create(path)?;
+        std::io::copy(&mut bytes, &mut file)?;
+        Ok(())
+    }
+
     /// Get the metadata from the wheel archive
     pub fn metadata(&self) -> miette::Result<(Vec<u8>, WheelCoreMetadata)> {
         let WheelVitals {
@@ -333,6 +350,60 @@ impl Wheel {
         Ok((metadata_blob, metadata))
     }
 
+    /// Copies the files of the wheel that would be installed into `site-packages` to `writer`.
+    /// Files in the `.data` directory that are not installed into `site-packages` are skipped and
+    /// so are paths that are already present in `written`.
+    ///
+    /// Returns the console scripts defined by the wheel.
+    pub(crate) fn copy_site_packages_to_zip<W: Write + Seek>(
+        &self,
+        writer: &mut ZipWriter<W>,
+        written: &mut HashSet<String>,
+    ) -> Result<Vec<EntryPoint>, UnpackError> {
+        let vitals = self
+            .get_vitals()
+            .map_err(UnpackError::FailedToParseWheelVitals)?;
+        let mut archive = self.archive.lock();
+        let scripts = Scripts::from_wheel(&mut archive, &vitals.dist_info, None)?;
+
+        for index in 0..archive.len() {
+            let zip_entry = archive
+                .by_index_raw(index)
+                .map_err(|e| UnpackError::from_zip_error(format!("<index {index}>"), e))?;
+            if zip_entry.is_dir() {
+                continue;
+            }
+
+            // Files in the purelib and platlib categories of the data directory end up in
+            // site-packages, everything else in the data directory is skipped.
+            let name = zip_entry.name().to_owned();
+            let destination = match name.strip_prefix(&format!("{}/", vitals.data)) {
+                Some(data_path) => match data_path.split_once('/') {
+                    Some(("purelib" | "platlib", rest)) => rest.to_owned(),
+                    _ => continue,
+                },
+                None => name.clone(),
+            };
+
+            if !written.insert(destination.clone()) {
+                tracing::warn!(
+                    "skipping {destination} from {} because it was already written",
+                    self.name
+                );
+                continue;
+            }
+
+            let result = if destination == name {
+                writer.raw_copy_file(zip_entry)
+            } else {
+                writer.raw_copy_file_rename(zip_entry, &destination)
+            };
+            result.map_err(|e| UnpackError::from_zip_error(destination, e))?;
+        }
+
+        Ok(scripts.console_scripts)
+    }
+
     /// Read metadata from bytes-stream
     pub async fn read_metadata_bytes(
         name: &WheelFilename,
@@ -345,11 +416,11 @@ impl Wheel {
 #[derive(Debug)]
 /// Internals for the wheel that have to be present
 pub struct WheelVitals {
-    dist_info: String,
+    pub(crate) dist_info: String,
     data: String,
-    root_is_purelib: bool,
+    pub(crate) root_is_purelib: bool,
     metadata_blob: Vec<u8>,
-    metadata: WheelCoreMetadata,
+    pub(crate) metadata: WheelCoreMetadata,
 }
 
 #[derive(Debug, Error)]
@@ -445,6 +516,30 @@ fn read_entry_to_end<R: ReadAndSeek>(
     Ok(bytes)
 }
 
+/// The locations of the installation categories of a wheel, similar to a `sysconfig` scheme.
+///
+/// Relative paths are interpreted relative to the installation destination, absolute paths are
+/// used as is. This makes it possible to target layouts that differ from the standard schemes,
+/// for instance the plugin directory of an application with an embedded interpreter.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InstallScheme {
+    /// The location of pure python packages and of wheels with `Root-Is-Purelib: true`.
+    pub purelib: PathBuf,
+
+    /// The location of platform specific packages and of wheels with `Root-Is-Purelib: false`.
+    pub platlib: PathBuf,
+
+    /// The location of scripts and entry points.
+    pub scripts: PathBuf,
+
+    /// The location of the files in the `data` directory of a wheel.
+    pub data: PathBuf,
+
+    /// The location of the C header files of a wheel. The headers of a distribution are placed in
+    /// a subdirectory named after the distribution.
+    pub headers: PathBuf,
+}
+
 /// A struct of installation categories to where they should be stored relative to the
 /// installation destination.
 #[derive(Debug, Clone)]
@@ -493,6 +588,88 @@ impl InstallPaths {
         }
     }
 
+    /// Populates mappings of installation targets for a plain directory, similar to
+    /// `pip install --target`. Both purelib and platlib are flattened into the root of the
+    /// directory which makes the directory directly usable on `PYTHONPATH`, e.g. as an AWS Lambda
+    /// layer. Scripts are written to `scripts`, which is interpreted relative to the directory
+    /// unless it is absolute.
+    pub fn for_target_dir(scripts: impl Into<PathBuf>, windows: bool) -> Self {
+        Self {
+            purelib: PathBuf::from(""),
+            platlib: PathBuf::from(""),
+            scripts: scripts.into(),
+            data: PathBuf::from(""),
+            windows,
+            headers: PathBuf::from("include"),
+        }
+    }
+
+    /// Populates mappings of installation targets for the user scheme (`pip install --user`). The
+    /// paths are relative to the user base directory (`site.getuserbase()`) and match the
+    /// `posix_user`, `osx_framework_user` and `nt_user` schemes of `sysconfig`.
+    pub fn for_user_site<V: Into<PythonInterpreterVersion>>(
+        version: V,
+        windows: bool,
+        macos_framework: bool,
+    ) -> Self {
+        let version = version.into();
+
+        let (site_packages, scripts, headers) = if windows {
+            let python_dir = PathBuf::from(format!("Python{}{}", version.major, version.minor));
+            (
+                python_dir.join("site-packages"),
+                python_dir.join("Scripts"),
+                python_dir.join("Include"),
+            )
+        } else if macos_framework {
+            (
+                Path::new("lib").join("python").join("site-packages"),
+                PathBuf::from("bin"),
+                Path::new("include").join(format!("python{}.{}", version.major, version.minor)),
+            )
+        } else {
+            let python_dir = format!("python{}.{}", version.major, version.minor);
+            (
+                Path::new("lib").join(&python_dir).join("site-packages"),
+                PathBuf::from("bin"),
+                Path::new("include").join(python_dir),
+            )
+        };
+
+        Self {
+            purelib: site_packages.clone(),
+            platlib: site_packages,
+            scripts,
+            data: PathBuf::from(""),
+            windows,
+            headers,
+        }
+    }
+
+    /// Constructs install paths from a custom [`InstallScheme`]. `windows` determines whether
+    /// scripts are installed as windows launchers.
+    pub fn from_scheme(scheme: InstallScheme, windows: bool) -> Self {
+        Self {
+            purelib: scheme.purelib,
+            platlib: scheme.platlib,
+            scripts: scheme.scripts,
+            data: scheme.data,
+            headers: scheme.headers,
+            windows,
+        }
+    }
+
+    /// Returns the [`InstallScheme`] that describes these install paths.
+    pub fn scheme(&self) -> InstallScheme {
+        InstallScheme {
+            purelib: self.purelib.clone(),
+            platlib: self.platlib.clone(),
+            scripts: self.scripts.clone(),
+            data: self.data.clone(),
+            headers: self.headers.clone(),
+        }
+    }
+
     /// Determines whether this is a windows InstallPath
     pub fn is_windows(&self) -> bool {
         self.windows
@@ -503,6 +680,17 @@ impl InstallPaths {
         &self.purelib
     }
 
+    /// Returns the location of the root of a wheel, which is also where its `.dist-info`
+    /// directory is placed. This is either the purelib or the platlib location depending on the
+    /// `Root-Is-Purelib` field of the wheel.
+    pub fn root_location(&self, root_is_purelib: bool) -> &Path {
+        if root_is_purelib {
+            &self.purelib
+        } else {
+            &self.platlib
+        }
+    }
+
     /// Reference to pure python library location.
     pub fn purelib(&self) -> &Path {
         &self.purelib
@@ -566,6 +754,9 @@ pub enum UnpackError {
     #[error("failed to write {0}")]
     IoError(String, #[source] std::io::Error),
 
+    #[error("failed to replace {0} because it is in use by another process, close any programs that use the environment and try again")]
+    FileInUse(String, #[source] std::io::Error),
+
     #[error("RECORD file is invalid")]
     RecordCsv(#[from] csv::Error),
 
@@ -586,6 +777,8 @@ pub enum UnpackError {
 
     #[error("failed to write `direct_url.json` to .dist-info")]
     FailedToWriteDirectUrlJson(#[from] serde_json::Error),
+    #[error(transparent)]
+    InsufficientDiskSpace(#[from] InsufficientDiskSpace),
 }
 
 impl UnpackError {
@@ -595,17 +788,30 @@ impl UnpackError {
             _ => Self::ZipError(file, error),
         }
     }
+
+    pub(crate) fn from_io_error(file: String, error: std::io::Error) -> Self {
+        if is_file_in_use(&error) {
+            Self::FileInUse(file, error)
+        } else {
+            Self::IoError(file, error)
+        }
+    }
 }
 
 /// Additional optional settings to pass to [`Wheel::unpack`].
 ///
 /// Not all options in this struct are relevant. Typically you will default a number of fields.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct UnpackWheelOptions<'i> {
     /// When specified an INSTALLER file is written to the dist-info folder of the package.
     /// INSTALLER files are used to track the installer of a package. See [PEP 376](https://peps.python.org/pep-0376/) for more information.
     pub installer: Option<String>,
 
+    /// When true an empty REQUESTED file is written to the dist-info folder of the package to
+    /// indicate that the package was explicitly requested by the user instead of being installed
+    /// as a dependency. See [PEP 376](https://peps.python.org/pep-0376/#requested).
+    pub requested: bool,
+
     /// The extras of the wheel that should be activated. This affects the creation of entry points.
     /// If `None` is specified, extras are *not* taken into account. This is different from
     /// specifying an empty set because when specifying `None` no filtering based on extras is
@@ -625,6 +831,40 @@ pub struct UnpackWheelOptions<'i> {
     /// because when using `unpack` on the wheel we do not know where it came from.
     /// This needs to be supplied manually.
     pub direct_url_json: Option<DirectUrlJson>,
+
+    /// The `provenance_url.json` file of [PEP 710](https://peps.python.org/pep-0710/) that should
+    /// be written to the dist-info folder of the package, which records the index and artifact
+    /// the wheel was installed from.
+    pub provenance_url_json: Option<ProvenanceUrlJson>,
+
+    /// Determines how the files of the wheel are placed into the destination.
+    pub install_options: InstallOptions,
+
+    /// Generate scripts and launchers that locate the python interpreter relative to their own
+    /// location instead of through its absolute path. This keeps the scripts working when the
+    /// environment is moved to another location, as long as the interpreter moves with it.
+    pub relocatable: bool,
+
+    /// When specified, the files of the wheel are written below this directory instead of
+    /// directly into the destination, similar to `DESTDIR` in `make install`. The paths that end
+    /// up in the installed files, like the RECORD file and the scripts, still refer to the
+    /// destination. This is useful to stage an environment into a build root that is later
+    /// packaged or copied to its final location. See [`staged_path`].
+    ///
+    /// Note that byte code that is compiled during the installation refers to the staged location
+    /// of the source files.
+    pub destdir: Option<PathBuf>,
+}
+
+/// Returns the location where `path` is staged when installing with a `destdir`. The root (and
+/// on windows the drive) of `path` is stripped and the remainder is appended to `destdir`, e.g.
+/// `/opt/env` staged into `/tmp/build` becomes `/tmp/build/opt/env`.
+pub fn staged_path(destdir: &Path, path: &Path) -> PathBuf {
+    let relative = path
+        .components()
+        .filter(|component| !matches!(component, Component::Prefix(_) | Component::RootDir))
+        .collect::<PathBuf>();
+    destdir.join(relative)
 }
 
 #[derive(Debug)]
@@ -654,6 +894,64 @@ impl Wheel {
         paths: &InstallPaths,
         python_executable: &Path,
         options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        // Fail before any file is written if the files of the wheel will not fit
+        let root = match options.destdir.as_deref() {
+            Some(destdir) => staged_path(destdir, dest),
+            None => dest.to_path_buf(),
+        };
+        ensure_available_space(&root, self.unpacked_size()?, || {
+            format!("install {}", self.name)
+        })?;
+
+        match options.install_options.unpacked_cache.as_ref() {
+            Some(cache) if options.byte_code_compiler.is_none() => {
+                cache.unpack(self, dest, paths, python_executable, options)
+            }
+            _ => self.unpack_files(dest, paths, python_executable, options),
+        }
+    }
+
+    /// Returns the total size of the files in the wheel once they are decompressed.
+    fn unpacked_size(&self) -> Result<u64, UnpackError> {
+        let mut archive = self.archive.lock();
+        let mut size = 0;
+        for index in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(index)
+                .map_err(|e| UnpackError::from_zip_error(format!("<index {index}>"), e))?;
+            size += entry.size();
+        }
+        Ok(size)
+    }
+
+    /// Returns a digest of the contents of the wheel that is computed from the names, sizes and
+    /// checksums of the files in the archive, without decompressing them.
+    pub(crate) fn content_digest(&self) -> Result<String, UnpackError> {
+        let mut archive = self.archive.lock();
+        let mut entries = String::new();
+        for index in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(index)
+                .map_err(|e| UnpackError::from_zip_error(format!("<index {index}>"), e))?;
+            entries.push_str(&format!(
+                "{}:{}:{:08x}\n",
+                entry.name(),
+                entry.size(),
+                entry.crc32()
+            ));
+        }
+        let digest = rattler_digest::compute_bytes_digest::<Sha256>(entries.as_bytes());
+        Ok(format!("{digest:x}"))
+    }
+
+    /// Unpacks the files of the wheel, see [`Wheel::unpack`].
+    pub(crate) fn unpack_files(
+        &self,
+        dest: &Path,
+        paths: &InstallPaths,
+        python_executable: &Path,
+        options: &UnpackWheelOptions,
     ) -> Result<UnpackedWheel, UnpackError> {
         let vitals = self
             .get_vitals()
@@ -668,6 +966,7 @@ impl Wheel {
 
         let trampoline_maker = TrampolineMaker {
             python_executable: python_executable.to_path_buf(),
+            scripts_dir: options.relocatable.then(|| dest.join(paths.scripts())),
             kind: if paths.is_windows() {
                 TrampolineMakerKind::Windows {
                     arch: options.launcher_arch,
@@ -677,7 +976,16 @@ impl Wheel {
             },
         };
 
-        let site_packages = dest.join(paths.site_packages());
+        // When staging, all files are written below the destdir while the paths that are embedded
+        // in the installed files refer to `dest`.
+        let root = match options.destdir.as_deref() {
+            Some(destdir) => Cow::Owned(staged_path(destdir, dest)),
+            None => Cow::Borrowed(dest),
+        };
+
+        // All paths in the RECORD file are relative to the directory that contains the
+        // `.dist-info` directory.
+        let site_packages = root.join(paths.root_location(vitals.root_is_purelib));
         let mut archive = self.archive.lock();
 
         // Read the RECORD file from the wheel
@@ -689,12 +997,39 @@ impl Wheel {
         )?;
         let record_relative_path = Path::new(&record_filename);
 
+        // Index the RECORD entries by their path. Preceding slashes are stripped since all paths
+        // in the wheel RECORD should be relative.
+        let record_entries = record
+            .iter()
+            .map(|entry| (entry.path.trim_start_matches('/'), entry))
+            .collect::<HashMap<_, _>>();
+
         // Read `entry_points.txt` and parse any scripts we need to create.
         let scripts =
             Scripts::from_wheel(&mut archive, &vitals.dist_info, options.extras.as_ref())?;
 
         let mut resulting_records = Vec::new();
         let (pyc_tx, pyc_rx) = channel();
+        let compile = |destination: PathBuf| -> Result<(), UnpackError> {
+            let Some(bytecode_compiler) = options.byte_code_compiler.as_ref() else {
+                return Ok(());
+            };
+            let pyc_tx = pyc_tx.clone();
+            let cloned_destination = destination.clone();
+            bytecode_compiler
+                .compile(&destination, move |result| {
+                    // Ignore any error that might occur due to the receiver being closed.
+                    let _ = pyc_tx.send((cloned_destination, result));
+                })
+                .map_err(|err| {
+                    UnpackError::ByteCodeCompilationFailed(destination.display().to_string(), err)
+                })
+        };
+
+        // Small files are written in batches if io_uring is available. Python files that are
+        // written in a batch can only be compiled once the batch has been flushed.
+        let mut batch = FileBatch::new();
+        let mut uncompiled = Vec::new();
         for index in 0..archive.len() {
             let mut zip_entry = archive
                 .by_index(index)
@@ -723,7 +1058,7 @@ impl Wheel {
             else {
                 continue;
             };
-            let destination = dest.join(relative_destination);
+            let destination = root.join(relative_destination);
 
             // If the entry refers to a directory we simply create it.
             if zip_entry.is_dir() {
@@ -784,27 +1119,41 @@ impl Wheel {
                     // Otherwise copy the file verbatim
                     write_wheel_file(&mut buf_reader, &destination, true)?
                 }
+            } else if let Some(file_cache) = options.install_options.file_cache.as_ref() {
+                // Place the file from the cache, if the RECORD contains the hash of the file we
+                // might not have to extract it at all.
+                let relative_path_string = relative_path.display().to_string();
+                let recorded_hash = record_entries
+                    .get(relative_path_string.as_str())
+                    .and_then(|entry| entry.hash.as_deref());
+                link_wheel_file(
+                    file_cache,
+                    options.install_options.link_strategy,
+                    &mut zip_entry,
+                    recorded_hash,
+                    &destination,
+                    executable,
+                    batch.as_mut(),
+                )?
+            } else if let Some(batch) = batch
+                .as_mut()
+                .filter(|_| zip_entry.size() <= MAX_BATCHED_FILE_SIZE)
+            {
+                // Queue the file to be written together with other small files.
+                queue_wheel_file(batch, &mut zip_entry, &destination, executable)?
             } else {
                 // Otherwise copy the file to its final destination.
                 write_wheel_file(&mut zip_entry, &destination, executable)?
             };
 
             // If the file is a python file we need to compile it to bytecode
-            if let Some(bytecode_compiler) = options.byte_code_compiler.as_ref() {
-                if destination.extension() == Some(OsStr::new("py")) {
-                    let pyc_tx = pyc_tx.clone();
-                    let cloned_destination = destination.clone();
-                    bytecode_compiler
-                        .compile(&destination, move |result| {
-                            // Ignore any error that might occur due to the receiver being closed.
-                            let _ = pyc_tx.send((cloned_destination, result));
-                        })
-                        .map_err(|err| {
-                            UnpackError::ByteCodeCompilationFailed(
-                                destination.display().to_string(),
-                                err,
-                            )
-                        })?;
+            if options.byte_code_compiler.is_some()
+                && destination.extension() == Some(OsStr::new("py"))
+            {
+                if batch.is_some() {
+                    uncompiled.push(destination.clone());
+                } else {
+                    compile(destination.clone())?;
                 }
             }
 
@@ -813,13 +1162,8 @@ impl Wheel {
                 let relative_path_string = relative_path.display().to_string();
 
                 // Find the record in the RECORD entries
-                let recorded_hash = record
-                    .iter()
-                    .find(|entry| {
-                        // Strip any preceding slashes from the path since all paths in the wheel
-                        // RECORD should be relative.
-                        entry.path.trim_start_matches('/') == relative_path_string
-                    })
+                let recorded_hash = record_entries
+                    .get(relative_path_string.as_str())
                     .and_then(|entry| entry.hash.as_ref())
                     .ok_or_else(|| {
                         UnpackError::RecordFile(format!(
@@ -858,9 +1202,20 @@ impl Wheel {
             }
         }
 
+        // Write the remaining batched files and compile the python files among them
+        if let Some(batch) = batch.as_mut() {
+            batch
+                .flush()
+                .map_err(|err| UnpackError::IoError(root.display().to_string(), err))?;
+        }
+        for destination in uncompiled {
+            compile(destination)?;
+        }
+
         // Generate the script entrypoints
         write_script_entrypoint(
-            dest,
+            &root,
+            &site_packages,
             paths,
             &scripts.console_scripts,
             &trampoline_maker,
@@ -868,7 +1223,8 @@ impl Wheel {
             &mut resulting_records,
         )?;
         write_script_entrypoint(
-            dest,
+            &root,
+            &site_packages,
             paths,
             &scripts.gui_scripts,
             &trampoline_maker,
@@ -893,6 +1249,16 @@ impl Wheel {
             )?);
         }
 
+        // Write the REQUESTED file if the package was requested explicitly
+        if options.requested {
+            resulting_records.push(write_generated_file(
+                Path::new(&format!("{}/REQUESTED", &vitals.dist_info)),
+                &site_packages,
+                "",
+                false,
+            )?);
+        }
+
         // Write `direct_url.json` if requested
         if let Some(direct_url_json) = options.direct_url_json.as_ref() {
             resulting_records.push(write_generated_file(
@@ -903,6 +1269,20 @@ impl Wheel {
             )?);
         }
 
+        // Write `provenance_url.json` if requested
+        if let Some(provenance_url_json) = options.provenance_url_json.as_ref() {
+            resulting_records.push(write_generated_file(
+                Path::new(&format!(
+                    "{}/{}",
+                    &vitals.dist_info,
+                    ProvenanceUrlJson::FILE_NAME
+                )),
+                &site_packages,
+                serde_json::to_string(provenance_url_json)?,
+                false,
+            )?);
+        }
+
         // Write all the compiled bytecode files to the RECORD file
         drop(pyc_tx);
         for (source, result) in pyc_rx {
@@ -946,6 +1326,7 @@ impl Wheel {
 /// Construct trampolines for entry-points.
 fn write_script_entrypoint(
     dest: &Path,
+    site_packages: &Path,
     install_paths: &InstallPaths,
     entry_points: &Vec<EntryPoint>,
     trampoline_maker: &TrampolineMaker,
@@ -981,9 +1362,8 @@ fn write_script_entrypoint(
         let script_path = dest
             .join(install_paths.scripts())
             .join(script_name.as_ref());
-        let site_packages = dest.join(install_paths.site_packages());
-        let relative_path = pathdiff::diff_paths(script_path, &site_packages).expect("should always be able to create relative path from site-packages to the scripts directory");
-        let record = write_generated_file(&relative_path, &site_packages, &trampoline, true)?;
+        let relative_path = pathdiff::diff_paths(script_path, site_packages).expect("should always be able to create relative path from site-packages to the scripts directory");
+        let record = write_generated_file(&relative_path, site_packages, &trampoline, true)?;
         records.push(record)
     }
 
@@ -1000,6 +1380,9 @@ fn write_script_entrypoint(
 /// [`crate::launcher`] for more information.
 struct TrampolineMaker {
     python_executable: PathBuf,
+    /// The directory that contains the trampolines. If this is specified the trampolines refer to
+    /// the python executable relative to this directory.
+    scripts_dir: Option<PathBuf>,
     kind: TrampolineMakerKind,
 }
 
@@ -1017,7 +1400,17 @@ impl TrampolineMaker {
         launcher_type: LauncherType,
         script: &[u8],
     ) -> Result<Vec<u8>, UnpackError> {
-        let shebang = get_shebang(&self.python_executable);
+        let windows = matches!(self.kind, TrampolineMakerKind::Windows { .. });
+        let relative_python_executable = self
+            .scripts_dir
+            .as_ref()
+            .and_then(|scripts_dir| pathdiff::diff_paths(&self.python_executable, scripts_dir));
+        let shebang = match relative_python_executable {
+            Some(relative_python_executable) => {
+                get_relocatable_shebang(&relative_python_executable, windows)
+            }
+            None => get_shebang(&self.python_executable, windows),
+        };
         match self.kind {
             TrampolineMakerKind::Windows { arch } => {
                 let arch = match arch {
@@ -1044,11 +1437,63 @@ impl TrampolineMaker {
     }
 }
 
+/// The maximum length of a shebang line that is supported by the kernel. Longer shebangs are
+/// truncated.
+const MAX_SHEBANG_LENGTH: usize = if cfg!(target_os = "macos") { 512 } else { 127 };
+
 /// Returns the shebang to use when calling a python script.
-/// TODO: In the future we should make this much more configurable. This is much more complex in pip:
-///  <https://github.com/pypa/pip/blob/7f8a6844037fb7255cfd0d34ff8e8cf44f2598d4/src/pip/_vendor/distlib/scripts.py#L158>
-fn get_shebang(python_executable: &Path) -> String {
-    format!(r"#!{}", dunce::simplified(python_executable).display())
+///
+/// On unix, if the path to the interpreter is too long or contains spaces it cannot be used in a
+/// shebang directly. Like distlib, we then generate a shebang that runs the script with `/bin/sh`
+/// which `exec`s the interpreter instead. The script remains valid python because python parses
+/// the second line as a string literal.
+///
+/// On windows the shebang is read by the launcher which supports quoted paths.
+///
+/// See: <https://github.com/pypa/pip/blob/7f8a6844037fb7255cfd0d34ff8e8cf44f2598d4/src/pip/_vendor/distlib/scripts.py#L158>
+fn get_shebang(python_executable: &Path, windows: bool) -> String {
+    let executable = dunce::simplified(python_executable).display().to_string();
+    if windows {
+        if executable.contains(' ') {
+            format!(r#"#!"{executable}""#)
+        } else {
+            format!("#!{executable}")
+        }
+    } else if executable.len() + 2 > MAX_SHEBANG_LENGTH || executable.contains(' ') {
+        let executable = executable.replace('\'', r"'\''");
+        format!("#!/bin/sh\n'''exec' '{executable}' \"$0\" \"$@\"\n' '''")
+    } else {
+        format!("#!{executable}")
+    }
+}
+
+/// Returns a shebang that runs the interpreter at `python_executable`, which is relative to the
+/// directory that contains the script.
+///
+/// On unix a shebang cannot refer to a path relative to the script, so similar to [`get_shebang`]
+/// the script is run with `/bin/sh` which resolves the directory of the script and `exec`s the
+/// interpreter from there. The directory is resolved with `cd -P` and `pwd -P` because `realpath`
+/// is not available everywhere, e.g. on macOS before 13.
+///
+/// On windows the launcher replaces `<launcher_dir>` with the directory of the launcher.
+fn get_relocatable_shebang(python_executable: &Path, windows: bool) -> String {
+    if windows {
+        let executable = python_executable.display().to_string().replace('/', "\\");
+        if executable.contains(' ') {
+            format!(r#"#!"<launcher_dir>\{executable}""#)
+        } else {
+            format!(r"#!<launcher_dir>\{executable}")
+        }
+    } else {
+        let executable = python_executable
+            .display()
+            .to_string()
+            .replace('\\', "/")
+            .replace('\'', r"'\''");
+        format!(
+            "#!/bin/sh\n'''exec' \"$(cd -P -- \"$(dirname -- \"$0\")\" && pwd -P)\"/'{executable}' \"$0\" \"$@\"\n' '''"
+        )
+    }
 }
 
 /// The scripts that should be installed as part of the wheel installation.
@@ -1161,35 +1606,49 @@ fn parse_entry_points_from_ini_section(
     Ok(result)
 }
 
-fn write_generated_file(
+/// Writes a file that is generated by the installer to `relative_path` inside `site_packages` and
+/// returns its entry for the RECORD file.
+pub(crate) fn write_generated_file(
     relative_path: &Path,
     site_packages: &Path,
     content: impl AsRef<[u8]>,
     _executable: bool,
 ) -> Result<RecordEntry, UnpackError> {
-    let mut options = fs::OpenOptions::new();
-    options.write(true).create(true).truncate(true);
+    let mut builder = tempfile::Builder::new();
 
     #[cfg(unix)]
     {
-        use fs::os::unix::fs::OpenOptionsExt;
+        use std::os::unix::fs::PermissionsExt;
         if _executable {
-            options.mode(0o777);
+            builder.permissions(std::fs::Permissions::from_mode(0o777));
         } else {
-            options.mode(0o666);
+            builder.permissions(std::fs::Permissions::from_mode(0o666));
         }
     }
 
-    let (size, digest) = options
-        .open(site_packages.join(relative_path))
+    // Write the file to a temporary location next to the destination first and move it in place
+    // afterwards. Wheels that are installed concurrently may generate the same file (e.g. an entry
+    // point with the same name), this ensures the file is never observed partially written.
+    let destination = site_packages.join(relative_path);
+    let (size, digest) = builder
+        .tempfile_in(destination.parent().unwrap_or(site_packages))
         .map(rattler_digest::HashingWriter::<_, Sha256>::new)
         .and_then(|mut file| {
             let content = content.as_ref();
             file.write_all(content)?;
-            let (_, digest) = file.finalize();
+            let (file, digest) = file.finalize();
+            if let Err(err) = file.persist(&destination) {
+                // An existing file that is in use cannot be replaced on windows, move it out of
+                // the way first.
+                if !is_file_in_use(&err.error) {
+                    return Err(err.error);
+                }
+                remove_file(&destination)?;
+                err.file.persist(&destination)?;
+            }
             Ok((content.len(), digest))
         })
-        .map_err(|err| UnpackError::IoError(relative_path.display().to_string(), err))?;
+        .map_err(|err| UnpackError::from_io_error(relative_path.display().to_string(), err))?;
 
     Ok(RecordEntry {
         path: relative_path.display().to_string().replace('\\', "/"),
@@ -1221,9 +1680,14 @@ fn write_wheel_file(
         fs::create_dir_all(parent)
             .map_err(|err| UnpackError::IoError(parent.display().to_string(), err))?;
     }
-    let mut file = options
-        .open(destination)
-        .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?;
+
+    // Remove an existing file first, this also works if the file is in use on windows.
+    if destination.symlink_metadata().is_ok() {
+        remove_file(destination)
+            .map_err(|err| UnpackError::from_io_error(destination.display().to_string(), err))?;
+    }
+    let mut file = retry_in_use(|| options.open(destination))
+        .map_err(|err| UnpackError::from_io_error(destination.display().to_string(), err))?;
     let size = std::io::copy(&mut reader, &mut file)
         .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?;
     let (_, digest) = reader.finalize();
@@ -1233,6 +1697,67 @@ fn write_wheel_file(
     ))
 }
 
+/// Places a file from a wheel at `destination` by storing it in the `file_cache` and linking it
+/// from there. If the cache already contains a file with the `recorded_hash` the file is not read
+/// from the wheel at all.
+fn link_wheel_file(
+    file_cache: &WheelFileCache,
+    link_strategy: LinkStrategy,
+    reader: &mut impl Read,
+    recorded_hash: Option<&str>,
+    destination: &Path,
+    executable: bool,
+    batch: Option<&mut FileBatch>,
+) -> Result<(Option<u64>, Option<String>), UnpackError> {
+    let entry = match recorded_hash.and_then(|hash| file_cache.get(hash, executable)) {
+        Some(entry) => entry,
+        None => file_cache
+            .insert(reader, executable)
+            .map_err(|err| UnpackError::IoError(file_cache.root().display().to_string(), err))?,
+    };
+
+    match batch {
+        Some(batch) if link_strategy == LinkStrategy::Hardlink => batch
+            .hard_link(&entry.path, destination)
+            .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?,
+        _ => {
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|err| UnpackError::IoError(parent.display().to_string(), err))?;
+            }
+            link_strategy
+                .link(&entry.path, destination)
+                .map_err(|err| {
+                    UnpackError::from_io_error(destination.display().to_string(), err)
+                })?;
+        }
+    }
+
+    Ok((Some(entry.size), Some(entry.hash)))
+}
+
+/// Reads a file from a wheel archive and queues writing it to disk in `batch`.
+fn queue_wheel_file(
+    batch: &mut FileBatch,
+    reader: &mut impl Read,
+    destination: &Path,
+    executable: bool,
+) -> Result<(Option<u64>, Option<String>), UnpackError> {
+    let mut contents = Vec::new();
+    reader
+        .read_to_end(&mut contents)
+        .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?;
+    let digest = rattler_digest::compute_bytes_digest::<Sha256>(&contents);
+    let size = contents.len() as u64;
+    batch
+        .write(destination, contents, executable)
+        .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?;
+    Ok((
+        Some(size),
+        Some(format!("sha256={}", BASE64URL_NOPAD.encode(&digest))),
+    ))
+}
+
 /// Implements the logic to determine where a files from a wheel should be placed on the filesystem
 /// and whether we should apply special logic.
 ///
@@ -1260,8 +1785,9 @@ impl<'a> WheelPathTransformer<'a> {
             let mut components = data_path.components();
             if let Some(category) = components.next() {
                 let Component::Normal(name) = category else {
-                    // TODO: Better error handling
-                    panic!("invalid path")
+                    return Err(UnpackError::UnsupportedDataDirectory(
+                        data_path.display().to_string(),
+                    ));
                 };
                 (name.to_string_lossy(), components.as_path())
             } else {
@@ -1288,7 +1814,7 @@ impl<'a> WheelPathTransformer<'a> {
 mod test {
     use super::*;
     use crate::python_env::{system_python_executable, PythonLocation, VEnv, WheelTags};
-    use crate::types::{DirectUrlHashes, DirectUrlJson, DirectUrlSource};
+    use crate::types::{DirectUrlHashes, DirectUrlJson, DirectUrlSource, ProvenanceArchiveInfo};
     use rstest::rstest;
     use tempfile::{tempdir, TempDir};
     use test_utils::download_and_cache_file_async;
@@ -1374,7 +1900,7 @@ mod test {
 
         // Determine the location where we would expect the RECORD file to exist
         let record_path = unpacked.dist_info.join("RECORD");
-        let record_content = fs::read_to_string(&unpacked.tmpdir.path().join(&record_path))
+        let record_content = fs::read_to_string(unpacked.tmpdir.path().join(&record_path))
             .unwrap_or_else(|_| panic!("failed to read RECORD from {}", record_path.display()));
 
         insta::assert_snapshot!(filename, record_content);
@@ -1396,6 +1922,97 @@ mod test {
         assert_eq!(installer_content, format!("{INSTALLER}\n"));
     }
 
+    #[test]
+    fn test_requested() {
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &"miniblack".parse().unwrap(),
+        )
+        .unwrap();
+        let tmpdir = tempdir().unwrap();
+        let unpacked = wheel
+            .unpack(
+                tmpdir.path(),
+                &InstallPaths::for_venv((3, 8, 5), false),
+                Path::new("/invalid"),
+                &UnpackWheelOptions {
+                    installer: Some(String::from(INSTALLER)),
+                    requested: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert!(unpacked.dist_info.join("REQUESTED").is_file());
+
+        // Every file that was generated by the installer is part of the RECORD
+        let record = Record::from_path(&unpacked.dist_info.join("RECORD")).unwrap();
+        let recorded = record
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect::<Vec<_>>();
+        for file in ["REQUESTED", "INSTALLER"] {
+            assert!(
+                recorded.contains(&format!("miniblack-23.1.0.dist-info/{file}")),
+                "{file} is not recorded"
+            );
+        }
+        assert!(recorded.contains(&String::from("../../../bin/black")));
+    }
+
+    #[test]
+    fn test_destdir() {
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &"miniblack".parse().unwrap(),
+        )
+        .unwrap();
+        let destdir = tempdir().unwrap();
+        let unpacked = wheel
+            .unpack(
+                Path::new("/opt/env"),
+                &InstallPaths::for_venv((3, 8, 5), false),
+                Path::new("/opt/env/bin/python"),
+                &UnpackWheelOptions {
+                    destdir: Some(destdir.path().to_path_buf()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // All files are written below the destdir
+        let staged = destdir.path().join("opt/env");
+        assert_eq!(
+            unpacked.dist_info,
+            staged.join("lib/python3.8/site-packages/miniblack-23.1.0.dist-info")
+        );
+        assert!(staged
+            .join("lib/python3.8/site-packages/black/__init__.py")
+            .is_file());
+
+        // But the scripts refer to the final location
+        let script = fs::read_to_string(staged.join("bin/black")).unwrap();
+        assert!(script.starts_with("#!/opt/env/bin/python\n"), "{script}");
+        let record = Record::from_path(&unpacked.dist_info.join("RECORD")).unwrap();
+        assert!(record
+            .iter()
+            .any(|entry| entry.path == "../../../bin/black"));
+    }
+
+    #[test]
+    fn test_staged_path() {
+        assert_eq!(
+            staged_path(Path::new("/tmp/build"), Path::new("/opt/env")),
+            Path::new("/tmp/build/opt/env")
+        );
+        assert_eq!(
+            staged_path(Path::new("/tmp/build"), Path::new("env")),
+            Path::new("/tmp/build/env")
+        );
+    }
+
     #[test]
     fn test_byte_code_compilation() {
         // We check this specific package because some of the files will fail to compile.
@@ -1409,7 +2026,7 @@ mod test {
 
         // Determine the location where we would expect the RECORD file to exist
         let record_path = unpacked.dist_info.join("RECORD");
-        let record_content = fs::read_to_string(&unpacked.tmpdir.path().join(&record_path))
+        let record_content = fs::read_to_string(unpacked.tmpdir.path().join(&record_path))
             .unwrap_or_else(|_| panic!("failed to read RECORD from {}", record_path.display()));
 
         // Replace all cpython references with cpython-xxx to ensure that no matter the version of
@@ -1450,6 +2067,7 @@ mod test {
 
         let direct_url = DirectUrlJson {
             url: Url::from_directory_path(&package_path).unwrap(),
+            subdirectory: None,
             source: DirectUrlSource::Archive {
                 hashes: Some(DirectUrlHashes {
                     sha256: "95a7e86f46de9b5da6ec9365e1e96d1644c67328".to_string(),
@@ -1470,6 +2088,50 @@ mod test {
         assert!(wheel.dist_info.join("direct_url.json").exists());
     }
 
+    #[test]
+    fn test_provenance_url() {
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &"miniblack".parse().unwrap(),
+        )
+        .unwrap();
+        let provenance = ProvenanceUrlJson {
+            url: "https://files.pythonhosted.org/packages/miniblack-23.1.0-py3-none-any.whl"
+                .parse()
+                .unwrap(),
+            index_url: "https://pypi.org/simple/".parse().unwrap(),
+            archive_info: ProvenanceArchiveInfo {
+                hashes: Some(DirectUrlHashes {
+                    sha256: "dd0a6b2b3f2d1f6d4cc2b3e0e5f5d6c0ab2ef6c4d2c4b0b6f6e4b2e3a1f0e7d9"
+                        .to_string(),
+                }),
+            },
+        };
+        let tmpdir = tempdir().unwrap();
+        let unpacked = wheel
+            .unpack(
+                tmpdir.path(),
+                &InstallPaths::for_venv((3, 8, 5), false),
+                Path::new("/invalid"),
+                &UnpackWheelOptions {
+                    provenance_url_json: Some(provenance.clone()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // The file is written, recorded and can be read back
+        assert_eq!(
+            ProvenanceUrlJson::from_dist_info(&unpacked.dist_info).unwrap(),
+            Some(provenance)
+        );
+        let record = Record::from_path(&unpacked.dist_info.join("RECORD")).unwrap();
+        assert!(record
+            .iter()
+            .any(|entry| entry.path == "miniblack-23.1.0.dist-info/provenance_url.json"));
+    }
+
     #[test]
     fn test_entry_points() {
         // Create a virtual environment in a temporary directory
@@ -1511,6 +2173,42 @@ mod test {
         insta::assert_snapshot!(stdout);
     }
 
+    #[test]
+    fn test_relocatable_shebang() {
+        assert_eq!(
+            get_relocatable_shebang(Path::new("python"), false),
+            "#!/bin/sh\n'''exec' \"$(cd -P -- \"$(dirname -- \"$0\")\" && pwd -P)\"/'python' \"$0\" \"$@\"\n' '''"
+        );
+        assert_eq!(
+            get_relocatable_shebang(Path::new("../python/bin/python3"), false),
+            "#!/bin/sh\n'''exec' \"$(cd -P -- \"$(dirname -- \"$0\")\" && pwd -P)\"/'../python/bin/python3' \"$0\" \"$@\"\n' '''"
+        );
+        assert_eq!(
+            get_relocatable_shebang(Path::new("python.exe"), true),
+            r"#!<launcher_dir>\python.exe"
+        );
+    }
+
+    #[test]
+    fn test_shebang() {
+        assert_eq!(
+            get_shebang(Path::new("/usr/bin/python3"), false),
+            "#!/usr/bin/python3"
+        );
+        assert_eq!(
+            get_shebang(Path::new("/opt/my envs/bin/python3"), false),
+            "#!/bin/sh\n'''exec' '/opt/my envs/bin/python3' \"$0\" \"$@\"\n' '''"
+        );
+
+        let long_path = format!("/{}/bin/python3", "a".repeat(MAX_SHEBANG_LENGTH));
+        assert!(get_shebang(Path::new(&long_path), false).starts_with("#!/bin/sh\n"));
+
+        assert_eq!(
+            get_shebang(Path::new(r"C:\Program Files\Python\python.exe"), true),
+            r#"#!"C:\Program Files\Python\python.exe""#
+        );
+    }
+
     async fn download_best_ruff_wheel() -> PathBuf {
         download_best_matching_wheel("ruff",
             &[
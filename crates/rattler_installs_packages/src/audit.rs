@@ -0,0 +1,576 @@
+//! Checks packages for known vulnerabilities with the [OSV](https://osv.dev) database, which
+//! includes the advisories of the [PyPA advisory database](https://github.com/pypa/advisory-database).
+//!
+//! The packages of a resolution, a lockfile or an installed environment are converted to
+//! [`AuditPackage`]s and passed to [`OsvClient::audit`], which queries the batch endpoint of OSV
+//! and returns an [`AuditReport`] with a [`Finding`] for every vulnerability that affects one of the
+//! packages. The vulnerability records are stored in the cache directory so they are only
+//! downloaded again when they are modified.
+
+use crate::cache::CacheBucket;
+use crate::lock::Lockfile;
+use crate::python_env::Distribution;
+use crate::resolve::PinnedPackage;
+use crate::types::{NormalizedPackageName, Version};
+use fs_err as fs;
+use futures::{stream, StreamExt, TryStreamExt};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use url::Url;
+
+/// The endpoint of the OSV database that is queried by default.
+pub const OSV_API_URL: &str = "https://api.osv.dev/v1/";
+
+/// The maximum number of packages in a single batch query.
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// The number of vulnerability records that are downloaded concurrently.
+const CONCURRENT_DOWNLOADS: usize = 16;
+
+/// A package version that is checked for vulnerabilities.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AuditPackage {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The version of the package
+    pub version: Version,
+}
+
+impl AuditPackage {
+    /// Returns the packages of all targets in a lockfile. Packages that are locked to a direct url
+    /// are skipped because they are not published to the index.
+    pub fn from_lockfile(lockfile: &Lockfile) -> Vec<Self> {
+        Self::collect(
+            lockfile
+                .targets
+                .iter()
+                .flat_map(|target| target.packages.iter())
+                .filter(|package| package.url.is_none())
+                .map(|package| (&package.name, &package.version)),
+        )
+    }
+
+    /// Returns the packages of a resolution, except for the packages from a direct url.
+    pub fn from_resolution(packages: &[PinnedPackage]) -> Vec<Self> {
+        Self::collect(
+            packages
+                .iter()
+                .filter(|package| package.url.is_none())
+                .map(|package| (&package.name, &package.version)),
+        )
+    }
+
+    /// Returns the packages that are installed in an environment, see
+    /// [`crate::python_env::find_distributions_in_venv`]. Distributions that were installed from a
+    /// direct url are skipped.
+    pub fn from_distributions(distributions: &[Distribution]) -> Vec<Self> {
+        Self::collect(
+            distributions
+                .iter()
+                .filter(|distribution| distribution.direct_url.is_none())
+                .map(|distribution| (&distribution.name, &distribution.version)),
+        )
+    }
+
+    fn collect<'a>(
+        packages: impl Iterator<Item = (&'a NormalizedPackageName, &'a Version)>,
+    ) -> Vec<Self> {
+        packages
+            .map(|(name, version)| AuditPackage {
+                name: name.clone(),
+                version: version.clone(),
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+/// The severity of a vulnerability as reported by the advisory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(missing_docs)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Parses the severity that is reported in the `database_specific` section of GitHub
+    /// advisories.
+    fn from_advisory(severity: &str) -> Option<Self> {
+        match severity.to_ascii_uppercase().as_str() {
+            "LOW" => Some(Severity::Low),
+            "MODERATE" | "MEDIUM" => Some(Severity::Medium),
+            "HIGH" => Some(Severity::High),
+            "CRITICAL" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{severity}")
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            _ => Err(format!(
+                "'{s}' is not a severity, expected one of low, medium, high or critical"
+            )),
+        }
+    }
+}
+
+/// A vulnerability that affects one of the audited packages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    /// The name of the affected package
+    pub name: NormalizedPackageName,
+
+    /// The affected version of the package
+    pub version: Version,
+
+    /// The id of the vulnerability in the OSV database, e.g. `PYSEC-2023-62`
+    pub id: String,
+
+    /// Other ids of the vulnerability, e.g. CVE or GHSA ids
+    pub aliases: Vec<String>,
+
+    /// The severity of the vulnerability if the advisory reports it
+    pub severity: Option<Severity>,
+
+    /// A short description of the vulnerability
+    pub summary: Option<String>,
+
+    /// The lowest newer version of the package that fixes the vulnerability, if any
+    pub fixed_version: Option<Version>,
+}
+
+/// The result of [`OsvClient::audit`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditReport {
+    /// The number of packages that were audited
+    pub audited: usize,
+
+    /// The vulnerabilities that affect the audited packages
+    pub vulnerabilities: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// Returns the findings of at least the `threshold` severity, or all findings if there is no
+    /// threshold. Findings without a known severity only pass without a threshold.
+    pub fn findings_at_least(&self, threshold: Option<Severity>) -> impl Iterator<Item = &Finding> {
+        self.vulnerabilities.iter().filter(move |finding| {
+            threshold.map_or(true, |threshold| {
+                finding
+                    .severity
+                    .is_some_and(|severity| severity >= threshold)
+            })
+        })
+    }
+}
+
+/// An error that can occur when auditing packages.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum AuditError {
+    #[error("failed to query the OSV vulnerability database")]
+    QueryFailed(#[source] reqwest_middleware::Error),
+
+    #[error("failed to retrieve the vulnerability {0} from the OSV database")]
+    FetchFailed(String, #[source] reqwest_middleware::Error),
+
+    #[error("the OSV database returned an invalid record for the vulnerability {0}")]
+    InvalidRecord(String, #[source] serde_json::Error),
+
+    #[error("failed to access {0}")]
+    IoError(PathBuf, #[source] io::Error),
+}
+
+impl AuditError {
+    fn query(err: impl Into<reqwest_middleware::Error>) -> Self {
+        AuditError::QueryFailed(err.into())
+    }
+
+    fn fetch(id: &str, err: impl Into<reqwest_middleware::Error>) -> Self {
+        AuditError::FetchFailed(id.to_string(), err.into())
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryBatchResponse {
+    results: Vec<QueryResult>,
+}
+
+#[derive(Deserialize)]
+struct QueryResult {
+    #[serde(default)]
+    vulns: Vec<VulnerabilityId>,
+}
+
+#[derive(Deserialize)]
+struct VulnerabilityId {
+    id: String,
+    #[serde(default)]
+    modified: Option<String>,
+}
+
+/// The parts of an OSV vulnerability record that are reported.
+#[derive(Debug, Deserialize)]
+struct Vulnerability {
+    id: String,
+    #[serde(default)]
+    modified: Option<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+    #[serde(default)]
+    database_specific: Option<DatabaseSpecific>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Affected {
+    package: AffectedPackage,
+    #[serde(default)]
+    ranges: Vec<AffectedRange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedPackage {
+    name: String,
+    ecosystem: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRange {
+    #[serde(default)]
+    events: Vec<HashMap<String, String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DatabaseSpecific {
+    #[serde(default)]
+    severity: Option<String>,
+}
+
+impl Vulnerability {
+    fn severity(&self) -> Option<Severity> {
+        self.database_specific
+            .as_ref()?
+            .severity
+            .as_deref()
+            .and_then(Severity::from_advisory)
+    }
+
+    /// Returns the lowest version of the package that fixes this vulnerability and that is newer
+    /// than `version`.
+    fn fixed_version(&self, name: &NormalizedPackageName, version: &Version) -> Option<Version> {
+        self.affected
+            .iter()
+            .filter(|affected| affected.package.ecosystem == "PyPI")
+            .filter(|affected| {
+                NormalizedPackageName::from_str(&affected.package.name)
+                    .ok()
+                    .as_ref()
+                    == Some(name)
+            })
+            .flat_map(|affected| affected.ranges.iter())
+            .flat_map(|range| range.events.iter())
+            .filter_map(|event| Version::from_str(event.get("fixed")?).ok())
+            .filter(|fixed| fixed > version)
+            .min()
+    }
+}
+
+/// Queries the OSV database for the vulnerabilities of packages.
+#[derive(Debug, Clone)]
+pub struct OsvClient {
+    client: ClientWithMiddleware,
+    api_url: Url,
+    cache_dir: Option<PathBuf>,
+}
+
+impl OsvClient {
+    /// Constructs a client that queries the public OSV database.
+    pub fn new(client: ClientWithMiddleware) -> Self {
+        Self {
+            client,
+            api_url: Url::parse(OSV_API_URL).expect("the OSV url is valid"),
+            cache_dir: None,
+        }
+    }
+
+    /// Queries the OSV compatible API at `api_url` instead of the public database.
+    pub fn with_api_url(mut self, api_url: Url) -> Self {
+        self.api_url = api_url;
+        self
+    }
+
+    /// Stores the vulnerability records in the [`CacheBucket::Advisories`] bucket of the cache
+    /// directory.
+    pub fn with_cache_dir(mut self, cache_dir: &Path) -> Self {
+        self.cache_dir = Some(CacheBucket::Advisories.path(cache_dir));
+        self
+    }
+
+    /// Returns the vulnerabilities that affect `packages`.
+    pub async fn audit(&self, packages: &[AuditPackage]) -> Result<AuditReport, AuditError> {
+        // The batch endpoint only returns the ids of the vulnerabilities
+        let mut results = Vec::with_capacity(packages.len());
+        for chunk in packages.chunks(MAX_BATCH_SIZE) {
+            let queries = chunk
+                .iter()
+                .map(|package| {
+                    serde_json::json!({
+                        "package": { "name": package.name.as_str(), "ecosystem": "PyPI" },
+                        "version": package.version.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let response: QueryBatchResponse = self
+                .client
+                .post(self.endpoint("querybatch"))
+                .json(&serde_json::json!({ "queries": queries }))
+                .send()
+                .await
+                .map_err(AuditError::query)?
+                .error_for_status()
+                .map_err(AuditError::query)?
+                .json()
+                .await
+                .map_err(AuditError::query)?;
+            results.extend(response.results);
+        }
+
+        // Retrieve the details of every vulnerability once
+        let mut ids = results
+            .iter()
+            .flat_map(|result| result.vulns.iter())
+            .map(|vuln| (vuln.id.as_str(), vuln.modified.as_deref()))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup_by_key(|(id, _)| *id);
+        let vulnerabilities: HashMap<String, Vulnerability> = stream::iter(ids)
+            .map(|(id, modified)| self.vulnerability(id, modified))
+            .buffer_unordered(CONCURRENT_DOWNLOADS)
+            .map_ok(|vulnerability| (vulnerability.id.clone(), vulnerability))
+            .try_collect()
+            .await?;
+
+        let mut findings = Vec::new();
+        for (package, result) in packages.iter().zip(&results) {
+            for vulnerability in result
+                .vulns
+                .iter()
+                .filter_map(|vuln| vulnerabilities.get(&vuln.id))
+            {
+                findings.push(Finding {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    id: vulnerability.id.clone(),
+                    aliases: vulnerability.aliases.clone(),
+                    severity: vulnerability.severity(),
+                    summary: vulnerability.summary.clone(),
+                    fixed_version: vulnerability.fixed_version(&package.name, &package.version),
+                });
+            }
+        }
+
+        Ok(AuditReport {
+            audited: packages.len(),
+            vulnerabilities: findings,
+        })
+    }
+
+    /// Returns the vulnerability record `id` from the cache if it was not modified since, or
+    /// downloads it.
+    async fn vulnerability(
+        &self,
+        id: &str,
+        modified: Option<&str>,
+    ) -> Result<Vulnerability, AuditError> {
+        let cache_path = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", id.replace(['/', '\\'], "_"))));
+        if let (Some(path), Some(modified)) = (&cache_path, modified) {
+            let cached = fs::read(path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<Vulnerability>(&bytes).ok());
+            if let Some(cached) =
+                cached.filter(|cached| cached.modified.as_deref() == Some(modified))
+            {
+                return Ok(cached);
+            }
+        }
+
+        let bytes = self
+            .client
+            .get(self.endpoint(&format!("vulns/{id}")))
+            .send()
+            .await
+            .map_err(|e| AuditError::fetch(id, e))?
+            .error_for_status()
+            .map_err(|e| AuditError::fetch(id, e))?
+            .bytes()
+            .await
+            .map_err(|e| AuditError::fetch(id, e))?;
+        let vulnerability: Vulnerability = serde_json::from_slice(&bytes)
+            .map_err(|e| AuditError::InvalidRecord(id.to_string(), e))?;
+
+        // A record without a modification time cannot be validated, so it is not cached either
+        if let (Some(path), Some(_)) = (cache_path, &vulnerability.modified) {
+            fs::create_dir_all(path.parent().expect("the cache path has a parent"))
+                .and_then(|_| fs::write(&path, &bytes))
+                .map_err(|e| AuditError::IoError(path, e))?;
+        }
+        Ok(vulnerability)
+    }
+
+    fn endpoint(&self, path: &str) -> Url {
+        let mut api_url = self.api_url.clone();
+        if !api_url.path().ends_with('/') {
+            api_url.set_path(&format!("{}/", api_url.path()));
+        }
+        api_url
+            .join(path)
+            .expect("the endpoint is a valid relative url")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PackageName;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use reqwest::Client;
+    use serde_json::{json, Value};
+    use std::future::IntoFuture;
+    use std::net::SocketAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn package(name: &str, version: &str) -> AuditPackage {
+        AuditPackage {
+            name: PackageName::from_str(name).unwrap().into(),
+            version: Version::from_str(version).unwrap(),
+        }
+    }
+
+    /// Serves an OSV API that reports a single vulnerability for flask 2.0.0, returns the url of
+    /// the API and a counter of the vulnerability records that were served.
+    async fn make_osv_server() -> (Url, Arc<AtomicUsize>) {
+        let downloads = Arc::new(AtomicUsize::new(0));
+        let served = downloads.clone();
+        let router = Router::new()
+            .route(
+                "/v1/querybatch",
+                post(|Json(body): Json<Value>| async move {
+                    let results = body["queries"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|query| {
+                            if query["package"]["name"] == "flask" && query["version"] == "2.0.0" {
+                                json!({ "vulns": [{ "id": "GHSA-m2qf", "modified": "2023-05-01T00:00:00Z" }] })
+                            } else {
+                                json!({})
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    Json(json!({ "results": results }))
+                }),
+            )
+            .route(
+                "/v1/vulns/GHSA-m2qf",
+                get(move || async move {
+                    served.fetch_add(1, Ordering::SeqCst);
+                    Json(json!({
+                        "id": "GHSA-m2qf",
+                        "modified": "2023-05-01T00:00:00Z",
+                        "summary": "Possible disclosure of a permanent session cookie",
+                        "aliases": ["CVE-2023-30861"],
+                        "affected": [{
+                            "package": { "name": "Flask", "ecosystem": "PyPI" },
+                            "ranges": [{ "events": [
+                                { "introduced": "0" }, { "fixed": "2.2.5" },
+                                { "introduced": "2.3.0" }, { "fixed": "2.3.2" }
+                            ] }]
+                        }],
+                        "database_specific": { "severity": "HIGH" }
+                    }))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind(SocketAddr::new([127, 0, 0, 1].into(), 0))
+            .await
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+        (
+            Url::parse(&format!("http://{address}/v1")).unwrap(),
+            downloads,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_audit() {
+        let (api_url, downloads) = make_osv_server().await;
+        let cache_dir = tempfile::tempdir().unwrap();
+        let client = OsvClient::new(ClientWithMiddleware::from(Client::new()))
+            .with_api_url(api_url)
+            .with_cache_dir(cache_dir.path());
+
+        let packages = [package("Flask", "2.0.0"), package("click", "8.1.7")];
+        let report = client.audit(&packages).await.unwrap();
+        assert_eq!(report.audited, 2);
+        assert_eq!(report.vulnerabilities.len(), 1);
+
+        let finding = &report.vulnerabilities[0];
+        assert_eq!(finding.name, packages[0].name);
+        assert_eq!(finding.aliases, vec![String::from("CVE-2023-30861")]);
+        assert_eq!(finding.severity, Some(Severity::High));
+        assert_eq!(
+            finding.fixed_version,
+            Some(Version::from_str("2.2.5").unwrap())
+        );
+        assert_eq!(report.findings_at_least(Some(Severity::High)).count(), 1);
+        assert_eq!(
+            report.findings_at_least(Some(Severity::Critical)).count(),
+            0
+        );
+
+        // Records that were not modified are read from the cache
+        client.audit(&packages).await.unwrap();
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+    }
+}
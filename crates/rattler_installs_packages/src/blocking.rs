@@ -0,0 +1,309 @@
+//! A blocking API for consumers that do not use async Rust, similar to `reqwest::blocking`.
+//!
+//! The types in this module wrap their async counterparts and drive them to completion on a
+//! runtime that is owned by the [`PackageDb`]. All objects that are created from the same
+//! [`PackageDb`] share that runtime.
+//!
+//! The functions in this module must not be called from within an async runtime, doing so
+//! panics. Use the async API directly in that case.
+//!
+//! ```no_run
+//! # use rattler_installs_packages::blocking::PackageDb;
+//! # use rattler_installs_packages::index::PackageSources;
+//! # use rattler_installs_packages::python_env::{Pep508EnvMakers, WheelTags};
+//! # use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+//! # use rattler_installs_packages::types::Requirement;
+//! # use std::collections::HashMap;
+//! # use std::str::FromStr;
+//! # use std::sync::Arc;
+//! # fn main() -> miette::Result<()> {
+//! let sources = PackageSources::from(url::Url::parse("https://pypi.org/simple/").unwrap());
+//! let client = reqwest_middleware::ClientWithMiddleware::from(reqwest::Client::new());
+//! let package_db = PackageDb::new(sources, client, "/tmp/rip-cache".as_ref())?;
+//!
+//! let python = std::path::Path::new("/usr/bin/python3");
+//! let env_markers = Arc::new(package_db.block_on(Pep508EnvMakers::from_python(python)).unwrap().0);
+//! let wheel_tags = Arc::new(package_db.block_on(WheelTags::from_python(python)).unwrap());
+//!
+//! let requirements = [Requirement::from_str("flask").unwrap()];
+//! let packages = package_db.resolve(
+//!     &requirements,
+//!     env_markers,
+//!     Some(wheel_tags),
+//!     HashMap::default(),
+//!     HashMap::default(),
+//!     ResolveOptions::default(),
+//!     HashMap::default(),
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! By default a multi-threaded runtime is created. Use [`PackageDb::from_async_with_runtime`] to
+//! provide a runtime of your own instead, e.g. a current-thread runtime to drive all async work
+//! on the calling thread. See the [crate documentation](crate#runtimes) for what the library
+//! requires of a runtime.
+
+use crate::artifacts::wheel::UnpackWheelOptions;
+use crate::artifacts::Wheel;
+use crate::index::{self, ArtifactRequest, PackageSources};
+use crate::install::{self, InstallReport, InstallTarget};
+use crate::python_env::{ParsePythonInterpreterVersionError, WheelTags};
+use crate::resolve::solve_options::ResolveOptions;
+use crate::resolve::{self, PinnedPackage, PypiVersion};
+use crate::types::{
+    ArtifactFromSource, ArtifactInfo, DirectUrlJson, NormalizedPackageName, WheelCoreMetadata,
+};
+use crate::wheel_builder::{self, WheelBuildError};
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep508_rs::{MarkerEnvironment, Requirement};
+use reqwest_middleware::ClientWithMiddleware;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// A blocking version of [`index::PackageDb`].
+#[derive(Clone)]
+pub struct PackageDb {
+    inner: Arc<index::PackageDb>,
+    runtime: Arc<Runtime>,
+}
+
+impl PackageDb {
+    /// Constructs a new [`PackageDb`] that reads information from the specified sources. See
+    /// [`index::PackageDb::new`].
+    pub fn new(
+        package_sources: PackageSources,
+        client: ClientWithMiddleware,
+        cache_dir: &Path,
+    ) -> miette::Result<Self> {
+        let package_db = index::PackageDb::new(package_sources, client, cache_dir)?;
+        Self::from_async(Arc::new(package_db))
+    }
+
+    /// Wraps an existing [`index::PackageDb`].
+    pub fn from_async(package_db: Arc<index::PackageDb>) -> miette::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("rip-blocking")
+            .build()
+            .into_diagnostic()?;
+        Ok(Self::from_async_with_runtime(package_db, runtime))
+    }
+
+    /// Wraps an existing [`index::PackageDb`] and drives it on the given `runtime`. The runtime
+    /// must have its IO and time drivers enabled, see [`tokio::runtime::Builder::enable_all`].
+    ///
+    /// ```no_run
+    /// # use rattler_installs_packages::blocking::PackageDb;
+    /// # use rattler_installs_packages::index;
+    /// # use std::sync::Arc;
+    /// # fn example(package_db: Arc<index::PackageDb>) -> std::io::Result<()> {
+    /// let runtime = tokio::runtime::Builder::new_current_thread()
+    ///     .enable_all()
+    ///     .build()?;
+    /// let package_db = PackageDb::from_async_with_runtime(package_db, runtime);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_async_with_runtime(package_db: Arc<index::PackageDb>, runtime: Runtime) -> Self {
+        Self {
+            inner: package_db,
+            runtime: Arc::new(runtime),
+        }
+    }
+
+    /// Returns the async [`index::PackageDb`] that is wrapped by this instance.
+    pub fn as_async(&self) -> &Arc<index::PackageDb> {
+        &self.inner
+    }
+
+    /// Runs an arbitrary future of the async API to completion on the runtime of this instance.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Returns the available artifacts of the package with the given name, ordered from the
+    /// highest to the lowest version. See [`index::PackageDb::available_artifacts`].
+    pub fn available_artifacts(
+        &self,
+        name: NormalizedPackageName,
+    ) -> miette::Result<IndexMap<PypiVersion, Vec<Arc<ArtifactInfo>>>> {
+        self.block_on(
+            self.inner
+                .available_artifacts(ArtifactRequest::FromIndex(name)),
+        )
+        .cloned()
+    }
+
+    /// Returns the metadata from a set of artifacts. See [`index::PackageDb::get_metadata`].
+    pub fn get_metadata<'a, A: Borrow<ArtifactInfo>>(
+        &self,
+        artifacts: &'a [A],
+        wheel_builder: Option<&WheelBuilder>,
+    ) -> miette::Result<Option<(&'a A, WheelCoreMetadata)>> {
+        self.block_on(self.inner.get_metadata(
+            artifacts,
+            wheel_builder.map(|builder| builder.inner.as_ref()),
+        ))
+    }
+
+    /// Downloads the wheel of an artifact, building it if the artifact is an sdist. See
+    /// [`index::PackageDb::get_wheel`].
+    pub fn get_wheel(
+        &self,
+        artifact_info: &ArtifactInfo,
+        wheel_builder: Option<&WheelBuilder>,
+    ) -> miette::Result<(Wheel, Option<DirectUrlJson>)> {
+        self.block_on(self.inner.get_wheel(
+            artifact_info,
+            wheel_builder.map(|builder| builder.inner.as_ref()),
+        ))
+    }
+
+    /// Resolves the `requirements` to a set of pinned packages. See [`resolve::resolve`] for a
+    /// description of the arguments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn resolve<'r>(
+        &self,
+        requirements: impl IntoIterator<Item = &'r Requirement>,
+        env_markers: Arc<MarkerEnvironment>,
+        compatible_tags: Option<Arc<WheelTags>>,
+        locked_packages: HashMap<NormalizedPackageName, PinnedPackage>,
+        favored_packages: HashMap<NormalizedPackageName, PinnedPackage>,
+        options: ResolveOptions,
+        env_variables: HashMap<String, String>,
+    ) -> miette::Result<Vec<PinnedPackage>> {
+        self.block_on(resolve::resolve(
+            self.inner.clone(),
+            requirements,
+            env_markers,
+            compatible_tags,
+            locked_packages,
+            favored_packages,
+            options,
+            env_variables,
+        ))
+    }
+
+    /// Constructs a [`WheelBuilder`] that builds wheels from sdists with this instance. See
+    /// [`wheel_builder::WheelBuilder::new`].
+    pub fn wheel_builder(
+        &self,
+        env_markers: Arc<MarkerEnvironment>,
+        wheel_tags: Option<Arc<WheelTags>>,
+        resolve_options: ResolveOptions,
+        env_variables: HashMap<String, String>,
+    ) -> Result<WheelBuilder, ParsePythonInterpreterVersionError> {
+        let inner = wheel_builder::WheelBuilder::new(
+            self.inner.clone(),
+            env_markers,
+            wheel_tags,
+            resolve_options,
+            env_variables,
+        )?;
+        Ok(WheelBuilder {
+            inner: Arc::new(inner),
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// Converges the `target` environment to contain exactly the specified `packages`. See
+    /// [`install::sync_environment`].
+    pub fn sync_environment<T: InstallTarget + Sync + ?Sized>(
+        &self,
+        target: &T,
+        wheel_builder: Option<&WheelBuilder>,
+        packages: &[PinnedPackage],
+        options: &UnpackWheelOptions<'_>,
+    ) -> miette::Result<InstallReport> {
+        self.block_on(install::sync_environment(
+            target,
+            &self.inner,
+            wheel_builder.map(|builder| builder.inner.as_ref()),
+            packages,
+            options,
+        ))
+    }
+}
+
+/// A blocking version of [`wheel_builder::WheelBuilder`]. Construct it with
+/// [`PackageDb::wheel_builder`].
+#[derive(Clone)]
+pub struct WheelBuilder {
+    inner: Arc<wheel_builder::WheelBuilder>,
+    runtime: Arc<Runtime>,
+}
+
+impl WheelBuilder {
+    /// Returns the async [`wheel_builder::WheelBuilder`] that is wrapped by this instance.
+    pub fn as_async(&self) -> &Arc<wheel_builder::WheelBuilder> {
+        &self.inner
+    }
+
+    /// Builds a wheel from an sdist or source tree. See
+    /// [`wheel_builder::WheelBuilder::build_wheel`].
+    pub fn build_wheel<S: ArtifactFromSource>(&self, sdist: &S) -> Result<Wheel, WheelBuildError> {
+        self.runtime.block_on(self.inner.build_wheel(sdist))
+    }
+
+    /// Returns the metadata of an sdist or source tree. See
+    /// [`wheel_builder::WheelBuilder::get_sdist_metadata`].
+    pub fn get_sdist_metadata<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
+        self.runtime.block_on(self.inner.get_sdist_metadata(sdist))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index::PackageSourcesBuilder;
+
+    #[test]
+    fn test_block_on() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSources::from(url::Url::parse("https://pypi.org/simple/").unwrap());
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        let package_db = PackageDb::new(sources, client, cache_dir.path()).unwrap();
+
+        // Clones share the runtime
+        let clone = package_db.clone();
+        assert_eq!(clone.block_on(async { 1 + 1 }), 2);
+        assert!(Arc::ptr_eq(&package_db.runtime, &clone.runtime));
+    }
+
+    #[test]
+    fn test_current_thread_runtime() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSourcesBuilder::new("https://pypi.org/simple/".parse().unwrap())
+            .with_find_links(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/wheels"))
+            .without_index()
+            .build()
+            .unwrap();
+        let client = ClientWithMiddleware::from(reqwest::Client::new());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let package_db = PackageDb::from_async_with_runtime(
+            Arc::new(index::PackageDb::new(sources, client, cache_dir.path()).unwrap()),
+            runtime,
+        );
+
+        let name: NormalizedPackageName = "wordle_python".parse().unwrap();
+        let artifacts = package_db.available_artifacts(name).unwrap();
+        let (_, artifacts) = artifacts.first().unwrap();
+        let (_, metadata) = package_db.get_metadata(artifacts, None).unwrap().unwrap();
+        assert_eq!(metadata.name.as_str(), "wordle-python");
+
+        let (wheel, _) = package_db.get_wheel(&artifacts[0], None).unwrap();
+        assert_eq!(wheel.name.distribution.as_str(), "wordle-python");
+    }
+}
@@ -0,0 +1,487 @@
+//! Inspecting, cleaning and verifying the cache directory of a [`crate::index::PackageDb`].
+//!
+//! The cache directory is divided into buckets, one for every kind of data that is cached, see
+//! [`CacheBucket`]. Most of the buckets are keyed by hashes, so only the buckets that store built
+//! or unpacked wheels can be cleaned for a specific package.
+
+use crate::types::{NormalizedPackageName, PackageName};
+#[cfg(feature = "sdist")]
+use crate::wheel_builder::{WheelCache, WheelCacheError};
+use fs_err as fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// The maximum depth at which the `.dist-info` directory of a wheel is located in an unpacked
+/// tree, e.g. `lib/python3.11/site-packages/foo-1.0.dist-info`.
+const MAX_DIST_INFO_DEPTH: usize = 4;
+
+/// A subdirectory of the cache directory that stores a single kind of data.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CacheBucket {
+    /// Responses of http requests, including the pages of the index and downloaded artifacts
+    Http,
+
+    /// The metadata of artifacts, keyed by the hash of the artifact
+    Metadata,
+
+    /// Wheels that were built from source distributions
+    BuiltWheels,
+
+    /// The files of wheels that are linked into environments, see
+    /// [`crate::install::WheelFileCache`]
+    WheelFiles,
+
+    /// Wheels that were unpacked for a specific environment, see
+    /// [`crate::install::UnpackedWheelCache`]
+    UnpackedWheels,
+
+    /// Environments that are provisioned on demand, see
+    /// [`crate::install::EphemeralEnvironments`]
+    Environments,
+
+    /// Standalone python interpreters, see [`crate::python_env::ManagedPythons`]
+    Pythons,
+
+    /// The TUF metadata that was verified for repositories, see
+    /// [`crate::index::TufRepository`]
+    Tuf,
+
+    /// Vulnerability records of the OSV database, see [`crate::audit::OsvClient`]
+    Advisories,
+
+    /// The repositories of git dependencies and the checkouts of their revisions
+    Git,
+}
+
+impl CacheBucket {
+    /// All the buckets in the cache directory.
+    pub const ALL: [CacheBucket; 10] = [
+        CacheBucket::Http,
+        CacheBucket::Metadata,
+        CacheBucket::BuiltWheels,
+        CacheBucket::WheelFiles,
+        CacheBucket::UnpackedWheels,
+        CacheBucket::Environments,
+        CacheBucket::Pythons,
+        CacheBucket::Tuf,
+        CacheBucket::Advisories,
+        CacheBucket::Git,
+    ];
+
+    /// Returns the name of the subdirectory of the cache directory that contains the bucket.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            CacheBucket::Http => "http",
+            CacheBucket::Metadata => "metadata",
+            CacheBucket::BuiltWheels => "local_wheels",
+            CacheBucket::WheelFiles => "wheel-files",
+            CacheBucket::UnpackedWheels => "unpacked-wheels",
+            CacheBucket::Environments => "environments",
+            CacheBucket::Pythons => "pythons",
+            CacheBucket::Tuf => "tuf",
+            CacheBucket::Advisories => "advisories",
+            CacheBucket::Git => "git",
+        }
+    }
+
+    /// Returns the location of the bucket in the cache directory.
+    pub fn path(self, cache_dir: &Path) -> PathBuf {
+        cache_dir.join(self.dir_name())
+    }
+}
+
+/// An error that can occur when inspecting or modifying the cache directory.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum CacheError {
+    #[error("failed to access {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[cfg(feature = "sdist")]
+    #[error(transparent)]
+    BuiltWheels(#[from] WheelCacheError),
+}
+
+/// The disk usage of a single bucket of the cache.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CacheBucketInfo {
+    /// The bucket
+    pub bucket: CacheBucket,
+
+    /// The location of the bucket
+    pub path: PathBuf,
+
+    /// The number of files in the bucket
+    pub files: u64,
+
+    /// The total size of the files in the bucket in bytes
+    pub size: u64,
+}
+
+/// Determines which entries are removed by [`clean_cache`].
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// Only remove entries that were last modified longer ago than this
+    pub older_than: Option<Duration>,
+
+    /// Only remove the built and unpacked wheels of this package
+    pub package: Option<NormalizedPackageName>,
+
+    /// Also remove the files of wheels that are linked into environments. Environments that were
+    /// installed with [`crate::install::LinkStrategy::Symlink`] refer to these files and break
+    /// when they are removed.
+    pub wheel_files: bool,
+}
+
+/// The number of files and bytes that were freed by [`clean_cache`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CleanSummary {
+    /// The number of removed files
+    pub files: u64,
+
+    /// The total size of the removed files in bytes
+    pub size: u64,
+}
+
+/// An entry in the cache whose contents do not match the hash it is stored under.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CorruptedEntry {
+    /// The bucket that contains the entry
+    pub bucket: CacheBucket,
+
+    /// A description of the entry, e.g. its path or the filename of the wheel
+    pub entry: String,
+}
+
+/// Returns the disk usage of every bucket in the cache directory.
+pub fn cache_info(cache_dir: &Path) -> Result<Vec<CacheBucketInfo>, CacheError> {
+    CacheBucket::ALL
+        .into_iter()
+        .map(|bucket| {
+            let path = bucket.path(cache_dir);
+            let (files, size) = disk_usage(&path)?;
+            Ok(CacheBucketInfo {
+                bucket,
+                path,
+                files,
+                size,
+            })
+        })
+        .collect()
+}
+
+/// Removes entries from the cache directory. Without any options the whole cache is removed,
+/// except for the managed python interpreters and the wheel files because environments refer to
+/// them, see [`CleanOptions::wheel_files`].
+pub fn clean_cache(cache_dir: &Path, options: &CleanOptions) -> Result<CleanSummary, CacheError> {
+    let before = cache_info(cache_dir)?;
+
+    let cutoff = options.older_than.map(|older_than| {
+        SystemTime::now()
+            .checked_sub(older_than)
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    });
+    let is_expired = |time: SystemTime| cutoff.map_or(true, |cutoff| time < cutoff);
+
+    for bucket in CacheBucket::ALL {
+        let path = bucket.path(cache_dir);
+        match (bucket, &options.package) {
+            (CacheBucket::Pythons, _) => {}
+            (CacheBucket::WheelFiles, _) if !options.wheel_files => {}
+            (_, None) if cutoff.is_none() => remove_all(&path)?,
+            #[cfg(feature = "sdist")]
+            (CacheBucket::BuiltWheels, package) => {
+                WheelCache::new(path).remove_wheels(|filename, time| {
+                    package.as_ref().map_or(true, |package| {
+                        filename.distribution.as_str() == package.as_str()
+                    }) && is_expired(time)
+                })?;
+            }
+            (CacheBucket::UnpackedWheels, package) => {
+                for entry in read_dir(&path)? {
+                    let matches_package = match package {
+                        Some(package) => contains_dist_info(&entry, package, MAX_DIST_INFO_DEPTH)?,
+                        None => true,
+                    };
+                    if matches_package && is_expired(modified(&entry)?) {
+                        remove_all(&entry)?;
+                    }
+                }
+            }
+            // The other buckets cannot be attributed to a package.
+            (_, Some(_)) => {}
+            (CacheBucket::Environments, None) => {
+                for entry in read_dir(&path)? {
+                    if is_expired(modified(&entry)?) {
+                        remove_all(&entry)?;
+                    }
+                }
+            }
+            // Only whole checkouts are removed, removing files from the repositories would
+            // corrupt them
+            (CacheBucket::Git, None) => {
+                for repository in read_dir(&path.join("checkouts"))? {
+                    for entry in read_dir(&repository)? {
+                        if is_expired(modified(&entry)?) {
+                            remove_all(&entry)?;
+                        }
+                    }
+                }
+            }
+            (_, None) => remove_expired_files(&path, &is_expired)?,
+        }
+    }
+
+    let after = cache_info(cache_dir)?;
+    Ok(before
+        .iter()
+        .zip(after.iter())
+        .fold(CleanSummary::default(), |summary, (before, after)| {
+            CleanSummary {
+                files: summary.files + before.files.saturating_sub(after.files),
+                size: summary.size + before.size.saturating_sub(after.size),
+            }
+        }))
+}
+
+/// Checks the integrity of the content addressed buckets of the cache, i.e. the built wheels and
+/// the wheel files. Returns the entries whose contents do not match the hash they are stored
+/// under.
+pub fn verify_cache(cache_dir: &Path) -> Result<Vec<CorruptedEntry>, CacheError> {
+    #[cfg(feature = "sdist")]
+    let built_wheels = WheelCache::new(CacheBucket::BuiltWheels.path(cache_dir))
+        .verify()?
+        .into_iter()
+        .map(|filename| CorruptedEntry {
+            bucket: CacheBucket::BuiltWheels,
+            entry: filename.to_string(),
+        });
+    // Without the `sdist` feature no wheels are built, so there are none to verify
+    #[cfg(not(feature = "sdist"))]
+    let built_wheels = std::iter::empty();
+
+    let wheel_files_path = CacheBucket::WheelFiles.path(cache_dir);
+    let wheel_files = crate::install::WheelFileCache::new(&wheel_files_path)
+        .verify()
+        .map_err(|e| CacheError::IoError(wheel_files_path, e))?
+        .into_iter()
+        .map(|path| CorruptedEntry {
+            bucket: CacheBucket::WheelFiles,
+            entry: path.display().to_string(),
+        });
+
+    Ok(built_wheels.chain(wheel_files).collect())
+}
+
+/// Returns the paths of the entries in `dir`, or nothing if `dir` does not exist.
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, CacheError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect()
+        })
+        .map_err(|e| CacheError::IoError(dir.to_path_buf(), e))
+}
+
+/// Returns the time at which `path` was last modified.
+fn modified(path: &Path) -> Result<SystemTime, CacheError> {
+    fs::symlink_metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| CacheError::IoError(path.to_path_buf(), e))
+}
+
+/// Removes the file or directory at `path` if it exists.
+fn remove_all(path: &Path) -> Result<(), CacheError> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    };
+    result.map_err(|e| CacheError::IoError(path.to_path_buf(), e))
+}
+
+/// Removes the files in `dir` and its subdirectories for which `is_expired` returns true.
+fn remove_expired_files(
+    dir: &Path,
+    is_expired: &impl Fn(SystemTime) -> bool,
+) -> Result<(), CacheError> {
+    for entry in read_dir(dir)? {
+        let metadata =
+            fs::symlink_metadata(&entry).map_err(|e| CacheError::IoError(entry.clone(), e))?;
+        if metadata.is_dir() {
+            remove_expired_files(&entry, is_expired)?;
+        } else if is_expired(modified(&entry)?) {
+            remove_all(&entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the number of files in `path` and their total size in bytes.
+fn disk_usage(path: &Path) -> Result<(u64, u64), CacheError> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(CacheError::IoError(path.to_path_buf(), e)),
+    };
+    if !metadata.is_dir() {
+        return Ok((1, metadata.len()));
+    }
+
+    let mut usage = (0, 0);
+    for entry in read_dir(path)? {
+        let (files, size) = disk_usage(&entry)?;
+        usage = (usage.0 + files, usage.1 + size);
+    }
+    Ok(usage)
+}
+
+/// Returns true if `dir` contains the `.dist-info` directory of `package` at most `depth` levels
+/// deep.
+fn contains_dist_info(
+    dir: &Path,
+    package: &NormalizedPackageName,
+    depth: usize,
+) -> Result<bool, CacheError> {
+    if depth == 0 || !fs::symlink_metadata(dir).is_ok_and(|metadata| metadata.is_dir()) {
+        return Ok(false);
+    }
+    for entry in read_dir(dir)? {
+        let is_dist_info = entry
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".dist-info"))
+            .and_then(|stem| stem.rsplit_once('-'))
+            .and_then(|(name, _version)| PackageName::from_str(name).ok())
+            .is_some_and(|name| name.as_str() == package.as_str());
+        if is_dist_info || contains_dist_info(&entry, package, depth - 1)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_cache_info() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join("http/a/b/c/entry"), "hello");
+        write(&dir.path().join("metadata/a/b/c/entry"), "hi");
+        write(&dir.path().join("metadata/a/b/d/entry"), "hi");
+
+        let info = cache_info(dir.path()).unwrap();
+        assert_eq!(info.len(), CacheBucket::ALL.len());
+        assert_eq!(
+            (info[0].bucket, info[0].files, info[0].size),
+            (CacheBucket::Http, 1, 5)
+        );
+        assert_eq!(
+            (info[1].bucket, info[1].files, info[1].size),
+            (CacheBucket::Metadata, 2, 4)
+        );
+        assert_eq!((info[2].files, info[2].size), (0, 0));
+    }
+
+    #[test]
+    fn test_clean_everything_but_pythons_and_wheel_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join("http/a/b/c/entry"), "hello");
+        write(&dir.path().join("unpacked-wheels/abc/foo/__init__.py"), "");
+        write(&dir.path().join("pythons/cpython-3.12/bin/python"), "");
+        write(&dir.path().join("wheel-files/ab/cdef"), "");
+
+        let summary = clean_cache(dir.path(), &CleanOptions::default()).unwrap();
+        assert_eq!(summary, CleanSummary { files: 2, size: 5 });
+        assert!(!dir.path().join("http").exists());
+        assert!(!dir.path().join("unpacked-wheels").exists());
+        assert!(dir.path().join("pythons/cpython-3.12/bin/python").exists());
+        assert!(dir.path().join("wheel-files/ab/cdef").exists());
+
+        let options = CleanOptions {
+            wheel_files: true,
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_cache(dir.path(), &options).unwrap(),
+            CleanSummary { files: 1, size: 0 }
+        );
+        assert!(!dir.path().join("wheel-files").exists());
+    }
+
+    #[test]
+    fn test_clean_older_than() {
+        let dir = tempfile::tempdir().unwrap();
+        write(&dir.path().join("http/a/b/c/entry"), "hello");
+
+        let options = CleanOptions {
+            older_than: Some(Duration::from_secs(3600)),
+            ..CleanOptions::default()
+        };
+        assert_eq!(
+            clean_cache(dir.path(), &options).unwrap(),
+            CleanSummary::default()
+        );
+        assert!(dir.path().join("http/a/b/c/entry").exists());
+    }
+
+    #[test]
+    fn test_clean_package() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            &dir.path().join(
+                "unpacked-wheels/abc/lib/python3.12/site-packages/Foo_Bar-1.0.dist-info/RECORD",
+            ),
+            "",
+        );
+        write(
+            &dir.path()
+                .join("unpacked-wheels/def/Lib/site-packages/baz-2.0.dist-info/RECORD"),
+            "",
+        );
+        write(&dir.path().join("http/a/b/c/entry"), "hello");
+
+        let options = CleanOptions {
+            package: Some("foo-bar".parse().unwrap()),
+            ..CleanOptions::default()
+        };
+        clean_cache(dir.path(), &options).unwrap();
+        assert!(!dir.path().join("unpacked-wheels/abc").exists());
+        assert!(dir.path().join("unpacked-wheels/def").exists());
+        assert!(dir.path().join("http/a/b/c/entry").exists());
+    }
+
+    #[test]
+    fn test_verify_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(verify_cache(dir.path()).unwrap().is_empty());
+
+        let files = crate::install::WheelFileCache::new(CacheBucket::WheelFiles.path(dir.path()));
+        let entry = files.insert(&mut "hello".as_bytes(), false).unwrap();
+        assert!(verify_cache(dir.path()).unwrap().is_empty());
+
+        fs::write(&entry.path, "goodbye").unwrap();
+        assert_eq!(
+            verify_cache(dir.path()).unwrap(),
+            vec![CorruptedEntry {
+                bucket: CacheBucket::WheelFiles,
+                entry: entry.path.display().to_string(),
+            }]
+        );
+    }
+}
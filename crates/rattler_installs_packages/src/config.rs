@@ -0,0 +1,661 @@
+//! Layered configuration files and environment variables.
+//!
+//! The configuration is read from several layers, in order of increasing precedence:
+//!
+//! 1. The global configuration file, `/etc/rip/rip.toml` or `%PROGRAMDATA%\rip\rip.toml` on
+//!    Windows.
+//! 2. The user configuration file, `rip/rip.toml` in the configuration directory of the user, e.g.
+//!    `~/.config/rip/rip.toml` on Linux.
+//! 3. The project configuration, a `rip.toml` file or the `[tool.rip]` table of a
+//!    `pyproject.toml` in the project directory or one of its parents.
+//! 4. Environment variables that start with `RIP_`, e.g. `RIP_INDEX_URL`.
+//!
+//! A setting in a layer replaces the setting of the layers below it, lists are not merged.
+//! Applications apply their command line flags on top of the result of [`ConfigLoader::load`].
+
+use crate::license_policy::LicensePolicy;
+use crate::resolve::policy::{PackageRule, PackageRules};
+use crate::resolve::solve_options::SDistResolution;
+use fs_err as fs;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use url::Url;
+
+/// The name of a standalone configuration file.
+pub const CONFIG_FILE_NAME: &str = "rip.toml";
+
+/// The settings of a single layer or of the merged layers, see the [module docs](self).
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// The url of the index to retrieve packages from
+    pub index_url: Option<Url>,
+
+    /// Local directories with artifacts to use next to the index. Relative paths are relative to
+    /// the configuration file they are specified in.
+    pub find_links: Option<Vec<PathBuf>>,
+
+    /// Only use the artifacts from the `find_links` directories
+    pub no_index: Option<bool>,
+
+    /// The directory in which downloads, metadata and built wheels are cached
+    pub cache_dir: Option<PathBuf>,
+
+    /// How to handle sdists during resolution, e.g. `prefer-wheels`
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub sdist_resolution: Option<SDistResolution>,
+
+    /// Allow pre-releases to be selected during resolution
+    pub pre: Option<bool>,
+
+    /// Never access the network, only use what is in the cache
+    pub offline: Option<bool>,
+
+    /// Warn about requested packages whose name looks like the name of a popular package
+    pub typosquatting_check: Option<bool>,
+
+    /// The maximum number of requests that are performed at the same time
+    pub max_connections: Option<NonZeroUsize>,
+
+    /// The maximum number of requests to a single host that are performed at the same time
+    pub max_connections_per_host: Option<NonZeroUsize>,
+
+    /// How wheels are built from source
+    #[serde(default)]
+    pub build: BuildConfig,
+
+    /// How the index is verified with TUF metadata
+    #[serde(default)]
+    pub tuf: TufConfig,
+
+    /// Which packages can be selected during resolution
+    #[serde(default)]
+    pub policy: PolicyConfig,
+
+    /// How the repositories of git dependencies are accessed
+    #[serde(default)]
+    pub git: GitConfig,
+}
+
+/// The `[build]` table of a [`Config`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildConfig {
+    /// The config settings that are passed to the build backend
+    pub config_settings: Option<IndexMap<String, String>>,
+
+    /// Do not inherit the environment variables of the current process in build environments
+    pub clean_env: Option<bool>,
+
+    /// Keep the environments of failed builds for inspection
+    pub save_on_failure: Option<bool>,
+}
+
+/// The `[tuf]` table of a [`Config`], see [`crate::index::TufRepository`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TufConfig {
+    /// The trusted `root.json` of the repository, relative paths are relative to the
+    /// configuration file they are specified in
+    pub root: Option<PathBuf>,
+
+    /// The url of the directory that contains the TUF metadata of the repository
+    pub metadata_url: Option<Url>,
+
+    /// The urls that the target paths in the metadata are relative to
+    pub target_urls: Option<Vec<Url>>,
+}
+
+/// The `[policy]` table of a [`Config`], see [`crate::resolve::policy`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// If specified, only the packages and versions that match one of these rules can be selected
+    pub allow: Option<Vec<PackageRule>>,
+
+    /// The packages and versions that can never be selected
+    pub deny: Option<Vec<PackageRule>>,
+
+    /// If specified, packages whose license is not one of these violate the license policy
+    pub allow_licenses: Option<Vec<String>>,
+
+    /// The licenses that violate the license policy
+    pub deny_licenses: Option<Vec<String>>,
+
+    /// Fail installations of packages that violate the license policy instead of only warning
+    pub enforce_licenses: Option<bool>,
+}
+
+impl PolicyConfig {
+    /// Returns the policy that blocks the packages according to this configuration.
+    pub fn package_rules(&self) -> PackageRules {
+        let rules = self
+            .allow
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(PackageRules::new(), PackageRules::with_allowed);
+        self.deny
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(rules, PackageRules::with_denied)
+    }
+
+    /// Returns the policy that the licenses of packages are checked against according to this
+    /// configuration.
+    pub fn license_policy(&self) -> LicensePolicy {
+        let policy = self
+            .allow_licenses
+            .iter()
+            .flatten()
+            .fold(LicensePolicy::new(), |policy, license| {
+                policy.with_allowed(license)
+            });
+        self.deny_licenses
+            .iter()
+            .flatten()
+            .fold(policy, |policy, license| policy.with_denied(license))
+            .with_enforced(self.enforce_licenses.unwrap_or(false))
+    }
+}
+
+/// The `[git]` table of a [`Config`], see [`crate::index::GitAuth`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GitConfig {
+    /// The private key that is used to access repositories over ssh, relative paths are relative
+    /// to the configuration file they are specified in
+    pub ssh_key: Option<PathBuf>,
+
+    /// Use the keys of the ssh-agent
+    pub ssh_agent: Option<bool>,
+}
+
+/// An error that can occur when loading the configuration.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ConfigError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    ParseError(PathBuf, #[source] toml::de::Error),
+
+    #[error("invalid value '{1}' for the environment variable {0}")]
+    InvalidEnvVar(String, String),
+}
+
+/// The parts of a `pyproject.toml` file that contain the configuration.
+#[derive(Debug, Default, Deserialize)]
+struct PyProject {
+    #[serde(default)]
+    tool: Tool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Tool {
+    rip: Option<toml::Table>,
+}
+
+impl Config {
+    /// Reads the configuration from a `rip.toml` file or, if the file is called `pyproject.toml`,
+    /// from its `[tool.rip]` table.
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| ConfigError::IoError(path.to_path_buf(), e))?;
+        let config = if path
+            .file_name()
+            .is_some_and(|name| name == "pyproject.toml")
+        {
+            let mut rip = toml::from_str::<PyProject>(&contents)
+                .map_err(|e| ConfigError::ParseError(path.to_path_buf(), e))?
+                .tool
+                .rip
+                .unwrap_or_default();
+            // The members of a workspace are not a setting, see `crate::types::Workspace`
+            rip.remove("workspace");
+            Config::deserialize(toml::Value::Table(rip))
+                .map_err(|e| ConfigError::ParseError(path.to_path_buf(), e))?
+        } else {
+            toml::from_str::<Config>(&contents)
+                .map_err(|e| ConfigError::ParseError(path.to_path_buf(), e))?
+        };
+
+        // Paths are relative to the directory of the configuration file
+        let base = path.parent().unwrap_or(Path::new("."));
+        Ok(Self {
+            find_links: config
+                .find_links
+                .map(|paths| paths.into_iter().map(|path| base.join(path)).collect()),
+            cache_dir: config.cache_dir.map(|path| base.join(path)),
+            tuf: TufConfig {
+                root: config.tuf.root.map(|path| base.join(path)),
+                ..config.tuf
+            },
+            git: GitConfig {
+                ssh_key: config.git.ssh_key.map(|path| base.join(path)),
+                ..config.git
+            },
+            ..config
+        })
+    }
+
+    /// Reads the configuration from the `RIP_` environment variables in `vars`:
+    ///
+    /// * `RIP_INDEX_URL`
+    /// * `RIP_FIND_LINKS`, multiple directories are separated like in `PATH`
+    /// * `RIP_NO_INDEX`
+    /// * `RIP_CACHE_DIR`
+    /// * `RIP_SDIST_RESOLUTION`
+    /// * `RIP_PRE`
+    /// * `RIP_OFFLINE`
+    /// * `RIP_TYPOSQUATTING_CHECK`
+    /// * `RIP_MAX_CONNECTIONS`
+    /// * `RIP_MAX_CONNECTIONS_PER_HOST`
+    /// * `RIP_GIT_SSH_KEY`
+    /// * `RIP_GIT_SSH_AGENT`
+    /// * `RIP_ENFORCE_LICENSES`
+    pub fn from_env_vars<K: AsRef<str>, V: AsRef<str>>(
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        for (key, value) in vars {
+            let (key, value) = (key.as_ref(), value.as_ref());
+            let invalid = || ConfigError::InvalidEnvVar(key.to_string(), value.to_string());
+            match key {
+                "RIP_INDEX_URL" => {
+                    config.index_url = Some(Url::parse(value).map_err(|_| invalid())?)
+                }
+                "RIP_FIND_LINKS" => {
+                    config.find_links = Some(std::env::split_paths(value).collect());
+                }
+                "RIP_NO_INDEX" => config.no_index = Some(parse_bool(value).ok_or_else(invalid)?),
+                "RIP_CACHE_DIR" => config.cache_dir = Some(PathBuf::from(value)),
+                "RIP_SDIST_RESOLUTION" => {
+                    config.sdist_resolution =
+                        Some(SDistResolution::from_str(value).map_err(|_| invalid())?);
+                }
+                "RIP_PRE" => config.pre = Some(parse_bool(value).ok_or_else(invalid)?),
+                "RIP_OFFLINE" => config.offline = Some(parse_bool(value).ok_or_else(invalid)?),
+                "RIP_TYPOSQUATTING_CHECK" => {
+                    config.typosquatting_check = Some(parse_bool(value).ok_or_else(invalid)?);
+                }
+                "RIP_MAX_CONNECTIONS" => {
+                    config.max_connections = Some(value.parse().map_err(|_| invalid())?);
+                }
+                "RIP_MAX_CONNECTIONS_PER_HOST" => {
+                    config.max_connections_per_host = Some(value.parse().map_err(|_| invalid())?);
+                }
+                "RIP_GIT_SSH_KEY" => config.git.ssh_key = Some(PathBuf::from(value)),
+                "RIP_GIT_SSH_AGENT" => {
+                    config.git.ssh_agent = Some(parse_bool(value).ok_or_else(invalid)?);
+                }
+                "RIP_ENFORCE_LICENSES" => {
+                    config.policy.enforce_licenses = Some(parse_bool(value).ok_or_else(invalid)?);
+                }
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
+    /// Merges the settings of `other` into this configuration. The settings of `other` take
+    /// precedence.
+    pub fn merge(self, other: Config) -> Config {
+        Config {
+            index_url: other.index_url.or(self.index_url),
+            find_links: other.find_links.or(self.find_links),
+            no_index: other.no_index.or(self.no_index),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            sdist_resolution: other.sdist_resolution.or(self.sdist_resolution),
+            pre: other.pre.or(self.pre),
+            offline: other.offline.or(self.offline),
+            typosquatting_check: other.typosquatting_check.or(self.typosquatting_check),
+            max_connections: other.max_connections.or(self.max_connections),
+            max_connections_per_host: other
+                .max_connections_per_host
+                .or(self.max_connections_per_host),
+            build: BuildConfig {
+                config_settings: other.build.config_settings.or(self.build.config_settings),
+                clean_env: other.build.clean_env.or(self.build.clean_env),
+                save_on_failure: other.build.save_on_failure.or(self.build.save_on_failure),
+            },
+            tuf: TufConfig {
+                root: other.tuf.root.or(self.tuf.root),
+                metadata_url: other.tuf.metadata_url.or(self.tuf.metadata_url),
+                target_urls: other.tuf.target_urls.or(self.tuf.target_urls),
+            },
+            policy: PolicyConfig {
+                allow: other.policy.allow.or(self.policy.allow),
+                deny: other.policy.deny.or(self.policy.deny),
+                allow_licenses: other.policy.allow_licenses.or(self.policy.allow_licenses),
+                deny_licenses: other.policy.deny_licenses.or(self.policy.deny_licenses),
+                enforce_licenses: other
+                    .policy
+                    .enforce_licenses
+                    .or(self.policy.enforce_licenses),
+            },
+            git: GitConfig {
+                ssh_key: other.git.ssh_key.or(self.git.ssh_key),
+                ssh_agent: other.git.ssh_agent.or(self.git.ssh_agent),
+            },
+        }
+    }
+}
+
+/// Parses the value of a boolean environment variable.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the location of the global configuration file.
+pub fn global_config_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("PROGRAMDATA").map(|program_data| {
+            PathBuf::from(program_data)
+                .join("rip")
+                .join(CONFIG_FILE_NAME)
+        })
+    } else {
+        Some(PathBuf::from("/etc/rip").join(CONFIG_FILE_NAME))
+    }
+}
+
+/// Returns the location of the configuration file of the current user.
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join("rip").join(CONFIG_FILE_NAME))
+}
+
+/// Returns the configuration file of the project in `dir` or in one of its parents: a `rip.toml`
+/// file, or a `pyproject.toml` file with a `[tool.rip]` table.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| {
+        let rip_toml = dir.join(CONFIG_FILE_NAME);
+        if rip_toml.is_file() {
+            return Some(rip_toml);
+        }
+        let pyproject = dir.join("pyproject.toml");
+        let has_rip_table = fs::read_to_string(&pyproject)
+            .ok()
+            .and_then(|contents| toml::from_str::<toml::Table>(&contents).ok())
+            .is_some_and(|table| table.get("tool").and_then(|tool| tool.get("rip")).is_some());
+        has_rip_table.then_some(pyproject)
+    })
+}
+
+/// Loads the configuration from all layers, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    global_config: Option<PathBuf>,
+    user_config: Option<PathBuf>,
+    project_dir: Option<PathBuf>,
+    env_vars: Option<Vec<(String, String)>>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    /// Constructs a loader that reads the global and user configuration files from their default
+    /// locations and the environment variables of the current process. No project configuration
+    /// is read unless a project directory is set with [`Self::with_project_dir`].
+    pub fn new() -> Self {
+        Self {
+            global_config: global_config_path(),
+            user_config: user_config_path(),
+            project_dir: None,
+            env_vars: None,
+        }
+    }
+
+    /// Sets the location of the global configuration file, `None` skips the global layer.
+    pub fn with_global_config(mut self, path: Option<PathBuf>) -> Self {
+        self.global_config = path;
+        self
+    }
+
+    /// Sets the location of the user configuration file, `None` skips the user layer.
+    pub fn with_user_config(mut self, path: Option<PathBuf>) -> Self {
+        self.user_config = path;
+        self
+    }
+
+    /// Reads the project configuration from `dir` or one of its parents.
+    pub fn with_project_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.project_dir = Some(dir.into());
+        self
+    }
+
+    /// Reads the environment variables from `vars` instead of from the current process.
+    pub fn with_env_vars<K: Into<String>, V: Into<String>>(
+        mut self,
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.env_vars = Some(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Returns the configuration files that exist, from the lowest to the highest precedence.
+    pub fn config_files(&self) -> Vec<PathBuf> {
+        [
+            self.global_config.clone(),
+            self.user_config.clone(),
+            self.project_dir.as_deref().and_then(find_project_config),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|path| path.is_file())
+        .collect()
+    }
+
+    /// Reads and merges all layers.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        for path in self.config_files() {
+            tracing::debug!("reading configuration from {}", path.display());
+            config = config.merge(Config::from_path(&path)?);
+        }
+
+        let env = match &self.env_vars {
+            Some(vars) => Config::from_env_vars(vars.iter().map(|(k, v)| (k, v)))?,
+            None => Config::from_env_vars(std::env::vars_os().filter_map(|(key, value)| {
+                Some((key.into_string().ok()?, value.into_string().ok()?))
+            }))?,
+        };
+        Ok(config.merge(env))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::resolve::policy::{PackagePolicy, PolicyDecision};
+
+    #[test]
+    fn test_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            r#"
+index-url = "https://example.com/simple/"
+find-links = ["wheelhouse"]
+sdist-resolution = "prefer-wheels"
+
+[build]
+config-settings = { "--build-option" = "--quiet" }
+
+[tuf]
+root = "keys/root.json"
+metadata-url = "https://example.com/metadata/"
+
+[git]
+ssh-key = "keys/id_ed25519"
+ssh-agent = false
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_path(&path).unwrap();
+        assert_eq!(
+            config.index_url,
+            Some(Url::parse("https://example.com/simple/").unwrap())
+        );
+        assert_eq!(config.find_links, Some(vec![dir.path().join("wheelhouse")]));
+        assert_eq!(config.sdist_resolution, Some(SDistResolution::PreferWheels));
+        assert_eq!(
+            config.build.config_settings,
+            Some(IndexMap::from([(
+                String::from("--build-option"),
+                String::from("--quiet")
+            )]))
+        );
+
+        assert_eq!(config.tuf.root, Some(dir.path().join("keys/root.json")));
+        assert_eq!(
+            config.tuf.metadata_url,
+            Some(Url::parse("https://example.com/metadata/").unwrap())
+        );
+        assert_eq!(config.git.ssh_key, Some(dir.path().join("keys/id_ed25519")));
+        assert_eq!(config.git.ssh_agent, Some(false));
+
+        // Unknown settings are rejected
+        fs::write(&path, "index = \"https://example.com\"").unwrap();
+        assert!(matches!(
+            Config::from_path(&path),
+            Err(ConfigError::ParseError(..))
+        ));
+    }
+
+    #[test]
+    fn test_pyproject() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join("pyproject.toml"),
+            "[project]\nname = \"demo\"\n\n[tool.rip]\npre = true\n\n[tool.rip.workspace]\nmembers = []\n",
+        )
+        .unwrap();
+
+        let path = find_project_config(&project.join("src")).unwrap();
+        assert_eq!(path, project.join("pyproject.toml"));
+        assert_eq!(Config::from_path(&path).unwrap().pre, Some(true));
+
+        // A rip.toml takes precedence over the pyproject.toml
+        fs::write(project.join(CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(
+            find_project_config(&project).unwrap(),
+            project.join(CONFIG_FILE_NAME)
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            r#"
+[policy]
+deny-licenses = ["AGPL-3.0-only"]
+enforce-licenses = true
+
+[[policy.deny]]
+name = "ctx"
+version = ">=0.2.2,<0.2.7"
+reason = "compromised releases"
+
+[[policy.deny]]
+name = "Banned_Package"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_path(&path).unwrap();
+        assert_eq!(config.policy.allow, None);
+        let rules = config.policy.package_rules();
+        assert_eq!(
+            rules.check(&"ctx".parse().unwrap(), Some(&"0.2.6".parse().unwrap())),
+            PolicyDecision::Deny(String::from(
+                "it is denied by the package policy: compromised releases"
+            ))
+        );
+        assert_eq!(
+            rules.check(&"ctx".parse().unwrap(), Some(&"0.2.1".parse().unwrap())),
+            PolicyDecision::Allow
+        );
+        assert!(matches!(
+            rules.check(&"banned-package".parse().unwrap(), None),
+            PolicyDecision::Deny(_)
+        ));
+
+        let licenses = config.policy.license_policy();
+        assert!(licenses.is_enforced());
+        assert!(licenses.check_license(Some("AGPL-3.0-only")).is_some());
+        assert_eq!(licenses.check_license(Some("MIT")), None);
+    }
+
+    #[test]
+    fn test_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let global = dir.path().join("global.toml");
+        fs::write(
+            &global,
+            "index-url = \"https://global.example.com/simple/\"\npre = true\nmax-connections = 64\n",
+        )
+        .unwrap();
+        let user = dir.path().join("user.toml");
+        fs::write(&user, "index-url = \"https://user.example.com/simple/\"\n").unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(
+            project.join(CONFIG_FILE_NAME),
+            "sdist-resolution = \"only-wheels\"\n",
+        )
+        .unwrap();
+
+        let loader = ConfigLoader::new()
+            .with_global_config(Some(global))
+            .with_user_config(Some(user))
+            .with_project_dir(&project)
+            .with_env_vars([
+                ("RIP_SDIST_RESOLUTION", "only-sdists"),
+                ("RIP_NO_INDEX", "1"),
+                ("RIP_MAX_CONNECTIONS_PER_HOST", "8"),
+            ]);
+        let config = loader.load().unwrap();
+        assert_eq!(
+            config.index_url,
+            Some(Url::parse("https://user.example.com/simple/").unwrap())
+        );
+        assert_eq!(config.pre, Some(true));
+        assert_eq!(config.sdist_resolution, Some(SDistResolution::OnlySDists));
+        assert_eq!(config.no_index, Some(true));
+        assert_eq!(config.max_connections, NonZeroUsize::new(64));
+        assert_eq!(config.max_connections_per_host, NonZeroUsize::new(8));
+
+        // Invalid environment variables are reported
+        let loader = loader.with_env_vars([("RIP_PRE", "maybe")]);
+        assert!(matches!(loader.load(), Err(ConfigError::InvalidEnvVar(..))));
+    }
+}
@@ -0,0 +1,75 @@
+//! Skips the files of a project that can never be installed in a specific environment.
+//!
+//! The pages of projects that publish wheels for many platforms and interpreters list tens of
+//! thousands of files. When a [`crate::index::PackageDb`] is only used for a single environment
+//! most of them are irrelevant, an [`ArtifactFilter`] drops those while the page is parsed, before
+//! an [`ArtifactInfo`] is created for them, see [`crate::index::PackageDb::with_artifact_filter`].
+
+use crate::artifacts::ArtifactRegistry;
+use crate::python_env::WheelTags;
+use crate::types::{ArtifactInfo, ArtifactName, Version, VersionSpecifiers};
+use std::sync::Arc;
+
+/// Determines which files of a project are kept when the page of the project is parsed. Files are
+/// only skipped when they are known to be incompatible, sdists are never skipped based on tags.
+#[derive(Debug, Clone, Default)]
+pub struct ArtifactFilter {
+    wheel_tags: Option<Arc<WheelTags>>,
+    python_version: Option<Version>,
+}
+
+impl ArtifactFilter {
+    /// Constructs a filter that keeps every file.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips wheels, and custom artifacts, that are not compatible with any of `wheel_tags`.
+    pub fn with_wheel_tags(mut self, wheel_tags: Arc<WheelTags>) -> Self {
+        self.wheel_tags = Some(wheel_tags);
+        self
+    }
+
+    /// Skips files whose `Requires-Python` does not include `python_version`.
+    pub fn with_python_version(mut self, python_version: Version) -> Self {
+        self.python_version = Some(python_version);
+        self
+    }
+
+    /// Returns true if this filter keeps every file.
+    pub fn is_empty(&self) -> bool {
+        self.wheel_tags.is_none() && self.python_version.is_none()
+    }
+
+    /// Returns true if a file with the specified name can be installed given the wheel tags of
+    /// this filter.
+    pub fn accepts_filename(&self, filename: &ArtifactName, registry: &ArtifactRegistry) -> bool {
+        let Some(wheel_tags) = &self.wheel_tags else {
+            return true;
+        };
+        match filename {
+            ArtifactName::Wheel(wheel) => wheel
+                .all_tags_iter()
+                .any(|tag| wheel_tags.is_compatible(&tag)),
+            ArtifactName::Custom(name) => registry.is_compatible(name, Some(wheel_tags)),
+            ArtifactName::SDist(_) | ArtifactName::STree(_) => true,
+        }
+    }
+
+    /// Returns true if a file with the specified `Requires-Python` can be installed given the
+    /// python version of this filter.
+    pub fn accepts_requires_python(&self, requires_python: Option<&VersionSpecifiers>) -> bool {
+        match (&self.python_version, requires_python) {
+            (Some(python_version), Some(requires_python)) => {
+                requires_python.contains(python_version)
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns true if the artifact is kept by this filter.
+    pub fn accepts(&self, artifact: &ArtifactInfo, registry: &ArtifactRegistry) -> bool {
+        self.accepts_filename(&artifact.filename, registry)
+            && self.accepts_requires_python(artifact.requires_python.as_ref())
+    }
+}
@@ -2,9 +2,9 @@ use crate::artifacts::{SDist, STree, Wheel};
 use crate::index::package_database::DirectUrlArtifactResponse;
 use crate::resolve::PypiVersion;
 use crate::types::{
-    ArtifactFromBytes, ArtifactHashes, ArtifactInfo, ArtifactType, DirectUrlJson, DirectUrlSource,
-    DistInfoMetadata, NormalizedPackageName, PackageName, SDistFilename, SDistFormat,
-    STreeFilename, WheelCoreMetadata, Yanked,
+    ArtifactFromBytes, ArtifactHashes, ArtifactInfo, ArtifactType, DirectUrlHashes, DirectUrlJson,
+    DirectUrlSource, DistInfoMetadata, NormalizedPackageName, PackageName, SDistFilename,
+    SDistFormat, STreeFilename, WheelCoreMetadata, Yanked,
 };
 use crate::wheel_builder::{WheelBuildError, WheelBuilder};
 use indexmap::IndexMap;
@@ -128,6 +128,18 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
 
     let normalized_package_name = p.into();
 
+    // Local archives are recorded together with their hash, directories are recorded as such.
+    let direct_url_source = if path.is_file() {
+        let sha256 = rattler_digest::compute_file_digest::<Sha256>(&path).into_diagnostic()?;
+        DirectUrlSource::Archive {
+            hashes: Some(DirectUrlHashes {
+                sha256: format!("{sha256:x}"),
+            }),
+        }
+    } else {
+        DirectUrlSource::Dir { editable: None }
+    };
+
     let (metadata_bytes, metadata, artifact) = if path.is_file() && str_name.ends_with(".whl") {
         let wheel = Wheel::from_path(&path, &normalized_package_name)
             .map_err(|e| WheelBuildError::Error(format!("Could not build wheel: {}", e)))
@@ -174,6 +186,7 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
         requires_python: metadata.requires_python.clone(),
         dist_info_metadata: DistInfoMetadata::default(),
         yanked: Yanked::default(),
+        size: None,
     });
 
     let mut result = IndexMap::default();
@@ -181,7 +194,8 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
 
     let direct_url_json = DirectUrlJson {
         url: url.clone(),
-        source: DirectUrlSource::Dir { editable: None },
+        subdirectory: None,
+        source: direct_url_source,
     };
 
     Ok(DirectUrlArtifactResponse {
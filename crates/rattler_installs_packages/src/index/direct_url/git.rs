@@ -1,4 +1,7 @@
-use crate::index::git_interop::{git_clone, GitSource, ParsedUrl};
+use crate::index::git_auth::GitAuth;
+use crate::index::git_interop::{git_clone, GitSource, GitUrl, ParsedUrl};
+use crate::index::git_lfs::fetch_lfs_objects;
+use crate::index::http::Http;
 use crate::index::package_database::DirectUrlArtifactResponse;
 use crate::resolve::PypiVersion;
 use crate::types::{
@@ -9,29 +12,43 @@ use crate::wheel_builder::WheelBuilder;
 use indexmap::IndexMap;
 use miette::IntoDiagnostic;
 use rattler_digest::{compute_bytes_digest, Sha256};
-use std::str::FromStr;
+use std::path::Path;
 use std::sync::Arc;
 use url::Url;
 
 /// Get artifact by git reference
 pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
+    http: &Http,
     p: P,
     url: Url,
     wheel_builder: &WheelBuilder,
+    git_auth: &GitAuth,
+    cache_dir: &Path,
 ) -> miette::Result<DirectUrlArtifactResponse> {
     let normalized_package_name = p.into();
 
     let parsed_url = ParsedUrl::new(&url)?;
 
+    // The url that is recorded in `direct_url.json` should not contain the VCS prefix, the
+    // revision or the subdirectory.
+    let source_url = match &parsed_url.git_url {
+        GitUrl::Url(url) => url.clone(),
+        GitUrl::Path(path) => Url::from_file_path(path)
+            .map_err(|_| miette::miette!("could not convert '{}' to a file url", path.display()))?,
+    };
+
     let git_source = GitSource {
         url: parsed_url.git_url,
         rev: parsed_url.revision,
     };
 
-    let (mut location, git_rev) = git_clone(&git_source).into_diagnostic()?;
+    let (mut location, git_rev) = git_clone(&git_source, git_auth, cache_dir).into_diagnostic()?;
+
+    // Builds would otherwise package the pointers of the files that are stored with Git LFS
+    fetch_lfs_objects(http, &git_source.url, &location, git_auth).await?;
 
-    if let Some(subdirectory) = parsed_url.subdirectory {
-        location.push(&subdirectory);
+    if let Some(subdirectory) = &parsed_url.subdirectory {
+        location.push(subdirectory);
         if !location.exists() {
             return Err(miette::miette!(
                 "Requested subdirectory fragment {:?} can't be located at following url {:?}",
@@ -62,7 +79,8 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
     };
 
     let direct_url_json = DirectUrlJson {
-        url: Url::from_str(parsed_url.url.as_str()).expect("URL should be parseable"),
+        url: source_url,
+        subdirectory: parsed_url.subdirectory,
         source: DirectUrlSource::Vcs {
             vcs: DirectUrlVcs::Git,
             requested_revision: git_source.rev,
@@ -82,6 +100,7 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
         requires_python,
         dist_info_metadata,
         yanked,
+        size: None,
     });
 
     let mut result = IndexMap::default();
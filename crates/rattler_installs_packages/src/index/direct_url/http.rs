@@ -13,7 +13,7 @@ use indexmap::IndexMap;
 use miette::IntoDiagnostic;
 use pep440_rs::Version;
 use rattler_digest::Sha256;
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE};
 use reqwest::Method;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -26,12 +26,12 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
     url: Url,
     wheel_builder: &WheelBuilder,
 ) -> miette::Result<crate::index::package_database::DirectUrlArtifactResponse> {
-    let str_name = url.path();
     let url_hash = url.fragment().and_then(parse_hash);
 
     let normalized_package_name = p.into();
 
-    // Get the contents of the artifact
+    // Get the contents of the artifact. The response is cached by url and revalidated with its
+    // etag, so archives of branches are downloaded again when the branch changes.
     let artifact_bytes = http
         .request(
             url.clone(),
@@ -40,6 +40,7 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
             CacheMode::Default,
         )
         .await?;
+    let filename = artifact_filename(&url, artifact_bytes.headers())?;
 
     let mut bytes = artifact_bytes
         .into_body()
@@ -70,16 +71,16 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
             .expect("hash should be already calculated")
     );
 
-    let (metadata_bytes, metadata, artifact) = if str_name.ends_with(".whl") {
-        let wheel = Wheel::from_url_and_bytes(url.path(), &normalized_package_name, bytes)?;
+    let (metadata_bytes, metadata, artifact) = if filename.ends_with(".whl") {
+        let wheel = Wheel::from_url_and_bytes(&filename, &normalized_package_name, bytes)?;
 
         let (data_bytes, metadata) = wheel.metadata()?;
 
         (data_bytes, metadata, ArtifactType::Wheel(wheel))
     } else {
+        let format = SDistFormat::get_extension(&filename).into_diagnostic()?;
         let (wheel_metadata, sdist) =
-            get_sdist_from_bytes(&normalized_package_name, url.clone(), bytes, wheel_builder)
-                .await?;
+            get_sdist_from_bytes(&normalized_package_name, format, bytes, wheel_builder).await?;
 
         (
             wheel_metadata.0,
@@ -96,6 +97,7 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
         requires_python: metadata.requires_python.clone(),
         dist_info_metadata: DistInfoMetadata::default(),
         yanked: Yanked::default(),
+        size: None,
     });
 
     let mut result = IndexMap::default();
@@ -103,6 +105,7 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
 
     let direct_url_json = DirectUrlJson {
         url: url.clone(),
+        subdirectory: None,
         source: DirectUrlSource::Archive {
             hashes: Some(DirectUrlHashes { sha256: hash_str }),
         },
@@ -117,17 +120,64 @@ pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
     })
 }
 
+/// Returns the filename of the artifact at `url`. The archives of GitHub and GitLab are often
+/// served from urls without a file extension, like `https://codeload.github.com/org/repo/tar.gz/main`
+/// or the release assets of the GitHub API, their filename is then taken from the
+/// `Content-Disposition` or `Content-Type` headers of the response.
+fn artifact_filename(url: &Url, headers: &HeaderMap) -> miette::Result<String> {
+    let is_artifact =
+        |filename: &str| filename.ends_with(".whl") || SDistFormat::get_extension(filename).is_ok();
+
+    let last_segment = url
+        .path_segments()
+        .and_then(|segments| segments.last())
+        .unwrap_or_default();
+    if is_artifact(last_segment) {
+        return Ok(last_segment.to_string());
+    }
+
+    let disposition = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value.split(';').find_map(|parameter| {
+                let filename = parameter.trim().strip_prefix("filename=")?;
+                let filename = filename.trim_matches('"');
+                // Only the name of the file is used, never a path
+                filename.rsplit(['/', '\\']).next()
+            })
+        });
+    if let Some(filename) = disposition.filter(|filename| is_artifact(filename)) {
+        return Ok(filename.to_string());
+    }
+
+    let extension = match headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or_default().trim())
+    {
+        Some("application/zip" | "application/x-zip-compressed") => "zip",
+        Some("application/gzip" | "application/x-gzip" | "application/x-tar+gzip") => "tar.gz",
+        _ => miette::bail!("cannot determine the archive format of {url}"),
+    };
+    let stem = if last_segment.is_empty() {
+        "archive"
+    } else {
+        last_segment
+    };
+    Ok(format!("{stem}.{extension}"))
+}
+
 /// Return an sdist from http
 async fn get_sdist_from_bytes(
     normalized_package_name: &NormalizedPackageName,
-    url: Url,
+    format: SDistFormat,
     bytes: Box<dyn ReadAndSeek + Send>,
     wheel_builder: &WheelBuilder,
 ) -> miette::Result<((Vec<u8>, WheelCoreMetadata), SDist)> {
     // it's probably an sdist
     let distribution = PackageName::from(normalized_package_name.clone());
     let version = Version::from_str("0.0.0").expect("0.0.0 version should always be parseable");
-    let format = SDistFormat::get_extension(url.path()).into_diagnostic()?;
 
     let dummy_sdist_file_name = SDistFilename {
         distribution,
@@ -156,3 +206,60 @@ async fn get_sdist_from_bytes(
 
     Ok((wheel_metadata, sdist))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_artifact_filename() {
+        let filename = |url: &str, headers: &[(reqwest::header::HeaderName, &'static str)]| {
+            let mut header_map = HeaderMap::new();
+            for (name, value) in headers {
+                header_map.insert(name.clone(), HeaderValue::from_static(value));
+            }
+            artifact_filename(&Url::parse(url).unwrap(), &header_map).ok()
+        };
+
+        assert_eq!(
+            filename(
+                "https://github.com/Textualize/rich/archive/refs/tags/v13.7.0.zip",
+                &[]
+            )
+            .as_deref(),
+            Some("v13.7.0.zip")
+        );
+        assert_eq!(
+            filename(
+                "https://codeload.github.com/Textualize/rich/tar.gz/refs/tags/v13.7.0",
+                &[(
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=rich-13.7.0.tar.gz"
+                )]
+            )
+            .as_deref(),
+            Some("rich-13.7.0.tar.gz")
+        );
+        assert_eq!(
+            filename(
+                "https://api.github.com/repos/org/repo/releases/assets/1",
+                &[(
+                    CONTENT_DISPOSITION,
+                    "attachment; filename=\"../pkg-1.0-py3-none-any.whl\""
+                )]
+            )
+            .as_deref(),
+            Some("pkg-1.0-py3-none-any.whl")
+        );
+        assert_eq!(
+            filename(
+                "https://api.github.com/repos/org/repo/zipball/main",
+                &[(CONTENT_TYPE, "application/zip")]
+            )
+            .as_deref(),
+            Some("main.zip")
+        );
+        assert_eq!(filename("https://example.com/download", &[]), None);
+    }
+}
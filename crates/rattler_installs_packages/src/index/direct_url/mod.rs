@@ -1,16 +1,42 @@
+use crate::index::git_auth::GitAuth;
 use crate::index::http::Http;
 use crate::index::package_database::DirectUrlArtifactResponse;
 use crate::types::NormalizedPackageName;
 use crate::wheel_builder::WheelBuilder;
+use std::path::Path;
 use url::Url;
 
 pub(crate) mod file;
+#[cfg(feature = "git")]
 pub(crate) mod git;
 pub(crate) mod http;
 
+/// Stand-in for the git support when the crate is compiled without the `git` feature.
+#[cfg(not(feature = "git"))]
+pub(crate) mod git {
+    use super::*;
+
+    /// Always fails because git support is not available.
+    pub(crate) async fn get_artifacts_and_metadata<P: Into<NormalizedPackageName>>(
+        _http: &Http,
+        p: P,
+        url: Url,
+        _wheel_builder: &WheelBuilder,
+        _git_auth: &GitAuth,
+        _cache_dir: &Path,
+    ) -> miette::Result<DirectUrlArtifactResponse> {
+        Err(miette::miette!(
+            "cannot get '{}' from {url}, git support is not available because the `git` feature is disabled",
+            p.into()
+        ))
+    }
+}
+
 /// Get artifact directly from file, vcs, or url
 pub(crate) async fn fetch_artifact_and_metadata_by_direct_url<P: Into<NormalizedPackageName>>(
     http: &Http,
+    git_auth: &GitAuth,
+    cache_dir: &Path,
     p: P,
     url: Url,
     wheel_builder: &WheelBuilder,
@@ -24,9 +50,17 @@ pub(crate) async fn fetch_artifact_and_metadata_by_direct_url<P: Into<Normalized
         // This can be a Wheel or SDist artifact
         super::direct_url::http::get_artifacts_and_metadata(http, p.clone(), url, wheel_builder)
             .await
-    } else if url.scheme() == "git+https" || url.scheme() == "git+file" {
+    } else if matches!(url.scheme(), "git+https" | "git+ssh" | "git+file") {
         // This can be a STree artifact
-        super::direct_url::git::get_artifacts_and_metadata(p.clone(), url, wheel_builder).await
+        super::direct_url::git::get_artifacts_and_metadata(
+            http,
+            p.clone(),
+            url,
+            wheel_builder,
+            git_auth,
+            cache_dir,
+        )
+        .await
     } else {
         Err(miette::miette!(
             "Usage of insecure protocol or unsupported scheme {:?}",
@@ -86,6 +86,11 @@ impl FileStore {
         Ok(Self { base, tmp })
     }
 
+    /// Returns the directory in which the data is stored.
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
     /// Gets readable access to the data with the specified key. If no such entry exists the
     /// function `f` is called to populate the entry.
     pub async fn get_or_set<K: CacheKey, F>(&self, key: &K, f: F) -> io::Result<impl Read + Seek>
@@ -0,0 +1,108 @@
+//! Support for local directories of artifacts, similar to `pip --find-links`.
+use crate::artifacts::ArtifactRegistry;
+use crate::types::{ArtifactHashes, ArtifactInfo, ArtifactName, NormalizedPackageName};
+use fs_err as fs;
+use rattler_digest::Sha256;
+use std::path::Path;
+use url::Url;
+
+/// Returns the artifacts of the package `name` in the directory `dir`. Files that are not
+/// artifacts of the package are ignored. A directory that does not exist contains no artifacts.
+///
+/// The artifacts are hashed on the fly because the metadata cache is keyed by the hashes of
+/// artifacts.
+pub(crate) fn find_links_artifacts(
+    dir: &Path,
+    name: &NormalizedPackageName,
+    registry: &ArtifactRegistry,
+) -> std::io::Result<Vec<ArtifactInfo>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut result = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(filename) = ArtifactName::from_filename(file_name, None, name)
+            .ok()
+            .or_else(|| registry.parse_filename(file_name, name))
+        else {
+            continue;
+        };
+
+        let path = fs::canonicalize(&path)?;
+        let url = Url::from_file_path(&path).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("could not convert '{}' to a file url", path.display()),
+            )
+        })?;
+        let sha256 = rattler_digest::compute_file_digest::<Sha256>(&path)?;
+        let size = fs::metadata(&path)?.len();
+        result.push(ArtifactInfo {
+            filename,
+            url,
+            is_direct_url: false,
+            hashes: Some(ArtifactHashes {
+                sha256: Some(sha256),
+            }),
+            requires_python: None,
+            dist_info_metadata: Default::default(),
+            yanked: Default::default(),
+            size: Some(size),
+        });
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PackageName;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_find_links_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        for file_name in [
+            "flask-3.0.0-py3-none-any.whl",
+            "flask-2.3.3.tar.gz",
+            "werkzeug-3.0.1-py3-none-any.whl",
+            "README.md",
+        ] {
+            fs::write(dir.path().join(file_name), file_name).unwrap();
+        }
+
+        let name = NormalizedPackageName::from(PackageName::from_str("Flask").unwrap());
+        let mut artifacts = find_links_artifacts(dir.path(), &name, &ArtifactRegistry::default())
+            .unwrap()
+            .into_iter()
+            .map(|artifact| artifact.filename.to_string())
+            .collect::<Vec<_>>();
+        artifacts.sort();
+
+        assert_eq!(
+            artifacts,
+            ["flask-2.3.3.tar.gz", "flask-3.0.0-py3-none-any.whl"]
+        );
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        let name = NormalizedPackageName::from(PackageName::from_str("flask").unwrap());
+        let artifacts = find_links_artifacts(
+            Path::new("does-not-exist"),
+            &name,
+            &ArtifactRegistry::default(),
+        )
+        .unwrap();
+        assert!(artifacts.is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Authentication for the archives and release assets of repositories on GitHub and GitLab.
+
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use std::fmt::{Debug, Formatter};
+use url::Url;
+
+/// The hosts that serve the archives and release assets of repositories on GitHub.
+const GITHUB_HOSTS: [&str; 4] = [
+    "github.com",
+    "api.github.com",
+    "codeload.github.com",
+    "raw.githubusercontent.com",
+];
+
+/// The host of GitLab if no other host is configured.
+const DEFAULT_GITLAB_HOST: &str = "gitlab.com";
+
+/// Tokens that are sent with the requests to GitHub and GitLab. They allow direct requirements on
+/// the archives and release assets of private repositories, e.g.
+/// `pkg @ https://api.github.com/repos/org/repo/tarball/v1.0`.
+///
+/// The tokens are only sent over https, as a bearer token in the `Authorization` header, which is
+/// dropped when a request is redirected to another host.
+#[derive(Clone, Default)]
+pub struct ForgeTokens {
+    github: Option<String>,
+    gitlab: Vec<(String, String)>,
+}
+
+impl Debug for ForgeTokens {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForgeTokens")
+            .field("github", &self.github.as_ref().map(|_| "<redacted>"))
+            .field(
+                "gitlab",
+                &self.gitlab.iter().map(|(host, _)| host).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl ForgeTokens {
+    /// Reads the tokens from the environment variables that the command line tools of GitHub and
+    /// GitLab use: `GITHUB_TOKEN` or `GH_TOKEN` for GitHub, and `GITLAB_TOKEN` for the GitLab
+    /// instance at `GITLAB_HOST`, which defaults to `gitlab.com`.
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let mut tokens = Self::default();
+        if let Some(token) = var("GITHUB_TOKEN").or_else(|| var("GH_TOKEN")) {
+            tokens = tokens.with_github_token(token);
+        }
+        if let Some(token) = var("GITLAB_TOKEN") {
+            let host = var("GITLAB_HOST").unwrap_or_else(|| DEFAULT_GITLAB_HOST.to_string());
+            tokens = tokens.with_gitlab_token(host, token);
+        }
+        tokens
+    }
+
+    /// Sets the token that is sent with requests to GitHub.
+    pub fn with_github_token(self, token: impl Into<String>) -> Self {
+        Self {
+            github: Some(token.into()),
+            ..self
+        }
+    }
+
+    /// Adds a token that is sent with requests to the GitLab instance at `host`, e.g.
+    /// `gitlab.com`. The host may also be given as a url like `https://gitlab.example.com`.
+    pub fn with_gitlab_token(mut self, host: impl Into<String>, token: impl Into<String>) -> Self {
+        let host = host.into();
+        let host = Url::parse(&host)
+            .ok()
+            .and_then(|url| url.host_str().map(ToString::to_string))
+            .unwrap_or(host);
+        self.gitlab.push((host, token.into()));
+        self
+    }
+
+    /// Adds the headers that authenticate a request to `url`, and returns whether a token was
+    /// added. Requests for the release assets of the GitHub API also ask for the binary contents
+    /// of the asset.
+    pub(crate) fn authenticate(&self, url: &Url, headers: &mut HeaderMap) -> bool {
+        if url.scheme() != "https" || headers.contains_key(AUTHORIZATION) {
+            return false;
+        }
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+
+        // The release assets of the API are only served as binary with this header, otherwise
+        // their description is returned.
+        if host == "api.github.com"
+            && url.path().contains("/releases/assets/")
+            && !headers.contains_key(ACCEPT)
+        {
+            headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+        }
+
+        let token = if GITHUB_HOSTS.contains(&host) {
+            self.github.as_deref()
+        } else {
+            self.gitlab
+                .iter()
+                .find(|(gitlab_host, _)| gitlab_host == host)
+                .map(|(_, token)| token.as_str())
+        };
+
+        let Some(Ok(mut value)) =
+            token.map(|token| HeaderValue::from_str(&format!("Bearer {token}")))
+        else {
+            return false;
+        };
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_authenticate() {
+        let tokens = ForgeTokens::default()
+            .with_github_token("ghp_secret")
+            .with_gitlab_token("https://gitlab.example.com", "glpat_secret");
+        let authenticate = |url: &str| {
+            let mut headers = HeaderMap::new();
+            tokens
+                .authenticate(&Url::parse(url).unwrap(), &mut headers)
+                .then_some(headers)
+        };
+
+        let headers =
+            authenticate("https://api.github.com/repos/org/repo/releases/assets/1").unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer ghp_secret");
+        assert_eq!(headers[ACCEPT], "application/octet-stream");
+
+        let headers = authenticate("https://codeload.github.com/org/repo/tar.gz/main").unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer ghp_secret");
+        assert!(!headers.contains_key(ACCEPT));
+
+        let headers = authenticate(
+            "https://gitlab.example.com/api/v4/projects/1/repository/archive.tar.gz?sha=main",
+        )
+        .unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer glpat_secret");
+
+        assert!(authenticate("https://gitlab.com/org/repo/-/archive/main/repo-main.zip").is_none());
+        assert!(authenticate("https://pypi.org/simple/flask/").is_none());
+        assert!(authenticate("http://github.com/org/repo/archive/main.zip").is_none());
+        assert!(!format!("{tokens:?}").contains("secret"));
+    }
+}
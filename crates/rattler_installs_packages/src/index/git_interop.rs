@@ -1,14 +1,18 @@
-use std::collections::HashMap;
 use std::fmt;
 use std::{
     fmt::{Display, Formatter},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::Command,
     str::FromStr,
 };
 
+use crate::cache::CacheBucket;
+use crate::index::git_auth::GitAuth;
+use fs4::FileExt;
+use fs_err as fs;
 use fs_extra::dir::remove;
 use miette::IntoDiagnostic;
+use rattler_digest::{compute_bytes_digest, Sha256};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use url::Url;
@@ -45,11 +49,6 @@ pub enum GitRev {
 }
 
 impl GitRev {
-    /// Returns true if the revision is HEAD.
-    pub fn is_head(&self) -> bool {
-        matches!(self, Self::Head)
-    }
-
     pub fn get_commit(&self) -> String {
         match self {
             Self::Branch(branch) => branch.clone(),
@@ -110,21 +109,29 @@ pub struct ParsedUrl {
 
 impl ParsedUrl {
     pub fn new(url: &Url) -> miette::Result<Self> {
-        let url_str = url.as_str();
-
-        let revision = Self::extract_revision_from_git_url(url_str);
-        let subdirectory = Self::subdirectory_fragment(url_str);
-        let mut clean_url = Self::clean_url(url_str);
-
-        let git_url = if clean_url.contains("git+https") {
-            clean_url = clean_url.replace("git+https", "https");
-            let url = Url::from_str(&clean_url).into_diagnostic()?;
-            GitUrl::Url(url)
-        } else {
-            let path = url.path();
-            clean_url = path.replace(".git", "");
-            let path = PathBuf::from_str(&clean_url).into_diagnostic()?;
-            GitUrl::Path(path)
+        // The revision is separated from the path by an `@`, an `@` before the path is part of
+        // the user info of the url, e.g. in `git+ssh://git@github.com/org/repo.git@v1.0`.
+        let (path, revision) = match url.path().rsplit_once('@') {
+            Some((path, revision)) => (path, Some(revision.to_string())),
+            None => (url.path(), None),
+        };
+        let subdirectory = Self::subdirectory_fragment(url.as_str());
+
+        let (git_url, clean_url) = match url.scheme() {
+            "git+https" | "git+ssh" => {
+                let mut repository = url.clone();
+                repository.set_path(path);
+                repository.set_query(None);
+                repository.set_fragment(None);
+                let clean_url = repository.as_str().trim_start_matches("git+").to_string();
+                let url = Url::from_str(&clean_url).into_diagnostic()?;
+                (GitUrl::Url(url), clean_url)
+            }
+            _ => {
+                let clean_url = path.replace(".git", "");
+                let path = PathBuf::from_str(&clean_url).into_diagnostic()?;
+                (GitUrl::Path(path), clean_url)
+            }
         };
 
         Ok(ParsedUrl {
@@ -135,24 +142,6 @@ impl ParsedUrl {
         })
     }
 
-    /// Extract git revision if it's present
-    /// and return url without revision and the revision
-    fn extract_revision_from_git_url(url: &str) -> Option<String> {
-        // Split the string at '@' and take the second part
-        let rev = if url.contains('@') {
-            let split: Vec<&str> = url.split('@').collect();
-            if let Some((rev, _)) = split.split_last() {
-                Some(String::from(*rev))
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        rev
-    }
-
     fn subdirectory_fragment(url: &str) -> Option<String> {
         let subdirectory_fragment_re = Regex::new(r#"[#&]subdirectory=([^&]*)"#).unwrap();
 
@@ -163,19 +152,6 @@ impl ParsedUrl {
         }
         None
     }
-
-    fn clean_url(url: &str) -> String {
-        // Find the index of ".git" in the repository URL, or use the length if ".git" is not present
-        let repo_index = url.find(".git").map(|index| index + 4).unwrap_or_else(|| {
-            // .git is missing, remove @ if present
-            url.find('@').unwrap_or(url.len())
-        });
-
-        // Remove everything after ".git"
-        let clean_url = url.chars().take(repo_index).collect();
-
-        clean_url
-    }
 }
 
 /// Git source information.
@@ -205,9 +181,6 @@ pub enum SourceError {
     #[error("FileSystem error: '{0}'")]
     FileSystemError(fs_extra::error::Error),
 
-    #[error("Download could not be validated with checksum!")]
-    ValidationFailed,
-
     #[error("Failed to run git command: {0}")]
     GitError(String),
 
@@ -216,86 +189,119 @@ pub enum SourceError {
 }
 
 /// Create a `git` command with the given subcommand.
-fn git_command(sub_cmd: &str) -> Command {
+pub(crate) fn git_command(sub_cmd: &str) -> Command {
     let mut command = Command::new("git");
     command.arg(sub_cmd);
 
     command
 }
 
-fn git_version() -> miette::Result<(u8, u8)> {
-    let output = Command::new("git")
-        .arg("version")
-        .output()
-        .into_diagnostic()?;
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let regex = Regex::new(r"^git version (\d+)\.(\d+)(?:\s+\(.*\))?*").into_diagnostic()?;
-    let captures = regex.captures(&output_str);
-    if let Some(version) = captures {
-        let major = u8::from_str(&version[1]).into_diagnostic()?;
-        let minor = u8::from_str(&version[2]).into_diagnostic()?;
-        Ok((major, minor))
-    } else {
-        Err(miette::miette!(
-            help = "Can't parse git version.",
-            "{}",
-            output_str
-        ))
+/// Runs a `git` command and returns its stdout, or an error with its stderr if it failed.
+pub(crate) fn run(command: &mut Command) -> Result<String, SourceError> {
+    let output = command.output()?;
+    if !output.status.success() {
+        // Only the subcommand is reported, the arguments can contain credentials
+        let sub_cmd = command.get_args().next().unwrap_or_default();
+        return Err(SourceError::GitError(format!(
+            "`git {}` failed: {}",
+            sub_cmd.to_string_lossy(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
     }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-fn support_partial_clone() -> miette::Result<bool> {
-    let version = git_version()?;
-    if version >= (2, 17) {
-        return Ok(true);
-    }
-    Ok(false)
+/// Returns true if `rev` looks like a full commit hash.
+fn is_commit_hash(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
 }
 
-fn get_revision_sha(dest: &PathBuf, rev: Option<String>) -> Result<GitRev, SourceError> {
-    // Pass rev to pre-filter the list.
-    let rev = if let Some(rev) = rev {
-        rev
-    } else {
-        "HEAD".to_owned()
-    };
-
-    let output = Command::new("git")
-        .args(["show-ref", &rev])
-        .current_dir(dest)
-        .output()?;
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let refs: HashMap<_, _> = output_str
+/// Returns the revision that was fetched last, from the `FETCH_HEAD` file of the repository.
+fn read_fetch_head(db: &Path) -> Result<GitRev, SourceError> {
+    let fetch_head = fs::read_to_string(db.join("FETCH_HEAD"))?;
+    let (sha, description) = fetch_head
         .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| {
-            let mut parts = line.splitn(2, ' ');
-            let ref_sha = parts.next().unwrap().to_string();
-            let ref_name = parts.next().unwrap().to_string();
-            (ref_name, ref_sha)
-        })
-        .collect();
-
-    let branch_ref = format!("refs/remotes/origin/{}", rev);
-    let tag_ref = format!("refs/tags/{}", rev);
+        .next()
+        .and_then(|line| line.split_once('\t'))
+        .ok_or_else(|| SourceError::GitErrorStr("failed to read FETCH_HEAD"))?;
+
+    // The description is `branch 'main' of <url>`, `tag 'v1.0' of <url>` or only the url
+    let description = description.trim_start_matches("not-for-merge").trim_start();
+    let sha = sha.to_string();
+    Ok(if description.starts_with("branch '") {
+        GitRev::Branch(sha)
+    } else if description.starts_with("tag '") {
+        GitRev::Tag(sha)
+    } else {
+        GitRev::Commit(sha)
+    })
+}
 
-    let sha = refs.get(&branch_ref).cloned();
-    if let Some(sha) = sha {
-        return Ok(GitRev::Branch(sha));
+/// Fetches `rev` from `remote` into the bare repository `db` and returns the commit it resolves
+/// to. Only the commit itself is fetched, unless `rev` is an abbreviated commit hash, which can
+/// only be resolved with the full history.
+fn fetch_revision(
+    db: &Path,
+    remote: &str,
+    rev: &str,
+    auth: &GitAuth,
+    ssh: bool,
+) -> Result<GitRev, SourceError> {
+    // A commit never changes, so it doesn't have to be fetched again
+    if is_commit_hash(rev)
+        && run(git_command("cat-file")
+            .current_dir(db)
+            .args(["-e", &format!("{rev}^{{commit}}")]))
+        .is_ok()
+    {
+        return Ok(GitRev::Commit(rev.to_string()));
     }
 
-    let sha = refs.get(&tag_ref).cloned();
-    if let Some(sha) = sha {
-        return Ok(GitRev::Tag(sha));
-    }
+    let mut fetch = git_command("fetch");
+    fetch
+        .current_dir(db)
+        .args(["-q", "--depth=1", "--no-tags", remote, rev]);
+    let _askpass = auth.configure(&mut fetch, ssh)?;
+    let git_rev = match run(&mut fetch) {
+        Ok(_) => read_fetch_head(db)?,
+        Err(err) if rev.chars().all(|c| c.is_ascii_hexdigit()) => {
+            tracing::debug!("fetching {rev} failed, fetching the full history instead: {err}");
+            let mut fetch = git_command("fetch");
+            fetch.current_dir(db).args(["-q", "--tags"]);
+            if db.join("shallow").exists() {
+                fetch.arg("--unshallow");
+            }
+            fetch.args([remote, "+refs/heads/*:refs/remotes/origin/*"]);
+            let _askpass = auth.configure(&mut fetch, ssh)?;
+            run(&mut fetch)?;
+            let sha = run(git_command("rev-parse")
+                .current_dir(db)
+                .args(["--verify", &format!("{rev}^{{commit}}")]))?;
+            GitRev::Commit(sha)
+        }
+        Err(err) => return Err(err),
+    };
 
-    Ok(GitRev::Commit(rev.to_owned()))
+    // Keep a reference to the commit so it is not removed when the repository is cleaned up
+    let sha = git_rev.get_commit();
+    run(git_command("update-ref")
+        .current_dir(db)
+        .args([&format!("refs/rip/{sha}"), &sha]))?;
+    Ok(git_rev)
 }
 
-/// Fetch the git repository specified by the given source and place it in the cache directory.
-pub fn git_clone(source: &GitSource) -> Result<(PathBuf, GitRev), SourceError> {
+/// Fetch the git repository specified by the given source into the cache directory and returns a
+/// checkout of the requested revision. Remotes that are accessed over ssh, including those of
+/// submodules, are authenticated with `auth`.
+///
+/// Every repository is fetched into a bare repository in `git/db` of the cache directory, of
+/// which only the requested revisions are fetched without their history. Every commit is checked
+/// out once, as a worktree of the bare repository in `git/checkouts`, and reused afterwards.
+pub fn git_clone(
+    source: &GitSource,
+    auth: &GitAuth,
+    cache_dir: &Path,
+) -> Result<(PathBuf, GitRev), SourceError> {
     // test if git is available locally as we fetch the git from PATH,
     if !Command::new("git")
         .arg("--version")
@@ -308,128 +314,172 @@ pub fn git_clone(source: &GitSource) -> Result<(PathBuf, GitRev), SourceError> {
         ));
     }
 
-    let tmp_dir = tempfile::tempdir().unwrap().into_path();
-
-    let cache_dir = tmp_dir.join("rip-git-cache");
-    let recipe_dir = tmp_dir.join("rip-clone-dir");
-
-    let filename = match &source.url() {
-        GitUrl::Url(url) => (|| Some(url.path_segments()?.last()?.to_string()))()
-            .ok_or_else(|| SourceError::GitErrorStr("failed to get filename from url"))?,
-        GitUrl::Path(path) => recipe_dir
-            .join(path)
-            .canonicalize()?
-            .file_name()
-            .expect("unreachable, canonicalized paths shouldn't end with ..")
-            .to_string_lossy()
-            .to_string(),
-    };
-
-    let cache_name = PathBuf::from(filename);
-    let cache_path = cache_dir.join(cache_name);
-
-    // Initialize or clone the repository depending on the source's git_url.
-    match &source.url() {
-        GitUrl::Url(_) => {
-            // If the cache_path exists, initialize the repo and fetch the specified revision.
-            if !cache_path.exists() {
-                let mut command = git_command("clone");
-                if support_partial_clone().is_ok() {
-                    command.arg("--filter=blob:none");
-                } else {
-                    command.arg("--recursive");
-                }
-
-                command
-                    .arg(source.url().to_string().as_str())
-                    .arg(cache_path.as_os_str());
-
-                let output = command
-                    .output()
-                    .map_err(|_e| SourceError::GitErrorStr("Failed to execute clone command"))?;
-                if !output.status.success() {
-                    return Err(SourceError::GitErrorStr("Git clone failed for source"));
-                }
-            }
-        }
+    let (remote, ssh) = match &source.url() {
+        GitUrl::Url(url) => (url.to_string(), url.scheme() == "ssh"),
         GitUrl::Path(path) => {
-            if cache_path.exists() {
-                // Remove old cache so it can be overwritten.
-                if let Err(remove_error) = remove(&cache_path) {
-                    tracing::error!("Failed to remove old cache directory: {}", remove_error);
-                    return Err(SourceError::FileSystemError(remove_error));
-                }
-            }
             // git doesn't support UNC paths, hence we can't use std::fs::canonicalize
             let path = dunce::canonicalize(path).map_err(|e| {
                 tracing::error!("Path not found on system: {}", e);
                 SourceError::GitError(format!("{}: Path not found on system", e))
             })?;
-
-            let mut command = git_command("clone");
-
-            command
-                .arg("--recursive")
-                .arg(path)
-                .arg(cache_path.as_os_str());
-
-            let output = command
-                .output()
-                .map_err(|_| SourceError::ValidationFailed)?;
-
-            if !output.status.success() {
-                tracing::error!("Command failed: {:?}", command);
-                let err = String::from_utf8(output.stdout).unwrap();
-                let err_msg = format!(
-                    "failed to execute clone from file {:?} {:?}",
-                    output.status, err
-                );
-                return Err(SourceError::GitError(err_msg));
-            }
+            (path.to_string_lossy().to_string(), false)
         }
     };
 
-    let git_rev = get_revision_sha(&cache_path, source.rev.clone())?;
-
-    let mut checkout = git_command("checkout");
-
-    let cmd = if !git_rev.is_head() {
-        Some(checkout.args(["-q", git_rev.get_commit().as_str()]))
-    } else {
-        None
-    };
+    // The name of the repository, followed by a hash of its location to keep repositories with
+    // the same name apart
+    let name = remote
+        .trim_end_matches('/')
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    let hash = compute_bytes_digest::<Sha256>(remote.as_bytes());
+    let name = format!("{name}-{}", &format!("{hash:x}")[..16]);
+
+    let git_dir = CacheBucket::Git.path(cache_dir);
+    let db = git_dir.join("db").join(&name);
+    fs::create_dir_all(&db)?;
+
+    // Only one process at a time fetches into a repository and creates its checkouts
+    let lock = fs::File::create(git_dir.join("db").join(format!("{name}.lock")))?;
+    lock.file().lock_exclusive()?;
+
+    if !db.join("HEAD").exists() {
+        run(git_command("init").args(["-q", "--bare"]).arg(&db))?;
+    }
+    // The remote is used by `git lfs` to fetch the files that are stored with Git LFS
+    run(git_command("config")
+        .current_dir(&db)
+        .args(["remote.origin.url", &remote]))?;
+
+    let rev = source.rev.as_deref().unwrap_or("HEAD");
+    let git_rev = fetch_revision(&db, &remote, rev, auth, ssh)?;
+    let sha = git_rev.get_commit();
+
+    let checkouts = git_dir.join("checkouts").join(&name);
+    let checkout = checkouts.join(&sha);
+    let ready = checkouts.join(format!("{sha}.ok"));
+    if ready.exists() && checkout.exists() {
+        return Ok((checkout, git_rev));
+    }
 
-    if let Some(cmd) = cmd {
-        let output = cmd
-            .current_dir(&cache_path)
-            .output()
-            .map_err(|_| SourceError::GitErrorStr("git checkout failed"))?;
-
-        if !output.status.success() {
-            tracing::error!(
-                "Command failed: `git checkout \"{}\"`",
-                &git_rev.to_string()
-            );
-            return Err(SourceError::GitErrorStr(
-                "failed to checkout for a valid rev",
-            ));
-        }
+    // Clean up a checkout that was interrupted
+    if checkout.exists() {
+        remove(&checkout).map_err(SourceError::FileSystemError)?;
     }
+    run(git_command("worktree").current_dir(&db).args(["prune"]))?;
+    fs::create_dir_all(&checkouts)?;
+    // Files that are stored with Git LFS are fetched afterwards, see `git_lfs`
+    run(git_command("worktree")
+        .current_dir(&db)
+        .env("GIT_LFS_SKIP_SMUDGE", "1")
+        .args(["add", "-q", "--detach", "-f"])
+        .arg(&checkout)
+        .arg(&sha))?;
 
     // update submodules
-    if cache_path.join(".gitmodules").exists() {
+    let gitmodules = checkout.join(".gitmodules");
+    if gitmodules.exists() {
         let mut submodule = git_command("submodule");
-        let output = submodule
-            .current_dir(&cache_path)
-            .arg("update")
-            .args(["--init", "--recursive", "-q"])
-            .output()
-            .map_err(|_| SourceError::GitErrorStr("git submodule update failed"))?;
-
-        if !output.status.success() {
-            return Err(SourceError::GitErrorStr("failed to update git module"));
-        }
+        let contents = fs::read_to_string(&gitmodules)?;
+        let ssh = contents.contains("ssh://") || contents.contains("git@");
+        let _askpass = auth.configure(&mut submodule, ssh)?;
+        submodule
+            .current_dir(&checkout)
+            .env("GIT_LFS_SKIP_SMUDGE", "1")
+            .args(["update", "--init", "--recursive", "--depth=1", "-q"]);
+        run(&mut submodule)?;
+    }
+
+    fs::write(&ready, "")?;
+    Ok((checkout, git_rev))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parsed_url() {
+        let parsed = ParsedUrl::new(
+            &Url::parse("git+ssh://git@github.com/org/repo.git@v1.0#subdirectory=python").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.git_url,
+            GitUrl::Url(Url::parse("ssh://git@github.com/org/repo.git").unwrap())
+        );
+        assert_eq!(parsed.revision.as_deref(), Some("v1.0"));
+        assert_eq!(parsed.subdirectory.as_deref(), Some("python"));
+
+        let parsed =
+            ParsedUrl::new(&Url::parse("git+ssh://git@github.com/org/repo").unwrap()).unwrap();
+        assert_eq!(parsed.url, "ssh://git@github.com/org/repo");
+        assert_eq!(parsed.revision, None);
+
+        let parsed =
+            ParsedUrl::new(&Url::parse("git+https://github.com/org/repo.git@main").unwrap())
+                .unwrap();
+        assert_eq!(parsed.url, "https://github.com/org/repo.git");
+        assert_eq!(parsed.revision.as_deref(), Some("main"));
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        run(Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=rip", "-c", "user.email=rip@example.com"])
+            .args(args))
+        .unwrap()
     }
 
-    Ok((cache_path, git_rev))
+    #[test]
+    fn test_git_clone() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let cache_dir = dir.path().join("cache");
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q", "-b", "main"]);
+        fs::write(repo.join("version.txt"), "1").unwrap();
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "-q", "-m", "first"]);
+        git(&repo, &["tag", "v1"]);
+        let first = git(&repo, &["rev-parse", "HEAD"]);
+        fs::write(repo.join("version.txt"), "2").unwrap();
+        git(&repo, &["commit", "-q", "-am", "second"]);
+        let second = git(&repo, &["rev-parse", "HEAD"]);
+
+        let clone = |rev: Option<&str>| {
+            let source = GitSource {
+                url: GitUrl::Path(repo.clone()),
+                rev: rev.map(ToString::to_string),
+            };
+            git_clone(&source, &GitAuth::default(), &cache_dir).unwrap()
+        };
+
+        let (checkout, rev) = clone(Some("main"));
+        assert_eq!(rev, GitRev::Branch(second.clone()));
+        assert_eq!(
+            fs::read_to_string(checkout.join("version.txt")).unwrap(),
+            "2"
+        );
+
+        let (checkout, rev) = clone(Some("v1"));
+        assert_eq!(rev, GitRev::Tag(first.clone()));
+        assert_eq!(
+            fs::read_to_string(checkout.join("version.txt")).unwrap(),
+            "1"
+        );
+
+        // The history is not fetched, the abbreviated hash is resolved from the full history
+        let (abbreviated, rev) = clone(Some(&first[..8]));
+        assert_eq!(rev, GitRev::Commit(first.clone()));
+        assert_eq!(abbreviated, checkout);
+
+        // A commit is reused without access to the repository
+        fs::remove_dir_all(repo.join(".git")).unwrap();
+        let (cached, rev) = clone(Some(&second));
+        assert_eq!(rev, GitRev::Commit(second.clone()));
+        assert_eq!(fs::read_to_string(cached.join("version.txt")).unwrap(), "2");
+    }
 }
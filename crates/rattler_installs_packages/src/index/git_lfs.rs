@@ -0,0 +1,442 @@
+//! Fetching the files of git dependencies that are stored with [Git LFS](https://git-lfs.com).
+//!
+//! Repositories are checked out without running the LFS filters, so the files that are stored
+//! with LFS contain a small pointer to their contents instead. Building a wheel from such a
+//! checkout would package the pointers, so they are replaced by their contents before the build.
+//! The `git-lfs` binary is used if it is installed, otherwise the objects are copied from a local
+//! repository or downloaded through the LFS batch API of the server.
+
+use crate::index::git_auth::GitAuth;
+use crate::index::git_interop::{git_command, run, GitUrl};
+use crate::index::http::{CacheMode, Http};
+use fs_err as fs;
+use miette::IntoDiagnostic;
+use rattler_digest::{compute_bytes_digest, Sha256};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use reqwest::Method;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use url::Url;
+
+/// The first line of a pointer file.
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// Pointer files are never larger than this.
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// The media type of the requests and responses of the batch API.
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// A file in a checkout that contains a pointer to an LFS object instead of its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LfsPointer {
+    /// The location of the file
+    pub path: PathBuf,
+    /// The sha256 of the contents as hex
+    pub oid: String,
+    /// The size of the contents
+    pub size: u64,
+}
+
+/// Parses the contents of a pointer file into the oid and size of the object.
+fn parse_pointer(contents: &str) -> Option<(String, u64)> {
+    let mut lines = contents.lines();
+    if lines.next()? != POINTER_VERSION {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ')? {
+            ("oid", value) => oid = Some(value.strip_prefix("sha256:")?.to_string()),
+            ("size", value) => size = Some(value.parse().ok()?),
+            _ => {}
+        }
+    }
+    Some((oid?, size?))
+}
+
+/// Returns the files in `checkout` that contain a pointer to an LFS object. Nothing is returned
+/// if none of the `.gitattributes` files of the repository store files with LFS.
+pub(crate) fn find_pointers(checkout: &Path) -> miette::Result<Vec<LfsPointer>> {
+    let files = run(git_command("ls-files").current_dir(checkout).arg("-z")).into_diagnostic()?;
+    let files = files.split('\0').filter(|file| !file.is_empty());
+
+    let uses_lfs = files.clone().any(|file| {
+        Path::new(file).ends_with(".gitattributes")
+            && fs::read_to_string(checkout.join(file))
+                .is_ok_and(|attributes| attributes.contains("filter=lfs"))
+    });
+    if !uses_lfs {
+        return Ok(Vec::new());
+    }
+
+    let mut pointers = Vec::new();
+    for file in files {
+        let path = checkout.join(file);
+        if !fs::metadata(&path).is_ok_and(|metadata| metadata.len() < MAX_POINTER_SIZE) {
+            continue;
+        }
+        if let Some((oid, size)) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| parse_pointer(&contents))
+        {
+            pointers.push(LfsPointer { path, oid, size });
+        }
+    }
+    Ok(pointers)
+}
+
+/// Replaces the pointer file with the contents of its object, after verifying them.
+fn write_object(pointer: &LfsPointer, contents: &[u8]) -> miette::Result<()> {
+    let sha256 = format!("{:x}", compute_bytes_digest::<Sha256>(contents));
+    if sha256 != pointer.oid || contents.len() as u64 != pointer.size {
+        miette::bail!(
+            "the LFS object of {} does not match its pointer, expected sha256 {} but got {sha256}",
+            pointer.path.display(),
+            pointer.oid
+        );
+    }
+
+    // Replace the file atomically so an interrupted write leaves the pointer behind
+    let dir = pointer.path.parent().unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir).into_diagnostic()?;
+    std::io::Write::write_all(&mut file, contents).into_diagnostic()?;
+    let permissions = fs::metadata(&pointer.path).into_diagnostic()?.permissions();
+    std::fs::set_permissions(file.path(), permissions).into_diagnostic()?;
+    file.persist(&pointer.path).into_diagnostic()?;
+    Ok(())
+}
+
+/// Copies the objects from the LFS store of the local repository at `repository`.
+fn copy_from_local(repository: &Path, pointers: &[LfsPointer]) -> miette::Result<()> {
+    let git_dir = if repository.join(".git").is_dir() {
+        repository.join(".git")
+    } else {
+        repository.to_path_buf()
+    };
+    for pointer in pointers {
+        let object = git_dir
+            .join("lfs/objects")
+            .join(&pointer.oid[..2])
+            .join(&pointer.oid[2..4])
+            .join(&pointer.oid);
+        let contents = fs::read(&object).map_err(|_| {
+            miette::miette!(
+                "the LFS object of {} is not available in {}, run `git lfs fetch` in the repository",
+                pointer.path.display(),
+                repository.display()
+            )
+        })?;
+        write_object(pointer, &contents)?;
+    }
+    Ok(())
+}
+
+/// Returns true if the `git-lfs` binary is installed.
+fn has_git_lfs() -> bool {
+    Command::new("git")
+        .args(["lfs", "version"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    objects: Vec<BatchObject>,
+}
+
+#[derive(Deserialize)]
+struct BatchObject {
+    oid: String,
+    actions: Option<BatchActions>,
+    error: Option<BatchError>,
+}
+
+#[derive(Deserialize)]
+struct BatchActions {
+    download: Option<BatchAction>,
+}
+
+#[derive(Deserialize)]
+struct BatchAction {
+    href: Url,
+    #[serde(default)]
+    header: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct BatchError {
+    message: String,
+}
+
+/// Returns the url of the batch API of the repository at `remote`, e.g.
+/// `https://github.com/org/repo.git/info/lfs/objects/batch`.
+fn batch_url(remote: &Url) -> Url {
+    let mut url = remote.clone();
+    let path = url.path().trim_end_matches('/');
+    let path = if path.ends_with(".git") {
+        path.to_string()
+    } else {
+        format!("{path}.git")
+    };
+    url.set_path(&format!("{path}/info/lfs/objects/batch"));
+    url
+}
+
+/// Downloads the objects through the batch API of the server of the repository at `remote`.
+async fn download(http: &Http, remote: &Url, pointers: &[LfsPointer]) -> miette::Result<()> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_static(LFS_MEDIA_TYPE));
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(LFS_MEDIA_TYPE));
+    let objects = pointers
+        .iter()
+        .map(|pointer| serde_json::json!({ "oid": pointer.oid, "size": pointer.size }))
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({
+        "operation": "download",
+        "transfers": ["basic"],
+        "objects": objects,
+    });
+    let response = http
+        .post_json(batch_url(remote), headers, &body)
+        .await
+        .into_diagnostic()?;
+    let response: BatchResponse = serde_json::from_value(response).into_diagnostic()?;
+
+    for object in response.objects {
+        let action = match (
+            object.actions.and_then(|actions| actions.download),
+            object.error,
+        ) {
+            (_, Some(error)) => miette::bail!(
+                "failed to download the LFS object {} from {remote}: {}",
+                object.oid,
+                error.message
+            ),
+            (Some(action), None) => action,
+            (None, None) => continue,
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &action.header {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).into_diagnostic()?,
+                HeaderValue::from_str(value).into_diagnostic()?,
+            );
+        }
+        let response = http
+            .request(action.href, Method::GET, headers, CacheMode::NoStore)
+            .await
+            .into_diagnostic()?;
+        let mut contents = Vec::new();
+        response
+            .into_body()
+            .into_local()
+            .await
+            .into_diagnostic()?
+            .read_to_end(&mut contents)
+            .into_diagnostic()?;
+
+        for pointer in pointers.iter().filter(|pointer| pointer.oid == object.oid) {
+            write_object(pointer, &contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the pointers in the checkout of the repository at `url` by the contents of the files
+/// that are stored with LFS.
+pub(crate) async fn fetch_lfs_objects(
+    http: &Http,
+    url: &GitUrl,
+    checkout: &Path,
+    auth: &GitAuth,
+) -> miette::Result<()> {
+    let pointers = find_pointers(checkout)?;
+    if pointers.is_empty() {
+        return Ok(());
+    }
+    tracing::info!(
+        "fetching {} files that are stored with Git LFS from {url}",
+        pointers.len()
+    );
+
+    match url {
+        GitUrl::Path(repository) => copy_from_local(repository, &pointers),
+        GitUrl::Url(url) if has_git_lfs() => {
+            let mut pull = git_command("lfs");
+            pull.current_dir(checkout).args(["pull", "origin"]);
+            let _askpass = auth
+                .configure(&mut pull, url.scheme() == "ssh")
+                .into_diagnostic()?;
+            run(&mut pull).into_diagnostic()?;
+            Ok(())
+        }
+        GitUrl::Url(url) if url.scheme() == "https" => download(http, url, &pointers).await,
+        GitUrl::Url(url) => miette::bail!(
+            "{url} stores files with Git LFS, install `git-lfs` to fetch them over {}",
+            url.scheme()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index::file_store::FileStore;
+    use axum::body::Bytes;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use reqwest::Client;
+    use reqwest_middleware::ClientWithMiddleware;
+    use serde_json::{json, Value};
+    use std::future::IntoFuture;
+    use std::net::SocketAddr;
+
+    const CONTENTS: &[u8] = b"large binary data";
+
+    fn git(dir: &Path, args: &[&str]) {
+        run(Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=rip", "-c", "user.email=rip@example.com"])
+            .args(args))
+        .unwrap();
+    }
+
+    /// Creates a repository whose `data.bin` is stored with LFS, as it is checked out without the
+    /// LFS filters. Returns the pointer.
+    fn make_repository(dir: &Path) -> LfsPointer {
+        let oid = format!("{:x}", compute_bytes_digest::<Sha256>(CONTENTS));
+        fs::create_dir_all(dir).unwrap();
+        git(dir, &["init", "-q"]);
+        fs::write(
+            dir.join(".gitattributes"),
+            "*.bin filter=lfs diff=lfs merge=lfs -text\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("data.bin"),
+            format!(
+                "{POINTER_VERSION}\noid sha256:{oid}\nsize {}\n",
+                CONTENTS.len()
+            ),
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "not stored with LFS\n").unwrap();
+        git(dir, &["add", "."]);
+        git(dir, &["commit", "-q", "-m", "lfs"]);
+        LfsPointer {
+            path: dir.join("data.bin"),
+            oid,
+            size: CONTENTS.len() as u64,
+        }
+    }
+
+    #[test]
+    fn test_find_pointers() {
+        let dir = tempfile::tempdir().unwrap();
+        let pointer = make_repository(dir.path());
+        assert_eq!(find_pointers(dir.path()).unwrap(), vec![pointer]);
+
+        // Without LFS attributes pointers are not looked for
+        fs::remove_file(dir.path().join(".gitattributes")).unwrap();
+        git(dir.path(), &["commit", "-q", "-am", "no lfs"]);
+        assert!(find_pointers(dir.path()).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_from_local_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = dir.path().join("repository");
+        let pointer = make_repository(&repository);
+        let object = repository
+            .join(".git/lfs/objects")
+            .join(&pointer.oid[..2])
+            .join(&pointer.oid[2..4]);
+        fs::create_dir_all(&object).unwrap();
+        fs::write(object.join(&pointer.oid), CONTENTS).unwrap();
+
+        let checkout = dir.path().join("checkout");
+        git(
+            dir.path(),
+            &["clone", "-q", repository.to_str().unwrap(), "checkout"],
+        );
+        let http = Http::new(
+            ClientWithMiddleware::from(Client::new()),
+            FileStore::new(&dir.path().join("http")).unwrap(),
+        );
+        fetch_lfs_objects(
+            &http,
+            &GitUrl::Path(repository),
+            &checkout,
+            &GitAuth::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(fs::read(checkout.join("data.bin")).unwrap(), CONTENTS);
+        assert!(find_pointers(&checkout).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download() {
+        let listener = tokio::net::TcpListener::bind(SocketAddr::new([127, 0, 0, 1].into(), 0))
+            .await
+            .unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route(
+                "/org/repo.git/info/lfs/objects/batch",
+                post(move |Json(body): Json<Value>| async move {
+                    let objects = body["objects"]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|object| {
+                            json!({
+                                "oid": object["oid"],
+                                "size": object["size"],
+                                "actions": { "download": {
+                                    "href": format!("http://{address}/objects/{}", object["oid"].as_str().unwrap()),
+                                    "header": { "X-Token": "secret" }
+                                } }
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    Json(json!({ "transfer": "basic", "objects": objects }))
+                }),
+            )
+            .route(
+                "/objects/:oid",
+                get(|headers: axum::http::HeaderMap| async move {
+                    assert_eq!(headers["x-token"], "secret");
+                    Bytes::from_static(CONTENTS)
+                }),
+            );
+        tokio::spawn(axum::serve(listener, router).into_future());
+
+        let dir = tempfile::tempdir().unwrap();
+        let pointer = make_repository(&dir.path().join("checkout"));
+        let http = Http::new(
+            ClientWithMiddleware::from(Client::new()),
+            FileStore::new(&dir.path().join("http")).unwrap(),
+        );
+        let remote = Url::parse(&format!("http://{address}/org/repo")).unwrap();
+        download(&http, &remote, &[pointer.clone()]).await.unwrap();
+        assert_eq!(fs::read(&pointer.path).unwrap(), CONTENTS);
+    }
+
+    #[test]
+    fn test_batch_url() {
+        assert_eq!(
+            batch_url(&Url::parse("https://github.com/org/repo").unwrap()).as_str(),
+            "https://github.com/org/repo.git/info/lfs/objects/batch"
+        );
+        assert_eq!(
+            batch_url(&Url::parse("https://gitlab.com/org/repo.git").unwrap()).as_str(),
+            "https://gitlab.com/org/repo.git/info/lfs/objects/batch"
+        );
+    }
+}
@@ -2,6 +2,8 @@
 use std::str::FromStr;
 use std::{borrow::Borrow, default::Default};
 
+use crate::artifacts::ArtifactRegistry;
+use crate::index::ArtifactFilter;
 use crate::{types::ArtifactHashes, types::ArtifactName, types::NormalizedPackageName};
 use miette::{miette, IntoDiagnostic};
 use pep440_rs::VersionSpecifiers;
@@ -28,6 +30,8 @@ fn into_artifact_info(
     base: &Url,
     normalized_package_name: &NormalizedPackageName,
     tag: &HTMLTag,
+    registry: &ArtifactRegistry,
+    filter: &ArtifactFilter,
 ) -> Option<ArtifactInfo> {
     let attributes = tag.attributes();
     // Get first href attribute to use as filename
@@ -36,12 +40,15 @@ fn into_artifact_info(
     // Join with base
     let url = base.join(href.as_ref()).ok()?;
     let filename = url.path_segments().and_then(|mut s| s.next_back());
-    let filename = filename
-        .map(|s| ArtifactName::from_filename(s, None, normalized_package_name))?
-        .ok()?;
+    let filename = filename.and_then(|s| {
+        ArtifactName::from_filename(s, None, normalized_package_name)
+            .ok()
+            .or_else(|| registry.parse_filename(s, normalized_package_name))
+    })?;
+    if !filter.accepts_filename(&filename, registry) {
+        return None;
+    }
 
-    // We found a valid link
-    let hash = url.fragment().and_then(parse_hash);
     let requires_python = attributes
         .get("data-requires-python")
         .flatten()
@@ -54,6 +61,12 @@ fn into_artifact_info(
         })
         .transpose()
         .ok()?;
+    if !filter.accepts_requires_python(requires_python.as_ref()) {
+        return None;
+    }
+
+    // We found a valid link
+    let hash = url.fragment().and_then(parse_hash);
 
     let metadata_attr = attributes
         .get("data-dist-info-metadata")
@@ -98,13 +111,36 @@ fn into_artifact_info(
         requires_python,
         dist_info_metadata,
         yanked,
+        size: None,
     })
 }
 
 /// Parses information regarding the different artifacts for a project
 pub fn parse_project_info_html(base: &Url, body: &str) -> miette::Result<ProjectInfo> {
-    let dom = tl::parse(body, tl::ParserOptions::default()).into_diagnostic()?;
-    let variants = dom.query_selector("a");
+    parse_project_info_html_with_registry(base, body, &ArtifactRegistry::default())
+}
+
+/// Parses information regarding the different artifacts for a project. Filenames that are not
+/// recognized as a wheel or sdist are passed to the kinds registered in the `registry`.
+pub fn parse_project_info_html_with_registry(
+    base: &Url,
+    body: &str,
+    registry: &ArtifactRegistry,
+) -> miette::Result<ProjectInfo> {
+    parse_project_info_html_with_filter(base, body, registry, &ArtifactFilter::default())
+}
+
+/// Parses information regarding the different artifacts for a project, skipping the files that
+/// are rejected by `filter` before any further information about them is parsed.
+///
+/// The page is processed one tag at a time instead of building a DOM of the whole document, the
+/// pages of some projects list tens of thousands of files.
+pub fn parse_project_info_html_with_filter(
+    base: &Url,
+    body: &str,
+    registry: &ArtifactRegistry,
+    filter: &ArtifactFilter,
+) -> miette::Result<ProjectInfo> {
     let mut project_info = ProjectInfo::default();
 
     // Find the package name from the URL
@@ -129,57 +165,128 @@ pub fn parse_project_info_html(base: &Url, body: &str) -> miette::Result<Project
         return Err(miette!("no package segments found in url: '{base}'"));
     };
 
+    // Collect the <a></a> tags, the repository version and the base url. A <base> tag applies to
+    // the whole document so the links are only resolved once all tags have been seen.
+    let mut repository_version = None;
+    let mut base_href = None;
+    let mut a_tags = Vec::new();
+    for (name, tag) in StartTags::new(body) {
+        if name.eq_ignore_ascii_case("a") {
+            a_tags.push(tag);
+        } else if name.eq_ignore_ascii_case("base") && base_href.is_none() {
+            base_href = Some(with_tag(tag, |tag| attribute(tag, "href")));
+        } else if name.eq_ignore_ascii_case("meta") && repository_version.is_none() {
+            repository_version = with_tag(tag, |tag| {
+                (attribute(tag, "name")? == "pypi:repository-version")
+                    .then(|| attribute(tag, "content"))
+                    .flatten()
+            });
+        }
+    }
+
     // Select repository version
-    project_info.meta.version = dom
-        .query_selector("meta[name=\"pypi:repository-version\"]")
-        // Take the first value
-        .and_then(|mut v| v.next())
-        // Get node access
-        .and_then(|v| v.get(dom.parser()))
-        // Require it to be a tag
-        .and_then(|v| v.as_tag())
-        // Get attributes, content specifically
-        .and_then(|v| v.attributes().get("content"))
-        // Get the version
-        .and_then(|v| v.map(|v| v.as_utf8_str().to_string()))
-        .unwrap_or_default();
-
-    // Select base url
-    let base = dom
-        .query_selector("base")
-        // Take the first value
-        .and_then(|mut v| v.next())
-        // Get node access
-        .and_then(|v| v.get(dom.parser()))
-        // Require it to be a tag
-        .and_then(|v| v.as_tag())
-        // Get attributes, href specifically
-        .and_then(|v| v.attributes().get("href"))
-        // Get the version
-        .and_then(|v| v.map(|v| v.as_utf8_str().to_string()))
-        // Parse the url
-        .and_then(|v| Url::parse(&v).ok())
-        // If we didn't find a base, use the one we were given
-        .unwrap_or_else(|| base.clone());
+    project_info.meta.version = repository_version.unwrap_or_default();
 
-    if let Some(variants) = variants {
-        // Filter for <a></a> tags
-        let a_tags = variants
-            .filter_map(|a| a.get(dom.parser()))
-            .filter_map(|h| h.as_tag());
+    // Select base url, if we didn't find a base, use the one we were given
+    let base = base_href
+        .flatten()
+        .and_then(|href| Url::parse(&href).ok())
+        .unwrap_or_else(|| base.clone());
 
-        // Parse and add <a></a> tags
-        for a in a_tags {
-            let artifact_info = into_artifact_info(&base, &normalized_package_name, a);
-            if let Some(artifact_info) = artifact_info {
-                project_info.files.push(artifact_info);
-            }
+    // Parse and add <a></a> tags
+    for a in a_tags {
+        let artifact_info = with_tag(a, |a| {
+            into_artifact_info(&base, &normalized_package_name, a, registry, filter)
+        });
+        if let Some(artifact_info) = artifact_info {
+            project_info.files.push(artifact_info);
         }
-    };
+    }
 
     Ok(project_info)
 }
 
+/// Parses a single start tag, e.g. `<a href="...">`, and calls `f` with it.
+fn with_tag<T>(tag: &str, f: impl FnOnce(&HTMLTag) -> Option<T>) -> Option<T> {
+    let dom = tl::parse(tag, tl::ParserOptions::default()).ok()?;
+    let tag = dom.children().first()?.get(dom.parser())?.as_tag()?;
+    f(tag)
+}
+
+/// Returns the value of the attribute with the specified name.
+fn attribute(tag: &HTMLTag, name: &str) -> Option<String> {
+    tag.attributes()
+        .get(name)
+        .flatten()
+        .map(|value| value.as_utf8_str().to_string())
+}
+
+/// Iterates over the start tags of an HTML document, returning the name of each tag together with
+/// the source of the whole tag. Comments, end tags and text are skipped.
+struct StartTags<'a> {
+    body: &'a str,
+    position: usize,
+}
+
+impl<'a> StartTags<'a> {
+    fn new(body: &'a str) -> Self {
+        Self { body, position: 0 }
+    }
+}
+
+impl<'a> Iterator for StartTags<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.position + self.body.get(self.position..)?.find('<')?;
+            let rest = &self.body[start + 1..];
+
+            // Skip comments as a whole, they might contain tags
+            if let Some(comment) = rest.strip_prefix("!--") {
+                self.position = comment
+                    .find("-->")
+                    .map_or(self.body.len(), |end| start + 4 + end + 3);
+                continue;
+            }
+
+            // End tags, doctypes and a stray `<` are not start tags
+            let name_len = rest
+                .bytes()
+                .take_while(|b| b.is_ascii_alphanumeric())
+                .count();
+            if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                self.position = start + 1;
+                continue;
+            }
+
+            // Find the end of the tag, a `>` can also occur in a quoted attribute value
+            let mut quote = None;
+            let mut end = None;
+            for (offset, byte) in rest.bytes().enumerate().skip(name_len) {
+                match quote {
+                    Some(q) if byte == q => quote = None,
+                    Some(_) => {}
+                    None if byte == b'"' || byte == b'\'' => quote = Some(byte),
+                    None if byte == b'>' => {
+                        end = Some(start + 1 + offset);
+                        break;
+                    }
+                    None => {}
+                }
+            }
+
+            // An unterminated tag ends the document
+            let Some(end) = end else {
+                self.position = self.body.len();
+                return None;
+            };
+            self.position = end + 1;
+            return Some((&rest[..name_len], &self.body[start..=end]));
+        }
+    }
+}
+
 /// Parse package names from a pypyi repository index.
 #[tracing::instrument(level = "debug", skip(body))]
 pub fn parse_package_names_html(body: &str) -> miette::Result<Vec<String>> {
@@ -200,6 +307,9 @@ pub fn parse_package_names_html(body: &str) -> miette::Result<Vec<String>> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::python_env::{WheelTag, WheelTags};
+    use crate::types::Version;
+    use std::sync::Arc;
 
     #[test]
     fn test_sink_simple() {
@@ -305,6 +415,66 @@ mod test {
         "###);
     }
 
+    #[test]
+    fn test_filtered_parsing() {
+        let body = r#"<!DOCTYPE html>
+            <HTML>
+              <!-- <a href="commented-1.0.tar.gz">commented</a> -->
+              <body>
+                <a href="link-1.0-py3-none-any.whl" title="a > b">link</a>
+                <A href="link-1.0-cp311-cp311-manylinux_2_17_x86_64.whl">link</A>
+                <a href="link-1.0-cp311-cp311-win_amd64.whl">link</a>
+                <a href="link-1.0.tar.gz">link</a>
+                <a href="link-2.0-py3-none-any.whl" data-requires-python="&lt;3.8">link</a>
+                <a href="link-2.0.tar.gz" data-requires-python="&lt;3.8">link</a>
+                <a
+                  href="link-3.0-py3-none-any.whl"
+                  data-requires-python="&gt;=3.8">link</a>
+              </body>
+            </HTML>
+        "#;
+        let base = Url::parse("https://example.com/simple/link/").unwrap();
+        let registry = ArtifactRegistry::default();
+        let filenames = |filter: &ArtifactFilter| {
+            parse_project_info_html_with_filter(&base, body, &registry, filter)
+                .unwrap()
+                .files
+                .into_iter()
+                .map(|artifact| artifact.filename.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            filenames(&ArtifactFilter::default()),
+            [
+                "link-1.0-py3-none-any.whl",
+                "link-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+                "link-1.0-cp311-cp311-win_amd64.whl",
+                "link-1.0.tar.gz",
+                "link-2.0-py3-none-any.whl",
+                "link-2.0.tar.gz",
+                "link-3.0-py3-none-any.whl",
+            ]
+        );
+
+        let wheel_tags = ["cp311-cp311-manylinux_2_17_x86_64", "py3-none-any"]
+            .into_iter()
+            .flat_map(|tag| WheelTag::from_compound_string(tag).unwrap())
+            .collect::<WheelTags>();
+        let filter = ArtifactFilter::new()
+            .with_wheel_tags(Arc::new(wheel_tags))
+            .with_python_version(Version::from_str("3.11.4").unwrap());
+        assert_eq!(
+            filenames(&filter),
+            [
+                "link-1.0-py3-none-any.whl",
+                "link-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+                "link-1.0.tar.gz",
+                "link-3.0-py3-none-any.whl",
+            ]
+        );
+    }
+
     #[test]
     fn test_package_name_parsing() {
         let html = r#"
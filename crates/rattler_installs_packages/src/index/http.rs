@@ -1,24 +1,37 @@
 use super::file_store::FileLock;
 use super::file_store::FileStore;
+use super::forge::ForgeTokens;
 use super::package_database::NotCached;
-use crate::utils::{ReadAndSeek, SeekSlice, StreamingOrLocal};
+use super::tuf::{TufError, TufVerifier};
+use crate::metrics::Metrics;
+use crate::reporter::Reporter;
+use crate::utils::{
+    ensure_available_space, InsufficientDiskSpace, LocalFile, ReadAndSeek, SeekSlice,
+    StreamingOrLocal,
+};
 use bytes::Bytes;
-use futures::{Stream, StreamExt, TryStreamExt};
-use http_cache_semantics::{AfterResponse, BeforeRequest, CachePolicy};
+use futures::future::{select, Either};
+use futures::{Future, Stream, StreamExt, TryStreamExt};
+use http_cache_semantics::{AfterResponse, BeforeRequest, CacheOptions, CachePolicy};
 use miette::Diagnostic;
-use reqwest::header::{ACCEPT, CACHE_CONTROL};
+use reqwest::header::{HeaderValue, ACCEPT, CACHE_CONTROL};
 use reqwest::{header::HeaderMap, Method};
 use reqwest_middleware::ClientWithMiddleware;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::num::NonZeroUsize;
+use std::pin::{pin, Pin};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::SystemTime;
 use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tokio_util::compat::FuturesAsyncReadCompatExt;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
 use url::Url;
 
 const CURRENT_VERSION: u8 = 1;
@@ -45,10 +58,109 @@ pub enum CacheMode {
     NoStore,
 }
 
+/// Determines how the cache is used for all requests of an [`Http`] instance, on top of the
+/// [`CacheMode`] of the individual requests.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum CacheSetting {
+    /// Use the [`CacheMode`] of the request
+    #[default]
+    Default,
+    /// Never access the network, requests are served from the cache even if the cached response
+    /// is stale
+    Offline,
+    /// Revalidate every cached response with the server, even if it is still fresh
+    Refresh,
+    /// Don't look in cache, and don't write to cache
+    NoCache,
+}
+
+/// Limits how many requests are performed at the same time, in total and to a single host. A
+/// request counts against the limits until its response body has been received.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimits {
+    /// The maximum number of requests in total, unlimited if `None`
+    pub total: Option<NonZeroUsize>,
+
+    /// The maximum number of requests to a single host, unlimited if `None`
+    pub per_host: Option<NonZeroUsize>,
+}
+
+/// Hands out the permits to perform requests within the [`ConcurrencyLimits`].
+#[derive(Debug, Default)]
+struct ConcurrencyLimiter {
+    per_host: Option<NonZeroUsize>,
+    total: Option<Arc<Semaphore>>,
+    hosts: parking_lot::Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ConcurrencyLimiter {
+    fn new(limits: ConcurrencyLimits) -> Self {
+        Self {
+            per_host: limits.per_host,
+            total: limits
+                .total
+                .map(|total| Arc::new(Semaphore::new(total.get()))),
+            hosts: Default::default(),
+        }
+    }
+
+    /// Waits until a request to `url` can be performed within the limits.
+    async fn acquire(&self, url: &Url) -> RequestPermit {
+        // The permit of the host is acquired first, so a request never holds a permit of the
+        // total while it waits for its host.
+        let host = match self.per_host {
+            Some(per_host) => {
+                let key = format!(
+                    "{}:{}",
+                    url.host_str().unwrap_or_default(),
+                    url.port_or_known_default().unwrap_or_default()
+                );
+                let semaphore = self
+                    .hosts
+                    .lock()
+                    .entry(key)
+                    .or_insert_with(|| Arc::new(Semaphore::new(per_host.get())))
+                    .clone();
+                Some(acquire_owned(semaphore).await)
+            }
+            None => None,
+        };
+        let total = match &self.total {
+            Some(semaphore) => Some(acquire_owned(semaphore.clone()).await),
+            None => None,
+        };
+        RequestPermit {
+            _host: host,
+            _total: total,
+        }
+    }
+}
+
+async fn acquire_owned(semaphore: Arc<Semaphore>) -> OwnedSemaphorePermit {
+    semaphore
+        .acquire_owned()
+        .await
+        .expect("the semaphore is never closed")
+}
+
+/// Allows a request to be performed within the [`ConcurrencyLimits`] of an [`Http`] instance,
+/// the permit is released when this is dropped.
+#[derive(Debug)]
+pub(crate) struct RequestPermit {
+    _host: Option<OwnedSemaphorePermit>,
+    _total: Option<OwnedSemaphorePermit>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Http {
     pub(crate) client: ClientWithMiddleware,
     http_cache: Arc<FileStore>,
+    cache_setting: CacheSetting,
+    tuf: Option<Arc<TufVerifier>>,
+    forge_tokens: Arc<ForgeTokens>,
+    metrics: Arc<Metrics>,
+    limiter: Arc<ConcurrencyLimiter>,
+    cancellation: CancellationToken,
 }
 
 #[derive(Debug, Error, Diagnostic)]
@@ -62,6 +174,18 @@ pub enum HttpRequestError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     NotCached(#[from] NotCached),
+
+    #[error("{0} is not available in the cache and network access is disabled")]
+    Offline(Url),
+
+    #[error(transparent)]
+    Tuf(#[from] TufError),
+
+    #[error("the request was interrupted")]
+    Interrupted,
+
+    #[error(transparent)]
+    InsufficientDiskSpace(#[from] InsufficientDiskSpace),
 }
 
 impl From<reqwest::Error> for HttpRequestError {
@@ -76,6 +200,151 @@ impl Http {
         Http {
             client,
             http_cache: Arc::new(http_cache),
+            cache_setting: CacheSetting::Default,
+            tuf: None,
+            forge_tokens: Arc::default(),
+            metrics: Arc::default(),
+            limiter: Arc::default(),
+            cancellation: CancellationToken::new(),
+        }
+    }
+
+    /// Sets how the cache is used for all requests.
+    pub fn with_cache_setting(self, cache_setting: CacheSetting) -> Self {
+        Self {
+            cache_setting,
+            ..self
+        }
+    }
+
+    /// Returns how the cache is used for all requests.
+    pub fn cache_setting(&self) -> CacheSetting {
+        self.cache_setting
+    }
+
+    /// Verifies the responses of GET requests that are covered by the TUF metadata of a
+    /// repository.
+    pub(crate) fn with_tuf(self, tuf: Option<Arc<TufVerifier>>) -> Self {
+        Self { tuf, ..self }
+    }
+
+    /// Authenticates the requests to GitHub and GitLab with `forge_tokens`.
+    pub(crate) fn with_forge_tokens(self, forge_tokens: ForgeTokens) -> Self {
+        Self {
+            forge_tokens: Arc::new(forge_tokens),
+            ..self
+        }
+    }
+
+    /// Records the requests and the downloaded bytes in `metrics`.
+    pub(crate) fn with_metrics(self, metrics: Arc<Metrics>) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// Limits how many requests are performed at the same time.
+    pub(crate) fn with_concurrency_limits(self, limits: ConcurrencyLimits) -> Self {
+        Self {
+            limiter: Arc::new(ConcurrencyLimiter::new(limits)),
+            ..self
+        }
+    }
+
+    /// Interrupts the requests and downloads that are in flight when `cancellation` is cancelled,
+    /// and fails new requests. The partially downloaded bodies are discarded.
+    pub(crate) fn with_cancellation_token(self, cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// Returns the token that interrupts the requests of this instance when it is cancelled.
+    pub(crate) fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Waits until a request to `url` can be performed within the [`ConcurrencyLimits`], for
+    /// requests that are not performed by this instance like range requests. The request should
+    /// be performed while the returned permit is alive.
+    pub(crate) async fn acquire_permit(&self, url: &Url) -> RequestPermit {
+        self.limiter.acquire(url).await
+    }
+
+    /// Fails before a body of `total_bytes` is written to the cache if the volume of the cache
+    /// does not have enough space for it.
+    fn ensure_cache_space(
+        &self,
+        url: &Url,
+        total_bytes: Option<u64>,
+    ) -> Result<(), InsufficientDiskSpace> {
+        match total_bytes {
+            Some(total_bytes) => {
+                ensure_available_space(self.http_cache.base(), total_bytes, || {
+                    format!("download {url}")
+                })
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Sends `request` to `url` within the [`ConcurrencyLimits`]. The returned permit should be
+    /// kept alive until the body of the response has been received.
+    async fn execute(
+        &self,
+        url: &Url,
+        request: reqwest::Request,
+    ) -> Result<(reqwest::Response, RequestPermit), HttpRequestError> {
+        if self.cancellation.is_cancelled() {
+            return Err(HttpRequestError::Interrupted);
+        }
+        let execute = pin!(async {
+            let permit = self.limiter.acquire(url).await;
+            self.metrics.requests.inc();
+            let response = self.client.execute(request).await?;
+            Ok((response, permit))
+        });
+        match select(execute, pin!(self.cancellation.cancelled())).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(HttpRequestError::Interrupted),
+        }
+    }
+
+    /// Performs a POST request with a JSON body, e.g. to an API. The response is never cached and
+    /// its body is returned as JSON.
+    #[cfg(feature = "git")]
+    pub(crate) async fn post_json(
+        &self,
+        url: Url,
+        mut headers: HeaderMap,
+        body: &serde_json::Value,
+    ) -> Result<serde_json::Value, HttpRequestError> {
+        if self.cache_setting == CacheSetting::Offline {
+            return Err(HttpRequestError::Offline(url));
+        }
+        self.forge_tokens.authenticate(&url, &mut headers);
+        let request = self
+            .client
+            .post(url.clone())
+            .headers(headers)
+            .json(body)
+            .build()?;
+        let (response, _permit) = self.execute(&url, request).await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Returns true if responses are verified with TUF metadata.
+    pub(crate) fn verifies_tuf(&self) -> bool {
+        self.tuf.is_some()
+    }
+
+    /// Removes the cached response to a `GET` request of `url`, e.g. because its contents turned
+    /// out to be invalid.
+    pub(crate) async fn remove_cached(&self, url: &Url) -> io::Result<()> {
+        let key = key_for_request(url, Method::GET, &HeaderMap::new());
+        let lock = self.http_cache.lock(&key.as_slice()).await?;
+        match lock.remove() {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
         }
     }
 
@@ -87,19 +356,124 @@ impl Http {
         headers: HeaderMap,
         cache_mode: CacheMode,
     ) -> Result<http::Response<StreamingOrLocal>, HttpRequestError> {
+        self.request_with_reporter(url, method, headers, cache_mode, None)
+            .await
+    }
+
+    /// Performs a single request like [`Self::request`] and reports the download of the body to
+    /// the `reporter` if it is retrieved from the network.
+    #[tracing::instrument(
+        name = "http_request",
+        skip_all,
+        fields(method = %method, url = %url, cache_status, cache_hit, bytes)
+    )]
+    pub async fn request_with_reporter(
+        &self,
+        url: Url,
+        method: Method,
+        headers: HeaderMap,
+        cache_mode: CacheMode,
+        reporter: Option<&Arc<dyn Reporter>>,
+    ) -> Result<http::Response<StreamingOrLocal>, HttpRequestError> {
+        let target_path = match &self.tuf {
+            Some(tuf) if method == Method::GET => tuf.repository().target_path(&url),
+            _ => None,
+        };
+        let response = self
+            .request_unverified(url, method, headers, cache_mode, reporter)
+            .await?;
+        if let Some(cache_status) = response.extensions().get::<CacheStatus>() {
+            let span = tracing::Span::current();
+            span.record("cache_status", tracing::field::debug(cache_status));
+            span.record(
+                "cache_hit",
+                matches!(
+                    cache_status,
+                    CacheStatus::Fresh | CacheStatus::StaleButValidated
+                ),
+            );
+        }
+        let (Some(tuf), Some(target_path)) = (&self.tuf, target_path) else {
+            return Ok(response);
+        };
+
+        // The whole body is needed to verify it
+        let (parts, body) = response.into_parts();
+        let body = tuf
+            .verify_target(self, &target_path, body.into_local().await?)
+            .await?;
+        Ok(http::Response::from_parts(
+            parts,
+            StreamingOrLocal::Local(body),
+        ))
+    }
+
+    /// Performs a request without verifying the response with TUF metadata, e.g. to retrieve the
+    /// metadata itself.
+    pub(crate) async fn request_unverified(
+        &self,
+        url: Url,
+        method: Method,
+        headers: HeaderMap,
+        cache_mode: CacheMode,
+        reporter: Option<&Arc<dyn Reporter>>,
+    ) -> Result<http::Response<StreamingOrLocal>, HttpRequestError> {
+        // Requests that only look in the cache fail with `NotCached` so the caller can fall back to
+        // another request, other requests fail because they would require the network.
+        let not_cached = if cache_mode == CacheMode::OnlyIfCached {
+            HttpRequestError::NotCached(NotCached)
+        } else {
+            HttpRequestError::Offline(url.clone())
+        };
+
+        // The global cache setting overrides the cache mode of the request
+        let cache_mode = match (self.cache_setting, cache_mode) {
+            (CacheSetting::NoCache, CacheMode::OnlyIfCached) => return Err(NotCached.into()),
+            (CacheSetting::NoCache, _) => CacheMode::NoStore,
+            (CacheSetting::Offline, CacheMode::NoStore) => {
+                return Err(HttpRequestError::Offline(url))
+            }
+            (CacheSetting::Offline, _) => CacheMode::OnlyIfCached,
+            (_, cache_mode) => cache_mode,
+        };
         tracing::info!(url=%url, cache_mode=?cache_mode, "executing request");
 
-        // Construct a request using the reqwest client.
+        // Construct a request using the reqwest client. When refreshing, the request asks for
+        // revalidation so even fresh responses in the cache are checked with the server. When
+        // offline, stale responses in the cache are accepted. This does not affect the key of the
+        // cache entry.
+        let mut request_headers = headers.clone();
+        let authenticated = self.forge_tokens.authenticate(&url, &mut request_headers);
+        match self.cache_setting {
+            CacheSetting::Refresh => {
+                request_headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+            }
+            CacheSetting::Offline => {
+                request_headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-stale"));
+            }
+            CacheSetting::Default | CacheSetting::NoCache => {}
+        }
         let request = self
             .client
             .request(method.clone(), url.clone())
-            .headers(headers.clone())
+            .headers(request_headers)
             .build()?;
 
         if cache_mode == CacheMode::NoStore {
-            let mut response =
-                convert_response(self.client.execute(request).await?.error_for_status()?)
-                    .map(body_to_streaming_or_local);
+            let (response, permit) = self.execute(&url, request).await?;
+            let response = response.error_for_status()?;
+            let total_bytes = response.content_length();
+            let mut response = convert_response(response).map(|body| {
+                body_to_streaming_or_local(ReportingStream::new(
+                    body,
+                    &url,
+                    total_bytes,
+                    reporter,
+                    &self.metrics,
+                    permit,
+                    &self.cancellation,
+                ))
+            });
 
             // Add the `CacheStatus` to the response
             response.extensions_mut().insert(CacheStatus::Uncacheable);
@@ -110,11 +484,13 @@ impl Http {
             let lock = self.http_cache.lock(&key.as_slice()).await?;
 
             if let Some((old_policy, final_url, old_body)) = lock.reader().and_then(|reader| {
-                read_cache(reader.detach_unlocked(), CACHE_BOM, CURRENT_VERSION).ok()
+                let file = LocalFile::from_file(reader.detach_unlocked()).ok()?;
+                read_cache(file, CACHE_BOM, CURRENT_VERSION).ok()
             }) {
                 match old_policy.before_request(&request, SystemTime::now()) {
                     BeforeRequest::Fresh(parts) => {
                         tracing::debug!(url=%url, "is fresh");
+                        self.metrics.cache_hits.inc();
                         let mut response = http::Response::from_parts(
                             parts,
                             StreamingOrLocal::Local(Box::new(old_body)),
@@ -128,15 +504,17 @@ impl Http {
                         matches: _,
                     } => {
                         if cache_mode == CacheMode::OnlyIfCached {
-                            return Err(NotCached.into());
+                            return Err(not_cached);
                         }
 
                         // Perform the request with the new headers to determine if the cache is up
                         // to date or not.
                         let request = convert_request(self.client.clone(), new_parts)?;
-                        let response = self
-                            .client
-                            .execute(request.try_clone().expect("clone of request cannot fail"))
+                        let (response, permit) = self
+                            .execute(
+                                &url,
+                                request.try_clone().expect("clone of request cannot fail"),
+                            )
                             .await?;
                         let final_url = response.url().clone();
 
@@ -144,6 +522,7 @@ impl Http {
                         match old_policy.after_response(&request, &response, SystemTime::now()) {
                             AfterResponse::NotModified(_, new_parts) => {
                                 tracing::debug!(url=%url, "stale, but not modified");
+                                self.metrics.cache_hits.inc();
                                 Ok(make_response(
                                     new_parts,
                                     StreamingOrLocal::Local(Box::new(old_body)),
@@ -154,18 +533,25 @@ impl Http {
                             AfterResponse::Modified(new_policy, parts) => {
                                 tracing::debug!(url=%url, "stale, but *and* modified");
                                 drop(old_body);
+                                let total_bytes = response.content_length();
+                                let body = ReportingStream::new(
+                                    response.bytes_stream(),
+                                    &url,
+                                    total_bytes,
+                                    reporter,
+                                    &self.metrics,
+                                    permit,
+                                    &self.cancellation,
+                                );
                                 let new_body = if new_policy.is_storable() {
-                                    let new_body = fill_cache_async(
-                                        &new_policy,
-                                        &final_url,
-                                        response.bytes_stream(),
-                                        lock,
-                                    )
-                                    .await?;
+                                    self.ensure_cache_space(&final_url, total_bytes)?;
+                                    let new_body =
+                                        fill_cache_async(&new_policy, &final_url, body, lock)
+                                            .await?;
                                     StreamingOrLocal::Local(Box::new(new_body))
                                 } else {
                                     lock.remove()?;
-                                    body_to_streaming_or_local(response.bytes_stream())
+                                    body_to_streaming_or_local(body)
                                 };
                                 Ok(make_response(
                                     parts,
@@ -179,20 +565,40 @@ impl Http {
                 }
             } else {
                 if cache_mode == CacheMode::OnlyIfCached {
-                    return Err(NotCached.into());
+                    return Err(not_cached);
                 }
 
-                let response = self
-                    .client
-                    .execute(request.try_clone().expect("failed to clone request?"))
-                    .await?
-                    .error_for_status()?;
+                let (response, permit) = self
+                    .execute(&url, request.try_clone().expect("failed to clone request?"))
+                    .await?;
+                let response = response.error_for_status()?;
                 let final_url = response.url().clone();
+                let total_bytes = response.content_length();
                 let response = convert_response(response);
 
-                let new_policy = CachePolicy::new(&request, &response);
+                // Responses to authenticated requests are only stored by a private cache, which the
+                // cache of a single user is.
+                let new_policy = CachePolicy::new_options(
+                    &request,
+                    &response,
+                    SystemTime::now(),
+                    CacheOptions {
+                        shared: !authenticated,
+                        ..CacheOptions::default()
+                    },
+                );
                 let (parts, body) = response.into_parts();
+                let body = ReportingStream::new(
+                    body,
+                    &url,
+                    total_bytes,
+                    reporter,
+                    &self.metrics,
+                    permit,
+                    &self.cancellation,
+                );
                 let new_body = if new_policy.is_storable() {
+                    self.ensure_cache_space(&final_url, total_bytes)?;
                     let new_body = fill_cache_async(&new_policy, &final_url, body, lock).await?;
                     StreamingOrLocal::Local(Box::new(new_body))
                 } else {
@@ -324,7 +730,7 @@ fn verify_cache_bom_and_version<R: Read + Seek>(
 async fn fill_cache_async(
     policy: &CachePolicy,
     url: &Url,
-    mut body: impl Stream<Item = reqwest::Result<Bytes>> + Send + Unpin,
+    mut body: impl Stream<Item = std::io::Result<Bytes>> + Send + Unpin,
     handle: FileLock,
 ) -> Result<impl Read + Seek, std::io::Error> {
     let cache_writer = handle.begin()?;
@@ -368,16 +774,15 @@ async fn fill_cache_async(
 
     buf_cache_writer.seek(SeekFrom::Start(body_start)).unwrap();
 
+    // If the download fails or is interrupted the temporary file is dropped without being
+    // committed, so the entry in the cache is never left half written.
     while let Some(bytes) = body.next().await {
-        buf_cache_writer.write_all(
-            bytes
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
-                .as_ref(),
-        )?;
+        buf_cache_writer.write_all(bytes?.as_ref())?;
     }
 
     let body_end = buf_cache_writer.stream_position()?;
-    let cache_entry = buf_cache_writer.into_inner()?.commit()?.detach_unlocked();
+    let cache_entry =
+        LocalFile::from_file(buf_cache_writer.into_inner()?.commit()?.detach_unlocked())?;
 
     SeekSlice::new(cache_entry, body_start, body_end)
 }
@@ -419,15 +824,98 @@ fn convert_response(
         .expect("building should never fail")
 }
 
+/// A stream of the body of a response that reports the progress of the download to a
+/// [`Reporter`] and counts the downloaded bytes in the [`Metrics`].
+///
+/// The stream keeps the span of the request open until the body was received and records the
+/// number of downloaded `bytes` in it. The request counts against the [`ConcurrencyLimits`] until
+/// the stream is dropped. When the cancellation token of the [`Http`] instance is cancelled the
+/// stream fails with an [`std::io::ErrorKind::Interrupted`] error.
+struct ReportingStream<S> {
+    inner: S,
+    reporter: Option<(Arc<dyn Reporter>, usize)>,
+    bytes_downloaded: u64,
+    metrics: Arc<Metrics>,
+    span: tracing::Span,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+    _permit: RequestPermit,
+}
+
+impl<S> ReportingStream<S> {
+    fn new(
+        inner: S,
+        url: &Url,
+        total_bytes: Option<u64>,
+        reporter: Option<&Arc<dyn Reporter>>,
+        metrics: &Arc<Metrics>,
+        permit: RequestPermit,
+        cancellation: &CancellationToken,
+    ) -> Self {
+        Self {
+            inner,
+            reporter: reporter.map(|reporter| {
+                let index = reporter.on_download_start(url, total_bytes);
+                (reporter.clone(), index)
+            }),
+            bytes_downloaded: 0,
+            metrics: metrics.clone(),
+            span: tracing::Span::current(),
+            cancelled: Box::pin(cancellation.clone().cancelled_owned()),
+            _permit: permit,
+        }
+    }
+}
+
+impl<S: Stream<Item = reqwest::Result<Bytes>> + Unpin> Stream for ReportingStream<S> {
+    type Item = std::io::Result<Bytes>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        if self.cancelled.as_mut().poll(cx).is_ready() {
+            return std::task::Poll::Ready(Some(Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "the download was interrupted",
+            ))));
+        }
+        let poll = self
+            .inner
+            .poll_next_unpin(cx)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
+        match &poll {
+            std::task::Poll::Ready(Some(Ok(bytes))) => {
+                self.bytes_downloaded += bytes.len() as u64;
+                self.metrics.bytes_downloaded.add(bytes.len() as u64);
+                if let Some((reporter, index)) = &self.reporter {
+                    reporter.on_download_progress(*index, self.bytes_downloaded);
+                }
+            }
+            std::task::Poll::Ready(None) => {
+                self.span.record("bytes", self.bytes_downloaded);
+                if let Some((reporter, index)) = self.reporter.take() {
+                    reporter.on_download_complete(index);
+                }
+            }
+            _ => {}
+        }
+        poll
+    }
+}
+
+impl<S> Drop for ReportingStream<S> {
+    fn drop(&mut self) {
+        // The download was aborted before the end of the stream
+        if let Some((reporter, index)) = self.reporter.take() {
+            reporter.on_download_complete(index);
+        }
+    }
+}
+
 fn body_to_streaming_or_local(
-    stream: impl Stream<Item = reqwest::Result<Bytes>> + Send + Unpin + 'static,
+    stream: impl Stream<Item = std::io::Result<Bytes>> + Send + Unpin + 'static,
 ) -> StreamingOrLocal {
-    StreamingOrLocal::Streaming(Box::new(
-        stream
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
-            .into_async_read()
-            .compat(),
-    ))
+    StreamingOrLocal::Streaming(Box::new(stream.into_async_read().compat()))
 }
 
 #[cfg(test)]
@@ -443,7 +931,15 @@ mod tests {
     use std::{fs, io::BufWriter, sync::Arc};
     use tempfile::TempDir;
 
-    use super::{key_for_request, read_cache, CacheMode, Http};
+    use super::{
+        key_for_request, read_cache, CacheMode, CacheSetting, ConcurrencyLimits, Http,
+        HttpRequestError,
+    };
+    use futures::StreamExt;
+    use std::future::IntoFuture;
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
 
     fn get_http_client() -> (Arc<Http>, TempDir) {
         let tempdir = tempfile::tempdir().unwrap();
@@ -505,4 +1001,136 @@ mod tests {
 
         assert!(read_again.is_err());
     }
+
+    #[tokio::test]
+    pub async fn test_cache_setting_prevents_network_access() {
+        let url = url::Url::parse("https://pypi.org/simple/boltons").unwrap();
+        let (http, _tmpdir) = get_http_client();
+
+        // When offline, nothing is cached so every request fails without touching the network
+        let offline = http
+            .as_ref()
+            .clone()
+            .with_cache_setting(CacheSetting::Offline);
+        for cache_mode in [CacheMode::Default, CacheMode::NoStore] {
+            let err = offline
+                .request(url.clone(), Method::GET, HeaderMap::new(), cache_mode)
+                .await
+                .err()
+                .expect("the request must fail");
+            assert!(matches!(err, HttpRequestError::Offline(_)), "{err:?}");
+        }
+        let err = offline
+            .request(
+                url.clone(),
+                Method::GET,
+                HeaderMap::new(),
+                CacheMode::OnlyIfCached,
+            )
+            .await
+            .err()
+            .expect("the request must fail");
+        assert!(matches!(err, HttpRequestError::NotCached(_)), "{err:?}");
+
+        // Without a cache, requests that only look in the cache never succeed
+        let no_cache = http
+            .as_ref()
+            .clone()
+            .with_cache_setting(CacheSetting::NoCache);
+        let err = no_cache
+            .request(url, Method::GET, HeaderMap::new(), CacheMode::OnlyIfCached)
+            .await
+            .err()
+            .expect("the request must fail");
+        assert!(matches!(err, HttpRequestError::NotCached(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    pub async fn test_concurrency_limits() {
+        let (http, _tmpdir) = get_http_client();
+        let http = http
+            .as_ref()
+            .clone()
+            .with_concurrency_limits(ConcurrencyLimits {
+                total: NonZeroUsize::new(2),
+                per_host: NonZeroUsize::new(1),
+            });
+        let pypi = url::Url::parse("https://pypi.org/simple/boltons").unwrap();
+        let files = url::Url::parse("https://files.pythonhosted.org/boltons.whl").unwrap();
+        let other = url::Url::parse("https://example.com/simple/boltons").unwrap();
+        let timeout = Duration::from_millis(50);
+
+        // A second request to the same host has to wait for the first
+        let first = http.acquire_permit(&pypi).await;
+        assert!(tokio::time::timeout(timeout, http.acquire_permit(&pypi))
+            .await
+            .is_err());
+
+        // Until the total limit is reached, requests to other hosts are not affected
+        let second = http.acquire_permit(&files).await;
+        assert!(tokio::time::timeout(timeout, http.acquire_permit(&other))
+            .await
+            .is_err());
+
+        drop(first);
+        drop(second);
+        let _third = http.acquire_permit(&pypi).await;
+        let _fourth = http.acquire_permit(&other).await;
+    }
+
+    #[tokio::test]
+    pub async fn test_interrupted_download_is_not_cached() {
+        // Serves the first bytes of a body and then stalls forever
+        let router = axum::Router::new().route(
+            "/simple/boltons/",
+            axum::routing::get(|| async {
+                let body = futures::stream::once(async {
+                    Ok::<_, std::io::Error>(bytes::Bytes::from_static(b"partial"))
+                })
+                .chain(futures::stream::pending());
+                (
+                    [("cache-control", "max-age=600")],
+                    axum::body::Body::from_stream(body),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, router).into_future());
+
+        let (http, tmpdir) = get_http_client();
+        let cancellation = CancellationToken::new();
+        let http = http
+            .as_ref()
+            .clone()
+            .with_cancellation_token(cancellation.clone());
+        let url = url::Url::parse(&format!("http://{address}/simple/boltons/")).unwrap();
+
+        let request = http.request(
+            url.clone(),
+            Method::GET,
+            HeaderMap::default(),
+            CacheMode::Default,
+        );
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancellation.cancel();
+        };
+        let (result, _) = tokio::join!(request, cancel);
+        assert!(matches!(
+            result,
+            Err(HttpRequestError::IoError(err)) if err.kind() == std::io::ErrorKind::Interrupted
+        ));
+
+        // The partial body was discarded
+        let tmp = tmpdir.path().join("http/.tmp");
+        assert_eq!(fs::read_dir(tmp).unwrap().count(), 0);
+
+        // New requests fail right away
+        assert!(matches!(
+            http.request(url, Method::GET, HeaderMap::default(), CacheMode::Default)
+                .await,
+            Err(HttpRequestError::Interrupted)
+        ));
+    }
 }
@@ -1,16 +1,42 @@
 //! This module contains functions for working with PyPA packaging repositories.
 
+mod artifact_filter;
 mod file_store;
 
 mod direct_url;
+mod find_links;
+mod forge;
+#[cfg(feature = "git")]
+mod git_auth;
+#[cfg(feature = "git")]
 mod git_interop;
+#[cfg(feature = "git")]
+mod git_lfs;
 pub mod html;
 mod http;
 mod package_database;
 mod package_sources;
+mod tuf;
+mod typosquatting;
+mod warm;
 
-pub use package_database::{ArtifactRequest, PackageDb};
+/// Stand-in for the authentication of the `git` commands when the crate is compiled without the
+/// `git` feature.
+#[cfg(not(feature = "git"))]
+mod git_auth {
+    #[derive(Clone, Default)]
+    pub(crate) struct GitAuth {}
+}
+
+pub use artifact_filter::ArtifactFilter;
+pub use forge::ForgeTokens;
+#[cfg(feature = "git")]
+pub use git_auth::{GitAuth, GitCredentialCallback};
+pub use package_database::{ArtifactHashMismatch, ArtifactRequest, PackageDb};
 pub use package_sources::{PackageSources, PackageSourcesBuilder};
+pub use tuf::{TufError, TufRepository};
+pub use typosquatting::{TyposquattingCheck, TyposquattingReason, TyposquattingWarning};
+pub use warm::{WarmOptions, WarmRequest, WarmSummary};
 
-pub use self::http::CacheMode;
+pub use self::http::{CacheMode, CacheSetting, ConcurrencyLimits};
 pub use html::parse_hash;
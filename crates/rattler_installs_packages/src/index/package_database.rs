@@ -1,16 +1,32 @@
-use crate::artifacts::{SDist, STree, Wheel};
+use crate::artifacts::{ArtifactRegistry, SDist, STree, Wheel};
+use crate::index::artifact_filter::ArtifactFilter;
 use crate::index::file_store::FileStore;
+use crate::index::find_links::find_links_artifacts;
+use crate::index::forge::ForgeTokens;
+use crate::index::git_auth::GitAuth;
 
-use crate::index::html::{parse_package_names_html, parse_project_info_html};
-use crate::index::http::{CacheMode, Http, HttpRequestError};
+use crate::index::html::{parse_package_names_html, parse_project_info_html_with_filter};
+use crate::index::http::{
+    CacheMode, CacheSetting, CacheStatus, ConcurrencyLimits, Http, HttpRequestError,
+};
 use crate::index::package_sources::PackageSources;
-use crate::resolve::PypiVersion;
+use crate::index::tuf::{TufRepository, TufVerifier};
+use crate::index::typosquatting::{TyposquattingCheck, TyposquattingWarning};
+use crate::index::warm::{WarmOptions, WarmRequest, WarmSummary};
+use crate::metrics::Metrics;
+use crate::reporter::Reporter;
+use crate::resolve::policy::PackagePolicy;
+use crate::resolve::{PinnedPackage, PypiVersion};
 use crate::types::{
-    ArtifactInfo, ArtifactType, DirectUrlHashes, DirectUrlJson, DirectUrlSource, ProjectInfo,
-    STreeFilename, WheelCoreMetadata,
+    ArtifactInfo, ArtifactType, CustomArtifactName, DirectUrlHashes, DirectUrlJson,
+    DirectUrlSource, ProjectInfo, STreeFilename, WheelCoreMetadata,
 };
+use crate::types::{PackageName, Requirement};
 
-use crate::wheel_builder::{WheelBuildError, WheelBuilder, WheelCache};
+use crate::utils::{ensure_available_space, LocalFile, ReadAndSeek};
+#[cfg(feature = "sdist")]
+use crate::wheel_builder::WheelCache;
+use crate::wheel_builder::{WheelBuildError, WheelBuilder};
 use crate::{
     types::ArtifactFromBytes, types::InnerAsArtifactName, types::NormalizedPackageName,
     types::WheelFilename,
@@ -18,9 +34,11 @@ use crate::{
 use async_http_range_reader::{AsyncHttpRangeReader, CheckSupportMethod};
 use async_recursion::async_recursion;
 use elsa::sync::FrozenMap;
+use fs_err as fs;
 use futures::{pin_mut, stream, StreamExt};
 use indexmap::IndexMap;
 use miette::{self, Diagnostic, IntoDiagnostic};
+use pep508_rs::VersionOrUrl;
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::Method;
 
@@ -32,9 +50,17 @@ use std::path::PathBuf;
 
 use itertools::Itertools;
 use std::ops::Deref;
+use std::str::FromStr;
 use std::sync::Arc;
-use std::{fmt::Display, io::Read, path::Path};
+use std::{
+    fmt::Display,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
 
+use rattler_digest::{digest::Digest, Sha256};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 use url::Url;
 
 type VersionArtifacts = IndexMap<PypiVersion, Vec<Arc<ArtifactInfo>>>;
@@ -51,11 +77,39 @@ pub struct PackageDb {
     /// A cache of package name to version to artifacts.
     artifacts: FrozenMap<NormalizedPackageName, Box<VersionArtifacts>>,
 
+    /// The index that the artifacts in `artifacts` were listed on, by the url of the artifact
+    artifact_index_urls: FrozenMap<Url, Box<Url>>,
+
     /// Cache to locally built wheels
+    #[cfg(feature = "sdist")]
     local_wheel_cache: WheelCache,
 
     /// Reference to the cache directory for all caches
     cache_dir: PathBuf,
+
+    /// Additional kinds of artifacts that are recognized next to wheels and sdists
+    artifact_registry: ArtifactRegistry,
+
+    /// Skips the artifacts that can never be installed in the environment this instance is used for
+    artifact_filter: ArtifactFilter,
+
+    /// Receives the progress of downloads and builds
+    reporter: Option<Arc<dyn Reporter>>,
+
+    /// Warns about requested packages whose name looks like the name of a popular package
+    typosquatting_check: Option<TyposquattingCheck>,
+
+    /// Blocks packages or versions of packages from being selected during resolution
+    package_policy: Option<Arc<dyn PackagePolicy>>,
+
+    /// Collects the metrics of the requests, metadata and builds
+    metrics: Arc<Metrics>,
+
+    /// How the repositories of git dependencies are accessed over ssh
+    git_auth: GitAuth,
+
+    /// Verifies the contents of artifacts that are read from the cache against their hash as well
+    require_hashes: bool,
 }
 
 /// Type of request to get from the `available_artifacts` function.
@@ -88,12 +142,15 @@ impl PackageDb {
         client: ClientWithMiddleware,
         cache_dir: &Path,
     ) -> miette::Result<Self> {
+        let metrics = Arc::new(Metrics::default());
         let http = Http::new(
             client,
             FileStore::new(&cache_dir.join("http")).into_diagnostic()?,
-        );
+        )
+        .with_metrics(metrics.clone());
 
         let metadata_cache = FileStore::new(&cache_dir.join("metadata")).into_diagnostic()?;
+        #[cfg(feature = "sdist")]
         let local_wheel_cache = WheelCache::new(cache_dir.join("local_wheels"));
 
         Ok(Self {
@@ -101,17 +158,208 @@ impl PackageDb {
             sources: package_sources,
             metadata_cache,
             artifacts: Default::default(),
+            artifact_index_urls: Default::default(),
+            #[cfg(feature = "sdist")]
             local_wheel_cache,
             cache_dir: cache_dir.to_owned(),
+            artifact_registry: ArtifactRegistry::default(),
+            artifact_filter: ArtifactFilter::default(),
+            reporter: None,
+            typosquatting_check: None,
+            package_policy: None,
+            metrics,
+            git_auth: GitAuth::default(),
+            require_hashes: false,
         })
     }
 
+    /// Sets the registry of custom artifact kinds that should be recognized next to wheels and
+    /// sdists.
+    pub fn with_artifact_registry(mut self, artifact_registry: ArtifactRegistry) -> Self {
+        self.artifact_registry = artifact_registry;
+        self
+    }
+
+    /// Skips the artifacts that are rejected by `filter` when the available artifacts of a package
+    /// are read, e.g. the wheels for other platforms. This speeds up working with packages that
+    /// publish many artifacts, but the instance can then only be used for environments that are
+    /// accepted by the filter.
+    pub fn with_artifact_filter(mut self, filter: ArtifactFilter) -> Self {
+        self.artifact_filter = filter;
+        self
+    }
+
+    /// Verifies the contents of every artifact against its sha256 hash before it is used, like
+    /// `pip install --require-hashes`. Without this only artifacts that are downloaded are verified,
+    /// not the ones that are read from the cache or from a local directory.
+    pub fn with_require_hashes(mut self, require_hashes: bool) -> Self {
+        self.require_hashes = require_hashes;
+        self
+    }
+
+    /// Sets how the HTTP cache is used for every request, e.g. to work offline.
+    pub fn with_cache_setting(mut self, cache_setting: CacheSetting) -> Self {
+        self.http = self.http.with_cache_setting(cache_setting);
+        self
+    }
+
+    /// Returns how the HTTP cache is used for every request
+    pub fn cache_setting(&self) -> CacheSetting {
+        self.http.cache_setting()
+    }
+
+    /// Limits how many requests to the index, artifact downloads and range requests included, are
+    /// performed at the same time, in total and to a single host.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.http = self.http.with_concurrency_limits(limits);
+        self
+    }
+
+    /// Interrupts the operations of this instance when `cancellation` is cancelled, e.g. when the
+    /// process receives an interrupt signal. Requests and downloads that are in flight fail, their
+    /// partially downloaded bodies are discarded instead of being written to the cache, resolutions
+    /// stop and no new wheels are built from source. Build environments of builds that are
+    /// interrupted are deleted or saved according to
+    /// [`crate::resolve::solve_options::OnWheelBuildFailure`].
+    pub fn with_cancellation_token(mut self, cancellation: CancellationToken) -> Self {
+        self.http = self.http.with_cancellation_token(cancellation);
+        self
+    }
+
+    /// Returns the token that interrupts the operations of this instance when it is cancelled.
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        self.http.cancellation_token()
+    }
+
+    /// Verifies the pages of the index and the artifacts that are covered by the TUF metadata of
+    /// `repository`. Metadata of artifacts is then always read from the verified artifacts.
+    pub fn with_tuf_repository(mut self, repository: TufRepository) -> Self {
+        let verifier = TufVerifier::new(repository, &self.cache_dir);
+        self.http = self.http.with_tuf(Some(Arc::new(verifier)));
+        self
+    }
+
+    /// Authenticates the requests to GitHub and GitLab with `tokens`, which allows direct
+    /// requirements on the archives and release assets of private repositories.
+    pub fn with_forge_tokens(mut self, tokens: ForgeTokens) -> Self {
+        self.http = self.http.with_forge_tokens(tokens);
+        self
+    }
+
+    /// Sets how the `git` commands that clone the repositories of git dependencies authenticate
+    /// over ssh, e.g. with a key file or an ssh-agent.
+    #[cfg(feature = "git")]
+    pub fn with_git_auth(mut self, auth: GitAuth) -> Self {
+        self.git_auth = auth;
+        self
+    }
+
+    /// Sets the reporter that is notified about the progress of downloads, and of builds by a
+    /// [`WheelBuilder`] that uses this instance.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Warns about the top-level requirements of a resolution whose name looks like the name of a
+    /// popular package, before any of them is fetched. See [`PackageDb::check_typosquatting`].
+    pub fn with_typosquatting_check(mut self, check: TyposquattingCheck) -> Self {
+        self.typosquatting_check = Some(check);
+        self
+    }
+
+    /// Blocks the versions of packages that are denied by `policy` from being selected when
+    /// resolving with this instance, see [`crate::resolve::policy`].
+    pub fn with_package_policy(mut self, policy: Arc<dyn PackagePolicy>) -> Self {
+        self.package_policy = Some(policy);
+        self
+    }
+
+    /// Returns the policy that is consulted when resolving with this instance, if any.
+    pub fn package_policy(&self) -> Option<&Arc<dyn PackagePolicy>> {
+        self.package_policy.as_ref()
+    }
+
+    /// Records the metrics of this instance in `metrics` instead of in an instance of its own,
+    /// e.g. to combine the metrics of multiple package databases.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.http = self.http.with_metrics(metrics.clone());
+        self.metrics = metrics;
+        self
+    }
+
+    /// Returns the metrics of the requests, metadata, builds and installs that were performed
+    /// with this instance, see [`crate::metrics`].
+    pub fn metrics(&self) -> &Arc<Metrics> {
+        &self.metrics
+    }
+
+    /// Returns a warning for every requirement on a package whose name looks like the name of a
+    /// popular package, if the check was enabled with [`PackageDb::with_typosquatting_check`].
+    /// Requirements on a direct url are not checked.
+    pub fn check_typosquatting<'a>(
+        &self,
+        requirements: impl IntoIterator<Item = &'a Requirement>,
+    ) -> Vec<TyposquattingWarning> {
+        let Some(check) = &self.typosquatting_check else {
+            return Vec::new();
+        };
+        requirements
+            .into_iter()
+            .filter(|requirement| !matches!(requirement.version_or_url, Some(VersionOrUrl::Url(_))))
+            .filter_map(|requirement| PackageName::from_str(&requirement.name).ok())
+            .filter_map(|name| check.check(&name.into()))
+            .collect()
+    }
+
+    /// Returns the url of the index that `artifact` of the package `name` is listed on, or `None`
+    /// if it is not known or if the artifact is not from an index, e.g. from a find-links
+    /// directory or a direct url. Artifacts that were not listed by this instance, like those of a
+    /// lockfile, are attributed to the index of the package if it has only one.
+    pub fn artifact_index_url(
+        &self,
+        name: &NormalizedPackageName,
+        artifact: &ArtifactInfo,
+    ) -> Option<Url> {
+        if artifact.is_direct_url || artifact.url.scheme() == "file" {
+            return None;
+        }
+        if let Some(index_url) = self.artifact_index_urls.get(&artifact.url) {
+            return Some(index_url.clone());
+        }
+        match self.sources.index_url(name).as_slice() {
+            [index_url] => Some((*index_url).clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the reporter that is notified about the progress of downloads and builds
+    pub fn reporter(&self) -> Option<&Arc<dyn Reporter>> {
+        self.reporter.as_ref()
+    }
+
+    /// Returns the registry of custom artifact kinds
+    pub fn artifact_registry(&self) -> &ArtifactRegistry {
+        &self.artifact_registry
+    }
+
+    /// Returns the indexes and local directories that packages are found in
+    pub fn sources(&self) -> &PackageSources {
+        &self.sources
+    }
+
     /// Returns the cache directory
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
 
+    /// Returns the client that is used for all requests, including its middleware
+    pub fn client(&self) -> &ClientWithMiddleware {
+        &self.http.client
+    }
+
     /// Returns the local wheel cache
+    #[cfg(feature = "sdist")]
     pub fn local_wheel_cache(&self) -> &WheelCache {
         &self.local_wheel_cache
     }
@@ -128,14 +376,28 @@ impl PackageDb {
                 }
                 // Start downloading the information for each url.
                 let http = self.http.clone();
+                let registry = &self.artifact_registry;
+                let filter = &self.artifact_filter;
                 let index_urls = self.sources.index_url(&p);
 
                 let urls = index_urls
                     .into_iter()
-                    .map(|url| url.join(&format!("{}/", p.as_str())).expect("invalid url"))
+                    .map(|index_url| {
+                        let url = index_url
+                            .join(&format!("{}/", p.as_str()))
+                            .expect("invalid url");
+                        (index_url.clone(), url)
+                    })
                     .collect_vec();
                 let request_iter = stream::iter(urls)
-                    .map(|url| fetch_simple_api(&http, url))
+                    .map(|(index_url, url)| {
+                        let http = http.clone();
+                        async move {
+                            fetch_simple_api(&http, url, registry, filter)
+                                .await
+                                .map(|response| response.map(|response| (index_url, response)))
+                        }
+                    })
                     .buffer_unordered(10)
                     .filter_map(|result| async { result.transpose() });
 
@@ -144,7 +406,39 @@ impl PackageDb {
                 // Add all the incoming results to the set of results
                 let mut result = VersionArtifacts::default();
                 while let Some(response) = request_iter.next().await {
-                    for artifact in response?.files {
+                    let (index_url, response) = response?;
+                    for artifact in response.files {
+                        if self.artifact_index_urls.get(&artifact.url).is_none() {
+                            self.artifact_index_urls
+                                .insert(artifact.url.clone(), Box::new(index_url.clone()));
+                        }
+                        result
+                            .entry(PypiVersion::Version {
+                                version: artifact.filename.version().clone(),
+                                package_allows_prerelease: artifact
+                                    .filename
+                                    .version()
+                                    .any_prerelease(),
+                            })
+                            .or_default()
+                            .push(Arc::new(artifact));
+                    }
+                }
+
+                // Add the artifacts from the local find-links directories
+                for dir in self.sources.find_links() {
+                    let artifacts = find_links_artifacts(dir, &p, registry)
+                        .into_diagnostic()
+                        .map_err(|e| {
+                            e.wrap_err(format!(
+                                "failed to read the artifacts in '{}'",
+                                dir.display()
+                            ))
+                        })?;
+                    for artifact in artifacts
+                        .into_iter()
+                        .filter(|artifact| filter.accepts(artifact, registry))
+                    {
                         result
                             .entry(PypiVersion::Version {
                                 version: artifact.filename.version().clone(),
@@ -182,15 +476,42 @@ impl PackageDb {
 
     /// Returns the metadata from a set of artifacts. This function assumes that metadata is
     /// consistent for all artifacts of a single version.
+    #[tracing::instrument(skip_all, fields(package, version, cache_hit))]
     pub async fn get_metadata<'a, A: Borrow<ArtifactInfo>>(
         &self,
         artifacts: &'a [A],
         wheel_builder: Option<&WheelBuilder>,
+    ) -> miette::Result<Option<(&'a A, WheelCoreMetadata)>> {
+        if let Some(artifact_info) = artifacts.first() {
+            let filename = &artifact_info.borrow().filename;
+            let span = tracing::Span::current();
+            span.record(
+                "package",
+                tracing::field::display(filename.distribution_name().as_str()),
+            );
+            span.record("version", tracing::field::display(filename.version()));
+        }
+        self.metrics.metadata_requests.inc();
+        let result = self.find_metadata(artifacts, wheel_builder).await?;
+        if let (Some(reporter), Some((artifact_info, _))) = (&self.reporter, &result) {
+            reporter.on_metadata_fetched((*artifact_info).borrow());
+        }
+        Ok(result)
+    }
+
+    /// Returns the metadata from a set of artifacts from the first source that has it, see
+    /// [`PackageDb::get_metadata`].
+    async fn find_metadata<'a, A: Borrow<ArtifactInfo>>(
+        &self,
+        artifacts: &'a [A],
+        wheel_builder: Option<&WheelBuilder>,
     ) -> miette::Result<Option<(&'a A, WheelCoreMetadata)>> {
         // Check if we already have information about any of the artifacts cached.
         // Return if we do
         for artifact_info in artifacts.iter() {
             if let Some(metadata_bytes) = self.metadata_from_cache(artifact_info.borrow()).await {
+                tracing::Span::current().record("cache_hit", true);
+                self.metrics.metadata_cache_hits.inc();
                 return Ok(Some((
                     artifact_info,
                     WheelCoreMetadata::try_from(metadata_bytes.as_slice()).into_diagnostic()?,
@@ -203,8 +524,11 @@ impl PackageDb {
         // In this case we can just return it
         let result = self.metadata_for_cached_artifacts(artifacts).await?;
         if result.is_some() {
+            tracing::Span::current().record("cache_hit", true);
+            self.metrics.metadata_cache_hits.inc();
             return Ok(result);
         }
+        tracing::Span::current().record("cache_hit", false);
 
         // We have exhausted all options to read the metadata from the cache. We'll have to hit the
         // network to get to the information.
@@ -215,6 +539,12 @@ impl PackageDb {
             return Ok(result);
         }
 
+        // Custom artifacts are converted to wheels to get to their metadata
+        let result = self.get_metadata_custom(artifacts).await?;
+        if result.is_some() {
+            return Ok(result);
+        }
+
         // No wheels found with metadata, try to get metadata from sdists
         // by building them or using the appropriate hooks
         if let Some(wheel_builder) = wheel_builder {
@@ -235,6 +565,7 @@ impl PackageDb {
 
     /// Opens the specified artifact info. Downloads the artifact data from the remote location if
     /// the information is not already cached.
+    #[tracing::instrument(skip_all, fields(artifact = %artifact_info.filename))]
     #[async_recursion]
     pub async fn get_wheel(
         &self,
@@ -246,6 +577,8 @@ impl PackageDb {
             if let Some(builder) = builder {
                 let response = super::direct_url::fetch_artifact_and_metadata_by_direct_url(
                     &self.http,
+                    &self.git_auth,
+                    &self.cache_dir,
                     artifact_info.filename.distribution_name(),
                     artifact_info.url.clone(),
                     builder,
@@ -290,6 +623,7 @@ impl PackageDb {
                     };
                     Some(DirectUrlJson {
                         url: artifact_info.url.clone(),
+                        subdirectory: None,
                         source: DirectUrlSource::Archive {
                             hashes: direct_url_hash,
                         },
@@ -304,6 +638,14 @@ impl PackageDb {
             }
         }
 
+        // Custom artifacts are converted into a wheel
+        if let Some(name) = artifact_info.filename.as_custom() {
+            let wheel = self
+                .get_cached_custom_artifact(name, artifact_info, CacheMode::Default)
+                .await?;
+            return Ok((wheel, None));
+        }
+
         // Otherwise just retrieve the wheel
         let cached_whl = self
             .get_cached_artifact::<Wheel>(artifact_info, CacheMode::Default)
@@ -326,6 +668,8 @@ impl PackageDb {
 
         let response = super::direct_url::fetch_artifact_and_metadata_by_direct_url(
             &self.http,
+            &self.git_auth,
+            &self.cache_dir,
             p.clone(),
             url,
             wheel_builder,
@@ -349,6 +693,13 @@ impl PackageDb {
         Some(bytes)
     }
 
+    /// Returns the raw `METADATA` file of an artifact if it was previously retrieved with
+    /// [`Self::get_metadata`]. This can be used to read the fields that are not part of
+    /// [`WheelCoreMetadata`], see [`crate::types::PackageDescription`].
+    pub async fn cached_metadata_bytes(&self, artifact_info: &ArtifactInfo) -> Option<Vec<u8>> {
+        self.metadata_from_cache(artifact_info).await
+    }
+
     /// Writes the metadata for the given artifact into the cache. If the metadata already exists
     /// its not overwritten.
     async fn put_metadata_in_cache(&self, ai: &ArtifactInfo, blob: &[u8]) -> miette::Result<()> {
@@ -439,21 +790,29 @@ impl PackageDb {
         for artifact_info in wheels {
             let ai = artifact_info.borrow();
 
+            // When offline only the cached wheel itself can be used, the same goes for when
+            // artifacts are verified because only whole artifacts can be verified
+            let offline = self.cache_setting() == CacheSetting::Offline || self.http.verifies_tuf();
+
             // Retrieve the metadata instead of the entire wheel
             // If the dist-info is available separately, we can use that instead
-            if ai.dist_info_metadata.available {
+            if ai.dist_info_metadata.available && !offline {
                 return Ok(Some(self.get_pep658_metadata(artifact_info).await?));
             }
 
             // Try to load the data by sparsely reading the artifact (if supported)
-            if let Some(metadata) = self.get_lazy_metadata_wheel(ai).await? {
-                return Ok(Some((artifact_info, metadata)));
+            if !offline {
+                if let Some(metadata) = self.get_lazy_metadata_wheel(ai).await? {
+                    return Ok(Some((artifact_info, metadata)));
+                }
             }
 
             let metadata = if ai.is_direct_url {
                 if let Some(wheel_builder) = wheel_builder {
                     let response = super::direct_url::fetch_artifact_and_metadata_by_direct_url(
                         &self.http,
+                        &self.git_auth,
+                        &self.cache_dir,
                         ai.filename.distribution_name(),
                         ai.url.clone(),
                         wheel_builder,
@@ -492,6 +851,39 @@ impl PackageDb {
         Ok(None)
     }
 
+    async fn get_metadata_custom<'a, A: Borrow<ArtifactInfo>>(
+        &self,
+        artifacts: &'a [A],
+    ) -> miette::Result<Option<(&'a A, WheelCoreMetadata)>> {
+        for artifact_info in artifacts.iter() {
+            let ai = artifact_info.borrow();
+            let Some(name) = ai.filename.as_custom() else {
+                continue;
+            };
+
+            let metadata = self
+                .get_cached_custom_artifact(name, ai, CacheMode::Default)
+                .await
+                .and_then(|wheel| wheel.metadata());
+
+            match metadata {
+                Ok((blob, metadata)) => {
+                    self.put_metadata_in_cache(ai, &blob).await?;
+                    return Ok(Some((artifact_info, metadata)));
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "Error reading metadata from artifact '{}' skipping ({:?})",
+                        ai.filename,
+                        err
+                    );
+                    continue;
+                }
+            }
+        }
+        Ok(None)
+    }
+
     async fn get_metadata_sdists<'a, A: Borrow<ArtifactInfo>>(
         &self,
         artifacts: &'a [A],
@@ -509,6 +901,8 @@ impl PackageDb {
             let metadata = if artifact_info.is_direct_url {
                 let response = super::direct_url::fetch_artifact_and_metadata_by_direct_url(
                     &self.http,
+                    &self.git_auth,
+                    &self.cache_dir,
                     artifact_info.filename.distribution_name(),
                     artifact_info.url.clone(),
                     wheel_builder,
@@ -573,6 +967,8 @@ impl PackageDb {
                 });
             let response = super::direct_url::fetch_artifact_and_metadata_by_direct_url(
                 &self.http,
+                &self.git_auth,
+                &self.cache_dir,
                 stree_name.distribution.clone(),
                 artifact_info.url.clone(),
                 wheel_builder,
@@ -607,12 +1003,19 @@ impl PackageDb {
         &self,
         artifact_info: &ArtifactInfo,
     ) -> miette::Result<Option<WheelCoreMetadata>> {
+        // Local files are cheap to read completely
+        if artifact_info.url.scheme() == "file" {
+            return Ok(None);
+        }
+
         tracing::info!(url=%artifact_info.url, "lazy reading artifact");
 
         // Check if the artifact is the same type as the info.
         let name = WheelFilename::try_as(&artifact_info.filename)
             .expect("the specified artifact does not refer to type requested to read");
 
+        // The range requests count against the concurrency limits until the metadata was read
+        let _permit = self.http.acquire_permit(&artifact_info.url).await;
         if let Ok((mut reader, _)) = AsyncHttpRangeReader::new(
             self.http.client.clone(),
             artifact_info.url.clone(),
@@ -702,28 +1105,250 @@ impl PackageDb {
                 )
             });
 
-        // Get the contents of the artifact
-        let artifact_bytes = self
-            .http
-            .request(
-                artifact_info.url.clone(),
-                Method::GET,
-                HeaderMap::default(),
-                cache_mode,
-            )
+        let bytes = self.get_artifact_bytes(artifact_info, cache_mode).await?;
+        A::from_bytes(name.clone(), bytes)
+    }
+
+    /// Writes the artifact to the file at `destination`. Downloads the artifact data from the
+    /// remote location if it is not already cached.
+    pub async fn download_artifact(
+        &self,
+        artifact_info: &ArtifactInfo,
+        destination: &Path,
+    ) -> miette::Result<()> {
+        let mut bytes = self
+            .get_artifact_bytes(artifact_info, CacheMode::Default)
+            .await?;
+        let size = bytes.seek(SeekFrom::End(0)).into_diagnostic()?;
+        bytes.rewind().into_diagnostic()?;
+        ensure_available_space(destination, size, || {
+            format!("write {}", destination.display())
+        })?;
+        let mut file = fs::File::create(destination).into_diagnostic()?;
+        std::io::copy(&mut bytes, &mut file).into_diagnostic()?;
+        Ok(())
+    }
+
+    /// Prefetches the index pages, the metadata and optionally the artifacts of a set of packages
+    /// into the cache, at most `options` concurrency packages at a time. Afterwards resolving (and
+    /// with artifacts also installing) these packages does not have to wait for the network and
+    /// also works with [`CacheSetting::Offline`].
+    ///
+    /// Metadata is only prefetched from wheels, source distributions are not built.
+    pub async fn warm(
+        &self,
+        request: impl Into<WarmRequest>,
+        options: &WarmOptions,
+    ) -> miette::Result<WarmSummary> {
+        let packages = match request.into() {
+            WarmRequest::Names(names) => names.into_iter().map(|name| (name, None)).collect_vec(),
+            WarmRequest::Pinned(packages) => packages
+                .into_iter()
+                .filter(|package| package.url.is_none())
+                .map(|package| (package.name.clone(), Some(package)))
+                .collect_vec(),
+        };
+
+        let summaries = stream::iter(packages)
+            .map(|(name, pinned)| self.warm_package(name, pinned, options))
+            .buffer_unordered(options.concurrency.get());
+        pin_mut!(summaries);
+
+        let mut summary = WarmSummary::default();
+        while let Some(package_summary) = summaries.next().await {
+            summary += package_summary?;
+        }
+        Ok(summary)
+    }
+
+    /// Prefetches a single package for [`Self::warm`].
+    async fn warm_package(
+        &self,
+        name: NormalizedPackageName,
+        pinned: Option<PinnedPackage>,
+        options: &WarmOptions,
+    ) -> miette::Result<WarmSummary> {
+        let available = self
+            .available_artifacts(ArtifactRequest::FromIndex(name))
             .await?;
+        let mut summary = WarmSummary {
+            packages: 1,
+            ..WarmSummary::default()
+        };
+
+        // Select the artifacts of the pinned version, preferring the ones listed by the index over
+        // the locked ones so their urls and hashes are up to date. Without a pin the latest
+        // release is selected.
+        let artifacts = match pinned {
+            Some(pinned) => pinned
+                .artifacts
+                .into_iter()
+                .map(|locked| {
+                    available
+                        .values()
+                        .flatten()
+                        .find(|artifact| artifact.filename == locked.filename)
+                        .cloned()
+                        .unwrap_or(locked)
+                })
+                .collect_vec(),
+            None => available
+                .iter()
+                .find(|(version, _)| !version.any_prerelease())
+                .or_else(|| available.first())
+                .map(|(_, artifacts)| artifacts.clone())
+                .unwrap_or_default(),
+        };
 
-        // Turn the response into a seekable response.
-        let bytes = artifact_bytes
-            .into_body()
-            .into_local()
-            .await
-            .into_diagnostic()?;
-        A::from_bytes(name.clone(), bytes)
+        let wheels = artifacts
+            .iter()
+            .filter(|artifact| artifact.is::<Wheel>())
+            .cloned()
+            .collect_vec();
+        if self.get_metadata(&wheels, None).await?.is_some() {
+            summary.metadata += 1;
+        }
+
+        if options.artifacts {
+            // Local artifacts are never cached, there is nothing to prefetch.
+            for artifact in artifacts.iter().filter(|a| a.url.scheme() != "file") {
+                self.get_artifact_bytes(artifact, CacheMode::Default)
+                    .await?;
+                summary.artifacts += 1;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Opens a custom artifact and converts it into a wheel using the kind it was registered with.
+    /// Downloads the artifact data from the remote location if it is not already cached.
+    async fn get_cached_custom_artifact(
+        &self,
+        name: &CustomArtifactName,
+        artifact_info: &ArtifactInfo,
+        cache_mode: CacheMode,
+    ) -> miette::Result<Wheel> {
+        let bytes = self.get_artifact_bytes(artifact_info, cache_mode).await?;
+        self.artifact_registry.to_wheel(name, bytes)
+    }
+
+    /// Returns the (cached) contents of an artifact. If the sha256 hash of the artifact is known
+    /// the contents are verified against it when they are downloaded, or always when hashes are
+    /// required.
+    #[tracing::instrument(name = "get_artifact", skip_all, fields(artifact = %artifact_info.filename, bytes))]
+    async fn get_artifact_bytes(
+        &self,
+        artifact_info: &ArtifactInfo,
+        cache_mode: CacheMode,
+    ) -> miette::Result<Box<dyn ReadAndSeek + Send>> {
+        // Artifacts from the cache or from a local directory are not hashed again unless hashes
+        // are required
+        let mut downloaded = false;
+        let mut bytes: Box<dyn ReadAndSeek + Send> = if artifact_info.url.scheme() == "file" {
+            // Artifacts from find-links directories are read directly from disk, large ones are
+            // memory-mapped
+            let path = artifact_info
+                .url
+                .to_file_path()
+                .map_err(|_| miette::miette!("invalid file url '{}'", artifact_info.url))?;
+            Box::new(LocalFile::open(&path).into_diagnostic()?)
+        } else {
+            // Fail before the download is started if the size of the artifact is listed by the
+            // index and it does not fit in the cache
+            if let Some(size) = artifact_info.size {
+                if self.cache_setting() != CacheSetting::Offline {
+                    ensure_available_space(&self.cache_dir, size, || {
+                        format!("download {}", artifact_info.filename)
+                    })?;
+                }
+            }
+
+            // Get the contents of the artifact
+            let artifact_bytes = self
+                .http
+                .request_with_reporter(
+                    artifact_info.url.clone(),
+                    Method::GET,
+                    HeaderMap::default(),
+                    cache_mode,
+                    self.reporter.as_ref(),
+                )
+                .await?;
+            downloaded = !matches!(
+                artifact_bytes.extensions().get::<CacheStatus>(),
+                Some(CacheStatus::Fresh | CacheStatus::StaleButValidated)
+            );
+
+            // Turn the response into a seekable response.
+            artifact_bytes
+                .into_body()
+                .into_local()
+                .await
+                .into_diagnostic()?
+        };
+
+        let size = bytes.seek(SeekFrom::End(0)).into_diagnostic()?;
+        bytes.rewind().into_diagnostic()?;
+        tracing::Span::current().record("bytes", size);
+
+        let expected = artifact_info
+            .hashes
+            .as_ref()
+            .and_then(|hashes| hashes.sha256);
+        match expected.filter(|_| downloaded || self.require_hashes) {
+            Some(expected) => self.verify_artifact(artifact_info, bytes, expected).await,
+            None => Ok(bytes),
+        }
+    }
+
+    /// Verifies the contents of an artifact against its sha256 hash. If they do not match the
+    /// contents are removed from the cache.
+    async fn verify_artifact(
+        &self,
+        artifact_info: &ArtifactInfo,
+        mut bytes: Box<dyn ReadAndSeek + Send>,
+        expected: rattler_digest::Sha256Hash,
+    ) -> miette::Result<Box<dyn ReadAndSeek + Send>> {
+        // Hashing a large artifact takes a while, it should not block the runtime
+        let (bytes, actual) = tokio::task::spawn_blocking(move || {
+            let mut hasher = Sha256::default();
+            std::io::copy(&mut bytes, &mut hasher)?;
+            bytes.rewind()?;
+            Ok::<_, std::io::Error>((bytes, hasher.finalize()))
+        })
+        .await
+        .map_err(|e| match e.try_into_panic() {
+            Ok(panic) => std::panic::resume_unwind(panic),
+            Err(_) => miette::miette!("hashing {} was cancelled", artifact_info.filename),
+        })?
+        .into_diagnostic()?;
+
+        if actual != expected {
+            if artifact_info.url.scheme() != "file" {
+                self.http
+                    .remove_cached(&artifact_info.url)
+                    .await
+                    .into_diagnostic()?;
+            }
+            return Err(ArtifactHashMismatch {
+                filename: artifact_info.filename.to_string(),
+                expected: format!("{expected:x}"),
+                actual: format!("{actual:x}"),
+            }
+            .into());
+        }
+        Ok(bytes)
     }
 }
 
-async fn fetch_simple_api(http: &Http, url: Url) -> miette::Result<Option<ProjectInfo>> {
+#[tracing::instrument(name = "fetch_index_page", skip_all, fields(url = %url))]
+async fn fetch_simple_api(
+    http: &Http,
+    url: Url,
+    registry: &ArtifactRegistry,
+    filter: &ArtifactFilter,
+) -> miette::Result<Option<ProjectInfo>> {
     let mut headers = HeaderMap::new();
     headers.insert(CACHE_CONTROL, HeaderValue::from_static("max-age=0"));
 
@@ -764,9 +1389,13 @@ async fn fetch_simple_api(http: &Http, url: Url) -> miette::Result<Option<Projec
         content_type.type_().as_str(),
         content_type.subtype().as_str(),
     ) {
-        ("text", "html") => {
-            parse_project_info_html(&url, std::str::from_utf8(&bytes).into_diagnostic()?).map(Some)
-        }
+        ("text", "html") => parse_project_info_html_with_filter(
+            &url,
+            std::str::from_utf8(&bytes).into_diagnostic()?,
+            registry,
+            filter,
+        )
+        .map(Some),
         _ => miette::bail!(
             "simple API page expected Content-Type: text/html, but got {}",
             &content_type
@@ -777,7 +1406,7 @@ async fn fetch_simple_api(http: &Http, url: Url) -> miette::Result<Option<Projec
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::types::PackageName;
+    use crate::types::{ArtifactHashes, ArtifactName, PackageName};
     use reqwest::Client;
     use tempfile::TempDir;
     use tokio::task::JoinHandle;
@@ -974,6 +1603,164 @@ mod test {
 
         let (_artifact, _metadata) = package_db.get_pep658_metadata(artifact_info).await.unwrap();
     }
+
+    async fn get_file(axum::extract::Path(file): axum::extract::Path<String>) -> impl IntoResponse {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels")
+            .join(file);
+        match std::fs::read(path) {
+            Ok(bytes) => bytes.into_response(),
+            Err(_) => axum::http::StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warm() -> miette::Result<()> {
+        let addr = SocketAddr::new([127, 0, 0, 1].into(), 0);
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .into_diagnostic()?;
+        let address = listener.local_addr().into_diagnostic()?;
+        let router = Router::new()
+            .route(
+                "/simple/wordle-python/",
+                get(|| async {
+                    Html(r#"<a href="/files/wordle_python-2.3.32-py3-none-any.whl">wordle</a>"#)
+                }),
+            )
+            .route("/files/:file", get(get_file));
+        let _server = tokio::spawn(axum::serve(listener, router).into_future());
+
+        let index_url: Url = format!("http://{}/simple/", address)
+            .parse()
+            .into_diagnostic()?;
+        let cache_dir = TempDir::new().into_diagnostic()?;
+        let package_db = PackageDb::new(
+            index_url.clone().into(),
+            ClientWithMiddleware::from(Client::new()),
+            cache_dir.path(),
+        )?;
+
+        let name: NormalizedPackageName = "wordle_python".parse::<PackageName>().unwrap().into();
+        let summary = package_db
+            .warm(
+                vec![name.clone()],
+                &WarmOptions::default().with_artifacts(true),
+            )
+            .await?;
+        assert_eq!(
+            summary,
+            WarmSummary {
+                packages: 1,
+                metadata: 1,
+                artifacts: 1,
+            }
+        );
+
+        // Everything that was warmed is available offline
+        let package_db = PackageDb::new(
+            index_url.into(),
+            ClientWithMiddleware::from(Client::new()),
+            cache_dir.path(),
+        )?
+        .with_cache_setting(CacheSetting::Offline);
+        let artifacts = package_db
+            .available_artifacts(ArtifactRequest::FromIndex(name))
+            .await?
+            .values()
+            .flatten()
+            .cloned()
+            .collect_vec();
+        assert_eq!(artifacts.len(), 1);
+        package_db.get_wheel(&artifacts[0], None).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_artifact_hashes() {
+        let addr = SocketAddr::new([127, 0, 0, 1].into(), 0);
+        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = Router::new().route("/files/:file", get(get_file));
+        let _server = tokio::spawn(axum::serve(listener, router).into_future());
+
+        let filename = "wordle_python-2.3.32-py3-none-any.whl";
+        let contents = fs::read(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels")
+                .join(filename),
+        )
+        .unwrap();
+        let name = NormalizedPackageName::from_str("wordle-python").unwrap();
+        let artifact_info = |contents: &[u8]| ArtifactInfo {
+            filename: ArtifactName::from_filename(filename, None, &name).unwrap(),
+            url: format!("http://{address}/files/{filename}")
+                .parse()
+                .unwrap(),
+            is_direct_url: false,
+            hashes: Some(ArtifactHashes {
+                sha256: Some(rattler_digest::compute_bytes_digest::<Sha256>(contents)),
+            }),
+            requires_python: None,
+            dist_info_metadata: Default::default(),
+            yanked: Default::default(),
+            size: None,
+        };
+        let valid = artifact_info(&contents);
+        let invalid = artifact_info(b"something else");
+
+        // Downloaded artifacts are always verified
+        let (cache_dir, package_db) = make_package_db();
+        let err = package_db
+            .get_artifact_bytes(&invalid, CacheMode::Default)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            err.downcast_ref::<ArtifactHashMismatch>().is_some(),
+            "{err}"
+        );
+        package_db
+            .get_artifact_bytes(&valid, CacheMode::Default)
+            .await
+            .unwrap();
+
+        // Artifacts from the cache are only verified when hashes are required
+        let cached_package_db = || {
+            PackageDb::new(
+                Url::parse("https://pypi.org/simple/").unwrap().into(),
+                ClientWithMiddleware::from(Client::new()),
+                cache_dir.path(),
+            )
+            .unwrap()
+            .with_cache_setting(CacheSetting::Offline)
+        };
+        cached_package_db()
+            .get_artifact_bytes(&invalid, CacheMode::OnlyIfCached)
+            .await
+            .unwrap();
+        let package_db = cached_package_db().with_require_hashes(true);
+        package_db
+            .get_artifact_bytes(&valid, CacheMode::OnlyIfCached)
+            .await
+            .unwrap();
+        let err = package_db
+            .get_artifact_bytes(&invalid, CacheMode::OnlyIfCached)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            err.downcast_ref::<ArtifactHashMismatch>().is_some(),
+            "{err}"
+        );
+
+        // Contents that do not match are removed from the cache
+        assert!(package_db
+            .get_artifact_bytes(&valid, CacheMode::OnlyIfCached)
+            .await
+            .is_err());
+    }
 }
 
 #[derive(Debug, Diagnostic)]
@@ -986,3 +1773,13 @@ impl Display for NotCached {
 }
 
 impl std::error::Error for NotCached {}
+
+/// The contents of an artifact do not match the sha256 hash it was published or locked with.
+#[derive(Debug, Error, Diagnostic)]
+#[error("the sha256 hash of {filename} is {actual} but {expected} was expected")]
+#[allow(missing_docs)]
+pub struct ArtifactHashMismatch {
+    pub filename: String,
+    pub expected: String,
+    pub actual: String,
+}
@@ -1,6 +1,7 @@
 use crate::types::NormalizedPackageName;
 use miette::Diagnostic;
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use thiserror::Error;
 use url::Url;
 
@@ -24,6 +25,8 @@ pub struct PackageSourcesBuilder {
     base_source: Url,
     extra_sources: Vec<PackageSource>,
     overrides: BTreeMap<NormalizedPackageName, String>,
+    find_links: Vec<PathBuf>,
+    no_index: bool,
 }
 
 impl PackageSourcesBuilder {
@@ -34,6 +37,8 @@ impl PackageSourcesBuilder {
             base_source: base_index_url,
             extra_sources: Default::default(),
             overrides: Default::default(),
+            find_links: Default::default(),
+            no_index: false,
         }
     }
 
@@ -53,6 +58,20 @@ impl PackageSourcesBuilder {
         self
     }
 
+    /// Add a local directory that contains artifacts, similar to `pip --find-links`. The
+    /// artifacts in the directory are available next to the artifacts from the indexes
+    pub fn with_find_links(mut self, path: impl Into<PathBuf>) -> Self {
+        self.find_links.push(path.into());
+        self
+    }
+
+    /// Do not use any of the indexes, only the artifacts in the find-links directories are
+    /// available
+    pub fn without_index(mut self) -> Self {
+        self.no_index = true;
+        self
+    }
+
     /// Finalize the builder and create a `PackageSources` instance
     pub fn build(&self) -> Result<PackageSources, PackageSourceError> {
         let mut extra_sources_map = BTreeMap::new();
@@ -91,6 +110,8 @@ impl PackageSourcesBuilder {
         Ok(PackageSources {
             index_urls: (index_url, extra_index_urls),
             artifact_to_index,
+            find_links: self.find_links.clone(),
+            no_index: self.no_index,
         })
     }
 }
@@ -100,11 +121,17 @@ impl PackageSourcesBuilder {
 pub struct PackageSources {
     index_urls: (Url, Vec<Url>),
     artifact_to_index: BTreeMap<NormalizedPackageName, usize>,
+    find_links: Vec<PathBuf>,
+    no_index: bool,
 }
 
 impl PackageSources {
     /// Get the index URL for a package
     pub fn index_url(&self, package: &NormalizedPackageName) -> Vec<&Url> {
+        if self.no_index {
+            return Vec::new();
+        }
+
         let maybe_index = self
             .artifact_to_index
             .get(package)
@@ -119,6 +146,11 @@ impl PackageSources {
         }
     }
 
+    /// Get the local directories that contain artifacts
+    pub fn find_links(&self) -> &[PathBuf] {
+        &self.find_links
+    }
+
     /// Get the default (fallback) index URL
     pub fn default_index_url(&self) -> Url {
         self.index_urls.0.clone()
@@ -130,6 +162,8 @@ impl From<Url> for PackageSources {
         PackageSources {
             index_urls: (url, vec![]),
             artifact_to_index: Default::default(),
+            find_links: Default::default(),
+            no_index: false,
         }
     }
 }
@@ -171,4 +205,19 @@ mod tests {
             vec![&base_url, &foo_url, &bar_url]
         );
     }
+
+    #[test]
+    fn test_find_links_without_index() {
+        let base_url = Url::parse("https://example.com").unwrap();
+        let name = NormalizedPackageName::from(PackageName::from_str("pkg").unwrap());
+
+        let sources = PackageSourcesBuilder::new(base_url)
+            .with_find_links("wheelhouse")
+            .without_index()
+            .build()
+            .unwrap();
+
+        assert!(sources.index_url(&name).is_empty());
+        assert_eq!(sources.find_links(), [PathBuf::from("wheelhouse")]);
+    }
 }
@@ -0,0 +1,1394 @@
+//! Verification of the pages of an index and of its artifacts with
+//! [TUF](https://theupdateframework.io) metadata as proposed for package indexes by
+//! [PEP 458](https://peps.python.org/pep-0458/).
+//!
+//! The metadata of a repository is refreshed once per [`super::PackageDb`] following the client
+//! workflow of the TUF specification, starting from a root that is distributed out of band. Every
+//! response below one of the target base urls of the repository is then checked against the length
+//! and hashes that the targets metadata lists for it. The verified metadata is kept in the cache,
+//! metadata that is older than what was seen before is rejected to prevent rollback attacks.
+
+use super::http::{CacheMode, CacheSetting, Http, HttpRequestError};
+use crate::cache::CacheBucket;
+use crate::utils::ReadAndSeek;
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use fs_err as fs;
+use rattler_digest::digest::Digest;
+use rattler_digest::Sha256;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha512;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use url::Url;
+
+/// The maximum number of root versions that are applied in a single refresh.
+const MAX_ROOT_ROTATIONS: u64 = 256;
+
+/// The maximum depth of the delegations that are searched for a target.
+const MAX_DELEGATION_DEPTH: usize = 32;
+
+/// A repository that serves TUF metadata for its index pages and artifacts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TufRepository {
+    metadata_url: Url,
+    target_base_urls: Vec<Url>,
+    trusted_root: PathBuf,
+}
+
+impl TufRepository {
+    /// Constructs a repository whose metadata is served from `metadata_url`, the directory that
+    /// contains `timestamp.json`. The `trusted_root` is a `root.json` file that is distributed out
+    /// of band, it is only used until a newer root has been verified.
+    pub fn new(metadata_url: Url, trusted_root: impl Into<PathBuf>) -> Self {
+        Self {
+            metadata_url: with_trailing_slash(metadata_url),
+            target_base_urls: Vec::new(),
+            trusted_root: trusted_root.into(),
+        }
+    }
+
+    /// Adds a url that the target paths in the metadata are relative to, e.g. the host of the
+    /// index and the host the artifacts are downloaded from. Defaults to the origin of the metadata
+    /// url.
+    pub fn with_target_base_url(mut self, url: Url) -> Self {
+        self.target_base_urls.push(with_trailing_slash(url));
+        self
+    }
+
+    /// Returns the url of the directory that contains the metadata.
+    pub fn metadata_url(&self) -> &Url {
+        &self.metadata_url
+    }
+
+    /// Returns the location of the root that is distributed out of band.
+    pub fn trusted_root(&self) -> &Path {
+        &self.trusted_root
+    }
+
+    /// Returns the urls that the target paths in the metadata are relative to.
+    pub fn target_base_urls(&self) -> Vec<Url> {
+        if self.target_base_urls.is_empty() {
+            let mut origin = self.metadata_url.clone();
+            origin.set_path("/");
+            origin.set_query(None);
+            vec![origin]
+        } else {
+            self.target_base_urls.clone()
+        }
+    }
+
+    /// Returns the target path of `url` or `None` if the url is not below one of the target base
+    /// urls. A url of a directory, like the page of a project in the simple API, refers to its
+    /// `index.html`.
+    pub fn target_path(&self, url: &Url) -> Option<String> {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        url.set_query(None);
+        self.target_base_urls().iter().find_map(|base| {
+            let path = url.as_str().strip_prefix(base.as_str())?;
+            if path.is_empty() || path.ends_with('/') {
+                Some(format!("{path}index.html"))
+            } else {
+                Some(path.to_string())
+            }
+        })
+    }
+}
+
+fn with_trailing_slash(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+    url
+}
+
+/// An error that can occur when verifying TUF metadata or a target.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum TufError {
+    #[error("failed to access {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to download the TUF metadata {0}")]
+    FetchError(Url, #[source] Box<HttpRequestError>),
+
+    #[error("the repository does not serve the TUF metadata {0}")]
+    MissingMetadata(Url),
+
+    #[error("the TUF {0} metadata is invalid")]
+    InvalidMetadata(String, #[source] serde_json::Error),
+
+    #[error("expected TUF {expected} metadata but found '{found}'")]
+    UnexpectedType { expected: String, found: String },
+
+    #[error(
+        "the TUF {role} metadata is signed by {valid} trusted key(s), but {threshold} are required"
+    )]
+    ThresholdNotMet {
+        role: String,
+        valid: usize,
+        threshold: u64,
+    },
+
+    #[error("the TUF {role} metadata has an invalid expiration date '{expires}'")]
+    InvalidExpiration { role: String, expires: String },
+
+    #[error("the TUF {role} metadata expired at {expires}, this could be a freeze attack")]
+    Expired { role: String, expires: String },
+
+    #[error("the TUF {role} metadata was downgraded from version {trusted} to {fetched}, this could be a rollback attack")]
+    Downgrade {
+        role: String,
+        trusted: u64,
+        fetched: u64,
+    },
+
+    #[error("expected version {expected} of the TUF {role} metadata but got version {fetched}")]
+    VersionMismatch {
+        role: String,
+        expected: u64,
+        fetched: u64,
+    },
+
+    #[error("the TUF {0} metadata is not listed in the {1} metadata")]
+    UnlistedMetadata(String, String),
+
+    #[error("the TUF {0} metadata does not match the length or hashes listed in the {1} metadata")]
+    MetadataMismatch(String, String),
+
+    #[error("{0} is not listed in the TUF targets metadata of the repository")]
+    TargetNotFound(String),
+
+    #[error("{0} does not match the length or hashes listed in the TUF targets metadata")]
+    TargetMismatch(String),
+
+    #[error("{0} is listed without a supported hash in the TUF targets metadata")]
+    UnsupportedTargetHashes(String),
+
+    #[error("the TUF metadata refers to the role '{0}' which is not a valid file name")]
+    InvalidRoleName(String),
+}
+
+/// Signed metadata as it is served by the repository.
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    signatures: Vec<Signature>,
+    signed: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Signature {
+    keyid: String,
+    sig: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Key {
+    keytype: String,
+    scheme: String,
+    keyval: KeyValue,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct KeyValue {
+    public: String,
+}
+
+impl Key {
+    /// Returns true if `sig` is a valid signature of `message`. Only ed25519 keys are supported,
+    /// signatures of other keys are never valid.
+    fn verify(&self, message: &[u8], sig: &str) -> bool {
+        if self.keytype != "ed25519" || self.scheme != "ed25519" {
+            return false;
+        }
+        let (Ok(public), Ok(sig)) = (
+            HEXLOWER_PERMISSIVE.decode(self.keyval.public.as_bytes()),
+            HEXLOWER_PERMISSIVE.decode(sig.as_bytes()),
+        ) else {
+            return false;
+        };
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public)
+            .verify(message, &sig)
+            .is_ok()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RoleKeys {
+    keyids: Vec<String>,
+    threshold: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Root {
+    version: u64,
+    expires: String,
+    #[serde(default)]
+    consistent_snapshot: bool,
+    keys: HashMap<String, Key>,
+    roles: HashMap<String, RoleKeys>,
+}
+
+impl Root {
+    fn role(&self, role: &str) -> Result<&RoleKeys, TufError> {
+        self.roles.get(role).ok_or_else(|| {
+            TufError::InvalidMetadata(
+                String::from("root"),
+                serde::de::Error::custom(format!("the {role} role is missing")),
+            )
+        })
+    }
+}
+
+/// The contents of the timestamp and snapshot metadata.
+#[derive(Debug, Clone, Deserialize)]
+struct MetaList {
+    version: u64,
+    expires: String,
+    meta: HashMap<String, MetaFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MetaFile {
+    version: u64,
+    length: Option<u64>,
+    #[serde(default)]
+    hashes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Targets {
+    version: u64,
+    expires: String,
+    targets: HashMap<String, TargetFile>,
+    delegations: Option<Delegations>,
+}
+
+/// The length and hashes of a target.
+#[derive(Debug, Clone, Deserialize)]
+struct TargetFile {
+    length: u64,
+    hashes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Delegations {
+    keys: HashMap<String, Key>,
+    roles: Vec<DelegatedRole>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DelegatedRole {
+    name: String,
+    keyids: Vec<String>,
+    threshold: u64,
+    paths: Option<Vec<String>>,
+    path_hash_prefixes: Option<Vec<String>>,
+    #[serde(default)]
+    terminating: bool,
+}
+
+impl DelegatedRole {
+    fn keys(&self) -> RoleKeys {
+        RoleKeys {
+            keyids: self.keyids.clone(),
+            threshold: self.threshold,
+        }
+    }
+
+    /// Returns true if the role is trusted to sign the target `path`.
+    fn matches(&self, path: &str) -> bool {
+        if let Some(patterns) = &self.paths {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, path))
+        } else if let Some(prefixes) = &self.path_hash_prefixes {
+            let hash = HEXLOWER.encode(&Sha256::digest(path.as_bytes()));
+            prefixes.iter().any(|prefix| hash.starts_with(prefix))
+        } else {
+            false
+        }
+    }
+}
+
+/// Matches a target path against a pattern with shell-style wildcards, a wildcard never matches
+/// a `/`.
+fn matches_pattern(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match (pattern.first(), text.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                matches(&pattern[1..], text)
+                    || (!text.is_empty() && text[0] != b'/' && matches(pattern, &text[1..]))
+            }
+            (Some(b'?'), Some(&c)) => c != b'/' && matches(&pattern[1..], &text[1..]),
+            (Some(p), Some(c)) => p == c && matches(&pattern[1..], &text[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Serializes `value` as canonical JSON, the form of the metadata that is signed.
+fn canonical_json(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.extend_from_slice(b"null"),
+        Value::Bool(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+        Value::Number(number) => out.extend_from_slice(number.to_string().as_bytes()),
+        Value::String(string) => {
+            out.push(b'"');
+            for byte in string.bytes() {
+                if byte == b'"' || byte == b'\\' {
+                    out.push(b'\\');
+                }
+                out.push(byte);
+            }
+            out.push(b'"');
+        }
+        Value::Array(values) => {
+            out.push(b'[');
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.push(b',');
+                }
+                canonical_json(value, out);
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push(b'{');
+            for (index, (key, value)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(b',');
+                }
+                canonical_json(&Value::String(key.clone()), out);
+                out.push(b':');
+                canonical_json(value, out);
+            }
+            out.push(b'}');
+        }
+    }
+}
+
+/// Parses the metadata in `bytes` and checks that it is signed by at least the threshold of
+/// trusted `role_keys`.
+fn parse_envelope(
+    role: &str,
+    bytes: &[u8],
+    keys: &HashMap<String, Key>,
+    role_keys: &RoleKeys,
+) -> Result<Envelope, TufError> {
+    let envelope: Envelope = serde_json::from_slice(bytes)
+        .map_err(|e| TufError::InvalidMetadata(role.to_string(), e))?;
+    verify_signatures(role, &envelope, keys, role_keys)?;
+    Ok(envelope)
+}
+
+fn verify_signatures(
+    role: &str,
+    envelope: &Envelope,
+    keys: &HashMap<String, Key>,
+    role_keys: &RoleKeys,
+) -> Result<(), TufError> {
+    let mut message = Vec::new();
+    canonical_json(&envelope.signed, &mut message);
+
+    // The same key can be listed under multiple keyids, it only counts once towards the threshold
+    let mut valid = HashSet::new();
+    for signature in &envelope.signatures {
+        if !role_keys.keyids.contains(&signature.keyid) {
+            continue;
+        }
+        let Some(key) = keys.get(&signature.keyid) else {
+            continue;
+        };
+        let public = key.keyval.public.to_ascii_lowercase();
+        if !valid.contains(&public) && key.verify(&message, &signature.sig) {
+            valid.insert(public);
+        }
+    }
+
+    if (valid.len() as u64) < role_keys.threshold.max(1) {
+        return Err(TufError::ThresholdNotMet {
+            role: role.to_string(),
+            valid: valid.len(),
+            threshold: role_keys.threshold,
+        });
+    }
+    Ok(())
+}
+
+/// Deserializes the `signed` part of an envelope after checking that it contains the metadata of
+/// the `expected` type.
+fn parse_signed<T: DeserializeOwned>(
+    role: &str,
+    expected: &str,
+    signed: Value,
+) -> Result<T, TufError> {
+    let found = signed
+        .get("_type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if found != expected {
+        return Err(TufError::UnexpectedType {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        });
+    }
+    serde_json::from_value(signed).map_err(|e| TufError::InvalidMetadata(role.to_string(), e))
+}
+
+/// Parses an expiration date like `2030-01-01T00:00:00Z`.
+fn parse_expires(expires: &str) -> Option<SystemTime> {
+    let bytes = expires.as_bytes();
+    if bytes.len() != 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+        || bytes[19] != b'Z'
+    {
+        return None;
+    }
+    let number = |start: usize, end: usize| expires.get(start..end)?.parse::<u64>().ok();
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+    if !(1970..=9999).contains(&year)
+        || !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    // The number of days since 1970-01-01 in the proleptic Gregorian calendar
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era).checked_sub(719468)?;
+
+    Some(
+        SystemTime::UNIX_EPOCH
+            + Duration::from_secs(days * 86400 + hour * 3600 + minute * 60 + second),
+    )
+}
+
+fn check_expiry(role: &str, expires: &str, now: SystemTime) -> Result<(), TufError> {
+    let expiration = parse_expires(expires).ok_or_else(|| TufError::InvalidExpiration {
+        role: role.to_string(),
+        expires: expires.to_string(),
+    })?;
+    if expiration <= now {
+        return Err(TufError::Expired {
+            role: role.to_string(),
+            expires: expires.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The hash algorithms that are supported to verify metadata files and targets.
+const SUPPORTED_HASHES: [&str; 2] = ["sha256", "sha512"];
+
+/// Returns true if `reader` has the given `length` and matches all of the supported `hashes`.
+/// At least one of the hashes must be supported.
+fn matches_hashes(
+    reader: &mut dyn Read,
+    length: Option<u64>,
+    hashes: &HashMap<String, String>,
+) -> io::Result<bool> {
+    let mut sha256 = Sha256::new();
+    let mut sha512 = Sha512::new();
+    let mut total = 0;
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buf[..read]);
+        sha512.update(&buf[..read]);
+        total += read as u64;
+    }
+    if length.is_some_and(|length| length != total) {
+        return Ok(false);
+    }
+
+    let digests = [
+        (SUPPORTED_HASHES[0], HEXLOWER.encode(&sha256.finalize())),
+        (SUPPORTED_HASHES[1], HEXLOWER.encode(&sha512.finalize())),
+    ];
+    let mut supported = false;
+    for (algorithm, digest) in digests {
+        if let Some(expected) = hashes.get(algorithm) {
+            if !expected.eq_ignore_ascii_case(&digest) {
+                return Ok(false);
+            }
+            supported = true;
+        }
+    }
+    Ok(supported)
+}
+
+/// Verifies that `reader` is the target at `path`. Unlike metadata files, targets must be listed
+/// with at least one supported hash.
+fn check_target(path: &str, reader: &mut dyn Read, target: &TargetFile) -> Result<(), TufError> {
+    if !SUPPORTED_HASHES
+        .iter()
+        .any(|algorithm| target.hashes.contains_key(*algorithm))
+    {
+        return Err(TufError::UnsupportedTargetHashes(path.to_string()));
+    }
+    let matches = matches_hashes(reader, Some(target.length), &target.hashes)
+        .map_err(|e| TufError::IoError(PathBuf::from(path), e))?;
+    if !matches {
+        return Err(TufError::TargetMismatch(path.to_string()));
+    }
+    Ok(())
+}
+
+/// Returns an error if `role` cannot be used as the name of a metadata file. The names of
+/// delegated roles come from the remote metadata and end up in URLs and paths in the cache.
+fn check_role_name(role: &str) -> Result<(), TufError> {
+    if role.is_empty() || role.contains(['/', '\\']) || role.contains("..") {
+        return Err(TufError::InvalidRoleName(role.to_string()));
+    }
+    Ok(())
+}
+
+/// Verifies metadata bytes against the length and hashes listed for them in other metadata.
+fn check_meta_file(
+    role: &str,
+    listed_in: &str,
+    bytes: &[u8],
+    meta: &MetaFile,
+) -> Result<(), TufError> {
+    // Hashes are optional for metadata files, their version is checked instead
+    let matches = if meta.hashes.is_empty() {
+        meta.length
+            .map_or(true, |length| length == bytes.len() as u64)
+    } else {
+        matches_hashes(&mut &bytes[..], meta.length, &meta.hashes)
+            .expect("reading from memory cannot fail")
+    };
+    if !matches {
+        return Err(TufError::MetadataMismatch(
+            role.to_string(),
+            listed_in.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The result of looking up a target in the trusted metadata.
+#[derive(Debug)]
+enum TargetLookup {
+    /// The target is listed in the metadata of a role that is trusted for it
+    Found(TargetFile),
+    /// The metadata of `role`, delegated by `delegator`, is required to continue the search
+    Missing { delegator: String, role: String },
+    /// The target is not listed in any of the roles that are trusted for it
+    NotFound,
+}
+
+/// The metadata of a repository that has been verified, updated in the order of the client
+/// workflow of the TUF specification.
+#[derive(Debug)]
+struct TrustedMetadata {
+    now: SystemTime,
+    root: Root,
+    timestamp: Option<MetaList>,
+    snapshot: Option<MetaList>,
+    targets: HashMap<String, Targets>,
+}
+
+impl TrustedMetadata {
+    /// Trusts the `root` metadata, which must be signed by its own root keys. The root is not
+    /// required to be unexpired until all newer roots have been applied.
+    fn new(root: &[u8], now: SystemTime) -> Result<Self, TufError> {
+        let envelope: Envelope = serde_json::from_slice(root)
+            .map_err(|e| TufError::InvalidMetadata(String::from("root"), e))?;
+        let signed: Root = parse_signed("root", "root", envelope.signed.clone())?;
+        verify_signatures("root", &envelope, &signed.keys, signed.role("root")?)?;
+        Ok(Self {
+            now,
+            root: signed,
+            timestamp: None,
+            snapshot: None,
+            targets: HashMap::new(),
+        })
+    }
+
+    /// Applies the next version of the root, it must be signed by the keys of the current root
+    /// and by its own keys.
+    fn update_root(&mut self, bytes: &[u8]) -> Result<(), TufError> {
+        let envelope = parse_envelope("root", bytes, &self.root.keys, self.root.role("root")?)?;
+        let signed: Root = parse_signed("root", "root", envelope.signed.clone())?;
+        verify_signatures("root", &envelope, &signed.keys, signed.role("root")?)?;
+        if signed.version != self.root.version + 1 {
+            return Err(TufError::VersionMismatch {
+                role: String::from("root"),
+                expected: self.root.version + 1,
+                fetched: signed.version,
+            });
+        }
+        self.root = signed;
+        Ok(())
+    }
+
+    /// Checks that the root has not expired, after all newer roots have been applied.
+    fn check_root(&self) -> Result<(), TufError> {
+        check_expiry("root", &self.root.expires, self.now)
+    }
+
+    /// Loads the timestamp and snapshot that were trusted before. They are used to detect
+    /// rollbacks and are ignored if they are no longer signed by the keys of the current root.
+    fn load_trusted(&mut self, timestamp: Option<&[u8]>, snapshot: Option<&[u8]>) {
+        let parse = |role: &str, bytes: Option<&[u8]>| -> Option<MetaList> {
+            let envelope =
+                parse_envelope(role, bytes?, &self.root.keys, self.root.role(role).ok()?).ok()?;
+            parse_signed(role, role, envelope.signed).ok()
+        };
+        self.timestamp = parse("timestamp", timestamp);
+        self.snapshot = parse("snapshot", snapshot);
+    }
+
+    fn update_timestamp(&mut self, bytes: &[u8]) -> Result<(), TufError> {
+        let envelope = parse_envelope(
+            "timestamp",
+            bytes,
+            &self.root.keys,
+            self.root.role("timestamp")?,
+        )?;
+        let signed: MetaList = parse_signed("timestamp", "timestamp", envelope.signed)?;
+        let snapshot_version = |timestamp: &MetaList| {
+            timestamp
+                .meta
+                .get("snapshot.json")
+                .map(|meta| meta.version)
+                .ok_or_else(|| {
+                    TufError::UnlistedMetadata(String::from("snapshot"), String::from("timestamp"))
+                })
+        };
+
+        if let Some(trusted) = &self.timestamp {
+            check_downgrade("timestamp", trusted.version, signed.version)?;
+            check_downgrade(
+                "snapshot",
+                snapshot_version(trusted)?,
+                snapshot_version(&signed)?,
+            )?;
+        }
+        snapshot_version(&signed)?;
+        check_expiry("timestamp", &signed.expires, self.now)?;
+        self.timestamp = Some(signed);
+        Ok(())
+    }
+
+    fn update_snapshot(&mut self, bytes: &[u8]) -> Result<(), TufError> {
+        let meta = self
+            .meta_file("snapshot")
+            .expect("the timestamp is updated before the snapshot")
+            .clone();
+        check_meta_file("snapshot", "timestamp", bytes, &meta)?;
+
+        let envelope = parse_envelope(
+            "snapshot",
+            bytes,
+            &self.root.keys,
+            self.root.role("snapshot")?,
+        )?;
+        let signed: MetaList = parse_signed("snapshot", "snapshot", envelope.signed)?;
+        check_version("snapshot", meta.version, signed.version)?;
+
+        // The versions of the targets metadata must not go back either
+        if let Some(trusted) = &self.snapshot {
+            for (name, trusted_meta) in &trusted.meta {
+                let role = name.strip_suffix(".json").unwrap_or(name);
+                match signed.meta.get(name) {
+                    Some(meta) => check_downgrade(role, trusted_meta.version, meta.version)?,
+                    None => {
+                        return Err(TufError::UnlistedMetadata(
+                            role.to_string(),
+                            String::from("snapshot"),
+                        ))
+                    }
+                }
+            }
+        }
+        check_expiry("snapshot", &signed.expires, self.now)?;
+        self.snapshot = Some(signed);
+        self.targets.clear();
+        Ok(())
+    }
+
+    /// Updates the metadata of the top-level targets role, or of a `role` that is delegated by
+    /// the already trusted targets metadata of `delegator`.
+    fn update_targets(
+        &mut self,
+        delegator: &str,
+        role: &str,
+        bytes: &[u8],
+    ) -> Result<(), TufError> {
+        let meta = self
+            .meta_file(role)
+            .ok_or_else(|| TufError::UnlistedMetadata(role.to_string(), String::from("snapshot")))?
+            .clone();
+        check_meta_file(role, "snapshot", bytes, &meta)?;
+
+        let envelope = if delegator == "root" {
+            parse_envelope(role, bytes, &self.root.keys, self.root.role("targets")?)?
+        } else {
+            let delegations = self
+                .targets
+                .get(delegator)
+                .and_then(|targets| targets.delegations.as_ref())
+                .expect("the delegating role is trusted before its delegations");
+            let delegated = delegations
+                .roles
+                .iter()
+                .find(|delegated| delegated.name == role)
+                .expect("the role is delegated by the delegator");
+            parse_envelope(role, bytes, &delegations.keys, &delegated.keys())?
+        };
+        let signed: Targets = parse_signed(role, "targets", envelope.signed)?;
+        check_version(role, meta.version, signed.version)?;
+        check_expiry(role, &signed.expires, self.now)?;
+        self.targets.insert(role.to_string(), signed);
+        Ok(())
+    }
+
+    /// Returns the length, hashes and version of the metadata of `role` as listed in the
+    /// timestamp (for the snapshot) or in the snapshot (for all other roles).
+    fn meta_file(&self, role: &str) -> Option<&MetaFile> {
+        let list = if role == "snapshot" {
+            self.timestamp.as_ref()
+        } else {
+            self.snapshot.as_ref()
+        };
+        list?.meta.get(&format!("{role}.json"))
+    }
+
+    /// Returns the name of the file that contains the metadata of `role`.
+    fn file_name(&self, role: &str) -> String {
+        match self.meta_file(role) {
+            Some(meta) if self.root.consistent_snapshot => format!("{}.{role}.json", meta.version),
+            _ => format!("{role}.json"),
+        }
+    }
+
+    /// Searches the targets metadata and its delegations for the target `path`, in the order of
+    /// the delegations.
+    fn find_target(&self, path: &str) -> TargetLookup {
+        let mut stack = vec![(String::from("root"), String::from("targets"), 0)];
+        let mut visited = HashSet::new();
+        while let Some((delegator, role, depth)) = stack.pop() {
+            if depth > MAX_DELEGATION_DEPTH || !visited.insert(role.clone()) {
+                continue;
+            }
+            let Some(targets) = self.targets.get(&role) else {
+                return TargetLookup::Missing { delegator, role };
+            };
+            if let Some(target) = targets.targets.get(path) {
+                return TargetLookup::Found(target.clone());
+            }
+
+            let Some(delegations) = &targets.delegations else {
+                continue;
+            };
+            let mut children = Vec::new();
+            for delegated in delegations.roles.iter().filter(|role| role.matches(path)) {
+                children.push((role.clone(), delegated.name.clone(), depth + 1));
+                if delegated.terminating {
+                    // Nothing after a terminating role is searched, also when the role itself
+                    // does not list the target
+                    stack.clear();
+                    break;
+                }
+            }
+            stack.extend(children.into_iter().rev());
+        }
+        TargetLookup::NotFound
+    }
+}
+
+fn check_downgrade(role: &str, trusted: u64, fetched: u64) -> Result<(), TufError> {
+    if fetched < trusted {
+        return Err(TufError::Downgrade {
+            role: role.to_string(),
+            trusted,
+            fetched,
+        });
+    }
+    Ok(())
+}
+
+fn check_version(role: &str, expected: u64, fetched: u64) -> Result<(), TufError> {
+    if expected != fetched {
+        return Err(TufError::VersionMismatch {
+            role: role.to_string(),
+            expected,
+            fetched,
+        });
+    }
+    Ok(())
+}
+
+/// Verifies the responses of a [`TufRepository`], the metadata is refreshed on first use.
+#[derive(Debug)]
+pub(crate) struct TufVerifier {
+    repository: TufRepository,
+    state_dir: PathBuf,
+    trusted: tokio::sync::Mutex<Option<TrustedMetadata>>,
+}
+
+impl TufVerifier {
+    /// Constructs a verifier that keeps the trusted metadata in the `cache_dir`.
+    pub(crate) fn new(repository: TufRepository, cache_dir: &Path) -> Self {
+        let key = Sha256::digest(repository.metadata_url.as_str().as_bytes());
+        let state_dir = CacheBucket::Tuf
+            .path(cache_dir)
+            .join(HEXLOWER.encode(&key[..8]));
+        Self {
+            repository,
+            state_dir,
+            trusted: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Returns the repository whose responses are verified.
+    pub(crate) fn repository(&self) -> &TufRepository {
+        &self.repository
+    }
+
+    /// Verifies that `body` is the target at `path`, the metadata is retrieved with `http`.
+    pub(crate) async fn verify_target(
+        &self,
+        http: &Http,
+        path: &str,
+        mut body: Box<dyn ReadAndSeek + Send>,
+    ) -> Result<Box<dyn ReadAndSeek + Send>, TufError> {
+        let target = {
+            let mut trusted = self.trusted.lock().await;
+            if trusted.is_none() {
+                *trusted = Some(self.refresh(http).await?);
+            }
+            let trusted = trusted.as_mut().expect("the metadata was refreshed");
+            loop {
+                match trusted.find_target(path) {
+                    TargetLookup::Found(target) => break target,
+                    TargetLookup::NotFound => {
+                        return Err(TufError::TargetNotFound(path.to_string()))
+                    }
+                    TargetLookup::Missing { delegator, role } => {
+                        let bytes = self.fetch(http, &role, &trusted.file_name(&role)).await?;
+                        trusted.update_targets(&delegator, &role, &bytes)?;
+                        self.persist(&role, &bytes)?;
+                    }
+                }
+            }
+        };
+
+        let path = path.to_string();
+        tokio::task::spawn_blocking(move || {
+            check_target(&path, &mut body, &target)?;
+            body.rewind()
+                .map_err(|e| TufError::IoError(PathBuf::from(&path), e))?;
+            Ok(body)
+        })
+        .await
+        .map_err(|e| TufError::IoError(PathBuf::new(), io::Error::new(io::ErrorKind::Other, e)))?
+    }
+
+    /// Runs the client workflow of the TUF specification: updates the root, then the timestamp,
+    /// snapshot and top-level targets metadata. When offline the metadata that was trusted
+    /// before is verified again instead.
+    async fn refresh(&self, http: &Http) -> Result<TrustedMetadata, TufError> {
+        let offline = http.cache_setting() == CacheSetting::Offline;
+        let root = match self.read_trusted("root")? {
+            Some(root) => root,
+            None => {
+                let path = self.repository.trusted_root.clone();
+                fs::read(&path).map_err(|e| TufError::IoError(path, e))?
+            }
+        };
+        let mut trusted = TrustedMetadata::new(&root, SystemTime::now())?;
+
+        // Apply newer roots one version at a time until the repository has no newer root
+        for _ in 0..if offline { 0 } else { MAX_ROOT_ROTATIONS } {
+            let name = format!("{}.root.json", trusted.root.version + 1);
+            let Some(bytes) = self.fetch_optional(http, &name).await? else {
+                break;
+            };
+            trusted.update_root(&bytes)?;
+            self.persist("root", &bytes)?;
+        }
+        trusted.check_root()?;
+
+        trusted.load_trusted(
+            self.read_trusted("timestamp")?.as_deref(),
+            self.read_trusted("snapshot")?.as_deref(),
+        );
+        for role in ["timestamp", "snapshot", "targets"] {
+            let bytes = self.fetch(http, role, &trusted.file_name(role)).await?;
+            match role {
+                "timestamp" => trusted.update_timestamp(&bytes)?,
+                "snapshot" => trusted.update_snapshot(&bytes)?,
+                _ => trusted.update_targets("root", role, &bytes)?,
+            }
+            self.persist(role, &bytes)?;
+        }
+        Ok(trusted)
+    }
+
+    /// Retrieves the metadata of `role` from the file `name` in the repository, or from the
+    /// metadata that was trusted before when offline.
+    async fn fetch(&self, http: &Http, role: &str, name: &str) -> Result<Vec<u8>, TufError> {
+        check_role_name(role)?;
+        if http.cache_setting() == CacheSetting::Offline {
+            return self.read_trusted(role)?.ok_or_else(|| {
+                TufError::FetchError(
+                    self.metadata_file_url(name),
+                    Box::new(HttpRequestError::Offline(self.metadata_file_url(name))),
+                )
+            });
+        }
+        self.fetch_optional(http, name)
+            .await?
+            .ok_or_else(|| TufError::MissingMetadata(self.metadata_file_url(name)))
+    }
+
+    async fn fetch_optional(&self, http: &Http, name: &str) -> Result<Option<Vec<u8>>, TufError> {
+        let url = self.metadata_file_url(name);
+        let response = match http
+            .request_unverified(
+                url.clone(),
+                Method::GET,
+                HeaderMap::new(),
+                CacheMode::NoStore,
+                None,
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(HttpRequestError::HttpError(err))
+                if matches!(
+                    err.status(),
+                    Some(StatusCode::NOT_FOUND | StatusCode::FORBIDDEN)
+                ) =>
+            {
+                return Ok(None)
+            }
+            Err(err) => return Err(TufError::FetchError(url, Box::new(err))),
+        };
+
+        let mut bytes = Vec::new();
+        response
+            .into_body()
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| TufError::FetchError(url, Box::new(e.into())))?;
+        Ok(Some(bytes))
+    }
+
+    fn metadata_file_url(&self, name: &str) -> Url {
+        self.repository
+            .metadata_url
+            .join(name)
+            .expect("the name of a metadata file is a valid relative url")
+    }
+
+    /// Reads the metadata of `role` that was trusted before, if any.
+    fn read_trusted(&self, role: &str) -> Result<Option<Vec<u8>>, TufError> {
+        check_role_name(role)?;
+        let path = self.state_dir.join(format!("{role}.json"));
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(TufError::IoError(path, e)),
+        }
+    }
+
+    /// Stores verified metadata of `role` so it can be used to detect rollbacks later.
+    fn persist(&self, role: &str, bytes: &[u8]) -> Result<(), TufError> {
+        check_role_name(role)?;
+        let path = self.state_dir.join(format!("{role}.json"));
+        fs::create_dir_all(&self.state_dir)
+            .and_then(|_| fs::write(&path, bytes))
+            .map_err(|e| TufError::IoError(path, e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index::file_store::FileStore;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::json;
+
+    const EXPIRES: &str = "2100-01-01T00:00:00Z";
+
+    struct TestKey {
+        id: String,
+        pair: Ed25519KeyPair,
+    }
+
+    impl TestKey {
+        fn generate(id: &str) -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            Self {
+                id: id.to_string(),
+                pair: Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap(),
+            }
+        }
+
+        fn public(&self) -> Value {
+            json!({
+                "keytype": "ed25519",
+                "scheme": "ed25519",
+                "keyval": { "public": HEXLOWER.encode(self.pair.public_key().as_ref()) }
+            })
+        }
+    }
+
+    fn sign(signed: Value, keys: &[&TestKey]) -> Vec<u8> {
+        let mut message = Vec::new();
+        canonical_json(&signed, &mut message);
+        let signatures = keys
+            .iter()
+            .map(|key| {
+                json!({
+                    "keyid": key.id,
+                    "sig": HEXLOWER.encode(key.pair.sign(&message).as_ref()),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_vec(&json!({ "signatures": signatures, "signed": signed })).unwrap()
+    }
+
+    fn root(version: u64, root_key: &TestKey, key: &TestKey) -> Value {
+        let role = |key: &TestKey| json!({ "keyids": [key.id], "threshold": 1 });
+        json!({
+            "_type": "root",
+            "spec_version": "1.0.31",
+            "version": version,
+            "expires": EXPIRES,
+            "consistent_snapshot": true,
+            "keys": { &root_key.id: root_key.public(), &key.id: key.public() },
+            "roles": {
+                "root": role(root_key),
+                "timestamp": role(key),
+                "snapshot": role(key),
+                "targets": role(key),
+            }
+        })
+    }
+
+    fn meta_list(kind: &str, version: u64, meta: Value) -> Value {
+        json!({
+            "_type": kind,
+            "spec_version": "1.0.31",
+            "version": version,
+            "expires": EXPIRES,
+            "meta": meta,
+        })
+    }
+
+    fn hashes(bytes: &[u8]) -> Value {
+        json!({ "sha256": HEXLOWER.encode(&Sha256::digest(bytes)) })
+    }
+
+    #[test]
+    fn test_threshold_counts_distinct_keys() {
+        // Two keyids with the same key material
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let [key, alias] = ["a", "b"].map(|id| TestKey {
+            id: id.to_string(),
+            pair: Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap(),
+        });
+        let keys = HashMap::from([
+            (
+                key.id.clone(),
+                serde_json::from_value(key.public()).unwrap(),
+            ),
+            (
+                alias.id.clone(),
+                serde_json::from_value(alias.public()).unwrap(),
+            ),
+        ]);
+        let role_keys = RoleKeys {
+            keyids: vec![key.id.clone(), alias.id.clone()],
+            threshold: 2,
+        };
+
+        let bytes = sign(json!({ "_type": "timestamp" }), &[&key, &alias]);
+        let envelope: Envelope = serde_json::from_slice(&bytes).unwrap();
+        assert!(matches!(
+            verify_signatures("timestamp", &envelope, &keys, &role_keys),
+            Err(TufError::ThresholdNotMet { valid: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_expires() {
+        let time = |secs| Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(parse_expires("1970-01-01T00:00:00Z"), time(0));
+        assert_eq!(parse_expires("2000-03-01T01:02:03Z"), time(951_872_523));
+        assert_eq!(parse_expires("2024-02-29T23:59:59Z"), time(1_709_251_199));
+        assert_eq!(parse_expires("2024-02-29 23:59:59"), None);
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern(
+            "simple/*/index.html",
+            "simple/flask/index.html"
+        ));
+        assert!(matches_pattern(
+            "packages/*/??/*",
+            "packages/ab/cd/flask.whl"
+        ));
+        assert!(!matches_pattern("simple/*", "simple/flask/index.html"));
+        assert!(!matches_pattern("simple/?", "simple/ab"));
+    }
+
+    #[test]
+    fn test_workflow() {
+        let root_key = TestKey::generate("root");
+        let key = TestKey::generate("online");
+        let bins_key = TestKey::generate("bins");
+        let now = SystemTime::now();
+
+        let root_v1 = sign(root(1, &root_key, &key), &[&root_key]);
+        let mut trusted = TrustedMetadata::new(&root_v1, now).unwrap();
+
+        // A new root must be signed by the keys of the current root
+        let new_root_key = TestKey::generate("new-root");
+        let root_v2 = root(2, &new_root_key, &key);
+        assert!(matches!(
+            trusted.update_root(&sign(root_v2.clone(), &[&new_root_key])),
+            Err(TufError::ThresholdNotMet { .. })
+        ));
+        trusted
+            .update_root(&sign(root_v2, &[&root_key, &new_root_key]))
+            .unwrap();
+        trusted.check_root().unwrap();
+
+        // Targets that are delegated to a hashed bin
+        let page = b"<html></html>";
+        let bin = sign(
+            json!({
+                "_type": "targets",
+                "version": 1,
+                "expires": EXPIRES,
+                "targets": {
+                    "simple/flask/index.html": { "length": page.len(), "hashes": hashes(page) }
+                }
+            }),
+            &[&bins_key],
+        );
+        let targets = sign(
+            json!({
+                "_type": "targets",
+                "version": 3,
+                "expires": EXPIRES,
+                "targets": {},
+                "delegations": {
+                    "keys": { &bins_key.id: bins_key.public() },
+                    "roles": [{
+                        "name": "bins",
+                        "keyids": [bins_key.id],
+                        "threshold": 1,
+                        "paths": ["simple/*/index.html"],
+                        "terminating": true,
+                    }]
+                }
+            }),
+            &[&key],
+        );
+        let snapshot = sign(
+            meta_list(
+                "snapshot",
+                5,
+                json!({
+                    "targets.json": { "version": 3 },
+                    "bins.json": { "version": 1, "length": bin.len(), "hashes": hashes(&bin) },
+                }),
+            ),
+            &[&key],
+        );
+        let timestamp = sign(
+            meta_list(
+                "timestamp",
+                7,
+                json!({ "snapshot.json": { "version": 5, "hashes": hashes(&snapshot) } }),
+            ),
+            &[&key],
+        );
+
+        trusted.update_timestamp(&timestamp).unwrap();
+        assert_eq!(trusted.file_name("snapshot"), "5.snapshot.json");
+        trusted.update_snapshot(&snapshot).unwrap();
+        trusted.update_targets("root", "targets", &targets).unwrap();
+
+        let path = "simple/flask/index.html";
+        let TargetLookup::Missing { delegator, role } = trusted.find_target(path) else {
+            panic!("the delegated role must be loaded first");
+        };
+        assert_eq!((delegator.as_str(), role.as_str()), ("targets", "bins"));
+        assert_eq!(trusted.file_name("bins"), "1.bins.json");
+        trusted.update_targets(&delegator, &role, &bin).unwrap();
+
+        let TargetLookup::Found(target) = trusted.find_target(path) else {
+            panic!("the target must be found");
+        };
+        check_target(path, &mut &page[..], &target).unwrap();
+        assert!(matches!(
+            check_target(path, &mut &b"<html/>"[..], &target),
+            Err(TufError::TargetMismatch(_))
+        ));
+
+        // Targets without a supported hash are rejected, even if their length matches
+        let unhashed = TargetFile {
+            length: target.length,
+            hashes: HashMap::new(),
+        };
+        assert!(matches!(
+            check_target(path, &mut &page[..], &unhashed),
+            Err(TufError::UnsupportedTargetHashes(_))
+        ));
+        assert!(matches!(
+            trusted.find_target("simple/click/index.html"),
+            TargetLookup::NotFound
+        ));
+
+        // Metadata that is older than the trusted metadata is rejected
+        let old_timestamp = sign(
+            meta_list("timestamp", 6, json!({ "snapshot.json": { "version": 5 } })),
+            &[&key],
+        );
+        let err = trusted.update_timestamp(&old_timestamp).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                TufError::Downgrade {
+                    trusted: 7,
+                    fetched: 6,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+        assert_eq!(
+            err.to_string(),
+            "the TUF timestamp metadata was downgraded from version 7 to 6, this could be a rollback attack"
+        );
+
+        // A snapshot that does not match the hashes in the timestamp is rejected
+        let mut tampered = TrustedMetadata::new(&root_v1, now).unwrap();
+        tampered.update_timestamp(&timestamp).unwrap();
+        let other_snapshot = sign(meta_list("snapshot", 5, json!({})), &[&key]);
+        assert!(matches!(
+            tampered.update_snapshot(&other_snapshot),
+            Err(TufError::MetadataMismatch(..))
+        ));
+
+        // Expired metadata is rejected
+        let expired = TrustedMetadata::new(&root_v1, now + Duration::from_secs(100 * 365 * 86400))
+            .unwrap()
+            .check_root();
+        assert!(matches!(expired, Err(TufError::Expired { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_role_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let verifier = TufVerifier::new(
+            TufRepository::new(
+                Url::parse("https://example.com/metadata/").unwrap(),
+                "root.json",
+            ),
+            &dir.path().join("cache"),
+        );
+        let http = Http::new(
+            reqwest::Client::new().into(),
+            FileStore::new(&dir.path().join("http")).unwrap(),
+        );
+
+        for role in ["", "../../escaped", "bins/../../escaped", "..", "a\\b"] {
+            assert!(matches!(
+                verifier.persist(role, b"{}"),
+                Err(TufError::InvalidRoleName(_))
+            ));
+            assert!(matches!(
+                verifier.read_trusted(role),
+                Err(TufError::InvalidRoleName(_))
+            ));
+            assert!(matches!(
+                verifier.fetch(&http, role, &format!("1.{role}.json")).await,
+                Err(TufError::InvalidRoleName(_))
+            ));
+        }
+        assert!(!dir.path().join("escaped.json").exists());
+
+        verifier.persist("bins-0", b"{}").unwrap();
+        assert_eq!(verifier.read_trusted("bins-0").unwrap().unwrap(), b"{}");
+    }
+
+    #[test]
+    fn test_target_path() {
+        let repository = TufRepository::new(
+            Url::parse("https://example.com/metadata").unwrap(),
+            "root.json",
+        );
+        assert_eq!(
+            repository.metadata_url().as_str(),
+            "https://example.com/metadata/"
+        );
+        assert_eq!(
+            repository
+                .target_path(&Url::parse("https://example.com/simple/flask/").unwrap())
+                .as_deref(),
+            Some("simple/flask/index.html")
+        );
+        assert_eq!(
+            repository.target_path(&Url::parse("https://files.example.com/flask.whl").unwrap()),
+            None
+        );
+
+        let repository =
+            repository.with_target_base_url(Url::parse("https://files.example.com/").unwrap());
+        assert_eq!(
+            repository
+                .target_path(
+                    &Url::parse("https://files.example.com/a/flask.whl#sha256=00").unwrap()
+                )
+                .as_deref(),
+            Some("a/flask.whl")
+        );
+        assert_eq!(
+            repository.target_path(&Url::parse("https://example.com/simple/flask/").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Warnings for requirements on packages whose name looks like the name of a popular package.
+//!
+//! Malicious packages are often published under a name that is a typo of a popular package, like
+//! `reqeusts`, or that is visually confusable with one, like `nurnpy`. [`TyposquattingCheck`]
+//! compares the names of requested packages with a list of popular packages, see
+//! [`crate::index::PackageDb::with_typosquatting_check`].
+
+use crate::types::{NormalizedPackageName, PackageName};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The names of the most downloaded packages on PyPI, ordered by popularity.
+const POPULAR_PACKAGES: &str = include_str!("popular_packages.txt");
+
+/// Why the name of a requested package looks like the name of a popular package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TyposquattingReason {
+    /// The names differ by this number of inserted, removed, replaced or swapped characters
+    Typo(usize),
+
+    /// The names look the same, e.g. `0` and `o`, `1` and `l` or `rn` and `m`
+    Confusable,
+}
+
+/// A requested package whose name looks like the name of a popular package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TyposquattingWarning {
+    /// The name of the requested package
+    pub requested: NormalizedPackageName,
+
+    /// The name of the popular package it looks like
+    pub popular: NormalizedPackageName,
+
+    /// Why the names look alike
+    pub reason: TyposquattingReason,
+}
+
+impl Display for TyposquattingWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            TyposquattingReason::Typo(1) => String::from("differs by a single character from"),
+            TyposquattingReason::Typo(distance) => {
+                format!("differs by {distance} characters from")
+            }
+            TyposquattingReason::Confusable => String::from("looks confusingly similar to"),
+        };
+        write!(
+            f,
+            "the requested package `{}` {reason} the popular package `{}`, did you mean `{}`?",
+            self.requested, self.popular, self.popular
+        )
+    }
+}
+
+/// Compares the names of requested packages with a list of popular packages, see the
+/// [module docs](self).
+#[derive(Debug, Clone)]
+pub struct TyposquattingCheck {
+    popular: Vec<NormalizedPackageName>,
+}
+
+impl Default for TyposquattingCheck {
+    /// Compares names with a built-in list of the most downloaded packages on PyPI.
+    fn default() -> Self {
+        Self::new(
+            POPULAR_PACKAGES
+                .lines()
+                .filter_map(|name| PackageName::from_str(name).ok())
+                .map(NormalizedPackageName::from),
+        )
+    }
+}
+
+impl TyposquattingCheck {
+    /// Compares names with the given packages, ordered from most to least popular.
+    pub fn new(popular: impl IntoIterator<Item = NormalizedPackageName>) -> Self {
+        Self {
+            popular: popular.into_iter().collect(),
+        }
+    }
+
+    /// Returns a warning if `name` looks like the name of a popular package but is not one
+    /// itself. If it looks like several packages, the most popular one is returned.
+    pub fn check(&self, name: &NormalizedPackageName) -> Option<TyposquattingWarning> {
+        if self.popular.contains(name) {
+            return None;
+        }
+
+        let requested = name.as_str();
+        let skeleton = skeleton(requested);
+        self.popular.iter().find_map(|popular| {
+            let reason = if skeleton == self::skeleton(popular.as_str()) {
+                TyposquattingReason::Confusable
+            } else {
+                let distance = edit_distance(requested, popular.as_str());
+                if distance > max_typos(popular.as_str()) {
+                    return None;
+                }
+                TyposquattingReason::Typo(distance)
+            };
+            Some(TyposquattingWarning {
+                requested: name.clone(),
+                popular: popular.clone(),
+                reason,
+            })
+        })
+    }
+}
+
+/// The number of typos that is considered suspicious, short names are too likely to be close to
+/// other legitimate names.
+fn max_typos(name: &str) -> usize {
+    match name.len() {
+        0..=4 => 0,
+        5..=9 => 1,
+        _ => 2,
+    }
+}
+
+/// Returns the name with characters that look alike replaced by a single representative and
+/// without separators, names with the same skeleton are visually confusable.
+fn skeleton(name: &str) -> String {
+    let name = name.replace("rn", "m").replace("vv", "w");
+    name.chars()
+        .filter(|c| *c != '-')
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' => 'l',
+            '5' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+/// Returns the number of inserted, removed, replaced or swapped adjacent characters that turn `a`
+/// into `b` (the optimal string alignment distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..=b.len() {
+        distances[0][j] = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(name: &str) -> Option<(String, TyposquattingReason)> {
+        let name = NormalizedPackageName::from_str(name).unwrap();
+        TyposquattingCheck::default()
+            .check(&name)
+            .map(|warning| (warning.popular.to_string(), warning.reason))
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("requests", "requests"), 0);
+        assert_eq!(edit_distance("reqeusts", "requests"), 1);
+        assert_eq!(edit_distance("request", "requests"), 1);
+        assert_eq!(edit_distance("rekuests", "requests"), 1);
+        assert_eq!(edit_distance("python-datetuil", "python-dateutil"), 1);
+        assert_eq!(edit_distance("flask", "django"), 5);
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            check("reqeusts"),
+            Some((String::from("requests"), TyposquattingReason::Typo(1)))
+        );
+        assert_eq!(
+            check("python-datutil"),
+            Some((
+                String::from("python-dateutil"),
+                TyposquattingReason::Typo(1)
+            ))
+        );
+        assert_eq!(
+            check("nurnpy"),
+            Some((String::from("numpy"), TyposquattingReason::Confusable))
+        );
+        assert_eq!(
+            check("pythondateutil"),
+            Some((
+                String::from("python-dateutil"),
+                TyposquattingReason::Confusable
+            ))
+        );
+
+        // Popular packages, names that are too short and unrelated names are fine
+        assert_eq!(check("requests"), None);
+        assert_eq!(check("Requests"), None);
+        assert_eq!(check("six2"), None);
+        assert_eq!(check("rattler-build"), None);
+    }
+
+    #[test]
+    fn test_display() {
+        let warning = TyposquattingWarning {
+            requested: NormalizedPackageName::from_str("reqeusts").unwrap(),
+            popular: NormalizedPackageName::from_str("requests").unwrap(),
+            reason: TyposquattingReason::Typo(1),
+        };
+        assert_eq!(
+            warning.to_string(),
+            "the requested package `reqeusts` differs by a single character from the popular package `requests`, did you mean `requests`?"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Types that describe what [`super::PackageDb::warm`] prefetches into the cache.
+
+use crate::lock::{Lockfile, LockfileError};
+use crate::resolve::PinnedPackage;
+use crate::types::NormalizedPackageName;
+use std::num::NonZeroUsize;
+
+/// The packages whose index pages, metadata and artifacts are prefetched by
+/// [`super::PackageDb::warm`].
+#[derive(Debug, Clone)]
+pub enum WarmRequest {
+    /// Prefetch the index pages of these packages and the metadata of their latest release.
+    Names(Vec<NormalizedPackageName>),
+
+    /// Prefetch the index pages of these packages and the metadata and artifacts of their pinned
+    /// versions. Packages that are pinned to a direct url are skipped.
+    Pinned(Vec<PinnedPackage>),
+}
+
+impl WarmRequest {
+    /// Prefetch all the packages that are locked for any of the targets of a lockfile.
+    pub fn from_lockfile(lockfile: &Lockfile) -> Result<Self, LockfileError> {
+        let mut packages = Vec::new();
+        for target in &lockfile.targets {
+            for package in target.pinned_packages()? {
+                if !packages
+                    .iter()
+                    .any(|p: &PinnedPackage| p.name == package.name && p.version == package.version)
+                {
+                    packages.push(package);
+                }
+            }
+        }
+        Ok(Self::Pinned(packages))
+    }
+}
+
+impl From<Vec<NormalizedPackageName>> for WarmRequest {
+    fn from(names: Vec<NormalizedPackageName>) -> Self {
+        Self::Names(names)
+    }
+}
+
+impl From<Vec<PinnedPackage>> for WarmRequest {
+    fn from(packages: Vec<PinnedPackage>) -> Self {
+        Self::Pinned(packages)
+    }
+}
+
+/// Options that control [`super::PackageDb::warm`].
+#[derive(Debug, Clone)]
+pub struct WarmOptions {
+    pub(crate) concurrency: NonZeroUsize,
+    pub(crate) artifacts: bool,
+}
+
+impl Default for WarmOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: NonZeroUsize::new(10).expect("10 is not zero"),
+            artifacts: false,
+        }
+    }
+}
+
+impl WarmOptions {
+    /// Sets the maximum number of packages that are prefetched at the same time.
+    pub fn with_concurrency(self, concurrency: NonZeroUsize) -> Self {
+        Self {
+            concurrency,
+            ..self
+        }
+    }
+
+    /// Sets whether the artifacts themselves are downloaded as well. Without them only the index
+    /// pages and the metadata are cached, which is enough to resolve but not to install.
+    pub fn with_artifacts(self, artifacts: bool) -> Self {
+        Self { artifacts, ..self }
+    }
+}
+
+/// What [`super::PackageDb::warm`] prefetched into the cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WarmSummary {
+    /// The number of packages whose index pages were fetched.
+    pub packages: usize,
+
+    /// The number of packages whose metadata was fetched.
+    pub metadata: usize,
+
+    /// The number of artifacts that were downloaded.
+    pub artifacts: usize,
+}
+
+impl std::ops::AddAssign for WarmSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.packages += other.packages;
+        self.metadata += other.metadata;
+        self.artifacts += other.artifacts;
+    }
+}
@@ -0,0 +1,212 @@
+//! Editable installs of pure python projects without building a wheel.
+//!
+//! Instead of asking the build backend to build an editable wheel, which has to be repeated every
+//! time the metadata of the project changes, a `.pth` file is written into site-packages that adds
+//! the source directory of the project to `sys.path`. Together with a `.dist-info` directory that
+//! records the `.pth` file, the project is visible as a regular distribution and can be
+//! uninstalled like any other distribution.
+//!
+//! This only works for projects whose importable packages live in their source tree as is, i.e.
+//! pure python projects without extension modules or generated code. Console scripts of the
+//! project are not created.
+
+use crate::artifacts::wheel::{write_generated_file, UnpackError, UnpackWheelOptions};
+use crate::install::InstallTarget;
+use crate::types::{
+    DirectUrlJson, DirectUrlSource, Record, RecordEntry, WheelCoreMetaDataError, WheelCoreMetadata,
+};
+use fs_err as fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+/// An error that can occur when installing a project in editable mode through a `.pth` file.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum EditableInstallError {
+    #[error("{0} is not a directory")]
+    NotADirectory(PathBuf),
+
+    #[error("failed to parse the metadata of the project")]
+    InvalidMetadata(#[from] WheelCoreMetaDataError),
+
+    #[error(transparent)]
+    FailedToWriteFile(#[from] UnpackError),
+}
+
+/// Information about a project that was installed in editable mode.
+#[derive(Debug)]
+pub struct EditableInstall {
+    /// The path to the `.dist-info` directory of the project.
+    pub dist_info: PathBuf,
+
+    /// The path of the `.pth` file that adds the source directory to `sys.path`.
+    pub pth_file: PathBuf,
+
+    /// The metadata of the project.
+    pub metadata: WheelCoreMetadata,
+}
+
+/// Returns the directory that contains the importable packages of the project at `project_dir`.
+/// Projects that use the `src` layout keep their packages in a `src` directory, otherwise the
+/// packages are located in the root of the project.
+pub fn editable_source_root(project_dir: &Path) -> PathBuf {
+    let src = project_dir.join("src");
+    if src.is_dir() {
+        src
+    } else {
+        project_dir.to_path_buf()
+    }
+}
+
+/// Installs the project at `project_dir` into the `target` in editable mode by writing a `.pth`
+/// file that refers to the source directory of the project, see [`editable_source_root`].
+///
+/// The `metadata` is the content of the `METADATA` file of the project, for instance as returned
+/// by [`crate::wheel_builder::WheelBuilder::get_sdist_metadata`]. Of the `options` only the
+/// `installer` and `requested` fields are used.
+pub fn install_editable_pth<T: InstallTarget + ?Sized>(
+    target: &T,
+    project_dir: &Path,
+    metadata: &[u8],
+    options: &UnpackWheelOptions,
+) -> Result<EditableInstall, EditableInstallError> {
+    if !project_dir.is_dir() {
+        return Err(EditableInstallError::NotADirectory(
+            project_dir.to_path_buf(),
+        ));
+    }
+    let project_dir = dunce::canonicalize(project_dir)
+        .map_err(|e| UnpackError::IoError(project_dir.display().to_string(), e))?;
+    let parsed_metadata = WheelCoreMetadata::try_from(metadata)?;
+
+    let name = parsed_metadata.name.as_str().replace('-', "_");
+    let version = &parsed_metadata.version;
+    let dist_info = PathBuf::from(format!("{name}-{version}.dist-info"));
+    let pth_file = PathBuf::from(format!("__editable__.{name}-{version}.pth"));
+
+    // All paths in the RECORD file are relative to the directory that contains the `.dist-info`
+    // directory.
+    let site_packages = target.root().join(target.install_paths().purelib());
+    fs::create_dir_all(site_packages.join(&dist_info))
+        .map_err(|e| UnpackError::IoError(site_packages.display().to_string(), e))?;
+
+    let mut records = vec![
+        write_generated_file(&dist_info.join("METADATA"), &site_packages, metadata, false)?,
+        write_generated_file(
+            &pth_file,
+            &site_packages,
+            format!("{}\n", editable_source_root(&project_dir).display()),
+            false,
+        )?,
+    ];
+
+    if let Some(installer) = options.installer.as_ref() {
+        records.push(write_generated_file(
+            &dist_info.join("INSTALLER"),
+            &site_packages,
+            format!("{}\n", installer.trim()),
+            false,
+        )?);
+    }
+
+    if options.requested {
+        records.push(write_generated_file(
+            &dist_info.join("REQUESTED"),
+            &site_packages,
+            "",
+            false,
+        )?);
+    }
+
+    let direct_url_json = DirectUrlJson {
+        url: Url::from_directory_path(&project_dir)
+            .expect("a canonicalized path is always absolute"),
+        subdirectory: None,
+        source: DirectUrlSource::Dir {
+            editable: Some(true),
+        },
+    };
+    records.push(write_generated_file(
+        &dist_info.join(DirectUrlJson::FILE_NAME),
+        &site_packages,
+        serde_json::to_string(&direct_url_json).map_err(UnpackError::from)?,
+        false,
+    )?);
+
+    // Write the RECORD file last, it lists itself without a hash
+    let record_path = dist_info.join("RECORD");
+    records.push(RecordEntry {
+        path: record_path.display().to_string().replace('\\', "/"),
+        hash: None,
+        size: None,
+    });
+    Record::from_iter(records)
+        .write_to_path(&site_packages.join(&record_path))
+        .map_err(UnpackError::from)?;
+
+    Ok(EditableInstall {
+        dist_info: site_packages.join(dist_info),
+        pth_file: site_packages.join(pth_file),
+        metadata: parsed_metadata,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::{find_distributions_in_venv, uninstall_distribution, TargetDirectory};
+
+    #[test]
+    fn test_install_editable_pth() {
+        let project = tempfile::tempdir().unwrap();
+        fs::create_dir_all(project.path().join("src/my_project")).unwrap();
+        fs::write(project.path().join("src/my_project/__init__.py"), "").unwrap();
+
+        let env = tempfile::tempdir().unwrap();
+        let target = TargetDirectory::new(env.path(), "/usr/bin/python3");
+        let installed = install_editable_pth(
+            &target,
+            project.path(),
+            b"Metadata-Version: 2.1\nName: my-project\nVersion: 0.1.0\n",
+            &UnpackWheelOptions {
+                installer: Some(String::from("rip")),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let pth = fs::read_to_string(&installed.pth_file).unwrap();
+        assert_eq!(
+            pth.trim(),
+            dunce::canonicalize(project.path().join("src"))
+                .unwrap()
+                .display()
+                .to_string()
+        );
+
+        // The project is visible as an editable distribution
+        let distributions =
+            find_distributions_in_venv(target.root(), target.install_paths()).unwrap();
+        assert_eq!(distributions.len(), 1);
+        assert_eq!(distributions[0].name.as_str(), "my-project");
+        assert!(distributions[0].is_editable());
+
+        // Uninstalling removes the .pth file and the .dist-info directory
+        uninstall_distribution(env.path(), Path::new("my_project-0.1.0.dist-info")).unwrap();
+        assert!(!installed.pth_file.exists());
+        assert!(!installed.dist_info.exists());
+        assert!(project.path().join("src/my_project/__init__.py").is_file());
+    }
+
+    #[test]
+    fn test_editable_source_root() {
+        let project = tempfile::tempdir().unwrap();
+        assert_eq!(editable_source_root(project.path()), project.path());
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        assert_eq!(
+            editable_source_root(project.path()),
+            project.path().join("src")
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Running commands in cached, throwaway environments, similar to `pipx run` or `uvx`.
+//!
+//! An environment is provisioned for a set of requirements the first time a command is run with
+//! them. Subsequent runs with the same requirements and interpreter reuse the environment, so only
+//! the first invocation pays for resolving and installing the packages.
+//!
+//! Single-file scripts that declare their dependencies with
+//! [PEP 723](https://peps.python.org/pep-0723/) metadata can be run with
+//! [`EphemeralEnvironments::provision_script`].
+
+use crate::artifacts::wheel::UnpackWheelOptions;
+use crate::index::PackageDb;
+use crate::install::{sync_environment, InstallOptions, UnpackedWheelCache, WheelFileCache};
+use crate::python_env::{
+    find_interpreter, Implementation, Pep508EnvMakers, PythonLocation, VEnv, WheelTags,
+};
+use crate::resolve::resolve;
+use crate::resolve::solve_options::ResolveOptions;
+use crate::types::{Requirement, ScriptMetadata, Version, VersionSpecifiers};
+use crate::wheel_builder::WheelBuilder;
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use rattler_digest::Sha256;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The name of the file that marks an environment as completely provisioned.
+const COMPLETE_MARKER: &str = ".rip-complete";
+
+/// A directory of environments that are provisioned on demand for a set of requirements.
+#[derive(Debug, Clone)]
+pub struct EphemeralEnvironments {
+    root: PathBuf,
+}
+
+/// An environment that was provisioned by [`EphemeralEnvironments::provision`].
+#[derive(Debug)]
+pub struct EphemeralEnvironment {
+    venv: VEnv,
+    reused: bool,
+}
+
+impl EphemeralEnvironments {
+    /// Constructs a new instance that stores the environments in the specified directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory in which the environments are stored.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the location of the environment for the given `requirements` and python
+    /// interpreter. The order of the requirements does not matter.
+    pub fn environment_dir(&self, requirements: &[Requirement], python: &Path) -> PathBuf {
+        let mut requirements = requirements
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        requirements.sort();
+        requirements.dedup();
+
+        let key = serde_json::json!({
+            "requirements": requirements,
+            "python": python,
+        });
+        let digest = rattler_digest::compute_bytes_digest::<Sha256>(key.to_string());
+        self.root.join(format!("{digest:x}"))
+    }
+
+    /// Returns an environment that contains the packages that satisfy the `requirements`. An
+    /// environment that was provisioned previously for the same requirements and interpreter is
+    /// reused, otherwise the requirements are resolved and installed into a new environment.
+    ///
+    /// The interpreter of the environment is taken from [`ResolveOptions::python_location`].
+    pub async fn provision(
+        &self,
+        package_db: Arc<PackageDb>,
+        requirements: &[Requirement],
+        resolve_options: ResolveOptions,
+    ) -> miette::Result<EphemeralEnvironment> {
+        let python = resolve_options
+            .python_location
+            .executable()
+            .into_diagnostic()?;
+        let env_dir = self.environment_dir(requirements, &python);
+
+        if env_dir.join(COMPLETE_MARKER).is_file() {
+            tracing::debug!("reusing the environment at {}", env_dir.display());
+            let venv = VEnv::create(&env_dir, resolve_options.python_location).into_diagnostic()?;
+            return Ok(EphemeralEnvironment { venv, reused: true });
+        }
+
+        // Remove the remains of a provisioning that did not complete
+        if env_dir.exists() {
+            fs::remove_dir_all(&env_dir).into_diagnostic()?;
+        }
+
+        let env_markers = Arc::new(
+            Pep508EnvMakers::from_python(&python)
+                .await
+                .into_diagnostic()
+                .wrap_err("failed to determine the environment markers of the interpreter")?
+                .0,
+        );
+        let wheel_tags = Arc::new(
+            WheelTags::from_python(&python)
+                .await
+                .into_diagnostic()
+                .wrap_err("failed to determine the compatible wheel tags of the interpreter")?,
+        );
+
+        let blueprint = resolve(
+            package_db.clone(),
+            requirements,
+            env_markers.clone(),
+            Some(wheel_tags.clone()),
+            HashMap::default(),
+            HashMap::default(),
+            resolve_options.clone(),
+            HashMap::default(),
+        )
+        .await?;
+
+        let wheel_builder = WheelBuilder::new(
+            package_db.clone(),
+            env_markers,
+            Some(wheel_tags),
+            resolve_options.clone(),
+            HashMap::default(),
+        )
+        .into_diagnostic()?;
+
+        let venv = VEnv::create(&env_dir, resolve_options.python_location).into_diagnostic()?;
+        sync_environment(
+            &venv,
+            &package_db,
+            Some(&wheel_builder),
+            &blueprint,
+            &UnpackWheelOptions {
+                installer: Some(String::from("rip")),
+                install_options: InstallOptions {
+                    file_cache: Some(WheelFileCache::new(
+                        package_db.cache_dir().join("wheel-files"),
+                    )),
+                    unpacked_cache: Some(UnpackedWheelCache::new(
+                        package_db.cache_dir().join("unpacked-wheels"),
+                    )),
+                    ..InstallOptions::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        fs::write(env_dir.join(COMPLETE_MARKER), "").into_diagnostic()?;
+        Ok(EphemeralEnvironment {
+            venv,
+            reused: false,
+        })
+    }
+
+    /// Returns an environment that contains the dependencies that the script at `script` declares
+    /// in its [PEP 723](https://peps.python.org/pep-0723/) metadata, together with the
+    /// `additional_requirements`. Scripts without metadata only get the additional requirements.
+    ///
+    /// If the script specifies `requires-python` and the interpreter from the `resolve_options` is
+    /// the system interpreter that does not satisfy it, a matching interpreter is selected from
+    /// the `PATH` instead. An explicitly specified interpreter that does not satisfy it results in
+    /// an error. Use [`EphemeralEnvironment::python_command`] to run the script.
+    pub async fn provision_script(
+        &self,
+        package_db: Arc<PackageDb>,
+        script: &Path,
+        additional_requirements: &[Requirement],
+        mut resolve_options: ResolveOptions,
+    ) -> miette::Result<EphemeralEnvironment> {
+        let metadata = ScriptMetadata::from_path(script)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read the metadata of {}", script.display()))?
+            .unwrap_or_default();
+
+        if let Some(requires_python) = &metadata.requires_python {
+            if !interpreter_satisfies(&resolve_options.python_location, requires_python)? {
+                if resolve_options.python_location != PythonLocation::System {
+                    miette::bail!(
+                        "the python interpreter does not satisfy the requirement of the script: \
+                        python{requires_python}"
+                    );
+                }
+                let interpreter =
+                    find_interpreter(requires_python, Implementation::Any).into_diagnostic()?;
+                tracing::info!(
+                    "selected python {}.{}.{} at {} for {}",
+                    interpreter.version.major,
+                    interpreter.version.minor,
+                    interpreter.version.patch,
+                    interpreter.path.display(),
+                    script.display()
+                );
+                resolve_options.python_location =
+                    PythonLocation::CustomWithVersion(interpreter.path, interpreter.version);
+            }
+        }
+
+        let mut requirements = metadata.dependencies;
+        requirements.extend(additional_requirements.iter().cloned());
+        self.provision(package_db, &requirements, resolve_options)
+            .await
+    }
+}
+
+/// Returns true if the version of the interpreter at `python_location` satisfies `specifiers`.
+fn interpreter_satisfies(
+    python_location: &PythonLocation,
+    specifiers: &VersionSpecifiers,
+) -> miette::Result<bool> {
+    let version = python_location.version().into_diagnostic()?;
+    let version = Version::from_str(&format!(
+        "{}.{}.{}",
+        version.major, version.minor, version.patch
+    ))
+    .expect("a python interpreter version is always a valid version");
+    Ok(specifiers.contains(&version))
+}
+
+impl EphemeralEnvironment {
+    /// Returns the virtual environment.
+    pub fn venv(&self) -> &VEnv {
+        &self.venv
+    }
+
+    /// Returns true if the environment was provisioned by an earlier invocation.
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Returns a [`Command`] that runs `program` inside the environment. If `program` is a script
+    /// of the environment it is run from the scripts directory, otherwise it is looked up on the
+    /// `PATH` which has the scripts directory prepended.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let scripts_dir = self.venv.root().join(self.venv.install_paths().scripts());
+        let program = program.as_ref();
+
+        let mut candidates = vec![scripts_dir.join(program)];
+        if cfg!(windows) {
+            let mut exe = program.to_os_string();
+            exe.push(".exe");
+            candidates.push(scripts_dir.join(exe));
+        }
+        let program = candidates
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+            .map_or_else(|| program.to_os_string(), PathBuf::into_os_string);
+
+        let mut paths = vec![scripts_dir];
+        if let Some(path) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&path));
+        }
+        let path = std::env::join_paths(paths).unwrap_or_else(|_| OsString::from(""));
+
+        let mut command = Command::new(program);
+        command
+            .env("PATH", path)
+            .env("VIRTUAL_ENV", self.venv.root())
+            .env_remove("PYTHONHOME");
+        command
+    }
+
+    /// Returns a [`Command`] that runs the python `script` with the interpreter of the
+    /// environment.
+    pub fn python_command(&self, script: impl AsRef<OsStr>) -> Command {
+        let mut command = self.command(self.venv.python_executable());
+        command.arg(script.as_ref());
+        command
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_environment_dir() {
+        let environments = EphemeralEnvironments::new("/cache/environments");
+        let python = Path::new("/usr/bin/python3");
+        let black = Requirement::from_str("black>=23").unwrap();
+        let click = Requirement::from_str("click").unwrap();
+
+        let dir = environments.environment_dir(&[black.clone(), click.clone()], python);
+        assert!(dir.starts_with("/cache/environments"));
+
+        // The order of the requirements does not matter
+        assert_eq!(
+            dir,
+            environments.environment_dir(&[click.clone(), black.clone()], python)
+        );
+
+        // Different requirements or interpreters result in a different environment
+        assert_ne!(dir, environments.environment_dir(&[black.clone()], python));
+        assert_ne!(
+            dir,
+            environments.environment_dir(&[black, click], Path::new("/usr/bin/python3.12"))
+        );
+    }
+}
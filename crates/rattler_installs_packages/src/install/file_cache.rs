@@ -0,0 +1,199 @@
+use data_encoding::BASE64URL_NOPAD;
+use fs_err as fs;
+use rattler_digest::Sha256;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// A content-addressed store of files extracted from wheels. Files are stored by their sha256 hash
+/// so that files which are shared between wheels, or between different installations of the same
+/// wheel, only have to be extracted once. Files are placed into an environment from the cache
+/// using a [`super::LinkStrategy`].
+#[derive(Debug, Clone)]
+pub struct WheelFileCache {
+    root: PathBuf,
+}
+
+/// A file that is stored in a [`WheelFileCache`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CachedWheelEntry {
+    /// The location of the file in the cache
+    pub path: PathBuf,
+
+    /// The hash of the file in the format used by RECORD files, e.g. `sha256=<urlsafe base64>`.
+    pub hash: String,
+
+    /// The size of the file in bytes
+    pub size: u64,
+}
+
+impl WheelFileCache {
+    /// Constructs a new cache that stores its files in the specified directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory in which the files are stored.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the location of the file with the given hash. Executable files are stored
+    /// separately because a hardlink shares its permissions with the file it links to.
+    fn entry_path(&self, hash: &str, executable: bool) -> Option<PathBuf> {
+        let digest = hash.strip_prefix("sha256=")?;
+        if digest.len() < 3
+            || !digest
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+        let (prefix, rest) = digest.split_at(2);
+        let file_name = if executable {
+            format!("{rest}.x")
+        } else {
+            rest.to_owned()
+        };
+        Some(self.root.join(prefix).join(file_name))
+    }
+
+    /// Returns the cached file with the specified RECORD hash if it exists.
+    pub fn get(&self, hash: &str, executable: bool) -> Option<CachedWheelEntry> {
+        let path = self.entry_path(hash, executable)?;
+        let metadata = fs::metadata(&path).ok()?;
+        Some(CachedWheelEntry {
+            path,
+            hash: hash.to_owned(),
+            size: metadata.len(),
+        })
+    }
+
+    /// Stores the contents of `reader` in the cache and returns the resulting entry.
+    pub fn insert(&self, reader: &mut impl Read, executable: bool) -> io::Result<CachedWheelEntry> {
+        fs::create_dir_all(&self.root)?;
+
+        // Write the contents to a temporary file first, we only know the hash afterwards.
+        let mut temp_file = tempfile::NamedTempFile::new_in(&self.root)?;
+        let (hash, size) = copy_and_hash(reader, temp_file.as_file_mut())?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = if executable { 0o755 } else { 0o644 };
+            temp_file
+                .as_file()
+                .set_permissions(std::fs::Permissions::from_mode(mode))?;
+        }
+
+        let path = self
+            .entry_path(&hash, executable)
+            .expect("the hash we computed is always valid");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Another process might have stored the same file concurrently, in which case the
+        // existing file is kept.
+        if let Err(err) = temp_file.persist_noclobber(&path) {
+            if !path.is_file() {
+                return Err(err.error);
+            }
+        }
+
+        Ok(CachedWheelEntry { path, hash, size })
+    }
+
+    /// Checks that the contents of every file in the cache match the hash it is stored under.
+    /// Returns the paths of the files that do not match.
+    pub fn verify(&self) -> io::Result<Vec<PathBuf>> {
+        let mut corrupted = Vec::new();
+        if !self.root.is_dir() {
+            return Ok(corrupted);
+        }
+
+        for prefix in fs::read_dir(&self.root)? {
+            let prefix = prefix?;
+            // Files directly in the root are temporary files of interrupted insertions.
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(prefix.path())? {
+                let path = entry?.path();
+                let (Some(prefix), Some(file_name)) = (
+                    prefix.file_name().to_str().map(ToOwned::to_owned),
+                    path.file_name().and_then(|name| name.to_str()),
+                ) else {
+                    corrupted.push(path);
+                    continue;
+                };
+                let expected = format!(
+                    "sha256={prefix}{}",
+                    file_name.strip_suffix(".x").unwrap_or(file_name)
+                );
+                let (hash, _) = copy_and_hash(&mut fs::File::open(&path)?, &mut io::sink())?;
+                if hash != expected {
+                    corrupted.push(path);
+                }
+            }
+        }
+        Ok(corrupted)
+    }
+}
+
+/// Copies the contents of `reader` to `writer` and returns the hash of the contents in the format
+/// used by RECORD files, together with the number of bytes that were copied.
+pub(crate) fn copy_and_hash(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> io::Result<(String, u64)> {
+    let mut reader = rattler_digest::HashingReader::<_, Sha256>::new(reader);
+    let size = io::copy(&mut reader, writer)?;
+    let (_, digest) = reader.finalize();
+    Ok((format!("sha256={}", BASE64URL_NOPAD.encode(&digest)), size))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WheelFileCache::new(dir.path());
+
+        let entry = cache.insert(&mut "hello".as_bytes(), false).unwrap();
+        assert_eq!(
+            entry.hash,
+            "sha256=LPJNul-wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ"
+        );
+        assert_eq!(entry.size, 5);
+        assert_eq!(fs::read_to_string(&entry.path).unwrap(), "hello");
+
+        // Inserting the same content again results in the same entry
+        assert_eq!(cache.insert(&mut "hello".as_bytes(), false).unwrap(), entry);
+        assert_eq!(cache.get(&entry.hash, false), Some(entry.clone()));
+
+        // Executables are stored separately
+        assert_eq!(cache.get(&entry.hash, true), None);
+        assert_ne!(
+            cache.insert(&mut "hello".as_bytes(), true).unwrap().path,
+            entry.path
+        );
+
+        assert_eq!(cache.get("md5=abc", false), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = WheelFileCache::new(dir.path());
+        assert!(cache.verify().unwrap().is_empty());
+
+        let entry = cache.insert(&mut "hello".as_bytes(), false).unwrap();
+        cache.insert(&mut "world".as_bytes(), true).unwrap();
+        assert!(cache.verify().unwrap().is_empty());
+
+        fs::write(&entry.path, "goodbye").unwrap();
+        assert_eq!(cache.verify().unwrap(), vec![entry.path]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Hooks that allow integrators to run custom logic around the installation and removal of
+//! distributions, e.g. to label files, to scan them or for custom logging.
+
+use crate::artifacts::wheel::{InstallPaths, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::python_env::Distribution;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// The error type that is returned by an [`InstallHooks`] implementation.
+pub type HookError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The environment that a hook is invoked for.
+#[derive(Debug, Clone, Copy)]
+pub struct HookTarget<'a> {
+    /// The root of the environment, see [`crate::install::InstallTarget::root`].
+    pub root: &'a Path,
+
+    /// The locations of the different parts of a wheel relative to `root`.
+    pub install_paths: &'a InstallPaths,
+}
+
+/// Hooks that are invoked around the installation and the removal of every distribution by
+/// [`crate::install::install_wheels`] and [`crate::install::execute_sync_plan`].
+///
+/// All methods do nothing by default. Returning an error from a hook aborts the operation. Because
+/// wheels can be installed in parallel, hooks may be invoked concurrently from multiple threads.
+pub trait InstallHooks: Debug + Send + Sync {
+    /// Called before the `wheel` is installed into the `target`.
+    fn pre_install(&self, _wheel: &Wheel, _target: HookTarget<'_>) -> Result<(), HookError> {
+        Ok(())
+    }
+
+    /// Called after a wheel was installed into the `target`. The `installed` wheel contains the
+    /// location of its `.dist-info` directory and its metadata.
+    fn post_install(
+        &self,
+        _installed: &UnpackedWheel,
+        _target: HookTarget<'_>,
+    ) -> Result<(), HookError> {
+        Ok(())
+    }
+
+    /// Called before the `distribution` is removed from the `target`. The `dist_info` path of the
+    /// distribution is relative to the root of the target.
+    fn pre_uninstall(
+        &self,
+        _distribution: &Distribution,
+        _target: HookTarget<'_>,
+    ) -> Result<(), HookError> {
+        Ok(())
+    }
+
+    /// Called after the `distribution` was removed from the `target`.
+    fn post_uninstall(
+        &self,
+        _distribution: &Distribution,
+        _target: HookTarget<'_>,
+    ) -> Result<(), HookError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::UnpackWheelOptions;
+    use crate::install::{install_wheels, InstallOptions, InstallWheelsError};
+    use crate::python_env::TargetDirectory;
+    use crate::types::NormalizedPackageName;
+    use parking_lot::Mutex;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[derive(Debug, Default)]
+    struct RecordingHooks {
+        calls: Mutex<Vec<String>>,
+        reject: bool,
+    }
+
+    impl InstallHooks for RecordingHooks {
+        fn pre_install(&self, wheel: &Wheel, _target: HookTarget<'_>) -> Result<(), HookError> {
+            self.calls
+                .lock()
+                .push(format!("pre_install {}", wheel.name.distribution.as_str()));
+            if self.reject {
+                return Err("rejected".into());
+            }
+            Ok(())
+        }
+
+        fn post_install(
+            &self,
+            installed: &UnpackedWheel,
+            target: HookTarget<'_>,
+        ) -> Result<(), HookError> {
+            assert!(installed.dist_info.starts_with(target.root));
+            self.calls
+                .lock()
+                .push(format!("post_install {}", installed.metadata.name.as_str()));
+            Ok(())
+        }
+    }
+
+    fn install_miniblack(
+        target: &TargetDirectory,
+        hooks: Arc<RecordingHooks>,
+    ) -> Result<(), InstallWheelsError> {
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+        let options = UnpackWheelOptions {
+            install_options: InstallOptions {
+                hooks: Some(hooks),
+                ..InstallOptions::default()
+            },
+            ..Default::default()
+        };
+        install_wheels(target, vec![(wheel, options)], None).map(|_| ())
+    }
+
+    #[test]
+    fn test_install_hooks() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = TargetDirectory::new(tmpdir.path(), "/usr/bin/python3");
+
+        let hooks = Arc::new(RecordingHooks::default());
+        install_miniblack(&target, hooks.clone()).unwrap();
+        assert_eq!(
+            *hooks.calls.lock(),
+            ["pre_install miniblack", "post_install miniblack"]
+        );
+    }
+
+    #[test]
+    fn test_rejecting_hook_aborts_install() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = TargetDirectory::new(tmpdir.path(), "/usr/bin/python3");
+
+        let hooks = Arc::new(RecordingHooks {
+            reject: true,
+            ..RecordingHooks::default()
+        });
+        let result = install_miniblack(&target, hooks.clone());
+        assert!(matches!(result, Err(InstallWheelsError::HookFailed(..))));
+        assert_eq!(*hooks.calls.lock(), ["pre_install miniblack"]);
+        assert!(!tmpdir.path().join("black").exists());
+    }
+}
@@ -0,0 +1,560 @@
+//! Batches the creation of the files of a wheel that is installed through io_uring on Linux.
+//!
+//! Installing a wheel with thousands of small files spends most of its time in system calls, every
+//! file requires a separate call to remove, open, write and close it. A [`FileBatch`] queues the
+//! files, and the hardlinks to files in the [`super::WheelFileCache`], and submits them in batches
+//! of [`BATCH_SIZE`] through a single io_uring. The backend is only available when the `io-uring`
+//! feature is enabled, [`FileBatch::new`] returns `None` otherwise or when the kernel does not
+//! support the required operations, in which case files are written one by one.
+
+/// The maximum size of a file that is queued in a [`FileBatch`]. Larger files are written
+/// directly, the contents of queued files are kept in memory until the batch is submitted.
+pub(crate) const MAX_BATCHED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// The maximum number of files and hardlinks that are submitted at once.
+#[cfg_attr(not(all(target_os = "linux", feature = "io-uring")), allow(dead_code))]
+const BATCH_SIZE: usize = 128;
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub(crate) use ring::FileBatch;
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+pub(crate) use unsupported::FileBatch;
+
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+mod unsupported {
+    use std::io;
+    use std::path::Path;
+
+    /// A batch of files that can never be constructed because io_uring is not available.
+    pub(crate) struct FileBatch(std::convert::Infallible);
+
+    impl FileBatch {
+        /// Always returns `None`, io_uring is not available on this platform or the `io-uring`
+        /// feature is not enabled.
+        pub(crate) fn new() -> Option<Self> {
+            None
+        }
+
+        pub(crate) fn write(&mut self, _: &Path, _: Vec<u8>, _: bool) -> io::Result<()> {
+            match self.0 {}
+        }
+
+        pub(crate) fn hard_link(&mut self, _: &Path, _: &Path) -> io::Result<()> {
+            match self.0 {}
+        }
+
+        pub(crate) fn flush(&mut self) -> io::Result<()> {
+            match self.0 {}
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod ring {
+    use super::BATCH_SIZE;
+    use crate::install::LinkStrategy;
+    use fs_err as fs;
+    use io_uring::{opcode, squeue, types, EnterFlags, IoUring, Probe};
+    use std::collections::HashSet;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    /// Two operations are submitted for every queued file or hardlink.
+    const RING_ENTRIES: u32 = (BATCH_SIZE * 2) as u32;
+
+    /// The operations that are used by a [`FileBatch`], `IORING_OP_LINKAT` requires Linux 5.15.
+    const REQUIRED_OPS: [(&str, u8); 4] = [
+        ("unlinkat", opcode::UnlinkAt::CODE),
+        ("openat", opcode::OpenAt::CODE),
+        ("write", opcode::Write::CODE),
+        ("linkat", opcode::LinkAt::CODE),
+    ];
+
+    /// Relative paths are resolved against the current directory, like the `std::fs` functions do.
+    const CWD: types::Fd = types::Fd(libc::AT_FDCWD);
+
+    enum Operation {
+        Write {
+            destination: PathBuf,
+            path: CString,
+            contents: Vec<u8>,
+            executable: bool,
+        },
+        HardLink {
+            source: PathBuf,
+            destination: PathBuf,
+            source_path: CString,
+            path: CString,
+        },
+    }
+
+    impl Operation {
+        fn destination(&self) -> &Path {
+            match self {
+                Operation::Write { destination, .. } | Operation::HardLink { destination, .. } => {
+                    destination
+                }
+            }
+        }
+
+        fn path(&self) -> &CString {
+            match self {
+                Operation::Write { path, .. } | Operation::HardLink { path, .. } => path,
+            }
+        }
+    }
+
+    /// Queues files and hardlinks and creates them in batches through io_uring, see the
+    /// [module docs](super). Existing files at the destinations are replaced. The queued operations
+    /// are only guaranteed to be performed after [`FileBatch::flush`] returns successfully.
+    pub(crate) struct FileBatch {
+        ring: Ring,
+        operations: Vec<Operation>,
+        created_dirs: HashSet<PathBuf>,
+    }
+
+    impl FileBatch {
+        /// Constructs a new batch, returns `None` if io_uring is not available, e.g. because it is
+        /// disabled in a container or because the kernel is too old.
+        pub(crate) fn new() -> Option<Self> {
+            match Ring::new() {
+                Ok(ring) => Some(Self {
+                    ring,
+                    operations: Vec::with_capacity(BATCH_SIZE),
+                    created_dirs: HashSet::new(),
+                }),
+                Err(err) => {
+                    tracing::debug!("io_uring is not available, writing files directly ({err})");
+                    None
+                }
+            }
+        }
+
+        /// Queues writing `contents` to a new file at `destination`.
+        pub(crate) fn write(
+            &mut self,
+            destination: &Path,
+            contents: Vec<u8>,
+            executable: bool,
+        ) -> io::Result<()> {
+            self.push(Operation::Write {
+                destination: destination.to_path_buf(),
+                path: c_path(destination)?,
+                contents,
+                executable,
+            })
+        }
+
+        /// Queues creating a hardlink at `destination` to the file at `source`. If the hardlink
+        /// cannot be created the file is copied instead.
+        pub(crate) fn hard_link(&mut self, source: &Path, destination: &Path) -> io::Result<()> {
+            self.push(Operation::HardLink {
+                source: source.to_path_buf(),
+                destination: destination.to_path_buf(),
+                source_path: c_path(source)?,
+                path: c_path(destination)?,
+            })
+        }
+
+        fn push(&mut self, operation: Operation) -> io::Result<()> {
+            if let Some(parent) = operation.destination().parent() {
+                if !self.created_dirs.contains(parent) {
+                    fs::create_dir_all(parent)?;
+                    self.created_dirs.insert(parent.to_path_buf());
+                }
+            }
+            self.operations.push(operation);
+            if self.operations.len() >= BATCH_SIZE {
+                self.flush()?;
+            }
+            Ok(())
+        }
+
+        /// Performs all queued operations.
+        pub(crate) fn flush(&mut self) -> io::Result<()> {
+            if self.operations.is_empty() {
+                return Ok(());
+            }
+            let operations = std::mem::take(&mut self.operations);
+
+            // Remove the existing files and open the new files or create the hardlinks. The
+            // removal is linked to the second operation with a hardlink so the chain continues
+            // when there is no file to remove.
+            let mut sqes = Vec::with_capacity(operations.len() * 2);
+            for (index, operation) in operations.iter().enumerate() {
+                sqes.push(
+                    opcode::UnlinkAt::new(CWD, operation.path().as_ptr())
+                        .build()
+                        .flags(squeue::Flags::IO_HARDLINK)
+                        .user_data(u64::MAX),
+                );
+
+                let sqe = match operation {
+                    Operation::Write {
+                        path, executable, ..
+                    } => opcode::OpenAt::new(CWD, path.as_ptr())
+                        .flags(libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC)
+                        .mode(if *executable { 0o777 } else { 0o666 })
+                        .build(),
+                    Operation::HardLink {
+                        source_path, path, ..
+                    } => opcode::LinkAt::new(CWD, source_path.as_ptr(), CWD, path.as_ptr()).build(),
+                };
+                sqes.push(sqe.user_data(index as u64));
+            }
+
+            let mut results = Vec::with_capacity(sqes.len());
+            let submitted = self.ring.submit_and_wait(&sqes, &mut results);
+            let mut files = Vec::new();
+            let mut first_error = None;
+            for (user_data, result) in results {
+                let Some(operation) = operations.get(user_data as usize) else {
+                    // The result of removing an existing file is ignored, a failure to remove it
+                    // also fails the next operation.
+                    continue;
+                };
+                match (operation, result) {
+                    (Operation::Write { contents, .. }, Ok(fd)) => {
+                        // SAFETY: The file was opened by this operation and is not owned by
+                        // anything else.
+                        let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+                        files.push((fd, contents, operation.destination()));
+                    }
+                    (
+                        Operation::HardLink {
+                            source,
+                            destination,
+                            ..
+                        },
+                        Err(err),
+                    ) => {
+                        tracing::debug!(
+                            "failed to hardlink {} to {}, falling back to linking directly ({err})",
+                            source.display(),
+                            destination.display()
+                        );
+                        if let Err(err) = LinkStrategy::Hardlink.link(source, destination) {
+                            first_error.get_or_insert(err);
+                        }
+                    }
+                    (Operation::HardLink { .. }, Ok(_)) => {}
+                    (Operation::Write { destination, .. }, Err(err)) => {
+                        first_error.get_or_insert(with_path(err, destination));
+                    }
+                }
+            }
+            // The files that were opened are closed when `files` is dropped.
+            submitted?;
+
+            // Write the contents of the files that were opened. The files are closed when `files`
+            // is dropped, also when submitting the writes fails.
+            let sqes = files
+                .iter()
+                .enumerate()
+                .map(|(index, (fd, contents, _))| {
+                    opcode::Write::new(
+                        types::Fd(fd.as_raw_fd()),
+                        contents.as_ptr(),
+                        contents.len() as u32,
+                    )
+                    .offset(0)
+                    .build()
+                    .user_data(index as u64)
+                })
+                .collect::<Vec<_>>();
+            let mut results = Vec::with_capacity(sqes.len());
+            let submitted = self.ring.submit_and_wait(&sqes, &mut results);
+            for (user_data, result) in results {
+                let Some((_, contents, destination)) = files.get(user_data as usize) else {
+                    continue;
+                };
+                match result {
+                    Ok(written) if written as usize == contents.len() => {}
+                    // Writes to regular files are not expected to be short, but if they are the
+                    // file is simply written again.
+                    Ok(_) => {
+                        if let Err(err) = fs::write(destination, contents) {
+                            first_error.get_or_insert(err);
+                        }
+                    }
+                    Err(err) => {
+                        first_error.get_or_insert(with_path(err, destination));
+                    }
+                }
+            }
+            submitted?;
+
+            match first_error {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+    }
+
+    fn c_path(path: &Path) -> io::Result<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+
+    fn with_path(err: io::Error, path: &Path) -> io::Error {
+        io::Error::new(err.kind(), format!("{err} ({})", path.display()))
+    }
+
+    /// Submits batches of operations to an io_uring instance and waits for them to complete.
+    struct Ring {
+        /// `None` after a failed submission, a new instance is created for the next submission.
+        ring: Option<IoUring>,
+        /// Skips the given number of submissions and then makes the first call to
+        /// `io_uring_enter` of the next submission fail after it submitted the operations.
+        #[cfg(test)]
+        fail_submission: Option<(usize, i32)>,
+    }
+
+    impl Ring {
+        fn new() -> io::Result<Self> {
+            let ring = IoUring::new(RING_ENTRIES)?;
+            let mut probe = Probe::new();
+            ring.submitter().register_probe(&mut probe)?;
+            for (name, code) in REQUIRED_OPS {
+                if !probe.is_supported(code) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Unsupported,
+                        format!("the kernel does not support {name}"),
+                    ));
+                }
+            }
+            Ok(Self {
+                ring: Some(ring),
+                #[cfg(test)]
+                fail_submission: None,
+            })
+        }
+
+        /// Submits the operations and waits for all of them to complete, the user data and the
+        /// result of every completed operation are added to `results`.
+        ///
+        /// If submitting fails the operations that were not yet consumed by the kernel are
+        /// discarded, but this only returns after all the operations that were consumed have
+        /// completed. The memory that the operations refer to can therefore always be released
+        /// after this returns, and no completions are left behind for the next call.
+        fn submit_and_wait(
+            &mut self,
+            sqes: &[squeue::Entry],
+            results: &mut Vec<(u64, io::Result<u32>)>,
+        ) -> io::Result<()> {
+            #[cfg(test)]
+            let mut fail_enter = match &mut self.fail_submission {
+                Some((0, errno)) => {
+                    let errno = *errno;
+                    self.fail_submission = None;
+                    Some(errno)
+                }
+                Some((skip, _)) => {
+                    *skip -= 1;
+                    None
+                }
+                None => None,
+            };
+
+            let ring = match self.ring.take() {
+                Some(ring) => ring,
+                None => IoUring::new(RING_ENTRIES)?,
+            };
+            let ring = self.ring.insert(ring);
+            // SAFETY: The entries only refer to memory that outlives this call, and this only
+            // returns after all the operations that the kernel consumed have completed.
+            unsafe { ring.submission().push_multiple(sqes) }.map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    "the io_uring submission queue is full",
+                )
+            })?;
+
+            // Submit the operations and wait for them to complete. After an error only the
+            // operations that were consumed by the kernel complete.
+            let mut completed = 0;
+            let mut error: Option<(io::Error, usize)> = None;
+            loop {
+                let expected = error.as_ref().map_or(sqes.len(), |(_, consumed)| *consumed);
+                if completed >= expected {
+                    break;
+                }
+                let entered = match error {
+                    None => ring.submit_and_wait(expected - completed),
+                    // SAFETY: Nothing is submitted, this only waits for completions.
+                    Some(_) => unsafe {
+                        ring.submitter().enter::<libc::sigset_t>(
+                            0,
+                            (expected - completed) as u32,
+                            EnterFlags::GETEVENTS.bits(),
+                            None,
+                        )
+                    },
+                };
+                #[cfg(test)]
+                let entered = match fail_enter.take() {
+                    Some(errno) => entered.and(Err(io::Error::from_raw_os_error(errno))),
+                    None => entered,
+                };
+                match entered {
+                    Ok(_) => {}
+                    Err(err)
+                        if matches!(
+                            err.raw_os_error(),
+                            Some(libc::EINTR | libc::EAGAIN | libc::EBUSY)
+                        ) => {}
+                    Err(err) if error.is_none() => {
+                        let consumed = sqes.len() - ring.submission().len();
+                        error = Some((err, consumed));
+                    }
+                    // Waiting for the operations that are still in flight failed as well, give the
+                    // kernel a chance to complete them before checking again.
+                    Err(_) => std::thread::yield_now(),
+                }
+                for cqe in ring.completion() {
+                    let result = cqe.result();
+                    results.push((
+                        cqe.user_data(),
+                        if result < 0 {
+                            Err(io::Error::from_raw_os_error(-result))
+                        } else {
+                            Ok(result as u32)
+                        },
+                    ));
+                    completed += 1;
+                }
+            }
+
+            match error {
+                Some((err, _)) => {
+                    // The operations that were not consumed are still queued, drop the instance so
+                    // they are never submitted. Nothing is in flight anymore.
+                    self.ring = None;
+                    Err(err)
+                }
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        /// Returns a new batch, or `None` if io_uring is not available in this environment, e.g.
+        /// because it is blocked by seccomp in a container.
+        fn file_batch() -> Option<FileBatch> {
+            let batch = FileBatch::new();
+            if batch.is_none() {
+                eprintln!("io_uring is not available in this environment, skipping the test");
+            }
+            batch
+        }
+
+        #[test]
+        fn test_file_batch() {
+            let Some(mut batch) = file_batch() else {
+                return;
+            };
+
+            let dir = tempfile::tempdir().unwrap();
+            let source = dir.path().join("source");
+            fs::write(&source, "linked").unwrap();
+
+            // Write more files than fit in a single batch, replacing an existing file
+            fs::create_dir_all(dir.path().join("files")).unwrap();
+            fs::write(dir.path().join("files/0"), "existing").unwrap();
+            for index in 0..BATCH_SIZE + 10 {
+                let destination = dir.path().join(format!("files/{index}"));
+                batch
+                    .write(&destination, index.to_string().into_bytes(), index == 1)
+                    .unwrap();
+            }
+            batch
+                .hard_link(&source, &dir.path().join("nested/dir/link"))
+                .unwrap();
+            batch.flush().unwrap();
+
+            for index in 0..BATCH_SIZE + 10 {
+                let destination = dir.path().join(format!("files/{index}"));
+                assert_eq!(fs::read_to_string(destination).unwrap(), index.to_string());
+            }
+            let mode = |path: &Path| {
+                use std::os::unix::fs::PermissionsExt;
+                fs::metadata(path).unwrap().permissions().mode() & 0o111
+            };
+            assert_ne!(mode(&dir.path().join("files/1")), 0);
+            assert_eq!(mode(&dir.path().join("files/2")), 0);
+            assert_eq!(
+                fs::read_to_string(dir.path().join("nested/dir/link")).unwrap(),
+                "linked"
+            );
+
+            // Files cannot be written below other files
+            batch
+                .write(&dir.path().join("files/0/not-a-dir"), Vec::new(), false)
+                .unwrap_err();
+        }
+
+        #[test]
+        fn test_failed_submission_waits_for_submitted_operations() {
+            let Some(mut batch) = file_batch() else {
+                return;
+            };
+
+            let nops = || {
+                (0..10)
+                    .map(|index| opcode::Nop::new().build().user_data(index))
+                    .collect::<Vec<_>>()
+            };
+            batch.ring.fail_submission = Some((0, libc::EIO));
+            let mut results = Vec::new();
+            let err = batch
+                .ring
+                .submit_and_wait(&nops(), &mut results)
+                .unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EIO));
+            assert_eq!(results.len(), 10);
+
+            // The next submission only sees its own completions
+            let mut results = Vec::new();
+            batch.ring.submit_and_wait(&nops(), &mut results).unwrap();
+            assert_eq!(results.len(), 10);
+        }
+
+        #[test]
+        fn test_failed_write_does_not_leave_completions() {
+            let Some(mut batch) = file_batch() else {
+                return;
+            };
+
+            let dir = tempfile::tempdir().unwrap();
+            let files = (0..10)
+                .map(|index| dir.path().join(format!("files/{index}")))
+                .collect::<Vec<_>>();
+
+            // Fail entering the ring when the contents of the files are written
+            for destination in &files {
+                batch.write(destination, b"failed".to_vec(), false).unwrap();
+            }
+            batch.ring.fail_submission = Some((1, libc::EIO));
+            let err = batch.flush().unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(libc::EIO));
+
+            // A later flush only sees its own completions
+            for (index, destination) in files.iter().enumerate() {
+                batch
+                    .write(destination, index.to_string().into_bytes(), false)
+                    .unwrap();
+            }
+            batch.flush().unwrap();
+            for (index, destination) in files.iter().enumerate() {
+                assert_eq!(fs::read_to_string(destination).unwrap(), index.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::utils::{remove_file, retry_in_use};
+use fs_err as fs;
+use std::io;
+use std::path::Path;
+
+/// Determines how a file from the [`super::WheelFileCache`] is placed into an environment.
+///
+/// If the selected strategy is not supported by the platform or the filesystem, for instance
+/// because the cache and the environment reside on different devices, the file is copied instead.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum LinkStrategy {
+    /// Create a hardlink to the cached file. This is fast and doesn't take up extra space but
+    /// modifying the installed file also modifies the cached file.
+    #[default]
+    Hardlink,
+
+    /// Copy the cached file.
+    Copy,
+
+    /// Create a copy-on-write clone of the cached file (`FICLONE` on Linux, `clonefile` on
+    /// macOS). This is as fast as a hardlink but it is only supported by some filesystems.
+    Reflink,
+
+    /// Create a symbolic link to the cached file. Creating symbolic links on Windows requires
+    /// additional privileges.
+    Symlink,
+}
+
+impl LinkStrategy {
+    /// Places the file at `source` at `destination` using this strategy, falling back to copying
+    /// the file if that fails. Any existing file at `destination` is replaced, even if it is in use
+    /// on Windows.
+    ///
+    /// Returns the strategy that was actually used.
+    pub(crate) fn link(self, source: &Path, destination: &Path) -> io::Result<LinkStrategy> {
+        if destination.symlink_metadata().is_ok() {
+            remove_file(destination)?;
+        }
+
+        let result = match self {
+            LinkStrategy::Copy => {
+                return retry_in_use(|| fs::copy(source, destination)).map(|_| self)
+            }
+            LinkStrategy::Hardlink => fs::hard_link(source, destination),
+            LinkStrategy::Reflink => reflink_copy::reflink(source, destination),
+            LinkStrategy::Symlink => symlink(source, destination),
+        };
+
+        match result {
+            Ok(_) => Ok(self),
+            Err(err) => {
+                tracing::debug!(
+                    "failed to {self:?} {} to {}, falling back to copying ({err})",
+                    source.display(),
+                    destination.display()
+                );
+                fs::copy(source, destination).map(|_| LinkStrategy::Copy)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    fs::os::windows::fs::symlink_file(source, destination)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(LinkStrategy::Copy)]
+    #[case(LinkStrategy::Hardlink)]
+    #[case(LinkStrategy::Reflink)]
+    #[case(LinkStrategy::Symlink)]
+    fn test_link(#[case] strategy: LinkStrategy) {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let destination = dir.path().join("destination");
+        fs::write(&source, "hello").unwrap();
+
+        // Linking twice should replace the existing file.
+        strategy.link(&source, &destination).unwrap();
+        let used = strategy.link(&source, &destination).unwrap();
+        assert!(used == strategy || used == LinkStrategy::Copy);
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "hello");
+    }
+}
@@ -0,0 +1,113 @@
+//! Module that contains functionality to install wheels into an environment.
+
+mod editable;
+
+mod ephemeral;
+
+mod file_cache;
+
+mod hooks;
+
+mod io_uring;
+
+mod link;
+
+mod parallel;
+
+mod report;
+
+mod seed;
+
+mod sync;
+
+mod target;
+
+mod unpacked_cache;
+
+mod upgrade;
+
+mod verify;
+
+use crate::metrics::Metrics;
+use crate::reporter::Reporter;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+
+pub use editable::{
+    editable_source_root, install_editable_pth, EditableInstall, EditableInstallError,
+};
+pub use ephemeral::{EphemeralEnvironment, EphemeralEnvironments};
+pub use file_cache::{CachedWheelEntry, WheelFileCache};
+pub use hooks::{HookError, HookTarget, InstallHooks};
+pub(crate) use io_uring::{FileBatch, MAX_BATCHED_FILE_SIZE};
+pub use link::LinkStrategy;
+pub use parallel::{install_wheels, InstallWheelsError};
+pub use report::{
+    InstallReport, InstallReportItem, InstallReportTimings, RemoveReportItem,
+    INSTALL_REPORT_VERSION,
+};
+pub use seed::{seed_venv, DEFAULT_SEED_PACKAGES};
+pub use sync::{execute_sync_plan, sync_environment, SyncError, SyncPlan, SyncPlanOptions};
+pub use target::InstallTarget;
+pub use unpacked_cache::UnpackedWheelCache;
+pub use upgrade::{favored_installed_packages, packages_to_upgrade, UpgradeStrategy};
+pub use verify::{verify_environment, DistributionVerification, VerifyError};
+
+/// Options that determine how wheels are installed into an environment.
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// How files are placed into the environment from the `file_cache`.
+    pub link_strategy: LinkStrategy,
+
+    /// A content-addressed cache of files extracted from wheels. If this is `None` the files are
+    /// extracted from the wheel directly into the environment and the `link_strategy` is ignored.
+    pub file_cache: Option<WheelFileCache>,
+
+    /// A cache of wheels that have already been unpacked. When a wheel is present in this cache
+    /// its unpacked tree is placed into the environment in bulk using the `link_strategy` instead
+    /// of extracting the files of the wheel one by one.
+    pub unpacked_cache: Option<UnpackedWheelCache>,
+
+    /// The maximum number of wheels that are installed concurrently when installing multiple
+    /// wheels at once. If this is `None` the available parallelism of the system is used.
+    pub parallelism: Option<NonZeroUsize>,
+
+    /// Hooks that are invoked around the installation and the removal of every distribution.
+    pub hooks: Option<Arc<dyn InstallHooks>>,
+
+    /// Receives the progress of the installation of every wheel. When this is `None`,
+    /// [`execute_sync_plan`] uses the reporter of the package database.
+    pub reporter: Option<Arc<dyn Reporter>>,
+
+    /// Records how many wheels were installed or removed and how long installing them took. When
+    /// this is `None`, [`execute_sync_plan`] uses the metrics of the package database.
+    pub metrics: Option<Arc<Metrics>>,
+
+    /// Refuse to install packages whose artifact does not have a sha256 hash that its contents
+    /// can be verified against, like `pip install --require-hashes`. The contents of artifacts
+    /// with a hash are always verified before they are unpacked.
+    pub require_hashes: bool,
+
+    /// Record the index and the artifact that every package that is installed from an index was
+    /// downloaded from in a `provenance_url.json` file, see
+    /// [PEP 710](https://peps.python.org/pep-0710/). Used by [`execute_sync_plan`].
+    pub record_provenance: bool,
+}
+
+impl InstallOptions {
+    /// Constructs options that place files from the specified cache into the environment using
+    /// the default [`LinkStrategy`].
+    pub fn with_file_cache(file_cache: WheelFileCache) -> Self {
+        Self {
+            link_strategy: LinkStrategy::default(),
+            file_cache: Some(file_cache),
+            unpacked_cache: None,
+            parallelism: None,
+            hooks: None,
+            reporter: None,
+            metrics: None,
+            require_hashes: false,
+            record_provenance: false,
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Installs multiple wheels into an environment concurrently.
+
+use crate::artifacts::wheel::{UnpackError, UnpackWheelOptions, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::install::{HookError, HookTarget, InstallTarget};
+use crate::types::{NormalizedPackageName, PackageName, WheelCoreMetadata};
+use miette::Diagnostic;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::time::Instant;
+use thiserror::Error;
+
+/// An error that can occur when installing multiple wheels with [`install_wheels`].
+#[derive(Debug, Error, Diagnostic)]
+#[allow(missing_docs)]
+pub enum InstallWheelsError {
+    #[error("failed to install '{0}'")]
+    FailedToInstall(String, #[source] Box<UnpackError>),
+
+    #[error("an install hook failed for '{0}'")]
+    HookFailed(String, #[source] Box<HookError>),
+}
+
+/// Installs the `wheels` into the `target`, each with its own options.
+///
+/// Wheels are installed after the wheels they depend on. Wheels that do not depend on each other
+/// are installed concurrently on at most `parallelism` threads. If `parallelism` is `None` the
+/// available parallelism of the system is used.
+///
+/// Returns the unpacked wheels in the order in which they were installed.
+#[tracing::instrument(skip_all, fields(wheels = wheels.len()))]
+pub fn install_wheels<T: InstallTarget + Sync + ?Sized>(
+    target: &T,
+    wheels: Vec<(Wheel, UnpackWheelOptions<'_>)>,
+    parallelism: Option<NonZeroUsize>,
+) -> Result<Vec<UnpackedWheel>, InstallWheelsError> {
+    let parallelism = parallelism
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+
+    // Determine the dependencies between the wheels.
+    let mut wheels_with_names = Vec::with_capacity(wheels.len());
+    let mut dependencies = HashMap::new();
+    for (wheel, options) in wheels {
+        let metadata = wheel.get_vitals().map_err(|e| {
+            InstallWheelsError::FailedToInstall(
+                wheel.name.to_string(),
+                Box::new(UnpackError::from(e)),
+            )
+        })?;
+        let name = NormalizedPackageName::from(wheel.name.distribution.clone());
+        dependencies.insert(name.clone(), dependency_names(&metadata.metadata));
+        wheels_with_names.push((name, wheel, options));
+    }
+
+    let mut result = Vec::with_capacity(wheels_with_names.len());
+    for level in dependency_levels(wheels_with_names, |(name, _, _)| name, &dependencies) {
+        let level = level
+            .into_iter()
+            .map(|(_, wheel, options)| (wheel, options))
+            .collect::<Vec<_>>();
+        result.extend(install_concurrently(target, level, parallelism)?);
+    }
+
+    Ok(result)
+}
+
+/// Installs wheels that do not depend on each other using at most `parallelism` threads.
+fn install_concurrently<T: InstallTarget + Sync + ?Sized>(
+    target: &T,
+    wheels: Vec<(Wheel, UnpackWheelOptions<'_>)>,
+    parallelism: usize,
+) -> Result<Vec<UnpackedWheel>, InstallWheelsError> {
+    let workers = parallelism.min(wheels.len());
+    if workers <= 1 {
+        return wheels
+            .into_iter()
+            .map(|(wheel, options)| install_wheel(target, &wheel, &options))
+            .collect();
+    }
+
+    let queue = Mutex::new(wheels.into_iter());
+    let results = Mutex::new(Vec::new());
+    let span = tracing::Span::current();
+    std::thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|_| {
+                // Nest the spans of the installs in the span of the caller
+                scope.spawn(|| {
+                    span.in_scope(|| loop {
+                        let next = queue.lock().next();
+                        let Some((wheel, options)) = next else {
+                            return Ok(());
+                        };
+                        match install_wheel(target, &wheel, &options) {
+                            Ok(unpacked) => results.lock().push(unpacked),
+                            Err(err) => {
+                                // Make sure the other threads stop picking up new work.
+                                queue.lock().by_ref().for_each(drop);
+                                return Err(err);
+                            }
+                        }
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+        })
+    })?;
+
+    Ok(results.into_inner())
+}
+
+/// Installs a single wheel into the `target`.
+#[tracing::instrument(skip_all, fields(package = %wheel.name.distribution.as_str(), version = %wheel.name.version))]
+fn install_wheel<T: InstallTarget + ?Sized>(
+    target: &T,
+    wheel: &Wheel,
+    options: &UnpackWheelOptions<'_>,
+) -> Result<UnpackedWheel, InstallWheelsError> {
+    tracing::debug!("installing {}", &wheel.name);
+    let hooks = options.install_options.hooks.as_deref();
+    let hook_target = HookTarget {
+        root: target.root(),
+        install_paths: target.install_paths(),
+    };
+    if let Some(hooks) = hooks {
+        hooks
+            .pre_install(wheel, hook_target)
+            .map_err(|e| InstallWheelsError::HookFailed(wheel.name.to_string(), Box::new(e)))?;
+    }
+
+    let reporter = options.install_options.reporter.as_deref();
+    let index = reporter.map(|reporter| reporter.on_install_start(&wheel.name));
+    let start = Instant::now();
+    let unpacked = target.install_wheel(wheel, options);
+    if let (Some(reporter), Some(index)) = (reporter, index) {
+        reporter.on_install_complete(index);
+    }
+    if let (Some(metrics), Ok(_)) = (&options.install_options.metrics, &unpacked) {
+        metrics.install_seconds.record(start.elapsed());
+        metrics.packages_installed.inc();
+    }
+    let unpacked = unpacked
+        .map_err(|e| InstallWheelsError::FailedToInstall(wheel.name.to_string(), Box::new(e)))?;
+
+    if let Some(hooks) = hooks {
+        hooks
+            .post_install(&unpacked, hook_target)
+            .map_err(|e| InstallWheelsError::HookFailed(wheel.name.to_string(), Box::new(e)))?;
+    }
+
+    Ok(unpacked)
+}
+
+/// Returns the names of all packages the distribution can depend on, regardless of markers.
+pub(crate) fn dependency_names(metadata: &WheelCoreMetadata) -> Vec<NormalizedPackageName> {
+    metadata
+        .requires_dist
+        .iter()
+        .filter_map(|requirement| PackageName::from_str(&requirement.name).ok())
+        .map(NormalizedPackageName::from)
+        .collect()
+}
+
+/// Orders `items` such that every item comes after the items it depends on. Dependencies that are
+/// not part of `items` are ignored and cycles are broken arbitrarily.
+pub(crate) fn dependency_order<T>(
+    items: Vec<T>,
+    name: impl Fn(&T) -> &NormalizedPackageName,
+    dependencies: &HashMap<NormalizedPackageName, Vec<NormalizedPackageName>>,
+) -> Vec<T> {
+    let mut items_by_name: HashMap<NormalizedPackageName, T> = items
+        .into_iter()
+        .map(|item| (name(&item).clone(), item))
+        .collect();
+
+    // Visit the items in a deterministic order
+    let mut names = items_by_name.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+
+    let mut visited = HashSet::new();
+    let mut ordered_names = Vec::with_capacity(names.len());
+    for root in names {
+        // Iterative depth-first search that emits a name after all its dependencies.
+        let mut stack = vec![(root, false)];
+        while let Some((name, dependencies_visited)) = stack.pop() {
+            if dependencies_visited {
+                ordered_names.push(name);
+                continue;
+            }
+            if !items_by_name.contains_key(&name) || !visited.insert(name.clone()) {
+                continue;
+            }
+            stack.push((name.clone(), true));
+            if let Some(dependencies) = dependencies.get(&name) {
+                stack.extend(
+                    dependencies
+                        .iter()
+                        .rev()
+                        .filter(|dependency| !visited.contains(*dependency))
+                        .map(|dependency| (dependency.clone(), false)),
+                );
+            }
+        }
+    }
+
+    ordered_names
+        .into_iter()
+        .filter_map(|name| items_by_name.remove(&name))
+        .collect()
+}
+
+/// Groups `items` into levels such that every item only depends on items in previous levels. The
+/// items within a single level can be processed independently of each other.
+fn dependency_levels<T>(
+    items: Vec<T>,
+    name: impl Fn(&T) -> &NormalizedPackageName,
+    dependencies: &HashMap<NormalizedPackageName, Vec<NormalizedPackageName>>,
+) -> Vec<Vec<T>> {
+    let mut levels: Vec<Vec<T>> = Vec::new();
+    let mut level_by_name = HashMap::new();
+    for item in dependency_order(items, &name, dependencies) {
+        // Dependencies that come later in the order are part of a cycle and are ignored.
+        let level = dependencies
+            .get(name(&item))
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| level_by_name.get(dependency))
+            .map(|level| level + 1)
+            .max()
+            .unwrap_or(0);
+        level_by_name.insert(name(&item).clone(), level);
+        if levels.len() <= level {
+            levels.resize_with(level + 1, Vec::new);
+        }
+        levels[level].push(item);
+    }
+    levels
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn name(name: &str) -> NormalizedPackageName {
+        NormalizedPackageName::from_str(name).unwrap()
+    }
+
+    fn test_dependencies() -> HashMap<NormalizedPackageName, Vec<NormalizedPackageName>> {
+        HashMap::from([
+            (name("flask"), vec![name("click"), name("werkzeug")]),
+            (name("werkzeug"), vec![name("markupsafe")]),
+            (name("markupsafe"), vec![name("werkzeug")]),
+            (name("click"), vec![name("colorama")]),
+        ])
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let items = vec![
+            name("flask"),
+            name("werkzeug"),
+            name("click"),
+            name("markupsafe"),
+        ];
+        let ordered = dependency_order(items, |n| n, &test_dependencies());
+
+        let position = |n: &str| ordered.iter().position(|o| o.as_str() == n).unwrap();
+        assert_eq!(ordered.len(), 4);
+        assert!(position("click") < position("flask"));
+        assert!(position("werkzeug") < position("flask"));
+        assert!(position("markupsafe") < position("flask"));
+    }
+
+    #[test]
+    fn test_dependency_levels() {
+        let items = vec![
+            name("flask"),
+            name("werkzeug"),
+            name("click"),
+            name("markupsafe"),
+            name("six"),
+        ];
+        let levels = dependency_levels(items, |n| n, &test_dependencies())
+            .into_iter()
+            .map(|level| level.iter().map(|n| n.as_str().to_owned()).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        // The cycle between markupsafe and werkzeug is broken by the order in which they are visited.
+        assert_eq!(
+            levels,
+            vec![
+                vec!["click", "markupsafe", "six"],
+                vec!["werkzeug"],
+                vec!["flask"]
+            ]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! A machine-readable report of an installation. The format is similar in spirit to the report
+//! that pip generates with `pip install --report`. The report can be archived by CI systems to
+//! record exactly what went into an environment.
+
+use crate::artifacts::Wheel;
+use crate::install::SyncPlan;
+use crate::python_env::Distribution;
+use crate::resolve::PinnedPackage;
+use crate::types::{NormalizedPackageName, Version};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, DurationSecondsWithFrac};
+use std::time::Duration;
+use url::Url;
+
+/// The version of the report format. This is incremented when backwards incompatible changes are
+/// made to the format.
+pub const INSTALL_REPORT_VERSION: &str = "1";
+
+/// A report of the changes that were made to an environment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallReport {
+    /// The version of the report format, see [`INSTALL_REPORT_VERSION`].
+    pub version: String,
+
+    /// The packages that were installed.
+    pub install: Vec<InstallReportItem>,
+
+    /// The distributions that were removed, this includes distributions that were replaced by
+    /// another version.
+    pub remove: Vec<RemoveReportItem>,
+
+    /// How long the different phases of the installation took.
+    pub timings: InstallReportTimings,
+}
+
+/// A package that was installed.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallReportItem {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The version that was installed
+    #[serde_as(as = "DisplayFromStr")]
+    pub version: Version,
+
+    /// The version that was installed before if the package was upgraded or downgraded.
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub previous_version: Option<Version>,
+
+    /// The location of the artifact that was installed
+    pub url: Option<Url>,
+
+    /// True if the package was requested through a direct url instead of an index.
+    pub is_direct: bool,
+
+    /// The hex encoded sha256 hash of the artifact if it is known.
+    pub sha256: Option<String>,
+
+    /// True if the wheel was built from a source distribution or source tree.
+    pub built_from_source: bool,
+
+    /// The extras that were enabled for the package.
+    pub extras: Vec<String>,
+}
+
+/// A distribution that was removed.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveReportItem {
+    /// The name of the distribution
+    pub name: NormalizedPackageName,
+
+    /// The version that was removed
+    #[serde_as(as = "DisplayFromStr")]
+    pub version: Version,
+}
+
+/// How long the different phases of an installation took.
+#[serde_as]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallReportTimings {
+    /// The time it took to remove distributions, in seconds.
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub remove: Duration,
+
+    /// The time it took to download or build the wheels, in seconds.
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub download: Duration,
+
+    /// The time it took to install the wheels, in seconds.
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub install: Duration,
+
+    /// The total time of the installation, in seconds.
+    #[serde_as(as = "DurationSecondsWithFrac<f64>")]
+    pub total: Duration,
+}
+
+impl InstallReport {
+    /// Constructs a report of the changes described by the `plan`. The timings are left empty.
+    pub fn from_plan(plan: &SyncPlan) -> Self {
+        let install = plan
+            .install
+            .iter()
+            .map(|package| InstallReportItem::new(package, None))
+            .chain(
+                plan.reinstall
+                    .iter()
+                    .map(|(previous, package)| InstallReportItem::new(package, Some(previous))),
+            )
+            .collect();
+
+        let remove = plan
+            .remove
+            .iter()
+            .chain(plan.reinstall.iter().map(|(previous, _)| previous))
+            .map(|distribution| RemoveReportItem {
+                name: distribution.name.clone(),
+                version: distribution.version.clone(),
+            })
+            .collect();
+
+        Self {
+            version: String::from(INSTALL_REPORT_VERSION),
+            install,
+            remove,
+            timings: InstallReportTimings::default(),
+        }
+    }
+}
+
+impl InstallReportItem {
+    fn new(package: &PinnedPackage, previous: Option<&Distribution>) -> Self {
+        // The first artifact is the one that is installed.
+        let artifact = package.artifacts.first();
+        let mut extras = package
+            .extras
+            .iter()
+            .map(|extra| extra.as_str().to_owned())
+            .collect::<Vec<_>>();
+        extras.sort();
+
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            previous_version: previous.map(|distribution| distribution.version.clone()),
+            url: artifact
+                .map(|artifact| artifact.url.clone())
+                .or_else(|| package.url.clone()),
+            is_direct: package.url.is_some()
+                || artifact.is_some_and(|artifact| artifact.is_direct_url),
+            sha256: artifact
+                .and_then(|artifact| artifact.hashes.as_ref())
+                .and_then(|hashes| hashes.sha256)
+                .map(|sha256| format!("{sha256:x}")),
+            built_from_source: artifact.is_some_and(|artifact| !artifact.is::<Wheel>()),
+            extras,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{ArtifactHashes, ArtifactInfo, ArtifactName, Extra};
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_report_from_plan() {
+        let filename = "boltons-20.2.1.tar.gz";
+        let name = NormalizedPackageName::from_str("boltons").unwrap();
+        let artifact = ArtifactInfo {
+            filename: ArtifactName::from_filename(filename, None, &name).unwrap(),
+            url: Url::parse("https://files.pythonhosted.org/packages/boltons-20.2.1.tar.gz")
+                .unwrap(),
+            is_direct_url: false,
+            hashes: Some(ArtifactHashes {
+                sha256: rattler_digest::parse_digest_from_hex::<rattler_digest::Sha256>(
+                    "dd362291a460cc1e0c2e91cc6a60da3036ced77099b623112e8f833e6734bdc5",
+                ),
+            }),
+            requires_python: None,
+            dist_info_metadata: Default::default(),
+            yanked: Default::default(),
+            size: None,
+        };
+        let plan = SyncPlan {
+            install: vec![PinnedPackage {
+                name,
+                version: Version::from_str("20.2.1").unwrap(),
+                url: None,
+                extras: [Extra::from_str("dev").unwrap()].into_iter().collect(),
+                artifacts: vec![Arc::new(artifact)],
+                dependencies: vec![],
+            }],
+            ..Default::default()
+        };
+
+        let report = serde_json::to_value(InstallReport::from_plan(&plan)).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!({
+                "version": "1",
+                "install": [{
+                    "name": "boltons",
+                    "version": "20.2.1",
+                    "url": "https://files.pythonhosted.org/packages/boltons-20.2.1.tar.gz",
+                    "is_direct": false,
+                    "sha256": "dd362291a460cc1e0c2e91cc6a60da3036ced77099b623112e8f833e6734bdc5",
+                    "built_from_source": true,
+                    "extras": ["dev"],
+                }],
+                "remove": [],
+                "timings": {
+                    "remove": 0.0,
+                    "download": 0.0,
+                    "install": 0.0,
+                    "total": 0.0,
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Seeding a freshly created virtual environment with packaging tools like `pip`.
+
+use crate::artifacts::wheel::UnpackWheelOptions;
+use crate::index::{ArtifactRequest, PackageDb};
+use crate::install::install_wheels;
+use crate::python_env::{PythonInterpreterVersion, VEnv};
+use crate::resolve::PypiVersion;
+use crate::types::{ArtifactInfo, NormalizedPackageName, Version};
+use miette::{Context, IntoDiagnostic};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The packages that are installed by `virtualenv` by default.
+pub const DEFAULT_SEED_PACKAGES: &[&str] = &["pip", "setuptools", "wheel"];
+
+/// Installs the latest pure python wheel of each of the `packages` into the `venv` that is
+/// compatible with its interpreter, the same way `virtualenv` seeds new environments. The wheels
+/// are retrieved through the `package_db`, so they are taken from the cache when available.
+///
+/// Unlike a regular installation the dependencies of the packages are not resolved.
+pub async fn seed_venv(
+    venv: &VEnv,
+    package_db: &PackageDb,
+    packages: &[NormalizedPackageName],
+    options: &UnpackWheelOptions<'_>,
+) -> miette::Result<()> {
+    let python_version = PythonInterpreterVersion::from_path(&venv.python_executable())
+        .into_diagnostic()
+        .wrap_err("failed to determine the python version of the virtual environment")?;
+    let python_version = Version::from_str(&format!(
+        "{}.{}.{}",
+        python_version.major, python_version.minor, python_version.patch
+    ))
+    .expect("a python interpreter version is always a valid version");
+
+    let mut wheels = Vec::with_capacity(packages.len());
+    for package in packages {
+        let artifacts = package_db
+            .available_artifacts(ArtifactRequest::FromIndex(package.clone()))
+            .await?;
+        let artifact = latest_seed_wheel(artifacts.iter(), &python_version).ok_or_else(|| {
+            miette::miette!("could not find a compatible wheel to seed '{package}' with")
+        })?;
+        let (wheel, _) = package_db.get_wheel(&artifact, None).await?;
+        wheels.push((
+            wheel,
+            UnpackWheelOptions {
+                installer: options.installer.clone(),
+                launcher_arch: options.launcher_arch,
+                byte_code_compiler: options.byte_code_compiler,
+                install_options: options.install_options.clone(),
+                relocatable: options.relocatable,
+                ..Default::default()
+            },
+        ));
+    }
+
+    install_wheels(venv, wheels, options.install_options.parallelism).into_diagnostic()?;
+    Ok(())
+}
+
+/// Returns the first pure python wheel of the newest non-prerelease version that supports
+/// `python_version`. `versions` is expected to be sorted from newest to oldest, which is how
+/// [`PackageDb::available_artifacts`] returns them.
+fn latest_seed_wheel<'a>(
+    versions: impl IntoIterator<Item = (&'a PypiVersion, &'a Vec<Arc<ArtifactInfo>>)>,
+    python_version: &Version,
+) -> Option<Arc<ArtifactInfo>> {
+    versions
+        .into_iter()
+        .filter(|(version, _)| !version.any_prerelease())
+        .flat_map(|(_, artifacts)| artifacts.iter())
+        .find(|artifact| {
+            let Some(wheel_name) = artifact.filename.as_wheel() else {
+                return false;
+            };
+            !artifact.yanked.yanked
+                && wheel_name.py_tags.iter().any(|tag| tag == "py3")
+                && wheel_name.abi_tags.iter().any(|tag| tag == "none")
+                && wheel_name.arch_tags.iter().any(|tag| tag == "any")
+                && artifact
+                    .requires_python
+                    .as_ref()
+                    .map_or(true, |specifiers| specifiers.contains(python_version))
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::{ArtifactName, VersionSpecifiers};
+    use indexmap::IndexMap;
+    use url::Url;
+
+    fn artifact(filename: &str, requires_python: Option<&str>) -> Arc<ArtifactInfo> {
+        let name = NormalizedPackageName::from_str("pip").unwrap();
+        Arc::new(ArtifactInfo {
+            filename: ArtifactName::from_filename(filename, None, &name).unwrap(),
+            url: Url::parse(&format!(
+                "https://files.pythonhosted.org/packages/{filename}"
+            ))
+            .unwrap(),
+            is_direct_url: false,
+            hashes: None,
+            requires_python: requires_python.map(|s| VersionSpecifiers::from_str(s).unwrap()),
+            dist_info_metadata: Default::default(),
+            yanked: Default::default(),
+            size: None,
+        })
+    }
+
+    fn version(version: &str) -> PypiVersion {
+        PypiVersion::Version {
+            version: Version::from_str(version).unwrap(),
+            package_allows_prerelease: false,
+        }
+    }
+
+    #[test]
+    fn test_latest_seed_wheel() {
+        let versions: IndexMap<_, _> = [
+            (
+                version("25.0b1"),
+                vec![artifact("pip-25.0b1-py3-none-any.whl", None)],
+            ),
+            (
+                version("24.1"),
+                vec![
+                    artifact("pip-24.1-py3-none-any.whl", Some(">=3.8")),
+                    artifact("pip-24.1.tar.gz", Some(">=3.8")),
+                ],
+            ),
+            (
+                version("23.3"),
+                vec![
+                    artifact("pip-23.3.tar.gz", Some(">=3.7")),
+                    artifact("pip-23.3-py3-none-any.whl", Some(">=3.7")),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let select = |python: &str| {
+            latest_seed_wheel(&versions, &Version::from_str(python).unwrap())
+                .map(|artifact| artifact.filename.to_string())
+        };
+        assert_eq!(
+            select("3.11.4").as_deref(),
+            Some("pip-24.1-py3-none-any.whl")
+        );
+        assert_eq!(
+            select("3.7.1").as_deref(),
+            Some("pip-23.3-py3-none-any.whl")
+        );
+        assert_eq!(select("3.6.0"), None);
+    }
+}
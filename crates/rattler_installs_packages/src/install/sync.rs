@@ -0,0 +1,717 @@
+//! Functionality to converge an existing environment to a resolved set of packages.
+//!
+//! [`SyncPlan::new`] compares the distributions that are installed in an environment with the
+//! packages of a resolution and [`execute_sync_plan`] executes the resulting plan.
+//! [`sync_environment`] combines both steps.
+
+use crate::artifacts::wheel::UnpackWheelOptions;
+use crate::index::PackageDb;
+use crate::install::parallel::{
+    dependency_names, dependency_order, install_wheels, InstallWheelsError,
+};
+use crate::install::report::InstallReport;
+use crate::install::{HookError, HookTarget, InstallOptions, InstallTarget};
+use crate::python_env::{
+    find_distributions_in_venv, uninstall_distribution, Distribution, FindDistributionError,
+    UninstallDistributionError,
+};
+use crate::resolve::PinnedPackage;
+use crate::types::{
+    DirectUrlHashes, DirectUrlJson, DirectUrlSource, NormalizedPackageName, ProvenanceArchiveInfo,
+    ProvenanceUrlJson, WheelCoreMetadata,
+};
+use crate::utils::remove_pending_deletes;
+use crate::wheel_builder::WheelBuilder;
+use fs_err as fs;
+use futures::{stream, StreamExt, TryStreamExt};
+use miette::Diagnostic;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Instant;
+use thiserror::Error;
+use url::Url;
+
+/// The maximum number of wheels that are retrieved at the same time.
+const CONCURRENT_DOWNLOADS: usize = 8;
+
+/// Describes the changes that are required to converge an environment to a set of packages.
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlan {
+    /// Packages that are not currently installed in the environment.
+    pub install: Vec<PinnedPackage>,
+
+    /// Packages that are installed but with a different version or from a different source. The
+    /// installed distribution is removed before the package is installed.
+    pub reinstall: Vec<(Distribution, PinnedPackage)>,
+
+    /// Installed distributions that are not part of the set of packages.
+    pub remove: Vec<Distribution>,
+
+    /// Installed distributions that already match the set of packages.
+    pub unchanged: Vec<Distribution>,
+
+    /// The packages that were explicitly requested by the user, see
+    /// [`SyncPlanOptions::requested`].
+    pub requested: HashSet<NormalizedPackageName>,
+}
+
+/// Options that influence how a [`SyncPlan`] is determined.
+#[derive(Debug, Clone, Default)]
+pub struct SyncPlanOptions {
+    /// Keep installed distributions that are not part of the desired packages instead of removing
+    /// them. This is what `pip install` does, whereas `pip-sync` removes them.
+    pub keep_extraneous: bool,
+
+    /// Reinstall the desired packages even if the installed distribution is already up to date.
+    pub reinstall: bool,
+
+    /// Only remove extraneous distributions that were installed as a dependency. Extraneous
+    /// distributions that were explicitly requested by the user, as indicated by their
+    /// `REQUESTED` file, are kept.
+    pub keep_requested: bool,
+
+    /// The name of the installer that executes the plan, e.g. `rip`. If this is specified
+    /// extraneous distributions whose `INSTALLER` file names a different installer are kept, so
+    /// packages that are managed by another tool are not removed.
+    pub installer: Option<String>,
+
+    /// The packages that were explicitly requested by the user instead of being required as a
+    /// dependency. A `REQUESTED` file is written for these packages when they are installed.
+    pub requested: HashSet<NormalizedPackageName>,
+
+    /// Installed distributions that are managed by another package manager, e.g. the python
+    /// packages of a conda environment. These are never removed or reinstalled and satisfy a
+    /// desired package with the same name regardless of its version.
+    pub externally_managed: HashSet<NormalizedPackageName>,
+}
+
+impl SyncPlan {
+    /// Determines the changes that are required to go from the `installed` distributions to the
+    /// `desired` packages.
+    pub fn new(installed: Vec<Distribution>, desired: &[PinnedPackage]) -> Self {
+        Self::with_options(installed, desired, SyncPlanOptions::default())
+    }
+
+    /// Determines the changes that are required to go from the `installed` distributions to the
+    /// `desired` packages using the specified `options`.
+    pub fn with_options(
+        installed: Vec<Distribution>,
+        desired: &[PinnedPackage],
+        options: SyncPlanOptions,
+    ) -> Self {
+        let mut desired_by_name: HashMap<_, _> = desired
+            .iter()
+            .map(|package| (package.name.clone(), package))
+            .collect();
+
+        let mut plan = SyncPlan {
+            requested: options.requested,
+            ..SyncPlan::default()
+        };
+        for distribution in installed {
+            if options.externally_managed.contains(&distribution.name) {
+                if let Some(package) = desired_by_name.remove(&distribution.name) {
+                    if package.version != distribution.version {
+                        tracing::warn!(
+                            "{} {} is managed by another package manager, keeping it instead of installing {}",
+                            distribution.name,
+                            distribution.version,
+                            package.version
+                        );
+                    }
+                }
+                plan.unchanged.push(distribution);
+                continue;
+            }
+
+            match desired_by_name.remove(&distribution.name) {
+                None if options.keep_extraneous
+                    || (options.keep_requested && distribution.requested)
+                    || installed_by_other(&distribution, options.installer.as_deref()) =>
+                {
+                    plan.unchanged.push(distribution)
+                }
+                None => plan.remove.push(distribution),
+                Some(package) if !options.reinstall && is_up_to_date(&distribution, package) => {
+                    plan.unchanged.push(distribution)
+                }
+                Some(package) => plan.reinstall.push((distribution, package.clone())),
+            }
+        }
+
+        // Everything that was not matched with an installed distribution has to be installed.
+        plan.install = desired
+            .iter()
+            .filter(|package| desired_by_name.contains_key(&package.name))
+            .cloned()
+            .collect();
+
+        plan
+    }
+
+    /// Returns true if the environment already matches the set of packages.
+    pub fn is_empty(&self) -> bool {
+        self.install.is_empty() && self.reinstall.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Returns true if the `distribution` was installed by another installer than `installer`.
+/// Distributions without an `INSTALLER` file are not attributed to any installer.
+fn installed_by_other(distribution: &Distribution, installer: Option<&str>) -> bool {
+    match (installer, distribution.installer.as_deref()) {
+        (Some(installer), Some(installed_by)) => installer != installed_by,
+        _ => false,
+    }
+}
+
+/// Returns true if the installed `distribution` matches the `package`.
+fn is_up_to_date(distribution: &Distribution, package: &PinnedPackage) -> bool {
+    if distribution.version != package.version {
+        return false;
+    }
+
+    match (&package.url, &distribution.direct_url) {
+        (None, None) => true,
+        (Some(url), Some(direct_url)) => same_source(url, direct_url),
+        // The package switched between an index and a direct url.
+        _ => false,
+    }
+}
+
+/// Compares the url of a requirement with the url recorded in `direct_url.json`. The latter does
+/// not contain the `<vcs>+` prefix of the scheme, the `@<rev>` suffix of a VCS url is recorded
+/// separately as the requested revision.
+fn same_source(requested: &Url, installed: &DirectUrlJson) -> bool {
+    let Some(mut url) = requested
+        .as_str()
+        .split_once('+')
+        .filter(|(vcs, _)| !vcs.contains(['/', ':']))
+        .and_then(|(_, url)| Url::parse(url).ok())
+    else {
+        return requested == &installed.url;
+    };
+
+    // The revision is separated from the path by an `@`, an `@` before the path is part of the
+    // user info of the url.
+    url.set_fragment(None);
+    let path = url.path().to_string();
+    let revision = path.rsplit_once('@').map(|(path, revision)| {
+        url.set_path(path);
+        revision
+    });
+    if url != installed.url {
+        return false;
+    }
+    match &installed.source {
+        DirectUrlSource::Vcs {
+            requested_revision,
+            commit_id,
+            ..
+        } => revision == requested_revision.as_deref() || revision == Some(commit_id.as_str()),
+        _ => revision.is_none(),
+    }
+}
+
+/// An error that can occur when synchronizing an environment.
+#[derive(Debug, Error, Diagnostic)]
+#[allow(missing_docs)]
+pub enum SyncError {
+    #[error("failed to determine the installed distributions")]
+    FailedToFindDistributions(#[from] FindDistributionError),
+
+    #[error("failed to uninstall '{0}'")]
+    FailedToUninstall(NormalizedPackageName, #[source] UninstallDistributionError),
+
+    #[error(transparent)]
+    FailedToInstall(#[from] InstallWheelsError),
+
+    #[error("package '{0}' does not have any artifacts")]
+    NoArtifacts(NormalizedPackageName),
+
+    #[error("hashes are required but the artifact of '{0}', {1}, does not have a hash to verify it against")]
+    MissingHash(NormalizedPackageName, String),
+
+    #[error("an uninstall hook failed for '{0}'")]
+    HookFailed(NormalizedPackageName, #[source] HookError),
+}
+
+/// Converges the environment to contain exactly the specified `packages`.
+///
+/// Packages that are missing are installed, packages that have a different version or source are
+/// reinstalled and distributions that are not part of `packages` are removed. Distributions are
+/// removed before their dependencies and packages are installed after their dependencies so the
+/// environment remains usable if the operation is interrupted halfway. All wheels are retrieved
+/// before the first distribution is removed.
+///
+/// The `options` are used for every package that is installed, except for the extras and the
+/// `direct_url.json` which are determined per package. Returns a report of the changes that were
+/// made to the environment.
+pub async fn sync_environment<T: InstallTarget + Sync + ?Sized>(
+    target: &T,
+    package_db: &PackageDb,
+    wheel_builder: Option<&WheelBuilder>,
+    packages: &[PinnedPackage],
+    options: &UnpackWheelOptions<'_>,
+) -> miette::Result<InstallReport> {
+    let installed = find_distributions_in_venv(target.root(), target.install_paths())
+        .map_err(SyncError::from)?;
+    let plan = SyncPlan::new(installed, packages);
+    execute_sync_plan(target, package_db, wheel_builder, &plan, options).await
+}
+
+/// Executes the changes described by `plan` on the `target`. See [`sync_environment`] for more
+/// information.
+#[tracing::instrument(name = "sync", skip_all, fields(install = plan.install.len(), reinstall = plan.reinstall.len(), remove = plan.remove.len()))]
+pub async fn execute_sync_plan<T: InstallTarget + Sync + ?Sized>(
+    target: &T,
+    package_db: &PackageDb,
+    wheel_builder: Option<&WheelBuilder>,
+    plan: &SyncPlan,
+    options: &UnpackWheelOptions<'_>,
+) -> miette::Result<InstallReport> {
+    let start = Instant::now();
+    let mut report = InstallReport::from_plan(plan);
+    if plan.is_empty() {
+        report.timings.total = start.elapsed();
+        return Ok(report);
+    }
+
+    // Clean up files that could not be removed during a previous installation because they were
+    // in use.
+    if cfg!(windows) {
+        if let Err(err) = remove_pending_deletes(target.root()) {
+            tracing::debug!("failed to remove pending deletes: {err}");
+        }
+    }
+
+    let metrics = options
+        .install_options
+        .metrics
+        .clone()
+        .unwrap_or_else(|| package_db.metrics().clone());
+
+    // Retrieve the wheels of all packages that need to be installed, multiple at the same time.
+    // This happens before anything is removed so a failed download or build leaves the environment
+    // untouched.
+    let download_start = Instant::now();
+    let metrics = &metrics;
+    let wheels = stream::iter(
+        plan.install
+            .iter()
+            .chain(plan.reinstall.iter().map(|(_, package)| package)),
+    )
+    .map(|package| async move {
+        let artifact_info = package
+            .artifacts
+            .first()
+            .ok_or_else(|| SyncError::NoArtifacts(package.name.clone()))?;
+        // Artifacts from a direct url are fetched without checking their hash
+        let verifiable = !artifact_info.is_direct_url
+            && artifact_info
+                .hashes
+                .as_ref()
+                .is_some_and(|hashes| hashes.sha256.is_some());
+        if options.install_options.require_hashes && !verifiable {
+            return Err(SyncError::MissingHash(
+                package.name.clone(),
+                artifact_info.url.to_string(),
+            )
+            .into());
+        }
+        let (wheel, direct_url_json) = package_db.get_wheel(artifact_info, wheel_builder).await?;
+        let provenance_url_json =
+            if options.install_options.record_provenance && direct_url_json.is_none() {
+                package_db
+                    .artifact_index_url(&package.name, artifact_info)
+                    .map(|index_url| ProvenanceUrlJson {
+                        url: artifact_info.url.clone(),
+                        index_url,
+                        archive_info: ProvenanceArchiveInfo {
+                            hashes: artifact_info
+                                .hashes
+                                .as_ref()
+                                .and_then(|hashes| hashes.sha256)
+                                .map(|sha256| DirectUrlHashes {
+                                    sha256: format!("{sha256:x}"),
+                                }),
+                        },
+                    })
+            } else {
+                None
+            };
+        let wheel_options = UnpackWheelOptions {
+            installer: options.installer.clone(),
+            requested: plan.requested.contains(&package.name),
+            extras: Some(package.extras.clone()),
+            launcher_arch: options.launcher_arch,
+            byte_code_compiler: options.byte_code_compiler,
+            direct_url_json,
+            provenance_url_json,
+            install_options: InstallOptions {
+                reporter: options
+                    .install_options
+                    .reporter
+                    .clone()
+                    .or_else(|| package_db.reporter().cloned()),
+                metrics: Some(metrics.clone()),
+                ..options.install_options.clone()
+            },
+            relocatable: options.relocatable,
+            destdir: options.destdir.clone(),
+        };
+        miette::Result::<_>::Ok((wheel, wheel_options))
+    })
+    .buffered(CONCURRENT_DOWNLOADS)
+    .try_collect::<Vec<_>>()
+    .await?;
+    report.timings.download = download_start.elapsed();
+
+    // Remove the distributions, dependents before their dependencies.
+    let remove_start = Instant::now();
+    let to_remove = plan
+        .remove
+        .iter()
+        .chain(plan.reinstall.iter().map(|(distribution, _)| distribution))
+        .collect::<Vec<_>>();
+    let installed_dependencies = to_remove
+        .iter()
+        .map(|distribution| {
+            let dependencies =
+                read_installed_metadata(&target.root().join(&distribution.dist_info))
+                    .map(|metadata| dependency_names(&metadata))
+                    .unwrap_or_default();
+            (distribution.name.clone(), dependencies)
+        })
+        .collect::<HashMap<_, _>>();
+    let mut removal_order = dependency_order(to_remove, |d| &d.name, &installed_dependencies);
+    removal_order.reverse();
+    for distribution in removal_order {
+        let _span = tracing::info_span!(
+            "uninstall",
+            package = %distribution.name,
+            version = %distribution.version
+        )
+        .entered();
+        tracing::debug!("removing {} {}", distribution.name, distribution.version);
+        let dist_info = target.root().join(&distribution.dist_info);
+        let (Some(site_packages), Some(dist_info_dir)) =
+            (dist_info.parent(), dist_info.file_name())
+        else {
+            continue;
+        };
+        let hook_target = HookTarget {
+            root: target.root(),
+            install_paths: target.install_paths(),
+        };
+        if let Some(hooks) = &options.install_options.hooks {
+            hooks
+                .pre_uninstall(distribution, hook_target)
+                .map_err(|e| SyncError::HookFailed(distribution.name.clone(), e))?;
+        }
+        let reporter = options.install_options.reporter.as_deref();
+        let index = reporter.map(|reporter| reporter.on_uninstall_start(distribution));
+        let result = uninstall_distribution(site_packages, Path::new(dist_info_dir));
+        if let (Some(reporter), Some(index)) = (reporter, index) {
+            reporter.on_uninstall_complete(index);
+        }
+        result.map_err(|e| SyncError::FailedToUninstall(distribution.name.clone(), e))?;
+        metrics.packages_removed.inc();
+        if let Some(hooks) = &options.install_options.hooks {
+            hooks
+                .post_uninstall(distribution, hook_target)
+                .map_err(|e| SyncError::HookFailed(distribution.name.clone(), e))?;
+        }
+    }
+    report.timings.remove = remove_start.elapsed();
+
+    // Install the packages, dependencies before their dependents.
+    let install_start = Instant::now();
+    install_wheels(target, wheels, options.install_options.parallelism).map_err(SyncError::from)?;
+    report.timings.install = install_start.elapsed();
+
+    report.timings.total = start.elapsed();
+    Ok(report)
+}
+
+/// Reads the `METADATA` file from an installed `.dist-info` directory.
+pub(crate) fn read_installed_metadata(dist_info: &Path) -> Option<WheelCoreMetadata> {
+    let bytes = fs::read(dist_info.join("METADATA")).ok()?;
+    WheelCoreMetadata::try_from(bytes.as_slice()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::{staged_path, InstallPaths};
+    use crate::python_env::TargetDirectory;
+    use crate::types::{ArtifactHashes, ArtifactInfo, ArtifactName, DirectUrlVcs};
+    use rattler_digest::Sha256;
+    use reqwest_middleware::ClientWithMiddleware;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    fn name(name: &str) -> NormalizedPackageName {
+        NormalizedPackageName::from_str(name).unwrap()
+    }
+
+    fn pinned(package_name: &str, version: &str) -> PinnedPackage {
+        PinnedPackage {
+            name: name(package_name),
+            version: version.parse().unwrap(),
+            url: None,
+            extras: Default::default(),
+            artifacts: vec![],
+            dependencies: vec![],
+        }
+    }
+
+    fn names<'a>(names: impl IntoIterator<Item = &'a NormalizedPackageName>) -> Vec<&'a str> {
+        let mut names = names.into_iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Returns the distributions of the test environment in `test-data/find_distributions`.
+    fn installed_distributions() -> Vec<Distribution> {
+        let venv_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/find_distributions/");
+        let install_paths = InstallPaths::for_venv((3, 8, 5), true);
+        find_distributions_in_venv(&venv_path, &install_paths).unwrap()
+    }
+
+    #[test]
+    fn test_sync_plan() {
+        let installed = installed_distributions();
+
+        let plan = SyncPlan::new(
+            installed,
+            &[
+                pinned("flask", "1.1.4"),
+                pinned("click", "8.1.7"),
+                pinned("requests", "2.31.0"),
+            ],
+        );
+
+        assert_eq!(names(plan.install.iter().map(|p| &p.name)), ["requests"]);
+        assert_eq!(
+            names(plan.reinstall.iter().map(|(d, _)| &d.name)),
+            ["click"]
+        );
+        assert_eq!(names(plan.unchanged.iter().map(|d| &d.name)), ["flask"]);
+        assert_eq!(
+            names(plan.remove.iter().map(|d| &d.name)),
+            [
+                "itsdangerous",
+                "jinja2",
+                "markupsafe",
+                "pip",
+                "setuptools",
+                "werkzeug"
+            ]
+        );
+        assert!(!plan.is_empty());
+    }
+
+    #[test]
+    fn test_sync_plan_options() {
+        let installed = installed_distributions();
+
+        let plan = SyncPlan::with_options(
+            installed.clone(),
+            &[pinned("flask", "1.1.4"), pinned("click", "8.1.7")],
+            SyncPlanOptions {
+                keep_extraneous: true,
+                reinstall: false,
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert!(plan.remove.is_empty());
+        assert!(plan.install.is_empty());
+        assert_eq!(
+            names(plan.reinstall.iter().map(|(d, _)| &d.name)),
+            ["click"]
+        );
+        assert_eq!(plan.unchanged.len(), installed.len() - 1);
+
+        let plan = SyncPlan::with_options(
+            installed,
+            &[pinned("flask", "1.1.4")],
+            SyncPlanOptions {
+                keep_extraneous: true,
+                reinstall: true,
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert_eq!(
+            names(plan.reinstall.iter().map(|(d, _)| &d.name)),
+            ["flask"]
+        );
+    }
+
+    #[test]
+    fn test_sync_plan_keep_requested_and_other_installers() {
+        let installed = installed_distributions();
+
+        // Flask is the only distribution with a REQUESTED file
+        let plan = SyncPlan::with_options(
+            installed.clone(),
+            &[pinned("click", "7.1.2")],
+            SyncPlanOptions {
+                keep_requested: true,
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert_eq!(
+            names(plan.unchanged.iter().map(|d| &d.name)),
+            ["click", "flask"]
+        );
+
+        // All distributions were installed by pip
+        let plan = SyncPlan::with_options(
+            installed.clone(),
+            &[],
+            SyncPlanOptions {
+                installer: Some(String::from("rip")),
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert!(plan.remove.is_empty());
+
+        let plan = SyncPlan::with_options(
+            installed,
+            &[],
+            SyncPlanOptions {
+                installer: Some(String::from("pip")),
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert_eq!(plan.remove.len(), 8);
+    }
+
+    #[test]
+    fn test_sync_plan_externally_managed() {
+        let installed = installed_distributions();
+
+        let plan = SyncPlan::with_options(
+            installed,
+            &[pinned("flask", "1.1.4"), pinned("click", "8.1.7")],
+            SyncPlanOptions {
+                reinstall: true,
+                externally_managed: HashSet::from([name("click"), name("pip")]),
+                ..SyncPlanOptions::default()
+            },
+        );
+        assert_eq!(
+            names(plan.reinstall.iter().map(|(d, _)| &d.name)),
+            ["flask"]
+        );
+        assert!(plan.install.is_empty());
+        assert!(plan.unchanged.iter().any(|d| d.name.as_str() == "click"));
+        assert!(plan.unchanged.iter().any(|d| d.name.as_str() == "pip"));
+        assert!(plan.remove.iter().all(|d| d.name.as_str() != "pip"));
+    }
+
+    #[test]
+    fn test_same_source() {
+        let installed = DirectUrlJson {
+            url: Url::parse("https://github.com/pallets/flask.git").unwrap(),
+            subdirectory: None,
+            source: DirectUrlSource::Vcs {
+                vcs: DirectUrlVcs::Git,
+                requested_revision: Some(String::from("3.0.0")),
+                commit_id: String::from("4df377cfbfc1d15e962a61c18920b22aebc9aa41"),
+            },
+        };
+        let requested = |url: &str| Url::parse(url).unwrap();
+        assert!(same_source(
+            &requested("git+https://github.com/pallets/flask.git@3.0.0"),
+            &installed
+        ));
+        assert!(same_source(
+            &requested(
+                "git+https://github.com/pallets/flask.git@4df377cfbfc1d15e962a61c18920b22aebc9aa41"
+            ),
+            &installed
+        ));
+        assert!(!same_source(
+            &requested("git+https://github.com/pallets/flask.git@2.3.3"),
+            &installed
+        ));
+        assert!(!same_source(
+            &requested("git+https://github.com/pallets/flask.git"),
+            &installed
+        ));
+        assert!(!same_source(
+            &requested("git+https://github.com/pallets/click.git@3.0.0"),
+            &installed
+        ));
+
+        let installed = DirectUrlJson {
+            url: Url::parse("https://example.com/flask-3.0.0.tar.gz").unwrap(),
+            subdirectory: None,
+            source: DirectUrlSource::Archive { hashes: None },
+        };
+        assert!(same_source(&installed.url, &installed));
+        assert!(!same_source(
+            &requested("https://example.com/flask-2.3.3.tar.gz"),
+            &installed
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sync_with_destdir() {
+        let wheel_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl")
+            .canonicalize()
+            .unwrap();
+        let artifact_info = ArtifactInfo {
+            filename: ArtifactName::from_filename(
+                "miniblack-23.1.0-py3-none-any.whl",
+                None,
+                &name("miniblack"),
+            )
+            .unwrap(),
+            url: Url::from_file_path(&wheel_path).unwrap(),
+            is_direct_url: false,
+            hashes: Some(ArtifactHashes {
+                sha256: Some(rattler_digest::compute_file_digest::<Sha256>(&wheel_path).unwrap()),
+            }),
+            requires_python: None,
+            dist_info_metadata: Default::default(),
+            yanked: Default::default(),
+            size: None,
+        };
+        let package = PinnedPackage {
+            artifacts: vec![Arc::new(artifact_info)],
+            ..pinned("miniblack", "23.1.0")
+        };
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let package_db = PackageDb::new(
+            Url::parse("https://pypi.org/simple/").unwrap().into(),
+            ClientWithMiddleware::from(reqwest::Client::new()),
+            cache_dir.path(),
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("env");
+        let destdir = dir.path().join("staging");
+        let target = TargetDirectory::new(&location, "/usr/bin/python3");
+        let options = UnpackWheelOptions {
+            destdir: Some(destdir.clone()),
+            ..UnpackWheelOptions::default()
+        };
+        let report = sync_environment(&target, &package_db, None, &[package], &options)
+            .await
+            .unwrap();
+        assert_eq!(report.install.len(), 1);
+
+        // The files are staged into the destdir instead of being written to the target
+        assert!(staged_path(&destdir, &location)
+            .join("black")
+            .join("__init__.py")
+            .is_file());
+        assert!(!location.join("black").exists());
+    }
+}
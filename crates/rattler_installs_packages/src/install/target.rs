@@ -0,0 +1,113 @@
+//! Defines the locations into which wheels can be installed.
+
+use crate::artifacts::wheel::{InstallPaths, UnpackError, UnpackWheelOptions, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::python_env::{CondaEnvironment, PyodideEnvironment, TargetDirectory, UserSite, VEnv};
+use std::path::Path;
+
+/// A location into which wheels can be installed, e.g. a [`VEnv`], a [`TargetDirectory`], a
+/// [`UserSite`], a [`CondaEnvironment`] or a [`PyodideEnvironment`].
+pub trait InstallTarget {
+    /// Install a single wheel into this location.
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError>;
+
+    /// The directory relative to which the [`InstallTarget::install_paths`] are interpreted.
+    fn root(&self) -> &Path;
+
+    /// The locations of the different parts of a wheel relative to [`InstallTarget::root`].
+    fn install_paths(&self) -> &InstallPaths;
+}
+
+impl InstallTarget for VEnv {
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        VEnv::install_wheel(self, wheel, options)
+    }
+
+    fn root(&self) -> &Path {
+        VEnv::root(self)
+    }
+
+    fn install_paths(&self) -> &InstallPaths {
+        VEnv::install_paths(self)
+    }
+}
+
+impl InstallTarget for TargetDirectory {
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        TargetDirectory::install_wheel(self, wheel, options)
+    }
+
+    fn root(&self) -> &Path {
+        TargetDirectory::root(self)
+    }
+
+    fn install_paths(&self) -> &InstallPaths {
+        TargetDirectory::install_paths(self)
+    }
+}
+
+impl InstallTarget for UserSite {
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        UserSite::install_wheel(self, wheel, options)
+    }
+
+    fn root(&self) -> &Path {
+        UserSite::root(self)
+    }
+
+    fn install_paths(&self) -> &InstallPaths {
+        UserSite::install_paths(self)
+    }
+}
+
+impl InstallTarget for CondaEnvironment {
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        CondaEnvironment::install_wheel(self, wheel, options)
+    }
+
+    fn root(&self) -> &Path {
+        CondaEnvironment::root(self)
+    }
+
+    fn install_paths(&self) -> &InstallPaths {
+        CondaEnvironment::install_paths(self)
+    }
+}
+
+impl InstallTarget for PyodideEnvironment {
+    fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        PyodideEnvironment::install_wheel(self, wheel, options)
+    }
+
+    fn root(&self) -> &Path {
+        PyodideEnvironment::root(self)
+    }
+
+    fn install_paths(&self) -> &InstallPaths {
+        PyodideEnvironment::install_paths(self)
+    }
+}
@@ -0,0 +1,263 @@
+use crate::artifacts::wheel::{
+    staged_path, InstallPaths, InstallScheme, UnpackError, UnpackWheelOptions, UnpackedWheel,
+};
+use crate::artifacts::Wheel;
+use crate::install::LinkStrategy;
+use crate::types::{DirectUrlJson, ProvenanceUrlJson};
+use fs_err as fs;
+use rattler_digest::Sha256;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A cache of wheels that have already been unpacked. Installing a wheel that is present in the
+/// cache does not require extracting and hashing the individual files of the wheel, instead the
+/// unpacked tree is placed into the environment in bulk using the [`LinkStrategy`] of the
+/// installation. This significantly speeds up the creation of environments on platforms where
+/// extracting many small files is slow, like Windows.
+///
+/// The unpacked tree of a wheel depends on the environment it is installed into, e.g. on the
+/// python interpreter that is referenced by the scripts. Every combination of a wheel and the
+/// relevant [`UnpackWheelOptions`] is therefore stored separately.
+///
+/// Wheels that are byte compiled during the installation are not cached because the compiled files
+/// refer to the location of their sources.
+#[derive(Debug, Clone)]
+pub struct UnpackedWheelCache {
+    root: PathBuf,
+}
+
+/// Everything that determines the contents of an unpacked wheel.
+#[derive(Serialize)]
+struct CacheKey<'a> {
+    wheel: String,
+    content: String,
+    install_paths: InstallScheme,
+    windows: bool,
+    python_executable: &'a Path,
+    installer: Option<&'a str>,
+    requested: bool,
+    extras: Option<Vec<&'a str>>,
+    launcher_arch: Option<String>,
+    direct_url_json: Option<&'a DirectUrlJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provenance_url_json: Option<&'a ProvenanceUrlJson>,
+    relocatable_dest: Option<&'a Path>,
+}
+
+impl UnpackedWheelCache {
+    /// Constructs a new cache that stores the unpacked wheels in the specified directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Returns the directory in which the unpacked wheels are stored.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory that contains the unpacked tree of `wheel` for the given options.
+    fn entry_path(
+        &self,
+        wheel: &Wheel,
+        dest: &Path,
+        paths: &InstallPaths,
+        python_executable: &Path,
+        options: &UnpackWheelOptions,
+    ) -> Result<PathBuf, UnpackError> {
+        let mut extras = options.extras.as_ref().map(|extras| {
+            extras
+                .iter()
+                .map(|extra| extra.as_str())
+                .collect::<Vec<_>>()
+        });
+        if let Some(extras) = extras.as_mut() {
+            extras.sort();
+        }
+
+        let key = CacheKey {
+            wheel: wheel.name.to_string(),
+            content: wheel.content_digest()?,
+            install_paths: paths.scheme(),
+            windows: paths.is_windows(),
+            python_executable,
+            installer: options.installer.as_deref().map(str::trim),
+            requested: options.requested,
+            extras,
+            launcher_arch: options.launcher_arch.map(|arch| format!("{arch:?}")),
+            direct_url_json: options.direct_url_json.as_ref(),
+            provenance_url_json: options.provenance_url_json.as_ref(),
+            // Relocatable scripts refer to the interpreter relative to the destination
+            relocatable_dest: options.relocatable.then_some(dest),
+        };
+        let key = serde_json::to_vec(&key)?;
+        let digest = rattler_digest::compute_bytes_digest::<Sha256>(key);
+        Ok(self.root.join(format!("{digest:x}")))
+    }
+
+    /// Unpacks the `wheel` into `dest` using the unpacked tree from the cache, unpacking the wheel
+    /// into the cache first if it is not present yet.
+    pub(crate) fn unpack(
+        &self,
+        wheel: &Wheel,
+        dest: &Path,
+        paths: &InstallPaths,
+        python_executable: &Path,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        let entry = self.entry_path(wheel, dest, paths, python_executable, options)?;
+        if !entry.is_dir() {
+            self.populate(wheel, dest, paths, python_executable, options, &entry)?;
+        }
+
+        let root = match options.destdir.as_deref() {
+            Some(destdir) => staged_path(destdir, dest),
+            None => dest.to_path_buf(),
+        };
+        place_tree(&entry, &root, options.install_options.link_strategy)?;
+
+        let vitals = wheel
+            .get_vitals()
+            .map_err(UnpackError::FailedToParseWheelVitals)?;
+        Ok(UnpackedWheel {
+            dist_info: root
+                .join(paths.root_location(vitals.root_is_purelib))
+                .join(&vitals.dist_info),
+            metadata: vitals.metadata,
+        })
+    }
+
+    /// Unpacks the `wheel` into the cache `entry`. The wheel is staged in a temporary directory
+    /// first so that a partially unpacked wheel is never visible in the cache.
+    fn populate(
+        &self,
+        wheel: &Wheel,
+        dest: &Path,
+        paths: &InstallPaths,
+        python_executable: &Path,
+        options: &UnpackWheelOptions,
+        entry: &Path,
+    ) -> Result<(), UnpackError> {
+        fs::create_dir_all(&self.root)
+            .map_err(|err| UnpackError::IoError(self.root.display().to_string(), err))?;
+        let staging = tempfile::tempdir_in(&self.root)
+            .map_err(|err| UnpackError::IoError(self.root.display().to_string(), err))?;
+
+        // Unpack with `dest` as the destination so all embedded paths refer to it, but write the
+        // files to the staging directory.
+        wheel.unpack_files(
+            dest,
+            paths,
+            python_executable,
+            &UnpackWheelOptions {
+                destdir: Some(staging.path().to_path_buf()),
+                ..options.clone()
+            },
+        )?;
+
+        // Another process might have populated the same entry concurrently, in which case the
+        // existing entry is kept.
+        let staged = staged_path(staging.path(), dest);
+        if let Err(err) = fs::rename(staged, entry) {
+            if !entry.is_dir() {
+                return Err(UnpackError::IoError(entry.display().to_string(), err));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Places all the files in the `source` directory in `destination` using the `link_strategy`.
+fn place_tree(
+    source: &Path,
+    destination: &Path,
+    link_strategy: LinkStrategy,
+) -> Result<(), UnpackError> {
+    fs::create_dir_all(destination)
+        .map_err(|err| UnpackError::IoError(destination.display().to_string(), err))?;
+    let entries = fs::read_dir(source)
+        .map_err(|err| UnpackError::IoError(source.display().to_string(), err))?;
+    for entry in entries {
+        let entry = entry.map_err(|err| UnpackError::IoError(source.display().to_string(), err))?;
+        let path = entry.path();
+        let target = destination.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .map_err(|err| UnpackError::IoError(path.display().to_string(), err))?;
+        if file_type.is_dir() {
+            place_tree(&path, &target, link_strategy)?;
+        } else {
+            link_strategy
+                .link(&path, &target)
+                .map_err(|err| UnpackError::IoError(target.display().to_string(), err))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::install::InstallOptions;
+    use crate::types::NormalizedPackageName;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_unpacked_cache() {
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = UnpackedWheelCache::new(cache_dir.path());
+        let install_paths = InstallPaths::for_venv((3, 8, 5), false);
+        let options = UnpackWheelOptions {
+            installer: Some(String::from("rip")),
+            install_options: InstallOptions {
+                link_strategy: LinkStrategy::Copy,
+                unpacked_cache: Some(cache.clone()),
+                ..InstallOptions::default()
+            },
+            ..Default::default()
+        };
+
+        // The first installation populates the cache, the second one uses it.
+        let mut trees = Vec::new();
+        for _ in 0..2 {
+            let env = tempfile::tempdir().unwrap();
+            let unpacked = wheel
+                .unpack(
+                    env.path(),
+                    &install_paths,
+                    Path::new("/usr/bin/python3"),
+                    &options,
+                )
+                .unwrap();
+            assert_eq!(
+                unpacked.dist_info,
+                env.path()
+                    .join("lib/python3.8/site-packages/miniblack-23.1.0.dist-info")
+            );
+            trees.push((
+                fs::read_to_string(unpacked.dist_info.join("RECORD")).unwrap(),
+                fs::read_to_string(env.path().join("bin/black")).unwrap(),
+            ));
+        }
+        assert_eq!(trees[0], trees[1]);
+        assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 1);
+
+        // Different options result in a different entry
+        let env = tempfile::tempdir().unwrap();
+        wheel
+            .unpack(
+                env.path(),
+                &install_paths,
+                Path::new("/usr/bin/python3.8"),
+                &options,
+            )
+            .unwrap();
+        assert_eq!(fs::read_dir(cache_dir.path()).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,142 @@
+//! Functionality to resolve packages for an existing environment while keeping the installed
+//! versions of packages that do not have to be upgraded, similar to `pip install --upgrade`.
+//!
+//! Installed distributions that should not be upgraded are passed to the resolver as favored
+//! packages. The resolver then selects the installed version unless it conflicts with the
+//! requirements, after which the sync installer leaves them untouched.
+
+use crate::install::parallel::dependency_names;
+use crate::install::sync::read_installed_metadata;
+use crate::python_env::Distribution;
+use crate::resolve::PinnedPackage;
+use crate::types::NormalizedPackageName;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Determines which installed packages are upgraded when packages are explicitly upgraded.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum UpgradeStrategy {
+    /// Only the explicitly upgraded packages are upgraded. Their dependencies are only upgraded
+    /// if the installed version no longer satisfies the requirements. This is the default
+    /// strategy of pip.
+    #[default]
+    OnlyIfNeeded,
+
+    /// The explicitly upgraded packages and all of their dependencies, recursively, are upgraded
+    /// to the latest versions that satisfy the requirements.
+    Eager,
+}
+
+/// Returns the names of the `installed` distributions that should be upgraded according to the
+/// `strategy` when the `upgrade` packages are upgraded. `root` is the directory relative to which
+/// the `dist_info` paths of the distributions are interpreted.
+pub fn packages_to_upgrade(
+    root: &Path,
+    installed: &[Distribution],
+    upgrade: &HashSet<NormalizedPackageName>,
+    strategy: UpgradeStrategy,
+) -> HashSet<NormalizedPackageName> {
+    let installed_by_name = installed
+        .iter()
+        .map(|distribution| (&distribution.name, distribution))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = upgrade
+        .iter()
+        .filter(|name| installed_by_name.contains_key(name))
+        .cloned()
+        .collect::<HashSet<_>>();
+    if strategy == UpgradeStrategy::OnlyIfNeeded {
+        return result;
+    }
+
+    // Walk the installed dependencies of the upgraded packages
+    let mut queue = result.iter().cloned().collect::<Vec<_>>();
+    while let Some(name) = queue.pop() {
+        let Some(metadata) = installed_by_name
+            .get(&name)
+            .and_then(|distribution| read_installed_metadata(&root.join(&distribution.dist_info)))
+        else {
+            continue;
+        };
+        for dependency in dependency_names(&metadata) {
+            if installed_by_name.contains_key(&dependency) && result.insert(dependency.clone()) {
+                queue.push(dependency);
+            }
+        }
+    }
+
+    result
+}
+
+/// Constructs the favored packages to pass to the resolver to keep the `installed` distributions
+/// that should not be upgraded according to [`packages_to_upgrade`].
+///
+/// The favored packages have no artifacts, the resolver takes them from the index when it
+/// retrieves the versions of a package, so only the packages that are part of the resolution are
+/// looked up. Distributions that were installed from a direct url are not favored, neither are
+/// distributions whose version is no longer available on the index.
+pub fn favored_installed_packages(
+    root: &Path,
+    installed: &[Distribution],
+    upgrade: &HashSet<NormalizedPackageName>,
+    strategy: UpgradeStrategy,
+) -> HashMap<NormalizedPackageName, PinnedPackage> {
+    let upgraded = packages_to_upgrade(root, installed, upgrade, strategy);
+    installed
+        .iter()
+        .filter(|distribution| {
+            distribution.direct_url.is_none() && !upgraded.contains(&distribution.name)
+        })
+        .map(|distribution| {
+            (
+                distribution.name.clone(),
+                PinnedPackage {
+                    name: distribution.name.clone(),
+                    version: distribution.version.clone(),
+                    url: None,
+                    extras: Default::default(),
+                    artifacts: Vec::new(),
+                    dependencies: Vec::new(),
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::InstallPaths;
+    use crate::python_env::find_distributions_in_venv;
+    use std::str::FromStr;
+
+    fn names(names: &HashSet<NormalizedPackageName>) -> Vec<&str> {
+        let mut names = names.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_packages_to_upgrade() {
+        let venv_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/find_distributions/");
+        let install_paths = InstallPaths::for_venv((3, 8, 5), true);
+        let installed = find_distributions_in_venv(&venv_path, &install_paths).unwrap();
+        let upgrade = HashSet::from([
+            NormalizedPackageName::from_str("jinja2").unwrap(),
+            NormalizedPackageName::from_str("not-installed").unwrap(),
+        ]);
+
+        let only_if_needed = packages_to_upgrade(
+            &venv_path,
+            &installed,
+            &upgrade,
+            UpgradeStrategy::OnlyIfNeeded,
+        );
+        assert_eq!(names(&only_if_needed), ["jinja2"]);
+
+        let eager = packages_to_upgrade(&venv_path, &installed, &upgrade, UpgradeStrategy::Eager);
+        assert_eq!(names(&eager), ["jinja2", "markupsafe"]);
+    }
+}
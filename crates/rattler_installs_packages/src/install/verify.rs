@@ -0,0 +1,266 @@
+//! Functionality to audit the files of installed distributions against their `RECORD` files.
+
+use crate::artifacts::wheel::InstallPaths;
+use crate::install::file_cache::copy_and_hash;
+use crate::python_env::{find_distributions_in_venv, Distribution, FindDistributionError};
+use crate::types::Record;
+use fs_err as fs;
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// The result of verifying a single distribution against its `RECORD` file.
+#[derive(Debug, Clone)]
+pub struct DistributionVerification {
+    /// The distribution that was verified
+    pub distribution: Distribution,
+
+    /// Files whose size or hash differs from the one in the `RECORD` file.
+    pub modified: Vec<PathBuf>,
+
+    /// Files that are listed in the `RECORD` file but that do not exist.
+    pub missing: Vec<PathBuf>,
+
+    /// Files that are located in one of the directories of the distribution but that are not
+    /// listed in the `RECORD` file of any distribution. Byte code in `__pycache__` directories is
+    /// ignored because it is often generated after installation.
+    pub unexpected: Vec<PathBuf>,
+}
+
+impl DistributionVerification {
+    /// Returns true if the installed files exactly match the `RECORD` file.
+    pub fn is_ok(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// An error that can occur when verifying an environment.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum VerifyError {
+    #[error("failed to determine the installed distributions")]
+    FailedToFindDistributions(#[from] FindDistributionError),
+
+    #[error("the RECORD file of '{0}' is invalid")]
+    RecordFileInvalid(PathBuf, #[source] csv::Error),
+
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+}
+
+/// Verifies all distributions that are installed in the environment at `root` by re-hashing
+/// their files and comparing them with the hashes recorded in their `RECORD` files.
+///
+/// All paths in the results are relative to the site-packages directory of the distribution, the
+/// same as in `RECORD` files. A distribution without a `RECORD` file is reported as missing its
+/// `RECORD` file.
+pub fn verify_environment(
+    root: &Path,
+    install_paths: &InstallPaths,
+) -> Result<Vec<DistributionVerification>, VerifyError> {
+    // Read the RECORD files of all distributions up front, unexpected files are only those that
+    // are not recorded by any distribution.
+    let mut distributions = Vec::new();
+    let mut recorded_files = HashSet::new();
+    for distribution in find_distributions_in_venv(root, install_paths)? {
+        let dist_info = root.join(&distribution.dist_info);
+        let site_packages = dist_info.parent().unwrap_or(root).to_path_buf();
+        let record_path = dist_info.join("RECORD");
+        let record = match Record::from_path(&record_path) {
+            Ok(record) => Some(record),
+            Err(e) => match e.kind() {
+                csv::ErrorKind::Io(e) if e.kind() == io::ErrorKind::NotFound => None,
+                _ => return Err(VerifyError::RecordFileInvalid(record_path, e)),
+            },
+        };
+        if let Some(record) = &record {
+            recorded_files.extend(
+                record
+                    .iter()
+                    .map(|entry| normalize_path(&site_packages.join(&entry.path))),
+            );
+        }
+        distributions.push((distribution, site_packages, record));
+    }
+
+    distributions
+        .into_iter()
+        .map(|(distribution, site_packages, record)| {
+            verify_distribution(distribution, &site_packages, record, &recorded_files)
+        })
+        .collect()
+}
+
+/// Verifies a single distribution against its `RECORD` file.
+fn verify_distribution(
+    distribution: Distribution,
+    site_packages: &Path,
+    record: Option<Record>,
+    recorded_files: &HashSet<PathBuf>,
+) -> Result<DistributionVerification, VerifyError> {
+    let mut result = DistributionVerification {
+        distribution,
+        modified: Vec::new(),
+        missing: Vec::new(),
+        unexpected: Vec::new(),
+    };
+
+    let dist_info_dir = PathBuf::from(
+        result
+            .distribution
+            .dist_info
+            .file_name()
+            .unwrap_or_default(),
+    );
+    let Some(record) = record else {
+        result.missing.push(dist_info_dir.join("RECORD"));
+        return Ok(result);
+    };
+
+    // The directories directly below site-packages that contain files of this distribution.
+    let mut top_level_dirs = BTreeSet::from([dist_info_dir]);
+
+    for entry in record {
+        let relative_path = PathBuf::from(&entry.path);
+        let path = site_packages.join(&relative_path);
+        let mut components = relative_path.components();
+        if let (Some(Component::Normal(first)), Some(_)) = (components.next(), components.next()) {
+            top_level_dirs.insert(PathBuf::from(first));
+        }
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                result.missing.push(relative_path);
+                continue;
+            }
+            Err(e) => return Err(VerifyError::IoError(path, e)),
+        };
+
+        // Entries without a hash (e.g. the RECORD file itself) can only be checked for existence.
+        let Some(expected_hash) = entry.hash else {
+            continue;
+        };
+
+        if entry.size.is_some_and(|size| size != metadata.len()) {
+            result.modified.push(relative_path);
+            continue;
+        }
+
+        let (hash, _) = fs::File::open(&path)
+            .and_then(|mut file| copy_and_hash(&mut file, &mut io::sink()))
+            .map_err(|e| VerifyError::IoError(path.clone(), e))?;
+        if hash != expected_hash {
+            result.modified.push(relative_path);
+        }
+    }
+
+    for top_level_dir in top_level_dirs {
+        find_unexpected_files(
+            site_packages,
+            &top_level_dir,
+            recorded_files,
+            &mut result.unexpected,
+        )?;
+    }
+
+    result.modified.sort();
+    result.missing.sort();
+    result.unexpected.sort();
+    Ok(result)
+}
+
+/// Recursively finds all files in `site_packages/relative_dir` that are not part of
+/// `recorded_files` and adds them to `unexpected`.
+fn find_unexpected_files(
+    site_packages: &Path,
+    relative_dir: &Path,
+    recorded_files: &HashSet<PathBuf>,
+    unexpected: &mut Vec<PathBuf>,
+) -> Result<(), VerifyError> {
+    let dir = site_packages.join(relative_dir);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(VerifyError::IoError(dir, e)),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(|e| VerifyError::IoError(dir.clone(), e))?;
+        let relative_path = relative_dir.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .map_err(|e| VerifyError::IoError(entry.path(), e))?;
+        if file_type.is_dir() {
+            if entry.file_name() != "__pycache__" {
+                find_unexpected_files(site_packages, &relative_path, recorded_files, unexpected)?;
+            }
+        } else if !recorded_files.contains(&normalize_path(&entry.path())) {
+            unexpected.push(relative_path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Lexically removes `.` and `..` components from a path so paths from `RECORD` files, which may
+/// point outside of site-packages, can be compared with paths on disk.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::UnpackWheelOptions;
+    use crate::artifacts::Wheel;
+    use crate::python_env::TargetDirectory;
+    use crate::types::NormalizedPackageName;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_verify_environment() {
+        let wheel_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl");
+        let wheel = Wheel::from_path(
+            &wheel_path,
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = TargetDirectory::new(tmpdir.path(), "/usr/bin/python3");
+        target
+            .install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+
+        // A freshly installed environment matches its RECORD files.
+        let verification = verify_environment(tmpdir.path(), target.install_paths()).unwrap();
+        assert_eq!(verification.len(), 1);
+        assert!(verification[0].is_ok(), "{:?}", verification[0]);
+
+        // Tamper with the environment
+        fs::write(tmpdir.path().join("black/__init__.py"), "print('hello')").unwrap();
+        fs::remove_file(tmpdir.path().join("blackd/__init__.py")).unwrap();
+        fs::write(tmpdir.path().join("black/extra.py"), "").unwrap();
+        fs::create_dir_all(tmpdir.path().join("black/__pycache__")).unwrap();
+        fs::write(tmpdir.path().join("black/__pycache__/extra.pyc"), "").unwrap();
+
+        let verification = verify_environment(tmpdir.path(), target.install_paths()).unwrap();
+        let verification = &verification[0];
+        assert_eq!(verification.modified, [Path::new("black/__init__.py")]);
+        assert_eq!(verification.missing, [Path::new("blackd/__init__.py")]);
+        assert_eq!(verification.unexpected, [Path::new("black/extra.py")]);
+    }
+}
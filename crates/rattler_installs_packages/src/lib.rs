@@ -2,6 +2,19 @@
 //! It's based on our experience with building Rattler and aims to provide the same experience but for PyPI instead of Conda.
 //! It should be fast and easy to use.
 //! Like Rattler, this library is not a package manager itself but provides the low-level plumbing to be used in one.
+//!
+//! # Runtimes
+//!
+//! The async API must be polled from within a [tokio](https://tokio.rs) runtime that has its IO and
+//! time drivers enabled, because the HTTP client, the spawned python processes and the file locks
+//! of the caches are tokio based. Both the multi-threaded and the current-thread runtime are
+//! supported. On a current-thread runtime all async work runs on a single thread, only the solver
+//! and blocking filesystem work are moved to the blocking thread pool of the runtime, whose size
+//! can be limited with [`tokio::runtime::Builder::max_blocking_threads`].
+//!
+//! Applications that use a different executor, or no executor at all, can use the [`blocking`]
+//! API, which drives the async API on a runtime that it owns. A runtime of any flavor can be
+//! passed to [`blocking::PackageDb::from_async_with_runtime`].
 
 #![deny(missing_docs)]
 
@@ -21,4 +34,30 @@ mod win;
 
 pub mod artifacts;
 
-pub use utils::normalize_index_url;
+pub mod install;
+
+pub mod tools;
+
+pub mod zipapp;
+
+pub mod blocking;
+
+pub mod lock;
+
+pub mod cache;
+
+pub mod config;
+
+pub mod reporter;
+
+pub mod telemetry;
+
+pub mod metrics;
+
+pub mod audit;
+
+pub mod sbom;
+
+pub mod license_policy;
+
+pub use utils::{normalize_index_url, InsufficientDiskSpace};
@@ -0,0 +1,354 @@
+//! Checks the licenses of resolved or installed packages against an allow and deny list.
+//!
+//! A [`LicensePolicy`] evaluates the license of every component of an [`Sbom`]. Licenses that are
+//! SPDX expressions like `MIT OR Apache-2.0` are evaluated by their license ids: an `OR`
+//! expression passes if any of its alternatives passes, an `AND` expression only if all of its
+//! parts pass. Licenses that are free text pass if they match an allowed license exactly, ignoring
+//! case. Packages without a license only violate a policy that has allowed licenses.
+//!
+//! The result is a [`LicenseReport`] with the packages that violate the policy. Whether an
+//! operation fails because of the violations is up to the caller, see
+//! [`LicensePolicy::is_enforced`].
+
+use crate::sbom::Sbom;
+use crate::types::{NormalizedPackageName, Version};
+use serde::Serialize;
+
+/// The licenses that packages can have, see the [module docs](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LicensePolicy {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    enforce: bool,
+}
+
+impl LicensePolicy {
+    /// Constructs a policy that allows every license.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a license that packages can have. Once a license is allowed, packages whose license
+    /// is not allowed violate the policy.
+    pub fn with_allowed(mut self, license: impl Into<String>) -> Self {
+        self.allow.push(license.into());
+        self
+    }
+
+    /// Adds a license that packages can never have, even if it is also allowed.
+    pub fn with_denied(mut self, license: impl Into<String>) -> Self {
+        self.deny.push(license.into());
+        self
+    }
+
+    /// Sets whether operations fail if packages violate the policy, by default the violations are
+    /// only reported.
+    pub fn with_enforced(mut self, enforce: bool) -> Self {
+        self.enforce = enforce;
+        self
+    }
+
+    /// Returns true if the policy neither allows nor denies any license, in which case no package
+    /// can violate it.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Returns true if operations should fail if packages violate the policy.
+    pub fn is_enforced(&self) -> bool {
+        self.enforce
+    }
+
+    /// Returns why a package with the given `license` violates the policy, or `None` if it
+    /// doesn't.
+    pub fn check_license(&self, license: Option<&str>) -> Option<String> {
+        let Some(license) = license.map(str::trim).filter(|license| !license.is_empty()) else {
+            return (!self.allow.is_empty())
+                .then(|| String::from("the license is unknown and not allowed by the policy"));
+        };
+
+        match parse_expression(license) {
+            Some(expression) => self.evaluate(&expression).err(),
+            None => self.check_id(license).err(),
+        }
+    }
+
+    /// Checks the license of every component of `sbom`.
+    pub fn check(&self, sbom: &Sbom) -> LicenseReport {
+        let violations = sbom
+            .components
+            .iter()
+            .filter_map(|component| {
+                let reason = self.check_license(component.license.as_deref())?;
+                Some(LicenseViolation {
+                    name: component.name.clone(),
+                    version: component.version.clone(),
+                    license: component.license.clone(),
+                    reason,
+                })
+            })
+            .collect();
+        LicenseReport {
+            checked: sbom.components.len(),
+            violations,
+        }
+    }
+
+    fn evaluate(&self, expression: &Expression) -> Result<(), String> {
+        match expression {
+            Expression::License(id) => self.check_id(id),
+            Expression::Or(alternatives) => {
+                let mut first_err = None;
+                for alternative in alternatives {
+                    match self.evaluate(alternative) {
+                        Ok(()) => return Ok(()),
+                        Err(err) => {
+                            first_err.get_or_insert(err);
+                        }
+                    }
+                }
+                first_err.map_or(Ok(()), Err)
+            }
+            Expression::And(parts) => parts.iter().try_for_each(|part| self.evaluate(part)),
+        }
+    }
+
+    fn check_id(&self, id: &str) -> Result<(), String> {
+        let matches = |licenses: &[String]| {
+            licenses
+                .iter()
+                .any(|license| license.trim().eq_ignore_ascii_case(id))
+        };
+        if matches(&self.deny) {
+            Err(format!("{id} is denied by the license policy"))
+        } else if !self.allow.is_empty() && !matches(&self.allow) {
+            Err(format!("{id} is not allowed by the license policy"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A package that violates a [`LicensePolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LicenseViolation {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The version of the package
+    pub version: Version,
+
+    /// The license of the package, if it is known
+    pub license: Option<String>,
+
+    /// Why the license violates the policy
+    pub reason: String,
+}
+
+/// The result of [`LicensePolicy::check`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LicenseReport {
+    /// The number of packages that were checked
+    pub checked: usize,
+
+    /// The packages that violate the policy
+    pub violations: Vec<LicenseViolation>,
+}
+
+/// A parsed SPDX license expression. A license with an exception, e.g.
+/// `GPL-2.0-only WITH Classpath-exception-2.0`, is a single license.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    License(String),
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+}
+
+/// Parses an SPDX license expression, returns `None` if `license` is free text instead.
+fn parse_expression(license: &str) -> Option<Expression> {
+    let spaced = license.replace('(', " ( ").replace(')', " ) ");
+    let tokens = spaced.split_whitespace().collect::<Vec<_>>();
+    let mut parser = Parser { tokens, pos: 0 };
+    let expression = parser.or()?;
+    (parser.pos == parser.tokens.len()).then_some(expression)
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    fn next_is(&mut self, operator: &str) -> bool {
+        let matches = self
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(operator));
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Option<Expression> {
+        let mut alternatives = vec![self.and()?];
+        while self.next_is("OR") {
+            alternatives.push(self.and()?);
+        }
+        Some(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Expression::Or(alternatives)
+        })
+    }
+
+    fn and(&mut self) -> Option<Expression> {
+        let mut parts = vec![self.license()?];
+        while self.next_is("AND") {
+            parts.push(self.license()?);
+        }
+        Some(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expression::And(parts)
+        })
+    }
+
+    fn license(&mut self) -> Option<Expression> {
+        if self.next_is("(") {
+            let expression = self.or()?;
+            return self.next_is(")").then_some(expression);
+        }
+        let id = self.id()?;
+        if self.next_is("WITH") {
+            let exception = self.id()?;
+            return Some(Expression::License(format!("{id} WITH {exception}")));
+        }
+        Some(Expression::License(id.to_owned()))
+    }
+
+    fn id(&mut self) -> Option<&'a str> {
+        let token = self.next()?;
+        let is_id = !["AND", "OR", "WITH"]
+            .iter()
+            .any(|operator| token.eq_ignore_ascii_case(operator))
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | ':'));
+        is_id.then_some(token)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sbom::SbomComponent;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_expression() {
+        let license = |id: &str| Expression::License(id.to_owned());
+        assert_eq!(parse_expression("MIT"), Some(license("MIT")));
+        assert_eq!(
+            parse_expression("MIT OR (Apache-2.0 AND BSD-3-Clause)"),
+            Some(Expression::Or(vec![
+                license("MIT"),
+                Expression::And(vec![license("Apache-2.0"), license("BSD-3-Clause")])
+            ]))
+        );
+        assert_eq!(
+            parse_expression("GPL-2.0-only WITH Classpath-exception-2.0 or MIT"),
+            Some(Expression::Or(vec![
+                license("GPL-2.0-only WITH Classpath-exception-2.0"),
+                license("MIT")
+            ]))
+        );
+        assert_eq!(parse_expression("BSD License"), None);
+        assert_eq!(parse_expression("(MIT"), None);
+        assert_eq!(parse_expression("MIT AND"), None);
+    }
+
+    #[test]
+    fn test_check_license() {
+        let policy = LicensePolicy::new()
+            .with_allowed("MIT")
+            .with_allowed("apache-2.0")
+            .with_allowed("BSD License")
+            .with_allowed("GPL-3.0-only")
+            .with_denied("GPL-3.0-only");
+
+        assert_eq!(policy.check_license(Some("MIT")), None);
+        assert_eq!(policy.check_license(Some("MIT OR GPL-3.0-only")), None);
+        assert_eq!(policy.check_license(Some("Apache-2.0 AND MIT")), None);
+        assert_eq!(policy.check_license(Some("bsd license")), None);
+        assert_eq!(
+            policy.check_license(Some("GPL-3.0-only")).unwrap(),
+            "GPL-3.0-only is denied by the license policy"
+        );
+        assert_eq!(
+            policy.check_license(Some("MIT AND LGPL-2.1")).unwrap(),
+            "LGPL-2.1 is not allowed by the license policy"
+        );
+        assert_eq!(
+            policy
+                .check_license(Some("Proprietary, all rights reserved"))
+                .unwrap(),
+            "Proprietary, all rights reserved is not allowed by the license policy"
+        );
+        assert!(policy.check_license(None).is_some());
+
+        // Without allowed licenses only the denied licenses violate the policy
+        let policy = LicensePolicy::new().with_denied("AGPL-3.0-only");
+        assert_eq!(policy.check_license(None), None);
+        assert_eq!(policy.check_license(Some("Anything goes")), None);
+        assert_eq!(policy.check_license(Some("AGPL-3.0-only OR MIT")), None);
+        assert!(policy.check_license(Some("agpl-3.0-only")).is_some());
+        assert!(LicensePolicy::new().is_empty());
+        assert!(!policy.is_enforced());
+    }
+
+    #[test]
+    fn test_check() {
+        let component = |name: &str, license: Option<&str>| SbomComponent {
+            name: NormalizedPackageName::from_str(name).unwrap(),
+            version: Version::from_str("1.0").unwrap(),
+            license: license.map(String::from),
+            source_url: None,
+            hashes: None,
+            built_from_source: false,
+            dependencies: Vec::new(),
+        };
+        let sbom = Sbom::new(
+            "project",
+            vec![
+                component("flask", Some("BSD-3-Clause")),
+                component("mystery", None),
+                component("copyleft", Some("GPL-3.0-or-later")),
+            ],
+        );
+
+        let report = LicensePolicy::new()
+            .with_allowed("BSD-3-Clause")
+            .with_denied("GPL-3.0-or-later")
+            .check(&sbom);
+        assert_eq!(report.checked, 3);
+        assert_eq!(
+            report
+                .violations
+                .iter()
+                .map(|violation| violation.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["copyleft", "mystery"]
+        );
+        assert_eq!(report.violations[1].license, None);
+    }
+}
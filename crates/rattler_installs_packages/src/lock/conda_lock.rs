@@ -0,0 +1,199 @@
+//! Export of a [`Lockfile`] to the `pip` packages of a
+//! [conda-lock](https://conda.github.io/conda-lock/) lockfile.
+//!
+//! conda-lock stores the packages of all package managers in a single `package` list, the pip
+//! packages are the entries with `manager: pip`. Every entry is locked for a single conda
+//! platform (e.g. `linux-64`) and references a single artifact by url and hash.
+
+use super::{LockedPackage, Lockfile, LockfileError};
+use crate::python_env::PlatformTag;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Returns the conda platform (the name of a conda subdir) that corresponds to a wheel platform
+/// tag, or `None` if conda does not support the platform.
+fn conda_platform(platform: &PlatformTag) -> Option<&'static str> {
+    let platform = match platform {
+        PlatformTag::Manylinux { arch, .. }
+        | PlatformTag::LegacyManylinux { arch, .. }
+        | PlatformTag::Musllinux { arch, .. }
+        | PlatformTag::Linux { arch } => match arch.as_str() {
+            "x86_64" => "linux-64",
+            "i686" => "linux-32",
+            "aarch64" => "linux-aarch64",
+            "armv7l" => "linux-armv7l",
+            "ppc64le" => "linux-ppc64le",
+            "s390x" => "linux-s390x",
+            _ => return None,
+        },
+        PlatformTag::MacOs { binary_format, .. } => match binary_format.as_str() {
+            "x86_64" => "osx-64",
+            "arm64" => "osx-arm64",
+            _ => return None,
+        },
+        PlatformTag::Emscripten { arch, .. } if arch == "wasm32" => "emscripten-wasm32",
+        PlatformTag::Wasi { arch } if arch == "wasm32" => "wasi-wasm32",
+        PlatformTag::Other(tag) => match tag.as_str() {
+            "win_amd64" => "win-64",
+            "win32" => "win-32",
+            "win_arm64" => "win-arm64",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(platform)
+}
+
+/// Quotes a string as a single quoted YAML scalar.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn write_package(
+    out: &mut String,
+    package: &LockedPackage,
+    versions: &HashMap<&str, String>,
+    platform: &str,
+) -> Result<(), LockfileError> {
+    // conda-lock records a single artifact per package, the artifacts are ordered by preference.
+    let artifact = package.artifacts.first().ok_or_else(|| {
+        LockfileError::NoCompatibleArtifacts(package.name.clone(), platform.to_string())
+    })?;
+
+    let mut write = || -> std::fmt::Result {
+        writeln!(out, "- name: {}", package.name.as_str())?;
+        writeln!(out, "  version: {}", quote(&package.version.to_string()))?;
+        writeln!(out, "  manager: pip")?;
+        writeln!(out, "  platform: {platform}")?;
+        if package.dependencies.is_empty() {
+            writeln!(out, "  dependencies: {{}}")?;
+        } else {
+            writeln!(out, "  dependencies:")?;
+            for dependency in &package.dependencies {
+                let specifier = versions
+                    .get(dependency.as_str())
+                    .map(|version| format!("=={version}"))
+                    .unwrap_or_else(|| String::from("*"));
+                writeln!(out, "    {}: {}", dependency.as_str(), quote(&specifier))?;
+            }
+        }
+        writeln!(out, "  url: {}", quote(artifact.url.as_str()))?;
+        match artifact.hashes.sha256 {
+            Some(sha256) => {
+                writeln!(out, "  hash:")?;
+                writeln!(out, "    sha256: {sha256:x}")?;
+            }
+            None => writeln!(out, "  hash: {{}}")?,
+        }
+        writeln!(out, "  category: main")?;
+        writeln!(out, "  optional: false")
+    };
+    write().expect("writing to a string cannot fail");
+    Ok(())
+}
+
+pub(super) fn to_conda_lock_pip(lockfile: &Lockfile) -> Result<String, LockfileError> {
+    let mut out = String::from("package:\n");
+    let mut platforms: HashMap<&str, String> = HashMap::new();
+    for target in &lockfile.targets {
+        let platform = conda_platform(&target.target.platform)
+            .ok_or_else(|| LockfileError::UnsupportedCondaPlatform(target.target.to_string()))?;
+
+        // conda-lock has no notion of a python version, so every platform can only be locked once.
+        if let Some(existing) = platforms.insert(platform, target.target.to_string()) {
+            return Err(LockfileError::DuplicateCondaPlatform(
+                platform.to_string(),
+                existing,
+                target.target.to_string(),
+            ));
+        }
+
+        let versions = target
+            .packages
+            .iter()
+            .map(|package| (package.name.as_str(), package.version.to_string()))
+            .collect::<HashMap<_, _>>();
+        for package in &target.packages {
+            write_package(&mut out, package, &versions, platform)?;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lock::test::package;
+    use crate::lock::{LockTarget, LockedTarget};
+    use pep440_rs::Version;
+    use std::str::FromStr;
+
+    const SHA256: &str = "aca0cab64ad4e6a9f4830ca8922d6229aa9baed0e0544dab7af1b229a3813c89";
+
+    fn target(python_version: &str, platform: &str) -> LockTarget {
+        LockTarget::new(
+            Version::from_str(python_version).unwrap(),
+            PlatformTag::from_str(platform).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_to_conda_lock_pip() {
+        let mut lockfile = Lockfile::new(Vec::new());
+        lockfile.targets.push(LockedTarget {
+            target: target("3.11", "manylinux_2_17_x86_64"),
+            packages: vec![
+                package(
+                    "certifi",
+                    "2024.2.2",
+                    &[("certifi-2024.2.2-py3-none-any.whl", Some(SHA256))],
+                    &[],
+                ),
+                package(
+                    "requests",
+                    "2.31.0",
+                    &[("requests-2.31.0-py3-none-any.whl", Some(SHA256))],
+                    &["certifi"],
+                ),
+            ],
+        });
+        lockfile.targets.push(LockedTarget {
+            target: target("3.11", "win_amd64"),
+            packages: vec![package(
+                "certifi",
+                "2024.2.2",
+                &[("certifi-2024.2.2-py3-none-any.whl", Some(SHA256))],
+                &[],
+            )],
+        });
+
+        insta::assert_snapshot!(to_conda_lock_pip(&lockfile).unwrap());
+
+        lockfile.targets.push(LockedTarget {
+            target: target("3.12", "manylinux_2_28_x86_64"),
+            packages: Vec::new(),
+        });
+        assert!(matches!(
+            to_conda_lock_pip(&lockfile),
+            Err(LockfileError::DuplicateCondaPlatform(platform, _, _)) if platform == "linux-64"
+        ));
+    }
+
+    #[test]
+    fn test_conda_platform() {
+        for (tag, expected) in [
+            ("manylinux_2_17_aarch64", Some("linux-aarch64")),
+            ("musllinux_1_2_x86_64", Some("linux-64")),
+            ("macosx_11_0_arm64", Some("osx-arm64")),
+            ("macosx_10_12_universal2", None),
+            ("win_amd64", Some("win-64")),
+            ("any", None),
+        ] {
+            assert_eq!(
+                conda_platform(&PlatformTag::from_str(tag).unwrap()),
+                expected,
+                "{tag}"
+            );
+        }
+    }
+}
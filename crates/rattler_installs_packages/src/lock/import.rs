@@ -0,0 +1,298 @@
+//! Functionality that is shared by the importers of the lockfiles of other tools.
+
+use super::{LockTarget, LockedArtifact, LockedPackage, LockfileError};
+use crate::artifacts::ArtifactRegistry;
+use crate::index::{parse_hash, ArtifactFilter, ArtifactRequest, PackageDb};
+use crate::resolve::PypiVersion;
+use crate::types::{ArtifactHashes, ArtifactName, Extra, NormalizedPackageName};
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep440_rs::Version;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use url::Url;
+
+/// Parses a hash in the `<algorithm>:<hex>` format that most tools use. Hashes with other
+/// algorithms than sha256 are ignored.
+pub(super) fn parse_locked_hash(hash: &str) -> ArtifactHashes {
+    hash.split_once(':')
+        .and_then(|(algorithm, hex)| parse_hash(&format!("{algorithm}={hex}")))
+        .unwrap_or_default()
+}
+
+/// A file of a package that was locked by another tool.
+pub(super) struct ImportedFile {
+    /// The filename of the artifact
+    pub filename: String,
+
+    /// The url of the artifact if the tool records it, otherwise the artifact is looked up on the
+    /// indexes.
+    pub url: Option<Url>,
+
+    /// The hashes that were locked for the artifact
+    pub hashes: ArtifactHashes,
+}
+
+/// A package that was locked by another tool.
+pub(super) struct ImportedPackage {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The locked version
+    pub version: Version,
+
+    /// The direct URL of the package if it was not locked from an index
+    pub url: Option<Url>,
+
+    /// The locked files of the package
+    pub files: Vec<ImportedFile>,
+
+    /// The locked hashes of the files of the package if the tool does not record the files
+    /// themselves. The files with these hashes are looked up on the indexes.
+    pub hashes: Vec<ArtifactHashes>,
+}
+
+impl ImportedPackage {
+    /// Returns the artifacts of the package. Files without a url are looked up on the indexes of
+    /// `package_db`, and the locked hashes are verified against the hashes of the index.
+    pub async fn artifacts(&self, package_db: &PackageDb) -> miette::Result<Vec<LockedArtifact>> {
+        if let Some(url) = &self.url {
+            // A source tree is represented by a single artifact without a file extension.
+            if self.files.is_empty() {
+                return Ok(vec![LockedArtifact {
+                    filename: format!("{}-{}", self.name.as_str(), self.version),
+                    url: url.clone(),
+                    hashes: ArtifactHashes::default(),
+                    requires_python: None,
+                }]);
+            }
+            return Ok(self
+                .files
+                .iter()
+                .map(|file| LockedArtifact {
+                    filename: file.filename.clone(),
+                    url: file.url.clone().unwrap_or_else(|| url.clone()),
+                    hashes: file.hashes.clone(),
+                    requires_python: None,
+                })
+                .collect());
+        }
+
+        if self.files.is_empty() {
+            return self.artifacts_by_hash(package_db).await;
+        }
+
+        let available = if self.files.iter().any(|file| file.url.is_none()) {
+            Some(
+                package_db
+                    .available_artifacts(ArtifactRequest::FromIndex(self.name.clone()))
+                    .await?,
+            )
+        } else {
+            None
+        };
+
+        let mut artifacts = Vec::with_capacity(self.files.len());
+        for file in &self.files {
+            let indexed = available.and_then(|available| {
+                available
+                    .values()
+                    .flatten()
+                    .find(|artifact| artifact.filename.to_string() == file.filename)
+            });
+            let url = match (&file.url, indexed) {
+                (Some(url), _) => url.clone(),
+                (None, Some(indexed)) => indexed.url.clone(),
+                (None, None) => {
+                    return Err(LockfileError::MissingArtifact(
+                        self.name.clone(),
+                        file.filename.clone(),
+                    ))
+                    .into_diagnostic()
+                }
+            };
+
+            let indexed_hashes = indexed.and_then(|indexed| indexed.hashes.as_ref());
+            if let (Some(locked), Some(indexed)) = (
+                file.hashes.sha256,
+                indexed_hashes.and_then(|hashes| hashes.sha256),
+            ) {
+                if locked != indexed {
+                    return Err(LockfileError::ImportedHashMismatch(
+                        self.name.clone(),
+                        file.filename.clone(),
+                    ))
+                    .into_diagnostic();
+                }
+            }
+
+            artifacts.push(LockedArtifact {
+                filename: file.filename.clone(),
+                url,
+                hashes: if file.hashes.is_empty() {
+                    indexed_hashes.cloned().unwrap_or_default()
+                } else {
+                    file.hashes.clone()
+                },
+                requires_python: indexed.and_then(|indexed| indexed.requires_python.clone()),
+            });
+        }
+        Ok(artifacts)
+    }
+
+    /// Returns the artifacts of the locked version on the indexes of `package_db` that match one of
+    /// the locked hashes, or all of them if no hashes are locked.
+    async fn artifacts_by_hash(
+        &self,
+        package_db: &PackageDb,
+    ) -> miette::Result<Vec<LockedArtifact>> {
+        let available = package_db
+            .available_artifacts(ArtifactRequest::FromIndex(self.name.clone()))
+            .await?;
+        let artifacts = available
+            .iter()
+            .filter(|(version, _)| {
+                matches!(version, PypiVersion::Version { version, .. } if version == &self.version)
+            })
+            .flat_map(|(_, artifacts)| artifacts)
+            .filter(|artifact| {
+                self.hashes.is_empty()
+                    || artifact
+                        .hashes
+                        .as_ref()
+                        .and_then(|hashes| hashes.sha256)
+                        .map_or(false, |sha256| {
+                            self.hashes.iter().any(|hashes| hashes.sha256 == Some(sha256))
+                        })
+            })
+            .map(|artifact| LockedArtifact {
+                filename: artifact.filename.to_string(),
+                url: artifact.url.clone(),
+                hashes: artifact.hashes.clone().unwrap_or_default(),
+                requires_python: artifact.requires_python.clone(),
+            })
+            .collect::<Vec<_>>();
+        if artifacts.is_empty() {
+            return Err(LockfileError::MissingArtifact(
+                self.name.clone(),
+                format!("version {}", self.version),
+            ))
+            .into_diagnostic();
+        }
+        Ok(artifacts)
+    }
+
+    /// Returns the package with the artifacts that can be installed on `target`, wheels before
+    /// sdists.
+    pub fn locked_for_target(
+        &self,
+        artifacts: &[LockedArtifact],
+        extras: Vec<Extra>,
+        dependencies: Vec<NormalizedPackageName>,
+        target: &LockTarget,
+        registry: &ArtifactRegistry,
+    ) -> Result<LockedPackage, LockfileError> {
+        let filter = ArtifactFilter::new()
+            .with_wheel_tags(Arc::new(target.wheel_tags()))
+            .with_python_version(target.python_version.clone());
+
+        let mut compatible = Vec::new();
+        for artifact in artifacts {
+            let filename =
+                ArtifactName::from_filename(&artifact.filename, self.url.clone(), &self.name)
+                    .map_err(|e| {
+                        LockfileError::InvalidArtifact(
+                            self.name.clone(),
+                            artifact.filename.clone(),
+                            e,
+                        )
+                    })?;
+            if filter.accepts_filename(&filename, registry)
+                && filter.accepts_requires_python(artifact.requires_python.as_ref())
+            {
+                compatible.push((matches!(filename, ArtifactName::Wheel(_)), artifact.clone()));
+            }
+        }
+        if compatible.is_empty() {
+            return Err(LockfileError::NoCompatibleArtifacts(
+                self.name.clone(),
+                target.to_string(),
+            ));
+        }
+        compatible.sort_by_key(|(is_wheel, _)| !is_wheel);
+
+        let mut extras = extras;
+        extras.sort();
+        extras.dedup();
+        let mut dependencies = dependencies;
+        dependencies.sort();
+        dependencies.dedup();
+        Ok(LockedPackage {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            url: self.url.clone(),
+            extras,
+            artifacts: compatible
+                .into_iter()
+                .map(|(_, artifact)| artifact)
+                .collect(),
+            dependencies,
+        })
+    }
+}
+
+/// A package that is installed on a target, see [`select_packages`].
+pub(super) struct SelectedPackage {
+    /// The index of the package in the lockfile of the other tool
+    pub index: usize,
+
+    /// The extras that are enabled for the package
+    pub extras: Vec<Extra>,
+
+    /// The indices of the selected packages that the package depends on
+    pub dependencies: Vec<usize>,
+}
+
+/// Selects the packages that are installed on a target by following the dependencies from the
+/// `roots`. `dependencies` returns the dependencies of a package with the given extras that apply
+/// to the target, together with the extras that are enabled for them.
+pub(super) fn select_packages<E>(
+    roots: impl IntoIterator<Item = usize>,
+    mut dependencies: impl FnMut(usize, &[Extra]) -> Result<Vec<(usize, Vec<Extra>)>, E>,
+) -> Result<Vec<SelectedPackage>, E> {
+    let mut selected: IndexMap<usize, (Vec<Extra>, BTreeSet<usize>)> = IndexMap::new();
+    let mut queue = Vec::new();
+    for root in roots {
+        if selected.insert(root, Default::default()).is_none() {
+            queue.push(root);
+        }
+    }
+
+    while let Some(index) = queue.pop() {
+        let extras = selected[&index].0.clone();
+        for (dependency, dependency_extras) in dependencies(index, &extras)? {
+            selected[&index].1.insert(dependency);
+            let entry = selected.entry(dependency);
+            let mut changed = matches!(entry, indexmap::map::Entry::Vacant(_));
+            let (enabled_extras, _) = entry.or_default();
+            for extra in dependency_extras {
+                if !enabled_extras.contains(&extra) {
+                    enabled_extras.push(extra);
+                    changed = true;
+                }
+            }
+            if changed {
+                queue.push(dependency);
+            }
+        }
+    }
+
+    Ok(selected
+        .into_iter()
+        .map(|(index, (extras, dependencies))| SelectedPackage {
+            index,
+            extras,
+            dependencies: dependencies.into_iter().collect(),
+        })
+        .collect())
+}
@@ -0,0 +1,873 @@
+//! Reading and writing lockfiles.
+//!
+//! A lockfile records the result of resolving a set of requirements for one or more targets. A
+//! target is a combination of a python version and a platform, e.g. python 3.11 on
+//! `manylinux_2_17_x86_64`. For every target the pinned packages are stored together with the
+//! artifacts that can be installed for them, which allows recreating an environment without
+//! resolving again.
+//!
+//! The native format of rip is stored in a `rip.lock` file. A lockfile can also be exported to
+//! the standardized `pylock.toml` format that is described in
+//! [PEP 751](https://peps.python.org/pep-0751/), see [`Lockfile::to_pylock`].
+//!
+//! The lockfile records the hashes of the files that the requirements were read from, and it can
+//! be signed with a minisign key, see [`Lockfile::to_path_signed`] and
+//! [`Lockfile::from_path_verified`]. Together they prove that an environment is installed from the
+//! lockfile that was reviewed, for the requirements that are checked in.
+//!
+//! The lockfiles of other tools can be imported without resolving again, see
+//! [`PoetryLock::to_lockfile`], [`UvLock::to_lockfile`] and [`PipfileLock::to_lockfile`]. A lockfile can also be exported to the
+//! `uv.lock` format, see [`Lockfile::to_uv_lock`], and to the pip packages of a conda-lock
+//! lockfile, see [`Lockfile::to_conda_lock_pip`]. A single target can be exported as an annotated
+//! requirements file like the ones written by pip-compile, see [`LockedTarget::to_requirements_txt`].
+
+mod conda_lock;
+mod import;
+mod pip_compile;
+mod pipfile;
+mod poetry;
+mod pylock;
+mod signature;
+mod target;
+mod uv;
+
+pub use pipfile::{PipfileLock, PIPFILE_LOCK_NAME};
+pub use poetry::{PoetryLock, POETRY_LOCK_NAME};
+pub use signature::{PublicKey, SecretKey, Signature, SignatureError};
+pub use target::LockTarget;
+pub use uv::{UvLock, UV_LOCK_NAME};
+
+use crate::python_env::WheelTagTarget;
+use crate::resolve::PinnedPackage;
+use crate::types::{
+    ArtifactHashes, ArtifactInfo, ArtifactName, Extra, NormalizedPackageName,
+    ParseArtifactNameError,
+};
+use fs_err as fs;
+use itertools::Itertools;
+use pep440_rs::{Version, VersionSpecifiers};
+use pep508_rs::Requirement;
+use rattler_digest::serde::SerializableHash;
+use rattler_digest::{Sha256, Sha256Hash};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use thiserror::Error;
+use toml_edit::{Document, Item, TableLike, Value};
+use url::Url;
+
+/// The version of the lockfile format that is written by this version of rip.
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// The default file name of a lockfile.
+pub const LOCKFILE_NAME: &str = "rip.lock";
+
+/// An error that can occur when reading or writing a lockfile.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum LockfileError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse lockfile {0}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+
+    #[error("failed to parse lockfile {0}")]
+    ParseJson(PathBuf, #[source] serde_json::Error),
+
+    #[error("failed to serialize lockfile")]
+    Serialize(#[from] toml::ser::Error),
+
+    #[error("lockfile {0} has version {1} but only version {LOCKFILE_VERSION} is supported")]
+    UnsupportedVersion(PathBuf, u32),
+
+    #[error("lockfile {0} has pipfile-spec {1} but only pipfile-spec 6 is supported")]
+    UnsupportedPipfileSpec(PathBuf, u32),
+
+    #[error("invalid artifact '{1}' for package {0}")]
+    InvalidArtifact(
+        NormalizedPackageName,
+        String,
+        #[source] ParseArtifactNameError,
+    ),
+
+    #[error("cannot lock for platform '{0}', only concrete platforms are supported")]
+    UnsupportedPlatform(crate::python_env::PlatformTag),
+
+    #[error("{0} changed after the lockfile was created, lock the requirements again")]
+    InputChanged(PathBuf),
+
+    #[error("lockfile {0} is not signed, {1} does not exist")]
+    Unsigned(PathBuf, PathBuf),
+
+    #[error("failed to verify the signature of lockfile {0}")]
+    Signature(PathBuf, #[source] SignatureError),
+
+    #[error("the signature of lockfile {0} was created for a different file '{1}'")]
+    SignedFileMismatch(PathBuf, String),
+
+    #[error("{1} of package {0} is not available from the configured indexes")]
+    MissingArtifact(NormalizedPackageName, String),
+
+    #[error("the locked hash of {1} of package {0} does not match the hash on the index")]
+    ImportedHashMismatch(NormalizedPackageName, String),
+
+    #[error("none of the artifacts of package {0} can be installed on {1}")]
+    NoCompatibleArtifacts(NormalizedPackageName, String),
+
+    #[error("invalid locked version '{1}' of package {0}")]
+    InvalidLockedVersion(NormalizedPackageName, String),
+
+    #[error("package {0} is locked from a source of type '{1}', which cannot be imported")]
+    UnsupportedSource(NormalizedPackageName, String),
+
+    #[error("invalid source url '{1}' of package {0}")]
+    InvalidSourceUrl(NormalizedPackageName, String, #[source] url::ParseError),
+
+    #[error("invalid environment markers '{1}' of package {0}")]
+    InvalidMarker(
+        NormalizedPackageName,
+        String,
+        #[source] Box<pep508_rs::Pep508Error>,
+    ),
+
+    #[error("target {0} has no corresponding conda platform")]
+    UnsupportedCondaPlatform(String),
+
+    #[error("targets {1} and {2} both correspond to conda platform {0}")]
+    DuplicateCondaPlatform(String, String, String),
+}
+
+/// The contents of a lockfile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lockfile {
+    /// The version of the lockfile format.
+    pub version: u32,
+
+    /// The requirements that were resolved.
+    #[serde(default)]
+    pub requirements: Vec<Requirement>,
+
+    /// The files that the requirements were read from, ordered by path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<LockInput>,
+
+    /// The resolved packages per target.
+    #[serde(default)]
+    pub targets: Vec<LockedTarget>,
+}
+
+/// A file that the requirements of a [`Lockfile`] were read from, e.g. a requirements file or a
+/// `pyproject.toml`.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockInput {
+    /// The path of the file relative to the directory of the lockfile, with `/` separators
+    pub path: String,
+
+    /// The hash of the contents of the file
+    #[serde_as(as = "SerializableHash<Sha256>")]
+    pub sha256: Sha256Hash,
+}
+
+/// The packages that were resolved for a single [`LockTarget`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedTarget {
+    /// The target the packages were resolved for.
+    #[serde(flatten)]
+    pub target: LockTarget,
+
+    /// The pinned packages, ordered by name.
+    #[serde(default)]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A pinned package in a lockfile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedPackage {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The pinned version
+    pub version: Version,
+
+    /// The direct URL of the package if it was not resolved from an index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+
+    /// The extras that were selected for the package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extras: Vec<Extra>,
+
+    /// The artifacts that can be installed for the package, ordered by preference.
+    #[serde(default)]
+    pub artifacts: Vec<LockedArtifact>,
+
+    /// The names of the packages of the same target that this package depends on, ordered by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<NormalizedPackageName>,
+}
+
+/// A single artifact of a [`LockedPackage`].
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedArtifact {
+    /// The filename of the artifact
+    pub filename: String,
+
+    /// The url to download the artifact from. The urls of local files, e.g. from a `--find-links`
+    /// directory, are written relative to the lockfile
+    pub url: Url,
+
+    /// The hashes of the artifact
+    #[serde(default, skip_serializing_if = "ArtifactHashes::is_empty")]
+    pub hashes: ArtifactHashes,
+
+    /// The python versions the artifact can be installed for
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_python: Option<VersionSpecifiers>,
+}
+
+impl LockedPackage {
+    /// Records a package that was pinned by the resolver.
+    pub fn from_pinned(package: &PinnedPackage) -> Self {
+        let mut extras = package.extras.iter().cloned().collect::<Vec<_>>();
+        extras.sort();
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            url: package.url.clone(),
+            extras,
+            artifacts: package
+                .artifacts
+                .iter()
+                .map(|artifact| LockedArtifact {
+                    filename: artifact.filename.to_string(),
+                    url: artifact.url.clone(),
+                    hashes: artifact.hashes.clone().unwrap_or_default(),
+                    requires_python: artifact.requires_python.clone(),
+                })
+                .collect(),
+            dependencies: package
+                .dependencies
+                .iter()
+                .filter_map(|requirement| requirement.name.parse().ok())
+                .sorted()
+                .dedup()
+                .collect(),
+        }
+    }
+
+    /// Converts the locked package back into a [`PinnedPackage`] that can be passed to the
+    /// resolver as a locked or favored package, or that can be installed directly.
+    pub fn to_pinned(&self) -> Result<PinnedPackage, LockfileError> {
+        let artifacts = self
+            .artifacts
+            .iter()
+            .map(|artifact| {
+                let filename =
+                    ArtifactName::from_filename(&artifact.filename, self.url.clone(), &self.name)
+                        .map_err(|e| {
+                        LockfileError::InvalidArtifact(
+                            self.name.clone(),
+                            artifact.filename.clone(),
+                            e,
+                        )
+                    })?;
+                Ok(Arc::new(ArtifactInfo {
+                    filename,
+                    url: artifact.url.clone(),
+                    is_direct_url: self.url.is_some(),
+                    hashes: (!artifact.hashes.is_empty()).then(|| artifact.hashes.clone()),
+                    requires_python: artifact.requires_python.clone(),
+                    dist_info_metadata: Default::default(),
+                    yanked: Default::default(),
+                    size: None,
+                }))
+            })
+            .collect::<Result<_, LockfileError>>()?;
+
+        Ok(PinnedPackage {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            url: self.url.clone(),
+            extras: self.extras.iter().cloned().collect(),
+            artifacts,
+            dependencies: Vec::new(),
+        })
+    }
+}
+
+impl LockedTarget {
+    /// Converts all packages of the target into [`PinnedPackage`]s.
+    pub fn pinned_packages(&self) -> Result<Vec<PinnedPackage>, LockfileError> {
+        self.packages.iter().map(LockedPackage::to_pinned).collect()
+    }
+
+    /// Exports the packages of the target as a pinned requirements file in the format of
+    /// `pip-compile --generate-hashes`, annotated with `# via` comments. `origins` contains the
+    /// annotations of the packages that are required directly, e.g. `-r requirements.in`.
+    pub fn to_requirements_txt(
+        &self,
+        origins: &HashMap<NormalizedPackageName, Vec<String>>,
+    ) -> String {
+        pip_compile::to_requirements_txt(self, origins)
+    }
+}
+
+impl Lockfile {
+    /// Constructs an empty lockfile for the given requirements.
+    pub fn new(requirements: Vec<Requirement>) -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            requirements,
+            inputs: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    /// Reads a lockfile from disk.
+    pub fn from_path(path: &Path) -> Result<Self, LockfileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        Self::from_str_with_path(&contents, path)
+    }
+
+    fn from_str_with_path(contents: &str, path: &Path) -> Result<Self, LockfileError> {
+        // Resolve the urls of local artifacts that are relative to the lockfile. A document that
+        // cannot be parsed is reported by `toml::from_str`.
+        let contents = match contents.parse::<Document>() {
+            Ok(mut document) => {
+                let directory = lockfile_directory_url(path);
+                map_artifact_urls(&mut document, |url| match Url::parse(url) {
+                    Err(url::ParseError::RelativeUrlWithoutBase) => directory
+                        .as_ref()
+                        .and_then(|directory| directory.join(url).ok())
+                        .map(String::from),
+                    _ => None,
+                });
+                document.to_string()
+            }
+            Err(_) => contents.to_owned(),
+        };
+        let lockfile: Self =
+            toml::from_str(&contents).map_err(|e| LockfileError::Parse(path.to_path_buf(), e))?;
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(LockfileError::UnsupportedVersion(
+                path.to_path_buf(),
+                lockfile.version,
+            ));
+        }
+        Ok(lockfile)
+    }
+
+    /// Serializes the lockfile to a TOML string.
+    pub fn to_toml_string(&self) -> Result<String, LockfileError> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Serializes the lockfile that is written to `path`. Unlike [`Lockfile::to_toml_string`] the
+    /// urls of local artifacts are relative to the lockfile, so the lockfile does not depend on the
+    /// location of the checkout.
+    fn to_toml_string_at(&self, path: &Path) -> Result<String, LockfileError> {
+        let mut document = self
+            .to_toml_string()?
+            .parse::<Document>()
+            .expect("a serialized lockfile is valid TOML");
+        map_artifact_urls(&mut document, |url| {
+            relative_artifact_url(&Url::parse(url).ok()?, path)
+        });
+        Ok(document.to_string())
+    }
+
+    /// Writes the lockfile to disk.
+    pub fn to_path(&self, path: &Path) -> Result<(), LockfileError> {
+        let contents = self.to_toml_string_at(path)?;
+        fs::write(path, contents).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))
+    }
+
+    /// Writes the lockfile to disk together with a signature of its contents, which is written to
+    /// [`signature_path`]. The signature is compatible with `minisign -Vm`.
+    pub fn to_path_signed(&self, path: &Path, secret_key: &SecretKey) -> Result<(), LockfileError> {
+        let contents = self.to_toml_string_at(path)?;
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let file_name = path
+            .file_name()
+            .map_or_else(Default::default, |name| name.to_string_lossy());
+        let signature = secret_key.sign(
+            contents.as_bytes(),
+            &format!("timestamp:{timestamp}\tfile:{file_name}"),
+        );
+
+        fs::write(path, contents).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        let signature_path = signature_path(path);
+        fs::write(&signature_path, signature.to_string())
+            .map_err(|e| LockfileError::IoError(signature_path, e))
+    }
+
+    /// Reads a lockfile from disk after verifying that it was signed by `public_key` under the same
+    /// file name, see [`Lockfile::to_path_signed`].
+    pub fn from_path_verified(path: &Path, public_key: &PublicKey) -> Result<Self, LockfileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        let signature_path = signature_path(path);
+        let signature = match fs::read_to_string(&signature_path) {
+            Ok(signature) => signature,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(LockfileError::Unsigned(path.to_path_buf(), signature_path))
+            }
+            Err(e) => return Err(LockfileError::IoError(signature_path, e)),
+        };
+        let signature = signature
+            .parse::<Signature>()
+            .map_err(|e| LockfileError::Signature(path.to_path_buf(), e))?;
+        signature
+            .verify(public_key, contents.as_bytes())
+            .map_err(|e| LockfileError::Signature(path.to_path_buf(), e))?;
+
+        // A valid signature of another lockfile must not be accepted for this one
+        let file_name = path.file_name().map(|name| name.to_string_lossy());
+        if signature.file_name() != file_name.as_deref() {
+            return Err(LockfileError::SignedFileMismatch(
+                path.to_path_buf(),
+                signature.file_name().unwrap_or_default().to_string(),
+            ));
+        }
+        Self::from_str_with_path(&contents, path)
+    }
+
+    /// Records the hash of a file that the requirements were read from, replacing an earlier
+    /// record of the same file. The path is stored relative to the lockfile that will be written
+    /// to `lockfile_path`.
+    pub fn add_input(&mut self, input: &Path, lockfile_path: &Path) -> Result<(), LockfileError> {
+        let contents =
+            fs::read(input).map_err(|e| LockfileError::IoError(input.to_path_buf(), e))?;
+        let input = LockInput {
+            path: relative_path(input, lockfile_path),
+            sha256: rattler_digest::compute_bytes_digest::<Sha256>(contents),
+        };
+        self.inputs.retain(|existing| existing.path != input.path);
+        self.inputs.push(input);
+        self.inputs.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(())
+    }
+
+    /// Verifies that the files the requirements were read from did not change since the lockfile
+    /// at `lockfile_path` was created, see [`Lockfile::add_input`].
+    pub fn verify_inputs(&self, lockfile_path: &Path) -> Result<(), LockfileError> {
+        let directory = lockfile_path.parent().unwrap_or(Path::new(""));
+        for input in &self.inputs {
+            let path = directory.join(&input.path);
+            let contents =
+                fs::read(&path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+            if rattler_digest::compute_bytes_digest::<Sha256>(contents) != input.sha256 {
+                return Err(LockfileError::InputChanged(path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the locked packages for the given target, if the target is part of the lockfile.
+    pub fn target(&self, target: &LockTarget) -> Option<&LockedTarget> {
+        self.targets.iter().find(|locked| &locked.target == target)
+    }
+
+    /// Returns the locked target that best matches an interpreter, i.e. the target with the same
+    /// python version whose platform is the most specific one that the interpreter supports.
+    pub fn select_target(&self, interpreter: &WheelTagTarget) -> Option<&LockedTarget> {
+        let compatible_platforms = interpreter.platform.compatible_platforms();
+        self.targets
+            .iter()
+            .filter(|locked| locked.target.python_major_minor() == interpreter.python_version)
+            .filter_map(|locked| {
+                let preference = compatible_platforms
+                    .iter()
+                    .position(|platform| platform == &locked.target.platform)?;
+                Some((preference, locked))
+            })
+            .min_by_key(|(preference, _)| *preference)
+            .map(|(_, locked)| locked)
+    }
+
+    /// Records the packages that were resolved for `target`, replacing any packages that were
+    /// previously recorded for the same target.
+    pub fn add_target(&mut self, target: LockTarget, packages: &[PinnedPackage]) {
+        let mut packages = packages
+            .iter()
+            .map(LockedPackage::from_pinned)
+            .collect::<Vec<_>>();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let locked = LockedTarget { target, packages };
+        match self
+            .targets
+            .iter_mut()
+            .find(|existing| existing.target == locked.target)
+        {
+            Some(existing) => *existing = locked,
+            None => self.targets.push(locked),
+        }
+    }
+
+    /// Exports the lockfile in the `pylock.toml` format of
+    /// [PEP 751](https://peps.python.org/pep-0751/). Packages that are only part of some targets
+    /// are guarded by an environment marker that selects those targets.
+    pub fn to_pylock(&self) -> Result<String, LockfileError> {
+        pylock::to_pylock(self)
+    }
+
+    /// Exports the lockfile in the `uv.lock` format of [uv](https://docs.astral.sh/uv/). uv
+    /// requires the project itself to be part of the lock, it is recorded as a virtual package
+    /// named `project` that depends on the requirements of the lockfile. Packages from an index
+    /// are recorded as coming from `index_url`.
+    pub fn to_uv_lock(
+        &self,
+        project: &NormalizedPackageName,
+        index_url: &Url,
+    ) -> Result<String, LockfileError> {
+        uv::to_uv_lock(self, project, index_url)
+    }
+
+    /// Exports the lockfile as the `pip` packages of a [conda-lock](https://conda.github.io/conda-lock/)
+    /// lockfile. The result is a YAML document with a `package` list that can be merged into the
+    /// `package` list of a `conda-lock.yml`, with one entry per package and conda platform. Every
+    /// target must correspond to a different conda platform, e.g. `linux-64` or `osx-arm64`.
+    pub fn to_conda_lock_pip(&self) -> Result<String, LockfileError> {
+        conda_lock::to_conda_lock_pip(self)
+    }
+}
+
+/// Returns the path of the signature of the lockfile at `path`, which is the path with a
+/// `.minisig` suffix like minisign uses.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".minisig");
+    PathBuf::from(signature_path)
+}
+
+/// Calls `f` with the url of every artifact of a serialized lockfile, and replaces the url with
+/// the result if it returns `Some`.
+fn map_artifact_urls(document: &mut Document, mut f: impl FnMut(&str) -> Option<String>) {
+    fn tables(item: Option<&mut Item>) -> Vec<&mut dyn TableLike> {
+        match item {
+            Some(Item::ArrayOfTables(tables)) => tables
+                .iter_mut()
+                .map(|table| table as &mut dyn TableLike)
+                .collect(),
+            Some(Item::Value(Value::Array(array))) => array
+                .iter_mut()
+                .filter_map(Value::as_inline_table_mut)
+                .map(|table| table as &mut dyn TableLike)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    for target in tables(document.get_mut("targets")) {
+        for package in tables(target.get_mut("packages")) {
+            for artifact in tables(package.get_mut("artifacts")) {
+                let Some(url) = artifact.get_mut("url") else {
+                    continue;
+                };
+                if let Some(replacement) = url.as_str().and_then(&mut f) {
+                    *url = toml_edit::value(replacement);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the url of a local artifact relative to the directory of the lockfile at
+/// `lockfile_path`, or `None` if the artifact is not a local file or cannot be referred to
+/// relatively, e.g. because it is on another drive.
+fn relative_artifact_url(url: &Url, lockfile_path: &Path) -> Option<String> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    let relative = relative_path(&url.to_file_path().ok()?, lockfile_path);
+    let resolved = lockfile_directory_url(lockfile_path)?
+        .join(&relative)
+        .ok()?;
+    (&resolved == url).then_some(relative)
+}
+
+/// Returns the url of the directory that contains the lockfile at `lockfile_path`.
+fn lockfile_directory_url(lockfile_path: &Path) -> Option<Url> {
+    Url::from_directory_path(absolute_path(
+        lockfile_path.parent().unwrap_or(Path::new("")),
+    ))
+    .ok()
+}
+
+/// Returns the canonical absolute form of `path`, or `path` relative to the current directory if
+/// it does not exist.
+fn absolute_path(path: &Path) -> PathBuf {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    path.canonicalize()
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(path)))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Returns `path` relative to the directory of the lockfile at `lockfile_path`, with `/` separators.
+fn relative_path(path: &Path, lockfile_path: &Path) -> String {
+    let path = absolute_path(path);
+    let path = path.components().collect::<Vec<_>>();
+    let directory = absolute_path(lockfile_path.parent().unwrap_or(Path::new("")));
+    let directory = directory.components().collect::<Vec<_>>();
+    let common = path
+        .iter()
+        .zip(&directory)
+        .take_while(|(a, b)| a == b)
+        .count();
+    directory[common..]
+        .iter()
+        .map(|_| String::from(".."))
+        .chain(
+            path[common..]
+                .iter()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                    _ => None,
+                }),
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::PlatformTag;
+    use std::str::FromStr;
+
+    /// Returns a locked package for tests. The `artifacts` are given as a filename and an optional
+    /// sha256 hash, they are located at `https://files.example.com/`.
+    pub(super) fn package(
+        name: &str,
+        version: &str,
+        artifacts: &[(&str, Option<&str>)],
+        dependencies: &[&str],
+    ) -> LockedPackage {
+        LockedPackage {
+            name: NormalizedPackageName::from_str(name).unwrap(),
+            version: Version::from_str(version).unwrap(),
+            url: None,
+            extras: Vec::new(),
+            artifacts: artifacts
+                .iter()
+                .map(|(filename, sha256)| LockedArtifact {
+                    filename: filename.to_string(),
+                    url: Url::parse("https://files.example.com/")
+                        .unwrap()
+                        .join(filename)
+                        .unwrap(),
+                    hashes: ArtifactHashes {
+                        sha256: sha256.map(|hash| {
+                            rattler_digest::parse_digest_from_hex::<Sha256>(hash).unwrap()
+                        }),
+                    },
+                    requires_python: None,
+                })
+                .collect(),
+            dependencies: dependencies
+                .iter()
+                .map(|name| NormalizedPackageName::from_str(name).unwrap())
+                .collect(),
+        }
+    }
+
+    fn pinned(name: &str, version: &str, filename: &str) -> PinnedPackage {
+        let name = NormalizedPackageName::from_str(name).unwrap();
+        PinnedPackage {
+            name: name.clone(),
+            version: Version::from_str(version).unwrap(),
+            url: None,
+            extras: Default::default(),
+            artifacts: vec![Arc::new(ArtifactInfo {
+                filename: ArtifactName::from_filename(filename, None, &name).unwrap(),
+                url: Url::parse(&format!("https://files.pythonhosted.org/{filename}")).unwrap(),
+                is_direct_url: false,
+                hashes: None,
+                requires_python: Some(VersionSpecifiers::from_str(">=3.8").unwrap()),
+                dist_info_metadata: Default::default(),
+                yanked: Default::default(),
+                size: None,
+            })],
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let target = LockTarget::new(
+            Version::from_str("3.11").unwrap(),
+            PlatformTag::from_str("manylinux_2_17_x86_64").unwrap(),
+        );
+        let packages = vec![
+            pinned("flask", "3.0.0", "flask-3.0.0-py3-none-any.whl"),
+            pinned("click", "8.1.7", "click-8.1.7.tar.gz"),
+        ];
+
+        let mut lockfile = Lockfile::new(vec![Requirement::from_str("flask>=3").unwrap()]);
+        lockfile.add_target(target.clone(), &packages);
+
+        let serialized = lockfile.to_toml_string().unwrap();
+        let parsed = Lockfile::from_str_with_path(&serialized, Path::new(LOCKFILE_NAME)).unwrap();
+        assert_eq!(parsed, lockfile);
+
+        let locked = parsed.target(&target).unwrap();
+        assert_eq!(locked.packages[0].name.as_str(), "click");
+        let mut roundtripped = locked.pinned_packages().unwrap();
+        roundtripped.sort_by(|a, b| b.name.cmp(&a.name));
+        assert_eq!(roundtripped, packages);
+    }
+
+    #[test]
+    fn test_select_target() {
+        let mut lockfile = Lockfile::new(Vec::new());
+        for (python_version, platform) in [
+            ("3.11", "manylinux_2_17_x86_64"),
+            ("3.11", "manylinux_2_28_x86_64"),
+            ("3.12", "manylinux_2_35_x86_64"),
+            ("3.11", "win_amd64"),
+        ] {
+            lockfile.add_target(
+                LockTarget::new(
+                    Version::from_str(python_version).unwrap(),
+                    PlatformTag::from_str(platform).unwrap(),
+                ),
+                &[],
+            );
+        }
+
+        let select = |python_version, platform| {
+            let interpreter =
+                WheelTagTarget::cpython(python_version, PlatformTag::from_str(platform).unwrap());
+            lockfile
+                .select_target(&interpreter)
+                .map(|locked| locked.target.platform.to_string())
+        };
+        assert_eq!(
+            select((3, 11), "manylinux_2_35_x86_64").as_deref(),
+            Some("manylinux_2_28_x86_64")
+        );
+        assert_eq!(
+            select((3, 11), "manylinux_2_17_x86_64").as_deref(),
+            Some("manylinux_2_17_x86_64")
+        );
+        assert_eq!(select((3, 12), "manylinux_2_17_x86_64"), None);
+        assert_eq!(select((3, 11), "macosx_14_0_arm64"), None);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        assert!(matches!(
+            Lockfile::from_str_with_path("version = 42\n", Path::new(LOCKFILE_NAME)),
+            Err(LockfileError::UnsupportedVersion(_, 42))
+        ));
+    }
+
+    #[test]
+    fn test_signed_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let requirements = dir.path().join("requirements").join("base.txt");
+        fs::create_dir_all(requirements.parent().unwrap()).unwrap();
+        fs::write(&requirements, "flask>=3\n").unwrap();
+
+        let path = dir.path().join(LOCKFILE_NAME);
+        let mut lockfile = Lockfile::new(vec![Requirement::from_str("flask>=3").unwrap()]);
+        lockfile.add_input(&requirements, &path).unwrap();
+        assert_eq!(lockfile.inputs[0].path, "requirements/base.txt");
+
+        let secret_key = SecretKey::generate().unwrap();
+        let public_key = secret_key.public_key();
+        lockfile.to_path_signed(&path, &secret_key).unwrap();
+        let verified = Lockfile::from_path_verified(&path, &public_key).unwrap();
+        assert_eq!(verified, lockfile);
+        verified.verify_inputs(&path).unwrap();
+
+        // Modifying the requirements invalidates the lockfile
+        fs::write(&requirements, "flask>=2\n").unwrap();
+        assert!(matches!(
+            verified.verify_inputs(&path),
+            Err(LockfileError::InputChanged(_))
+        ));
+
+        // Modifying the lockfile invalidates the signature
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("{contents}\n# modified\n")).unwrap();
+        assert!(matches!(
+            Lockfile::from_path_verified(&path, &public_key),
+            Err(LockfileError::Signature(
+                _,
+                SignatureError::VerificationFailed
+            ))
+        ));
+
+        // The signature of another lockfile is not valid for this one
+        let other = dir.path().join("other.lock");
+        lockfile.to_path_signed(&other, &secret_key).unwrap();
+        fs::copy(&other, &path).unwrap();
+        fs::copy(signature_path(&other), signature_path(&path)).unwrap();
+        assert!(matches!(
+            Lockfile::from_path_verified(&path, &public_key),
+            Err(LockfileError::SignedFileMismatch(_, name)) if name == "other.lock"
+        ));
+
+        fs::remove_file(signature_path(&path)).unwrap();
+        assert!(matches!(
+            Lockfile::from_path_verified(&path, &public_key),
+            Err(LockfileError::Unsigned(..))
+        ));
+    }
+
+    #[test]
+    fn test_relative_artifact_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().canonicalize().unwrap();
+        let filename = "flask-3.0.0-py3-none-any.whl";
+        let wheel = dir.join("wheels").join(filename);
+        fs::create_dir_all(wheel.parent().unwrap()).unwrap();
+        fs::write(&wheel, "").unwrap();
+
+        let mut local = pinned("flask", "3.0.0", filename);
+        local.artifacts[0] = Arc::new(ArtifactInfo {
+            url: Url::from_file_path(&wheel).unwrap(),
+            ..(*local.artifacts[0]).clone()
+        });
+        let mut lockfile = Lockfile::new(vec![Requirement::from_str("flask>=3").unwrap()]);
+        lockfile.add_target(
+            LockTarget::new(
+                Version::from_str("3.11").unwrap(),
+                PlatformTag::from_str("manylinux_2_17_x86_64").unwrap(),
+            ),
+            &[local, pinned("click", "8.1.7", "click-8.1.7.tar.gz")],
+        );
+
+        let path = dir.join("project").join(LOCKFILE_NAME);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        lockfile.to_path(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(
+            contents.contains(&format!("url = \"../wheels/{filename}\"")),
+            "{contents}"
+        );
+        assert!(contents.contains("url = \"https://files.pythonhosted.org/click-8.1.7.tar.gz\""));
+        assert_eq!(Lockfile::from_path(&path).unwrap(), lockfile);
+    }
+}
@@ -0,0 +1,148 @@
+//! Export of a [`LockedTarget`] to a pinned requirements file in the format that is written by
+//! `pip-compile` of [pip-tools](https://pip-tools.readthedocs.io/).
+//!
+//! Every package is pinned to its locked version, followed by the `--hash` options of its
+//! artifacts and a `# via` comment that lists why the package is part of the environment: the
+//! files that require it directly and the packages that depend on it.
+
+use super::LockedTarget;
+use crate::types::{Extra, NormalizedPackageName};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+pub(super) fn to_requirements_txt(
+    target: &LockedTarget,
+    origins: &HashMap<NormalizedPackageName, Vec<String>>,
+) -> String {
+    let mut parents: HashMap<&NormalizedPackageName, BTreeSet<&str>> = HashMap::new();
+    for package in &target.packages {
+        // A package that requires its own extras is not a reason for itself to be installed
+        for dependency in package.dependencies.iter().filter(|d| *d != &package.name) {
+            parents
+                .entry(dependency)
+                .or_default()
+                .insert(package.name.as_str());
+        }
+    }
+
+    let mut out = String::new();
+    let mut write = || -> std::fmt::Result {
+        writeln!(out, "#")?;
+        writeln!(
+            out,
+            "# This file is autogenerated by rip for {}",
+            target.target
+        )?;
+        writeln!(out, "#")?;
+
+        let mut packages = target.packages.iter().collect::<Vec<_>>();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        for package in packages {
+            let mut requirement = package.name.as_str().to_string();
+            if !package.extras.is_empty() {
+                let extras = package.extras.iter().map(Extra::as_str).collect::<Vec<_>>();
+                write!(requirement, "[{}]", extras.join(","))?;
+            }
+            match &package.url {
+                Some(url) => write!(requirement, " @ {url}")?,
+                None => write!(requirement, "=={}", package.version)?,
+            }
+
+            let hashes = package
+                .artifacts
+                .iter()
+                .filter_map(|artifact| artifact.hashes.sha256)
+                .map(|sha256| format!("{sha256:x}"))
+                .collect::<BTreeSet<_>>();
+            write!(out, "{requirement}")?;
+            for hash in hashes {
+                write!(out, " \\\n    --hash=sha256:{hash}")?;
+            }
+            writeln!(out)?;
+
+            let via = origins
+                .get(&package.name)
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .chain(parents.get(&package.name).into_iter().flatten().copied())
+                .collect::<Vec<_>>();
+            match via.as_slice() {
+                [] => {}
+                [via] => writeln!(out, "    # via {via}")?,
+                via => {
+                    writeln!(out, "    # via")?;
+                    for via in via {
+                        writeln!(out, "    #   {via}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    };
+    write().expect("writing to a string cannot fail");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lock::test::package;
+    use crate::lock::LockTarget;
+    use crate::python_env::PlatformTag;
+    use pep440_rs::Version;
+    use std::str::FromStr;
+    use url::Url;
+
+    #[test]
+    fn test_to_requirements_txt() {
+        let hash = |c: char| c.to_string().repeat(64);
+        let (a, b, c) = (hash('a'), hash('b'), hash('c'));
+        let mut flask = package(
+            "flask",
+            "3.0.0",
+            &[("flask-3.0.0-py3-none-any.whl", Some(&a))],
+            &["click", "flask", "jinja2"],
+        );
+        flask.extras = vec![Extra::from_str("async").unwrap()];
+        let mut local = package("local", "0.1.0", &[], &["click"]);
+        local.url = Some(Url::parse("file:///work/local").unwrap());
+        let target = LockedTarget {
+            target: LockTarget::new(
+                Version::from_str("3.11").unwrap(),
+                PlatformTag::from_str("manylinux_2_17_x86_64").unwrap(),
+            ),
+            packages: vec![
+                package(
+                    "click",
+                    "8.1.7",
+                    &[
+                        ("click-8.1.7-py3-none-any.whl", Some(&c)),
+                        ("click-8.1.7.tar.gz", Some(&b)),
+                    ],
+                    &[],
+                ),
+                flask,
+                package(
+                    "jinja2",
+                    "3.1.3",
+                    &[("jinja2-3.1.3-py3-none-any.whl", Some(&b))],
+                    &[],
+                ),
+                local,
+            ],
+        };
+        let origins = HashMap::from([
+            (
+                NormalizedPackageName::from_str("flask").unwrap(),
+                vec![String::from("-r requirements.in")],
+            ),
+            (
+                NormalizedPackageName::from_str("click").unwrap(),
+                vec![String::from("-r requirements.in")],
+            ),
+        ]);
+
+        insta::assert_snapshot!(to_requirements_txt(&target, &origins));
+    }
+}
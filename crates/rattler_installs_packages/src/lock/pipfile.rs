@@ -0,0 +1,464 @@
+//! Import of the `Pipfile.lock` files that are written by [Pipenv](https://pipenv.pypa.io/).
+//!
+//! A `Pipfile.lock` contains a flat list of packages in a `default` and a `develop` section. Every
+//! package is locked to a single version and is guarded by environment markers, which are
+//! evaluated for every target to select the packages that are installed on it. The file records
+//! the hashes of the locked files but not their names, so the files are looked up on the indexes by
+//! their hashes.
+
+use super::import::{parse_locked_hash, ImportedFile, ImportedPackage};
+use super::{LockTarget, LockedTarget, Lockfile, LockfileError};
+use crate::index::PackageDb;
+use crate::types::{Extra, NormalizedPackageName};
+use crate::utils::normalize_index_url;
+use fs_err as fs;
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep440_rs::Version;
+use pep508_rs::{MarkerEnvironment, MarkerTree, Requirement};
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use url::Url;
+
+/// The default file name of a Pipenv lockfile.
+pub const PIPFILE_LOCK_NAME: &str = "Pipfile.lock";
+
+/// The version of the `Pipfile.lock` format that can be imported.
+const PIPFILE_SPEC: u32 = 6;
+
+#[derive(Deserialize)]
+struct RawPipfileLock {
+    #[serde(rename = "_meta")]
+    meta: PipfileMeta,
+
+    #[serde(default)]
+    default: IndexMap<NormalizedPackageName, PipenvPackage>,
+
+    #[serde(default)]
+    develop: IndexMap<NormalizedPackageName, PipenvPackage>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PipfileMeta {
+    pipfile_spec: u32,
+
+    #[serde(default)]
+    sources: Vec<PipenvSource>,
+}
+
+#[derive(Deserialize)]
+struct PipenvSource {
+    name: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct PipenvPackage {
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    hashes: Vec<String>,
+    #[serde(default)]
+    markers: Option<String>,
+    #[serde(default)]
+    extras: Vec<Extra>,
+    #[serde(default)]
+    index: Option<String>,
+    #[serde(default)]
+    git: Option<String>,
+    #[serde(default, rename = "ref")]
+    reference: Option<String>,
+    #[serde(default)]
+    subdirectory: Option<String>,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    path: Option<String>,
+}
+
+/// The contents of a `Pipfile.lock` file, see [`PipfileLock::to_lockfile`].
+pub struct PipfileLock {
+    packages: Vec<(NormalizedPackageName, PipenvPackage)>,
+
+    /// The number of packages in the `default` section, the `develop` packages follow them
+    default_len: usize,
+
+    /// The indexes that the packages were locked from, by name
+    sources: HashMap<String, Url>,
+
+    /// Whether the packages of the `develop` section are imported as well
+    develop: bool,
+
+    /// The directory of the lockfile, which relative paths of local packages are resolved against
+    directory: PathBuf,
+}
+
+impl PipfileLock {
+    /// Reads a `Pipfile.lock` file from disk.
+    pub fn from_path(path: &Path) -> Result<Self, LockfileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        Self::from_str_with_path(&contents, path)
+    }
+
+    fn from_str_with_path(contents: &str, path: &Path) -> Result<Self, LockfileError> {
+        let lock: RawPipfileLock = serde_json::from_str(contents)
+            .map_err(|e| LockfileError::ParseJson(path.to_path_buf(), e))?;
+        if lock.meta.pipfile_spec != PIPFILE_SPEC {
+            return Err(LockfileError::UnsupportedPipfileSpec(
+                path.to_path_buf(),
+                lock.meta.pipfile_spec,
+            ));
+        }
+
+        let sources = lock
+            .meta
+            .sources
+            .into_iter()
+            .filter_map(|source| {
+                let url = Url::parse(&source.url).ok()?;
+                Some((source.name, normalize_index_url(url)))
+            })
+            .collect();
+        let default_len = lock.default.len();
+        Ok(Self {
+            packages: lock.default.into_iter().chain(lock.develop).collect(),
+            default_len,
+            sources,
+            develop: false,
+            directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        })
+    }
+
+    /// Sets whether the packages of the `develop` section are imported as well. Only the packages
+    /// of the `default` section are imported by default.
+    pub fn with_develop(self, develop: bool) -> Self {
+        Self { develop, ..self }
+    }
+
+    /// Converts the Pipenv lockfile into a [`Lockfile`] for the given targets, without resolving
+    /// again. The files with the locked hashes are looked up on the indexes of `package_db`.
+    pub async fn to_lockfile(
+        &self,
+        package_db: &PackageDb,
+        targets: &[LockTarget],
+    ) -> miette::Result<Lockfile> {
+        // The lockfile does not record which packages were requested, so all of them are pinned.
+        let mut requirements = Vec::new();
+        for index in self.selected_sections() {
+            let requirement = self.requirement(index).into_diagnostic()?;
+            if !requirements.contains(&requirement) {
+                requirements.push(requirement);
+            }
+        }
+
+        let mut lockfile = Lockfile::new(requirements);
+        let mut artifacts = HashMap::new();
+        for target in targets {
+            let env = target.env_markers().into_diagnostic()?.0;
+            let mut packages = Vec::new();
+            for index in self.select(&env).into_diagnostic()? {
+                let (name, package) = &self.packages[index];
+                let imported = self.imported_package(index).into_diagnostic()?;
+                if let Entry::Vacant(entry) = artifacts.entry(index) {
+                    self.check_index(name, package, package_db);
+                    entry.insert(imported.artifacts(package_db).await?);
+                }
+                packages.push(
+                    imported
+                        .locked_for_target(
+                            &artifacts[&index],
+                            package.extras.clone(),
+                            Vec::new(),
+                            target,
+                            package_db.artifact_registry(),
+                        )
+                        .into_diagnostic()?,
+                );
+            }
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+            lockfile.targets.push(LockedTarget {
+                target: target.clone(),
+                packages,
+            });
+        }
+        Ok(lockfile)
+    }
+
+    /// Returns the indices of the packages in the sections that are imported.
+    fn selected_sections(&self) -> std::ops::Range<usize> {
+        if self.develop {
+            0..self.packages.len()
+        } else {
+            0..self.default_len
+        }
+    }
+
+    /// Returns the packages whose markers apply to the environment. A package that is part of both
+    /// sections is only selected once.
+    fn select(&self, env: &MarkerEnvironment) -> Result<Vec<usize>, LockfileError> {
+        let mut selected: IndexMap<&NormalizedPackageName, usize> = IndexMap::new();
+        for index in self.selected_sections() {
+            let (name, package) = &self.packages[index];
+            if selected.contains_key(name) {
+                continue;
+            }
+            if let Some(markers) = &package.markers {
+                if !parse_markers(name, markers)?.evaluate(env, &[]) {
+                    continue;
+                }
+            }
+            selected.insert(name, index);
+        }
+        Ok(selected.into_values().collect())
+    }
+
+    /// Returns a requirement that pins a locked package, including its extras and markers.
+    fn requirement(&self, index: usize) -> Result<Requirement, LockfileError> {
+        let (name, package) = &self.packages[index];
+        let mut requirement = name.as_str().to_string();
+        if !package.extras.is_empty() {
+            let extras = package.extras.iter().map(Extra::as_str).collect::<Vec<_>>();
+            requirement.push_str(&format!("[{}]", extras.join(",")));
+        }
+        match self.imported_package(index)?.url {
+            Some(url) => requirement.push_str(&format!(" @ {url}")),
+            None => requirement.push_str(&format!("=={}", self.version(index)?)),
+        }
+        if let Some(markers) = &package.markers {
+            requirement.push_str(&format!(" ; {markers}"));
+        }
+        Requirement::from_str(&requirement)
+            .map_err(|e| LockfileError::InvalidMarker(name.clone(), requirement, Box::new(e)))
+    }
+
+    /// Returns the locked version of a package. Packages from git or local paths are not locked to
+    /// a version.
+    fn version(&self, index: usize) -> Result<Version, LockfileError> {
+        let (name, package) = &self.packages[index];
+        match &package.version {
+            Some(version) => {
+                let pinned = version.trim_start_matches('=');
+                Version::from_str(pinned)
+                    .map_err(|_| LockfileError::InvalidLockedVersion(name.clone(), version.clone()))
+            }
+            None => Ok(Version::from_str("0.0.0").expect("0.0.0 is a valid version")),
+        }
+    }
+
+    /// Warns if a package was locked from an index that is not one of the indexes of
+    /// `package_db`, the files are then likely not found.
+    fn check_index(
+        &self,
+        name: &NormalizedPackageName,
+        package: &PipenvPackage,
+        package_db: &PackageDb,
+    ) {
+        let Some(url) = package
+            .index
+            .as_ref()
+            .and_then(|index| self.sources.get(index))
+        else {
+            return;
+        };
+        if !package_db.sources().index_url(name).contains(&url) {
+            tracing::warn!(
+                "{} was locked from index {url}, which is not one of the configured indexes",
+                name.as_str()
+            );
+        }
+    }
+
+    /// Converts a locked package into the representation that is shared by the importers.
+    fn imported_package(&self, index: usize) -> Result<ImportedPackage, LockfileError> {
+        let (name, package) = &self.packages[index];
+        let invalid_url =
+            |source: &str, e| LockfileError::InvalidSourceUrl(name.clone(), source.to_string(), e);
+        let hashes = package
+            .hashes
+            .iter()
+            .map(|hash| parse_locked_hash(hash))
+            .filter(|hashes| !hashes.is_empty())
+            .collect::<Vec<_>>();
+
+        let mut files = Vec::new();
+        let url = if let Some(git) = &package.git {
+            let mut url = format!("git+{}", git.trim_start_matches("git+"));
+            if let Some(reference) = &package.reference {
+                url = format!("{url}@{reference}");
+            }
+            if let Some(subdirectory) = &package.subdirectory {
+                url = format!("{url}#subdirectory={subdirectory}");
+            }
+            Some(Url::parse(&url).map_err(|e| invalid_url(git, e))?)
+        } else if let Some(file) = &package.file {
+            let url = Url::parse(file).map_err(|e| invalid_url(file, e))?;
+            let filename = url
+                .path_segments()
+                .and_then(|segments| segments.last())
+                .unwrap_or_default()
+                .to_string();
+            // Archives are installed from the url, directories are built as source trees.
+            if filename.ends_with(".whl")
+                || filename.ends_with(".tar.gz")
+                || filename.ends_with(".zip")
+            {
+                files.push(ImportedFile {
+                    filename,
+                    url: Some(url.clone()),
+                    hashes: hashes.first().cloned().unwrap_or_default(),
+                });
+            }
+            Some(url)
+        } else if let Some(path) = &package.path {
+            let path = self.directory.join(path);
+            let path = path.canonicalize().unwrap_or(path);
+            Some(Url::from_file_path(&path).map_err(|_| {
+                LockfileError::UnsupportedSource(name.clone(), path.display().to_string())
+            })?)
+        } else {
+            None
+        };
+
+        Ok(ImportedPackage {
+            name: name.clone(),
+            version: self.version(index)?,
+            url,
+            files,
+            hashes,
+        })
+    }
+}
+
+fn parse_markers(name: &NormalizedPackageName, markers: &str) -> Result<MarkerTree, LockfileError> {
+    MarkerTree::from_str(markers)
+        .map_err(|e| LockfileError::InvalidMarker(name.clone(), markers.to_string(), Box::new(e)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::PlatformTag;
+
+    const PIPFILE_LOCK: &str = r#"{
+    "_meta": {
+        "hash": {"sha256": "0000000000000000000000000000000000000000000000000000000000000000"},
+        "pipfile-spec": 6,
+        "requires": {"python_version": "3.11"},
+        "sources": [
+            {"name": "pypi", "url": "https://pypi.org/simple", "verify_ssl": true}
+        ]
+    },
+    "default": {
+        "colorama": {
+            "hashes": [
+                "sha256:4f1d9991f5acc0ca119f9d443620b77f9d6b33703e51011c16baf57afb285fc6"
+            ],
+            "index": "pypi",
+            "markers": "platform_system == 'Windows'",
+            "version": "==0.4.6"
+        },
+        "requests": {
+            "extras": ["socks"],
+            "hashes": [
+                "sha256:58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f",
+                "sha256:942c5a758f98d790eaed1a29cb6eefc7ffb0d1cf7af05c3d2791656dbd6ad1e1"
+            ],
+            "index": "pypi",
+            "markers": "python_version >= '3.7'",
+            "version": "==2.31.0"
+        },
+        "tool": {
+            "git": "https://github.com/example/tool.git",
+            "ref": "0123456789abcdef"
+        }
+    },
+    "develop": {
+        "pytest": {
+            "hashes": [],
+            "version": "==8.0.0"
+        },
+        "requests": {
+            "hashes": [],
+            "version": "==2.31.0"
+        }
+    }
+}"#;
+
+    fn selected<'a>(lock: &'a PipfileLock, platform: &str) -> Vec<&'a str> {
+        let target = LockTarget::new(
+            Version::from_str("3.11").unwrap(),
+            PlatformTag::from_str(platform).unwrap(),
+        );
+        let env = target.env_markers().unwrap().0;
+        lock.select(&env)
+            .unwrap()
+            .into_iter()
+            .map(|index| lock.packages[index].0.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_packages() {
+        let lock =
+            PipfileLock::from_str_with_path(PIPFILE_LOCK, Path::new(PIPFILE_LOCK_NAME)).unwrap();
+        assert_eq!(
+            selected(&lock, "manylinux_2_17_x86_64"),
+            ["requests", "tool"]
+        );
+        assert_eq!(
+            selected(&lock, "win_amd64"),
+            ["colorama", "requests", "tool"]
+        );
+
+        let lock = lock.with_develop(true);
+        assert_eq!(
+            selected(&lock, "manylinux_2_17_x86_64"),
+            ["requests", "tool", "pytest"]
+        );
+        assert_eq!(
+            (0..lock.packages.len())
+                .map(|index| lock.requirement(index).unwrap().to_string())
+                .collect::<Vec<_>>(),
+            [
+                "colorama ==0.4.6 ; platform_system == 'Windows'",
+                "requests[socks] ==2.31.0 ; python_version >= '3.7'",
+                "tool @ git+https://github.com/example/tool.git@0123456789abcdef",
+                "pytest ==8.0.0",
+                "requests ==2.31.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imported_package() {
+        let lock =
+            PipfileLock::from_str_with_path(PIPFILE_LOCK, Path::new(PIPFILE_LOCK_NAME)).unwrap();
+        let requests = lock.imported_package(1).unwrap();
+        assert_eq!(requests.version, Version::from_str("2.31.0").unwrap());
+        assert_eq!(requests.url, None);
+        assert!(requests.files.is_empty());
+        assert_eq!(requests.hashes.len(), 2);
+        assert_eq!(lock.sources["pypi"].as_str(), "https://pypi.org/simple/");
+
+        let tool = lock.imported_package(2).unwrap();
+        assert_eq!(
+            tool.url.unwrap().as_str(),
+            "git+https://github.com/example/tool.git@0123456789abcdef"
+        );
+        assert!(tool.files.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_spec() {
+        let contents = PIPFILE_LOCK.replace("\"pipfile-spec\": 6", "\"pipfile-spec\": 5");
+        assert!(matches!(
+            PipfileLock::from_str_with_path(&contents, Path::new(PIPFILE_LOCK_NAME)),
+            Err(LockfileError::UnsupportedPipfileSpec(_, 5))
+        ));
+    }
+}
@@ -0,0 +1,738 @@
+//! Import of the `poetry.lock` files that are written by [Poetry](https://python-poetry.org/).
+//!
+//! A `poetry.lock` file is not resolved for a specific target. Instead, the dependencies between
+//! the locked packages are guarded by environment markers, which are evaluated for every target
+//! to select the packages that are installed on it. The requirements of the project itself are not
+//! part of the file, so the packages that no other package depends on are used as the roots.
+//! Since lock version 2.1 Poetry also records the markers of every package, which are used when
+//! present.
+
+use super::import::{
+    parse_locked_hash, select_packages, ImportedFile, ImportedPackage, SelectedPackage,
+};
+use super::{LockTarget, LockedTarget, Lockfile, LockfileError};
+use crate::index::PackageDb;
+use crate::types::{Extra, NormalizedPackageName};
+use fs_err as fs;
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep440_rs::{Version, VersionSpecifiers};
+use pep508_rs::{MarkerEnvironment, MarkerTree, Requirement};
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use url::Url;
+
+/// The default file name of a Poetry lockfile.
+pub const POETRY_LOCK_NAME: &str = "poetry.lock";
+
+#[derive(Deserialize)]
+struct RawPoetryLock {
+    #[serde(default, rename = "package")]
+    packages: Vec<PoetryPackage>,
+
+    #[serde(default)]
+    metadata: PoetryMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct PoetryMetadata {
+    /// The files of every package, before lock version 2.0
+    #[serde(default)]
+    files: HashMap<NormalizedPackageName, Vec<PoetryFile>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PoetryPackage {
+    name: NormalizedPackageName,
+    version: Version,
+    #[serde(default)]
+    python_versions: Option<String>,
+    #[serde(default)]
+    files: Vec<PoetryFile>,
+    #[serde(default)]
+    dependencies: IndexMap<NormalizedPackageName, PoetryDependencies>,
+    #[serde(default)]
+    extras: HashMap<Extra, Vec<String>>,
+    #[serde(default)]
+    source: Option<PoetrySource>,
+    #[serde(default)]
+    markers: Option<PoetryMarkers>,
+}
+
+#[derive(Deserialize)]
+struct PoetryFile {
+    file: String,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct PoetrySource {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    #[serde(default)]
+    resolved_reference: Option<String>,
+    #[serde(default)]
+    subdirectory: Option<String>,
+}
+
+/// The markers of a package, either for all dependency groups at once or per group.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PoetryMarkers {
+    All(String),
+    PerGroup(HashMap<String, String>),
+}
+
+/// A dependency is either just a version constraint, a table or a list of tables that each apply
+/// to different environments.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PoetryDependencies {
+    Version(String),
+    Single(PoetryDependency),
+    Multiple(Vec<PoetryDependency>),
+}
+
+#[derive(Default, Deserialize)]
+struct PoetryDependency {
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    markers: Option<String>,
+    #[serde(default)]
+    extras: Vec<Extra>,
+    #[serde(default)]
+    optional: bool,
+}
+
+impl PoetryDependencies {
+    fn iter(&self) -> Box<dyn Iterator<Item = PoetryDependencyRef<'_>> + '_> {
+        match self {
+            PoetryDependencies::Version(version) => {
+                Box::new(std::iter::once(PoetryDependencyRef {
+                    version: Some(version),
+                    markers: None,
+                    extras: &[],
+                    optional: false,
+                }))
+            }
+            PoetryDependencies::Single(dependency) => {
+                Box::new(std::iter::once(PoetryDependencyRef::from(dependency)))
+            }
+            PoetryDependencies::Multiple(dependencies) => {
+                Box::new(dependencies.iter().map(PoetryDependencyRef::from))
+            }
+        }
+    }
+}
+
+struct PoetryDependencyRef<'a> {
+    version: Option<&'a str>,
+    markers: Option<&'a str>,
+    extras: &'a [Extra],
+    optional: bool,
+}
+
+impl<'a> From<&'a PoetryDependency> for PoetryDependencyRef<'a> {
+    fn from(dependency: &'a PoetryDependency) -> Self {
+        Self {
+            version: dependency.version.as_deref(),
+            markers: dependency.markers.as_deref(),
+            extras: &dependency.extras,
+            optional: dependency.optional,
+        }
+    }
+}
+
+/// The contents of a `poetry.lock` file, see [`PoetryLock::to_lockfile`].
+pub struct PoetryLock {
+    packages: Vec<PoetryPackage>,
+
+    /// The directory of the lockfile, which relative paths of local packages are resolved against
+    directory: PathBuf,
+}
+
+impl PoetryLock {
+    /// Reads a `poetry.lock` file from disk.
+    pub fn from_path(path: &Path) -> Result<Self, LockfileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        Self::from_str_with_path(&contents, path)
+    }
+
+    fn from_str_with_path(contents: &str, path: &Path) -> Result<Self, LockfileError> {
+        let mut lock: RawPoetryLock =
+            toml::from_str(contents).map_err(|e| LockfileError::Parse(path.to_path_buf(), e))?;
+        for package in &mut lock.packages {
+            if package.files.is_empty() {
+                package.files = lock
+                    .metadata
+                    .files
+                    .remove(&package.name)
+                    .unwrap_or_default();
+            }
+        }
+        Ok(Self {
+            packages: lock.packages,
+            directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        })
+    }
+
+    /// Converts the Poetry lockfile into a [`Lockfile`] for the given targets, without resolving
+    /// again. The urls of the locked files are looked up on the indexes of `package_db` and their
+    /// hashes are verified against the indexes.
+    pub async fn to_lockfile(
+        &self,
+        package_db: &PackageDb,
+        targets: &[LockTarget],
+    ) -> miette::Result<Lockfile> {
+        let roots = self.roots();
+        let mut requirements: Vec<Requirement> = Vec::new();
+        for &index in &roots {
+            let package = &self.packages[index];
+            let requirement =
+                Requirement::from_str(&format!("{}=={}", package.name.as_str(), package.version))
+                    .expect("a requirement formatted from a name and a version is always valid");
+            if !requirements.contains(&requirement) {
+                requirements.push(requirement);
+            }
+        }
+
+        let mut lockfile = Lockfile::new(requirements);
+        let mut artifacts = HashMap::new();
+        for target in targets {
+            let env = target.env_markers().into_diagnostic()?.0;
+            let mut packages = Vec::new();
+            for selected in self.select(&roots, &env, target).into_diagnostic()? {
+                let index = selected.index;
+                let package = self.imported_package(index).into_diagnostic()?;
+                if let Entry::Vacant(entry) = artifacts.entry(index) {
+                    entry.insert(package.artifacts(package_db).await?);
+                }
+                let dependencies = selected
+                    .dependencies
+                    .iter()
+                    .map(|&dependency| self.packages[dependency].name.clone())
+                    .collect();
+                packages.push(
+                    package
+                        .locked_for_target(
+                            &artifacts[&index],
+                            selected.extras,
+                            dependencies,
+                            target,
+                            package_db.artifact_registry(),
+                        )
+                        .into_diagnostic()?,
+                );
+            }
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+            lockfile.targets.push(LockedTarget {
+                target: target.clone(),
+                packages,
+            });
+        }
+        Ok(lockfile)
+    }
+
+    /// Returns the indices of the packages that no other package depends on.
+    fn roots(&self) -> Vec<usize> {
+        let dependencies = self
+            .packages
+            .iter()
+            .flat_map(|package| package.dependencies.keys())
+            .collect::<HashSet<_>>();
+        (0..self.packages.len())
+            .filter(|&index| !dependencies.contains(&self.packages[index].name))
+            .collect()
+    }
+
+    /// Returns the packages that are installed on a target, by following the dependencies whose
+    /// markers apply from the roots.
+    fn select(
+        &self,
+        roots: &[usize],
+        env: &MarkerEnvironment,
+        target: &LockTarget,
+    ) -> Result<Vec<SelectedPackage>, LockfileError> {
+        // Packages with markers are precise, otherwise start from the roots.
+        let has_markers = self
+            .packages
+            .iter()
+            .any(|package| package.markers.is_some());
+        let mut root_names = HashSet::new();
+        let mut selected_roots = Vec::new();
+        for index in 0..self.packages.len() {
+            let is_root = if has_markers {
+                self.package_applies(index, env)?
+            } else {
+                roots.contains(&index)
+            };
+            // A root can be locked in multiple versions for different python versions.
+            if is_root
+                && self.python_versions_contain(index, target)
+                && root_names.insert(&self.packages[index].name)
+            {
+                selected_roots.push(index);
+            }
+        }
+
+        select_packages(selected_roots, |index, extras| {
+            self.dependencies(index, extras, env, target)
+        })
+    }
+
+    /// Returns the dependencies of a package with the given extras that apply to a target.
+    fn dependencies(
+        &self,
+        index: usize,
+        extras: &[Extra],
+        env: &MarkerEnvironment,
+        target: &LockTarget,
+    ) -> Result<Vec<(usize, Vec<Extra>)>, LockfileError> {
+        let package = &self.packages[index];
+        let extra_names = extras.iter().map(Extra::as_str).collect::<Vec<_>>();
+        let optional_dependencies = extras
+            .iter()
+            .filter_map(|extra| package.extras.get(extra))
+            .flatten()
+            .filter_map(|requirement| requirement_name(requirement))
+            .collect::<HashSet<_>>();
+
+        let mut result = Vec::new();
+        for (name, dependencies) in &package.dependencies {
+            for dependency in dependencies.iter() {
+                if dependency.optional && !optional_dependencies.contains(name) {
+                    continue;
+                }
+                if let Some(markers) = dependency.markers {
+                    if !parse_markers(&package.name, markers)?.evaluate(env, &extra_names) {
+                        continue;
+                    }
+                }
+                if let Some(dependency_index) =
+                    self.find_dependency(name, dependency.version, env, target)?
+                {
+                    result.push((dependency_index, dependency.extras.to_vec()));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Returns the package that satisfies a dependency on `name`. If multiple versions of the
+    /// package are locked, the one that matches the version constraint and the python version of
+    /// the target is used.
+    fn find_dependency(
+        &self,
+        name: &NormalizedPackageName,
+        version: Option<&str>,
+        env: &MarkerEnvironment,
+        target: &LockTarget,
+    ) -> Result<Option<usize>, LockfileError> {
+        let mut candidates = Vec::new();
+        for index in 0..self.packages.len() {
+            if &self.packages[index].name == name && self.package_applies(index, env)? {
+                candidates.push(index);
+            }
+        }
+        if candidates.len() > 1 {
+            let matching = candidates
+                .iter()
+                .copied()
+                .filter(|&index| {
+                    version.map_or(true, |version| {
+                        constraint_contains(version, &self.packages[index].version) != Some(false)
+                    }) && self.python_versions_contain(index, target)
+                })
+                .collect::<Vec<_>>();
+            if !matching.is_empty() {
+                candidates = matching;
+            }
+        }
+        Ok(candidates
+            .into_iter()
+            .max_by(|&a, &b| self.packages[a].version.cmp(&self.packages[b].version)))
+    }
+
+    /// Returns true if the markers of the package, if any, apply to the environment.
+    fn package_applies(
+        &self,
+        index: usize,
+        env: &MarkerEnvironment,
+    ) -> Result<bool, LockfileError> {
+        let package = &self.packages[index];
+        let evaluate = |markers: &str| {
+            parse_markers(&package.name, markers).map(|markers| markers.evaluate(env, &[]))
+        };
+        match &package.markers {
+            None => Ok(true),
+            Some(PoetryMarkers::All(markers)) => evaluate(markers),
+            Some(PoetryMarkers::PerGroup(groups)) => {
+                for markers in groups.values() {
+                    if evaluate(markers)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Returns true if the python versions the package supports include the target.
+    fn python_versions_contain(&self, index: usize, target: &LockTarget) -> bool {
+        self.packages[index]
+            .python_versions
+            .as_deref()
+            .and_then(|constraint| constraint_contains(constraint, &target.python_version))
+            .unwrap_or(true)
+    }
+
+    /// Converts a locked package into the representation that is shared by the importers.
+    fn imported_package(&self, index: usize) -> Result<ImportedPackage, LockfileError> {
+        let package = &self.packages[index];
+        let invalid_url =
+            |e| LockfileError::InvalidSourceUrl(package.name.clone(), source_url(package), e);
+
+        let url = match &package.source {
+            None => None,
+            Some(source) => match source.kind.as_str() {
+                "legacy" => None,
+                "url" => Some(Url::parse(&source.url).map_err(invalid_url)?),
+                "git" => {
+                    let mut url = format!("git+{}", source.url);
+                    if let Some(reference) = &source.resolved_reference {
+                        url = format!("{url}@{reference}");
+                    }
+                    if let Some(subdirectory) = &source.subdirectory {
+                        url = format!("{url}#subdirectory={subdirectory}");
+                    }
+                    Some(Url::parse(&url).map_err(invalid_url)?)
+                }
+                "file" | "directory" => {
+                    let path = self.directory.join(&source.url);
+                    let path = path.canonicalize().unwrap_or(path);
+                    Some(Url::from_file_path(path).map_err(|_| {
+                        LockfileError::InvalidSourceUrl(
+                            package.name.clone(),
+                            source.url.clone(),
+                            url::ParseError::RelativeUrlWithoutBase,
+                        )
+                    })?)
+                }
+                kind => {
+                    return Err(LockfileError::UnsupportedSource(
+                        package.name.clone(),
+                        kind.to_string(),
+                    ))
+                }
+            },
+        };
+
+        // Files of source trees are not installable artifacts.
+        let is_directory = matches!(&package.source, Some(source) if source.kind == "directory" || source.kind == "git");
+        let files = if is_directory {
+            Vec::new()
+        } else {
+            package
+                .files
+                .iter()
+                .map(|file| ImportedFile {
+                    filename: file.file.clone(),
+                    url: None,
+                    hashes: parse_locked_hash(&file.hash),
+                })
+                .collect()
+        };
+
+        Ok(ImportedPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            url,
+            files,
+            hashes: Vec::new(),
+        })
+    }
+}
+
+fn source_url(package: &PoetryPackage) -> String {
+    package
+        .source
+        .as_ref()
+        .map(|source| source.url.clone())
+        .unwrap_or_default()
+}
+
+fn parse_markers(name: &NormalizedPackageName, markers: &str) -> Result<MarkerTree, LockfileError> {
+    MarkerTree::from_str(markers)
+        .map_err(|e| LockfileError::InvalidMarker(name.clone(), markers.to_string(), Box::new(e)))
+}
+
+/// Returns the name of a requirement in the `name (>=1.0) ; markers` format Poetry uses for extras.
+fn requirement_name(requirement: &str) -> Option<NormalizedPackageName> {
+    let end = requirement
+        .find(|c: char| c.is_whitespace() || "([;<>=!~".contains(c))
+        .unwrap_or(requirement.len());
+    requirement[..end].parse().ok()
+}
+
+/// Returns whether a Poetry version constraint contains a version, or `None` if the constraint
+/// cannot be parsed. Next to PEP 440 specifiers Poetry supports `*`, caret (`^1.2`) and tilde
+/// (`~1.2`) constraints and alternatives separated by `||`.
+fn constraint_contains(constraint: &str, version: &Version) -> Option<bool> {
+    let mut any = false;
+    for alternative in constraint.split("||") {
+        let mut specifiers = Vec::new();
+        for clause in alternative
+            .split(',')
+            .map(str::trim)
+            .filter(|clause| !clause.is_empty() && *clause != "*")
+        {
+            if let Some(base) = clause.strip_prefix('^') {
+                let (lower, upper) = caret_bounds(base)?;
+                specifiers.push(format!(">={lower}"));
+                specifiers.push(format!("<{upper}"));
+            } else if let Some(base) = clause
+                .strip_prefix('~')
+                .filter(|base| !base.starts_with('='))
+            {
+                let (lower, upper) = tilde_bounds(base)?;
+                specifiers.push(format!(">={lower}"));
+                specifiers.push(format!("<{upper}"));
+            } else if clause.starts_with(|c: char| c.is_ascii_digit()) {
+                specifiers.push(format!("=={clause}"));
+            } else {
+                specifiers.push(clause.to_string());
+            }
+        }
+        let specifiers = VersionSpecifiers::from_str(&specifiers.join(",")).ok()?;
+        any |= specifiers.contains(version);
+    }
+    Some(any)
+}
+
+fn release(base: &str) -> Option<Vec<u64>> {
+    base.trim()
+        .split('.')
+        .map(|component| component.parse().ok())
+        .collect()
+}
+
+fn join(release: &[u64]) -> String {
+    release
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// `^1.2.3` is `>=1.2.3,<2.0.0`, `^0.2.3` is `>=0.2.3,<0.3.0` and `^0.0.3` is `>=0.0.3,<0.0.4`.
+fn caret_bounds(base: &str) -> Option<(String, String)> {
+    let lower = release(base)?;
+    let position = lower
+        .iter()
+        .position(|&component| component != 0)
+        .unwrap_or(lower.len() - 1);
+    let mut upper = lower[..=position].to_vec();
+    upper[position] += 1;
+    Some((join(&lower), join(&upper)))
+}
+
+/// `~1.2.3` is `>=1.2.3,<1.3.0` and `~1` is `>=1,<2`.
+fn tilde_bounds(base: &str) -> Option<(String, String)> {
+    let lower = release(base)?;
+    let position = if lower.len() > 1 { 1 } else { 0 };
+    let mut upper = lower[..=position].to_vec();
+    upper[position] += 1;
+    Some((join(&lower), join(&upper)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::PlatformTag;
+
+    const POETRY_LOCK: &str = r#"
+[[package]]
+name = "click"
+version = "8.1.7"
+description = "Composable command line interface toolkit"
+optional = false
+python-versions = ">=3.7"
+files = [
+    {file = "click-8.1.7-py3-none-any.whl", hash = "sha256:ae74fb96c20a0277a1d615f1e4d73c8414f5a98db8b799a7931d1582f3390c28"},
+    {file = "click-8.1.7.tar.gz", hash = "sha256:ca9853ad459e787e2192211578cc907e7594e294c7ccc834310722b41b9ca6de"},
+]
+
+[package.dependencies]
+colorama = {version = "*", markers = "platform_system == \"Windows\""}
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+description = "Cross-platform colored terminal text."
+optional = false
+python-versions = "!=3.0.*,!=3.1.*,!=3.2.*,!=3.3.*,!=3.4.*,!=3.5.*,!=3.6.*,>=2.7"
+files = [
+    {file = "colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:4f1d9991f5acc0ca119f9d443620b77f9d6b33703e51011c16baf57afb285fc6"},
+]
+
+[[package]]
+name = "requests"
+version = "2.31.0"
+description = "Python HTTP for Humans."
+optional = false
+python-versions = ">=3.7"
+files = []
+
+[package.dependencies]
+pysocks = {version = ">=1.5.6,<1.5.7 || >1.5.7", optional = true}
+
+[package.extras]
+socks = ["PySocks (>=1.5.6,!=1.5.7)"]
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+description = ""
+optional = true
+python-versions = "*"
+files = []
+
+[[package]]
+name = "numpy"
+version = "1.24.4"
+description = ""
+optional = false
+python-versions = ">=3.8"
+files = []
+
+[[package]]
+name = "numpy"
+version = "1.26.4"
+description = ""
+optional = false
+python-versions = ">=3.9"
+files = []
+
+[[package]]
+name = "tool"
+version = "0.1.0"
+description = ""
+optional = false
+python-versions = "^3.8"
+files = []
+
+[package.dependencies]
+numpy = [
+    {version = "<1.25", markers = "python_version < \"3.9\""},
+    {version = "^1.26", markers = "python_version >= \"3.9\""},
+]
+requests = {version = "^2.31", extras = ["socks"]}
+
+[package.source]
+type = "git"
+url = "https://github.com/example/tool.git"
+reference = "main"
+resolved_reference = "0123456789abcdef"
+
+[metadata]
+lock-version = "2.0"
+python-versions = "^3.8"
+content-hash = "0000"
+"#;
+
+    fn selected(lock: &PoetryLock, python_version: &str, platform: &str) -> Vec<String> {
+        let target = LockTarget::new(
+            Version::from_str(python_version).unwrap(),
+            PlatformTag::from_str(platform).unwrap(),
+        );
+        let env = target.env_markers().unwrap().0;
+        let mut selected = lock
+            .select(&lock.roots(), &env, &target)
+            .unwrap()
+            .into_iter()
+            .map(|selected| {
+                let package = &lock.packages[selected.index];
+                let extras = selected
+                    .extras
+                    .iter()
+                    .map(|extra| format!("[{}]", extra.as_str()));
+                format!(
+                    "{}{} {}",
+                    package.name.as_str(),
+                    extras.collect::<String>(),
+                    package.version
+                )
+            })
+            .collect::<Vec<_>>();
+        selected.sort();
+        selected
+    }
+
+    #[test]
+    fn test_select_packages() {
+        let lock =
+            PoetryLock::from_str_with_path(POETRY_LOCK, Path::new(POETRY_LOCK_NAME)).unwrap();
+        assert_eq!(
+            selected(&lock, "3.11", "manylinux_2_17_x86_64"),
+            [
+                "click 8.1.7",
+                "numpy 1.26.4",
+                "pysocks 1.7.1",
+                "requests[socks] 2.31.0",
+                "tool 0.1.0"
+            ]
+        );
+        assert_eq!(
+            selected(&lock, "3.8", "win_amd64"),
+            [
+                "click 8.1.7",
+                "colorama 0.4.6",
+                "numpy 1.24.4",
+                "pysocks 1.7.1",
+                "requests[socks] 2.31.0",
+                "tool 0.1.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_imported_package() {
+        let lock =
+            PoetryLock::from_str_with_path(POETRY_LOCK, Path::new(POETRY_LOCK_NAME)).unwrap();
+        let click = lock.imported_package(0).unwrap();
+        assert_eq!(click.url, None);
+        assert_eq!(click.files.len(), 2);
+        assert!(click.files[0].hashes.sha256.is_some());
+
+        let tool = lock.imported_package(6).unwrap();
+        assert_eq!(
+            tool.url.unwrap().as_str(),
+            "git+https://github.com/example/tool.git@0123456789abcdef"
+        );
+        assert!(tool.files.is_empty());
+    }
+
+    #[test]
+    fn test_constraints() {
+        let contains = |constraint: &str, version: &str| {
+            constraint_contains(constraint, &Version::from_str(version).unwrap())
+        };
+        assert_eq!(contains("*", "1.0"), Some(true));
+        assert_eq!(contains("^1.26", "1.26.4"), Some(true));
+        assert_eq!(contains("^1.26", "2.0"), Some(false));
+        assert_eq!(contains("^0.2.3", "0.3.0"), Some(false));
+        assert_eq!(contains("~1.2", "1.2.9"), Some(true));
+        assert_eq!(contains("~1.2", "1.3"), Some(false));
+        assert_eq!(contains(">=1.5.6,<1.5.7 || >1.5.7", "1.5.7"), Some(false));
+        assert_eq!(contains(">=1.5.6,<1.5.7 || >1.5.7", "1.7.1"), Some(true));
+        assert_eq!(contains("1.0.0", "1.0"), Some(true));
+        assert_eq!(contains("invalid", "1.0"), None);
+    }
+}
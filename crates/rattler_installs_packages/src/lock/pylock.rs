@@ -0,0 +1,222 @@
+//! Export of a [`Lockfile`] to the `pylock.toml` format of
+//! [PEP 751](https://peps.python.org/pep-0751/).
+
+use super::{LockedArtifact, LockedPackage, Lockfile, LockfileError};
+use crate::types::{ArtifactHashes, NormalizedPackageName};
+use pep440_rs::Version;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use url::Url;
+
+/// The version of the `pylock.toml` format that is written.
+const PYLOCK_VERSION: &str = "1.0";
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLock {
+    lock_version: &'static str,
+    environments: Vec<String>,
+    created_by: &'static str,
+    packages: Vec<PyLockPackage>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct PyLockPackage {
+    name: NormalizedPackageName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    marker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<PyLockArchive>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sdist: Option<PyLockFile>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    wheels: Vec<PyLockFile>,
+}
+
+#[derive(Serialize)]
+struct PyLockFile {
+    name: String,
+    url: Url,
+    hashes: ArtifactHashes,
+}
+
+#[derive(Serialize)]
+struct PyLockArchive {
+    url: Url,
+    hashes: ArtifactHashes,
+}
+
+/// A package that is merged from all the targets it occurs in.
+struct MergedPackage<'a> {
+    package: &'a LockedPackage,
+    artifacts: Vec<&'a LockedArtifact>,
+    markers: Vec<String>,
+}
+
+pub(super) fn to_pylock(lockfile: &Lockfile) -> Result<String, LockfileError> {
+    let markers = lockfile
+        .targets
+        .iter()
+        .map(|target| target.target.marker_expression())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Merge packages with the same name, version and url that occur in multiple targets.
+    let mut merged: BTreeMap<(NormalizedPackageName, String, Option<Url>), MergedPackage> =
+        BTreeMap::new();
+    for (target, marker) in lockfile.targets.iter().zip(&markers) {
+        for package in &target.packages {
+            let key = (
+                package.name.clone(),
+                package.version.to_string(),
+                package.url.clone(),
+            );
+            let entry = merged.entry(key).or_insert_with(|| MergedPackage {
+                package,
+                artifacts: Vec::new(),
+                markers: Vec::new(),
+            });
+            for artifact in &package.artifacts {
+                if !entry
+                    .artifacts
+                    .iter()
+                    .any(|existing| existing.filename == artifact.filename)
+                {
+                    entry.artifacts.push(artifact);
+                }
+            }
+            entry.markers.push(marker.clone());
+        }
+    }
+
+    let packages = merged
+        .into_values()
+        .map(|merged| {
+            // A package that is part of every target does not need a marker.
+            let marker = (merged.markers.len() < markers.len()).then(|| {
+                merged
+                    .markers
+                    .iter()
+                    .map(|marker| format!("({marker})"))
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            });
+
+            let file = |artifact: &LockedArtifact| PyLockFile {
+                name: artifact.filename.clone(),
+                url: artifact.url.clone(),
+                hashes: artifact.hashes.clone(),
+            };
+
+            match &merged.package.url {
+                // Direct references are recorded as an archive without a version.
+                Some(url) => PyLockPackage {
+                    name: merged.package.name.clone(),
+                    version: None,
+                    marker,
+                    archive: Some(PyLockArchive {
+                        url: url.clone(),
+                        hashes: merged
+                            .artifacts
+                            .first()
+                            .map(|artifact| artifact.hashes.clone())
+                            .unwrap_or_default(),
+                    }),
+                    sdist: None,
+                    wheels: Vec::new(),
+                },
+                None => PyLockPackage {
+                    name: merged.package.name.clone(),
+                    version: Some(merged.package.version.clone()),
+                    marker,
+                    archive: None,
+                    sdist: merged
+                        .artifacts
+                        .iter()
+                        .find(|artifact| !artifact.filename.ends_with(".whl"))
+                        .map(|artifact| file(artifact)),
+                    wheels: merged
+                        .artifacts
+                        .iter()
+                        .filter(|artifact| artifact.filename.ends_with(".whl"))
+                        .map(|artifact| file(artifact))
+                        .collect(),
+                },
+            }
+        })
+        .collect();
+
+    let pylock = PyLock {
+        lock_version: PYLOCK_VERSION,
+        environments: markers,
+        created_by: "rip",
+        packages,
+    };
+    Ok(toml::to_string_pretty(&pylock)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lock::test::package;
+    use crate::lock::{LockTarget, LockedTarget};
+    use crate::python_env::PlatformTag;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_to_pylock() {
+        let mut lockfile = Lockfile::new(Vec::new());
+        for (platform, packages) in [
+            (
+                "manylinux_2_17_x86_64",
+                vec![
+                    package(
+                        "flask",
+                        "3.0.0",
+                        &[("flask-3.0.0-py3-none-any.whl", None)],
+                        &[],
+                    ),
+                    package("uvloop", "0.19.0", &[("uvloop-0.19.0.tar.gz", None)], &[]),
+                ],
+            ),
+            (
+                "win_amd64",
+                vec![package(
+                    "flask",
+                    "3.0.0",
+                    &[("flask-3.0.0-py3-none-any.whl", None)],
+                    &[],
+                )],
+            ),
+        ] {
+            lockfile.targets.push(LockedTarget {
+                target: LockTarget::new(
+                    Version::from_str("3.11").unwrap(),
+                    PlatformTag::from_str(platform).unwrap(),
+                ),
+                packages,
+            });
+        }
+
+        let pylock: toml::Value = toml::from_str(&lockfile.to_pylock().unwrap()).unwrap();
+        assert_eq!(pylock["lock-version"].as_str(), Some("1.0"));
+        assert_eq!(pylock["environments"].as_array().unwrap().len(), 2);
+
+        let packages = pylock["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0]["name"].as_str(), Some("flask"));
+        assert!(packages[0].get("marker").is_none());
+        assert_eq!(packages[0]["wheels"].as_array().unwrap().len(), 1);
+        assert_eq!(packages[1]["name"].as_str(), Some("uvloop"));
+        assert_eq!(
+            packages[1]["marker"].as_str(),
+            Some("(python_version == '3.11' and sys_platform == 'linux' and platform_machine == 'x86_64')")
+        );
+        assert_eq!(
+            packages[1]["sdist"]["name"].as_str(),
+            Some("uvloop-0.19.0.tar.gz")
+        );
+    }
+}
@@ -0,0 +1,433 @@
+//! Signing and verifying lockfiles with [minisign](https://jedisct1.github.io/minisign/)
+//! compatible ed25519 keys and signatures.
+//!
+//! A lockfile that is signed with `rip lock --sign` can be verified with `minisign -Vm rip.lock`
+//! and vice versa, a lockfile that is signed with `minisign -Sm rip.lock` can be verified by rip.
+//! Password protected secret keys are not supported, create a key without a password with
+//! `minisign -G -W` or with [`SecretKey::generate`].
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use data_encoding::{BASE64, HEXUPPER};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The algorithm of signatures over the message itself.
+const ALG_LEGACY: [u8; 2] = *b"Ed";
+
+/// The algorithm of signatures over the blake2b hash of the message, used for new signatures.
+const ALG_PREHASHED: [u8; 2] = *b"ED";
+
+/// The checksum algorithm of secret keys.
+const CHECKSUM_ALG: [u8; 2] = *b"B2";
+
+/// The key derivation algorithm of secret keys that are not protected by a password.
+const KDF_NONE: [u8; 2] = [0, 0];
+
+/// An error that can occur when parsing keys or signatures or when verifying a signature.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum SignatureError {
+    #[error("invalid public key")]
+    InvalidPublicKey,
+
+    #[error("invalid secret key")]
+    InvalidSecretKey,
+
+    #[error("the secret key is protected by a password which is not supported, create a key without a password with `minisign -G -W`")]
+    EncryptedSecretKey,
+
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    #[error("the signature was created with key {signature} but the public key is {public_key}")]
+    KeyMismatch {
+        signature: String,
+        public_key: String,
+    },
+
+    #[error("the signature does not match the contents, they were modified after signing")]
+    VerificationFailed,
+
+    #[error("the trusted comment of the signature was modified after signing")]
+    TrustedCommentModified,
+
+    #[error("failed to generate a key")]
+    KeyGeneration,
+}
+
+/// A minisign public key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl PublicKey {
+    /// Returns the id of the key as it is shown by minisign.
+    pub fn key_id(&self) -> String {
+        format_key_id(&self.key_id)
+    }
+
+    /// Returns the key in the format of a minisign public key file.
+    pub fn to_file_contents(&self) -> String {
+        format!(
+            "untrusted comment: minisign public key {}\n{self}\n",
+            self.key_id()
+        )
+    }
+}
+
+/// Parses either the base64 encoded key, e.g. `RWQBAgMEBQYHCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4`,
+/// or the contents of a public key file.
+impl FromStr for PublicKey {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or(SignatureError::InvalidPublicKey)?;
+        let bytes = BASE64
+            .decode(encoded.as_bytes())
+            .map_err(|_| SignatureError::InvalidPublicKey)?;
+        if bytes.len() != 42 || bytes[..2] != ALG_LEGACY {
+            return Err(SignatureError::InvalidPublicKey);
+        }
+        Ok(Self {
+            key_id: bytes[2..10].try_into().expect("the length was checked"),
+            key: bytes[10..].try_into().expect("the length was checked"),
+        })
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut bytes = ALG_LEGACY.to_vec();
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.key);
+        write!(f, "{}", BASE64.encode(&bytes))
+    }
+}
+
+/// A minisign secret key that is not protected by a password.
+pub struct SecretKey {
+    key_id: [u8; 8],
+    key_pair: Ed25519KeyPair,
+    seed: [u8; 32],
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretKey")
+            .field("key_id", &format_key_id(&self.key_id))
+            .finish_non_exhaustive()
+    }
+}
+
+impl SecretKey {
+    /// Generates a new random key.
+    pub fn generate() -> Result<Self, SignatureError> {
+        let rng = SystemRandom::new();
+        let mut key_id = [0; 8];
+        let mut seed = [0; 32];
+        rng.fill(&mut key_id)
+            .and_then(|_| rng.fill(&mut seed))
+            .map_err(|_| SignatureError::KeyGeneration)?;
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(&seed)
+            .map_err(|_| SignatureError::KeyGeneration)?;
+        Ok(Self {
+            key_id,
+            key_pair,
+            seed,
+        })
+    }
+
+    /// Returns the public key that verifies the signatures of this key.
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            key_id: self.key_id,
+            key: self
+                .key_pair
+                .public_key()
+                .as_ref()
+                .try_into()
+                .expect("ed25519 public keys are 32 bytes"),
+        }
+    }
+
+    /// Returns the key in the format of a minisign secret key file.
+    pub fn to_file_contents(&self) -> String {
+        let mut keynum = self.key_id.to_vec();
+        keynum.extend_from_slice(&self.seed);
+        keynum.extend_from_slice(self.key_pair.public_key().as_ref());
+
+        let mut bytes = ALG_LEGACY.to_vec();
+        bytes.extend_from_slice(&KDF_NONE);
+        bytes.extend_from_slice(&CHECKSUM_ALG);
+        bytes.extend_from_slice(&[0; 48]);
+        bytes.extend_from_slice(&keynum);
+        bytes.extend_from_slice(&checksum(&keynum));
+        format!(
+            "untrusted comment: minisign secret key {}\n{}\n",
+            format_key_id(&self.key_id),
+            BASE64.encode(&bytes)
+        )
+    }
+
+    /// Signs `message` with a trusted comment that is protected by the signature as well.
+    pub fn sign(&self, message: &[u8], trusted_comment: &str) -> Signature {
+        let signature = self.key_pair.sign(&Blake2b512::digest(message));
+        let signature: [u8; 64] = signature
+            .as_ref()
+            .try_into()
+            .expect("ed25519 signatures are 64 bytes");
+        let global_signature = self
+            .key_pair
+            .sign(&[&signature[..], trusted_comment.as_bytes()].concat());
+        Signature {
+            algorithm: ALG_PREHASHED,
+            key_id: self.key_id,
+            signature,
+            trusted_comment: trusted_comment.to_string(),
+            global_signature: global_signature
+                .as_ref()
+                .try_into()
+                .expect("ed25519 signatures are 64 bytes"),
+        }
+    }
+}
+
+/// Parses the contents of a minisign secret key file.
+impl FromStr for SecretKey {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with("untrusted comment:"))
+            .ok_or(SignatureError::InvalidSecretKey)?;
+        let bytes = BASE64
+            .decode(encoded.as_bytes())
+            .map_err(|_| SignatureError::InvalidSecretKey)?;
+        if bytes.len() != 158 || bytes[..2] != ALG_LEGACY || bytes[4..6] != CHECKSUM_ALG {
+            return Err(SignatureError::InvalidSecretKey);
+        }
+        if bytes[2..4] != KDF_NONE {
+            return Err(SignatureError::EncryptedSecretKey);
+        }
+
+        let keynum = &bytes[54..126];
+        if checksum(keynum) != bytes[126..] {
+            return Err(SignatureError::InvalidSecretKey);
+        }
+        let seed: [u8; 32] = keynum[8..40].try_into().expect("the length was checked");
+        let key_pair = Ed25519KeyPair::from_seed_and_public_key(&seed, &keynum[40..])
+            .map_err(|_| SignatureError::InvalidSecretKey)?;
+        Ok(Self {
+            key_id: keynum[..8].try_into().expect("the length was checked"),
+            key_pair,
+            seed,
+        })
+    }
+}
+
+/// A minisign signature, as stored in a `.minisig` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    algorithm: [u8; 2],
+    key_id: [u8; 8],
+    signature: [u8; 64],
+    trusted_comment: String,
+    global_signature: [u8; 64],
+}
+
+impl Signature {
+    /// Returns the comment that is protected by the signature.
+    pub fn trusted_comment(&self) -> &str {
+        &self.trusted_comment
+    }
+
+    /// Returns the name of the signed file that minisign records in the trusted comment, e.g.
+    /// `rip.lock` for the comment `timestamp:1700000000\tfile:rip.lock\thashed`.
+    pub fn file_name(&self) -> Option<&str> {
+        self.trusted_comment
+            .split('\t')
+            .find_map(|field| field.strip_prefix("file:"))
+    }
+
+    /// Verifies that `message` was signed by `public_key`.
+    pub fn verify(&self, public_key: &PublicKey, message: &[u8]) -> Result<(), SignatureError> {
+        if self.key_id != public_key.key_id {
+            return Err(SignatureError::KeyMismatch {
+                signature: format_key_id(&self.key_id),
+                public_key: public_key.key_id(),
+            });
+        }
+
+        let key = UnparsedPublicKey::new(&ED25519, &public_key.key);
+        let verified = if self.algorithm == ALG_PREHASHED {
+            key.verify(&Blake2b512::digest(message), &self.signature)
+        } else {
+            key.verify(message, &self.signature)
+        };
+        verified.map_err(|_| SignatureError::VerificationFailed)?;
+
+        key.verify(
+            &[&self.signature[..], self.trusted_comment.as_bytes()].concat(),
+            &self.global_signature,
+        )
+        .map_err(|_| SignatureError::TrustedCommentModified)
+    }
+}
+
+impl FromStr for Signature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim_end);
+        let (Some(untrusted_comment), Some(signature), Some(trusted_comment), Some(global)) =
+            (lines.next(), lines.next(), lines.next(), lines.next())
+        else {
+            return Err(SignatureError::InvalidSignature);
+        };
+        let (Some(_), Some(trusted_comment)) = (
+            untrusted_comment.strip_prefix("untrusted comment:"),
+            trusted_comment.strip_prefix("trusted comment: "),
+        ) else {
+            return Err(SignatureError::InvalidSignature);
+        };
+
+        let bytes = BASE64
+            .decode(signature.as_bytes())
+            .map_err(|_| SignatureError::InvalidSignature)?;
+        let global_signature = BASE64
+            .decode(global.as_bytes())
+            .map_err(|_| SignatureError::InvalidSignature)?;
+        if bytes.len() != 74 || (bytes[..2] != ALG_LEGACY && bytes[..2] != ALG_PREHASHED) {
+            return Err(SignatureError::InvalidSignature);
+        }
+        Ok(Self {
+            algorithm: bytes[..2].try_into().expect("the length was checked"),
+            key_id: bytes[2..10].try_into().expect("the length was checked"),
+            signature: bytes[10..].try_into().expect("the length was checked"),
+            trusted_comment: trusted_comment.to_string(),
+            global_signature: global_signature
+                .try_into()
+                .map_err(|_| SignatureError::InvalidSignature)?,
+        })
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut bytes = self.algorithm.to_vec();
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.signature);
+        writeln!(
+            f,
+            "untrusted comment: signature from rip secret key {}",
+            format_key_id(&self.key_id)
+        )?;
+        writeln!(f, "{}", BASE64.encode(&bytes))?;
+        writeln!(f, "trusted comment: {}", self.trusted_comment)?;
+        writeln!(f, "{}", BASE64.encode(&self.global_signature))
+    }
+}
+
+/// Formats a key id like minisign, as the hexadecimal little endian number.
+fn format_key_id(key_id: &[u8; 8]) -> String {
+    let mut reversed = *key_id;
+    reversed.reverse();
+    HEXUPPER.encode(&reversed)
+}
+
+/// The checksum of the key material in a secret key file.
+fn checksum(keynum: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(ALG_LEGACY);
+    hasher.update(keynum);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret_key = SecretKey::generate().unwrap();
+        let secret_key = SecretKey::from_str(&secret_key.to_file_contents()).unwrap();
+        let public_key = PublicKey::from_str(&secret_key.public_key().to_file_contents()).unwrap();
+        assert_eq!(public_key, secret_key.public_key());
+
+        let signature = secret_key.sign(b"version = 1\n", "file:rip.lock");
+        let signature = Signature::from_str(&signature.to_string()).unwrap();
+        assert_eq!(signature.trusted_comment(), "file:rip.lock");
+        assert_eq!(signature.file_name(), Some("rip.lock"));
+        signature.verify(&public_key, b"version = 1\n").unwrap();
+
+        assert!(matches!(
+            signature.verify(&public_key, b"version = 2\n"),
+            Err(SignatureError::VerificationFailed)
+        ));
+        let other_key = SecretKey::generate().unwrap().public_key();
+        assert!(matches!(
+            signature.verify(&other_key, b"version = 1\n"),
+            Err(SignatureError::KeyMismatch { .. })
+        ));
+
+        let mut modified = signature.clone();
+        modified.trusted_comment = String::from("file:other.lock");
+        assert!(matches!(
+            modified.verify(&public_key, b"version = 1\n"),
+            Err(SignatureError::TrustedCommentModified)
+        ));
+    }
+
+    #[test]
+    fn test_minisign_format() {
+        // A key with the seed 0..32 and the key id 1..9 in the minisign file formats, with a legacy
+        // and a prehashed signature of the same message
+        let secret_key = SecretKey::from_str(
+            "untrusted comment: minisign secret key\n\
+             RWQAAEIyAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAQIDBAUGBwgAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHwOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4UuhoZ0oj6XehkwAxqgcDfOhmwCrPXeYwQwAwVY5NB98=\n",
+        )
+        .unwrap();
+        let public_key =
+            PublicKey::from_str("RWQBAgMEBQYHCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4")
+                .unwrap();
+        assert_eq!(secret_key.public_key(), public_key);
+        assert_eq!(public_key.key_id(), "0807060504030201");
+
+        let legacy = Signature::from_str(
+            "untrusted comment: signature from minisign secret key\n\
+             RWQBAgMEBQYHCJAWBFuzwdLpLCK6m62sxJCnXV/HepVy5M+gmBWrQCf17RHB+oLDc2OOXjfn0sRkAD2Wu15tVW7edGzmrSnJ0ws=\n\
+             trusted comment: timestamp:1700000000\tfile:rip.lock\n\
+             1PNGUuhKERpNfAOWekdXa8kvGQgahH4U5yr63omb+xik9iG/pd0W43HMAviJ2viJj1a4thLmeDu67y6cxf17Ag==\n",
+        )
+        .unwrap();
+        legacy.verify(&public_key, b"version = 1\n").unwrap();
+        assert_eq!(legacy.file_name(), Some("rip.lock"));
+
+        let prehashed = Signature::from_str(
+            "untrusted comment: signature from minisign secret key\n\
+             RUQBAgMEBQYHCAQ8Ikg4AG4iVaUeAz3PFk/FwsYk/zEuoPk/4YaLaQMyZBZ9gtBDwDQohJEQtMPafHoOebhkhkhLPPfYAIWnBQk=\n\
+             trusted comment: timestamp:1700000000\tfile:rip.lock\n\
+             ZKdcI2SxoQ72zKyAdy1kOIzZ9C0tyV0kIFiD4nWH4ImjcMxWqyJTI6jAfdmlVdoo7LZmlkUBHz86aEIdkDRyAQ==\n",
+        )
+        .unwrap();
+        prehashed.verify(&public_key, b"version = 1\n").unwrap();
+
+        // Ed25519 signatures are deterministic
+        assert_eq!(
+            secret_key.sign(b"version = 1\n", "timestamp:1700000000\tfile:rip.lock"),
+            prehashed
+        );
+    }
+}
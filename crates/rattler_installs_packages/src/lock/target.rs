@@ -0,0 +1,222 @@
+use super::LockfileError;
+use crate::python_env::{Pep508EnvMakers, PlatformTag, WheelTagTarget, WheelTags};
+use pep440_rs::Version;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// A combination of a python version and a platform for which packages are locked.
+///
+/// Since the interpreter of a target is usually not available on the machine that creates the
+/// lockfile, the environment markers of a target are derived from its platform tag.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockTarget {
+    /// The `major.minor` version of python, e.g. `3.11`
+    pub python_version: Version,
+
+    /// The platform, e.g. `manylinux_2_17_x86_64` or `win_amd64`
+    pub platform: PlatformTag,
+}
+
+/// The values of the platform related environment markers of a target.
+struct PlatformMarkers {
+    sys_platform: &'static str,
+    platform_system: &'static str,
+    os_name: &'static str,
+    platform_machine: String,
+}
+
+impl LockTarget {
+    /// Constructs a new target. Only the major and minor components of `python_version` are used.
+    pub fn new(python_version: Version, platform: PlatformTag) -> Self {
+        let (major, minor) = major_minor(&python_version);
+        Self {
+            python_version: format!("{major}.{minor}")
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            platform,
+        }
+    }
+
+    /// Returns the major and minor version of python of the target.
+    pub fn python_major_minor(&self) -> (u32, u32) {
+        major_minor(&self.python_version)
+    }
+
+    /// Returns the description of a CPython interpreter for this target which is used to compute
+    /// the compatible wheel tags.
+    pub fn wheel_tag_target(&self) -> WheelTagTarget {
+        WheelTagTarget::cpython(self.python_major_minor(), self.platform.clone())
+    }
+
+    /// Returns the wheel tags that are compatible with the target.
+    pub fn wheel_tags(&self) -> WheelTags {
+        WheelTags::from_target(&self.wheel_tag_target())
+    }
+
+    /// Returns the environment markers of a CPython interpreter on this target. Markers that
+    /// cannot be derived from the platform tag, like `platform_release`, are left empty.
+    pub fn env_markers(&self) -> Result<Pep508EnvMakers, LockfileError> {
+        let platform = self.platform_markers()?;
+        let (major, minor) = self.python_major_minor();
+        let full_version = format!("{major}.{minor}.0");
+        Ok(pep508_rs::MarkerEnvironment {
+            implementation_name: String::from("cpython"),
+            implementation_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            os_name: platform.os_name.to_string(),
+            platform_machine: platform.platform_machine,
+            platform_python_implementation: String::from("CPython"),
+            platform_release: String::new(),
+            platform_system: platform.platform_system.to_string(),
+            platform_version: String::new(),
+            python_full_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            python_version: format!("{major}.{minor}")
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            sys_platform: platform.sys_platform.to_string(),
+        }
+        .into())
+    }
+
+    /// Returns a PEP 508 marker expression that only evaluates to true on this target, e.g.
+    /// `python_version == '3.11' and sys_platform == 'linux' and platform_machine == 'x86_64'`.
+    pub fn marker_expression(&self) -> Result<String, LockfileError> {
+        let platform = self.platform_markers()?;
+        let (major, minor) = self.python_major_minor();
+        Ok(format!(
+            "python_version == '{major}.{minor}' and sys_platform == '{}' and platform_machine == '{}'",
+            platform.sys_platform, platform.platform_machine
+        ))
+    }
+
+    fn platform_markers(&self) -> Result<PlatformMarkers, LockfileError> {
+        let unsupported = || LockfileError::UnsupportedPlatform(self.platform.clone());
+        let posix = |sys_platform, platform_system, arch: &str| PlatformMarkers {
+            sys_platform,
+            platform_system,
+            os_name: "posix",
+            platform_machine: arch.to_string(),
+        };
+
+        Ok(match &self.platform {
+            PlatformTag::Manylinux { arch, .. }
+            | PlatformTag::LegacyManylinux { arch, .. }
+            | PlatformTag::Musllinux { arch, .. }
+            | PlatformTag::Linux { arch } => posix("linux", "Linux", arch),
+            PlatformTag::MacOs { binary_format, .. } => {
+                let arch = match binary_format.as_str() {
+                    "arm64" | "universal2" => "arm64",
+                    "x86_64" | "intel" | "universal" => "x86_64",
+                    _ => return Err(unsupported()),
+                };
+                posix("darwin", "Darwin", arch)
+            }
+            PlatformTag::Emscripten { arch, .. } => posix("emscripten", "Emscripten", arch),
+            PlatformTag::Wasi { arch } => posix("wasi", "wasi", arch),
+            PlatformTag::Ios { multiarch, .. } => {
+                let (arch, _sdk) = multiarch.rsplit_once('_').ok_or_else(unsupported)?;
+                posix("ios", "iOS", arch)
+            }
+            PlatformTag::Android { abi, .. } => {
+                let arch = match abi.as_str() {
+                    "arm64_v8a" => "aarch64",
+                    "armeabi_v7a" => "armv7l",
+                    "x86" => "i686",
+                    "x86_64" => "x86_64",
+                    _ => return Err(unsupported()),
+                };
+                posix("android", "Android", arch)
+            }
+            PlatformTag::Other(tag) => {
+                let arch = match tag.as_str() {
+                    "win_amd64" => "AMD64",
+                    "win32" => "x86",
+                    "win_arm64" => "ARM64",
+                    _ => return Err(unsupported()),
+                };
+                PlatformMarkers {
+                    sys_platform: "win32",
+                    platform_system: "Windows",
+                    os_name: "nt",
+                    platform_machine: arch.to_string(),
+                }
+            }
+            PlatformTag::Any => return Err(unsupported()),
+        })
+    }
+}
+
+impl Display for LockTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "python {} on {}", self.python_version, self.platform)
+    }
+}
+
+fn major_minor(version: &Version) -> (u32, u32) {
+    let release = &version.release;
+    let component = |index: usize| release.get(index).copied().unwrap_or_default() as u32;
+    (component(0), component(1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn target(python_version: &str, platform: &str) -> LockTarget {
+        LockTarget::new(
+            Version::from_str(python_version).unwrap(),
+            PlatformTag::from_str(platform).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_python_version_is_truncated() {
+        assert_eq!(
+            target("3.11.4", "win_amd64").python_version,
+            Version::from_str("3.11").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_env_markers() {
+        let markers = target("3.12", "manylinux_2_17_aarch64")
+            .env_markers()
+            .unwrap();
+        assert_eq!(markers.sys_platform, "linux");
+        assert_eq!(markers.platform_machine, "aarch64");
+        assert_eq!(markers.python_version.string, "3.12");
+
+        let markers = target("3.12", "macosx_11_0_arm64").env_markers().unwrap();
+        assert_eq!(markers.sys_platform, "darwin");
+        assert_eq!(markers.platform_system, "Darwin");
+
+        let markers = target("3.12", "win_amd64").env_markers().unwrap();
+        assert_eq!(markers.sys_platform, "win32");
+        assert_eq!(markers.os_name, "nt");
+        assert_eq!(markers.platform_machine, "AMD64");
+
+        let markers = target("3.13", "ios_13_0_arm64_iphoneos")
+            .env_markers()
+            .unwrap();
+        assert_eq!(markers.sys_platform, "ios");
+        assert_eq!(markers.platform_machine, "arm64");
+
+        assert!(matches!(
+            target("3.12", "any").env_markers(),
+            Err(LockfileError::UnsupportedPlatform(_))
+        ));
+    }
+
+    #[test]
+    fn test_marker_expression() {
+        assert_eq!(
+            target("3.11", "win_amd64").marker_expression().unwrap(),
+            "python_version == '3.11' and sys_platform == 'win32' and platform_machine == 'AMD64'"
+        );
+    }
+}
@@ -0,0 +1,922 @@
+//! Import and export of the `uv.lock` files that are written by [uv](https://docs.astral.sh/uv/).
+//!
+//! Like a `poetry.lock`, a `uv.lock` is resolved for all targets at once. The packages that are
+//! installed on a target are selected by following the dependencies whose markers apply, starting
+//! at the members of the workspace. When a [`Lockfile`] is exported, the packages of all its
+//! targets are merged and every dependency is guarded by the markers of the targets it occurs in.
+
+use super::import::{
+    parse_locked_hash, select_packages, ImportedFile, ImportedPackage, SelectedPackage,
+};
+use super::{LockTarget, LockedArtifact, LockedPackage, LockedTarget, Lockfile, LockfileError};
+use crate::index::PackageDb;
+use crate::types::{ArtifactHashes, ArtifactName, Extra, NormalizedPackageName};
+use fs_err as fs;
+use indexmap::IndexMap;
+use miette::IntoDiagnostic;
+use pep440_rs::Version;
+use pep508_rs::{MarkerEnvironment, MarkerTree, Requirement, VersionOrUrl};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use url::Url;
+
+/// The default file name of a uv lockfile.
+pub const UV_LOCK_NAME: &str = "uv.lock";
+
+/// The version of the `uv.lock` format that is read and written.
+const UV_LOCK_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawUvLock {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requires_python: Option<String>,
+    #[serde(default, rename = "package")]
+    packages: Vec<UvPackage>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct UvPackage {
+    name: NormalizedPackageName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    source: UvSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dependencies: Vec<UvDependency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sdist: Option<UvFile>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    wheels: Vec<UvFile>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    optional_dependencies: IndexMap<Extra, Vec<UvDependency>>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    dev_dependencies: IndexMap<Extra, Vec<UvDependency>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<UvMetadata>,
+}
+
+/// Where a package was locked from. Paths are relative to the directory of the lockfile.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum UvSource {
+    Registry(String),
+    Git(String),
+    Url(Url),
+    Path(String),
+    Directory(String),
+    Editable(String),
+    Virtual(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct UvDependency {
+    name: NormalizedPackageName,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<Version>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    marker: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extra: Vec<Extra>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UvFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<Url>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct UvMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    requires_dist: Vec<UvRequirement>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct UvRequirement {
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extras: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    specifier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    marker: Option<String>,
+}
+
+/// The contents of a `uv.lock` file, see [`UvLock::to_lockfile`].
+pub struct UvLock {
+    packages: Vec<UvPackage>,
+
+    /// The directory of the lockfile, which relative paths are resolved against
+    directory: PathBuf,
+}
+
+impl UvLock {
+    /// Reads a `uv.lock` file from disk.
+    pub fn from_path(path: &Path) -> Result<Self, LockfileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LockfileError::IoError(path.to_path_buf(), e))?;
+        Self::from_str_with_path(&contents, path)
+    }
+
+    fn from_str_with_path(contents: &str, path: &Path) -> Result<Self, LockfileError> {
+        let lock: RawUvLock =
+            toml::from_str(contents).map_err(|e| LockfileError::Parse(path.to_path_buf(), e))?;
+        if lock.version != UV_LOCK_VERSION {
+            return Err(LockfileError::UnsupportedVersion(
+                path.to_path_buf(),
+                lock.version,
+            ));
+        }
+        Ok(Self {
+            packages: lock.packages,
+            directory: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+        })
+    }
+
+    /// Converts the uv lockfile into a [`Lockfile`] for the given targets, without resolving
+    /// again. The requirements of the lockfile are those of the members of the workspace.
+    pub async fn to_lockfile(
+        &self,
+        package_db: &PackageDb,
+        targets: &[LockTarget],
+    ) -> miette::Result<Lockfile> {
+        let roots = self.roots();
+        let mut lockfile = Lockfile::new(self.requirements(&roots).into_diagnostic()?);
+        let mut artifacts = HashMap::new();
+        for target in targets {
+            let env = target.env_markers().into_diagnostic()?.0;
+            let mut packages = Vec::new();
+            for selected in self.select(&roots, &env).into_diagnostic()? {
+                let index = selected.index;
+                let Some(package) = self.imported_package(index).into_diagnostic()? else {
+                    continue;
+                };
+                if let Entry::Vacant(entry) = artifacts.entry(index) {
+                    entry.insert(package.artifacts(package_db).await?);
+                }
+                let dependencies = selected
+                    .dependencies
+                    .iter()
+                    .filter(|&&dependency| !self.is_virtual(dependency))
+                    .map(|&dependency| self.packages[dependency].name.clone())
+                    .collect();
+                packages.push(
+                    package
+                        .locked_for_target(
+                            &artifacts[&index],
+                            selected.extras,
+                            dependencies,
+                            target,
+                            package_db.artifact_registry(),
+                        )
+                        .into_diagnostic()?,
+                );
+            }
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+            lockfile.targets.push(LockedTarget {
+                target: target.clone(),
+                packages,
+            });
+        }
+        Ok(lockfile)
+    }
+
+    /// Returns the members of the workspace, or the packages that no other package depends on if
+    /// the lockfile does not contain a workspace.
+    fn roots(&self) -> Vec<usize> {
+        let members = (0..self.packages.len())
+            .filter(|&index| {
+                matches!(
+                    self.packages[index].source,
+                    UvSource::Editable(_) | UvSource::Virtual(_)
+                )
+            })
+            .collect::<Vec<_>>();
+        if !members.is_empty() {
+            return members;
+        }
+
+        let dependencies = self
+            .packages
+            .iter()
+            .flat_map(|package| &package.dependencies)
+            .map(|dependency| &dependency.name)
+            .collect::<HashSet<_>>();
+        (0..self.packages.len())
+            .filter(|&index| !dependencies.contains(&self.packages[index].name))
+            .collect()
+    }
+
+    /// Returns the requirements of the roots as they were locked by uv, or a pin of every root if
+    /// uv did not record them.
+    fn requirements(&self, roots: &[usize]) -> Result<Vec<Requirement>, LockfileError> {
+        let mut requirements: Vec<Requirement> = Vec::new();
+        for &index in roots {
+            let package = &self.packages[index];
+            let recorded = package
+                .metadata
+                .as_ref()
+                .map(|metadata| metadata.requires_dist.as_slice())
+                .unwrap_or_default();
+            for requirement in recorded {
+                let requirement = requirement.to_requirement().map_err(|e| {
+                    LockfileError::InvalidMarker(
+                        package.name.clone(),
+                        requirement.marker.clone().unwrap_or_default(),
+                        Box::new(e),
+                    )
+                })?;
+                if !requirements.contains(&requirement) {
+                    requirements.push(requirement);
+                }
+            }
+            if recorded.is_empty() && !self.is_virtual(index) {
+                let requirement = match &package.version {
+                    Some(version) => format!("{}=={version}", package.name.as_str()),
+                    None => package.name.as_str().to_string(),
+                };
+                let requirement = Requirement::from_str(&requirement)
+                    .expect("a requirement formatted from a name and a version is always valid");
+                if !requirements.contains(&requirement) {
+                    requirements.push(requirement);
+                }
+            }
+        }
+        Ok(requirements)
+    }
+
+    /// Returns the packages that are installed on a target, including the virtual members of the
+    /// workspace that are not installed themselves.
+    fn select(
+        &self,
+        roots: &[usize],
+        env: &MarkerEnvironment,
+    ) -> Result<Vec<SelectedPackage>, LockfileError> {
+        select_packages(roots.iter().copied(), |index, extras| {
+            self.dependencies(index, extras, roots.contains(&index), env)
+        })
+    }
+
+    /// Returns the dependencies of a package with the given extras that apply to a target. The
+    /// dependencies of the `dev` group are included for the members of the workspace, like
+    /// `uv sync` does.
+    fn dependencies(
+        &self,
+        index: usize,
+        extras: &[Extra],
+        is_root: bool,
+        env: &MarkerEnvironment,
+    ) -> Result<Vec<(usize, Vec<Extra>)>, LockfileError> {
+        let package = &self.packages[index];
+        let extra_names = extras.iter().map(Extra::as_str).collect::<Vec<_>>();
+        let dev = Extra::from_str("dev").expect("dev is a valid group name");
+        let dependencies = package
+            .dependencies
+            .iter()
+            .chain(
+                extras
+                    .iter()
+                    .filter_map(|extra| package.optional_dependencies.get(extra))
+                    .flatten(),
+            )
+            .chain(
+                is_root
+                    .then(|| package.dev_dependencies.get(&dev))
+                    .flatten()
+                    .into_iter()
+                    .flatten(),
+            );
+
+        let mut result = Vec::new();
+        for dependency in dependencies {
+            if let Some(marker) = &dependency.marker {
+                let marker = MarkerTree::from_str(marker).map_err(|e| {
+                    LockfileError::InvalidMarker(package.name.clone(), marker.clone(), Box::new(e))
+                })?;
+                if !marker.evaluate(env, &extra_names) {
+                    continue;
+                }
+            }
+            let found = self
+                .packages
+                .iter()
+                .enumerate()
+                .filter(|(_, candidate)| {
+                    candidate.name == dependency.name
+                        && dependency
+                            .version
+                            .as_ref()
+                            .map_or(true, |version| candidate.version.as_ref() == Some(version))
+                })
+                .max_by(|(_, a), (_, b)| a.version.cmp(&b.version))
+                .map(|(index, _)| index);
+            if let Some(found) = found {
+                result.push((found, dependency.extra.clone()));
+            }
+        }
+        Ok(result)
+    }
+
+    fn is_virtual(&self, index: usize) -> bool {
+        matches!(self.packages[index].source, UvSource::Virtual(_))
+    }
+
+    /// Converts a locked package into the representation that is shared by the importers, or
+    /// `None` for a virtual package that is not installed.
+    fn imported_package(&self, index: usize) -> Result<Option<ImportedPackage>, LockfileError> {
+        let package = &self.packages[index];
+        let invalid_url =
+            |source: String, e| LockfileError::InvalidSourceUrl(package.name.clone(), source, e);
+        let path_url = |path: &str| {
+            let absolute = self.directory.join(path);
+            let absolute = absolute.canonicalize().unwrap_or(absolute);
+            Url::from_file_path(absolute)
+                .map_err(|_| invalid_url(path.to_string(), url::ParseError::RelativeUrlWithoutBase))
+        };
+
+        let (url, is_source_tree) = match &package.source {
+            UvSource::Virtual(_) => return Ok(None),
+            UvSource::Registry(_) => (None, false),
+            UvSource::Url(url) => (Some(url.clone()), false),
+            UvSource::Path(path) => (Some(path_url(path)?), false),
+            UvSource::Directory(path) | UvSource::Editable(path) => (Some(path_url(path)?), true),
+            UvSource::Git(git) => (
+                Some(git_url(git).map_err(|e| invalid_url(git.clone(), e))?),
+                true,
+            ),
+        };
+
+        let files = if is_source_tree {
+            Vec::new()
+        } else {
+            let mut files = Vec::new();
+            for file in package.sdist.iter().chain(&package.wheels) {
+                let file_url = match (&file.url, &file.path) {
+                    (Some(url), _) => Some(url.clone()),
+                    (None, Some(path)) => Some(path_url(path)?),
+                    (None, None) => None,
+                };
+                let filename = file.filename.clone().or_else(|| {
+                    file_url
+                        .as_ref()
+                        .or(url.as_ref())
+                        .and_then(|url| url.path_segments()?.last().map(ToString::to_string))
+                });
+                let Some(filename) = filename else {
+                    continue;
+                };
+                files.push(ImportedFile {
+                    filename,
+                    url: file_url,
+                    hashes: file
+                        .hash
+                        .as_deref()
+                        .map(parse_locked_hash)
+                        .unwrap_or_default(),
+                });
+            }
+            files
+        };
+
+        Ok(Some(ImportedPackage {
+            name: package.name.clone(),
+            version: package
+                .version
+                .clone()
+                .unwrap_or_else(|| Version::from_str("0.0.0").expect("0.0.0 is a valid version")),
+            url,
+            files,
+            hashes: Vec::new(),
+        }))
+    }
+}
+
+impl UvRequirement {
+    fn to_requirement(&self) -> Result<Requirement, pep508_rs::Pep508Error> {
+        let mut requirement = self.name.clone();
+        if !self.extras.is_empty() {
+            requirement.push_str(&format!("[{}]", self.extras.join(",")));
+        }
+        if let Some(specifier) = &self.specifier {
+            requirement.push_str(specifier);
+        }
+        if let Some(marker) = &self.marker {
+            requirement.push_str(&format!(" ; {marker}"));
+        }
+        Requirement::from_str(&requirement)
+    }
+
+    fn from_requirement(requirement: &Requirement) -> Self {
+        Self {
+            name: requirement.name.clone(),
+            extras: requirement.extras.clone().unwrap_or_default(),
+            specifier: match &requirement.version_or_url {
+                Some(VersionOrUrl::VersionSpecifier(specifiers)) => Some(specifiers.to_string()),
+                Some(VersionOrUrl::Url(url)) => Some(format!("@ {url}")),
+                None => None,
+            },
+            marker: requirement.marker.as_ref().map(ToString::to_string),
+        }
+    }
+}
+
+/// Converts a git source of uv like `https://github.com/org/repo?rev=main#<commit>` into the
+/// direct url format of rip, `git+https://github.com/org/repo@<commit>`.
+fn git_url(source: &str) -> Result<Url, url::ParseError> {
+    let url = Url::parse(source)?;
+    let query = url.query_pairs().collect::<HashMap<_, _>>();
+    let reference = url
+        .fragment()
+        .map(ToString::to_string)
+        .or_else(|| query.get("rev").map(ToString::to_string))
+        .or_else(|| query.get("tag").map(ToString::to_string))
+        .or_else(|| query.get("branch").map(ToString::to_string));
+
+    let mut base = url.clone();
+    base.set_query(None);
+    base.set_fragment(None);
+    let mut direct = format!("git+{base}");
+    if let Some(reference) = reference {
+        direct = format!("{direct}@{reference}");
+    }
+    if let Some(subdirectory) = query.get("subdirectory") {
+        direct = format!("{direct}#subdirectory={subdirectory}");
+    }
+    Url::parse(&direct)
+}
+
+/// Converts a direct url of rip into the source of a package in a `uv.lock`.
+fn uv_source(url: &Url, is_source_tree: bool) -> UvSource {
+    if let Some(git) = url.as_str().strip_prefix("git+") {
+        let (url, subdirectory) = match git.split_once("#subdirectory=") {
+            Some((url, subdirectory)) => (url, Some(subdirectory)),
+            None => (git, None),
+        };
+        let (url, reference) = match url.rsplit_once('@') {
+            Some((base, reference)) if !reference.contains('/') => (base, Some(reference)),
+            _ => (url, None),
+        };
+        let mut source = url.to_string();
+        let mut query = Vec::new();
+        if let Some(reference) = reference {
+            query.push(format!("rev={reference}"));
+        }
+        if let Some(subdirectory) = subdirectory {
+            query.push(format!("subdirectory={subdirectory}"));
+        }
+        if !query.is_empty() {
+            source = format!("{source}?{}", query.join("&"));
+        }
+        if let Some(reference) = reference {
+            source = format!("{source}#{reference}");
+        }
+        return UvSource::Git(source);
+    }
+    match url.to_file_path() {
+        Ok(path) if url.scheme() == "file" => {
+            let path = path.display().to_string();
+            if is_source_tree {
+                UvSource::Directory(path)
+            } else {
+                UvSource::Path(path)
+            }
+        }
+        _ => UvSource::Url(url.clone()),
+    }
+}
+
+fn uv_file(artifact: &LockedArtifact) -> UvFile {
+    UvFile {
+        url: Some(artifact.url.clone()),
+        path: None,
+        filename: None,
+        hash: format_hash(&artifact.hashes),
+    }
+}
+
+fn format_hash(hashes: &ArtifactHashes) -> Option<String> {
+    hashes.sha256.map(|sha256| format!("sha256:{sha256:x}"))
+}
+
+/// A package that is merged from all the targets it occurs in.
+struct MergedPackage<'a> {
+    package: &'a LockedPackage,
+    artifacts: Vec<&'a LockedArtifact>,
+    /// The markers of the targets the package is part of
+    markers: Vec<String>,
+    /// The markers of the targets that each dependency is part of, by name and version
+    dependencies: BTreeMap<(NormalizedPackageName, Version), Vec<String>>,
+}
+
+/// Joins the markers of targets, or returns `None` if the targets are all the targets of the
+/// dependent.
+fn join_markers(markers: &[String], all: &[String]) -> Option<String> {
+    (markers.len() < all.len()).then(|| {
+        markers
+            .iter()
+            .map(|marker| format!("({marker})"))
+            .collect::<Vec<_>>()
+            .join(" or ")
+    })
+}
+
+pub(super) fn to_uv_lock(
+    lockfile: &Lockfile,
+    project: &NormalizedPackageName,
+    index_url: &Url,
+) -> Result<String, LockfileError> {
+    let markers = lockfile
+        .targets
+        .iter()
+        .map(|target| target.target.marker_expression())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Merge packages with the same name and version that occur in multiple targets.
+    let mut merged: BTreeMap<(NormalizedPackageName, Version), MergedPackage> = BTreeMap::new();
+    let mut roots: BTreeMap<(NormalizedPackageName, Version), Vec<String>> = BTreeMap::new();
+    let root_names = lockfile
+        .requirements
+        .iter()
+        .filter_map(|requirement| requirement.name.parse::<NormalizedPackageName>().ok())
+        .collect::<HashSet<_>>();
+    for (target, marker) in lockfile.targets.iter().zip(&markers) {
+        let versions = target
+            .packages
+            .iter()
+            .map(|package| (&package.name, &package.version))
+            .collect::<HashMap<_, _>>();
+        // Packages that nothing depends on, like development dependencies, are only reachable
+        // from the project.
+        let dependents = target
+            .packages
+            .iter()
+            .flat_map(|package| &package.dependencies)
+            .collect::<HashSet<_>>();
+        for package in &target.packages {
+            let key = (package.name.clone(), package.version.clone());
+            if root_names.contains(&package.name) || !dependents.contains(&package.name) {
+                roots.entry(key.clone()).or_default().push(marker.clone());
+            }
+            let entry = merged.entry(key).or_insert_with(|| MergedPackage {
+                package,
+                artifacts: Vec::new(),
+                markers: Vec::new(),
+                dependencies: BTreeMap::new(),
+            });
+            for artifact in &package.artifacts {
+                if !entry
+                    .artifacts
+                    .iter()
+                    .any(|existing| existing.filename == artifact.filename)
+                {
+                    entry.artifacts.push(artifact);
+                }
+            }
+            entry.markers.push(marker.clone());
+            for dependency in &package.dependencies {
+                if let Some(version) = versions.get(dependency) {
+                    entry
+                        .dependencies
+                        .entry((dependency.clone(), (*version).clone()))
+                        .or_default()
+                        .push(marker.clone());
+                }
+            }
+        }
+    }
+
+    // Dependencies only need a version if multiple versions of a package are locked.
+    let mut version_counts = HashMap::new();
+    for (name, _) in merged.keys() {
+        *version_counts.entry(name.clone()).or_insert(0) += 1;
+    }
+    let dependency = |(name, version): &(NormalizedPackageName, Version),
+                      marker: Option<String>| UvDependency {
+        name: name.clone(),
+        version: (version_counts[name] > 1).then(|| version.clone()),
+        marker,
+        extra: Vec::new(),
+    };
+
+    let mut packages = vec![UvPackage {
+        name: project.clone(),
+        version: None,
+        source: UvSource::Virtual(String::from(".")),
+        dependencies: roots
+            .iter()
+            .map(|(key, target_markers)| dependency(key, join_markers(target_markers, &markers)))
+            .collect(),
+        sdist: None,
+        wheels: Vec::new(),
+        optional_dependencies: IndexMap::new(),
+        dev_dependencies: IndexMap::new(),
+        metadata: Some(UvMetadata {
+            requires_dist: lockfile
+                .requirements
+                .iter()
+                .map(UvRequirement::from_requirement)
+                .collect(),
+        }),
+    }];
+
+    for merged in merged.into_values() {
+        let package = merged.package;
+        let is_source_tree = merged.artifacts.iter().all(|artifact| {
+            matches!(
+                ArtifactName::from_filename(&artifact.filename, package.url.clone(), &package.name),
+                Ok(ArtifactName::STree(_))
+            )
+        });
+        let source = match &package.url {
+            Some(url) => uv_source(url, is_source_tree),
+            None => UvSource::Registry(index_url.to_string()),
+        };
+        let (sdist, wheels) = if is_source_tree {
+            (None, Vec::new())
+        } else {
+            (
+                merged
+                    .artifacts
+                    .iter()
+                    .find(|artifact| !artifact.filename.ends_with(".whl"))
+                    .map(|artifact| uv_file(artifact)),
+                merged
+                    .artifacts
+                    .iter()
+                    .filter(|artifact| artifact.filename.ends_with(".whl"))
+                    .map(|artifact| uv_file(artifact))
+                    .collect(),
+            )
+        };
+        packages.push(UvPackage {
+            name: package.name.clone(),
+            version: Some(package.version.clone()),
+            source,
+            dependencies: merged
+                .dependencies
+                .iter()
+                .map(|(key, dependency_markers)| {
+                    dependency(key, join_markers(dependency_markers, &merged.markers))
+                })
+                .collect(),
+            sdist,
+            wheels,
+            optional_dependencies: IndexMap::new(),
+            dev_dependencies: IndexMap::new(),
+            metadata: None,
+        });
+    }
+
+    let requires_python = lockfile
+        .targets
+        .iter()
+        .map(|target| target.target.python_major_minor())
+        .min()
+        .map(|(major, minor)| format!(">={major}.{minor}"));
+    let lock = RawUvLock {
+        version: UV_LOCK_VERSION,
+        requires_python,
+        packages,
+    };
+    Ok(toml::to_string_pretty(&lock)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::PlatformTag;
+
+    const UV_LOCK: &str = r#"
+version = 1
+requires-python = ">=3.11"
+
+[[package]]
+name = "click"
+version = "8.1.7"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [
+    { name = "colorama", marker = "platform_system == 'Windows'" },
+]
+sdist = { url = "https://files.pythonhosted.org/packages/96/d3/click-8.1.7.tar.gz", hash = "sha256:ca9853ad459e787e2192211578cc907e7594e294c7ccc834310722b41b9ca6de", size = 336121 }
+wheels = [
+    { url = "https://files.pythonhosted.org/packages/00/2e/click-8.1.7-py3-none-any.whl", hash = "sha256:ae74fb96c20a0277a1d615f1e4d73c8414f5a98db8b799a7931d1582f3390c28", size = 97941 },
+]
+
+[[package]]
+name = "colorama"
+version = "0.4.6"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.pythonhosted.org/packages/d1/d6/colorama-0.4.6-py2.py3-none-any.whl", hash = "sha256:4f1d9991f5acc0ca119f9d443620b77f9d6b33703e51011c16baf57afb285fc6", size = 25335 },
+]
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+source = { virtual = "." }
+dependencies = [
+    { name = "click" },
+    { name = "requests", extra = ["socks"] },
+]
+
+[package.dev-dependencies]
+dev = [
+    { name = "tool" },
+]
+
+[package.metadata]
+requires-dist = [
+    { name = "click", specifier = ">=8" },
+    { name = "requests", extras = ["socks"] },
+]
+
+[[package]]
+name = "pysocks"
+version = "1.7.1"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.pythonhosted.org/packages/8d/59/PySocks-1.7.1-py3-none-any.whl", hash = "sha256:2725bd0a9925919b9b51739eea5f9e2bae91e83288108a9ad338b2e3a4435ee5", size = 16725 },
+]
+
+[[package]]
+name = "requests"
+version = "2.31.0"
+source = { registry = "https://pypi.org/simple" }
+wheels = [
+    { url = "https://files.pythonhosted.org/packages/70/8e/requests-2.31.0-py3-none-any.whl", hash = "sha256:58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f", size = 62574 },
+]
+
+[package.optional-dependencies]
+socks = [
+    { name = "pysocks" },
+]
+
+[[package]]
+name = "tool"
+version = "0.2.0"
+source = { git = "https://github.com/example/tool?rev=main#0123456789abcdef" }
+"#;
+
+    fn target(platform: &str) -> LockTarget {
+        LockTarget::new(
+            Version::from_str("3.11").unwrap(),
+            PlatformTag::from_str(platform).unwrap(),
+        )
+    }
+
+    fn selected(lock: &UvLock, platform: &str) -> Vec<String> {
+        let env = target(platform).env_markers().unwrap().0;
+        let mut selected = lock
+            .select(&lock.roots(), &env)
+            .unwrap()
+            .into_iter()
+            .map(|selected| lock.packages[selected.index].name.as_str().to_string())
+            .collect::<Vec<_>>();
+        selected.sort();
+        selected
+    }
+
+    #[test]
+    fn test_select_packages() {
+        let lock = UvLock::from_str_with_path(UV_LOCK, Path::new(UV_LOCK_NAME)).unwrap();
+        assert_eq!(
+            selected(&lock, "manylinux_2_17_x86_64"),
+            ["click", "demo", "pysocks", "requests", "tool"]
+        );
+        assert_eq!(
+            selected(&lock, "win_amd64"),
+            ["click", "colorama", "demo", "pysocks", "requests", "tool"]
+        );
+
+        let requirements = lock.requirements(&lock.roots()).unwrap();
+        assert_eq!(
+            requirements
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["click >=8", "requests[socks]"]
+        );
+    }
+
+    #[test]
+    fn test_imported_package() {
+        let lock = UvLock::from_str_with_path(UV_LOCK, Path::new(UV_LOCK_NAME)).unwrap();
+        let click = lock.imported_package(0).unwrap().unwrap();
+        assert_eq!(click.url, None);
+        assert_eq!(
+            click
+                .files
+                .iter()
+                .map(|file| file.filename.as_str())
+                .collect::<Vec<_>>(),
+            ["click-8.1.7.tar.gz", "click-8.1.7-py3-none-any.whl"]
+        );
+        assert!(click.files[0].url.is_some());
+
+        assert!(lock.imported_package(2).unwrap().is_none());
+
+        let tool = lock.imported_package(5).unwrap().unwrap();
+        assert_eq!(
+            tool.url.as_ref().unwrap().as_str(),
+            "git+https://github.com/example/tool@0123456789abcdef"
+        );
+        assert!(matches!(
+            uv_source(tool.url.as_ref().unwrap(), true),
+            UvSource::Git(source) if source == "https://github.com/example/tool?rev=0123456789abcdef#0123456789abcdef"
+        ));
+    }
+
+    #[test]
+    fn test_export() {
+        let lock = UvLock::from_str_with_path(UV_LOCK, Path::new(UV_LOCK_NAME)).unwrap();
+        let mut lockfile = Lockfile::new(lock.requirements(&lock.roots()).unwrap());
+        for platform in ["manylinux_2_17_x86_64", "win_amd64"] {
+            let target = target(platform);
+            let env = target.env_markers().unwrap().0;
+            let mut packages = Vec::new();
+            for selected in lock.select(&lock.roots(), &env).unwrap() {
+                let Some(package) = lock.imported_package(selected.index).unwrap() else {
+                    continue;
+                };
+                let artifacts = package
+                    .files
+                    .iter()
+                    .map(|file| LockedArtifact {
+                        filename: file.filename.clone(),
+                        url: file.url.clone().or(package.url.clone()).unwrap(),
+                        hashes: file.hashes.clone(),
+                        requires_python: None,
+                    })
+                    .collect::<Vec<_>>();
+                let artifacts = if artifacts.is_empty() {
+                    vec![LockedArtifact {
+                        filename: format!("{}-{}", package.name.as_str(), package.version),
+                        url: package.url.clone().unwrap(),
+                        hashes: ArtifactHashes::default(),
+                        requires_python: None,
+                    }]
+                } else {
+                    artifacts
+                };
+                let dependencies = selected
+                    .dependencies
+                    .iter()
+                    .map(|&dependency| lock.packages[dependency].name.clone())
+                    .collect();
+                packages.push(
+                    package
+                        .locked_for_target(
+                            &artifacts,
+                            selected.extras,
+                            dependencies,
+                            &target,
+                            &Default::default(),
+                        )
+                        .unwrap(),
+                );
+            }
+            packages.sort_by(|a, b| a.name.cmp(&b.name));
+            lockfile.targets.push(LockedTarget { target, packages });
+        }
+
+        let exported = lockfile
+            .to_uv_lock(
+                &NormalizedPackageName::from_str("demo").unwrap(),
+                &Url::parse("https://pypi.org/simple").unwrap(),
+            )
+            .unwrap();
+        let reimported = UvLock::from_str_with_path(&exported, Path::new(UV_LOCK_NAME)).unwrap();
+        assert_eq!(
+            selected(&reimported, "manylinux_2_17_x86_64"),
+            ["click", "demo", "pysocks", "requests", "tool"]
+        );
+        assert_eq!(
+            selected(&reimported, "win_amd64"),
+            ["click", "colorama", "demo", "pysocks", "requests", "tool"]
+        );
+
+        let click = reimported
+            .packages
+            .iter()
+            .find(|package| package.name.as_str() == "click")
+            .unwrap();
+        assert_eq!(click.wheels.len(), 1);
+        assert!(click.sdist.is_some());
+        assert_eq!(
+            click.dependencies[0].marker.as_deref(),
+            Some("(python_version == '3.11' and sys_platform == 'win32' and platform_machine == 'AMD64')")
+        );
+    }
+}
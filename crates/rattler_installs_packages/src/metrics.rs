@@ -0,0 +1,241 @@
+//! Counters and histograms of the operations that are performed while resolving and installing.
+//!
+//! Every [`crate::index::PackageDb`] collects [`Metrics`] about the requests it makes, the
+//! metadata it retrieves and the wheels that are built with it. Installs performed by
+//! [`crate::install::execute_sync_plan`] are recorded in the same instance unless
+//! [`crate::install::InstallOptions::metrics`] is set. Use [`Metrics::snapshot`] to retrieve the
+//! values after an operation, e.g. to track the cost of the dependencies of a project in CI.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The upper bounds in seconds of the buckets of a [`HistogramSnapshot`]. Durations that are
+/// longer than the last bound are only counted in the total.
+pub const HISTOGRAM_BUCKETS: [f64; 10] = [0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+/// Collects the metrics of operations, see the [module docs](self). An instance can be shared
+/// between threads, all values are updated atomically.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub(crate) requests: Counter,
+    pub(crate) cache_hits: Counter,
+    pub(crate) bytes_downloaded: Counter,
+    pub(crate) metadata_requests: Counter,
+    pub(crate) metadata_cache_hits: Counter,
+    pub(crate) wheels_built: Counter,
+    pub(crate) build_failures: Counter,
+    pub(crate) packages_installed: Counter,
+    pub(crate) packages_removed: Counter,
+    pub(crate) resolve_seconds: Histogram,
+    pub(crate) build_seconds: Histogram,
+    pub(crate) install_seconds: Histogram,
+}
+
+impl Metrics {
+    /// Constructs an instance in which all values are zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current values of all metrics.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            requests: self.requests.get(),
+            cache_hits: self.cache_hits.get(),
+            bytes_downloaded: self.bytes_downloaded.get(),
+            metadata_requests: self.metadata_requests.get(),
+            metadata_cache_hits: self.metadata_cache_hits.get(),
+            wheels_built: self.wheels_built.get(),
+            build_failures: self.build_failures.get(),
+            packages_installed: self.packages_installed.get(),
+            packages_removed: self.packages_removed.get(),
+            resolve_seconds: self.resolve_seconds.snapshot(),
+            build_seconds: self.build_seconds.snapshot(),
+            install_seconds: self.install_seconds.snapshot(),
+        }
+    }
+
+    /// Sets all values back to zero, e.g. to measure the next operation on its own.
+    pub fn reset(&self) {
+        for counter in [
+            &self.requests,
+            &self.cache_hits,
+            &self.bytes_downloaded,
+            &self.metadata_requests,
+            &self.metadata_cache_hits,
+            &self.wheels_built,
+            &self.build_failures,
+            &self.packages_installed,
+            &self.packages_removed,
+        ] {
+            counter.0.store(0, Ordering::Relaxed);
+        }
+        for histogram in [
+            &self.resolve_seconds,
+            &self.build_seconds,
+            &self.install_seconds,
+        ] {
+            *histogram.0.lock() = HistogramSnapshot::default();
+        }
+    }
+}
+
+/// The values of [`Metrics`] at a point in time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsSnapshot {
+    /// The number of requests that were sent over the network, including requests to revalidate
+    /// a cached response
+    pub requests: u64,
+
+    /// The number of requests that were answered from the HTTP cache, either because the cached
+    /// response was fresh or because the server confirmed it was not modified
+    pub cache_hits: u64,
+
+    /// The number of bytes of response bodies that were received over the network
+    pub bytes_downloaded: u64,
+
+    /// The number of times the metadata of a package version was requested
+    pub metadata_requests: u64,
+
+    /// The number of times the metadata of a package version was found in the cache
+    pub metadata_cache_hits: u64,
+
+    /// The number of wheels that were built from sdists or source trees
+    pub wheels_built: u64,
+
+    /// The number of wheels that failed to build
+    pub build_failures: u64,
+
+    /// The number of wheels that were installed
+    pub packages_installed: u64,
+
+    /// The number of distributions that were removed from an environment
+    pub packages_removed: u64,
+
+    /// How long the resolutions took
+    pub resolve_seconds: HistogramSnapshot,
+
+    /// How long the builds of wheels took, including those that failed
+    pub build_seconds: HistogramSnapshot,
+
+    /// How long the installs of the individual wheels took
+    pub install_seconds: HistogramSnapshot,
+}
+
+/// The distribution of the durations of an operation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramSnapshot {
+    /// The number of recorded durations
+    pub count: u64,
+
+    /// The sum of the recorded durations in seconds
+    pub sum: f64,
+
+    /// The shortest recorded duration in seconds, or zero if nothing was recorded
+    pub min: f64,
+
+    /// The longest recorded duration in seconds
+    pub max: f64,
+
+    /// The number of recorded durations that are at most as long as the corresponding bound of
+    /// [`HISTOGRAM_BUCKETS`], like the cumulative buckets of a Prometheus histogram
+    pub buckets: Vec<u64>,
+}
+
+impl Default for HistogramSnapshot {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            min: 0.0,
+            max: 0.0,
+            buckets: vec![0; HISTOGRAM_BUCKETS.len()],
+        }
+    }
+}
+
+impl HistogramSnapshot {
+    /// Returns the average of the recorded durations in seconds, or `None` if nothing was
+    /// recorded.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+}
+
+/// A counter that can be incremented from multiple threads.
+#[derive(Debug, Default)]
+pub(crate) struct Counter(AtomicU64);
+
+impl Counter {
+    pub(crate) fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc(&self) {
+        self.add(1);
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Records durations into the buckets of [`HISTOGRAM_BUCKETS`].
+#[derive(Debug, Default)]
+pub(crate) struct Histogram(Mutex<HistogramSnapshot>);
+
+impl Histogram {
+    pub(crate) fn record(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut histogram = self.0.lock();
+        histogram.min = if histogram.count == 0 {
+            seconds
+        } else {
+            histogram.min.min(seconds)
+        };
+        histogram.max = histogram.max.max(seconds);
+        histogram.count += 1;
+        histogram.sum += seconds;
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(HISTOGRAM_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    fn snapshot(&self) -> HistogramSnapshot {
+        self.0.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let metrics = Metrics::new();
+        metrics.build_seconds.record(Duration::from_millis(200));
+        metrics.build_seconds.record(Duration::from_secs(20));
+        metrics.requests.inc();
+        metrics.bytes_downloaded.add(1024);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.bytes_downloaded, 1024);
+        assert_eq!(snapshot.build_seconds.count, 2);
+        assert_eq!(snapshot.build_seconds.min, 0.2);
+        assert_eq!(snapshot.build_seconds.max, 20.0);
+        assert_eq!(snapshot.build_seconds.mean(), Some(10.1));
+        assert_eq!(
+            snapshot.build_seconds.buckets,
+            [0, 0, 0, 1, 1, 1, 1, 2, 2, 2]
+        );
+        assert_eq!(snapshot.install_seconds.mean(), None);
+
+        metrics.reset();
+        assert_eq!(metrics.snapshot(), MetricsSnapshot::default());
+    }
+}
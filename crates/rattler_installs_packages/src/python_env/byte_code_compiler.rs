@@ -53,8 +53,8 @@ pub enum SpawnCompilerError {
 pub struct ByteCodeCompiler {
     /// The channel that is used to send compilation requests to the compilation host. If this is
     /// dropped the attached thread will drop stdin of the child which will signal the child to
-    /// exit.
-    request_tx: Option<Sender<CompilationRequest>>,
+    /// exit. The sender is wrapped in a mutex to allow using the compiler from multiple threads.
+    request_tx: Mutex<Option<Sender<CompilationRequest>>>,
 
     /// Callback functions per compilation request. These are called when the compilation host
     /// finishes processing a request.
@@ -177,7 +177,7 @@ impl ByteCodeCompiler {
         });
 
         Ok(Self {
-            request_tx: Some(request_tx),
+            request_tx: Mutex::new(Some(request_tx)),
             pending_callbacks,
             child: Some(child),
             _compilation_source: compilation_source,
@@ -214,6 +214,7 @@ impl ByteCodeCompiler {
             .push(Box::new(callback));
 
         self.request_tx
+            .lock()
             .as_ref()
             .expect("the channel is only dropped on drop")
             .send(source_path.to_owned())
@@ -236,7 +237,7 @@ impl ByteCodeCompiler {
         // Drop the request channel to signal the compilation host that we are done. This will
         // ensure that the stdin pipe of the compilation host is closed which will signal the host
         // to exit.
-        drop(self.request_tx.take());
+        drop(self.request_tx.get_mut().take());
 
         // Wait for the compilation host to exit
         self.child
@@ -250,7 +251,7 @@ impl ByteCodeCompiler {
 
 impl Drop for ByteCodeCompiler {
     fn drop(&mut self) {
-        drop(self.request_tx.take());
+        drop(self.request_tx.get_mut().take());
         if let Some(mut child) = self.child.take() {
             child.wait().unwrap();
         }
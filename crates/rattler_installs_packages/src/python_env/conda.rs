@@ -0,0 +1,264 @@
+//! Installing wheels into conda environments.
+//!
+//! A conda environment is a prefix that contains a `conda-meta` directory with a JSON record for
+//! every conda package that is installed in it. The layout of the python part of the prefix is the
+//! same as that of a virtual environment, except that on windows the interpreter is located in the
+//! root of the prefix.
+use crate::artifacts::wheel::{InstallPaths, UnpackError, UnpackWheelOptions, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::python_env::{
+    find_distributions_in_venv, Distribution, FindDistributionError,
+    ParsePythonInterpreterVersionError, PythonInterpreterVersion,
+};
+use fs_err as fs;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// An error that can occur when opening a conda environment.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum CondaEnvironmentError {
+    #[error("{0} is not a conda environment")]
+    NotACondaEnvironment(PathBuf),
+
+    #[error("python is not installed in the conda environment at {0}")]
+    PythonNotInstalled(PathBuf),
+
+    #[error("failed to read {0}")]
+    FailedToReadRecord(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    FailedToParseRecord(PathBuf, #[source] serde_json::Error),
+
+    #[error("failed to determine the python version of the conda environment")]
+    FailedToDetermineVersion(#[from] ParsePythonInterpreterVersionError),
+}
+
+/// The parts of a `conda-meta/<package>.json` record that are relevant to us.
+#[derive(Debug, Deserialize)]
+struct CondaPackageRecord {
+    name: String,
+    version: String,
+    #[serde(default)]
+    files: Vec<PathBuf>,
+}
+
+/// A conda environment into which wheels can be installed.
+///
+/// Python packages that were installed by conda are also visible as regular distributions. Use
+/// [`CondaEnvironment::conda_managed_distributions`] to determine which of the installed
+/// distributions are owned by conda, these should not be modified by the installer.
+#[derive(Debug, Clone)]
+pub struct CondaEnvironment {
+    /// The prefix of the environment
+    prefix: PathBuf,
+    /// Install paths relative to the prefix
+    install_paths: InstallPaths,
+    /// The `.dist-info` directories, relative to the prefix, that are part of conda packages.
+    conda_dist_infos: HashSet<PathBuf>,
+}
+
+impl CondaEnvironment {
+    /// Returns true if `path` looks like the prefix of a conda environment.
+    pub fn is_conda_environment(path: &Path) -> bool {
+        path.join("conda-meta").is_dir()
+    }
+
+    /// Opens the conda environment at `prefix`. The version of python is determined from the
+    /// `python` package that is installed in the environment.
+    pub fn from_prefix(prefix: impl Into<PathBuf>) -> Result<Self, CondaEnvironmentError> {
+        let prefix = prefix.into();
+        if !Self::is_conda_environment(&prefix) {
+            return Err(CondaEnvironmentError::NotACondaEnvironment(prefix));
+        }
+
+        let records = read_conda_meta(&prefix.join("conda-meta"))?;
+        let Some(python) = records.iter().find(|record| record.name == "python") else {
+            return Err(CondaEnvironmentError::PythonNotInstalled(prefix));
+        };
+
+        // Prefer the version from the record, but fall back to asking the interpreter for
+        // versions that are not simply `major.minor.patch`, e.g. release candidates.
+        let windows = cfg!(windows);
+        let version = match PythonInterpreterVersion::from_python_output(&format!(
+            "Python {}",
+            python.version
+        )) {
+            Ok(version) => version,
+            Err(_) => PythonInterpreterVersion::from_path(&python_executable(&prefix, windows))?,
+        };
+
+        // The files of a `.dist-info` directory are listed directly, the directory itself is not.
+        let conda_dist_infos = records
+            .iter()
+            .flat_map(|record| record.files.iter())
+            .filter_map(|file| file.parent())
+            .filter(|dir| dir.extension().is_some_and(|ext| ext == "dist-info"))
+            .map(Path::to_path_buf)
+            .collect();
+
+        Ok(Self {
+            install_paths: InstallPaths::for_venv(version, windows),
+            prefix,
+            conda_dist_infos,
+        })
+    }
+
+    /// Install a wheel into this conda environment
+    pub fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        wheel.unpack(
+            &self.prefix,
+            &self.install_paths,
+            &self.python_executable(),
+            options,
+        )
+    }
+
+    /// Returns all the distributions that are installed in this environment, both the ones
+    /// installed by conda and the ones installed from wheels. The `dist_info` paths of the
+    /// distributions are relative to [`CondaEnvironment::root`].
+    pub fn installed_distributions(&self) -> Result<Vec<Distribution>, FindDistributionError> {
+        find_distributions_in_venv(&self.prefix, &self.install_paths)
+    }
+
+    /// Returns the distributions that are part of a conda package. Replacing or removing these
+    /// would corrupt the conda environment.
+    pub fn conda_managed_distributions(&self) -> Result<Vec<Distribution>, FindDistributionError> {
+        Ok(self
+            .installed_distributions()?
+            .into_iter()
+            .filter(|distribution| self.is_conda_managed(distribution))
+            .collect())
+    }
+
+    /// Returns true if the `distribution` is part of a conda package.
+    pub fn is_conda_managed(&self, distribution: &Distribution) -> bool {
+        self.conda_dist_infos.contains(&distribution.dist_info)
+    }
+
+    /// Returns the [`InstallPaths`] relative to the prefix of the environment.
+    pub fn install_paths(&self) -> &InstallPaths {
+        &self.install_paths
+    }
+
+    /// Returns the prefix of the environment.
+    pub fn root(&self) -> &Path {
+        &self.prefix
+    }
+
+    /// Path to the python executable of the environment.
+    pub fn python_executable(&self) -> PathBuf {
+        python_executable(&self.prefix, self.install_paths.is_windows())
+    }
+}
+
+/// Returns the location of the python executable in a conda prefix.
+fn python_executable(prefix: &Path, windows: bool) -> PathBuf {
+    if windows {
+        prefix.join("python.exe")
+    } else {
+        prefix.join("bin").join("python")
+    }
+}
+
+/// Reads all the package records from a `conda-meta` directory.
+fn read_conda_meta(conda_meta: &Path) -> Result<Vec<CondaPackageRecord>, CondaEnvironmentError> {
+    let entries = fs::read_dir(conda_meta)
+        .map_err(|e| CondaEnvironmentError::FailedToReadRecord(conda_meta.to_path_buf(), e))?;
+
+    let mut records = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| CondaEnvironmentError::FailedToReadRecord(conda_meta.to_path_buf(), e))?
+            .path();
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| CondaEnvironmentError::FailedToReadRecord(path.clone(), e))?;
+        let record = serde_json::from_str(&contents)
+            .map_err(|e| CondaEnvironmentError::FailedToParseRecord(path, e))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write_dist_info(prefix: &Path, site_packages: &Path, name: &str, version: &str) {
+        let dist_info = prefix
+            .join(site_packages)
+            .join(format!("{name}-{version}.dist-info"));
+        fs::create_dir_all(&dist_info).unwrap();
+        fs::write(
+            dist_info.join("METADATA"),
+            format!("Metadata-Version: 2.1\nName: {name}\nVersion: {version}\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_conda_environment() {
+        let prefix = tempfile::tempdir().unwrap();
+        let prefix = prefix.path();
+        let site_packages = InstallPaths::for_venv((3, 11, 4), cfg!(windows))
+            .site_packages()
+            .to_path_buf();
+        let site_packages_str = site_packages.display().to_string().replace('\\', "/");
+
+        fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+        fs::write(
+            prefix.join("conda-meta/python-3.11.4-h955ad1f_0.json"),
+            r#"{"name": "python", "version": "3.11.4", "files": ["bin/python3.11"]}"#,
+        )
+        .unwrap();
+        fs::write(
+            prefix.join("conda-meta/numpy-1.26.0-py311h08b1b3b_0.json"),
+            format!(
+                r#"{{"name": "numpy", "version": "1.26.0", "files": ["{site_packages_str}/numpy-1.26.0.dist-info/METADATA"]}}"#
+            ),
+        )
+        .unwrap();
+        fs::write(prefix.join("conda-meta/history"), "").unwrap();
+
+        // numpy is installed by conda, requests from a wheel
+        write_dist_info(prefix, &site_packages, "numpy", "1.26.0");
+        write_dist_info(prefix, &site_packages, "requests", "2.31.0");
+
+        assert!(CondaEnvironment::is_conda_environment(prefix));
+        let env = CondaEnvironment::from_prefix(prefix).unwrap();
+        assert_eq!(env.install_paths().site_packages(), site_packages);
+
+        let installed = env.installed_distributions().unwrap();
+        assert_eq!(installed.len(), 2);
+
+        let conda_managed = env.conda_managed_distributions().unwrap();
+        assert_eq!(conda_managed.len(), 1);
+        assert_eq!(conda_managed[0].name.as_str(), "numpy");
+    }
+
+    #[test]
+    fn test_not_a_conda_environment() {
+        let prefix = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            CondaEnvironment::from_prefix(prefix.path()),
+            Err(CondaEnvironmentError::NotACondaEnvironment(_))
+        ));
+
+        fs::create_dir_all(prefix.path().join("conda-meta")).unwrap();
+        assert!(matches!(
+            CondaEnvironment::from_prefix(prefix.path()),
+            Err(CondaEnvironmentError::PythonNotInstalled(_))
+        ));
+    }
+}
@@ -6,7 +6,10 @@
 
 use crate::artifacts::wheel::InstallPaths;
 use crate::python_env::WheelTag;
-use crate::{types::NormalizedPackageName, types::PackageName, types::RFC822ish};
+use crate::{
+    types::DirectUrlJson, types::DirectUrlSource, types::NormalizedPackageName, types::PackageName,
+    types::RFC822ish,
+};
 use fs_err as fs;
 use indexmap::IndexSet;
 use itertools::Itertools;
@@ -15,6 +18,7 @@ use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
+    process::{Command, ExitStatus},
     str::FromStr,
 };
 use thiserror::Error;
@@ -28,15 +32,26 @@ pub struct Distribution {
     /// The version of the distribution
     pub version: Version,
 
-    /// The installer that was responsible for installing the distribution
+    /// The installer that was responsible for installing the distribution as recorded in its
+    /// `INSTALLER` file.
     pub installer: Option<String>,
 
+    /// True if the distribution was explicitly requested by the user instead of being installed
+    /// as a dependency of another distribution, which is indicated by a `REQUESTED` file. See
+    /// [PEP 376](https://peps.python.org/pep-0376/#requested).
+    #[serde(default)]
+    pub requested: bool,
+
     /// The path to the .dist-info directory relative to the root of the environment.
     pub dist_info: PathBuf,
 
     /// The specific tags of the distribution that was installed or `None` if this information
     /// could not be retrieved.
     pub tags: Option<IndexSet<WheelTag>>,
+
+    /// The contents of the `direct_url.json` file if the distribution was installed from a direct
+    /// url (e.g. a VCS, an archive url or a local directory).
+    pub direct_url: Option<DirectUrlJson>,
 }
 
 /// An error that can occur when running `find_distributions_in_venv`.
@@ -53,6 +68,38 @@ pub enum FindDistributionError {
     /// Failed to parse WHEEL tags
     #[error("failed to parse wheel tag {0}")]
     FailedToParseWheelTag(String),
+
+    /// Failed to parse a `direct_url.json` file
+    #[error("failed to parse '{0}'")]
+    FailedToParseDirectUrlJson(PathBuf, #[source] serde_json::Error),
+
+    /// Failed to execute the python interpreter
+    #[error("failed to execute {0}")]
+    FailedToExecutePython(PathBuf, #[source] std::io::Error),
+
+    /// The python interpreter exited with an error
+    #[error("{0} failed with exit code {1}")]
+    PythonFailed(PathBuf, ExitStatus),
+
+    /// Failed to parse the site-packages directories reported by the python interpreter
+    #[error("failed to parse the site-packages directories of {0}")]
+    FailedToParseSitePackages(PathBuf, #[source] serde_json::Error),
+}
+
+impl Distribution {
+    /// Returns true if the distribution was installed in editable mode from a local directory,
+    /// as recorded in its `direct_url.json` file.
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self.direct_url,
+            Some(DirectUrlJson {
+                source: DirectUrlSource::Dir {
+                    editable: Some(true)
+                },
+                ..
+            })
+        )
+    }
 }
 
 /// Locates the python distributions (packages) that have been installed in the specified directory.
@@ -112,6 +159,46 @@ pub fn find_distributions_in_venv(
     Ok(results)
 }
 
+/// Locates the python distributions (packages) that are visible to the python interpreter at
+/// `python`. This includes the distributions in the user site-packages directory if the
+/// interpreter has the user site enabled.
+///
+/// The interpreter is executed to determine its site-packages directories. Because the
+/// distributions can be located in unrelated directories, the `dist_info` path of the results is
+/// absolute.
+pub fn find_distributions_in_python(
+    python: &Path,
+) -> Result<Vec<Distribution>, FindDistributionError> {
+    let output = Command::new(python)
+        .arg("-c")
+        .arg(include_str!("site_packages.py"))
+        .output()
+        .map_err(|e| FindDistributionError::FailedToExecutePython(python.to_path_buf(), e))?;
+    if !output.status.success() {
+        return Err(FindDistributionError::PythonFailed(
+            python.to_path_buf(),
+            output.status,
+        ));
+    }
+
+    let locations: Vec<PathBuf> = serde_json::from_slice(&output.stdout)
+        .map_err(|e| FindDistributionError::FailedToParseSitePackages(python.to_path_buf(), e))?;
+
+    let mut results = Vec::new();
+    for dir in locations.into_iter().unique().filter(|p| p.is_dir()) {
+        results.extend(
+            find_distributions_in_directory(&dir)?
+                .into_iter()
+                .map(|dist| Distribution {
+                    dist_info: dir.join(dist.dist_info),
+                    ..dist
+                }),
+        );
+    }
+
+    Ok(results)
+}
+
 /// Analyzes a `.dist-info` directory to see if it actually contains a python distribution (package).
 fn analyze_distribution(
     dist_info_path: PathBuf,
@@ -149,6 +236,9 @@ fn analyze_distribution(
         .map(|i| i.trim().to_owned())
         .ok();
 
+    // The mere presence of the REQUESTED file indicates that the distribution was requested
+    let requested = dist_info_path.join("REQUESTED").is_file();
+
     // Check if there is a WHEEL file from where we can read tags
     let wheel_path = dist_info_path.join("WHEEL");
     let tags = if wheel_path.is_file() {
@@ -170,12 +260,22 @@ fn analyze_distribution(
         None
     };
 
+    // Check if the distribution was installed from a direct url
+    let direct_url = DirectUrlJson::from_dist_info(&dist_info_path).map_err(|e| {
+        FindDistributionError::FailedToParseDirectUrlJson(
+            dist_info_path.join(DirectUrlJson::FILE_NAME),
+            e,
+        )
+    })?;
+
     Ok(Some(Distribution {
         dist_info: dist_info_path,
         name: name.into(),
         version,
         installer,
+        requested,
         tags,
+        direct_url,
     }))
 }
 
@@ -201,5 +301,20 @@ mod test {
                 value.as_str().unwrap().replace('\\', "/")
             }),
         });
+
+        assert!(distributions.iter().all(|dist| !dist.is_editable()));
+    }
+
+    #[test]
+    fn test_find_distributions_in_python() {
+        let Ok(python) = crate::python_env::system_python_executable() else {
+            // This is fine, the test machine does not include a python binary.
+            return;
+        };
+
+        for distribution in find_distributions_in_python(python).unwrap() {
+            assert!(distribution.dist_info.is_absolute());
+            assert!(distribution.dist_info.join("METADATA").is_file());
+        }
     }
 }
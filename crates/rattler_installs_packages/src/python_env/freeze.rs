@@ -0,0 +1,145 @@
+//! Functionality to convert the distributions installed in an environment into pinned
+//! requirements, similar to `pip freeze`.
+
+use crate::python_env::Distribution;
+use crate::types::{
+    DirectUrlJson, DirectUrlSource, DirectUrlVcs, NormalizedPackageName, PackageName, Requirement,
+    RequirementBuilder, RequirementBuilderError,
+};
+use miette::Diagnostic;
+use thiserror::Error;
+use url::Url;
+
+/// An error that can occur when freezing distributions with [`freeze_distributions`].
+#[derive(Debug, Error, Diagnostic)]
+#[allow(missing_docs)]
+pub enum FreezeError {
+    #[error("the direct url of '{0}' is invalid")]
+    InvalidDirectUrl(NormalizedPackageName, #[source] url::ParseError),
+
+    #[error(transparent)]
+    FailedToBuildRequirement(#[from] RequirementBuilderError),
+}
+
+/// Converts the `distributions` into requirements that pin them exactly. Distributions that were
+/// installed from an index are pinned with `name==version`, distributions that were installed
+/// from a direct url are pinned with `name @ url`, where the url is reconstructed from their
+/// `direct_url.json` file. VCS urls are pinned to the commit that was installed.
+///
+/// The requirements are sorted by name so the output is stable and can be used as input for the
+/// resolver or written to a requirements file with [`crate::types::format_requirement`].
+pub fn freeze_distributions(
+    distributions: &[Distribution],
+) -> Result<Vec<Requirement>, FreezeError> {
+    let mut distributions = distributions.iter().collect::<Vec<_>>();
+    distributions.sort_by(|a, b| a.name.cmp(&b.name));
+
+    distributions
+        .into_iter()
+        .map(|distribution| {
+            let builder = RequirementBuilder::new(PackageName::from(distribution.name.clone()));
+            let builder = match &distribution.direct_url {
+                Some(direct_url) => builder
+                    .with_url(requirement_url(direct_url).map_err(|e| {
+                        FreezeError::InvalidDirectUrl(distribution.name.clone(), e)
+                    })?),
+                None => builder.with_exact_version(distribution.version.clone()),
+            };
+            Ok(builder.build()?)
+        })
+        .collect()
+}
+
+/// Reconstructs the url of a PEP 508 requirement from the contents of a `direct_url.json` file.
+fn requirement_url(direct_url: &DirectUrlJson) -> Result<Url, url::ParseError> {
+    let mut url = match &direct_url.source {
+        DirectUrlSource::Vcs { vcs, commit_id, .. } => {
+            let vcs = match vcs {
+                DirectUrlVcs::Git => "git",
+                DirectUrlVcs::Svn => "svn",
+                DirectUrlVcs::Bazaar => "bzr",
+                DirectUrlVcs::Mercurial => "hg",
+            };
+            let mut url = direct_url.url.clone();
+            url.set_fragment(None);
+            Url::parse(&format!("{vcs}+{url}@{commit_id}"))?
+        }
+        DirectUrlSource::Archive { .. } | DirectUrlSource::Dir { .. } => direct_url.url.clone(),
+    };
+
+    if let Some(subdirectory) = &direct_url.subdirectory {
+        url.set_fragment(Some(&format!("subdirectory={subdirectory}")));
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::InstallPaths;
+    use crate::python_env::find_distributions_in_venv;
+    use crate::types::format_requirement;
+    use std::path::Path;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_freeze_venv() {
+        let venv_path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/find_distributions/");
+        let install_paths = InstallPaths::for_venv((3, 8, 5), true);
+        let distributions = find_distributions_in_venv(&venv_path, &install_paths).unwrap();
+
+        let requirements = freeze_distributions(&distributions)
+            .unwrap()
+            .into_iter()
+            .map(|requirement| format_requirement(&requirement))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requirements,
+            [
+                "click==7.1.2",
+                "flask==1.1.4",
+                "itsdangerous==1.1.0",
+                "jinja2==2.11.3",
+                "markupsafe==1.1.1",
+                "pip==9.0.1",
+                "setuptools==28.8.0",
+                "werkzeug==1.0.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requirement_url() {
+        let git = DirectUrlJson {
+            url: Url::parse("https://github.com/pallets/flask.git").unwrap(),
+            subdirectory: Some(String::from("src")),
+            source: DirectUrlSource::Vcs {
+                vcs: DirectUrlVcs::Git,
+                requested_revision: Some(String::from("main")),
+                commit_id: String::from("1f6e4b6b8f0c6c4c3e6d2b2b4f3b0c8c0a1e2d3f"),
+            },
+        };
+        assert_eq!(
+            requirement_url(&git).unwrap().as_str(),
+            "git+https://github.com/pallets/flask.git@1f6e4b6b8f0c6c4c3e6d2b2b4f3b0c8c0a1e2d3f#subdirectory=src"
+        );
+
+        let dir = DirectUrlJson {
+            url: Url::parse("file:///home/user/project").unwrap(),
+            subdirectory: None,
+            source: DirectUrlSource::Dir {
+                editable: Some(true),
+            },
+        };
+        let requirement = RequirementBuilder::new(PackageName::from_str("project").unwrap())
+            .with_url(requirement_url(&dir).unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            requirement.to_string(),
+            "project @ file:///home/user/project"
+        );
+    }
+}
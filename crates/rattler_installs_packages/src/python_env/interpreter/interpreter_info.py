@@ -0,0 +1,14 @@
+# A program that outputs information about the interpreter that executes it in a JSON format.
+
+import json
+import sys
+import sysconfig
+
+information = {
+    'implementation': sys.implementation.name,
+    'version': [sys.version_info.major, sys.version_info.minor, sys.version_info.micro],
+    'executable': sys.executable,
+    'free_threaded': bool(sysconfig.get_config_var('Py_GIL_DISABLED')),
+}
+
+json.dump(information, sys.stdout)
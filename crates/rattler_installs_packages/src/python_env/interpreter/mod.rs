@@ -0,0 +1,339 @@
+//! Discovery and selection of the python interpreters that are available on the system.
+use crate::python_env::{PythonInterpreterVersion, PythonLocation};
+use crate::types::{Version, VersionSpecifiers};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The implementation of a python interpreter.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Implementation {
+    /// Any implementation
+    Any,
+    /// The reference implementation of python
+    CPython,
+    /// PyPy, see <https://pypy.org>
+    PyPy,
+    /// GraalPy, see <https://www.graalvm.org/python>
+    GraalPy,
+}
+
+impl Implementation {
+    /// Returns true if an interpreter with the given `sys.implementation.name` matches this
+    /// implementation.
+    fn matches(self, name: &str) -> bool {
+        match self {
+            Implementation::Any => true,
+            Implementation::CPython => name == "cpython",
+            Implementation::PyPy => name == "pypy",
+            Implementation::GraalPy => name == "graalpy",
+        }
+    }
+}
+
+impl Display for Implementation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Implementation::Any => "any",
+            Implementation::CPython => "cpython",
+            Implementation::PyPy => "pypy",
+            Implementation::GraalPy => "graalpy",
+        })
+    }
+}
+
+/// An error that can occur when parsing an [`Implementation`].
+#[derive(Debug, Clone, Error)]
+#[error("unknown python implementation '{0}'")]
+pub struct ParseImplementationError(String);
+
+impl FromStr for Implementation {
+    type Err = ParseImplementationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "any" => Ok(Implementation::Any),
+            "cpython" | "cp" => Ok(Implementation::CPython),
+            "pypy" | "pp" => Ok(Implementation::PyPy),
+            "graalpy" | "gp" => Ok(Implementation::GraalPy),
+            _ => Err(ParseImplementationError(s.to_owned())),
+        }
+    }
+}
+
+/// A python interpreter that was found on the system.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PythonInterpreter {
+    /// The path to the executable of the interpreter as reported by `sys.executable`.
+    pub path: PathBuf,
+
+    /// The version of the interpreter
+    pub version: PythonInterpreterVersion,
+
+    /// The name of the implementation as reported by `sys.implementation.name`, e.g. `cpython`.
+    pub implementation: String,
+
+    /// True if this is a free-threaded build of CPython that runs without the global interpreter
+    /// lock, e.g. `python3.13t`. Extension modules must be built specifically for these builds.
+    pub free_threaded: bool,
+}
+
+impl PythonInterpreter {
+    /// Queries the interpreter at `path` for its version and implementation. Returns `None` if the
+    /// executable could not be executed or is not a python interpreter.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct InterpreterInfo {
+            implementation: String,
+            version: (u32, u32, u32),
+            executable: PathBuf,
+            #[serde(default)]
+            free_threaded: bool,
+        }
+
+        let output = Command::new(path)
+            .arg("-c")
+            .arg(include_str!("interpreter_info.py"))
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let info: InterpreterInfo = serde_json::from_slice(&output.stdout).ok()?;
+        Some(Self {
+            // sys.executable can be empty if the interpreter cannot determine its location
+            path: if info.executable.as_os_str().is_empty() {
+                path.to_path_buf()
+            } else {
+                info.executable
+            },
+            version: info.version.into(),
+            implementation: info.implementation,
+            free_threaded: info.free_threaded,
+        })
+    }
+
+    /// Returns the version of the interpreter as a PEP 440 version.
+    pub fn pep440_version(&self) -> Version {
+        Version::from_str(&format!(
+            "{}.{}.{}",
+            self.version.major, self.version.minor, self.version.patch
+        ))
+        .expect("a python interpreter version is always a valid version")
+    }
+}
+
+impl From<PythonInterpreter> for PythonLocation {
+    fn from(interpreter: PythonInterpreter) -> Self {
+        PythonLocation::CustomWithVersion(interpreter.path, interpreter.version)
+    }
+}
+
+/// An error that can occur when selecting an interpreter with [`find_interpreter`].
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum FindInterpreterError {
+    #[error("could not find a {implementation} interpreter that matches '{specifiers}'")]
+    NoMatchingInterpreter {
+        specifiers: VersionSpecifiers,
+        implementation: Implementation,
+    },
+}
+
+/// Matches the names of python executables, e.g. `python`, `python3.11` or `pypy3.exe`.
+static INTERPRETER_NAME_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(python|pypy|graalpy)(\d+(\.\d+)?t?)?(\.exe)?$").expect("invalid regex")
+});
+
+/// Finds all python interpreters in the directories of the `PATH` environment variable. Every
+/// interpreter is only returned once, even if it can be found through multiple names or
+/// directories. Interpreters are returned in the order in which they appear on the `PATH`.
+pub fn find_interpreters() -> Vec<PythonInterpreter> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    find_interpreters_in(std::env::split_paths(&path))
+}
+
+/// Finds all python interpreters in the specified directories. See [`find_interpreters`].
+pub fn find_interpreters_in(
+    directories: impl IntoIterator<Item = PathBuf>,
+) -> Vec<PythonInterpreter> {
+    let candidates = directories
+        .into_iter()
+        .filter_map(|dir| dir.read_dir().ok())
+        .flat_map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| INTERPRETER_NAME_REGEX.is_match(name))
+                })
+                .map(|entry| entry.path())
+                // Sort within a directory to make the result deterministic
+                .sorted()
+        })
+        .filter(|path| path.is_file())
+        .unique_by(|path| dunce::canonicalize(path).unwrap_or_else(|_| path.clone()));
+
+    let mut seen = HashSet::new();
+    candidates
+        .filter_map(|path| PythonInterpreter::from_path(&path))
+        .filter(|interpreter| {
+            seen.insert(
+                dunce::canonicalize(&interpreter.path).unwrap_or_else(|_| interpreter.path.clone()),
+            )
+        })
+        .collect()
+}
+
+/// Selects the best interpreter from the interpreters on the `PATH` that matches the
+/// `specifiers` and the `implementation`. The best interpreter is the one with the highest
+/// version, if multiple interpreters have the same version a regular build is preferred over a
+/// free-threaded build and otherwise the one that comes first on the `PATH` is selected.
+///
+/// ```no_run
+/// # use std::str::FromStr;
+/// # use rattler_installs_packages::python_env::{find_interpreter, Implementation};
+/// # use rattler_installs_packages::types::VersionSpecifiers;
+/// let interpreter = find_interpreter(
+///     &VersionSpecifiers::from_str(">=3.10,<3.13").unwrap(),
+///     Implementation::CPython,
+/// )
+/// .unwrap();
+/// ```
+pub fn find_interpreter(
+    specifiers: &VersionSpecifiers,
+    implementation: Implementation,
+) -> Result<PythonInterpreter, FindInterpreterError> {
+    select_interpreter(find_interpreters(), specifiers, implementation).ok_or_else(|| {
+        FindInterpreterError::NoMatchingInterpreter {
+            specifiers: specifiers.clone(),
+            implementation,
+        }
+    })
+}
+
+/// Selects the best matching interpreter from `interpreters`. See [`find_interpreter`].
+fn select_interpreter(
+    interpreters: Vec<PythonInterpreter>,
+    specifiers: &VersionSpecifiers,
+    implementation: Implementation,
+) -> Option<PythonInterpreter> {
+    interpreters
+        .into_iter()
+        .filter(|interpreter| implementation.matches(&interpreter.implementation))
+        .filter(|interpreter| specifiers.contains(&interpreter.pep440_version()))
+        // `max_by_key` returns the last maximum element, reverse to prefer the first one.
+        .rev()
+        .max_by_key(|interpreter| {
+            let version = &interpreter.version;
+            (
+                version.major,
+                version.minor,
+                version.patch,
+                !interpreter.free_threaded,
+            )
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn interpreter(
+        path: &str,
+        version: (u32, u32, u32),
+        implementation: &str,
+    ) -> PythonInterpreter {
+        PythonInterpreter {
+            path: PathBuf::from(path),
+            version: version.into(),
+            implementation: implementation.to_owned(),
+            free_threaded: path.ends_with('t'),
+        }
+    }
+
+    #[test]
+    fn test_select_interpreter() {
+        let interpreters = vec![
+            interpreter("/usr/bin/python3.9", (3, 9, 18), "cpython"),
+            interpreter("/usr/bin/python3.12", (3, 12, 1), "cpython"),
+            interpreter("/opt/bin/python3.12", (3, 12, 1), "cpython"),
+            interpreter("/usr/bin/python3.13t", (3, 13, 0), "cpython"),
+            interpreter("/usr/bin/python3.13", (3, 13, 0), "cpython"),
+            interpreter("/usr/bin/pypy3", (3, 10, 13), "pypy"),
+        ];
+        let select = |specifiers: &str, implementation| {
+            select_interpreter(
+                interpreters.clone(),
+                &VersionSpecifiers::from_str(specifiers).unwrap(),
+                implementation,
+            )
+            .map(|interpreter| interpreter.path)
+        };
+
+        assert_eq!(
+            select(">=3.10,<3.13", Implementation::CPython),
+            Some(PathBuf::from("/usr/bin/python3.12"))
+        );
+        assert_eq!(
+            select(">=3.10,<3.12", Implementation::Any),
+            Some(PathBuf::from("/usr/bin/pypy3"))
+        );
+        assert_eq!(
+            select(">=3.10,<3.12", Implementation::PyPy),
+            Some(PathBuf::from("/usr/bin/pypy3"))
+        );
+        assert_eq!(select("<3.8", Implementation::CPython), None);
+
+        // Regular builds are preferred over free-threaded builds of the same version
+        assert_eq!(
+            select(">=3.13", Implementation::CPython),
+            Some(PathBuf::from("/usr/bin/python3.13"))
+        );
+    }
+
+    #[test]
+    fn test_interpreter_name_regex() {
+        for name in [
+            "python",
+            "python3",
+            "python3.12",
+            "python3.13t",
+            "pypy3.10",
+            "graalpy",
+            "python.exe",
+        ] {
+            assert!(INTERPRETER_NAME_REGEX.is_match(name), "{name}");
+        }
+        for name in ["python3-config", "python3.12-gdb.py", "pythonw", "ipython"] {
+            assert!(!INTERPRETER_NAME_REGEX.is_match(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_find_interpreters() {
+        // Every interpreter that was found must match its own version.
+        let interpreters = find_interpreters();
+        for interpreter in &interpreters {
+            let specifiers =
+                VersionSpecifiers::from_str(&format!("=={}", interpreter.pep440_version()))
+                    .unwrap();
+            assert!(
+                select_interpreter(interpreters.clone(), &specifiers, Implementation::Any)
+                    .is_some()
+            );
+        }
+    }
+}
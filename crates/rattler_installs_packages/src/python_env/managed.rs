@@ -0,0 +1,473 @@
+//! Downloading and caching standalone python builds.
+//!
+//! The builds of the [python-build-standalone](https://github.com/indygreg/python-build-standalone)
+//! project are self-contained CPython distributions that can be unpacked anywhere. Using them
+//! allows creating virtual environments and building wheels on machines without a python
+//! interpreter.
+
+use crate::python_env::{PythonInterpreter, PythonInterpreterVersion};
+use crate::types::{Version, VersionSpecifiers};
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use fs_err as fs;
+use rattler_digest::Sha256;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest_middleware::ClientWithMiddleware;
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tar::Archive;
+use thiserror::Error;
+use url::Url;
+
+/// The GitHub API endpoint that describes the latest release of python-build-standalone.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/indygreg/python-build-standalone/releases/latest";
+
+/// An error that can occur when downloading a managed python interpreter.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ManagedPythonError {
+    #[error("standalone python builds are not available for this platform")]
+    UnsupportedPlatform,
+
+    #[error("could not find a standalone python build that matches '{0}'")]
+    NoMatchingDownload(VersionSpecifiers),
+
+    #[error("failed to download {0}")]
+    DownloadFailed(Url, #[source] reqwest_middleware::Error),
+
+    #[error("cannot download {0} while offline")]
+    Offline(Url),
+
+    #[error("failed to parse the release information of python-build-standalone")]
+    InvalidReleaseInfo(#[source] reqwest::Error),
+
+    #[error("the sha256 hash of {0} does not match, expected {1} but got {2}")]
+    HashMismatch(Box<Url>, String, String),
+
+    #[error("the unpacked python distribution at {0} does not contain an interpreter")]
+    MissingInterpreter(PathBuf),
+
+    #[error("io error while accessing {0}")]
+    IoError(PathBuf, #[source] io::Error),
+}
+
+impl From<reqwest::Error> for ManagedPythonError {
+    fn from(e: reqwest::Error) -> Self {
+        let url = e
+            .url()
+            .cloned()
+            .unwrap_or_else(|| Url::parse(LATEST_RELEASE_URL).expect("invalid url"));
+        Self::DownloadFailed(url, e.into())
+    }
+}
+
+/// A standalone python build that can be downloaded.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PythonDownload {
+    /// The version of python
+    pub version: PythonInterpreterVersion,
+
+    /// The release of python-build-standalone that contains the build, e.g. `20240107`
+    pub release: String,
+
+    /// The target triple the build is made for, e.g. `x86_64-unknown-linux-gnu`
+    pub triple: String,
+
+    /// The location of the archive
+    pub url: Url,
+
+    /// The location of a file that contains the sha256 hash of the archive, if available
+    pub sha256_url: Option<Url>,
+}
+
+impl PythonDownload {
+    /// Parses the information of a download from the url of an `install_only` archive, e.g.
+    /// `.../cpython-3.12.1+20240107-x86_64-unknown-linux-gnu-install_only.tar.gz`. Returns `None`
+    /// if the url does not refer to such an archive.
+    pub fn from_url(url: Url) -> Option<Self> {
+        let file_name = url.path_segments()?.last()?;
+        let file_name = percent_decode(file_name);
+        let rest = file_name
+            .strip_prefix("cpython-")?
+            .strip_suffix("-install_only.tar.gz")?;
+        let (version, rest) = rest.split_once('+')?;
+        let (release, triple) = rest.split_once('-')?;
+
+        let mut parts = version.split('.').map(u32::from_str);
+        let (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch)), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+
+        Some(Self {
+            version: PythonInterpreterVersion::new(major, minor, patch),
+            release: release.to_string(),
+            triple: triple.to_string(),
+            url,
+            sha256_url: None,
+        })
+    }
+
+    /// Returns the name of the directory in which the build is installed.
+    fn install_name(&self) -> String {
+        let PythonInterpreterVersion {
+            major,
+            minor,
+            patch,
+        } = self.version;
+        format!("cpython-{major}.{minor}.{patch}-{}", self.triple)
+    }
+
+    fn pep440_version(&self) -> Version {
+        python_version_to_pep440(&self.version)
+    }
+}
+
+/// Returns the target triple of the standalone python builds that run on the current platform.
+pub fn current_platform_triple() -> Option<&'static str> {
+    if cfg!(all(
+        target_os = "linux",
+        target_arch = "x86_64",
+        target_env = "gnu"
+    )) {
+        Some("x86_64-unknown-linux-gnu")
+    } else if cfg!(all(
+        target_os = "linux",
+        target_arch = "aarch64",
+        target_env = "gnu"
+    )) {
+        Some("aarch64-unknown-linux-gnu")
+    } else if cfg!(all(
+        target_os = "linux",
+        target_arch = "x86_64",
+        target_env = "musl"
+    )) {
+        Some("x86_64-unknown-linux-musl")
+    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+        Some("x86_64-apple-darwin")
+    } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+        Some("aarch64-apple-darwin")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
+        Some("x86_64-pc-windows-msvc-shared")
+    } else if cfg!(all(target_os = "windows", target_arch = "x86")) {
+        Some("i686-pc-windows-msvc-shared")
+    } else {
+        None
+    }
+}
+
+/// A directory that contains downloaded standalone python builds.
+#[derive(Debug, Clone)]
+pub struct ManagedPythons {
+    root: PathBuf,
+    client: ClientWithMiddleware,
+    offline: bool,
+}
+
+impl ManagedPythons {
+    /// Constructs a new instance that stores the python builds in `root` and downloads them with
+    /// `client`.
+    pub fn new(root: impl Into<PathBuf>, client: ClientWithMiddleware) -> Self {
+        Self {
+            root: root.into(),
+            client,
+            offline: false,
+        }
+    }
+
+    /// Only use the python builds that have been downloaded before, downloading a build fails
+    /// with [`ManagedPythonError::Offline`].
+    pub fn with_offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Returns the directory that contains the python builds.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the interpreters that have been downloaded before.
+    pub fn installed(&self) -> Vec<PythonInterpreter> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let (version, _) = name.to_str()?.strip_prefix("cpython-")?.split_once('-')?;
+                let mut parts = version.split('.').map(u32::from_str);
+                let (Some(Ok(major)), Some(Ok(minor)), Some(Ok(patch))) =
+                    (parts.next(), parts.next(), parts.next())
+                else {
+                    return None;
+                };
+                let path = interpreter_path(&entry.path());
+                path.is_file().then(|| PythonInterpreter {
+                    path,
+                    version: PythonInterpreterVersion::new(major, minor, patch),
+                    implementation: String::from("cpython"),
+                    free_threaded: false,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the newest interpreter that has been downloaded before and matches `specifiers`.
+    pub fn find_installed(&self, specifiers: &VersionSpecifiers) -> Option<PythonInterpreter> {
+        self.installed()
+            .into_iter()
+            .filter(|interpreter| specifiers.contains(&interpreter.pep440_version()))
+            .max_by(|a, b| a.pep440_version().cmp(&b.pep440_version()))
+    }
+
+    /// Returns the python builds for the current platform that are part of the latest release of
+    /// python-build-standalone.
+    pub async fn available_downloads(&self) -> Result<Vec<PythonDownload>, ManagedPythonError> {
+        #[derive(Deserialize)]
+        struct Release {
+            assets: Vec<Asset>,
+        }
+
+        #[derive(Deserialize)]
+        struct Asset {
+            browser_download_url: Url,
+        }
+
+        let url = Url::parse(LATEST_RELEASE_URL).expect("invalid url");
+        if self.offline {
+            return Err(ManagedPythonError::Offline(url));
+        }
+        let triple = current_platform_triple().ok_or(ManagedPythonError::UnsupportedPlatform)?;
+        let release: Release = self
+            .client
+            .get(url.clone())
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "rip")
+            .send()
+            .await
+            .map_err(|e| ManagedPythonError::DownloadFailed(url, e))?
+            .error_for_status()?
+            .json()
+            .await
+            .map_err(ManagedPythonError::InvalidReleaseInfo)?;
+
+        let urls = release
+            .assets
+            .into_iter()
+            .map(|asset| asset.browser_download_url)
+            .collect::<Vec<_>>();
+        Ok(urls
+            .iter()
+            .cloned()
+            .filter_map(PythonDownload::from_url)
+            .filter(|download| download.triple == triple)
+            .map(|mut download| {
+                let sha256 = format!("{}.sha256", download.url);
+                download.sha256_url = urls.iter().find(|url| url.as_str() == sha256).cloned();
+                download
+            })
+            .collect())
+    }
+
+    /// Returns an interpreter that matches `specifiers`. A previously downloaded interpreter is
+    /// used if available, otherwise the newest matching build is downloaded.
+    pub async fn find_or_install(
+        &self,
+        specifiers: &VersionSpecifiers,
+    ) -> Result<PythonInterpreter, ManagedPythonError> {
+        if let Some(interpreter) = self.find_installed(specifiers) {
+            return Ok(interpreter);
+        }
+
+        let download = self
+            .available_downloads()
+            .await?
+            .into_iter()
+            .filter(|download| specifiers.contains(&download.pep440_version()))
+            .max_by(|a, b| a.pep440_version().cmp(&b.pep440_version()))
+            .ok_or_else(|| ManagedPythonError::NoMatchingDownload(specifiers.clone()))?;
+        self.install(&download).await
+    }
+
+    /// Downloads and unpacks the python build if it is not installed yet and returns its
+    /// interpreter.
+    pub async fn install(
+        &self,
+        download: &PythonDownload,
+    ) -> Result<PythonInterpreter, ManagedPythonError> {
+        let destination = self.root.join(download.install_name());
+        let interpreter = PythonInterpreter {
+            path: interpreter_path(&destination),
+            version: download.version.clone(),
+            implementation: String::from("cpython"),
+            free_threaded: false,
+        };
+        if interpreter.path.is_file() {
+            return Ok(interpreter);
+        }
+
+        tracing::info!("downloading python from {}", download.url);
+        let bytes = self.download(&download.url).await?;
+        if let Some(sha256_url) = &download.sha256_url {
+            let expected = self.download(sha256_url).await?;
+            let expected = String::from_utf8_lossy(&expected);
+            let expected = expected.split_whitespace().next().unwrap_or_default();
+            let actual = format!(
+                "{:x}",
+                rattler_digest::compute_bytes_digest::<Sha256>(&bytes)
+            );
+            if !expected.eq_ignore_ascii_case(&actual) {
+                return Err(ManagedPythonError::HashMismatch(
+                    Box::new(download.url.clone()),
+                    expected.to_string(),
+                    actual,
+                ));
+            }
+        }
+
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || unpack_archive(&bytes, &root, &destination))
+            .await
+            .map_err(|e| match e.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(_) => ManagedPythonError::IoError(
+                    self.root.clone(),
+                    io::Error::new(io::ErrorKind::Interrupted, "the task was cancelled"),
+                ),
+            })??;
+
+        if !interpreter.path.is_file() {
+            return Err(ManagedPythonError::MissingInterpreter(interpreter.path));
+        }
+        Ok(interpreter)
+    }
+
+    async fn download(&self, url: &Url) -> Result<Bytes, ManagedPythonError> {
+        if self.offline {
+            return Err(ManagedPythonError::Offline(url.clone()));
+        }
+        Ok(self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| ManagedPythonError::DownloadFailed(url.clone(), e))?
+            .error_for_status()?
+            .bytes()
+            .await?)
+    }
+}
+
+/// Unpacks an `install_only` archive, which contains a single `python` directory, to
+/// `destination`. The archive is unpacked into a temporary directory first so that a partially
+/// unpacked build is never used.
+fn unpack_archive(bytes: &[u8], root: &Path, destination: &Path) -> Result<(), ManagedPythonError> {
+    fs::create_dir_all(root).map_err(|e| ManagedPythonError::IoError(root.to_path_buf(), e))?;
+    let staging = tempfile::tempdir_in(root)
+        .map_err(|e| ManagedPythonError::IoError(root.to_path_buf(), e))?;
+    Archive::new(GzDecoder::new(bytes))
+        .unpack(staging.path())
+        .map_err(|e| ManagedPythonError::IoError(staging.path().to_path_buf(), e))?;
+
+    // Another process might have installed the same build concurrently
+    if let Err(err) = fs::rename(staging.path().join("python"), destination) {
+        if !destination.is_dir() {
+            return Err(ManagedPythonError::IoError(destination.to_path_buf(), err));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the location of the interpreter in an unpacked python build.
+fn interpreter_path(install_dir: &Path) -> PathBuf {
+    if cfg!(windows) {
+        install_dir.join("python.exe")
+    } else {
+        install_dir.join("bin/python3")
+    }
+}
+
+fn python_version_to_pep440(version: &PythonInterpreterVersion) -> Version {
+    Version::from_str(&format!(
+        "{}.{}.{}",
+        version.major, version.minor, version.patch
+    ))
+    .expect("a python interpreter version is always a valid version")
+}
+
+/// Decodes the `+` in the file names of the assets, which GitHub encodes as `%2B`.
+fn percent_decode(file_name: &str) -> String {
+    file_name.replace("%2B", "+").replace("%2b", "+")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_download_url() {
+        let url = Url::parse("https://github.com/indygreg/python-build-standalone/releases/download/20240107/cpython-3.12.1%2B20240107-x86_64-unknown-linux-gnu-install_only.tar.gz").unwrap();
+        let download = PythonDownload::from_url(url).unwrap();
+        assert_eq!(download.version, PythonInterpreterVersion::new(3, 12, 1));
+        assert_eq!(download.release, "20240107");
+        assert_eq!(download.triple, "x86_64-unknown-linux-gnu");
+        assert_eq!(
+            download.install_name(),
+            "cpython-3.12.1-x86_64-unknown-linux-gnu"
+        );
+
+        // Only the `install_only` archives are supported
+        assert!(PythonDownload::from_url(Url::parse("https://github.com/indygreg/python-build-standalone/releases/download/20240107/cpython-3.12.1%2B20240107-x86_64-unknown-linux-gnu-pgo%2Blto-full.tar.zst").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        let pythons = ManagedPythons::new(
+            dir.path(),
+            ClientWithMiddleware::from(reqwest::Client::new()),
+        );
+        assert!(pythons.installed().is_empty());
+
+        for version in ["3.11.7", "3.12.1"] {
+            let install_dir = dir
+                .path()
+                .join(format!("cpython-{version}-x86_64-unknown-linux-gnu"));
+            let interpreter = interpreter_path(&install_dir);
+            fs::create_dir_all(interpreter.parent().unwrap()).unwrap();
+            fs::write(interpreter, "").unwrap();
+        }
+
+        let found = pythons
+            .find_installed(&VersionSpecifiers::from_str(">=3.8").unwrap())
+            .unwrap();
+        assert_eq!(found.version, PythonInterpreterVersion::new(3, 12, 1));
+        let found = pythons
+            .find_installed(&VersionSpecifiers::from_str("<3.12").unwrap())
+            .unwrap();
+        assert_eq!(found.version, PythonInterpreterVersion::new(3, 11, 7));
+        assert!(pythons
+            .find_installed(&VersionSpecifiers::from_str(">=3.13").unwrap())
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let pythons = ManagedPythons::new(
+            dir.path(),
+            ClientWithMiddleware::from(reqwest::Client::new()),
+        )
+        .with_offline(true);
+        let err = pythons
+            .find_or_install(&VersionSpecifiers::from_str(">=3.8").unwrap())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ManagedPythonError::Offline(_)), "{err}");
+    }
+}
@@ -5,24 +5,49 @@ mod tags;
 
 mod distribution_finder;
 
+mod freeze;
+
 mod env_markers;
 
+mod interpreter;
+
 mod system_python;
 
+mod target_dir;
 mod uninstall;
+mod user_site;
 mod venv;
 
 mod byte_code_compiler;
 
-pub use tags::{WheelTag, WheelTags};
+mod conda;
+
+mod managed;
+
+mod pyodide;
+
+mod wasi;
+
+pub use tags::{PlatformTag, WheelTag, WheelTagTarget, WheelTags};
 
 pub use byte_code_compiler::{ByteCodeCompiler, CompilationError, SpawnCompilerError};
+pub use conda::{CondaEnvironment, CondaEnvironmentError};
 pub use distribution_finder::{
-    find_distributions_in_directory, find_distributions_in_venv, Distribution,
-    FindDistributionError,
+    find_distributions_in_directory, find_distributions_in_python, find_distributions_in_venv,
+    Distribution, FindDistributionError,
 };
 pub use env_markers::Pep508EnvMakers;
+pub use freeze::{freeze_distributions, FreezeError};
+pub use interpreter::{
+    find_interpreter, find_interpreters, find_interpreters_in, FindInterpreterError,
+    Implementation, ParseImplementationError, PythonInterpreter,
+};
+pub use managed::{current_platform_triple, ManagedPythonError, ManagedPythons, PythonDownload};
+pub use pyodide::{PyodideEnvironment, PyodideEnvironmentError};
 pub(crate) use system_python::{system_python_executable, FindPythonError};
 pub use system_python::{ParsePythonInterpreterVersionError, PythonInterpreterVersion};
+pub use target_dir::TargetDirectory;
 pub use uninstall::{uninstall_distribution, UninstallDistributionError};
-pub use venv::{PythonLocation, VEnv, VEnvError};
+pub use user_site::{UserSite, UserSiteError};
+pub use venv::{PythonLocation, VEnv, VEnvError, VEnvOptions};
+pub use wasi::WasiTarget;
@@ -0,0 +1,280 @@
+//! Installing wheels into [Pyodide](https://pyodide.org) environments.
+//!
+//! Pyodide is a distribution of CPython compiled to WebAssembly with emscripten. Pure python wheels
+//! can be installed into it as is, binary wheels have to be built for the exact emscripten version
+//! of the distribution. Packages that are part of the Pyodide distribution itself, like `numpy`,
+//! are described by its `pyodide-lock.json` file and must be loaded from the distribution instead
+//! of being installed from an index.
+//!
+//! The environment is a directory that mirrors the filesystem of the in-browser interpreter, i.e.
+//! packages are installed into `lib/pythonX.Y/site-packages` relative to it.
+use crate::artifacts::wheel::{InstallPaths, UnpackError, UnpackWheelOptions, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::python_env::{
+    find_distributions_in_venv, Distribution, FindDistributionError, Pep508EnvMakers, PlatformTag,
+    PythonInterpreterVersion, WheelTagTarget, WheelTags,
+};
+use crate::types::NormalizedPackageName;
+use fs_err as fs;
+use pep440_rs::Version;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// An error that can occur when opening a Pyodide environment.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum PyodideEnvironmentError {
+    #[error("failed to read {0}")]
+    FailedToReadLock(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    FailedToParseLock(PathBuf, #[source] serde_json::Error),
+
+    #[error("invalid python version '{0}' in the pyodide lock file")]
+    InvalidPythonVersion(String),
+
+    #[error("invalid platform '{0}' in the pyodide lock file")]
+    InvalidPlatform(String),
+}
+
+/// The parts of a `pyodide-lock.json` file that are relevant to us.
+#[derive(Debug, Deserialize)]
+struct PyodideLock {
+    info: PyodideLockInfo,
+    #[serde(default)]
+    packages: HashMap<String, PyodideLockPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyodideLockInfo {
+    arch: String,
+    platform: String,
+    python: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PyodideLockPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    package_type: Option<String>,
+}
+
+/// A Pyodide environment into which wheels can be installed.
+#[derive(Debug, Clone)]
+pub struct PyodideEnvironment {
+    /// The directory that represents the root of the filesystem of the interpreter
+    root: PathBuf,
+    /// Install paths relative to the root
+    install_paths: InstallPaths,
+    /// The version of python of the distribution
+    python_version: PythonInterpreterVersion,
+    /// The emscripten platform of the distribution, e.g. `emscripten_3_1_45_wasm32`
+    platform: PlatformTag,
+    /// The python packages that are part of the distribution
+    distribution_packages: HashMap<NormalizedPackageName, Version>,
+}
+
+impl PyodideEnvironment {
+    /// Opens a Pyodide environment at `root` for the distribution that is described by the
+    /// `pyodide-lock.json` file at `lock_file`.
+    pub fn from_lock_file(
+        root: impl Into<PathBuf>,
+        lock_file: &Path,
+    ) -> Result<Self, PyodideEnvironmentError> {
+        let contents = fs::read_to_string(lock_file)
+            .map_err(|e| PyodideEnvironmentError::FailedToReadLock(lock_file.to_path_buf(), e))?;
+        let lock: PyodideLock = serde_json::from_str(&contents)
+            .map_err(|e| PyodideEnvironmentError::FailedToParseLock(lock_file.to_path_buf(), e))?;
+
+        let python_version =
+            PythonInterpreterVersion::from_python_output(&format!("Python {}", lock.info.python))
+                .map_err(|_| PyodideEnvironmentError::InvalidPythonVersion(lock.info.python))?;
+        let tag = format!("{}_{}", lock.info.platform, lock.info.arch).replace(['.', '-'], "_");
+        let platform = match PlatformTag::from_str(&tag) {
+            Ok(platform @ PlatformTag::Emscripten { .. }) => platform,
+            _ => return Err(PyodideEnvironmentError::InvalidPlatform(tag)),
+        };
+
+        // Shared libraries and the standard library modules that are shipped separately are not
+        // python distributions.
+        let distribution_packages = lock
+            .packages
+            .into_values()
+            .filter(|package| {
+                package
+                    .package_type
+                    .as_deref()
+                    .map_or(true, |package_type| package_type == "package")
+            })
+            .filter_map(|package| {
+                let name = NormalizedPackageName::from_str(&package.name).ok()?;
+                let version = Version::from_str(&package.version).ok()?;
+                Some((name, version))
+            })
+            .collect();
+
+        Ok(Self {
+            root: root.into(),
+            install_paths: InstallPaths::for_venv(python_version.clone(), false),
+            python_version,
+            platform,
+            distribution_packages,
+        })
+    }
+
+    /// Install a wheel into this environment
+    pub fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        wheel.unpack(
+            &self.root,
+            &self.install_paths,
+            &self.python_executable(),
+            options,
+        )
+    }
+
+    /// Returns all the distributions that are installed in this environment. The `dist_info`
+    /// paths of the distributions are relative to [`PyodideEnvironment::root`].
+    pub fn installed_distributions(&self) -> Result<Vec<Distribution>, FindDistributionError> {
+        find_distributions_in_venv(&self.root, &self.install_paths)
+    }
+
+    /// Returns the python packages that are part of the Pyodide distribution together with their
+    /// versions. These cannot be installed from an index and should be treated as externally
+    /// managed, see [`crate::install::SyncPlanOptions::externally_managed`].
+    pub fn distribution_packages(&self) -> &HashMap<NormalizedPackageName, Version> {
+        &self.distribution_packages
+    }
+
+    /// Returns true if the package with the given name must be loaded from the Pyodide
+    /// distribution.
+    pub fn is_distribution_package(&self, name: &NormalizedPackageName) -> bool {
+        self.distribution_packages.contains_key(name)
+    }
+
+    /// Returns the description of the interpreter of the distribution that is used to compute the
+    /// compatible wheel tags.
+    pub fn wheel_tag_target(&self) -> WheelTagTarget {
+        WheelTagTarget::cpython(
+            (self.python_version.major, self.python_version.minor),
+            self.platform.clone(),
+        )
+    }
+
+    /// Returns the wheel tags that are compatible with the distribution.
+    pub fn wheel_tags(&self) -> WheelTags {
+        WheelTags::from_target(&self.wheel_tag_target())
+    }
+
+    /// Returns the environment markers of the in-browser interpreter.
+    pub fn env_markers(&self) -> Pep508EnvMakers {
+        let PythonInterpreterVersion {
+            major,
+            minor,
+            patch,
+        } = self.python_version;
+        let PlatformTag::Emscripten {
+            major: emscripten_major,
+            minor: emscripten_minor,
+            patch: emscripten_patch,
+            arch,
+        } = &self.platform
+        else {
+            unreachable!("the platform of a pyodide environment is always emscripten")
+        };
+
+        let full_version = format!("{major}.{minor}.{patch}");
+        pep508_rs::MarkerEnvironment {
+            implementation_name: String::from("cpython"),
+            implementation_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            os_name: String::from("posix"),
+            platform_machine: arch.clone(),
+            platform_python_implementation: String::from("CPython"),
+            platform_release: format!("{emscripten_major}.{emscripten_minor}.{emscripten_patch}"),
+            platform_system: String::from("Emscripten"),
+            platform_version: String::from("#1"),
+            python_full_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            python_version: format!("{major}.{minor}")
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            sys_platform: String::from("emscripten"),
+        }
+        .into()
+    }
+
+    /// Returns the [`InstallPaths`] relative to the root of the environment.
+    pub fn install_paths(&self) -> &InstallPaths {
+        &self.install_paths
+    }
+
+    /// Returns the root of the environment.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the python executable inside the in-browser filesystem. This is only referenced by
+    /// generated scripts, which cannot be executed in the browser anyway.
+    pub fn python_executable(&self) -> PathBuf {
+        PathBuf::from("/bin/python")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOCK: &str = r#"{
+        "info": {"arch": "wasm32", "platform": "emscripten_3_1_45", "version": "0.25.0", "python": "3.11.3"},
+        "packages": {
+            "numpy": {"name": "numpy", "version": "1.26.1", "file_name": "numpy-1.26.1-cp311-cp311-emscripten_3_1_45_wasm32.whl", "package_type": "package"},
+            "openssl": {"name": "openssl", "version": "1.1.1n", "file_name": "openssl-1.1.1n.zip", "package_type": "shared_library"}
+        }
+    }"#;
+
+    #[test]
+    fn test_pyodide_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock_file = dir.path().join("pyodide-lock.json");
+        fs::write(&lock_file, LOCK).unwrap();
+
+        let env = PyodideEnvironment::from_lock_file(dir.path().join("env"), &lock_file).unwrap();
+        assert_eq!(
+            env.install_paths().site_packages(),
+            Path::new("lib/python3.11/site-packages")
+        );
+        assert!(env.is_distribution_package(&NormalizedPackageName::from_str("numpy").unwrap()));
+        assert!(!env.is_distribution_package(&NormalizedPackageName::from_str("openssl").unwrap()));
+
+        let tags = env.wheel_tags();
+        let first = tags.tags().next().unwrap().to_string();
+        assert_eq!(first, "cp311-cp311-emscripten_3_1_45_wasm32");
+        assert!(tags.tags().any(|tag| tag.to_string() == "py3-none-any"));
+
+        let markers = env.env_markers();
+        assert_eq!(markers.sys_platform, "emscripten");
+        assert_eq!(markers.platform_machine, "wasm32");
+
+        // Pure python wheels can be installed
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+        env.install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+        assert_eq!(env.installed_distributions().unwrap().len(), 1);
+    }
+}
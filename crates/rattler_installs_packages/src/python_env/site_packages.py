@@ -0,0 +1,14 @@
+# A program that outputs the directories in which the interpreter looks for installed
+# distributions in a JSON format.
+
+import json
+import site
+import sys
+import sysconfig
+
+paths = sysconfig.get_paths()
+locations = [paths['purelib'], paths['platlib']]
+if site.ENABLE_USER_SITE:
+    locations.append(site.getusersitepackages())
+
+json.dump(locations, sys.stdout)
@@ -3,6 +3,11 @@
 //! running Python interpreter supports and determining if a wheel is compatible with a set of tags.
 
 mod from_env;
+mod platform_tag;
+mod target;
+
+pub use platform_tag::PlatformTag;
+pub use target::WheelTagTarget;
 
 use indexmap::IndexSet;
 use itertools::Itertools;
@@ -59,6 +64,12 @@ impl WheelTag {
             })
             .collect())
     }
+
+    /// Returns a typed representation of the platform part of this tag.
+    pub fn platform_tag(&self) -> PlatformTag {
+        PlatformTag::from_str(&self.platform)
+            .unwrap_or_else(|_| PlatformTag::Other(self.platform.clone()))
+    }
 }
 
 impl FromStr for WheelTag {
@@ -109,6 +120,22 @@ impl WheelTags {
     pub fn is_compatible(&self, tag: &WheelTag) -> bool {
         self.tags.contains(tag)
     }
+
+    /// Returns true if these are the tags of a free-threaded CPython interpreter, which only
+    /// supports extension modules that are built for its `cp3XXt` ABI.
+    pub fn is_free_threaded(&self) -> bool {
+        self.tags
+            .iter()
+            .any(|tag| tag.abi.starts_with("cp") && tag.abi.ends_with('t'))
+    }
+
+    /// Returns true if these are the tags of a WASI build of python, which cannot use native
+    /// wheels that were built for any other platform.
+    pub fn is_wasi(&self) -> bool {
+        self.tags
+            .iter()
+            .any(|tag| matches!(tag.platform_tag(), PlatformTag::Wasi { .. }))
+    }
 }
 
 impl FromIterator<WheelTag> for WheelTags {
@@ -0,0 +1,703 @@
+//! A typed model of the platform part of a wheel tag.
+//!
+//! The rules implemented here follow the ones in the `packaging` module:
+//! * manylinux: [PEP 600](https://peps.python.org/pep-0600/) and the legacy aliases from
+//!   [PEP 513](https://peps.python.org/pep-0513/), [PEP 571](https://peps.python.org/pep-0571/)
+//!   and [PEP 599](https://peps.python.org/pep-0599/).
+//! * musllinux: [PEP 656](https://peps.python.org/pep-0656/).
+//! * macOS: the logic from `packaging.tags.mac_platforms`.
+//! * emscripten: the tags used by [Pyodide](https://pyodide.org), e.g. `emscripten_3_1_45_wasm32`.
+//! * iOS: [PEP 730](https://peps.python.org/pep-0730/), e.g. `ios_17_0_arm64_iphoneos`.
+//! * Android: [PEP 738](https://peps.python.org/pep-0738/), e.g. `android_21_arm64_v8a`.
+//! * WASI: the tag of CPython builds for the WebAssembly System Interface, e.g. `wasm32_wasi`.
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The legacy manylinux tags and the glibc version they correspond to.
+const LEGACY_MANYLINUX: [(&str, (u16, u16)); 3] = [
+    ("manylinux2014", (2, 17)),
+    ("manylinux2010", (2, 12)),
+    ("manylinux1", (2, 5)),
+];
+
+/// The oldest iOS version that is supported by CPython.
+const OLDEST_IOS_MAJOR: u16 = 12;
+
+/// The oldest Android API level for which wheels are considered compatible.
+const OLDEST_ANDROID_API_LEVEL: u16 = 16;
+
+/// A parsed representation of the platform part of a [`super::WheelTag`], e.g.
+/// `manylinux_2_17_x86_64` or `macosx_11_0_universal2`.
+#[derive(Debug, Clone, Hash, Eq, PartialEq, SerializeDisplay, DeserializeFromStr)]
+pub enum PlatformTag {
+    /// The platform independent `any` tag.
+    Any,
+
+    /// A `manylinux_x_y_arch` tag that requires at least glibc `x.y`.
+    Manylinux {
+        /// The major version of glibc
+        major: u16,
+        /// The minor version of glibc
+        minor: u16,
+        /// The architecture, e.g. `x86_64`
+        arch: String,
+    },
+
+    /// A legacy manylinux tag like `manylinux2014_x86_64`. These are aliases for a specific glibc
+    /// version.
+    LegacyManylinux {
+        /// The name of the tag without the architecture, e.g. `manylinux2014`
+        name: &'static str,
+        /// The architecture, e.g. `x86_64`
+        arch: String,
+    },
+
+    /// A `musllinux_x_y_arch` tag that requires at least musl `x.y`.
+    Musllinux {
+        /// The major version of musl
+        major: u16,
+        /// The minor version of musl
+        minor: u16,
+        /// The architecture, e.g. `x86_64`
+        arch: String,
+    },
+
+    /// A plain `linux_arch` tag. These wheels are built for the system they were built on and are
+    /// not portable.
+    Linux {
+        /// The architecture, e.g. `x86_64`
+        arch: String,
+    },
+
+    /// A `macosx_x_y_format` tag that requires at least macOS `x.y`.
+    MacOs {
+        /// The major version of macOS
+        major: u16,
+        /// The minor version of macOS
+        minor: u16,
+        /// The binary format, e.g. `arm64`, `x86_64` or `universal2`
+        binary_format: String,
+    },
+
+    /// An `emscripten_x_y_z_arch` tag for WebAssembly environments like Pyodide. Binary wheels are
+    /// only compatible with the exact version of emscripten they were built with.
+    Emscripten {
+        /// The major version of emscripten
+        major: u16,
+        /// The minor version of emscripten
+        minor: u16,
+        /// The patch version of emscripten
+        patch: u16,
+        /// The architecture, e.g. `wasm32`
+        arch: String,
+    },
+
+    /// An `arch_wasi` tag for CPython builds that target the WebAssembly System Interface. There
+    /// is no versioning scheme for WASI binaries so only the exact tag is compatible.
+    Wasi {
+        /// The architecture, e.g. `wasm32`
+        arch: String,
+    },
+
+    /// An `ios_x_y_multiarch` tag that requires at least iOS `x.y`.
+    Ios {
+        /// The major version of iOS
+        major: u16,
+        /// The minor version of iOS
+        minor: u16,
+        /// The architecture and SDK, e.g. `arm64_iphoneos` or `x86_64_iphonesimulator`
+        multiarch: String,
+    },
+
+    /// An `android_level_abi` tag that requires at least the Android API level `level`.
+    Android {
+        /// The minimum API level
+        api_level: u16,
+        /// The Android ABI, e.g. `arm64_v8a` or `x86_64`
+        abi: String,
+    },
+
+    /// Any other platform tag like `win_amd64`.
+    Other(String),
+}
+
+impl PlatformTag {
+    /// Returns the glibc version required by this tag if this is a manylinux tag.
+    pub fn glibc_version(&self) -> Option<(u16, u16)> {
+        match self {
+            PlatformTag::Manylinux { major, minor, .. } => Some((*major, *minor)),
+            PlatformTag::LegacyManylinux { name, .. } => LEGACY_MANYLINUX
+                .iter()
+                .find(|(legacy, _)| legacy == name)
+                .map(|(_, version)| *version),
+            _ => None,
+        }
+    }
+
+    /// Returns the architecture of the tag if it can be determined.
+    pub fn arch(&self) -> Option<&str> {
+        match self {
+            PlatformTag::Manylinux { arch, .. }
+            | PlatformTag::LegacyManylinux { arch, .. }
+            | PlatformTag::Musllinux { arch, .. }
+            | PlatformTag::Linux { arch }
+            | PlatformTag::Emscripten { arch, .. }
+            | PlatformTag::Wasi { arch } => Some(arch),
+            PlatformTag::MacOs { binary_format, .. } => Some(binary_format),
+            PlatformTag::Ios { multiarch, .. } => Some(multiarch),
+            PlatformTag::Android { abi, .. } => Some(abi),
+            PlatformTag::Any | PlatformTag::Other(_) => None,
+        }
+    }
+
+    /// Given the most specific platform tag of a system, returns all the platform tags that are
+    /// supported by that system ordered from most to least specific.
+    ///
+    /// For instance, a system described by `manylinux_2_28_x86_64` also supports
+    /// `manylinux_2_17_x86_64`, `manylinux2014_x86_64` and `linux_x86_64`. The `any` tag is not
+    /// included in the result.
+    pub fn compatible_platforms(&self) -> Vec<PlatformTag> {
+        match self {
+            PlatformTag::Any => Vec::new(),
+            PlatformTag::Manylinux { major, minor, arch } => {
+                let mut result = manylinux_platforms((*major, *minor), arch);
+                result.push(PlatformTag::Linux { arch: arch.clone() });
+                result
+            }
+            PlatformTag::LegacyManylinux { arch, .. } => {
+                let glibc = self
+                    .glibc_version()
+                    .expect("legacy tags have a glibc version");
+                let mut result = manylinux_platforms(glibc, arch);
+                result.push(PlatformTag::Linux { arch: arch.clone() });
+                result
+            }
+            PlatformTag::Musllinux { major, minor, arch } => {
+                let mut result = (0..=*minor)
+                    .rev()
+                    .map(|minor| PlatformTag::Musllinux {
+                        major: *major,
+                        minor,
+                        arch: arch.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                result.push(PlatformTag::Linux { arch: arch.clone() });
+                result
+            }
+            PlatformTag::MacOs {
+                major,
+                minor,
+                binary_format,
+            } => mac_platforms((*major, *minor), binary_format),
+            PlatformTag::Ios {
+                major,
+                minor,
+                multiarch,
+            } => ios_platforms((*major, *minor), multiarch),
+            PlatformTag::Android { api_level, abi } => (OLDEST_ANDROID_API_LEVEL..=*api_level)
+                .rev()
+                .map(|api_level| PlatformTag::Android {
+                    api_level,
+                    abi: abi.clone(),
+                })
+                .collect(),
+            PlatformTag::Linux { .. }
+            | PlatformTag::Emscripten { .. }
+            | PlatformTag::Wasi { .. }
+            | PlatformTag::Other(_) => vec![self.clone()],
+        }
+    }
+
+    /// Returns true if a wheel with this platform tag can be installed on a system whose most
+    /// specific platform tag is `platform`.
+    pub fn is_compatible_with(&self, platform: &PlatformTag) -> bool {
+        match self {
+            PlatformTag::Any => true,
+            _ => platform.compatible_platforms().contains(self),
+        }
+    }
+}
+
+/// Returns all manylinux tags (both the PEP 600 and the legacy variants) supported by a system with
+/// the specified glibc version.
+fn manylinux_platforms(glibc: (u16, u16), arch: &str) -> Vec<PlatformTag> {
+    // glibc 2.5 is the oldest version for x86 (manylinux1), all other architectures start at
+    // glibc 2.17 (manylinux2014).
+    let oldest_minor = if matches!(arch, "x86_64" | "i686") {
+        5
+    } else {
+        17
+    };
+
+    let (major, minor) = glibc;
+    let mut result = Vec::new();
+    for minor in (oldest_minor..=minor).rev() {
+        result.push(PlatformTag::Manylinux {
+            major,
+            minor,
+            arch: arch.to_owned(),
+        });
+        if let Some((name, _)) = LEGACY_MANYLINUX
+            .iter()
+            .find(|(_, version)| *version == (major, minor))
+        {
+            result.push(PlatformTag::LegacyManylinux {
+                name,
+                arch: arch.to_owned(),
+            });
+        }
+    }
+    result
+}
+
+/// Returns the binary formats that are supported for a given macOS version and cpu architecture.
+/// This mirrors `packaging.tags._mac_binary_formats`.
+fn mac_binary_formats(version: (u16, u16), cpu_arch: &str) -> Vec<&str> {
+    let mut formats = vec![cpu_arch];
+    match cpu_arch {
+        "x86_64" => {
+            if version < (10, 4) {
+                return Vec::new();
+            }
+            formats.extend(["intel", "fat64", "fat32"]);
+        }
+        "i386" => {
+            if version < (10, 4) {
+                return Vec::new();
+            }
+            formats.extend(["intel", "fat32", "fat"]);
+        }
+        "ppc64" => {
+            if !((10, 4)..=(10, 5)).contains(&version) {
+                return Vec::new();
+            }
+            formats.push("fat64");
+        }
+        "ppc" => {
+            if version > (10, 6) {
+                return Vec::new();
+            }
+            formats.extend(["fat32", "fat"]);
+        }
+        _ => {}
+    }
+
+    if matches!(cpu_arch, "arm64" | "x86_64") {
+        formats.push("universal2");
+    }
+
+    if matches!(cpu_arch, "x86_64" | "i386" | "ppc64" | "ppc" | "intel") {
+        formats.push("universal");
+    }
+
+    formats
+}
+
+/// Returns all the macOS platform tags supported by the given macOS version and cpu architecture.
+/// This mirrors `packaging.tags.mac_platforms`.
+fn mac_platforms(version: (u16, u16), cpu_arch: &str) -> Vec<PlatformTag> {
+    let mut result = Vec::new();
+    let mut push_formats = |major: u16, minor: u16, formats: Vec<&str>| {
+        result.extend(formats.into_iter().map(|format| PlatformTag::MacOs {
+            major,
+            minor,
+            binary_format: format.to_owned(),
+        }))
+    };
+
+    // Prior to macOS 11, each yearly release of macOS bumped the "minor" version number.
+    if ((10, 0)..(11, 0)).contains(&version) {
+        for minor in (0..=version.1).rev() {
+            push_formats(10, minor, mac_binary_formats((10, minor), cpu_arch));
+        }
+    }
+
+    if version >= (11, 0) {
+        // Starting with macOS 11, each yearly release bumps the major version number.
+        for major in (11..=version.0).rev() {
+            push_formats(major, 0, mac_binary_formats((major, 0), cpu_arch));
+        }
+
+        // macOS 11 on x86_64 is compatible with binaries from previous releases. On arm64 only
+        // universal2 binaries from previous releases are compatible.
+        for minor in (4..=16).rev() {
+            if cpu_arch == "x86_64" {
+                push_formats(10, minor, mac_binary_formats((10, minor), cpu_arch));
+            } else {
+                push_formats(10, minor, vec!["universal2"]);
+            }
+        }
+    }
+
+    result
+}
+
+/// Returns all the iOS platform tags supported by the given iOS version and multiarch. This mirrors
+/// `packaging.tags.ios_platforms`, every minor release of older major versions is assumed to go up
+/// to 9.
+fn ios_platforms(version: (u16, u16), multiarch: &str) -> Vec<PlatformTag> {
+    let (major, minor) = version;
+    let tag = |major, minor| PlatformTag::Ios {
+        major,
+        minor,
+        multiarch: multiarch.to_owned(),
+    };
+
+    let mut result = (0..=minor)
+        .rev()
+        .map(|minor| tag(major, minor))
+        .collect::<Vec<_>>();
+    for major in (OLDEST_IOS_MAJOR..major).rev() {
+        result.extend((0..=9).rev().map(|minor| tag(major, minor)));
+    }
+    result
+}
+
+/// Splits `major_minor_rest` into its components.
+fn split_version_prefix(s: &str) -> Option<(u16, u16, &str)> {
+    let (major, rest) = s.split_once('_')?;
+    let (minor, rest) = rest.split_once('_')?;
+    Some((major.parse().ok()?, minor.parse().ok()?, rest))
+}
+
+impl FromStr for PlatformTag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "any" {
+            return Ok(PlatformTag::Any);
+        }
+
+        if let Some(rest) = s.strip_prefix("manylinux_") {
+            let (major, minor, arch) = split_version_prefix(rest)
+                .ok_or_else(|| format!("invalid manylinux platform tag '{s}'"))?;
+            return Ok(PlatformTag::Manylinux {
+                major,
+                minor,
+                arch: arch.to_owned(),
+            });
+        }
+
+        for (name, _) in LEGACY_MANYLINUX {
+            if let Some(arch) = s.strip_prefix(name).and_then(|rest| rest.strip_prefix('_')) {
+                return Ok(PlatformTag::LegacyManylinux {
+                    name,
+                    arch: arch.to_owned(),
+                });
+            }
+        }
+
+        if let Some(rest) = s.strip_prefix("musllinux_") {
+            let (major, minor, arch) = split_version_prefix(rest)
+                .ok_or_else(|| format!("invalid musllinux platform tag '{s}'"))?;
+            return Ok(PlatformTag::Musllinux {
+                major,
+                minor,
+                arch: arch.to_owned(),
+            });
+        }
+
+        if let Some(arch) = s.strip_prefix("linux_") {
+            return Ok(PlatformTag::Linux {
+                arch: arch.to_owned(),
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("macosx_") {
+            let (major, minor, binary_format) = split_version_prefix(rest)
+                .ok_or_else(|| format!("invalid macosx platform tag '{s}'"))?;
+            return Ok(PlatformTag::MacOs {
+                major,
+                minor,
+                binary_format: binary_format.to_owned(),
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("emscripten_") {
+            let invalid = || format!("invalid emscripten platform tag '{s}'");
+            let (major, minor, rest) = split_version_prefix(rest).ok_or_else(invalid)?;
+            let (patch, arch) = rest.split_once('_').ok_or_else(invalid)?;
+            return Ok(PlatformTag::Emscripten {
+                major,
+                minor,
+                patch: patch.parse().map_err(|_| invalid())?,
+                arch: arch.to_owned(),
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("ios_") {
+            let (major, minor, multiarch) = split_version_prefix(rest)
+                .ok_or_else(|| format!("invalid ios platform tag '{s}'"))?;
+            return Ok(PlatformTag::Ios {
+                major,
+                minor,
+                multiarch: multiarch.to_owned(),
+            });
+        }
+
+        if let Some(rest) = s.strip_prefix("android_") {
+            let invalid = || format!("invalid android platform tag '{s}'");
+            let (api_level, abi) = rest.split_once('_').ok_or_else(invalid)?;
+            return Ok(PlatformTag::Android {
+                api_level: api_level.parse().map_err(|_| invalid())?,
+                abi: abi.to_owned(),
+            });
+        }
+
+        if let Some(arch) = s.strip_suffix("_wasi") {
+            return Ok(PlatformTag::Wasi {
+                arch: arch.to_owned(),
+            });
+        }
+
+        if s.is_empty() {
+            return Err(String::from("empty platform tag"));
+        }
+
+        Ok(PlatformTag::Other(s.to_owned()))
+    }
+}
+
+impl Display for PlatformTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlatformTag::Any => write!(f, "any"),
+            PlatformTag::Manylinux { major, minor, arch } => {
+                write!(f, "manylinux_{major}_{minor}_{arch}")
+            }
+            PlatformTag::LegacyManylinux { name, arch } => write!(f, "{name}_{arch}"),
+            PlatformTag::Musllinux { major, minor, arch } => {
+                write!(f, "musllinux_{major}_{minor}_{arch}")
+            }
+            PlatformTag::Linux { arch } => write!(f, "linux_{arch}"),
+            PlatformTag::MacOs {
+                major,
+                minor,
+                binary_format,
+            } => write!(f, "macosx_{major}_{minor}_{binary_format}"),
+            PlatformTag::Emscripten {
+                major,
+                minor,
+                patch,
+                arch,
+            } => write!(f, "emscripten_{major}_{minor}_{patch}_{arch}"),
+            PlatformTag::Ios {
+                major,
+                minor,
+                multiarch,
+            } => write!(f, "ios_{major}_{minor}_{multiarch}"),
+            PlatformTag::Android { api_level, abi } => write!(f, "android_{api_level}_{abi}"),
+            PlatformTag::Wasi { arch } => write!(f, "{arch}_wasi"),
+            PlatformTag::Other(tag) => write!(f, "{tag}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    fn compatible(tag: &str) -> Vec<String> {
+        PlatformTag::from_str(tag)
+            .unwrap()
+            .compatible_platforms()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for tag in [
+            "any",
+            "manylinux_2_17_x86_64",
+            "manylinux2014_aarch64",
+            "manylinux1_i686",
+            "musllinux_1_2_x86_64",
+            "linux_armv7l",
+            "macosx_10_9_x86_64",
+            "macosx_11_0_universal2",
+            "emscripten_3_1_45_wasm32",
+            "ios_17_0_arm64_iphoneos",
+            "ios_13_2_x86_64_iphonesimulator",
+            "android_21_arm64_v8a",
+            "android_24_x86_64",
+            "wasm32_wasi",
+            "win_amd64",
+        ] {
+            assert_eq!(PlatformTag::from_str(tag).unwrap().to_string(), tag);
+        }
+    }
+
+    #[test]
+    fn test_manylinux_ordering() {
+        let tags = compatible("manylinux_2_28_x86_64");
+        let position = |tag: &str| tags.iter().position(|t| t == tag).unwrap();
+
+        assert_eq!(tags[0], "manylinux_2_28_x86_64");
+        assert!(position("manylinux_2_28_x86_64") < position("manylinux_2_17_x86_64"));
+        assert!(position("manylinux_2_17_x86_64") < position("manylinux2014_x86_64"));
+        assert!(position("manylinux2014_x86_64") < position("manylinux2010_x86_64"));
+        assert!(position("manylinux2010_x86_64") < position("manylinux1_x86_64"));
+        assert_eq!(tags.last().unwrap(), "linux_x86_64");
+
+        // Non x86 architectures start at manylinux2014
+        let tags = compatible("manylinux_2_17_aarch64");
+        assert_eq!(
+            tags,
+            vec![
+                "manylinux_2_17_aarch64",
+                "manylinux2014_aarch64",
+                "linux_aarch64"
+            ]
+        );
+
+        let wheel = PlatformTag::from_str("manylinux2014_x86_64").unwrap();
+        assert!(wheel.is_compatible_with(&"manylinux_2_28_x86_64".parse().unwrap()));
+        assert!(!wheel.is_compatible_with(&"manylinux_2_12_x86_64".parse().unwrap()));
+        assert!(!wheel.is_compatible_with(&"manylinux_2_28_aarch64".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_musllinux_ordering() {
+        assert_eq!(
+            compatible("musllinux_1_2_x86_64"),
+            vec![
+                "musllinux_1_2_x86_64",
+                "musllinux_1_1_x86_64",
+                "musllinux_1_0_x86_64",
+                "linux_x86_64"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_macos_ordering() {
+        let tags = compatible("macosx_14_0_arm64");
+        assert_eq!(
+            tags.iter().take(4).collect_vec(),
+            vec![
+                "macosx_14_0_arm64",
+                "macosx_14_0_universal2",
+                "macosx_13_0_arm64",
+                "macosx_13_0_universal2",
+            ]
+        );
+        // Older releases are only supported through fat binaries
+        assert!(tags.contains(&String::from("macosx_10_9_universal2")));
+        assert!(!tags.contains(&String::from("macosx_10_9_x86_64")));
+
+        let tags = compatible("macosx_10_15_x86_64");
+        assert_eq!(
+            tags.iter().take(6).collect_vec(),
+            vec![
+                "macosx_10_15_x86_64",
+                "macosx_10_15_intel",
+                "macosx_10_15_fat64",
+                "macosx_10_15_fat32",
+                "macosx_10_15_universal2",
+                "macosx_10_15_universal",
+            ]
+        );
+        assert!(tags.contains(&String::from("macosx_10_9_x86_64")));
+        assert!(!tags.iter().any(|t| t.starts_with("macosx_11")));
+    }
+
+    #[test]
+    fn test_emscripten() {
+        assert_eq!(
+            PlatformTag::from_str("emscripten_3_1_45_wasm32").unwrap(),
+            PlatformTag::Emscripten {
+                major: 3,
+                minor: 1,
+                patch: 45,
+                arch: String::from("wasm32")
+            }
+        );
+        assert!(PlatformTag::from_str("emscripten_3_1_wasm32").is_err());
+
+        // Binary wheels are only compatible with the exact emscripten version
+        assert_eq!(
+            compatible("emscripten_3_1_45_wasm32"),
+            vec!["emscripten_3_1_45_wasm32"]
+        );
+        let wheel = PlatformTag::from_str("emscripten_3_1_46_wasm32").unwrap();
+        assert!(!wheel.is_compatible_with(&"emscripten_3_1_45_wasm32".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_wasi() {
+        assert_eq!(
+            PlatformTag::from_str("wasm32_wasi").unwrap(),
+            PlatformTag::Wasi {
+                arch: String::from("wasm32")
+            }
+        );
+        assert_eq!(compatible("wasm32_wasi"), vec!["wasm32_wasi"]);
+
+        // Emscripten wheels cannot be used on WASI
+        let wheel = PlatformTag::from_str("emscripten_3_1_45_wasm32").unwrap();
+        assert!(!wheel.is_compatible_with(&"wasm32_wasi".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_ios() {
+        assert_eq!(
+            PlatformTag::from_str("ios_13_0_arm64_iphonesimulator").unwrap(),
+            PlatformTag::Ios {
+                major: 13,
+                minor: 0,
+                multiarch: String::from("arm64_iphonesimulator")
+            }
+        );
+
+        let tags = compatible("ios_13_2_arm64_iphoneos");
+        assert_eq!(
+            tags,
+            vec![
+                "ios_13_2_arm64_iphoneos",
+                "ios_13_1_arm64_iphoneos",
+                "ios_13_0_arm64_iphoneos",
+                "ios_12_9_arm64_iphoneos",
+                "ios_12_8_arm64_iphoneos",
+                "ios_12_7_arm64_iphoneos",
+                "ios_12_6_arm64_iphoneos",
+                "ios_12_5_arm64_iphoneos",
+                "ios_12_4_arm64_iphoneos",
+                "ios_12_3_arm64_iphoneos",
+                "ios_12_2_arm64_iphoneos",
+                "ios_12_1_arm64_iphoneos",
+                "ios_12_0_arm64_iphoneos",
+            ]
+        );
+
+        // Device and simulator wheels are not interchangeable
+        let wheel = PlatformTag::from_str("ios_12_0_arm64_iphonesimulator").unwrap();
+        assert!(!wheel.is_compatible_with(&"ios_13_2_arm64_iphoneos".parse().unwrap()));
+        assert!(wheel.is_compatible_with(&"ios_13_2_arm64_iphonesimulator".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_android() {
+        assert_eq!(
+            PlatformTag::from_str("android_21_arm64_v8a").unwrap(),
+            PlatformTag::Android {
+                api_level: 21,
+                abi: String::from("arm64_v8a")
+            }
+        );
+        assert!(PlatformTag::from_str("android_x86_64").is_err());
+
+        let tags = compatible("android_21_x86_64");
+        assert_eq!(tags.first().unwrap(), "android_21_x86_64");
+        assert_eq!(tags.last().unwrap(), "android_16_x86_64");
+        assert_eq!(tags.len(), 6);
+
+        let wheel = PlatformTag::from_str("android_24_arm64_v8a").unwrap();
+        assert!(!wheel.is_compatible_with(&"android_21_arm64_v8a".parse().unwrap()));
+        assert!(wheel.is_compatible_with(&"android_30_arm64_v8a".parse().unwrap()));
+    }
+}
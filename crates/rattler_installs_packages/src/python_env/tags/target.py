@@ -0,0 +1,98 @@
+# A program that outputs a description of the running interpreter and the platform it runs on in
+# a JSON format. The output is used to compute the compatible wheel tags without requiring the
+# `packaging` module. Most of the logic has been taken from `packaging.tags`.
+
+import glob
+import json
+import platform
+import re
+import subprocess
+import sys
+import sysconfig
+
+INTERPRETER_SHORT_NAMES = {
+    "python": "py",
+    "cpython": "cp",
+    "pypy": "pp",
+    "ironpython": "ip",
+    "jython": "jy",
+}
+
+
+def normalize(s):
+    return s.replace(".", "_").replace("-", "_").replace(" ", "_")
+
+
+def abi():
+    ext_suffix = sysconfig.get_config_var("EXT_SUFFIX") or sysconfig.get_config_var("SO") or ""
+    parts = ext_suffix.split(".")
+    if len(parts) < 3:
+        return None
+    soabi = parts[1]
+    if soabi.startswith("cpython"):
+        abi = "cp" + soabi.split("-")[1]
+    elif soabi.startswith("cp"):
+        abi = soabi.split("-")[0]
+    elif soabi.startswith("pypy"):
+        abi = "-".join(soabi.split("-")[:2])
+    elif soabi.startswith("graalpy"):
+        abi = "-".join(soabi.split("-")[:3])
+    else:
+        abi = soabi
+    return normalize(abi)
+
+
+def musl_version():
+    for ld in glob.glob("/lib/ld-musl-*.so.1"):
+        try:
+            proc = subprocess.run([ld], stderr=subprocess.PIPE, universal_newlines=True)
+        except OSError:
+            continue
+        match = re.search(r"Version (\d+)\.(\d+)", proc.stderr)
+        if match:
+            return match.group(1), match.group(2)
+    return None
+
+
+def platform_tag():
+    if sys.platform == "wasi":
+        return "%s_wasi" % (platform.machine() or "wasm32")
+    if sys.platform == "ios":
+        version = platform.ios_ver().release.split(".")
+        major, minor = int(version[0]), int(version[1]) if len(version) > 1 else 0
+        return "ios_%d_%d_%s" % (major, minor, normalize(sys.implementation._multiarch))
+    if sys.platform == "android":
+        abi = normalize(sysconfig.get_platform().split("-")[-1])
+        return "android_%d_%s" % (platform.android_ver().api_level, abi)
+    plat = normalize(sysconfig.get_platform())
+    if plat.startswith("linux_"):
+        arch = plat[len("linux_"):]
+        if arch == "x86_64" and sys.maxsize <= 2 ** 32:
+            arch = "i686"
+        elif arch == "aarch64" and sys.maxsize <= 2 ** 32:
+            arch = "armv8l"
+        libc, version = platform.libc_ver()
+        if libc == "glibc":
+            major, minor = version.split(".")[:2]
+            return "manylinux_%s_%s_%s" % (major, minor, arch)
+        musl = musl_version()
+        if musl is not None:
+            return "musllinux_%s_%s_%s" % (musl[0], musl[1], arch)
+        return "linux_%s" % arch
+    if plat.startswith("macosx_"):
+        version = platform.mac_ver()[0].split(".")
+        major, minor = int(version[0]), int(version[1]) if len(version) > 1 else 0
+        if major >= 11:
+            minor = 0
+        return "macosx_%d_%d_%s" % (major, minor, platform.machine())
+    return plat
+
+
+implementation = sys.implementation.name
+json.dump({
+    "interpreter": INTERPRETER_SHORT_NAMES.get(implementation, implementation),
+    "python_version": [sys.version_info[0], sys.version_info[1]],
+    "abi": abi(),
+    "free_threaded": bool(sysconfig.get_config_var("Py_GIL_DISABLED")),
+    "platform": platform_tag(),
+}, sys.stdout)
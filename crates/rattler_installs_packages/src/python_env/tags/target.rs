@@ -0,0 +1,292 @@
+//! Computes the compatible wheel tags of an interpreter natively from a small description of that
+//! interpreter. The logic mirrors `packaging.tags.sys_tags`.
+
+use super::from_env::FromPythonError;
+use super::{PlatformTag, WheelTag, WheelTags};
+use crate::python_env::{system_python_executable, FindPythonError};
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// Describes a Python interpreter and the platform it runs on. This is all the information that is
+/// required to compute the wheel tags that are compatible with the interpreter.
+///
+/// This can either be queried from an existing interpreter with [`WheelTagTarget::from_python`] or
+/// constructed manually to compute the tags for an interpreter that is not available on the
+/// current machine.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WheelTagTarget {
+    /// The short name of the interpreter implementation as defined by PEP 425, e.g. `cp` for
+    /// CPython or `pp` for PyPy.
+    pub interpreter: String,
+
+    /// The major and minor version of the python language the interpreter implements.
+    pub python_version: (u32, u32),
+
+    /// The ABI of the interpreter, e.g. `cp311` or `pypy39_pp73`. If this is `None` the ABI is
+    /// derived from the interpreter and version if possible.
+    pub abi: Option<String>,
+
+    /// True if the interpreter is a free-threaded build of CPython, e.g. `python3.13t`. These
+    /// interpreters use a separate ABI (`cp313t`) and do not support the stable ABI.
+    #[serde(default)]
+    pub free_threaded: bool,
+
+    /// The most specific platform tag supported by the system, e.g. `manylinux_2_35_x86_64`,
+    /// `macosx_14_0_arm64` or `win_amd64`.
+    pub platform: PlatformTag,
+}
+
+impl WheelTagTarget {
+    /// Creates a description of a CPython interpreter on the given platform.
+    pub fn cpython(python_version: (u32, u32), platform: PlatformTag) -> Self {
+        Self {
+            interpreter: String::from("cp"),
+            python_version,
+            abi: None,
+            free_threaded: false,
+            platform,
+        }
+    }
+
+    /// Creates a description of a free-threaded CPython interpreter on the given platform.
+    pub fn cpython_free_threaded(python_version: (u32, u32), platform: PlatformTag) -> Self {
+        Self {
+            free_threaded: true,
+            ..Self::cpython(python_version, platform)
+        }
+    }
+
+    /// Query the description of the system python interpreter.
+    pub async fn from_env() -> Result<Self, FromPythonError> {
+        Self::from_python(system_python_executable()?.as_path()).await
+    }
+
+    /// Query the description of the specified python interpreter. Contrary to
+    /// [`WheelTags::from_python`] this only requires the standard library.
+    pub async fn from_python(python: &Path) -> Result<Self, FromPythonError> {
+        let output = match tokio::process::Command::new(python)
+            .arg("-c")
+            .arg(include_str!("target.py"))
+            .output()
+            .await
+        {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(FromPythonError::CouldNotFindPythonExecutable(
+                    FindPythonError::NotFound,
+                ))
+            }
+            Err(e) => return Err(FromPythonError::FailedToExecute(e)),
+            Ok(output) => output,
+        };
+
+        // Ensure that we have a valid success code
+        if !output.status.success() {
+            return Err(FromPythonError::FailedToRun(output.status));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(serde_json::from_str(stdout.trim())?)
+    }
+
+    /// Returns the interpreter tag, e.g. `cp311`.
+    fn interpreter_tag(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.interpreter, self.python_version.0, self.python_version.1
+        )
+    }
+
+    /// Returns the ABIs supported by the interpreter from most to least specific.
+    fn abis(&self) -> Vec<String> {
+        match (&self.abi, self.interpreter.as_str()) {
+            (Some(abi), _) => vec![abi.clone()],
+            (None, "cp") => {
+                // Python 3.8 dropped the `m` (pymalloc) suffix, free-threaded builds use a `t` suffix
+                let suffix = if self.free_threaded {
+                    "t"
+                } else if self.python_version < (3, 8) {
+                    "m"
+                } else {
+                    ""
+                };
+                vec![format!(
+                    "cp{}{}{suffix}",
+                    self.python_version.0, self.python_version.1
+                )]
+            }
+            (None, _) => Vec::new(),
+        }
+    }
+
+    /// Returns true if the interpreter supports the stable ABI (abi3). Free-threaded builds do not.
+    fn abi3_applies(&self) -> bool {
+        self.interpreter == "cp" && self.python_version >= (3, 2) && !self.free_threaded
+    }
+
+    /// Returns the `py*` interpreter tags for the python version in order of preference, e.g.
+    /// `py311`, `py3`, `py310`, ... , `py30`.
+    fn py_interpreter_range(&self) -> Vec<String> {
+        let (major, minor) = self.python_version;
+        let mut result = vec![format!("py{major}{minor}"), format!("py{major}")];
+        result.extend((0..minor).rev().map(|minor| format!("py{major}{minor}")));
+        result
+    }
+}
+
+impl WheelTags {
+    /// Computes the compatible wheel tags for the specified target without executing a python
+    /// interpreter.
+    pub fn from_target(target: &WheelTagTarget) -> Self {
+        let platforms = target
+            .platform
+            .compatible_platforms()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        let mut tags = Vec::new();
+        let mut push = |interpreter: &str, abi: &str, platform: String| {
+            tags.push(WheelTag {
+                interpreter: interpreter.to_owned(),
+                abi: abi.to_owned(),
+                platform,
+            })
+        };
+
+        // Tags specific to the interpreter
+        let interpreter = target.interpreter_tag();
+        let mut abis = target.abis();
+        if target.abi3_applies() {
+            abis.retain(|abi| abi != "abi3");
+            abis.push(String::from("abi3"));
+        }
+        abis.retain(|abi| abi != "none");
+        abis.push(String::from("none"));
+        for abi in abis.iter() {
+            for platform in platforms.iter() {
+                push(&interpreter, abi, platform.clone());
+            }
+        }
+
+        // Older versions of CPython also support the stable ABI.
+        if target.abi3_applies() {
+            let (major, minor) = target.python_version;
+            for minor in (2..minor).rev() {
+                let interpreter = format!("cp{major}{minor}");
+                for platform in platforms.iter() {
+                    push(&interpreter, "abi3", platform.clone());
+                }
+            }
+        }
+
+        // Generic python tags
+        let py_interpreters = target.py_interpreter_range();
+        for py_interpreter in py_interpreters.iter() {
+            for platform in platforms.iter() {
+                push(py_interpreter, "none", platform.clone());
+            }
+        }
+        push(&interpreter, "none", String::from("any"));
+        for py_interpreter in py_interpreters.iter() {
+            push(py_interpreter, "none", String::from("any"));
+        }
+
+        tags.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags(target: &WheelTagTarget) -> Vec<String> {
+        WheelTags::from_target(target)
+            .tags()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_cpython_manylinux() {
+        let target = WheelTagTarget::cpython((3, 11), "manylinux_2_28_x86_64".parse().unwrap());
+        let tags = tags(&target);
+        let position = |tag: &str| tags.iter().position(|t| t == tag).unwrap();
+
+        assert_eq!(tags[0], "cp311-cp311-manylinux_2_28_x86_64");
+        assert!(
+            position("cp311-cp311-manylinux2014_x86_64")
+                < position("cp311-abi3-manylinux_2_28_x86_64")
+        );
+        assert!(
+            position("cp311-abi3-manylinux_2_28_x86_64")
+                < position("cp311-none-manylinux_2_28_x86_64")
+        );
+        assert!(position("cp311-none-linux_x86_64") < position("cp310-abi3-manylinux_2_28_x86_64"));
+        assert!(position("cp32-abi3-linux_x86_64") < position("py311-none-manylinux_2_28_x86_64"));
+        assert!(position("py30-none-linux_x86_64") < position("cp311-none-any"));
+        assert_eq!(tags.last().unwrap(), "py30-none-any");
+
+        assert!(!tags.iter().any(|t| t.starts_with("cp31-")));
+        assert!(!tags.iter().any(|t| t.contains("musllinux")));
+    }
+
+    #[test]
+    fn test_cpython_pymalloc() {
+        let target = WheelTagTarget::cpython((3, 7), "win_amd64".parse().unwrap());
+        assert_eq!(
+            tags(&target).iter().take(3).collect::<Vec<_>>(),
+            vec![
+                "cp37-cp37m-win_amd64",
+                "cp37-abi3-win_amd64",
+                "cp37-none-win_amd64"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pypy() {
+        let target = WheelTagTarget {
+            interpreter: String::from("pp"),
+            python_version: (3, 9),
+            abi: Some(String::from("pypy39_pp73")),
+            free_threaded: false,
+            platform: "macosx_14_0_arm64".parse().unwrap(),
+        };
+        let tags = tags(&target);
+
+        assert_eq!(tags[0], "pp39-pypy39_pp73-macosx_14_0_arm64");
+        assert!(!tags.iter().any(|t| t.contains("abi3")));
+        assert!(tags.contains(&String::from("pp39-none-any")));
+        assert!(tags.contains(&String::from("py3-none-macosx_11_0_universal2")));
+    }
+
+    #[test]
+    fn test_cpython_free_threaded() {
+        let target = WheelTagTarget::cpython_free_threaded(
+            (3, 13),
+            "manylinux_2_28_x86_64".parse().unwrap(),
+        );
+        let tags = tags(&target);
+
+        assert_eq!(tags[0], "cp313-cp313t-manylinux_2_28_x86_64");
+        assert!(!tags.iter().any(|t| t.contains("abi3")));
+        assert!(!tags.iter().any(|t| t.contains("-cp313-")));
+        assert!(tags.contains(&String::from("cp313-none-manylinux_2_28_x86_64")));
+        assert!(tags.contains(&String::from("py3-none-any")));
+        assert!(WheelTags::from_target(&target).is_free_threaded());
+    }
+
+    #[tokio::test]
+    pub async fn test_from_env() {
+        match WheelTagTarget::from_env().await {
+            Err(FromPythonError::CouldNotFindPythonExecutable(_)) => {
+                // This is fine, the test machine does not include a python binary.
+            }
+            Err(e) => panic!("{e:?}"),
+            Ok(target) => {
+                println!("Found the following target on the current system:\n{target:?}")
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! Installing wheels into a plain directory instead of a virtual environment.
+//!
+//! This mirrors `pip install --target`: the contents of the wheels are placed directly in the
+//! directory so it can be added to `PYTHONPATH` or shipped as is, for instance as an AWS Lambda
+//! layer.
+use crate::artifacts::wheel::{
+    staged_path, InstallPaths, UnpackError, UnpackWheelOptions, UnpackedWheel,
+};
+use crate::artifacts::Wheel;
+use std::path::{Path, PathBuf};
+
+/// A plain directory into which wheels are installed without creating a virtual environment.
+#[derive(Debug, Clone)]
+pub struct TargetDirectory {
+    /// Location of the directory
+    location: PathBuf,
+    /// Install paths for this directory
+    install_paths: InstallPaths,
+    /// The python interpreter that is referenced by the generated scripts
+    python_executable: PathBuf,
+    /// The directory into which the files are staged instead of writing them to `location`
+    destdir: Option<PathBuf>,
+    /// The location of the directory inside `destdir`
+    staged_location: Option<PathBuf>,
+}
+
+impl TargetDirectory {
+    /// Constructs a new target directory at `location`. Scripts are written to the `bin` (or
+    /// `Scripts` on windows) directory inside the target directory and refer to
+    /// `python_executable`, which should be the path of the interpreter on the machine where the
+    /// directory will be used.
+    pub fn new(location: impl Into<PathBuf>, python_executable: impl Into<PathBuf>) -> Self {
+        let scripts = if cfg!(windows) { "Scripts" } else { "bin" };
+        Self::with_scripts_dir(location, scripts, python_executable)
+    }
+
+    /// Constructs a new target directory at `location` that writes scripts to `scripts`. If
+    /// `scripts` is relative it is interpreted relative to `location`.
+    pub fn with_scripts_dir(
+        location: impl Into<PathBuf>,
+        scripts: impl Into<PathBuf>,
+        python_executable: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            location: location.into(),
+            install_paths: InstallPaths::for_target_dir(scripts, cfg!(windows)),
+            python_executable: python_executable.into(),
+            destdir: None,
+            staged_location: None,
+        }
+    }
+
+    /// Constructs a new target directory at `location` that places the files of wheels according
+    /// to `install_paths`. This can be used to target non-standard layouts, see
+    /// [`InstallPaths::from_scheme`].
+    pub fn with_install_paths(
+        location: impl Into<PathBuf>,
+        install_paths: InstallPaths,
+        python_executable: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            location: location.into(),
+            install_paths,
+            python_executable: python_executable.into(),
+            destdir: None,
+            staged_location: None,
+        }
+    }
+
+    /// Stage the files into `destdir` instead of writing them to the location of the directory,
+    /// see [`UnpackWheelOptions::destdir`]. The installed files refer to the location of the
+    /// directory, so the staged directory is only usable after it has been moved there.
+    pub fn with_destdir(mut self, destdir: impl Into<PathBuf>) -> Self {
+        let destdir = destdir.into();
+        self.staged_location = Some(staged_path(&destdir, &self.location));
+        self.destdir = Some(destdir);
+        self
+    }
+
+    /// Install a wheel into this directory
+    pub fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        match &self.destdir {
+            Some(destdir) => wheel.unpack(
+                &self.location,
+                &self.install_paths,
+                &self.python_executable,
+                &UnpackWheelOptions {
+                    destdir: Some(destdir.clone()),
+                    ..options.clone()
+                },
+            ),
+            None => wheel.unpack(
+                &self.location,
+                &self.install_paths,
+                &self.python_executable,
+                options,
+            ),
+        }
+    }
+
+    /// Returns the [`InstallPaths`] that defines where the different parts of a wheel are placed.
+    pub fn install_paths(&self) -> &InstallPaths {
+        &self.install_paths
+    }
+
+    /// Returns the root directory. When the directory is staged into a destdir this is the
+    /// location inside the destdir where the files are actually written.
+    pub fn root(&self) -> &Path {
+        self.staged_location.as_deref().unwrap_or(&self.location)
+    }
+
+    /// Returns the python interpreter that is referenced by the generated scripts.
+    pub fn python_executable(&self) -> &Path {
+        &self.python_executable
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::InstallScheme;
+    use crate::python_env::{find_distributions_in_directory, find_distributions_in_venv};
+    use crate::types::NormalizedPackageName;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_install_into_target_dir() {
+        let wheel_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl");
+        let wheel = Wheel::from_path(
+            &wheel_path,
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target =
+            TargetDirectory::with_scripts_dir(tmpdir.path(), "custom-bin", "/usr/bin/python3");
+        target
+            .install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+
+        // The packages are placed directly in the root of the directory
+        assert!(tmpdir.path().join("black").join("__init__.py").is_file());
+        assert!(tmpdir.path().join("custom-bin").is_dir());
+
+        let distributions = find_distributions_in_directory(tmpdir.path()).unwrap();
+        assert_eq!(distributions.len(), 1);
+        assert_eq!(distributions[0].name.as_str(), "miniblack");
+    }
+
+    #[test]
+    fn test_install_with_custom_scheme() {
+        let wheel_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels/purelib_and_platlib-1.0.0-cp38-cp38-linux_x86_64.whl");
+        let wheel = Wheel::from_path(
+            &wheel_path,
+            &NormalizedPackageName::from_str("purelib_and_platlib").unwrap(),
+        )
+        .unwrap();
+
+        let tmpdir = tempfile::tempdir().unwrap();
+        let scheme = InstallScheme {
+            purelib: PathBuf::from("pure"),
+            platlib: PathBuf::from("plat"),
+            scripts: PathBuf::from("tools"),
+            data: PathBuf::from("data"),
+            headers: PathBuf::from("headers"),
+        };
+        let target = TargetDirectory::with_install_paths(
+            tmpdir.path(),
+            InstallPaths::from_scheme(scheme.clone(), false),
+            "/usr/bin/python3",
+        );
+        assert_eq!(target.install_paths().scheme(), scheme);
+
+        let unpacked = target
+            .install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+
+        // The wheel is not purelib so its root, including the .dist-info, is placed in platlib
+        // while the files from its purelib data directory are placed in purelib.
+        assert_eq!(
+            unpacked.dist_info,
+            tmpdir
+                .path()
+                .join("plat/purelib_and_platlib-1.0.0.dist-info")
+        );
+        assert!(tmpdir.path().join("pure/pure.py").is_file());
+
+        // The RECORD file refers to the files relative to the directory of the .dist-info
+        let record = fs_err::read_to_string(unpacked.dist_info.join("RECORD")).unwrap();
+        assert!(record.contains("../pure/pure.py"), "{record}");
+
+        let distributions =
+            find_distributions_in_venv(tmpdir.path(), target.install_paths()).unwrap();
+        assert_eq!(distributions.len(), 1);
+    }
+
+    #[test]
+    fn test_install_with_destdir() {
+        let wheel_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl");
+        let wheel = Wheel::from_path(
+            &wheel_path,
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+
+        let destdir = tempfile::tempdir().unwrap();
+        let target =
+            TargetDirectory::new("/opt/lambda", "/usr/bin/python3").with_destdir(destdir.path());
+        assert_eq!(target.root(), destdir.path().join("opt/lambda"));
+
+        target
+            .install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+        assert!(target.root().join("black").join("__init__.py").is_file());
+        assert!(!Path::new("/opt/lambda/black").exists());
+
+        let distributions = find_distributions_in_directory(target.root()).unwrap();
+        assert_eq!(distributions.len(), 1);
+    }
+}
@@ -1,6 +1,7 @@
 //! Functionality to remove python distributions from an environment.
 
 use crate::types::Record;
+use crate::utils::{is_file_in_use, remove_file};
 use fs_err as fs;
 use indexmap::IndexSet;
 use itertools::Itertools;
@@ -27,6 +28,11 @@ pub enum UninstallDistributionError {
     #[error("failed to delete {0}")]
     FailedToDeleteFile(String, #[source] std::io::Error),
 
+    /// A file could not be deleted because it is in use by another process. This can happen on
+    /// Windows when, for instance, a DLL of the distribution is loaded by a running python process.
+    #[error("failed to delete {0} because it is in use by another process, close any programs that use the environment and try again")]
+    FileInUse(String, #[source] std::io::Error),
+
     /// Failed to delete a directory
     #[error("failed to delete {0}")]
     FailedToDeleteDirectory(String, #[source] std::io::Error),
@@ -60,8 +66,10 @@ pub fn uninstall_distribution(
     let mut directories = HashSet::new();
     for entry in record.into_iter() {
         let entry_path = site_packages_dir.join(&entry.path);
-        if let Err(e) = fs::remove_file(&entry_path) {
-            if e.kind() != std::io::ErrorKind::NotFound {
+        if let Err(e) = remove_file(&entry_path) {
+            if is_file_in_use(&e) {
+                return Err(UninstallDistributionError::FileInUse(entry.path, e));
+            } else if e.kind() != std::io::ErrorKind::NotFound {
                 return Err(UninstallDistributionError::FailedToDeleteFile(
                     entry.path, e,
                 ));
@@ -113,7 +121,7 @@ mod test {
         let site_packages_dir = temp_dir.path().join("site-packages");
         fs::create_dir(&site_packages_dir).unwrap();
         let dist_info_dir = Path::new("test-1.0.0.dist-info");
-        fs::create_dir(&site_packages_dir.join(dist_info_dir)).unwrap();
+        fs::create_dir(site_packages_dir.join(dist_info_dir)).unwrap();
 
         let files = [
             "test-1.0.0.dist-info/RECORD",
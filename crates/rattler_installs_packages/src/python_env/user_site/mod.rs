@@ -0,0 +1,216 @@
+//! Installing wheels into the user site-packages directory of an interpreter, the equivalent of
+//! `pip install --user`.
+use crate::artifacts::wheel::{InstallPaths, UnpackError, UnpackWheelOptions, UnpackedWheel};
+use crate::artifacts::Wheel;
+use crate::python_env::{FindPythonError, PythonInterpreterVersion};
+use serde::Deserialize;
+use std::io;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use thiserror::Error;
+
+/// An error that can occur when determining the user site of an interpreter.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum UserSiteError {
+    #[error(transparent)]
+    CouldNotFindPythonExecutable(#[from] FindPythonError),
+
+    #[error(transparent)]
+    FailedToExecute(#[from] io::Error),
+
+    #[error(transparent)]
+    FailedToParse(#[from] serde_json::Error),
+
+    #[error("execution failed with exit code {0}")]
+    FailedToRun(ExitStatus),
+
+    #[error("the user site-packages directory is disabled for the interpreter at {0}")]
+    Disabled(PathBuf),
+
+    #[error(
+        "the user site-packages directory is disabled for security reasons for the interpreter at {0}"
+    )]
+    DisabledForSecurity(PathBuf),
+}
+
+/// The information that is returned by `user_site.py`.
+#[derive(Debug, Deserialize)]
+struct UserSiteInfo {
+    enable_user_site: Option<bool>,
+    user_base: PathBuf,
+    user_site: PathBuf,
+    version: (u32, u32, u32),
+    windows: bool,
+    macos_framework: bool,
+}
+
+/// The user site-packages directory of an interpreter into which wheels can be installed.
+#[derive(Debug, Clone)]
+pub struct UserSite {
+    /// The user base directory, all install paths are relative to this directory.
+    user_base: PathBuf,
+    /// Install paths relative to the user base
+    install_paths: InstallPaths,
+    /// The interpreter whose user site this is
+    python_executable: PathBuf,
+}
+
+impl UserSite {
+    /// Determines the user site of the interpreter at `python`. The interpreter is executed to
+    /// determine the user base directory and whether the user site is enabled at all. Returns an
+    /// error if the interpreter does not use the user site, for instance because it runs in a
+    /// virtual environment or because `PYTHONNOUSERSITE` is set.
+    pub fn from_python(python: &Path) -> Result<Self, UserSiteError> {
+        let output = match Command::new(python)
+            .arg("-c")
+            .arg(include_str!("user_site.py"))
+            .output()
+        {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(UserSiteError::CouldNotFindPythonExecutable(
+                    FindPythonError::NotFound,
+                ))
+            }
+            Err(e) => return Err(UserSiteError::FailedToExecute(e)),
+            Ok(output) => output,
+        };
+
+        if !output.status.success() {
+            return Err(UserSiteError::FailedToRun(output.status));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let info: UserSiteInfo = serde_json::from_str(stdout.trim())?;
+        match info.enable_user_site {
+            Some(true) => {}
+            Some(false) => return Err(UserSiteError::Disabled(python.to_path_buf())),
+            None => return Err(UserSiteError::DisabledForSecurity(python.to_path_buf())),
+        }
+
+        let user_site = Self::new(
+            info.user_base,
+            info.version,
+            info.windows,
+            info.macos_framework,
+            python,
+        );
+        if user_site.site_packages() != info.user_site {
+            tracing::warn!(
+                "the user site-packages directory of the interpreter ({}) does not match the expected location ({})",
+                info.user_site.display(),
+                user_site.site_packages().display()
+            );
+        }
+        Ok(user_site)
+    }
+
+    /// Constructs a user site with the given `user_base` for an interpreter with the specified
+    /// version and platform. Prefer [`UserSite::from_python`] which also verifies that the user
+    /// site is enabled.
+    pub fn new(
+        user_base: impl Into<PathBuf>,
+        version: impl Into<PythonInterpreterVersion>,
+        windows: bool,
+        macos_framework: bool,
+        python_executable: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            user_base: user_base.into(),
+            install_paths: InstallPaths::for_user_site(version, windows, macos_framework),
+            python_executable: python_executable.into(),
+        }
+    }
+
+    /// Install a wheel into the user site
+    pub fn install_wheel(
+        &self,
+        wheel: &Wheel,
+        options: &UnpackWheelOptions,
+    ) -> Result<UnpackedWheel, UnpackError> {
+        wheel.unpack(
+            &self.user_base,
+            &self.install_paths,
+            &self.python_executable,
+            options,
+        )
+    }
+
+    /// Returns the [`InstallPaths`] relative to the user base directory.
+    pub fn install_paths(&self) -> &InstallPaths {
+        &self.install_paths
+    }
+
+    /// Returns the user base directory.
+    pub fn root(&self) -> &Path {
+        &self.user_base
+    }
+
+    /// Returns the absolute path of the user site-packages directory.
+    pub fn site_packages(&self) -> PathBuf {
+        self.user_base.join(self.install_paths.site_packages())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::system_python_executable;
+
+    #[test]
+    fn test_user_site_paths() {
+        let posix = UserSite::new("/home/user/.local", (3, 11, 4), false, false, "python");
+        assert_eq!(
+            posix.site_packages(),
+            Path::new("/home/user/.local/lib/python3.11/site-packages")
+        );
+        assert_eq!(posix.install_paths().scripts(), Path::new("bin"));
+        assert_eq!(
+            posix.install_paths().headers("foo"),
+            Path::new("include/python3.11/foo")
+        );
+
+        let framework = UserSite::new(
+            "/Users/user/Library/Python/3.11",
+            (3, 11, 4),
+            false,
+            true,
+            "python",
+        );
+        assert_eq!(
+            framework.site_packages(),
+            Path::new("/Users/user/Library/Python/3.11/lib/python/site-packages")
+        );
+
+        let windows = UserSite::new(
+            "C:/Users/user/AppData/Roaming/Python",
+            (3, 11, 4),
+            true,
+            false,
+            "python",
+        );
+        assert_eq!(
+            windows.install_paths().site_packages(),
+            Path::new("Python311").join("site-packages")
+        );
+        assert_eq!(
+            windows.install_paths().scripts(),
+            Path::new("Python311").join("Scripts")
+        );
+    }
+
+    #[test]
+    fn test_from_python() {
+        let Ok(python) = system_python_executable() else {
+            // This is fine, the test machine does not include a python binary.
+            return;
+        };
+        match UserSite::from_python(python) {
+            Ok(user_site) => println!("user site: {}", user_site.site_packages().display()),
+            // The user site is disabled when the tests are executed from a virtual environment
+            Err(UserSiteError::Disabled(_)) | Err(UserSiteError::DisabledForSecurity(_)) => {}
+            Err(e) => panic!("{e}"),
+        }
+    }
+}
@@ -0,0 +1,22 @@
+# A program that outputs the information that is required to install packages into the user site
+# directory of an interpreter in a JSON format.
+
+import json
+import os
+import site
+import sys
+import sysconfig
+
+information = {
+    # True if the user site is enabled, False if it was disabled by the user (for instance with
+    # `-s` or `PYTHONNOUSERSITE`) or because this is a virtual environment, and None if it was
+    # disabled for security reasons.
+    'enable_user_site': site.ENABLE_USER_SITE,
+    'user_base': site.getuserbase(),
+    'user_site': site.getusersitepackages(),
+    'version': [sys.version_info.major, sys.version_info.minor, sys.version_info.micro],
+    'windows': os.name == 'nt',
+    'macos_framework': sys.platform == 'darwin' and bool(sysconfig.get_config_var('PYTHONFRAMEWORK')),
+}
+
+json.dump(information, sys.stdout)
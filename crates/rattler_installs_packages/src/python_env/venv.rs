@@ -5,8 +5,8 @@
 use crate::artifacts::wheel::{InstallPaths, UnpackWheelOptions, Wheel};
 use crate::artifacts::wheel::{UnpackError, UnpackedWheel};
 use crate::python_env::{
-    system_python_executable, FindPythonError, ParsePythonInterpreterVersionError,
-    PythonInterpreterVersion,
+    find_distributions_in_venv, system_python_executable, Distribution, FindDistributionError,
+    FindPythonError, ParsePythonInterpreterVersionError, PythonInterpreterVersion,
 };
 use fs_err as fs;
 use std::ffi::OsStr;
@@ -28,6 +28,52 @@ pub fn copy_file<P: AsRef<Path>, U: AsRef<Path>>(from: P, to: U) -> std::io::Res
     Ok(())
 }
 
+/// Places the interpreter `from` at `to`, either by copying it or by using [`copy_file`]. When
+/// `replace` is true an existing file at `to` is removed first.
+#[cfg(not(windows))]
+fn place_interpreter(from: &Path, to: &Path, copies: bool, replace: bool) -> std::io::Result<()> {
+    if to.exists() || to.is_symlink() {
+        if !replace {
+            return Ok(());
+        }
+        fs::remove_file(to)?;
+    }
+    if copies {
+        fs::copy(from, to)?;
+        Ok(())
+    } else {
+        copy_file(from, to)
+    }
+}
+
+/// Options that influence how a virtual environment is created with [`VEnv::create_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct VEnvOptions {
+    /// The prompt that is displayed when the environment is activated. Defaults to the name of
+    /// the directory of the environment.
+    pub prompt: Option<String>,
+
+    /// Give the environment access to the packages installed in the site-packages directory of
+    /// the base interpreter.
+    pub system_site_packages: bool,
+
+    /// Copy the interpreter into the environment instead of symlinking it. On windows the
+    /// interpreter is always copied.
+    pub copies: bool,
+
+    /// Upgrade an existing environment in place, e.g. after the base interpreter was updated.
+    /// This replaces the interpreter executables of the environment while keeping the installed
+    /// packages.
+    pub upgrade: bool,
+
+    /// Create an environment that can be moved to another location. The environment is marked
+    /// as relocatable in its `pyvenv.cfg` file and the interpreter aliases refer to the
+    /// interpreter relative to their own location. Wheels should be installed into such an
+    /// environment with [`UnpackWheelOptions::relocatable`] so their scripts do not refer to the
+    /// absolute location of the environment either.
+    pub relocatable: bool,
+}
+
 /// Specifies where to find the python executable
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum PythonLocation {
@@ -118,6 +164,12 @@ impl VEnv {
         cmd.output()
     }
 
+    /// Returns the distributions that are installed in this virtual env. The `dist_info` paths of
+    /// the distributions are relative to [`VEnv::root`].
+    pub fn installed_distributions(&self) -> Result<Vec<Distribution>, FindDistributionError> {
+        find_distributions_in_venv(&self.location, &self.install_paths)
+    }
+
     /// Returns the [`InstallPaths`] that defines some of the common paths in the virtual env.
     pub fn install_paths(&self) -> &InstallPaths {
         &self.install_paths
@@ -143,7 +195,17 @@ impl VEnv {
     /// Create a virtual environment at specified directory
     /// for the platform we are running on
     pub fn create(venv_dir: &Path, python: PythonLocation) -> Result<VEnv, VEnvError> {
-        Self::create_custom(venv_dir, python, cfg!(windows))
+        Self::create_with_options(venv_dir, python, &VEnvOptions::default())
+    }
+
+    /// Create a virtual environment at specified directory for the platform we are running on
+    /// using the specified options.
+    pub fn create_with_options(
+        venv_dir: &Path,
+        python: PythonLocation,
+        options: &VEnvOptions,
+    ) -> Result<VEnv, VEnvError> {
+        Self::create_custom_with_options(venv_dir, python, cfg!(windows), options)
     }
 
     /// Create a virtual environment at specified directory
@@ -152,6 +214,15 @@ impl VEnv {
         venv_abs_dir: &Path,
         python: PythonLocation,
         windows: bool,
+    ) -> Result<VEnv, VEnvError> {
+        Self::create_custom_with_options(venv_abs_dir, python, windows, &VEnvOptions::default())
+    }
+
+    fn create_custom_with_options(
+        venv_abs_dir: &Path,
+        python: PythonLocation,
+        windows: bool,
+        options: &VEnvOptions,
     ) -> Result<VEnv, VEnvError> {
         let base_python_path = python.executable()?;
         let base_python_version = PythonInterpreterVersion::from_path(&base_python_path)?;
@@ -162,13 +233,23 @@ impl VEnv {
         let install_paths = InstallPaths::for_venv(base_python_version.clone(), windows);
 
         Self::create_install_paths(venv_abs_dir, &install_paths)?;
-        Self::create_pyvenv(venv_abs_dir, &base_python_path, base_python_version.clone())?;
+        Self::create_pyvenv(
+            venv_abs_dir,
+            &base_python_path,
+            base_python_version.clone(),
+            options,
+        )?;
 
         let exe_path = install_paths.scripts().join(base_python_name);
         let abs_exe_path = venv_abs_dir.join(exe_path);
 
         {
-            Self::setup_python(&abs_exe_path, &base_python_path, base_python_version)?;
+            Self::setup_python(
+                &abs_exe_path,
+                &base_python_path,
+                base_python_version,
+                options,
+            )?;
         }
 
         Ok(VEnv::new(venv_abs_dir.to_path_buf(), install_paths))
@@ -213,35 +294,43 @@ impl VEnv {
         venv_path: &Path,
         python_path: &Path,
         python_version: PythonInterpreterVersion,
+        options: &VEnvOptions,
     ) -> std::io::Result<()> {
-        let venv_name = venv_path
-            .file_name()
-            .and_then(OsStr::to_str)
-            .ok_or_else(|| {
-                std::io::Error::new(
-                    ErrorKind::InvalidData,
-                    format!(
-                        "cannot extract base name from venv path {}",
-                        venv_path.display()
-                    ),
-                )
-            })?;
-
-        let pyenv_cfg_content = format!(
+        let venv_name = match &options.prompt {
+            Some(prompt) => prompt.as_str(),
+            None => venv_path
+                .file_name()
+                .and_then(OsStr::to_str)
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "cannot extract base name from venv path {}",
+                            venv_path.display()
+                        ),
+                    )
+                })?,
+        };
+
+        let mut pyenv_cfg_content = format!(
             r#"
 home = {}
-include-system-site-packages = false
+include-system-site-packages = {}
 version = {}.{}.{}
 prompt = {}"#,
             python_path
                 .parent()
                 .expect("system python path should have parent folder")
                 .display(),
+            options.system_site_packages,
             python_version.major,
             python_version.minor,
             python_version.patch,
             venv_name,
         );
+        if options.relocatable {
+            pyenv_cfg_content.push_str("\nrelocatable = true");
+        }
 
         let cfg_path = Path::new(&venv_path).join("pyvenv.cfg");
         fs_err::write(cfg_path, pyenv_cfg_content)?;
@@ -253,6 +342,7 @@ prompt = {}"#,
         venv_exe_path: &Path,
         original_python_exe: &Path,
         python_version: PythonInterpreterVersion,
+        options: &VEnvOptions,
     ) -> std::io::Result<()> {
         let venv_bin = venv_exe_path
             .parent()
@@ -260,9 +350,12 @@ prompt = {}"#,
 
         #[cfg(not(windows))]
         {
-            if !venv_exe_path.exists() {
-                copy_file(original_python_exe, venv_exe_path)?;
-            }
+            place_interpreter(
+                original_python_exe,
+                venv_exe_path,
+                options.copies,
+                options.upgrade,
+            )?;
 
             let python_bins = [
                 "python",
@@ -272,10 +365,25 @@ prompt = {}"#,
 
             for bin_name in python_bins.into_iter() {
                 let venv_python_bin = venv_bin.join(bin_name);
-                if (!venv_python_bin.exists() && !venv_python_bin.is_symlink())
-                    && venv_exe_path != venv_python_bin
-                {
-                    copy_file(venv_exe_path, &venv_python_bin)?;
+                if venv_exe_path != venv_python_bin {
+                    // The aliases always point to the interpreter in the venv, so they only have
+                    // to be replaced if they are copies. Symlinks refer to the interpreter by its
+                    // file name so they remain valid when the environment is moved.
+                    let target = if options.copies {
+                        venv_exe_path
+                    } else {
+                        Path::new(
+                            venv_exe_path
+                                .file_name()
+                                .expect("venv exe binary should have a file name"),
+                        )
+                    };
+                    place_interpreter(
+                        target,
+                        &venv_python_bin,
+                        options.copies,
+                        options.upgrade && options.copies,
+                    )?;
                 }
             }
         }
@@ -310,7 +418,7 @@ prompt = {}"#,
                     .join(bin_name);
                 let venv_python_bin = venv_bin.join(bin_name);
 
-                if original_python_bin.exists() && !venv_python_bin.exists() {
+                if original_python_bin.exists() && (options.upgrade || !venv_python_bin.exists()) {
                     if original_python_scripts.is_file() {
                         copy_file(original_python_scripts, &venv_python_bin)?;
                     } else {
@@ -342,11 +450,13 @@ prompt = {}"#,
 
 #[cfg(test)]
 mod tests {
-    use super::VEnv;
+    use super::{VEnv, VEnvOptions};
+    use crate::artifacts::wheel::UnpackWheelOptions;
     use crate::python_env::PythonLocation;
     use crate::types::NormalizedPackageName;
     use std::env;
     use std::path::Path;
+    use std::process::Command;
     use std::str::FromStr;
 
     #[test]
@@ -418,6 +528,95 @@ mod tests {
         assert!(include_path.exists(), "include path is not created");
     }
 
+    #[test]
+    pub fn test_venv_options() {
+        let venv_dir = tempfile::tempdir().unwrap();
+        let options = VEnvOptions {
+            prompt: Some(String::from("my-prompt")),
+            system_site_packages: true,
+            copies: true,
+            upgrade: false,
+            relocatable: false,
+        };
+        let venv =
+            VEnv::create_with_options(venv_dir.path(), PythonLocation::System, &options).unwrap();
+
+        let pyvenv_cfg = fs_err::read_to_string(venv_dir.path().join("pyvenv.cfg")).unwrap();
+        assert!(pyvenv_cfg.contains("include-system-site-packages = true"));
+        assert!(pyvenv_cfg.contains("prompt = my-prompt"));
+        assert!(!venv.python_executable().is_symlink());
+
+        // Upgrading an existing environment in place keeps it functional
+        let venv = VEnv::create_with_options(
+            venv_dir.path(),
+            PythonLocation::System,
+            &VEnvOptions {
+                upgrade: true,
+                ..options
+            },
+        )
+        .unwrap();
+        let output = venv.execute_command("import sys; print(sys.prefix != sys.base_prefix)");
+        assert_eq!(
+            String::from_utf8(output.unwrap().stdout).unwrap().trim(),
+            "True"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test_relocatable_venv() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let venv_dir = tmpdir.path().join("before");
+        let venv = VEnv::create_with_options(
+            &venv_dir,
+            PythonLocation::System,
+            &VEnvOptions {
+                relocatable: true,
+                ..VEnvOptions::default()
+            },
+        )
+        .unwrap();
+
+        let wheel = crate::artifacts::Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+        venv.install_wheel(
+            &wheel,
+            &UnpackWheelOptions {
+                relocatable: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Move the environment to another location
+        let moved_dir = tmpdir.path().join("after");
+        fs_err::rename(&venv_dir, &moved_dir).unwrap();
+
+        let pyvenv_cfg = fs_err::read_to_string(moved_dir.join("pyvenv.cfg")).unwrap();
+        assert!(pyvenv_cfg.contains("relocatable = true"));
+
+        // Scripts must not refer to the original location
+        let script = fs_err::read_to_string(moved_dir.join("bin/black")).unwrap();
+        assert!(!script.contains(venv_dir.to_str().unwrap()), "{script}");
+
+        // The interpreter in the moved environment still uses the environment as its prefix
+        let output = Command::new(moved_dir.join("bin/python"))
+            .arg("-c")
+            .arg("import sys; print(sys.prefix)")
+            .output()
+            .unwrap();
+        let prefix = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            dunce::canonicalize(prefix.trim()).unwrap(),
+            dunce::canonicalize(&moved_dir).unwrap()
+        );
+    }
+
     #[test]
     pub fn test_same_venv_can_be_created_twice() {
         let venv_dir = tempfile::tempdir().unwrap();
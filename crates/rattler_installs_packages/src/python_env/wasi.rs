@@ -0,0 +1,114 @@
+//! Resolving packages for CPython builds that target the
+//! [WebAssembly System Interface](https://wasi.dev) (WASI).
+//!
+//! WASI builds of CPython, like the ones that are run with `wasmtime`, cannot load native
+//! extension modules from regular wheels. Only pure python wheels and wheels that were built
+//! explicitly for the `wasm32_wasi` platform can be used. Since the interpreter usually cannot be
+//! executed on the machine that resolves the environment, [`WasiTarget`] provides the environment
+//! markers and wheel tags of such a build instead of querying them from an interpreter.
+
+use crate::python_env::{
+    Pep508EnvMakers, PlatformTag, PythonInterpreterVersion, WheelTagTarget, WheelTags,
+};
+
+/// Describes a WASI build of CPython for which packages can be resolved.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WasiTarget {
+    python_version: PythonInterpreterVersion,
+    arch: String,
+}
+
+impl WasiTarget {
+    /// Constructs a description of a `wasm32` WASI build of the given python version.
+    pub fn new(python_version: PythonInterpreterVersion) -> Self {
+        Self {
+            python_version,
+            arch: String::from("wasm32"),
+        }
+    }
+
+    /// Returns the python version of the build.
+    pub fn python_version(&self) -> &PythonInterpreterVersion {
+        &self.python_version
+    }
+
+    /// Returns the platform tag of the build, e.g. `wasm32_wasi`.
+    pub fn platform(&self) -> PlatformTag {
+        PlatformTag::Wasi {
+            arch: self.arch.clone(),
+        }
+    }
+
+    /// Returns the description of the interpreter that is used to compute the compatible wheel
+    /// tags.
+    pub fn wheel_tag_target(&self) -> WheelTagTarget {
+        WheelTagTarget::cpython(
+            (self.python_version.major, self.python_version.minor),
+            self.platform(),
+        )
+    }
+
+    /// Returns the wheel tags that are compatible with the build.
+    pub fn wheel_tags(&self) -> WheelTags {
+        WheelTags::from_target(&self.wheel_tag_target())
+    }
+
+    /// Returns the environment markers of the build. These match the values that a WASI build of
+    /// CPython reports through the `platform` and `sys` modules.
+    pub fn env_markers(&self) -> Pep508EnvMakers {
+        let PythonInterpreterVersion {
+            major,
+            minor,
+            patch,
+        } = self.python_version;
+
+        let full_version = format!("{major}.{minor}.{patch}");
+        pep508_rs::MarkerEnvironment {
+            implementation_name: String::from("cpython"),
+            implementation_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            os_name: String::from("posix"),
+            platform_machine: self.arch.clone(),
+            platform_python_implementation: String::from("CPython"),
+            platform_release: String::from("0.0.0"),
+            platform_system: String::from("wasi"),
+            platform_version: String::from("0.0.0"),
+            python_full_version: full_version
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            python_version: format!("{major}.{minor}")
+                .parse()
+                .expect("a version formatted from integers is always valid"),
+            sys_platform: String::from("wasi"),
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::WheelTag;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_wasi_target() {
+        let target = WasiTarget::new(PythonInterpreterVersion::new(3, 12, 1));
+
+        let tags = target.wheel_tags();
+        let first = tags.tags().next().unwrap().to_string();
+        assert_eq!(first, "cp312-cp312-wasm32_wasi");
+        assert!(tags.is_compatible(&WheelTag::from_str("py3-none-any").unwrap()));
+        assert!(
+            !tags.is_compatible(&WheelTag::from_str("cp312-cp312-manylinux_2_17_x86_64").unwrap())
+        );
+        assert!(!tags
+            .is_compatible(&WheelTag::from_str("cp312-cp312-emscripten_3_1_45_wasm32").unwrap()));
+
+        let markers = target.env_markers();
+        assert_eq!(markers.sys_platform, "wasi");
+        assert_eq!(markers.platform_machine, "wasm32");
+        assert_eq!(markers.platform_system, "wasi");
+    }
+}
@@ -0,0 +1,166 @@
+//! Progress reporting for long running operations.
+//!
+//! A [`Reporter`] is notified when artifacts are downloaded, when their metadata is retrieved, when
+//! wheels are built from source and when distributions are installed or removed. It can be set on
+//! a [`crate::index::PackageDb`] with [`crate::index::PackageDb::with_reporter`], which is also
+//! used by the [`crate::wheel_builder::WheelBuilder`] unless it has a reporter of its own, and on
+//! the [`crate::install::InstallOptions`].
+
+use crate::python_env::Distribution;
+use crate::types::{ArtifactInfo, SourceArtifactName, WheelFilename};
+use std::fmt::Debug;
+use url::Url;
+
+/// Receives progress events of downloads, metadata, builds, installs and removals.
+///
+/// The `on_*_start` methods return an index that is passed to the other methods of the same
+/// operation, which allows implementations to keep track of operations that run concurrently. All
+/// methods do nothing by default. Because operations run in parallel, methods may be invoked
+/// concurrently from multiple threads.
+pub trait Reporter: Debug + Send + Sync {
+    /// Called when the download of the artifact at `url` starts. `total_bytes` is the size of the
+    /// artifact if the server reported it.
+    fn on_download_start(&self, _url: &Url, _total_bytes: Option<u64>) -> usize {
+        0
+    }
+
+    /// Called when more data of a download was received. `bytes_downloaded` is the total number
+    /// of bytes that was received so far.
+    fn on_download_progress(&self, _index: usize, _bytes_downloaded: u64) {}
+
+    /// Called when a download finished, also when it failed.
+    fn on_download_complete(&self, _index: usize) {}
+
+    /// Called when the metadata of an artifact was retrieved, either from the cache, from the
+    /// index or from the build backend. This happens for every version the resolver considers.
+    fn on_metadata_fetched(&self, _artifact: &ArtifactInfo) {}
+
+    /// Called when a wheel is about to be built from a source distribution or source tree.
+    fn on_build_start(&self, _name: &SourceArtifactName) -> usize {
+        0
+    }
+
+    /// Called for every line that the build backend writes to stdout or stderr while working on
+    /// `name`, also while it prepares the metadata of a source distribution outside of a build.
+    fn on_build_output(&self, _name: &SourceArtifactName, _line: &str) {}
+
+    /// Called when building a wheel finished, also when it failed.
+    fn on_build_complete(&self, _index: usize) {}
+
+    /// Called when a wheel is about to be installed.
+    fn on_install_start(&self, _name: &WheelFilename) -> usize {
+        0
+    }
+
+    /// Called when installing a wheel finished, also when it failed.
+    fn on_install_complete(&self, _index: usize) {}
+
+    /// Called when an installed distribution is about to be removed from an environment.
+    fn on_uninstall_start(&self, _distribution: &Distribution) -> usize {
+        0
+    }
+
+    /// Called when removing a distribution finished, also when it failed.
+    fn on_uninstall_complete(&self, _index: usize) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::UnpackWheelOptions;
+    use crate::artifacts::Wheel;
+    use crate::index::{ArtifactRequest, PackageDb, PackageSourcesBuilder};
+    use crate::install::{install_wheels, InstallOptions};
+    use crate::python_env::TargetDirectory;
+    use crate::types::NormalizedPackageName;
+    use parking_lot::Mutex;
+    use reqwest::Client;
+    use reqwest_middleware::ClientWithMiddleware;
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    #[derive(Debug, Default)]
+    struct RecordingReporter {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl Reporter for RecordingReporter {
+        fn on_install_start(&self, name: &WheelFilename) -> usize {
+            let mut events = self.events.lock();
+            events.push(format!("install {}", name.distribution.as_str()));
+            events.len()
+        }
+
+        fn on_install_complete(&self, index: usize) {
+            self.events.lock().push(format!("installed {index}"));
+        }
+
+        fn on_metadata_fetched(&self, artifact: &ArtifactInfo) {
+            self.events
+                .lock()
+                .push(format!("metadata {}", artifact.filename));
+        }
+    }
+
+    #[test]
+    fn test_install_is_reported() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = TargetDirectory::new(tmpdir.path(), "/usr/bin/python3");
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &NormalizedPackageName::from_str("miniblack").unwrap(),
+        )
+        .unwrap();
+
+        let reporter = Arc::new(RecordingReporter::default());
+        let options = UnpackWheelOptions {
+            install_options: InstallOptions {
+                reporter: Some(reporter.clone()),
+                ..InstallOptions::default()
+            },
+            ..Default::default()
+        };
+        install_wheels(&target, vec![(wheel, options)], None).unwrap();
+        assert_eq!(
+            *reporter.events.lock(),
+            ["install miniblack", "installed 1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_reported() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSourcesBuilder::new("https://pypi.org/simple/".parse().unwrap())
+            .with_find_links(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/wheels"))
+            .without_index()
+            .build()
+            .unwrap();
+        let reporter = Arc::new(RecordingReporter::default());
+        let package_db = PackageDb::new(
+            sources,
+            ClientWithMiddleware::from(Client::new()),
+            cache_dir.path(),
+        )
+        .unwrap()
+        .with_reporter(reporter.clone());
+
+        let name = NormalizedPackageName::from_str("miniblack").unwrap();
+        let artifacts = package_db
+            .available_artifacts(ArtifactRequest::FromIndex(name))
+            .await
+            .unwrap();
+        let (_, artifacts) = artifacts.first().unwrap();
+        let (_, metadata) = package_db
+            .get_metadata(artifacts, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.name.as_str(), "miniblack");
+        assert_eq!(
+            *reporter.events.lock(),
+            ["metadata miniblack-23.1.0-py3-none-any.whl"]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use super::{
+    policy::PolicyDecision,
     pypi_version_types::PypiPackageName,
     solve_options::{PreReleaseResolution, ResolveOptions, SDistResolution},
     PinnedPackage, PypiVersion, PypiVersionSet,
@@ -8,7 +9,8 @@ use crate::{
     index::{ArtifactRequest, PackageDb},
     python_env::WheelTags,
     types::{
-        ArtifactFromBytes, ArtifactInfo, ArtifactName, Extra, NormalizedPackageName, PackageName,
+        ArtifactFromBytes, ArtifactInfo, ArtifactName, BuildTag, Extra, NormalizedPackageName,
+        PackageName, WheelFilename,
     },
     wheel_builder::WheelBuilder,
 };
@@ -16,22 +18,32 @@ use elsa::FrozenMap;
 use itertools::Itertools;
 use miette::{Diagnostic, IntoDiagnostic, MietteDiagnostic};
 use parking_lot::Mutex;
-use pep440_rs::{Operator, VersionSpecifier, VersionSpecifiers};
+use pep440_rs::Version;
 use pep508_rs::{MarkerEnvironment, Requirement, VersionOrUrl};
 use resolvo::{
     Candidates, Dependencies, DependencyProvider, KnownDependencies, NameId, Pool, SolvableId,
     SolverCache,
 };
 use std::{
-    any::Any, borrow::Borrow, cmp::Ordering, collections::HashMap, rc::Rc, str::FromStr, sync::Arc,
+    any::Any,
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::HashMap,
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
 };
 use thiserror::Error;
+use tracing::Instrument;
 use url::Url;
 
 /// This is a [`DependencyProvider`] for PyPI packages
 pub(crate) struct PypiDependencyProvider {
     pub pool: Rc<Pool<PypiVersionSet, PypiPackageName>>,
     pub cached_artifacts: FrozenMap<SolvableId, Vec<Arc<ArtifactInfo>>>,
+    /// The requirements of every solvable whose markers evaluated to true.
+    pub dependencies: FrozenMap<SolvableId, Vec<Requirement>>,
     pub name_to_url: FrozenMap<NormalizedPackageName, String>,
     package_db: Arc<PackageDb>,
     wheel_builder: Arc<WheelBuilder>,
@@ -78,6 +90,7 @@ impl PypiDependencyProvider {
             markers,
             compatible_tags,
             cached_artifacts: Default::default(),
+            dependencies: Default::default(),
             favored_packages,
             locked_packages,
             name_to_url,
@@ -88,6 +101,7 @@ impl PypiDependencyProvider {
 
     fn filter_candidates<'a, A: Borrow<ArtifactInfo>>(
         &self,
+        name: &NormalizedPackageName,
         artifacts: &'a [A],
     ) -> Result<Vec<&'a A>, &'static str> {
         // Filter only artifacts we can work with
@@ -104,12 +118,25 @@ impl PypiDependencyProvider {
             return Err("it is yanked");
         }
 
-        // This should keep only the wheels
+        // Only keep the artifacts that match the required hashes
+        if let Some(required_hashes) = &self.options.required_hashes {
+            if !required_hashes.contains(name) {
+                return Err("hashes are required but none were specified for the package");
+            }
+            artifacts.retain(|a| required_hashes.matches(name, (*a).borrow().hashes.as_ref()));
+            if artifacts.is_empty() {
+                return Err("none of the artifacts match the required hashes");
+            }
+        }
+
+        // This should keep only the wheels and other binary artifacts
         let mut wheels = if self.options.sdist_resolution.allow_wheels() {
             let wheels = artifacts
                 .iter()
                 .copied()
-                .filter(|a| (*a).borrow().is::<Wheel>())
+                .filter(|a| {
+                    (*a).borrow().is::<Wheel>() || (*a).borrow().filename.as_custom().is_some()
+                })
                 .collect::<Vec<_>>();
 
             if !self.options.sdist_resolution.allow_sdists() && wheels.is_empty() {
@@ -158,31 +185,50 @@ impl PypiDependencyProvider {
 
         // Filter based on compatibility
         if self.options.sdist_resolution.allow_wheels() {
+            let has_wheels = !wheels.is_empty();
             if let Some(compatible_tags) = &self.compatible_tags {
+                let allow_version_specific_abi =
+                    self.options.abi3_resolution.allow_version_specific_abi();
+                let artifact_registry = self.package_db.artifact_registry();
                 wheels.retain(|artifact| match &(*artifact).borrow().filename {
-                    ArtifactName::Wheel(wheel_name) => wheel_name
-                        .all_tags_iter()
-                        .any(|t| compatible_tags.is_compatible(&t)),
+                    ArtifactName::Wheel(wheel_name) => {
+                        (allow_version_specific_abi || !wheel_name.is_abi_version_specific())
+                            && wheel_name
+                                .all_tags_iter()
+                                .any(|t| compatible_tags.is_compatible(&t))
+                    }
+                    ArtifactName::Custom(name) => {
+                        artifact_registry.is_compatible(name, Some(compatible_tags))
+                    }
                     ArtifactName::SDist(_) => false,
                     ArtifactName::STree(_) => false,
                 });
 
                 // Sort the artifacts from most compatible to least compatible, this ensures that we
                 // check the most compatible artifacts for dependencies first.
-                // this only needs to be done for wheels
+                // this only needs to be done for wheels, custom artifacts are considered last.
+                let prefer_abi3 = self.options.abi3_resolution.prefer_abi3();
                 wheels.sort_by_cached_key(|a| {
-                    -(*a)
-                        .borrow()
-                        .filename
-                        .as_wheel()
-                        .expect("only wheels are considered")
-                        .all_tags_iter()
-                        .filter_map(|tag| compatible_tags.compatibility(&tag))
-                        .max()
-                        .unwrap_or(0)
+                    (*a).borrow().filename.as_wheel().map_or(
+                        (true, Default::default()),
+                        |wheel_name| {
+                            (
+                                false,
+                                wheel_preference(wheel_name, compatible_tags, prefer_abi3),
+                            )
+                        },
+                    )
                 });
             }
 
+            let is_wasi = self
+                .compatible_tags
+                .as_ref()
+                .is_some_and(|compatible_tags| compatible_tags.is_wasi());
+            if is_wasi && has_wheels && wheels.is_empty() && sdists.is_empty() {
+                return Err("only native wheels are available, which cannot be used on WASI");
+            }
+
             if !self.options.sdist_resolution.allow_sdists() && wheels.is_empty() {
                 return Err(
                     "none of the artifacts are compatible with the Python interpreter or glibc version",
@@ -205,6 +251,40 @@ impl PypiDependencyProvider {
         Ok(artifacts)
     }
 
+    /// Returns the reason why the package policy of the package database blocks the `version` of
+    /// the package `name`, if it does.
+    fn blocked_by_policy(
+        &self,
+        name: &NormalizedPackageName,
+        version: Option<&Version>,
+    ) -> Option<String> {
+        match self.package_db.package_policy()?.check(name, version) {
+            PolicyDecision::Allow => None,
+            PolicyDecision::Deny(reason) => Some(reason),
+        }
+    }
+
+    /// Returns the artifacts of a locked or favored package that match the required hashes, or
+    /// all of them if hashes are not required.
+    fn hashed_artifacts(
+        &self,
+        name: &NormalizedPackageName,
+        artifacts: &[Arc<ArtifactInfo>],
+    ) -> Result<Vec<Arc<ArtifactInfo>>, &'static str> {
+        let Some(required_hashes) = &self.options.required_hashes else {
+            return Ok(artifacts.to_vec());
+        };
+        let artifacts = artifacts
+            .iter()
+            .filter(|artifact| required_hashes.matches(name, artifact.hashes.as_ref()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if artifacts.is_empty() {
+            return Err("none of the artifacts match the required hashes");
+        }
+        Ok(artifacts)
+    }
+
     fn solvable_has_artifact_type<S: ArtifactFromBytes>(&self, solvable_id: SolvableId) -> bool {
         self.cached_artifacts
             .get(&solvable_id)
@@ -225,6 +305,31 @@ impl PypiDependencyProvider {
     }
 }
 
+/// Returns a key that orders wheels from most preferred to least preferred.
+///
+/// Wheels are first ordered by how specific their tags are to the platform. Wheels that are
+/// equally compatible are ordered by their build tag, the highest build tag first. Wheels without
+/// a build tag sort below wheels with one. See the
+/// [binary distribution format](https://packaging.python.org/en/latest/specifications/binary-distribution-format/#file-name-convention).
+///
+/// If `prefer_abi3` is true, wheels that target the stable ABI are sorted before all other wheels.
+fn wheel_preference(
+    name: &WheelFilename,
+    compatible_tags: &WheelTags,
+    prefer_abi3: bool,
+) -> (bool, Reverse<i32>, Reverse<Option<BuildTag>>) {
+    let compatibility = name
+        .all_tags_iter()
+        .filter_map(|tag| compatible_tags.compatibility(&tag))
+        .max()
+        .unwrap_or(i32::MIN);
+    (
+        prefer_abi3 && !name.is_abi3(),
+        Reverse(compatibility),
+        Reverse(name.build_tag.clone()),
+    )
+}
+
 #[derive(Debug, Error, Diagnostic, Clone)]
 pub(crate) enum MetadataError {
     #[error("Extraction of metadata in case of wheels or building in case of sdists returned no results for following artifacts:\n{0}")]
@@ -236,6 +341,8 @@ pub(crate) enum MetadataError {
         #[related]
         errors: Vec<MietteDiagnostic>,
     },
+    #[error("the resolution was interrupted")]
+    Interrupted,
 }
 
 impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDependencyProvider {
@@ -244,6 +351,10 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
     }
 
     fn should_cancel_with_value(&self) -> Option<Box<dyn Any>> {
+        if self.package_db.cancellation_token().is_cancelled() {
+            return Some(Box::new(MetadataError::Interrupted));
+        }
+
         // Supply the error message
         self.should_cancel_with_value
             .lock()
@@ -299,6 +410,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
         })
     }
 
+    #[tracing::instrument(skip_all, fields(package = %self.pool.resolve_package_name(name), versions))]
     async fn get_candidates(&self, name: NameId) -> Option<Candidates> {
         let package_name = self.pool.resolve_package_name(name);
         tracing::info!("collecting {}", package_name);
@@ -317,6 +429,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
         };
 
         let lease = self.aquire_lease_to_run().await;
+        let start = Instant::now();
         let result: Result<_, miette::Report> = tokio::spawn({
             let package_db = self.package_db.clone();
             async move {
@@ -324,9 +437,13 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 drop(lease);
                 Ok(result)
             }
+            .in_current_span()
         })
         .await
         .expect("cancelled");
+        if let Some(profiler) = &self.options.profiler {
+            profiler.record_candidates(package_name.base(), start.elapsed());
+        }
 
         let artifacts = match result {
             Ok(artifacts) => artifacts,
@@ -337,6 +454,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 return None;
             }
         };
+        tracing::Span::current().record("versions", artifacts.len());
         let mut candidates = Candidates::default();
         let locked_package = self.locked_packages.get(package_name.base());
         let favored_package = self.favored_packages.get(package_name.base());
@@ -356,6 +474,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
             PreReleaseResolution::Allow => true,
         };
 
+        let mut favored_index_artifacts = None;
         for (artifact_version, artifacts) in artifacts.iter() {
             // Skip this version if a locked or favored version exists for this version. It will be
             // added below.
@@ -369,6 +488,11 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                     }
                 }
                 PypiVersion::Version { version, .. } => {
+                    if favored_package.is_some_and(|p| {
+                        p.url.is_none() && p.artifacts.is_empty() && &p.version == version
+                    }) {
+                        favored_index_artifacts = Some(artifacts.as_slice());
+                    }
                     if locked_package.map(|p| &p.version) == Some(version)
                         || favored_package.map(|p| &p.version) == Some(version)
                     {
@@ -391,8 +515,20 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
             let solvable_id = self.pool.intern_solvable(name, internable_version);
             candidates.candidates.push(solvable_id);
 
+            // Exclude the versions that are blocked by the package policy
+            let version = match artifact_version {
+                PypiVersion::Version { version, .. } => Some(version),
+                PypiVersion::Url(_) => None,
+            };
+            if let Some(reason) = self.blocked_by_policy(package_name.base(), version) {
+                candidates
+                    .excluded
+                    .push((solvable_id, self.pool.intern_string(reason)));
+                continue;
+            }
+
             // Determine the candidates
-            match self.filter_candidates(artifacts) {
+            match self.filter_candidates(package_name.base(), artifacts) {
                 Ok(artifacts) => {
                     self.cached_artifacts
                         .insert(solvable_id, artifacts.into_iter().cloned().collect());
@@ -417,35 +553,69 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
             };
             let solvable_id = self.pool.intern_solvable(name, version);
             candidates.candidates.push(solvable_id);
-            candidates.locked = Some(solvable_id);
-            self.cached_artifacts
-                .insert(solvable_id, locked.artifacts.clone());
+            let artifacts = match self.blocked_by_policy(package_name.base(), Some(&locked.version))
+            {
+                Some(reason) => Err(reason),
+                None => self
+                    .hashed_artifacts(package_name.base(), &locked.artifacts)
+                    .map_err(String::from),
+            };
+            match artifacts {
+                Ok(artifacts) => {
+                    candidates.locked = Some(solvable_id);
+                    self.cached_artifacts.insert(solvable_id, artifacts);
+                }
+                Err(reason) => candidates
+                    .excluded
+                    .push((solvable_id, self.pool.intern_string(reason))),
+            }
         }
 
-        // Add a favored dependency
+        // Add a favored dependency. A favored package without artifacts uses the artifacts of its
+        // version on the index, it is not favored if that version is not available.
         if let Some(favored) = self.favored_packages.get(package_name.base()) {
-            let version = if let Some(url) = &favored.url {
-                PypiVersion::Url(url.clone())
-            } else {
-                PypiVersion::Version {
-                    version: favored.version.clone(),
-                    package_allows_prerelease: favored.version.any_prerelease(),
+            let favored_artifacts = favored_index_artifacts.unwrap_or(&favored.artifacts);
+            if favored.url.is_some() || !favored_artifacts.is_empty() {
+                let version = if let Some(url) = &favored.url {
+                    PypiVersion::Url(url.clone())
+                } else {
+                    PypiVersion::Version {
+                        version: favored.version.clone(),
+                        package_allows_prerelease: favored.version.any_prerelease(),
+                    }
+                };
+                let solvable_id = self.pool.intern_solvable(name, version);
+                candidates.candidates.push(solvable_id);
+                let artifacts =
+                    match self.blocked_by_policy(package_name.base(), Some(&favored.version)) {
+                        Some(reason) => Err(reason),
+                        None => self
+                            .hashed_artifacts(package_name.base(), favored_artifacts)
+                            .map_err(String::from),
+                    };
+                match artifacts {
+                    Ok(artifacts) => {
+                        candidates.favored = Some(solvable_id);
+                        self.cached_artifacts.insert(solvable_id, artifacts);
+                    }
+                    Err(reason) => candidates
+                        .excluded
+                        .push((solvable_id, self.pool.intern_string(reason))),
                 }
-            };
-            let solvable_id = self.pool.intern_solvable(name, version);
-            candidates.candidates.push(solvable_id);
-            candidates.favored = Some(solvable_id);
-            self.cached_artifacts
-                .insert(solvable_id, favored.artifacts.clone());
+            }
         }
 
         Some(candidates)
     }
 
+    #[tracing::instrument(skip_all, fields(package, version))]
     async fn get_dependencies(&self, solvable_id: SolvableId) -> Dependencies {
         let solvable = self.pool.resolve_solvable(solvable_id);
         let package_name = self.pool.resolve_package_name(solvable.name_id());
         let package_version = solvable.inner();
+        let span = tracing::Span::current();
+        span.record("package", tracing::field::display(package_name));
+        span.record("version", tracing::field::display(package_version));
 
         tracing::info!(
             "obtaining dependency information from {}={}",
@@ -462,19 +632,12 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 .pool
                 .lookup_package_name(&PypiPackageName::Base(package_name.clone()))
                 .expect("base package not found while resolving extra");
-            let specifiers = match package_version {
-                PypiVersion::Version { version, .. } => {
-                    VersionOrUrl::VersionSpecifier(VersionSpecifiers::from_iter([
-                        VersionSpecifier::new(Operator::ExactEqual, version.clone(), false)
-                            .expect("failed to construct equality version specifier"),
-                    ]))
-                }
-                PypiVersion::Url(url_version) => VersionOrUrl::Url(url_version.clone()),
-            };
-
             let version_set_id = self.pool.intern_version_set(
                 base_name_id,
-                PypiVersionSet::from_spec(Some(specifiers), &self.options.pre_release_resolution),
+                PypiVersionSet::from_spec(
+                    Some(package_version.exact_spec()),
+                    &self.options.pre_release_resolution,
+                ),
             );
             dependencies.requirements.push(version_set_id);
         }
@@ -518,11 +681,18 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
             let wheel_builder = self.wheel_builder.clone();
             let artifacts = artifacts.to_vec();
             let lease = self.aquire_lease_to_run().await;
+            let profiler = self.options.profiler.clone();
+            let package_name = package_name.base().clone();
+            let package_version = package_version.clone();
             async move {
-                if let Some((ai, metadata)) = package_db
+                let start = Instant::now();
+                let metadata = package_db
                     .get_metadata(&artifacts, Some(&wheel_builder))
-                    .await?
-                {
+                    .await;
+                if let Some(profiler) = profiler {
+                    profiler.record_metadata(&package_name, package_version, start.elapsed());
+                }
+                if let Some((ai, metadata)) = metadata? {
                     drop(lease);
                     Ok(Some((ai.clone(), metadata)))
                 } else {
@@ -530,6 +700,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                     Ok(None)
                 }
             }
+            .in_current_span()
         })
         .await
         .expect("cancelled");
@@ -573,20 +744,10 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 let extra_name_id = self
                     .pool
                     .intern_package_name(PypiPackageName::Extra(package_name.clone(), extra));
-
-                let specifiers = match package_version {
-                    PypiVersion::Version { version, .. } => {
-                        VersionOrUrl::VersionSpecifier(VersionSpecifiers::from_iter([
-                            VersionSpecifier::new(Operator::ExactEqual, version.clone(), false)
-                                .expect("failed to construct equality version specifier"),
-                        ]))
-                    }
-                    PypiVersion::Url(url_version) => VersionOrUrl::Url(url_version.clone()),
-                };
                 let version_set_id = self.pool.intern_version_set(
                     extra_name_id,
                     PypiVersionSet::from_spec(
-                        Some(specifiers),
+                        Some(package_version.exact_spec()),
                         &self.options.pre_release_resolution,
                     ),
                 );
@@ -599,6 +760,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
             .into_iter()
             .map(|e| e.as_str())
             .collect::<Vec<_>>();
+        let mut applicable_requirements = Vec::new();
         for requirement in metadata.requires_dist {
             // Evaluate environment markers
             if let Some(markers) = requirement.marker.as_ref() {
@@ -606,6 +768,7 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                     continue;
                 }
             }
+            applicable_requirements.push(requirement.clone());
 
             // Add the dependency to the pool
             let Requirement {
@@ -615,31 +778,45 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 ..
             } = requirement;
             let name = PackageName::from_str(&name).expect("invalid package name");
-            let dependency_name_id = self
-                .pool
-                .intern_package_name(PypiPackageName::Base(name.clone().into()));
 
-            let version_set_id = self.pool.intern_version_set(
-                dependency_name_id,
-                PypiVersionSet::from_spec(
-                    version_or_url.clone(),
-                    &self.options.pre_release_resolution,
-                ),
-            );
+            // A package that requires its own extras, e.g. `demo[all]` requiring `demo[a,b]`,
+            // selects these extras of the same version, regardless of the version specifier. The
+            // requirement on the solvable itself is skipped, it is always satisfied and the solver
+            // cannot handle a solvable that depends on itself.
+            let self_reference = package_name.base() == &NormalizedPackageName::from(name.clone());
+            let version_or_url = if self_reference {
+                Some(package_version.exact_spec())
+            } else {
+                self.options
+                    .local_version_or_url(&name.clone().into(), version_or_url)
+            };
+
+            let dependency_name = PypiPackageName::Base(name.clone().into());
+            if &dependency_name != package_name {
+                let dependency_name_id = self.pool.intern_package_name(dependency_name);
+                let version_set_id = self.pool.intern_version_set(
+                    dependency_name_id,
+                    PypiVersionSet::from_spec(
+                        version_or_url.clone(),
+                        &self.options.pre_release_resolution,
+                    ),
+                );
+                dependencies.requirements.push(version_set_id);
+            }
 
             if let Some(VersionOrUrl::Url(url)) = version_or_url.clone() {
                 self.name_to_url
                     .insert(name.clone().into(), url.clone().as_str().to_owned());
             }
 
-            dependencies.requirements.push(version_set_id);
-
             // Add a unique package for each extra/optional dependency
             for extra in extras.into_iter().flatten() {
                 let extra = Extra::from_str(&extra).expect("invalid extra name");
-                let dependency_name_id = self
-                    .pool
-                    .intern_package_name(PypiPackageName::Extra(name.clone().into(), extra));
+                let dependency_name = PypiPackageName::Extra(name.clone().into(), extra);
+                if &dependency_name == package_name {
+                    continue;
+                }
+                let dependency_name_id = self.pool.intern_package_name(dependency_name);
                 let version_set_id = self.pool.intern_version_set(
                     dependency_name_id,
                     PypiVersionSet::from_spec(
@@ -650,7 +827,101 @@ impl<'p> DependencyProvider<PypiVersionSet, PypiPackageName> for &'p PypiDepende
                 dependencies.requirements.push(version_set_id);
             }
         }
+        self.dependencies
+            .insert(solvable_id, applicable_requirements);
 
         Dependencies::Known(dependencies)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::python_env::WheelTag;
+
+    fn sorted_wheels(
+        filenames: &[&str],
+        compatible_tags: &WheelTags,
+        prefer_abi3: bool,
+    ) -> Vec<String> {
+        let package_name = "foo".parse().unwrap();
+        filenames
+            .iter()
+            .map(|f| WheelFilename::from_filename(f, &package_name).unwrap())
+            .sorted_by_cached_key(|name| wheel_preference(name, compatible_tags, prefer_abi3))
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_prefer_highest_build_tag() {
+        let compatible_tags: WheelTags = ["cp311-cp311-manylinux_2_17_x86_64", "py3-none-any"]
+            .into_iter()
+            .map(|t| t.parse::<WheelTag>().unwrap())
+            .collect();
+
+        let sorted = sorted_wheels(
+            &[
+                "foo-1.0-py3-none-any.whl",
+                "foo-1.0-1-py3-none-any.whl",
+                "foo-1.0-2a-py3-none-any.whl",
+                "foo-1.0-10-py3-none-any.whl",
+                "foo-1.0-2-py3-none-any.whl",
+                "foo-1.0-1-cp311-cp311-manylinux_2_17_x86_64.whl",
+            ],
+            &compatible_tags,
+            false,
+        );
+
+        assert_eq!(
+            sorted,
+            vec![
+                // More specific tags always win, regardless of the build tag
+                "foo-1.0-1-cp311-cp311-manylinux_2_17_x86_64.whl",
+                // Numbers are compared numerically, then the suffix is compared as a string
+                "foo-1.0-10-py3-none-any.whl",
+                "foo-1.0-2a-py3-none-any.whl",
+                "foo-1.0-2-py3-none-any.whl",
+                "foo-1.0-1-py3-none-any.whl",
+                // No build tag sorts below any build tag
+                "foo-1.0-py3-none-any.whl",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefer_abi3() {
+        let compatible_tags: WheelTags = [
+            "cp311-cp311-manylinux_2_17_x86_64",
+            "cp311-abi3-manylinux_2_17_x86_64",
+            "cp38-abi3-manylinux_2_17_x86_64",
+            "py3-none-any",
+        ]
+        .into_iter()
+        .map(|t| t.parse::<WheelTag>().unwrap())
+        .collect();
+
+        let wheels = [
+            "foo-1.0-py3-none-any.whl",
+            "foo-1.0-cp38-abi3-manylinux_2_17_x86_64.whl",
+            "foo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+        ];
+
+        assert_eq!(
+            sorted_wheels(&wheels, &compatible_tags, false),
+            vec![
+                "foo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+                "foo-1.0-cp38-abi3-manylinux_2_17_x86_64.whl",
+                "foo-1.0-py3-none-any.whl",
+            ]
+        );
+        assert_eq!(
+            sorted_wheels(&wheels, &compatible_tags, true),
+            vec![
+                "foo-1.0-cp38-abi3-manylinux_2_17_x86_64.whl",
+                "foo-1.0-cp311-cp311-manylinux_2_17_x86_64.whl",
+                "foo-1.0-py3-none-any.whl",
+            ]
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use crate::python_env::Distribution;
+use crate::resolve::PinnedPackage;
+use crate::types::{Extra, NormalizedPackageName, PackageName, WheelCoreMetadata};
+use fs_err as fs;
+use indexmap::IndexMap;
+use pep440_rs::Version;
+use pep508_rs::{MarkerEnvironment, Requirement, VersionOrUrl};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A package in a [`DependencyGraph`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyNode {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The version of the package
+    pub version: Version,
+
+    /// The extras of the package that are selected
+    pub extras: Vec<Extra>,
+
+    /// The requirements of the package that apply to the environment
+    pub dependencies: Vec<Requirement>,
+}
+
+/// The dependencies between the packages of a resolved or installed environment.
+///
+/// The graph is built from the requirements of every package, so it can be rendered both top-down
+/// (what does a package depend on) and inverted (which packages depend on a package), see
+/// [`DependencyGraph::render`].
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    nodes: IndexMap<NormalizedPackageName, DependencyNode>,
+}
+
+impl DependencyGraph {
+    /// Constructs the graph of the result of [`super::resolve`].
+    pub fn from_pinned(packages: &[PinnedPackage]) -> Self {
+        Self::from_nodes(packages.iter().map(|package| {
+            let mut extras = package.extras.iter().cloned().collect::<Vec<_>>();
+            extras.sort();
+            DependencyNode {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                extras,
+                dependencies: package.dependencies.clone(),
+            }
+        }))
+    }
+
+    /// Constructs the graph of the distributions that are installed in the environment at `root`
+    /// by reading their `METADATA` files. Since it is not recorded which extras of a distribution
+    /// were installed, only the requirements that do not depend on an extra are included.
+    pub fn from_installed(
+        root: &Path,
+        distributions: &[Distribution],
+        env_markers: &MarkerEnvironment,
+    ) -> Self {
+        Self::from_nodes(distributions.iter().map(|distribution| {
+            let dependencies = fs::read(root.join(&distribution.dist_info).join("METADATA"))
+                .ok()
+                .and_then(|bytes| WheelCoreMetadata::try_from(bytes.as_slice()).ok())
+                .map(|metadata| metadata.requires_dist)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|requirement| {
+                    requirement
+                        .marker
+                        .as_ref()
+                        .map_or(true, |marker| marker.evaluate(env_markers, &[]))
+                })
+                .collect();
+            DependencyNode {
+                name: distribution.name.clone(),
+                version: distribution.version.clone(),
+                extras: Vec::new(),
+                dependencies,
+            }
+        }))
+    }
+
+    fn from_nodes(nodes: impl IntoIterator<Item = DependencyNode>) -> Self {
+        let mut nodes = nodes
+            .into_iter()
+            .map(|node| (node.name.clone(), node))
+            .collect::<IndexMap<_, _>>();
+        nodes.sort_keys();
+        Self { nodes }
+    }
+
+    /// Returns the package with the given name.
+    pub fn node(&self, name: &NormalizedPackageName) -> Option<&DependencyNode> {
+        self.nodes.get(name)
+    }
+
+    /// Returns all packages in the graph, ordered by name.
+    pub fn nodes(&self) -> impl Iterator<Item = &DependencyNode> + '_ {
+        self.nodes.values()
+    }
+
+    /// Returns the requirements of the package with the given name together with the package
+    /// that satisfies them, or `None` if the package is not part of the graph.
+    pub fn dependencies<'a>(
+        &'a self,
+        name: &NormalizedPackageName,
+    ) -> impl Iterator<Item = (&'a Requirement, Option<&'a DependencyNode>)> + 'a {
+        self.nodes
+            .get(name)
+            .into_iter()
+            .flat_map(|node| node.dependencies.iter())
+            .map(|requirement| (requirement, self.requirement_node(requirement)))
+    }
+
+    /// Returns the packages that depend on the package with the given name, together with the
+    /// requirement through which they depend on it.
+    pub fn dependents<'a>(
+        &'a self,
+        name: &'a NormalizedPackageName,
+    ) -> impl Iterator<Item = (&'a DependencyNode, &'a Requirement)> + 'a {
+        self.nodes.values().flat_map(move |node| {
+            node.dependencies
+                .iter()
+                .filter(move |requirement| requirement_name(requirement).as_ref() == Some(name))
+                .map(move |requirement| (node, requirement))
+        })
+    }
+
+    /// Returns the packages that no other package in the graph depends on.
+    pub fn roots(&self) -> Vec<&DependencyNode> {
+        let dependencies = self
+            .nodes
+            .values()
+            .flat_map(|node| {
+                node.dependencies
+                    .iter()
+                    .filter_map(requirement_name)
+                    .filter(move |name| name != &node.name)
+            })
+            .collect::<HashSet<_>>();
+        self.nodes
+            .values()
+            .filter(|node| !dependencies.contains(&node.name))
+            .collect()
+    }
+
+    /// Returns every chain of requirements through which one of the `roots` depends on the package
+    /// with the given name. A chain consists of the packages from the root down to the package,
+    /// each together with the requirement through which it requires the next package in the
+    /// chain. If the package is itself a root an empty chain is included.
+    pub fn requirement_chains<'a>(
+        &'a self,
+        name: &'a NormalizedPackageName,
+        roots: &[&NormalizedPackageName],
+    ) -> Vec<Vec<(&'a DependencyNode, &'a Requirement)>> {
+        let mut chains = Vec::new();
+        let mut chain = Vec::new();
+        self.collect_chains(name, roots, &mut chain, &mut chains);
+        chains
+    }
+
+    fn collect_chains<'a>(
+        &'a self,
+        name: &'a NormalizedPackageName,
+        roots: &[&NormalizedPackageName],
+        chain: &mut Vec<(&'a DependencyNode, &'a Requirement)>,
+        chains: &mut Vec<Vec<(&'a DependencyNode, &'a Requirement)>>,
+    ) {
+        if roots.contains(&name) {
+            chains.push(chain.iter().rev().cloned().collect());
+        }
+        for (dependent, requirement) in self.dependents(name) {
+            // Skip cycles
+            if &dependent.name == name || chain.iter().any(|(node, _)| node.name == dependent.name)
+            {
+                continue;
+            }
+            chain.push((dependent, requirement));
+            self.collect_chains(&dependent.name, roots, chain, chains);
+            chain.pop();
+        }
+    }
+
+    /// Returns the packages that are no longer needed when the `removed` packages are removed
+    /// from the graph, ordered by name: the dependencies of the removed packages that no remaining package depends
+    /// on. Packages for which `keep` returns true, e.g. because they were explicitly requested by
+    /// the user, are never considered orphans.
+    pub fn orphans(
+        &self,
+        removed: &[NormalizedPackageName],
+        keep: impl Fn(&DependencyNode) -> bool,
+    ) -> Vec<&DependencyNode> {
+        let mut removed = removed.iter().collect::<HashSet<_>>();
+        let mut orphans = Vec::new();
+        loop {
+            let orphan = self.nodes.values().find(|node| {
+                !removed.contains(&node.name)
+                    && !keep(node)
+                    && self
+                        .dependents(&node.name)
+                        .any(|(dependent, _)| removed.contains(&dependent.name))
+                    && self.dependents(&node.name).all(|(dependent, _)| {
+                        dependent.name == node.name || removed.contains(&dependent.name)
+                    })
+            });
+            let Some(orphan) = orphan else {
+                break;
+            };
+            removed.insert(&orphan.name);
+            orphans.push(orphan);
+        }
+        orphans.sort_by(|a, b| a.name.cmp(&b.name));
+        orphans
+    }
+
+    /// Renders the graph as a tree, starting at the given packages. Every package is only
+    /// expanded once, later occurrences are marked with `(*)`.
+    ///
+    /// If `invert` is true the tree shows the packages that depend on the packages instead of
+    /// their dependencies.
+    pub fn render(&self, roots: &[&NormalizedPackageName], invert: bool) -> String {
+        let mut output = String::new();
+        let mut expanded = HashSet::new();
+        for root in roots {
+            let Some(node) = self.nodes.get(*root) else {
+                continue;
+            };
+            writeln!(output, "{}", node_label(node)).expect("writing to a string cannot fail");
+            self.render_children(node, invert, "", &mut expanded, &mut output);
+        }
+        output
+    }
+
+    fn render_children<'a>(
+        &'a self,
+        node: &'a DependencyNode,
+        invert: bool,
+        prefix: &str,
+        expanded: &mut HashSet<&'a NormalizedPackageName>,
+        output: &mut String,
+    ) {
+        expanded.insert(&node.name);
+
+        let children: Vec<(String, Option<&DependencyNode>)> = if invert {
+            self.dependents(&node.name)
+                .map(|(dependent, requirement)| {
+                    let label = format!("{} [requires: {}]", node_label(dependent), requirement);
+                    (label, Some(dependent))
+                })
+                .collect()
+        } else {
+            self.dependencies(&node.name)
+                .map(|(requirement, dependency)| {
+                    let label = match dependency {
+                        Some(dependency) => format!(
+                            "{} [required: {}]",
+                            node_label(dependency),
+                            requirement_constraints(requirement)
+                        ),
+                        None => format!("{} (missing)", requirement),
+                    };
+                    (label, dependency)
+                })
+                .collect()
+        };
+
+        let count = children.len();
+        for (index, (label, child)) in children.into_iter().enumerate() {
+            let last = index + 1 == count;
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let repeated = child.is_some_and(|child| expanded.contains(&child.name));
+            writeln!(
+                output,
+                "{prefix}{branch}{label}{}",
+                if repeated { " (*)" } else { "" }
+            )
+            .expect("writing to a string cannot fail");
+            if let (Some(child), false) = (child, repeated) {
+                self.render_children(
+                    child,
+                    invert,
+                    &format!("{prefix}{indent}"),
+                    expanded,
+                    output,
+                );
+            }
+        }
+    }
+
+    fn requirement_node(&self, requirement: &Requirement) -> Option<&DependencyNode> {
+        self.nodes.get(&requirement_name(requirement)?)
+    }
+}
+
+/// Returns the normalized name of the package that is required by `requirement`.
+fn requirement_name(requirement: &Requirement) -> Option<NormalizedPackageName> {
+    PackageName::from_str(&requirement.name)
+        .ok()
+        .map(NormalizedPackageName::from)
+}
+
+/// Formats a package with its extras and version, e.g. `flask[async] 3.0.0`.
+fn node_label(node: &DependencyNode) -> String {
+    if node.extras.is_empty() {
+        format!("{} {}", node.name, node.version)
+    } else {
+        let extras = node
+            .extras
+            .iter()
+            .map(|extra| extra.as_str())
+            .collect::<Vec<_>>();
+        format!("{}[{}] {}", node.name, extras.join(","), node.version)
+    }
+}
+
+/// Formats the version constraints and markers of a requirement, e.g.
+/// `>=8.0, marker: platform_system == "Windows"`.
+fn requirement_constraints(requirement: &Requirement) -> String {
+    let mut constraints = match &requirement.version_or_url {
+        Some(VersionOrUrl::VersionSpecifier(specifiers)) if !specifiers.is_empty() => {
+            specifiers.to_string()
+        }
+        Some(VersionOrUrl::Url(url)) => format!("@ {url}"),
+        _ => String::from("*"),
+    };
+    if let Some(marker) = &requirement.marker {
+        write!(constraints, ", marker: {marker}").expect("writing to a string cannot fail");
+    }
+    constraints
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(name: &str, version: &str, dependencies: &[&str]) -> DependencyNode {
+        DependencyNode {
+            name: name.parse().unwrap(),
+            version: version.parse().unwrap(),
+            extras: Vec::new(),
+            dependencies: dependencies
+                .iter()
+                .map(|requirement| requirement.parse().unwrap())
+                .collect(),
+        }
+    }
+
+    fn graph() -> DependencyGraph {
+        DependencyGraph::from_nodes([
+            node("flask", "3.0.0", &["Werkzeug>=3.0.0", "click>=8.1.3"]),
+            node(
+                "click",
+                "8.1.7",
+                &["colorama; platform_system == 'Windows'"],
+            ),
+            node("colorama", "0.4.6", &[]),
+            node("werkzeug", "3.0.1", &["markupsafe>=2.1.1"]),
+            node("markupsafe", "2.1.3", &[]),
+            node("requests", "2.31.0", &["click", "idna<4"]),
+        ])
+    }
+
+    #[test]
+    fn test_roots() {
+        let graph = graph();
+        let roots = graph
+            .roots()
+            .into_iter()
+            .map(|node| node.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(roots, vec!["flask", "requests"]);
+    }
+
+    #[test]
+    fn test_orphans() {
+        let graph = graph();
+        let orphans = |removed: &[&str], keep: &[&str]| {
+            let removed = removed
+                .iter()
+                .map(|name| name.parse().unwrap())
+                .collect::<Vec<_>>();
+            graph
+                .orphans(&removed, |node| keep.contains(&node.name.as_str()))
+                .into_iter()
+                .map(|node| node.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        // click is still required by requests
+        assert_eq!(orphans(&["flask"], &[]), vec!["markupsafe", "werkzeug"]);
+        assert_eq!(
+            orphans(&["flask", "requests"], &[]),
+            vec!["click", "colorama", "markupsafe", "werkzeug"]
+        );
+        assert_eq!(orphans(&["flask"], &["werkzeug"]), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_requirement_chains() {
+        let graph = graph();
+        let flask = "flask".parse().unwrap();
+        let requests = "requests".parse().unwrap();
+        let chains = |name: &str| {
+            let name = name.parse().unwrap();
+            graph
+                .requirement_chains(&name, &[&flask, &requests])
+                .into_iter()
+                .map(|chain| {
+                    chain
+                        .into_iter()
+                        .map(|(node, requirement)| format!("{} ({requirement})", node.name))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            chains("markupsafe"),
+            vec![vec![
+                "flask (Werkzeug >=3.0.0)",
+                "werkzeug (markupsafe >=2.1.1)"
+            ]]
+        );
+        assert_eq!(
+            chains("click"),
+            vec![vec!["flask (click >=8.1.3)"], vec!["requests (click)"]]
+        );
+        assert_eq!(chains("flask"), vec![Vec::<String>::new()]);
+    }
+
+    #[test]
+    fn test_render() {
+        let graph = graph();
+        let flask = "flask".parse().unwrap();
+        let requests = "requests".parse().unwrap();
+        let marker = graph.node(&"click".parse().unwrap()).unwrap().dependencies[0]
+            .marker
+            .clone()
+            .unwrap();
+        assert_eq!(
+            graph.render(&[&flask, &requests], false),
+            format!(
+                "flask 3.0.0
+├── werkzeug 3.0.1 [required: >=3.0.0]
+│   └── markupsafe 2.1.3 [required: >=2.1.1]
+└── click 8.1.7 [required: >=8.1.3]
+    └── colorama 0.4.6 [required: *, marker: {marker}]
+requests 2.31.0
+├── click 8.1.7 [required: *] (*)
+└── idna <4 (missing)
+"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_inverted() {
+        let graph = graph();
+        let colorama = "colorama".parse().unwrap();
+        let requirement = &graph.node(&"click".parse().unwrap()).unwrap().dependencies[0];
+        assert_eq!(
+            graph.render(&[&colorama], true),
+            format!(
+                "colorama 0.4.6
+└── click 8.1.7 [requires: {requirement}]
+    ├── flask 3.0.0 [requires: click >=8.1.3]
+    └── requests 2.31.0 [requires: click]
+"
+            )
+        );
+    }
+}
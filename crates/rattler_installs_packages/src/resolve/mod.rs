@@ -9,11 +9,15 @@
 //!
 
 mod dependency_provider;
+mod graph;
+pub mod policy;
+pub mod profile;
 mod pypi_version_types;
 mod solve;
 pub mod solve_options;
 mod solve_types;
 
+pub use graph::{DependencyGraph, DependencyNode};
 pub use pypi_version_types::PypiVersion;
 pub use pypi_version_types::PypiVersionSet;
 pub use solve::{resolve, PinnedPackage};
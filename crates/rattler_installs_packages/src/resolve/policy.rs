@@ -0,0 +1,219 @@
+//! Policies that block packages or versions of packages from being selected during resolution.
+//!
+//! A [`PackagePolicy`] is consulted for every candidate version of a package, see
+//! [`crate::index::PackageDb::with_package_policy`]. Blocked versions are excluded from the
+//! resolution and the reason of the policy is part of the error if no solution can be found
+//! without them. [`PackageRules`] implements a policy from lists of allowed and denied packages,
+//! e.g. to block known malicious releases or packages that are banned within an organization.
+
+use crate::types::NormalizedPackageName;
+use pep440_rs::{Version, VersionSpecifiers};
+use serde::Deserialize;
+use std::fmt::Debug;
+
+/// Whether a [`PackagePolicy`] allows a version of a package to be selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyDecision {
+    /// The version can be selected
+    Allow,
+
+    /// The version is blocked for the given reason, which is shown to the user
+    Deny(String),
+}
+
+/// Decides which versions of packages can be selected during resolution, see the
+/// [module docs](self).
+pub trait PackagePolicy: Debug + Send + Sync {
+    /// Returns whether the `version` of the package `name` can be selected. The version is `None`
+    /// for packages that are requested by a direct url, their version is not known up front.
+    fn check(&self, name: &NormalizedPackageName, version: Option<&Version>) -> PolicyDecision;
+}
+
+/// Matches a package and optionally a range of its versions, e.g. the `[[policy.deny]]` entries
+/// of the configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageRule {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The versions of the package that match, all versions match if this is `None`
+    pub version: Option<VersionSpecifiers>,
+
+    /// Why the rule exists, e.g. a link to an advisory or to an internal policy
+    pub reason: Option<String>,
+}
+
+impl PackageRule {
+    /// Constructs a rule that matches all versions of the package `name`.
+    pub fn new(name: NormalizedPackageName) -> Self {
+        Self {
+            name,
+            version: None,
+            reason: None,
+        }
+    }
+
+    /// Only match the versions of the package that are contained in `version`.
+    pub fn with_version(mut self, version: VersionSpecifiers) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Sets the reason that is shown to the user when the rule blocks a version.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Returns true if the rule matches the `version` of the package `name`. Rules with a version
+    /// range never match a package with an unknown version.
+    pub fn matches(&self, name: &NormalizedPackageName, version: Option<&Version>) -> bool {
+        if &self.name != name {
+            return false;
+        }
+        match (&self.version, version) {
+            (None, _) => true,
+            (Some(specifiers), Some(version)) => specifiers.contains(version),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// A [`PackagePolicy`] that blocks the versions that match one of the denied rules. If any allowed
+/// rules are specified, only versions that match one of them can be selected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageRules {
+    allow: Vec<PackageRule>,
+    deny: Vec<PackageRule>,
+}
+
+impl PackageRules {
+    /// Constructs a policy that allows every package.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allows the versions that match this rule or another allowed rule.
+    pub fn with_allowed(mut self, rule: PackageRule) -> Self {
+        self.allow.push(rule);
+        self
+    }
+
+    /// Blocks the versions that match this rule, also if they are allowed.
+    pub fn with_denied(mut self, rule: PackageRule) -> Self {
+        self.deny.push(rule);
+        self
+    }
+
+    /// Returns true if the policy does not block anything.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+impl PackagePolicy for PackageRules {
+    fn check(&self, name: &NormalizedPackageName, version: Option<&Version>) -> PolicyDecision {
+        if let Some(rule) = self.deny.iter().find(|rule| rule.matches(name, version)) {
+            return PolicyDecision::Deny(match &rule.reason {
+                Some(reason) => format!("it is denied by the package policy: {reason}"),
+                None => String::from("it is denied by the package policy"),
+            });
+        }
+
+        if self.allow.is_empty() || self.allow.iter().any(|rule| rule.matches(name, version)) {
+            return PolicyDecision::Allow;
+        }
+
+        // Explain why the package is not allowed with the reason of a rule for the same package
+        let reason = self
+            .allow
+            .iter()
+            .filter(|rule| &rule.name == name)
+            .find_map(|rule| rule.reason.as_deref());
+        PolicyDecision::Deny(match reason {
+            Some(reason) => format!("it is not allowed by the package policy: {reason}"),
+            None => String::from("it is not allowed by the package policy"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn check(rules: &PackageRules, name: &str, version: Option<&str>) -> PolicyDecision {
+        rules.check(
+            &NormalizedPackageName::from_str(name).unwrap(),
+            version.map(|v| Version::from_str(v).unwrap()).as_ref(),
+        )
+    }
+
+    fn rule(name: &str) -> PackageRule {
+        PackageRule::new(name.parse().unwrap())
+    }
+
+    #[test]
+    fn test_deny() {
+        let rules = PackageRules::new()
+            .with_denied(
+                rule("ctx")
+                    .with_version(VersionSpecifiers::from_str(">=0.2.2,<0.2.7").unwrap())
+                    .with_reason("compromised releases, see SEC-123"),
+            )
+            .with_denied(rule("Banned_Package"));
+
+        assert_eq!(check(&rules, "ctx", Some("0.2.1")), PolicyDecision::Allow);
+        assert_eq!(
+            check(&rules, "ctx", Some("0.2.6")),
+            PolicyDecision::Deny(String::from(
+                "it is denied by the package policy: compromised releases, see SEC-123"
+            ))
+        );
+        assert_eq!(check(&rules, "ctx", None), PolicyDecision::Allow);
+        assert_eq!(
+            check(&rules, "banned-package", None),
+            PolicyDecision::Deny(String::from("it is denied by the package policy"))
+        );
+        assert_eq!(
+            check(&rules, "numpy", Some("1.26.0")),
+            PolicyDecision::Allow
+        );
+    }
+
+    #[test]
+    fn test_allow() {
+        let rules = PackageRules::new()
+            .with_allowed(rule("numpy"))
+            .with_allowed(
+                rule("requests")
+                    .with_version(VersionSpecifiers::from_str(">=2.31").unwrap())
+                    .with_reason("older versions are not approved"),
+            )
+            .with_denied(rule("numpy").with_version(VersionSpecifiers::from_str("==1.0").unwrap()));
+
+        assert_eq!(
+            check(&rules, "numpy", Some("1.26.0")),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            check(&rules, "numpy", Some("1.0")),
+            PolicyDecision::Deny(String::from("it is denied by the package policy"))
+        );
+        assert_eq!(
+            check(&rules, "requests", Some("2.31.0")),
+            PolicyDecision::Allow
+        );
+        assert_eq!(
+            check(&rules, "requests", Some("2.30.0")),
+            PolicyDecision::Deny(String::from(
+                "it is not allowed by the package policy: older versions are not approved"
+            ))
+        );
+        assert_eq!(
+            check(&rules, "flask", Some("3.0.0")),
+            PolicyDecision::Deny(String::from("it is not allowed by the package policy"))
+        );
+    }
+}
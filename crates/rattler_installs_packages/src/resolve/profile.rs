@@ -0,0 +1,227 @@
+//! Records where the time of a resolution went.
+//!
+//! A [`ResolutionProfiler`] that is set in [`super::ResolveOptions::profiler`] records for every
+//! package how long it took to list its artifacts, to retrieve the metadata of each of its
+//! versions and to build metadata from source distributions. A package whose metadata was
+//! retrieved for many versions is one the solver had to backtrack on. The
+//! [`ResolutionProfile`] ranks the packages by the time that was spent on them, so the package
+//! that slows down a resolution can be identified.
+
+use crate::types::NormalizedPackageName;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Collects the timings of a resolution, see the [module docs](self). The same profiler can be
+/// used for multiple resolutions, their timings are added up.
+#[derive(Debug, Default)]
+pub struct ResolutionProfiler {
+    packages: Mutex<HashMap<NormalizedPackageName, PackageTimings>>,
+}
+
+#[derive(Debug, Default)]
+struct PackageTimings {
+    candidates: Duration,
+    metadata: HashMap<String, Duration>,
+    builds: usize,
+    build_time: Duration,
+}
+
+impl ResolutionProfiler {
+    /// Constructs a profiler that has not recorded anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records how long it took to list the artifacts of the package `name`.
+    pub(crate) fn record_candidates(&self, name: &NormalizedPackageName, duration: Duration) {
+        self.packages
+            .lock()
+            .entry(name.clone())
+            .or_default()
+            .candidates += duration;
+    }
+
+    /// Records how long it took to retrieve the metadata of the `version` of the package `name`.
+    pub(crate) fn record_metadata(
+        &self,
+        name: &NormalizedPackageName,
+        version: impl Display,
+        duration: Duration,
+    ) {
+        *self
+            .packages
+            .lock()
+            .entry(name.clone())
+            .or_default()
+            .metadata
+            .entry(version.to_string())
+            .or_default() += duration;
+    }
+
+    /// Records how long it took to build the metadata of a source distribution of the package
+    /// `name`.
+    pub(crate) fn record_metadata_build(&self, name: &NormalizedPackageName, duration: Duration) {
+        let mut packages = self.packages.lock();
+        let timings = packages.entry(name.clone()).or_default();
+        timings.builds += 1;
+        timings.build_time += duration;
+    }
+
+    /// Returns the timings that were recorded so far, ranked by the time spent on each package.
+    pub fn report(&self) -> ResolutionProfile {
+        let packages = self
+            .packages
+            .lock()
+            .iter()
+            .map(|(name, timings)| PackageProfile {
+                name: name.clone(),
+                candidates_time: timings.candidates,
+                metadata_time: timings.metadata.values().sum(),
+                versions: timings.metadata.len(),
+                slowest_version: timings
+                    .metadata
+                    .iter()
+                    .max_by_key(|(_, duration)| **duration)
+                    .map(|(version, duration)| (version.clone(), *duration)),
+                metadata_builds: timings.builds,
+                metadata_build_time: timings.build_time,
+            })
+            .collect();
+        ResolutionProfile::new(packages)
+    }
+}
+
+/// The timings of a single package in a [`ResolutionProfile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageProfile {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// How long it took to list the artifacts of the package, e.g. to fetch its index page
+    pub candidates_time: Duration,
+
+    /// How long it took to retrieve the metadata of all versions of the package, including the
+    /// time to build metadata from source distributions
+    pub metadata_time: Duration,
+
+    /// The number of versions whose metadata was retrieved. More than one means that the solver
+    /// backtracked on the package.
+    pub versions: usize,
+
+    /// The version whose metadata took the longest to retrieve
+    pub slowest_version: Option<(String, Duration)>,
+
+    /// The number of times metadata was built from a source distribution of the package
+    pub metadata_builds: usize,
+
+    /// How long building metadata from source distributions took, including setting up the build
+    /// environments
+    pub metadata_build_time: Duration,
+}
+
+impl PackageProfile {
+    /// Returns the total time that was spent on the package.
+    pub fn total_time(&self) -> Duration {
+        self.candidates_time + self.metadata_time
+    }
+}
+
+/// The timings of the packages of a resolution, ranked from the package that took the longest to
+/// the package that took the shortest. The [`Display`] implementation renders it as a table.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolutionProfile {
+    /// The timings of every package
+    pub packages: Vec<PackageProfile>,
+}
+
+impl ResolutionProfile {
+    /// Constructs a profile from the timings of `packages` in any order.
+    pub fn new(mut packages: Vec<PackageProfile>) -> Self {
+        packages.sort_by(|a, b| {
+            b.total_time()
+                .cmp(&a.total_time())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        Self { packages }
+    }
+}
+
+impl Display for ResolutionProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name_width = self
+            .packages
+            .iter()
+            .map(|package| package.name.as_str().len())
+            .chain(std::iter::once("package".len()))
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:<name_width$}  {:>9}  {:>9}  {:>9}  {:>8}  {:>6}  slowest version",
+            "package", "total", "index", "metadata", "versions", "builds"
+        )?;
+        for package in &self.packages {
+            write!(
+                f,
+                "{:<name_width$}  {:>9}  {:>9}  {:>9}  {:>8}  {:>6}",
+                package.name.as_str(),
+                seconds(package.total_time()),
+                seconds(package.candidates_time),
+                seconds(package.metadata_time),
+                package.versions,
+                package.metadata_builds,
+            )?;
+            if let Some((version, duration)) = &package.slowest_version {
+                write!(f, "  {version} ({})", seconds(*duration))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a duration as seconds with millisecond precision.
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_report() {
+        let name = |name: &str| NormalizedPackageName::from_str(name).unwrap();
+        let profiler = ResolutionProfiler::new();
+        profiler.record_candidates(&name("flask"), Duration::from_millis(100));
+        profiler.record_metadata(&name("flask"), "3.0.0", Duration::from_millis(50));
+        profiler.record_candidates(&name("numpy"), Duration::from_millis(100));
+        profiler.record_metadata(&name("numpy"), "1.26.0", Duration::from_millis(300));
+        profiler.record_metadata(&name("numpy"), "1.25.0", Duration::from_millis(1200));
+        profiler.record_metadata_build(&name("numpy"), Duration::from_millis(1000));
+
+        let profile = profiler.report();
+        let numpy = &profile.packages[0];
+        assert_eq!(numpy.name, name("numpy"));
+        assert_eq!(numpy.total_time(), Duration::from_millis(1600));
+        assert_eq!(numpy.versions, 2);
+        assert_eq!(
+            numpy.slowest_version,
+            Some((String::from("1.25.0"), Duration::from_millis(1200)))
+        );
+        assert_eq!(numpy.metadata_builds, 1);
+        assert_eq!(profile.packages[1].name, name("flask"));
+
+        assert_eq!(
+            profile.to_string(),
+            "\
+package      total      index   metadata  versions  builds  slowest version
+numpy       1.600s     0.100s     1.500s         2       1  1.25.0 (1.200s)
+flask       0.150s     0.100s     0.050s         1       0  3.0.0 (0.050s)
+"
+        );
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::resolve::solve_options::PreReleaseResolution;
 use crate::types::{Extra, NormalizedPackageName};
-use pep440_rs::Version;
+use pep440_rs::{Operator, Version, VersionSpecifier, VersionSpecifiers};
 use pep508_rs::VersionOrUrl;
 use resolvo::VersionSet;
 use serde::{Deserialize, Serialize};
@@ -97,6 +97,20 @@ impl PypiVersion {
             PypiVersion::Url(url) => url.scheme().contains("git"),
         }
     }
+
+    /// Returns the spec that only matches this version, e.g. `===1.0` or the url itself. This
+    /// ties an extra of a package to the same version as the package.
+    pub(crate) fn exact_spec(&self) -> VersionOrUrl {
+        match self {
+            PypiVersion::Version { version, .. } => {
+                VersionOrUrl::VersionSpecifier(VersionSpecifiers::from_iter([
+                    VersionSpecifier::new(Operator::ExactEqual, version.clone(), false)
+                        .expect("failed to construct equality version specifier"),
+                ]))
+            }
+            PypiVersion::Url(url) => VersionOrUrl::Url(url.clone()),
+        }
+    }
 }
 
 impl VersionSet for PypiVersionSet {
@@ -18,6 +18,7 @@ use std::collections::HashSet;
 use std::convert::identity;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Represents a single locked down distribution (python package) after calling [`resolve`].
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -39,6 +40,13 @@ pub struct PinnedPackage {
     ///
     /// This list may be empty if the package was locked or favored.
     pub artifacts: Vec<Arc<ArtifactInfo>>,
+
+    /// The requirements of the package that apply to the environment, including the
+    /// requirements of the selected extras. Together these form the dependency graph of the
+    /// resolution, see [`super::DependencyGraph`].
+    ///
+    /// This list may be empty if the package was locked and its metadata was not retrieved.
+    pub dependencies: Vec<Requirement>,
 }
 
 /// Resolves an environment that contains the given requirements and all dependencies of those
@@ -51,6 +59,9 @@ pub struct PinnedPackage {
 /// If `compatible_tags` is defined then the available artifacts of a distribution are filtered to
 /// include only artifacts that are compatible with the specified tags. If `None` is passed, the
 /// artifacts are not filtered at all
+///
+/// If the [`PackageDb`] checks for typosquatting, a warning is logged for every requirement on a
+/// package whose name looks like the name of a popular package before resolving.
 // TODO: refactor this into an input type of sorts later
 #[allow(clippy::too_many_arguments)]
 pub async fn resolve(
@@ -64,8 +75,19 @@ pub async fn resolve(
     env_variables: HashMap<String, String>,
 ) -> miette::Result<Vec<PinnedPackage>> {
     let requirements: Vec<_> = requirements.into_iter().cloned().collect();
+    for warning in package_db.check_typosquatting(&requirements) {
+        tracing::warn!("{warning}");
+    }
+    let span = tracing::info_span!(
+        "resolve",
+        requirements = requirements.len(),
+        packages = tracing::field::Empty
+    );
+    let metrics = package_db.metrics().clone();
     tokio::task::spawn_blocking(move || {
-        resolve_inner(
+        let _enter = span.enter();
+        let start = Instant::now();
+        let result = resolve_inner(
             package_db,
             &requirements,
             env_markers,
@@ -74,7 +96,12 @@ pub async fn resolve(
             favored_packages,
             options,
             env_variables,
-        )
+        );
+        metrics.resolve_seconds.record(start.elapsed());
+        if let Ok(packages) = &result {
+            span.record("packages", packages.len());
+        }
+        result
     })
     .await
     .map_or_else(
@@ -104,19 +131,19 @@ fn resolve_inner<'r>(
     let name_to_url: FrozenMap<NormalizedPackageName, String> = FrozenMap::default();
 
     // Construct the root requirements from the requirements requested by the user.
-    let requirements = requirements.into_iter();
-    let requirement_count = requirements.size_hint();
-    let mut root_requirements =
-        Vec::with_capacity(requirement_count.1.unwrap_or(requirement_count.0));
+    let requirements: Vec<_> = requirements.into_iter().collect();
+    let mut root_requirements = Vec::with_capacity(requirements.len());
 
     for Requirement {
         name,
         version_or_url,
         extras,
         ..
-    } in requirements
+    } in requirements.iter().copied()
     {
         let name = PackageName::from_str(name).expect("invalid package name");
+        let version_or_url =
+            &options.local_version_or_url(&name.clone().into(), version_or_url.clone());
         let pypi_name = PypiPackageName::Base(name.clone().into());
         let dependency_package_name = pool.intern_package_name(pypi_name.clone());
         let version_set_id = pool.intern_version_set(
@@ -141,7 +168,12 @@ fn resolve_inner<'r>(
         }
     }
 
+    // The source trees of local packages may have changed since they were favored
+    let mut favored_packages = favored_packages;
+    favored_packages.retain(|name, _| !options.local_packages.contains_key(name));
+
     // Construct the provider
+    let local_packages = options.local_packages.clone();
     let provider = PypiDependencyProvider::new(
         pool,
         package_db,
@@ -213,16 +245,406 @@ fn resolve_inner<'r>(
                 url,
                 artifacts,
                 extras: Default::default(),
+                dependencies: Vec::new(),
             });
 
         // Add the extra if selected
         if let PypiPackageName::Extra(_, extra) = name {
             entry.extras.insert(extra.clone());
         }
+
+        // Merge the requirements of the base package and its extras
+        for requirement in provider
+            .dependencies
+            .get(&solvable_id)
+            .into_iter()
+            .flatten()
+        {
+            if !entry.dependencies.contains(requirement) {
+                entry.dependencies.push(requirement.clone());
+            }
+        }
     }
 
-    Ok(result.into_values().collect())
+    let packages: Vec<_> = result.into_values().collect();
+    check_local_packages(&local_packages, &requirements, &packages)?;
+    Ok(packages)
+}
+
+/// Checks that the versions of the local packages, which are selected regardless of the version
+/// specifiers of the requirements on them, satisfy these specifiers. `requirements` are the
+/// requirements of the user.
+fn check_local_packages(
+    local_packages: &HashMap<NormalizedPackageName, Url>,
+    requirements: &[&Requirement],
+    packages: &[PinnedPackage],
+) -> miette::Result<()> {
+    if local_packages.is_empty() {
+        return Ok(());
+    }
+    let required_by = requirements
+        .iter()
+        .map(|requirement| (None, *requirement))
+        .chain(packages.iter().flat_map(|package| {
+            package
+                .dependencies
+                .iter()
+                .map(move |requirement| (Some(&package.name), requirement))
+        }));
+    for (parent, requirement) in required_by {
+        let Some(VersionOrUrl::VersionSpecifier(specifiers)) = &requirement.version_or_url else {
+            continue;
+        };
+        let name = PackageName::from_str(&requirement.name)
+            .expect("invalid package name")
+            .into();
+        if !local_packages.contains_key(&name) {
+            continue;
+        }
+        let Some(package) = packages.iter().find(|package| package.name == name) else {
+            continue;
+        };
+        if !specifiers.contains(&package.version) {
+            let parent = match parent {
+                Some(parent) => parent.as_str().to_string(),
+                None => String::from("the requirements"),
+            };
+            miette::bail!(
+                "{parent} requires {requirement} but the local source tree of {} has version {}",
+                package.name.as_str(),
+                package.version
+            );
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::*;
+    use crate::index::PackageSourcesBuilder;
+    use crate::lock::LockTarget;
+    use reqwest::Client;
+    use reqwest_middleware::ClientWithMiddleware;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use tokio_util::sync::CancellationToken;
+
+    fn package_db(cancellation: CancellationToken) -> (Arc<PackageDb>, TempDir) {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSourcesBuilder::new("https://pypi.org/simple/".parse().unwrap())
+            .with_find_links(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data/wheels"))
+            .without_index()
+            .build()
+            .unwrap();
+        let package_db = PackageDb::new(
+            sources,
+            ClientWithMiddleware::from(Client::new()),
+            cache_dir.path(),
+        )
+        .unwrap()
+        .with_cancellation_token(cancellation);
+        (Arc::new(package_db), cache_dir)
+    }
+
+    async fn resolve_wordle(package_db: Arc<PackageDb>) -> miette::Result<Vec<PinnedPackage>> {
+        let target = LockTarget::new(
+            "3.11".parse().unwrap(),
+            "manylinux_2_17_x86_64".parse().unwrap(),
+        );
+        resolve(
+            package_db,
+            &[Requirement::from_str("wordle_python").unwrap()],
+            Arc::new(target.env_markers().unwrap().0),
+            Some(Arc::new(target.wheel_tags())),
+            HashMap::default(),
+            HashMap::default(),
+            ResolveOptions::default(),
+            HashMap::default(),
+        )
+        .await
+    }
+
+    /// The resolver does not depend on the worker threads of a multi-threaded runtime.
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_resolve_on_current_thread_runtime() {
+        let (package_db, _cache_dir) = package_db(CancellationToken::new());
+        let packages = resolve_wordle(package_db).await.unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name.as_str(), "wordle-python");
+    }
+
+    /// Writes a wheel that only contains the `METADATA` of a package with the given
+    /// `Requires-Dist` and `Provides-Extra` entries to `dir`.
+    fn write_wheel(dir: &Path, name: &str, version: &str, extras: &[&str], requires: &[&str]) {
+        use std::io::Write;
+        use zip::{write::FileOptions, ZipWriter};
+
+        let mut metadata = format!("Metadata-Version: 2.1\nName: {name}\nVersion: {version}\n");
+        for extra in extras {
+            metadata.push_str(&format!("Provides-Extra: {extra}\n"));
+        }
+        for requirement in requires {
+            metadata.push_str(&format!("Requires-Dist: {requirement}\n"));
+        }
+        let dist_info = format!("{name}-{version}.dist-info");
+        let file =
+            fs_err::File::create(dir.join(format!("{name}-{version}-py3-none-any.whl"))).unwrap();
+        let mut zip = ZipWriter::new(file);
+        for (path, contents) in [
+            ("METADATA", metadata.as_str()),
+            (
+                "WHEEL",
+                "Wheel-Version: 1.0\nRoot-Is-Purelib: true\nTag: py3-none-any\n",
+            ),
+            ("RECORD", ""),
+        ] {
+            zip.start_file(format!("{dist_info}/{path}"), FileOptions::default())
+                .unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Resolves `requirements` against the wheels in `find_links` and returns the selected
+    /// packages by name, with their versions and extras.
+    async fn resolve_find_links(
+        find_links: &Path,
+        requirements: &[&str],
+    ) -> miette::Result<Vec<(String, String, Vec<String>)>> {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSourcesBuilder::new("https://pypi.org/simple/".parse().unwrap())
+            .with_find_links(find_links)
+            .without_index()
+            .build()
+            .unwrap();
+        let package_db = PackageDb::new(
+            sources,
+            ClientWithMiddleware::from(Client::new()),
+            cache_dir.path(),
+        )
+        .unwrap();
+        let target = LockTarget::new(
+            "3.11".parse().unwrap(),
+            "manylinux_2_17_x86_64".parse().unwrap(),
+        );
+        let requirements = requirements
+            .iter()
+            .map(|requirement| Requirement::from_str(requirement).unwrap())
+            .collect::<Vec<_>>();
+        let packages = resolve(
+            Arc::new(package_db),
+            &requirements,
+            Arc::new(target.env_markers().unwrap().0),
+            Some(Arc::new(target.wheel_tags())),
+            HashMap::default(),
+            HashMap::default(),
+            ResolveOptions::default(),
+            HashMap::default(),
+        )
+        .await?;
+        let mut packages = packages
+            .into_iter()
+            .map(|package| {
+                let mut extras = package
+                    .extras
+                    .iter()
+                    .map(|extra| extra.as_str().to_string())
+                    .collect::<Vec<_>>();
+                extras.sort();
+                (
+                    package.name.as_str().to_string(),
+                    package.version.to_string(),
+                    extras,
+                )
+            })
+            .collect::<Vec<_>>();
+        packages.sort();
+        Ok(packages)
+    }
+
+    fn pinned_extras(name: &str, version: &str, extras: &[&str]) -> (String, String, Vec<String>) {
+        (
+            name.to_string(),
+            version.to_string(),
+            extras.iter().map(|extra| extra.to_string()).collect(),
+        )
+    }
+
+    /// An extra that requires other extras of the same package, like `all = ["demo[a,b]"]`.
+    #[tokio::test]
+    async fn test_self_referential_extras() {
+        let dir = tempfile::tempdir().unwrap();
+        for version in ["1.0", "2.0"] {
+            write_wheel(
+                dir.path(),
+                "demo",
+                version,
+                &["all", "a", "b"],
+                &[
+                    "demo[a,b]; extra == 'all'",
+                    "dep-a; extra == 'a'",
+                    "dep-b; extra == 'b'",
+                ],
+            );
+        }
+        write_wheel(dir.path(), "dep_a", "1.0", &[], &[]);
+        write_wheel(dir.path(), "dep_b", "1.0", &[], &[]);
+
+        assert_eq!(
+            resolve_find_links(dir.path(), &["demo[all]"])
+                .await
+                .unwrap(),
+            vec![
+                pinned_extras("demo", "2.0", &["a", "all", "b"]),
+                pinned_extras("dep-a", "1.0", &[]),
+                pinned_extras("dep-b", "1.0", &[]),
+            ]
+        );
+
+        // The extras of the same version are selected
+        assert_eq!(
+            resolve_find_links(dir.path(), &["demo[all]==1.0"])
+                .await
+                .unwrap(),
+            vec![
+                pinned_extras("demo", "1.0", &["a", "all", "b"]),
+                pinned_extras("dep-a", "1.0", &[]),
+                pinned_extras("dep-b", "1.0", &[]),
+            ]
+        );
+    }
+
+    /// Extras that require each other, or themselves, and a package that requires one of its own
+    /// extras that it does not declare.
+    #[tokio::test]
+    async fn test_circular_extras() {
+        let dir = tempfile::tempdir().unwrap();
+        write_wheel(
+            dir.path(),
+            "cycle",
+            "1.0",
+            &["a", "b"],
+            &[
+                "cycle[b]; extra == 'a'",
+                "cycle[a]; extra == 'b'",
+                "cycle[a]>=1; extra == 'a'",
+                "dep-a; extra == 'a'",
+                "dep-b; extra == 'b'",
+                "cycle[undeclared]",
+            ],
+        );
+        write_wheel(dir.path(), "dep_a", "1.0", &[], &[]);
+        write_wheel(dir.path(), "dep_b", "1.0", &[], &[]);
+
+        assert_eq!(
+            resolve_find_links(dir.path(), &["cycle[a]"]).await.unwrap(),
+            vec![
+                pinned_extras("cycle", "1.0", &["a", "b", "undeclared"]),
+                pinned_extras("dep-a", "1.0", &[]),
+                pinned_extras("dep-b", "1.0", &[]),
+            ]
+        );
+    }
+
+    /// A favored package without artifacts uses the artifacts of its version on the index, it is
+    /// ignored if that version is not available.
+    #[tokio::test]
+    async fn test_favored_without_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        write_wheel(dir.path(), "demo", "1.0", &[], &[]);
+        write_wheel(dir.path(), "demo", "2.0", &[], &[]);
+        let cache_dir = tempfile::tempdir().unwrap();
+        let sources = PackageSourcesBuilder::new("https://pypi.org/simple/".parse().unwrap())
+            .with_find_links(dir.path())
+            .without_index()
+            .build()
+            .unwrap();
+        let package_db = Arc::new(
+            PackageDb::new(
+                sources,
+                ClientWithMiddleware::from(Client::new()),
+                cache_dir.path(),
+            )
+            .unwrap(),
+        );
+        let target = LockTarget::new(
+            "3.11".parse().unwrap(),
+            "manylinux_2_17_x86_64".parse().unwrap(),
+        );
+
+        for (favored_version, expected) in [("1.0", "1.0"), ("3.0", "2.0")] {
+            let name = NormalizedPackageName::from_str("demo").unwrap();
+            let favored = PinnedPackage {
+                name: name.clone(),
+                version: favored_version.parse().unwrap(),
+                url: None,
+                extras: Default::default(),
+                artifacts: Vec::new(),
+                dependencies: Vec::new(),
+            };
+            let packages = resolve(
+                package_db.clone(),
+                &[Requirement::from_str("demo").unwrap()],
+                Arc::new(target.env_markers().unwrap().0),
+                Some(Arc::new(target.wheel_tags())),
+                HashMap::default(),
+                HashMap::from([(name, favored)]),
+                ResolveOptions::default(),
+                HashMap::default(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(packages.len(), 1);
+            assert_eq!(packages[0].version.to_string(), expected);
+            assert_eq!(
+                packages[0].artifacts[0].filename.to_string(),
+                format!("demo-{expected}-py3-none-any.whl")
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_local_packages() {
+        let url = Url::from_str("file:///workspace/core/").unwrap();
+        let local_packages = HashMap::from([("core".parse().unwrap(), url.clone())]);
+        let pinned =
+            |name: &str, version: &str, url: Option<Url>, dependencies: &[&str]| PinnedPackage {
+                name: name.parse().unwrap(),
+                version: version.parse().unwrap(),
+                url,
+                extras: HashSet::new(),
+                artifacts: Vec::new(),
+                dependencies: dependencies
+                    .iter()
+                    .map(|d| Requirement::from_str(d).unwrap())
+                    .collect(),
+            };
+        let requirement = Requirement::from_str("app").unwrap();
+
+        let packages = [
+            pinned("app", "1.0", None, &["core>=1.2"]),
+            pinned("core", "1.2.0", Some(url.clone()), &[]),
+        ];
+        check_local_packages(&local_packages, &[&requirement], &packages).unwrap();
+
+        let packages = [
+            pinned("app", "1.0", None, &["core>=2"]),
+            pinned("core", "1.2.0", Some(url), &[]),
+        ];
+        let err = check_local_packages(&local_packages, &[&requirement], &packages).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "app requires core >=2 but the local source tree of core has version 1.2.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_resolve() {
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        let (package_db, _cache_dir) = package_db(cancellation);
+        let err = resolve_wordle(package_db).await.unwrap_err();
+        assert_eq!(err.to_string(), "the resolution was interrupted");
+    }
+}
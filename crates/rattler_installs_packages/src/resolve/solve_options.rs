@@ -1,12 +1,15 @@
 //! Contains the options that can be passed to the [`super::solve::resolve`] function.
 
 use crate::python_env::PythonLocation;
+use crate::resolve::profile::ResolutionProfiler;
 use pep508_rs::{Requirement, VersionOrUrl};
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
+use url::Url;
 
-use crate::types::PackageName;
+use crate::types::{NormalizedPackageName, PackageName, RequiredHashes};
 
 /// Defines how to handle sdists during resolution.
 #[derive(Default, Debug, Clone, Copy, Eq, PartialOrd, PartialEq)]
@@ -104,6 +107,25 @@ pub enum SDistResolution {
     OnlySDists,
 }
 
+impl FromStr for SDistResolution {
+    type Err = String;
+
+    /// Parses the kebab-case name of a variant, e.g. `prefer-wheels`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(SDistResolution::Normal),
+            "prefer-wheels" => Ok(SDistResolution::PreferWheels),
+            "prefer-sdists" => Ok(SDistResolution::PreferSDists),
+            "only-wheels" => Ok(SDistResolution::OnlyWheels),
+            "only-sdists" => Ok(SDistResolution::OnlySDists),
+            _ => Err(format!(
+                "invalid sdist resolution '{s}', expected one of: normal, prefer-wheels, \
+                prefer-sdists, only-wheels, only-sdists"
+            )),
+        }
+    }
+}
+
 /// Defines how to pre-releases are handled during package resolution.
 #[derive(Debug, Clone, Eq, PartialOrd, PartialEq)]
 pub enum PreReleaseResolution {
@@ -166,9 +188,10 @@ impl PreReleaseResolution {
 }
 
 impl SDistResolution {
-    /// Returns true if sdists are allowed to be selected during resolution
+    /// Returns true if sdists are allowed to be selected during resolution. This is never the case
+    /// if the crate is compiled without the `sdist` feature because sdists cannot be built then.
     pub fn allow_sdists(&self) -> bool {
-        !matches!(self, SDistResolution::OnlyWheels)
+        cfg!(feature = "sdist") && !matches!(self, SDistResolution::OnlyWheels)
     }
 
     /// Returns true if sdists are allowed to be selected during resolution
@@ -177,6 +200,40 @@ impl SDistResolution {
     }
 }
 
+/// Defines how wheels that target the stable ABI (`abi3`) are treated during resolution.
+///
+/// Wheels built against the stable ABI of CPython remain installable when the interpreter is
+/// upgraded to a newer minor version, wheels built for a specific ABI (e.g. `cp311`) do not.
+/// Preferring `abi3` wheels therefore results in resolutions that stay valid across CPython
+/// upgrades.
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Abi3Resolution {
+    /// Wheels are ranked solely based on how specific their tags are to the interpreter. This
+    /// usually means that a version specific wheel is preferred over an `abi3` wheel.
+    #[default]
+    Normal,
+
+    /// If both an `abi3` wheel and a version specific wheel are available for a version, prefer
+    /// the `abi3` wheel.
+    PreferAbi3,
+
+    /// Never select wheels that are built for a version specific ABI. Wheels that target `abi3`
+    /// and wheels that do not target an ABI at all (e.g. pure python wheels) are still selected.
+    RequireAbi3,
+}
+
+impl Abi3Resolution {
+    /// Returns true if `abi3` wheels should be preferred over version specific wheels.
+    pub fn prefer_abi3(&self) -> bool {
+        !matches!(self, Abi3Resolution::Normal)
+    }
+
+    /// Returns true if version specific wheels are allowed to be selected.
+    pub fn allow_version_specific_abi(&self) -> bool {
+        !matches!(self, Abi3Resolution::RequireAbi3)
+    }
+}
+
 /// Specifies what to do with failed build environments
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum OnWheelBuildFailure {
@@ -196,6 +253,10 @@ pub struct ResolveOptions {
     /// as wheels.
     pub sdist_resolution: SDistResolution,
 
+    /// Defines how wheels that target the stable ABI are treated during resolution. By default
+    /// they are treated like any other wheel.
+    pub abi3_resolution: Abi3Resolution,
+
     /// Defines what python interpreter to use for resolution. By default the python interpreter
     /// from the system is used. This is only used during resolution and building of wheel files
     pub python_location: PythonLocation,
@@ -213,6 +274,22 @@ pub struct ResolveOptions {
 
     /// Limits the amount of concurrent tasks when resolving.
     pub max_concurrent_tasks: Arc<Semaphore>,
+
+    /// When set, every package must have hashes and only the artifacts that match one of them
+    /// are selected, like `pip install --require-hashes`. Packages without hashes cannot be
+    /// resolved.
+    pub required_hashes: Option<Arc<RequiredHashes>>,
+
+    /// When set, records where the time of the resolution went, see [`super::profile`]. Metadata
+    /// that is built from source distributions is also recorded in it.
+    pub profiler: Option<Arc<ResolutionProfiler>>,
+
+    /// Packages that are built from a local source tree instead of being selected from the
+    /// index, e.g. the members of a [`crate::types::Workspace`]. Every requirement on one of these
+    /// packages, by the user or by another package, resolves to the source tree at its url. The
+    /// version specifiers of those requirements are checked against the version of the source
+    /// tree once the resolution is complete.
+    pub local_packages: HashMap<NormalizedPackageName, Url>,
 }
 
 impl ResolveOptions {
@@ -223,17 +300,34 @@ impl ResolveOptions {
             ..Default::default()
         }
     }
+
+    /// Returns what a requirement on the package `name` resolves to: the url of its source tree if
+    /// it is one of the [`Self::local_packages`], otherwise `version_or_url` itself.
+    pub(crate) fn local_version_or_url(
+        &self,
+        name: &NormalizedPackageName,
+        version_or_url: Option<VersionOrUrl>,
+    ) -> Option<VersionOrUrl> {
+        match self.local_packages.get(name) {
+            Some(url) => Some(VersionOrUrl::Url(url.clone())),
+            None => version_or_url,
+        }
+    }
 }
 
 impl Default for ResolveOptions {
     fn default() -> Self {
         Self {
             sdist_resolution: SDistResolution::default(),
+            abi3_resolution: Abi3Resolution::default(),
             python_location: PythonLocation::default(),
             clean_env: false,
             on_wheel_build_failure: OnWheelBuildFailure::default(),
             pre_release_resolution: PreReleaseResolution::default(),
             max_concurrent_tasks: Arc::new(Semaphore::new(30)),
+            required_hashes: None,
+            profiler: None,
+            local_packages: HashMap::new(),
         }
     }
 }
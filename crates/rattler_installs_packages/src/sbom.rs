@@ -0,0 +1,528 @@
+//! Software bills of materials of resolved or installed environments.
+//!
+//! An [`Sbom`] lists the packages of an environment with their versions, licenses, the artifacts
+//! they are installed from and whether they were built from source. It is serialized as
+//! [CycloneDX](https://cyclonedx.org/docs/1.5/json/) with [`Sbom::to_cyclonedx_json`] or as
+//! [SPDX](https://spdx.github.io/spdx-spec/v2.3/) with [`Sbom::to_spdx_json`].
+
+use crate::artifacts::Wheel;
+use crate::index::PackageDb;
+use crate::python_env::Distribution;
+use crate::resolve::{DependencyGraph, PinnedPackage};
+use crate::types::{
+    ArtifactHashes, ArtifactInfo, DirectUrlSource, NormalizedPackageName, PackageDescription,
+    Version,
+};
+use data_encoding::HEXLOWER;
+use fs_err as fs;
+use pep508_rs::MarkerEnvironment;
+use rattler_digest::digest::Digest;
+use rattler_digest::Sha256;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::SystemTime;
+use url::Url;
+
+/// A package in an [`Sbom`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SbomComponent {
+    /// The name of the package
+    pub name: NormalizedPackageName,
+
+    /// The version of the package
+    pub version: Version,
+
+    /// The license of the package, either an SPDX expression or free text
+    pub license: Option<String>,
+
+    /// The url of the artifact the package is installed from, or of the source it was built from
+    pub source_url: Option<Url>,
+
+    /// The hashes of the artifact at `source_url`
+    pub hashes: Option<ArtifactHashes>,
+
+    /// True if the package was built from an sdist or a source tree instead of installed from a
+    /// wheel
+    pub built_from_source: bool,
+
+    /// The packages in the bill of materials that this package depends on
+    pub dependencies: Vec<NormalizedPackageName>,
+}
+
+impl SbomComponent {
+    /// Returns the package url of the component, e.g. `pkg:pypi/flask@3.0.0`.
+    pub fn purl(&self) -> String {
+        format!(
+            "pkg:pypi/{}@{}",
+            self.name.as_str(),
+            self.version.to_string().replace('+', "%2B")
+        )
+    }
+}
+
+/// A software bill of materials, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbom {
+    /// The name of the environment or project that is described
+    pub name: String,
+
+    /// When the bill of materials was created
+    pub created: SystemTime,
+
+    /// The packages, ordered by name
+    pub components: Vec<SbomComponent>,
+}
+
+impl Sbom {
+    /// Constructs the bill of materials of a resolution, e.g. of the packages of a lockfile. The
+    /// licenses are read from the metadata of the artifacts, which is retrieved if it is not
+    /// cached yet. The first artifact of every package is the one it is installed from.
+    pub async fn from_resolution(
+        name: impl Into<String>,
+        package_db: &PackageDb,
+        packages: &[PinnedPackage],
+    ) -> Self {
+        let graph = DependencyGraph::from_pinned(packages);
+        let mut components = Vec::with_capacity(packages.len());
+        for package in packages {
+            let artifact = package.artifacts.first();
+            components.push(SbomComponent {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                license: license(package_db, package).await,
+                source_url: package
+                    .url
+                    .clone()
+                    .or_else(|| artifact.map(|artifact| artifact.url.clone())),
+                hashes: artifact.and_then(|artifact| artifact.hashes.clone()),
+                built_from_source: artifact.is_some_and(|artifact| !artifact.is::<Wheel>()),
+                dependencies: dependencies(&graph, &package.name),
+            });
+        }
+        Self::new(name, components)
+    }
+
+    /// Constructs the bill of materials of the `distributions` that are installed in the
+    /// environment at `root`, see [`crate::python_env::find_distributions_in_venv`]. Only the
+    /// distributions that were installed from a direct url record their source, those are
+    /// considered built from source unless the url refers to a wheel.
+    pub fn from_environment(
+        name: impl Into<String>,
+        root: &Path,
+        distributions: &[Distribution],
+        env_markers: &MarkerEnvironment,
+    ) -> Self {
+        let graph = DependencyGraph::from_installed(root, distributions, env_markers);
+        let components = distributions
+            .iter()
+            .map(|distribution| {
+                let license = fs::read(root.join(&distribution.dist_info).join("METADATA"))
+                    .ok()
+                    .and_then(|bytes| PackageDescription::from_bytes(&bytes).ok())
+                    .and_then(|description| description.license);
+                let direct_url = distribution.direct_url.as_ref();
+                let built_from_source = direct_url.is_some_and(|direct_url| {
+                    !matches!(direct_url.source, DirectUrlSource::Archive { .. })
+                        || !direct_url.url.path().ends_with(".whl")
+                });
+                let hashes = direct_url.and_then(|direct_url| match &direct_url.source {
+                    DirectUrlSource::Archive {
+                        hashes: Some(hashes),
+                    } => rattler_digest::parse_digest_from_hex::<Sha256>(&hashes.sha256).map(
+                        |sha256| ArtifactHashes {
+                            sha256: Some(sha256),
+                        },
+                    ),
+                    _ => None,
+                });
+                SbomComponent {
+                    name: distribution.name.clone(),
+                    version: distribution.version.clone(),
+                    license,
+                    source_url: direct_url.map(|direct_url| direct_url.url.clone()),
+                    hashes,
+                    built_from_source,
+                    dependencies: dependencies(&graph, &distribution.name),
+                }
+            })
+            .collect();
+        Self::new(name, components)
+    }
+
+    /// Constructs a bill of materials from its components, created now.
+    pub fn new(name: impl Into<String>, mut components: Vec<SbomComponent>) -> Self {
+        components.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            name: name.into(),
+            created: SystemTime::now(),
+            components,
+        }
+    }
+
+    /// Serializes the bill of materials as a CycloneDX 1.5 JSON document.
+    pub fn to_cyclonedx_json(&self) -> String {
+        let components = self
+            .components
+            .iter()
+            .map(|component| {
+                let mut value = json!({
+                    "type": "library",
+                    "bom-ref": component.purl(),
+                    "name": component.name.as_str(),
+                    "version": component.version.to_string(),
+                    "purl": component.purl(),
+                });
+                if let Some(sha256) = sha256(component) {
+                    value["hashes"] = json!([{ "alg": "SHA-256", "content": sha256 }]);
+                }
+                if let Some(license) = &component.license {
+                    value["licenses"] = match spdx_expression(license) {
+                        Some(expression) => json!([{ "expression": expression }]),
+                        None => json!([{ "license": { "name": first_line(license) } }]),
+                    };
+                }
+                if let Some(url) = &component.source_url {
+                    value["externalReferences"] =
+                        json!([{ "type": "distribution", "url": url.as_str() }]);
+                }
+                value["properties"] = json!([{
+                    "name": "rip:built-from-source",
+                    "value": component.built_from_source.to_string(),
+                }]);
+                value
+            })
+            .collect::<Vec<_>>();
+        let dependencies = self
+            .components
+            .iter()
+            .map(|component| {
+                json!({
+                    "ref": component.purl(),
+                    "dependsOn": self.dependency_refs(component, SbomComponent::purl),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let document = json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": format_timestamp(self.created),
+                "tools": { "components": [tool()] },
+                "component": { "type": "application", "bom-ref": self.name, "name": self.name },
+            },
+            "components": components,
+            "dependencies": dependencies,
+        });
+        serde_json::to_string_pretty(&document).expect("the document is valid JSON")
+    }
+
+    /// Serializes the bill of materials as an SPDX 2.3 JSON document.
+    pub fn to_spdx_json(&self) -> String {
+        let packages = self
+            .components
+            .iter()
+            .map(|component| {
+                let mut value = json!({
+                    "name": component.name.as_str(),
+                    "SPDXID": spdx_id(component),
+                    "versionInfo": component.version.to_string(),
+                    "downloadLocation": component
+                        .source_url
+                        .as_ref()
+                        .map_or("NOASSERTION", Url::as_str),
+                    "filesAnalyzed": false,
+                    "licenseConcluded": "NOASSERTION",
+                    "licenseDeclared": component
+                        .license
+                        .as_deref()
+                        .and_then(spdx_expression)
+                        .unwrap_or("NOASSERTION"),
+                    "externalRefs": [{
+                        "referenceCategory": "PACKAGE-MANAGER",
+                        "referenceType": "purl",
+                        "referenceLocator": component.purl(),
+                    }],
+                });
+                if let Some(sha256) = sha256(component) {
+                    value["checksums"] =
+                        json!([{ "algorithm": "SHA256", "checksumValue": sha256 }]);
+                }
+                if let Some(license) = component
+                    .license
+                    .as_deref()
+                    .filter(|license| spdx_expression(license).is_none())
+                {
+                    value["licenseComments"] = json!(first_line(license));
+                }
+                if component.built_from_source {
+                    value["comment"] = json!("Built from source");
+                }
+                value
+            })
+            .collect::<Vec<_>>();
+
+        let mut relationships = self
+            .components
+            .iter()
+            .map(|component| {
+                json!({
+                    "spdxElementId": "SPDXRef-DOCUMENT",
+                    "relationshipType": "DESCRIBES",
+                    "relatedSpdxElement": spdx_id(component),
+                })
+            })
+            .collect::<Vec<_>>();
+        for component in &self.components {
+            for dependency in self.dependency_refs(component, spdx_id) {
+                relationships.push(json!({
+                    "spdxElementId": spdx_id(component),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": dependency,
+                }));
+            }
+        }
+
+        // The namespace must be unique for every document
+        let created = format_timestamp(self.created);
+        let digest = Sha256::digest(format!("{}{created}{packages:?}", self.name).as_bytes());
+        let document = json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.name,
+            "documentNamespace": format!(
+                "https://spdx.org/spdxdocs/{}-{}",
+                self.name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "-"),
+                HEXLOWER.encode(&digest[..16])
+            ),
+            "creationInfo": {
+                "created": created,
+                "creators": [format!("Tool: rattler_installs_packages-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        });
+        serde_json::to_string_pretty(&document).expect("the document is valid JSON")
+    }
+
+    /// Returns the references of the dependencies of `component` that are part of the bill of
+    /// materials.
+    fn dependency_refs(
+        &self,
+        component: &SbomComponent,
+        reference: impl Fn(&SbomComponent) -> String,
+    ) -> Vec<String> {
+        component
+            .dependencies
+            .iter()
+            .filter_map(|name| self.components.iter().find(|other| &other.name == name))
+            .map(reference)
+            .collect()
+    }
+}
+
+/// Returns the license in the metadata of the first artifact of `package` that has metadata.
+async fn license(package_db: &PackageDb, package: &PinnedPackage) -> Option<String> {
+    for artifact in &package.artifacts {
+        let bytes = match package_db.cached_metadata_bytes(artifact).await {
+            Some(bytes) => Some(bytes),
+            None => fetch_metadata_bytes(package_db, artifact).await,
+        };
+        if let Some(bytes) = bytes {
+            return PackageDescription::from_bytes(&bytes).ok()?.license;
+        }
+    }
+    None
+}
+
+async fn fetch_metadata_bytes(package_db: &PackageDb, artifact: &ArtifactInfo) -> Option<Vec<u8>> {
+    if !artifact.is::<Wheel>() {
+        return None;
+    }
+    match package_db.get_metadata(&[artifact], None).await {
+        Ok(Some(_)) => package_db.cached_metadata_bytes(artifact).await,
+        Ok(None) => None,
+        Err(err) => {
+            tracing::warn!(
+                "failed to retrieve the metadata of {}: {err}",
+                artifact.filename
+            );
+            None
+        }
+    }
+}
+
+fn dependencies(
+    graph: &DependencyGraph,
+    name: &NormalizedPackageName,
+) -> Vec<NormalizedPackageName> {
+    let mut dependencies = graph
+        .dependencies(name)
+        .filter_map(|(_, node)| Some(node?.name.clone()))
+        .collect::<Vec<_>>();
+    dependencies.sort();
+    dependencies.dedup();
+    dependencies
+}
+
+fn sha256(component: &SbomComponent) -> Option<String> {
+    let sha256 = component.hashes.as_ref()?.sha256?;
+    Some(format!("{sha256:x}"))
+}
+
+fn spdx_id(component: &SbomComponent) -> String {
+    let id = format!("{}-{}", component.name.as_str(), component.version).replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+        "-",
+    );
+    format!("SPDXRef-Package-{id}")
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim()
+}
+
+/// Returns the license if it is an SPDX license expression like `MIT OR Apache-2.0`, many
+/// packages specify the license as free text instead.
+fn spdx_expression(license: &str) -> Option<&str> {
+    let license = license.trim();
+    let mut expects_id = true;
+    for token in license
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+    {
+        match token {
+            "(" | ")" => {}
+            "AND" | "OR" | "WITH" if !expects_id => expects_id = true,
+            id if expects_id
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+' | ':')) =>
+            {
+                expects_id = false
+            }
+            _ => return None,
+        }
+    }
+    (!license.is_empty() && !expects_id).then_some(license)
+}
+
+/// Formats `time` as an RFC 3339 timestamp in UTC, e.g. `2024-01-31T12:00:00Z`.
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // The civil date of a number of days since 1970-01-01
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+fn tool() -> Value {
+    json!({
+        "type": "library",
+        "name": "rattler_installs_packages",
+        "version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PackageName;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    fn name(name: &str) -> NormalizedPackageName {
+        PackageName::from_str(name).unwrap().into()
+    }
+
+    fn sbom() -> Sbom {
+        let mut sbom = Sbom::new(
+            "demo",
+            vec![
+                SbomComponent {
+                    name: name("flask"),
+                    version: Version::from_str("3.0.0").unwrap(),
+                    license: Some(String::from("BSD-3-Clause")),
+                    source_url: Some(
+                        Url::parse("https://files.example.com/flask-3.0.0-py3-none-any.whl")
+                            .unwrap(),
+                    ),
+                    hashes: Some(ArtifactHashes {
+                        sha256: rattler_digest::parse_digest_from_hex::<Sha256>(
+                            "aca0cab64ad4e6a9f4830ca8922d6229aa9baed0e0544dab7af1b229a3813c89",
+                        ),
+                    }),
+                    built_from_source: false,
+                    dependencies: vec![name("click"), name("missing")],
+                },
+                SbomComponent {
+                    name: name("click"),
+                    version: Version::from_str("8.1.7+local").unwrap(),
+                    license: Some(String::from("BSD License")),
+                    source_url: None,
+                    hashes: None,
+                    built_from_source: true,
+                    dependencies: Vec::new(),
+                },
+            ],
+        );
+        sbom.created = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_251_199);
+        sbom
+    }
+
+    #[test]
+    fn test_spdx_expression() {
+        assert_eq!(spdx_expression("MIT"), Some("MIT"));
+        assert_eq!(
+            spdx_expression("(MIT OR Apache-2.0) AND BSD-3-Clause"),
+            Some("(MIT OR Apache-2.0) AND BSD-3-Clause")
+        );
+        assert_eq!(spdx_expression("BSD License"), None);
+        assert_eq!(spdx_expression("MIT OR"), None);
+        assert_eq!(spdx_expression(""), None);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(
+            format_timestamp(SystemTime::UNIX_EPOCH),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(format_timestamp(sbom().created), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn test_cyclonedx() {
+        let document: Value = serde_json::from_str(&sbom().to_cyclonedx_json()).unwrap();
+        insta::assert_snapshot!(serde_json::to_string_pretty(&document["components"]).unwrap());
+        insta::assert_snapshot!(serde_json::to_string_pretty(&document["dependencies"]).unwrap());
+    }
+
+    #[test]
+    fn test_spdx() {
+        let document: Value = serde_json::from_str(&sbom().to_spdx_json()).unwrap();
+        insta::assert_snapshot!(serde_json::to_string_pretty(&document["packages"]).unwrap());
+        insta::assert_snapshot!(serde_json::to_string_pretty(&document["relationships"]).unwrap());
+    }
+}
@@ -0,0 +1,50 @@
+//! The [`tracing`] spans that are emitted by the library.
+//!
+//! Resolving, fetching metadata, building and installing are instrumented with nested spans. The
+//! names and fields of the spans below are stable, they can be used to build dashboards or to find
+//! bottlenecks in a timeline. Spans are emitted at the `INFO` level unless noted otherwise.
+//!
+//! | Span                | Fields                                                   | Emitted by                                                   |
+//! |---------------------|----------------------------------------------------------|--------------------------------------------------------------|
+//! | `resolve`           | `requirements`, `packages`                               | [`crate::resolve::resolve`]                                  |
+//! | `get_candidates`    | `package`, `versions`                                    | the solver, when it first encounters a package               |
+//! | `get_dependencies`  | `package`, `version`                                     | the solver, for every candidate it considers                 |
+//! | `fetch_index_page`  | `url`                                                    | [`crate::index::PackageDb::available_artifacts`]             |
+//! | `get_metadata`      | `package`, `version`, `cache_hit`                        | [`crate::index::PackageDb::get_metadata`]                    |
+//! | `get_wheel`         | `artifact`                                               | [`crate::index::PackageDb::get_wheel`]                       |
+//! | `get_artifact`      | `artifact`, `bytes`                                      | downloading or reading an artifact from the cache            |
+//! | `http_request`      | `method`, `url`, `cache_status`, `cache_hit`, `bytes`    | every request to an index, until its body has been read      |
+//! | `build_metadata`    | `package`, `version`                                     | [`crate::wheel_builder::WheelBuilder::get_sdist_metadata`]   |
+//! | `build_wheel`       | `package`, `version`                                     | [`crate::wheel_builder::WheelBuilder::build_wheel`]          |
+//! | `build_sdist`       | `package`                                                | [`crate::wheel_builder::WheelBuilder::build_sdist`]          |
+//! | `setup_build_env`   | `package`                                                | creating the venv and installing the build requirements      |
+//! | `run_build_backend` | `package`, `stage`, `exit_code`                          | every invocation of the build backend                        |
+//! | `sync`              | `install`, `reinstall`, `remove`                         | [`crate::install::execute_sync_plan`]                        |
+//! | `uninstall`         | `package`, `version`                                     | removing a distribution during a sync                        |
+//! | `install_wheels`    | `wheels`                                                 | [`crate::install::install_wheels`]                           |
+//! | `install_wheel`     | `package`, `version`                                     | installing a single wheel                                    |
+//!
+//! Fields without a value when the span is created (e.g. `packages`, `bytes`, `cache_hit` and
+//! `exit_code`) are recorded once they are known. Spans are nested as follows, e.g. the
+//! `http_request` spans of a resolution are children of the `get_candidates` or `get_metadata`
+//! span that caused them:
+//!
+//! ```text
+//! resolve
+//! ├── get_candidates
+//! │   └── fetch_index_page ── http_request
+//! └── get_dependencies
+//!     └── get_metadata
+//!         ├── http_request
+//!         └── build_metadata ── setup_build_env ── run_build_backend
+//! sync
+//! ├── get_wheel
+//! │   ├── get_artifact ── http_request
+//! │   └── build_wheel ── setup_build_env ── run_build_backend
+//! ├── uninstall
+//! └── install_wheels ── install_wheel
+//! ```
+//!
+//! The spans are regular [`tracing`] spans, use `tracing-opentelemetry` to export them to an
+//! OpenTelemetry collector over OTLP, or `tracing-subscriber` with
+//! `FmtSpan::CLOSE` to log how long each of them took.
@@ -0,0 +1,316 @@
+//! Isolated environments for python applications, similar to `pipx`.
+//!
+//! Every tool is installed into its own virtual environment in a tools directory so that the
+//! dependencies of different applications never conflict. The scripts of the application are
+//! linked into a shared bin directory that is usually on the `PATH`. A receipt is stored in the
+//! environment of every tool that records how it was installed and which scripts were linked, so
+//! tools can be listed, upgraded and removed later.
+//!
+//! This module only manages the layout of the tools directory, resolving and installing the
+//! packages of a tool is left to the caller.
+
+use crate::install::{InstallTarget, LinkStrategy};
+use crate::python_env::{find_distributions_in_venv, FindDistributionError};
+use crate::types::{NormalizedPackageName, Record};
+use crate::utils::{normalize_path, remove_file};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The name of the file in the environment of a tool that holds its [`ToolReceipt`].
+pub const TOOL_RECEIPT_FILE_NAME: &str = "rip-tool.json";
+
+/// An error that can occur when managing tools.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ToolsError {
+    #[error("tool '{0}' is not installed")]
+    NotInstalled(NormalizedPackageName),
+
+    #[error("'{0}' is not installed in the environment of the tool")]
+    ApplicationNotInstalled(NormalizedPackageName),
+
+    #[error("failed to read the receipt at {0}")]
+    InvalidReceipt(PathBuf, #[source] serde_json::Error),
+
+    #[error("failed to read the RECORD file of '{0}'")]
+    InvalidRecord(NormalizedPackageName, #[source] csv::Error),
+
+    #[error("{0} already exists and is not managed by this tool")]
+    ScriptConflict(PathBuf),
+
+    #[error(transparent)]
+    FailedToFindDistributions(#[from] FindDistributionError),
+
+    #[error("io error while accessing {0}")]
+    IoError(PathBuf, #[source] io::Error),
+}
+
+/// Describes how a tool was installed.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ToolReceipt {
+    /// The name of the package that provides the application.
+    pub name: NormalizedPackageName,
+
+    /// The requirements that were used to install the tool, e.g. `black[d]>=23`.
+    pub requirements: Vec<String>,
+
+    /// The python interpreter that was used to create the environment of the tool, if it was
+    /// explicitly specified.
+    pub python: Option<PathBuf>,
+
+    /// The names of the scripts that were linked into the bin directory.
+    #[serde(default)]
+    pub scripts: Vec<String>,
+}
+
+/// A directory that contains the environments of tools together with the directory into which
+/// the scripts of the tools are linked.
+#[derive(Debug, Clone)]
+pub struct ToolsDirectory {
+    root: PathBuf,
+    bin_dir: PathBuf,
+}
+
+impl ToolsDirectory {
+    /// Constructs a new instance that stores the environments of the tools in `root` and links
+    /// their scripts into `bin_dir`.
+    pub fn new(root: impl Into<PathBuf>, bin_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            bin_dir: bin_dir.into(),
+        }
+    }
+
+    /// Returns the directory that contains the environments of the tools.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory into which the scripts of the tools are linked.
+    pub fn bin_dir(&self) -> &Path {
+        &self.bin_dir
+    }
+
+    /// Returns the location of the environment of the tool with the given name.
+    pub fn tool_dir(&self, name: &NormalizedPackageName) -> PathBuf {
+        self.root.join(name.as_str())
+    }
+
+    /// Returns the receipt of the tool with the given name or `None` if the tool is not installed.
+    pub fn receipt(&self, name: &NormalizedPackageName) -> Result<Option<ToolReceipt>, ToolsError> {
+        let path = self.tool_dir(name).join(TOOL_RECEIPT_FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents =
+            fs::read_to_string(&path).map_err(|e| ToolsError::IoError(path.clone(), e))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|e| ToolsError::InvalidReceipt(path, e))
+    }
+
+    /// Stores the receipt of a tool in its environment.
+    pub fn write_receipt(&self, receipt: &ToolReceipt) -> Result<(), ToolsError> {
+        let path = self.tool_dir(&receipt.name).join(TOOL_RECEIPT_FILE_NAME);
+        let contents = serde_json::to_string_pretty(receipt)
+            .map_err(|e| ToolsError::InvalidReceipt(path.clone(), e))?;
+        fs::write(&path, contents).map_err(|e| ToolsError::IoError(path, e))
+    }
+
+    /// Returns the receipts of all the installed tools, sorted by name.
+    pub fn list(&self) -> Result<Vec<ToolReceipt>, ToolsError> {
+        if !self.root.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut receipts = Vec::new();
+        let entries =
+            fs::read_dir(&self.root).map_err(|e| ToolsError::IoError(self.root.clone(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| ToolsError::IoError(self.root.clone(), e))?;
+            let Some(name) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<NormalizedPackageName>().ok())
+            else {
+                continue;
+            };
+            if let Some(receipt) = self.receipt(&name)? {
+                receipts.push(receipt);
+            }
+        }
+        receipts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(receipts)
+    }
+
+    /// Links the scripts of the application of the tool from its environment `env` into the bin
+    /// directory and records them in the `receipt`. Scripts that were linked previously but are no
+    /// longer provided by the application are removed.
+    ///
+    /// Only the scripts of the application itself are linked, not those of its dependencies.
+    /// Returns an error if a script would replace a file that is not managed by this tool.
+    pub fn link_scripts<T: InstallTarget + ?Sized>(
+        &self,
+        receipt: &mut ToolReceipt,
+        env: &T,
+    ) -> Result<Vec<PathBuf>, ToolsError> {
+        let scripts = application_scripts(&receipt.name, env)?;
+
+        // Make sure we do not overwrite any files that we don't own.
+        for script in scripts.iter() {
+            let file_name = script.file_name().expect("scripts have a file name");
+            let destination = self.bin_dir.join(file_name);
+            let owned = receipt
+                .scripts
+                .iter()
+                .any(|name| Path::new(name) == Path::new(file_name));
+            if !owned && destination.symlink_metadata().is_ok() {
+                return Err(ToolsError::ScriptConflict(destination));
+            }
+        }
+
+        fs::create_dir_all(&self.bin_dir)
+            .map_err(|e| ToolsError::IoError(self.bin_dir.clone(), e))?;
+        let mut linked = Vec::new();
+        let mut names = Vec::new();
+        for script in scripts {
+            let file_name = script.file_name().expect("scripts have a file name");
+            let destination = self.bin_dir.join(file_name);
+            LinkStrategy::Symlink
+                .link(&script, &destination)
+                .map_err(|e| ToolsError::IoError(destination.clone(), e))?;
+            names.push(file_name.to_string_lossy().into_owned());
+            linked.push(destination);
+        }
+
+        // Remove the scripts that are no longer provided
+        for stale in receipt.scripts.iter().filter(|name| !names.contains(name)) {
+            self.unlink_script(stale)?;
+        }
+
+        receipt.scripts = names;
+        Ok(linked)
+    }
+
+    /// Removes the tool with the given name together with the scripts that were linked for it.
+    /// Returns the receipt of the removed tool.
+    pub fn remove(&self, name: &NormalizedPackageName) -> Result<ToolReceipt, ToolsError> {
+        let receipt = self
+            .receipt(name)?
+            .ok_or_else(|| ToolsError::NotInstalled(name.clone()))?;
+        for script in receipt.scripts.iter() {
+            self.unlink_script(script)?;
+        }
+
+        let tool_dir = self.tool_dir(name);
+        fs::remove_dir_all(&tool_dir).map_err(|e| ToolsError::IoError(tool_dir, e))?;
+        Ok(receipt)
+    }
+
+    /// Removes a script that was linked into the bin directory.
+    fn unlink_script(&self, name: &str) -> Result<(), ToolsError> {
+        let path = self.bin_dir.join(name);
+        match remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(ToolsError::IoError(path, e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the absolute paths of the scripts that the distribution with the given `name` installed
+/// into the scripts directory of `env`. These are determined from the RECORD file of the
+/// distribution so both entry points and scripts from the `.data` directory are included.
+pub fn application_scripts<T: InstallTarget + ?Sized>(
+    name: &NormalizedPackageName,
+    env: &T,
+) -> Result<Vec<PathBuf>, ToolsError> {
+    let distribution = find_distributions_in_venv(env.root(), env.install_paths())?
+        .into_iter()
+        .find(|distribution| &distribution.name == name)
+        .ok_or_else(|| ToolsError::ApplicationNotInstalled(name.clone()))?;
+
+    let dist_info = env.root().join(distribution.dist_info);
+    let site_packages = dist_info
+        .parent()
+        .expect("a .dist-info directory always has a parent");
+    let record = Record::from_path(&dist_info.join("RECORD"))
+        .map_err(|e| ToolsError::InvalidRecord(name.clone(), e))?;
+
+    let scripts_dir = normalize_path(&env.root().join(env.install_paths().scripts()));
+    let mut scripts = record
+        .iter()
+        .map(|entry| normalize_path(&site_packages.join(&entry.path)))
+        .filter(|path| path.parent() == Some(scripts_dir.as_path()))
+        .collect::<Vec<_>>();
+    scripts.sort();
+    Ok(scripts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::artifacts::wheel::UnpackWheelOptions;
+    use crate::artifacts::Wheel;
+    use crate::python_env::TargetDirectory;
+    use std::str::FromStr;
+
+    fn install_miniblack(tools: &ToolsDirectory) -> (ToolReceipt, TargetDirectory) {
+        let name = NormalizedPackageName::from_str("miniblack").unwrap();
+        let env = TargetDirectory::new(tools.tool_dir(&name), "/usr/bin/python3");
+        let wheel = Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels/miniblack-23.1.0-py3-none-any.whl"),
+            &name,
+        )
+        .unwrap();
+        env.install_wheel(&wheel, &UnpackWheelOptions::default())
+            .unwrap();
+
+        let receipt = ToolReceipt {
+            name,
+            requirements: vec![String::from("miniblack")],
+            python: None,
+            scripts: Vec::new(),
+        };
+        (receipt, env)
+    }
+
+    #[test]
+    fn test_install_and_remove_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = ToolsDirectory::new(dir.path().join("tools"), dir.path().join("bin"));
+        let (mut receipt, env) = install_miniblack(&tools);
+
+        let linked = tools.link_scripts(&mut receipt, &env).unwrap();
+        tools.write_receipt(&receipt).unwrap();
+        assert_eq!(receipt.scripts, ["black", "blackd"]);
+        assert!(linked.iter().all(|script| script.is_file()));
+
+        // Linking again replaces the scripts we own
+        tools.link_scripts(&mut receipt, &env).unwrap();
+
+        assert_eq!(tools.list().unwrap(), vec![receipt.clone()]);
+
+        tools.remove(&receipt.name).unwrap();
+        assert!(tools.list().unwrap().is_empty());
+        assert!(!dir.path().join("bin/black").exists());
+        assert!(!tools.tool_dir(&receipt.name).exists());
+    }
+
+    #[test]
+    fn test_script_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = ToolsDirectory::new(dir.path().join("tools"), dir.path().join("bin"));
+        let (mut receipt, env) = install_miniblack(&tools);
+
+        fs::create_dir_all(tools.bin_dir()).unwrap();
+        fs::write(tools.bin_dir().join("black"), "").unwrap();
+        assert!(matches!(
+            tools.link_scripts(&mut receipt, &env),
+            Err(ToolsError::ScriptConflict(_))
+        ));
+    }
+}
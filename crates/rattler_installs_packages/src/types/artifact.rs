@@ -2,6 +2,7 @@ use super::artifact_name::InnerAsArtifactName;
 use crate::resolve::PypiVersion;
 use crate::types::SourceArtifactName;
 use crate::utils::ReadAndSeek;
+use std::io::Write;
 use std::path::Path;
 
 /// Trait to implement if it is a type that has an [`super::artifact_name::ArtifactName`]
@@ -47,6 +48,17 @@ pub trait ArtifactFromSource: HasArtifactName + Sync {
     /// that will we be used for hashing
     fn try_get_bytes(&self) -> Result<Vec<u8>, std::io::Error>;
 
+    /// Writes the bytes of [`Self::try_get_bytes`] to `writer`, e.g. a hasher. Implementations
+    /// stream the bytes instead of reading them into memory at once.
+    fn write_bytes_to(&self, writer: &mut dyn Write) -> Result<(), std::io::Error> {
+        writer.write_all(&self.try_get_bytes()?)
+    }
+
+    /// Returns the size of the archive in bytes, if this artifact is an archive.
+    fn archive_size(&self) -> Option<u64> {
+        None
+    }
+
     /// Distribution Name
     fn distribution_name(&self) -> String;
 
@@ -37,6 +37,8 @@ pub enum ArtifactName {
     SDist(SDistFilename),
     /// STree artifact
     STree(STreeFilename),
+    /// An artifact of a kind registered in an [`crate::artifacts::ArtifactRegistry`]
+    Custom(CustomArtifactName),
 }
 
 impl ArtifactName {
@@ -46,6 +48,7 @@ impl ArtifactName {
             ArtifactName::Wheel(name) => name.version.clone(),
             ArtifactName::SDist(name) => name.version.clone(),
             ArtifactName::STree(name) => name.version.clone(),
+            ArtifactName::Custom(name) => name.version.clone(),
         }
     }
 
@@ -55,6 +58,7 @@ impl ArtifactName {
             ArtifactName::Wheel(wheel) => Some(wheel),
             ArtifactName::SDist(_) => None,
             ArtifactName::STree(_) => None,
+            ArtifactName::Custom(_) => None,
         }
     }
 
@@ -64,6 +68,7 @@ impl ArtifactName {
             ArtifactName::Wheel(_) => None,
             ArtifactName::STree(_) => None,
             ArtifactName::SDist(sdist) => Some(sdist),
+            ArtifactName::Custom(_) => None,
         }
     }
 
@@ -73,6 +78,15 @@ impl ArtifactName {
             ArtifactName::Wheel(_) => None,
             ArtifactName::STree(name) => Some(name),
             ArtifactName::SDist(_) => None,
+            ArtifactName::Custom(_) => None,
+        }
+    }
+
+    /// Returns this name as the name of a custom artifact
+    pub fn as_custom(&self) -> Option<&CustomArtifactName> {
+        match self {
+            ArtifactName::Custom(name) => Some(name),
+            _ => None,
         }
     }
 
@@ -87,6 +101,7 @@ impl ArtifactName {
             ArtifactName::Wheel(name) => name.distribution.clone(),
             ArtifactName::STree(name) => name.distribution.clone(),
             ArtifactName::SDist(name) => name.distribution.clone(),
+            ArtifactName::Custom(name) => name.distribution.clone(),
         }
     }
 }
@@ -97,6 +112,7 @@ impl Display for ArtifactName {
             ArtifactName::Wheel(name) => write!(f, "{}", name),
             ArtifactName::SDist(name) => write!(f, "{}", name),
             ArtifactName::STree(name) => write!(f, "{}", name),
+            ArtifactName::Custom(name) => write!(f, "{}", name),
         }
     }
 }
@@ -149,6 +165,20 @@ impl WheelFilename {
                 platform: arch.clone(),
             })
     }
+
+    /// Returns true if this wheel targets the stable ABI of CPython (`abi3`).
+    pub fn is_abi3(&self) -> bool {
+        self.abi_tags.iter().any(|abi| abi == "abi3")
+    }
+
+    /// Returns true if this wheel only targets ABIs that are specific to an interpreter version,
+    /// e.g. `cp311`. Wheels that target `abi3` or no ABI at all (`none`) return false.
+    pub fn is_abi_version_specific(&self) -> bool {
+        !self
+            .abi_tags
+            .iter()
+            .any(|abi| abi == "abi3" || abi == "none")
+    }
 }
 
 impl Display for WheelFilename {
@@ -212,6 +242,29 @@ pub struct STreeFilename {
     pub url: Url,
 }
 
+/// The name of an artifact of a kind that is not natively supported but that was registered in an
+/// [`crate::artifacts::ArtifactRegistry`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct CustomArtifactName {
+    /// The name of the [`crate::artifacts::ArtifactKind`] that recognized the artifact.
+    pub kind: String,
+
+    /// Distribution name, e.g. ‘django’, ‘pyramid’.
+    pub distribution: PackageName,
+
+    /// Version of the distribution as derived from the filename
+    pub version: Version,
+
+    /// The original filename of the artifact
+    pub filename: String,
+}
+
+impl Display for CustomArtifactName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.filename)
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
 /// SourceArtifactName
 pub enum SourceArtifactName {
@@ -253,6 +306,86 @@ impl Display for STreeFilename {
     }
 }
 
+/// Structure that contains the information that is contained in a legacy `.egg` filename, e.g.
+/// `lxml-4.9.0-py3.9-linux-x86_64.egg`.
+///
+/// Eggs are not installed directly, instead they can be converted to a wheel with
+/// [`crate::artifacts::Egg`].
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+pub struct EggFilename {
+    /// Distribution name, e.g. ‘django’, ‘pyramid’.
+    pub distribution: PackageName,
+
+    /// Distribution version, e.g. 1.0.
+    pub version: Version,
+
+    /// The version of python the egg was built for, e.g. `3.9`.
+    pub python_version: Option<String>,
+
+    /// The platform the egg was built for, e.g. `linux-x86_64`. Eggs without a platform are pure
+    /// python.
+    pub platform: Option<String>,
+}
+
+impl FromStr for EggFilename {
+    type Err = ParseArtifactNameError;
+
+    /// Parse the egg name from a filename string, e.g. "six-1.16.0-py3.9.egg". Contrary to the
+    /// other filenames no package name is required because setuptools escapes dashes in the
+    /// distribution name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(file_stem) = s.strip_suffix(".egg") else {
+            return Err(ParseArtifactNameError::InvalidExtension(s.to_string()));
+        };
+
+        let mut parts = file_stem.splitn(4, '-');
+        let (Some(distribution), Some(version)) = (parts.next(), parts.next()) else {
+            return Err(ParseArtifactNameError::InvalidName);
+        };
+
+        let distribution = PackageName::from_str(distribution)
+            .map_err(ParseArtifactNameError::InvalidPackageName)?;
+        let version = Version::from_str(version)
+            .map_err(|e| ParseArtifactNameError::InvalidVersion(e.to_string()))?;
+
+        let python_version = match parts.next() {
+            Some(python_tag) => Some(
+                python_tag
+                    .strip_prefix("py")
+                    .ok_or(ParseArtifactNameError::InvalidName)?
+                    .to_owned(),
+            ),
+            None => None,
+        };
+        let platform = parts.next().map(ToOwned::to_owned);
+
+        Ok(Self {
+            distribution,
+            version,
+            python_version,
+            platform,
+        })
+    }
+}
+
+impl Display for EggFilename {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{dist}-{ver}",
+            dist = self.distribution.as_source_str().replace('-', "_"),
+            ver = self.version,
+        )?;
+        if let Some(python_version) = &self.python_version {
+            write!(f, "-py{python_version}")?;
+        }
+        if let Some(platform) = &self.platform {
+            write!(f, "-{platform}")?;
+        }
+        write!(f, ".egg")
+    }
+}
+
 /// Describes the format in which the source distribution is shipped.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
 #[allow(missing_docs)]
@@ -337,8 +470,8 @@ impl FromStr for BuildTag {
     type Err = ParseArtifactNameError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let first_alpha_idx = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
-        let (digits, name) = s.split_at(first_alpha_idx);
+        let first_non_digit_idx = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (digits, name) = s.split_at(first_non_digit_idx);
         Ok(Self {
             number: digits
                 .parse()
@@ -549,6 +682,12 @@ impl InnerAsArtifactName for STreeFilename {
     }
 }
 
+impl InnerAsArtifactName for CustomArtifactName {
+    fn try_as(name: &ArtifactName) -> Option<&Self> {
+        name.as_custom()
+    }
+}
+
 /// Enum that contains the different artifacts types:
 ///    * SDist is a python source distribution
 ///    * Wheel is a python binary distribution
@@ -700,4 +839,37 @@ mod test {
 
         assert_eq!(n.to_string(), "foo.bar-0.1b3-1local-py2.py3-none-any.whl");
     }
+
+    #[test]
+    fn test_build_tag_ordering() {
+        let tags = ["1", "1a", "1b", "2", "10", "10_x"]
+            .into_iter()
+            .map(|t| BuildTag::from_str(t).unwrap())
+            .collect::<Vec<_>>();
+        assert!(tags.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(BuildTag::from_str("10_x").unwrap().to_string(), "10_x");
+        assert!(BuildTag::from_str("a1").is_err());
+    }
+
+    #[test]
+    fn test_egg_name_from_str() {
+        let n = EggFilename::from_str("six-1.16.0-py3.9.egg").unwrap();
+        assert_eq!(n.distribution, "six".parse().unwrap());
+        assert_eq!(n.version, "1.16.0".parse().unwrap());
+        assert_eq!(n.python_version.as_deref(), Some("3.9"));
+        assert_eq!(n.platform, None);
+        assert_eq!(n.to_string(), "six-1.16.0-py3.9.egg");
+
+        let n = EggFilename::from_str("zope_interface-5.4.0-py3.9-linux-x86_64.egg").unwrap();
+        assert_eq!(n.distribution, "zope-interface".parse().unwrap());
+        assert_eq!(n.platform.as_deref(), Some("linux-x86_64"));
+        assert_eq!(n.to_string(), "zope_interface-5.4.0-py3.9-linux-x86_64.egg");
+
+        let n = EggFilename::from_str("foo-1.0.egg").unwrap();
+        assert_eq!(n.python_version, None);
+        assert_eq!(n.to_string(), "foo-1.0.egg");
+
+        assert!(EggFilename::from_str("foo-1.0.whl").is_err());
+    }
 }
@@ -1,7 +1,7 @@
 // Implementation comes from https://github.com/njsmith/posy/blob/main/src/vocab/core_metadata.rs
 // Licensed under MIT or Apache-2.0
 
-use super::extra::ParseExtraError;
+use super::extra::normalize_marker_extras;
 use crate::{
     types::Extra, types::PackageName, types::ParsePackageNameError, types::RFC822ish,
     types::Version, types::VersionSpecifiers,
@@ -99,9 +99,6 @@ pub enum WheelCoreMetaDataError {
     #[error(transparent)]
     InvalidPackageName(#[from] ParsePackageNameError),
 
-    #[error("invalid extra identifier '{0}'")]
-    InvalidExtra(String, #[source] ParseExtraError),
-
     #[error("{0}")]
     FailedToParse(String),
 }
@@ -122,11 +119,18 @@ impl TryFrom<PackageInfo> for WheelCoreMetadata {
 
         let mut requires_dist = Vec::new();
         for req_str in parsed.take_all("Requires-Dist").into_iter() {
-            match req_str.parse() {
+            match req_str.parse::<Requirement>() {
                 Err(e) => {
                     tracing::warn!("ignoring Requires-Dist: {req_str}, failed to parse: {e}")
                 }
-                Ok(req) => requires_dist.push(req),
+                Ok(mut req) => {
+                    // Normalize the extras in the markers so they can be compared with the
+                    // normalized extras of a package.
+                    if let Some(marker) = req.marker.as_mut() {
+                        normalize_marker_extras(marker);
+                    }
+                    requires_dist.push(req)
+                }
             }
         }
 
@@ -140,11 +144,14 @@ impl TryFrom<PackageInfo> for WheelCoreMetadata {
 
         let mut extras: HashSet<Extra> = HashSet::new();
         for extra in parsed.take_all("Provides-Extra").drain(..) {
-            extras.insert(
-                extra
-                    .parse()
-                    .map_err(|e| WheelCoreMetaDataError::InvalidExtra(extra, e))?,
-            );
+            match extra.parse() {
+                Err(e) => {
+                    tracing::warn!("ignoring Provides-Extra: {extra}, failed to parse: {e}")
+                }
+                Ok(extra) => {
+                    extras.insert(extra);
+                }
+            }
         }
 
         Ok(WheelCoreMetadata {
@@ -1,14 +1,19 @@
 use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
 use url::Url;
 
 /// Specifies the PyPa `direct_url.json` format.
 /// See: <https://packaging.python.org/en/latest/specifications/direct-url-data-structure/>
 ///
-#[derive(Debug, Serialize, Deserialize)]
 #[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DirectUrlJson {
     /// Url to the source.
     pub url: Url,
+    /// The path, relative to the root of the source, of the directory that contains the
+    /// `setup.py` or `pyproject.toml` if it is not located at the root.
+    pub subdirectory: Option<String>,
     /// Information about the source.
     #[serde(flatten)]
     pub source: DirectUrlSource,
@@ -17,7 +22,8 @@ pub struct DirectUrlJson {
 /// Specifies the source of a direct url.
 ///
 /// currently we do not support the deprecated `hash` field
-#[derive(Debug, Serialize, Deserialize)]
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum DirectUrlSource {
     #[serde(rename = "archive_info")]
     /// Information about the archive file.
@@ -44,16 +50,79 @@ pub enum DirectUrlSource {
     },
 }
 
+impl DirectUrlJson {
+    /// The name of the file in the `.dist-info` directory that holds this information.
+    pub const FILE_NAME: &'static str = "direct_url.json";
+
+    /// Reads the contents of a `direct_url.json` file from disk.
+    pub fn from_path(path: &Path) -> serde_json::Result<Self> {
+        Self::from_reader(fs_err::File::open(path).map_err(serde_json::Error::io)?)
+    }
+
+    /// Reads the `direct_url.json` file from a `.dist-info` directory. Returns `Ok(None)` if the
+    /// distribution was not installed from a direct url and therefore has no such file.
+    pub fn from_dist_info(dist_info: &Path) -> serde_json::Result<Option<Self>> {
+        let path = dist_info.join(Self::FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::from_path(&path).map(Some)
+    }
+
+    /// Reads the contents of a `direct_url.json` file from a reader.
+    pub fn from_reader(reader: impl Read) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Writes the contents of this instance to a writer.
+    pub fn to_writer(&self, writer: impl Write) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    /// Write to a `direct_url.json` file on disk
+    pub fn write_to_path(&self, path: &Path) -> serde_json::Result<()> {
+        self.to_writer(fs_err::File::create(path).map_err(serde_json::Error::io)?)
+    }
+
+    /// Returns true if this refers to a local directory that was installed in editable mode.
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self.source,
+            DirectUrlSource::Dir {
+                editable: Some(true)
+            }
+        )
+    }
+
+    /// Returns the commit that was checked out if this refers to a VCS source.
+    pub fn vcs_commit_id(&self) -> Option<&str> {
+        match &self.source {
+            DirectUrlSource::Vcs { commit_id, .. } => Some(commit_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the sha256 of the archive if this refers to an archive with a known hash.
+    pub fn archive_sha256(&self) -> Option<&str> {
+        match &self.source {
+            DirectUrlSource::Archive {
+                hashes: Some(hashes),
+            } => Some(&hashes.sha256),
+            _ => None,
+        }
+    }
+}
+
 /// Hashes for internal archive files.
 /// multiple hashes can be included but per recommendation only sha256 should be used.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DirectUrlHashes {
     /// Sha256 hash of the archive file.
     pub sha256: String,
 }
 
 /// Name of the VCS in a DirectUrlSource
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 #[allow(missing_docs)]
 pub enum DirectUrlVcs {
     #[serde(rename = "git")]
@@ -68,7 +137,7 @@ pub enum DirectUrlVcs {
 
 #[cfg(test)]
 mod tests {
-    use crate::types::direct_url_json::DirectUrlJson;
+    use crate::types::direct_url_json::{DirectUrlJson, DirectUrlSource};
 
     /// Tests if json outputs aligns with the examples at:
     /// https://packaging.python.org/en/latest/specifications/direct-url-data-structure/
@@ -101,6 +170,20 @@ mod tests {
         "#;
         serde_json::from_str::<DirectUrlJson>(example).unwrap();
 
+        // Git URL with a subdirectory:
+        let example = r#"
+        {
+            "url": "https://github.com/pypa/pip.git",
+            "subdirectory": "src/pip",
+            "vcs_info": {
+                "vcs": "git",
+                "commit_id": "7921be1537eac1e97bc40179a57f0349c2aee67d"
+            }
+        }
+        "#;
+        let parsed = serde_json::from_str::<DirectUrlJson>(example).unwrap();
+        assert_eq!(parsed.subdirectory.as_deref(), Some("src/pip"));
+
         // Local directory:
         let example = r#"
         {
@@ -121,4 +204,40 @@ mod tests {
         "#;
         serde_json::from_str::<DirectUrlJson>(example).unwrap();
     }
+
+    #[test]
+    pub fn test_roundtrip() {
+        let direct_url = DirectUrlJson {
+            url: "file:///home/user/project".parse().unwrap(),
+            subdirectory: None,
+            source: DirectUrlSource::Dir {
+                editable: Some(true),
+            },
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        direct_url
+            .write_to_path(&dir.path().join(DirectUrlJson::FILE_NAME))
+            .unwrap();
+        let read_back = DirectUrlJson::from_dist_info(dir.path()).unwrap().unwrap();
+
+        assert_eq!(direct_url, read_back);
+        assert!(read_back.is_editable());
+    }
+
+    #[test]
+    pub fn test_skip_none() {
+        let direct_url = DirectUrlJson {
+            url: "https://github.com/pypa/pip/archive/1.3.1.zip"
+                .parse()
+                .unwrap(),
+            subdirectory: None,
+            source: DirectUrlSource::Archive { hashes: None },
+        };
+
+        assert_eq!(
+            serde_json::to_string(&direct_url).unwrap(),
+            r#"{"url":"https://github.com/pypa/pip/archive/1.3.1.zip","archive_info":{}}"#
+        );
+    }
 }
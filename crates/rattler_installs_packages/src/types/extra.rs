@@ -1,13 +1,15 @@
 // Implementation comes from https://github.com/njsmith/posy/blob/main/src/vocab/extra.rs
 // Licensed under MIT or Apache-2.0
 
-// 'Extra' string format is not well specified. It looks like what poetry does is simply normalize
-// the name and be done with it.
+// Extras are normalized according to PEP 685, which means that any run of '-', '_' and '.' is
+// collapsed into a single '-' and the result is lowercased. This is the same normalization as is
+// done for package names in PEP 503.
 //
 // PEP 508's grammar for requirement specifiers says that extras have to
 // be "identifiers", which means: first char [A-Za-z0-9], remaining chars also
-// allowed to include '-_.'. But in practice we've seen Extras like "ssl:sys_platform=='win32'"
-// which do not follow that rule at all.
+// allowed to include '-_.'. In practice we've seen Extras like "ssl:sys_platform=='win32'"
+// which do not follow that rule at all. These are rejected when parsing, callers that read
+// metadata are expected to skip them.
 
 // ORIGINAL comment from Posy.
 
@@ -29,12 +31,16 @@
 // There's probably a better way to factor this and reduce code duplication...
 
 use miette::Diagnostic;
+use pep508_rs::{MarkerTree, MarkerValue};
+use regex::Regex;
 use serde::{Serialize, Serializer};
 use serde_with::DeserializeFromStr;
 use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::sync::OnceLock;
 use thiserror::Error;
 
 #[derive(Debug, Clone, Eq, DeserializeFromStr)]
@@ -59,26 +65,84 @@ impl Extra {
     pub fn as_str(&self) -> &str {
         self.normalized.as_ref()
     }
+
+    /// Normalizes the specified string according to
+    /// [PEP 685](https://peps.python.org/pep-0685/#specification) without validating it.
+    pub fn normalize(s: &str) -> String {
+        let mut normalized = String::with_capacity(s.len());
+        let mut last_was_separator = false;
+        for c in s.chars() {
+            if matches!(c, '-' | '_' | '.') {
+                if !last_was_separator {
+                    normalized.push('-');
+                }
+                last_was_separator = true;
+            } else {
+                normalized.push(c.to_ascii_lowercase());
+                last_was_separator = false;
+            }
+        }
+        normalized
+    }
+}
+
+/// Normalizes all the extras that are compared against in the specified marker tree. This ensures
+/// that a marker like `extra == "Foo_Bar"` matches the normalized extra `foo-bar`.
+pub(crate) fn normalize_marker_extras(tree: &mut MarkerTree) {
+    match tree {
+        MarkerTree::Expression(expression) => {
+            if let (MarkerValue::Extra, MarkerValue::QuotedString(value)) =
+                (&expression.l_value, &mut expression.r_value)
+            {
+                *value = Extra::normalize(value);
+            }
+            if let (MarkerValue::QuotedString(value), MarkerValue::Extra) =
+                (&mut expression.l_value, &expression.r_value)
+            {
+                *value = Extra::normalize(value);
+            }
+        }
+        MarkerTree::And(trees) | MarkerTree::Or(trees) => {
+            trees.iter_mut().for_each(normalize_marker_extras)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Error, Diagnostic)]
-pub enum ParseExtraError {}
+/// Error when parsing an extra
+#[allow(missing_docs)]
+pub enum ParseExtraError {
+    #[error("invalid extra '{0}'")]
+    InvalidExtra(String),
+}
 
 impl FromStr for Extra {
     type Err = ParseExtraError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        // https://www.python.org/dev/peps/pep-0503/#normalized-names
-        let mut normalized = s.replace(['-', '_', '.'], "-");
-        normalized.make_ascii_lowercase();
+        static EXTRA_VALIDATE: OnceLock<Regex> = OnceLock::new();
+        let extra_validate = EXTRA_VALIDATE.get_or_init(|| {
+            // https://peps.python.org/pep-0508/#names
+            Regex::new(r"(?i-u)^([A-Z0-9]|[A-Z0-9][A-Z0-9._-]*[A-Z0-9])$").unwrap()
+        });
+
+        if !extra_validate.is_match(s) {
+            return Err(ParseExtraError::InvalidExtra(s.into()));
+        }
 
         Ok(Self {
             source: s.to_owned().into_boxed_str(),
-            normalized: normalized.into_boxed_str(),
+            normalized: Self::normalize(s).into_boxed_str(),
         })
     }
 }
 
+impl Display for Extra {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.normalized)
+    }
+}
+
 impl Hash for Extra {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.normalized.hash(state)
@@ -117,3 +181,43 @@ impl Borrow<str> for Extra {
         self.normalized.as_ref()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_extra_normalization() {
+        let extra1: Extra = "Foo__Bar.-Baz".parse().unwrap();
+        assert_eq!(extra1.as_source_str(), "Foo__Bar.-Baz");
+        assert_eq!(extra1.as_str(), "foo-bar-baz");
+
+        let extra2: Extra = "foo.bar_baz".parse().unwrap();
+        assert_eq!(extra1, extra2);
+        assert_eq!(extra2.to_string(), "foo-bar-baz");
+
+        let extra3: Extra = "foo-barbaz".parse().unwrap();
+        assert_ne!(extra1, extra3);
+    }
+
+    #[test]
+    fn test_invalid_extra() {
+        assert!(Extra::from_str("").is_err());
+        assert!(Extra::from_str("-foo").is_err());
+        assert!(Extra::from_str("foo_").is_err());
+        assert!(Extra::from_str("ssl:sys_platform=='win32'").is_err());
+    }
+
+    #[test]
+    fn test_normalize_marker_extras() {
+        let mut marker = MarkerTree::from_str(
+            "python_version >= '3.8' and (extra == 'Foo_Bar' or 'Test' == extra)",
+        )
+        .unwrap();
+        normalize_marker_extras(&mut marker);
+        assert_eq!(
+            marker.to_string(),
+            "python_version >= '3.8' and (extra == 'foo-bar' or 'test' == extra)"
+        );
+    }
+}
@@ -17,24 +17,66 @@ mod entry_points;
 mod project_info;
 
 mod direct_url_json;
+
+mod provenance_url_json;
+
+mod script_metadata;
+
+mod requirement_builder;
 mod rfc822ish;
 
+mod requirements_file;
+
+mod required_hashes;
+
+mod project_requirements;
+
+mod workspace;
+
+mod pyproject_editor;
+
+mod package_description;
+
 pub use artifact::{ArtifactFromBytes, ArtifactFromSource, HasArtifactName, ReadPyProjectError};
 
 pub use artifact_name::{
-    ArtifactName, ArtifactType, BuildTag, InnerAsArtifactName, ParseArtifactNameError,
-    SDistFilename, SDistFormat, STreeFilename, SourceArtifactName, WheelFilename,
+    ArtifactName, ArtifactType, BuildTag, CustomArtifactName, EggFilename, InnerAsArtifactName,
+    ParseArtifactNameError, SDistFilename, SDistFormat, STreeFilename, SourceArtifactName,
+    WheelFilename,
 };
 
 pub use direct_url_json::{DirectUrlHashes, DirectUrlJson, DirectUrlSource, DirectUrlVcs};
 
+pub use provenance_url_json::{ProvenanceArchiveInfo, ProvenanceUrlJson};
+
 pub use core_metadata::{MetadataVersion, PackageInfo, WheelCoreMetaDataError, WheelCoreMetadata};
 
+pub use package_description::PackageDescription;
+
 pub use record::{Record, RecordEntry};
 
+pub use script_metadata::{ScriptMetadata, ScriptMetadataError};
+
 pub use package_name::{NormalizedPackageName, PackageName, ParsePackageNameError};
 
-pub use extra::Extra;
+pub use extra::{Extra, ParseExtraError};
+
+pub use requirement_builder::{format_requirement, RequirementBuilder, RequirementBuilderError};
+
+pub use requirements_file::{
+    parse_requirements_file, read_requirements_file, RequirementsFile, RequirementsFileError,
+};
+
+pub use required_hashes::RequiredHashes;
+
+pub use project_requirements::{
+    dependency_group_requirements, project_requirement, project_requirements,
+    ProjectRequirementsError,
+};
+
+pub use workspace::{Workspace, WorkspaceError, WorkspaceMember};
+
+pub use pyproject_editor::{DependencyList, PyProjectEditError, PyProjectEditor};
 
 pub use entry_points::{EntryPoint, ParseEntryPointError};
 
@@ -0,0 +1,107 @@
+use super::{PackageInfo, WheelCoreMetaDataError};
+
+/// The descriptive fields of the core metadata of a distribution, e.g. its summary and license.
+/// These fields are not needed to resolve or install a distribution and are therefore not part of
+/// [`super::WheelCoreMetadata`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageDescription {
+    /// A one-line summary of what the distribution does
+    /// Matches the Summary field
+    pub summary: Option<String>,
+
+    /// The license of the distribution, either an SPDX expression or free text
+    /// Matches the License-Expression field, or the License field if it is absent
+    pub license: Option<String>,
+
+    /// The URL of the home page of the distribution
+    /// Matches the Home-page field
+    pub home_page: Option<String>,
+
+    /// The author of the distribution
+    /// Matches the Author field, or the Author-email field if it is absent
+    pub author: Option<String>,
+
+    /// Labeled URLs of the project, e.g. `("Documentation", "https://...")`
+    /// Matches the Project-URL fields
+    pub project_urls: Vec<(String, String)>,
+}
+
+impl PackageDescription {
+    /// Parses the descriptive fields from the contents of a `METADATA` or `PKG-INFO` file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WheelCoreMetaDataError> {
+        let mut parsed = PackageInfo::from_bytes(bytes)?.parsed;
+
+        // These fields are purely informational, so when a field is unexpectedly repeated the
+        // first value is used instead of failing.
+        let mut first = |key: &str| {
+            parsed
+                .take_all(key)
+                .into_iter()
+                .map(|value| value.trim().to_string())
+                .find(|value| !value.is_empty() && value != "UNKNOWN")
+        };
+        let summary = first("Summary");
+        let license = first("License-Expression").or_else(|| first("License"));
+        let home_page = first("Home-page");
+        let author = first("Author").or_else(|| first("Author-email"));
+
+        let project_urls = parsed
+            .take_all("Project-URL")
+            .into_iter()
+            .filter_map(|value| {
+                let (label, url) = value.split_once(',')?;
+                Some((label.trim().to_string(), url.trim().to_string()))
+            })
+            .collect();
+
+        Ok(Self {
+            summary,
+            license,
+            home_page,
+            author,
+            project_urls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let metadata = b"Metadata-Version: 2.1
+Name: flask
+Version: 3.0.0
+Summary: A simple framework for building complex web applications.
+Author-email: Armin Ronacher <armin.ronacher@active-4.com>
+License: UNKNOWN
+Project-URL: Documentation, https://flask.palletsprojects.com/
+Project-URL: Source Code, https://github.com/pallets/flask/
+Requires-Dist: Werkzeug>=3.0.0
+
+Flask is a lightweight WSGI web application framework.
+";
+        assert_eq!(
+            PackageDescription::from_bytes(metadata).unwrap(),
+            PackageDescription {
+                summary: Some(String::from(
+                    "A simple framework for building complex web applications."
+                )),
+                license: None,
+                home_page: None,
+                author: Some(String::from("Armin Ronacher <armin.ronacher@active-4.com>")),
+                project_urls: vec![
+                    (
+                        String::from("Documentation"),
+                        String::from("https://flask.palletsprojects.com/")
+                    ),
+                    (
+                        String::from("Source Code"),
+                        String::from("https://github.com/pallets/flask/")
+                    ),
+                ],
+            }
+        );
+    }
+}
@@ -43,6 +43,10 @@ pub struct ArtifactInfo {
     /// Yanked information
     #[serde(default)]
     pub yanked: Yanked,
+    /// Size of the artifact in bytes, as specified in
+    /// [PEP 700](https://peps.python.org/pep-0700/)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
 }
 
 impl ArtifactInfo {
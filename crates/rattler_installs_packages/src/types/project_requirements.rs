@@ -0,0 +1,280 @@
+use crate::types::{Extra, PackageName, RequirementBuilder, RequirementBuilderError};
+use fs_err as fs;
+use indexmap::IndexMap;
+use pep508_rs::Requirement;
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+/// The parts of a `pyproject.toml` file that are relevant to determine the requirements of a
+/// project.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(super) struct PyProject {
+    pub(super) project: Option<Project>,
+
+    #[serde(default)]
+    pub(super) dependency_groups: IndexMap<Extra, Vec<DependencyGroupEntry>>,
+}
+
+/// An entry of a dependency group as specified by
+/// [PEP 735](https://peps.python.org/pep-0735/). Group names are normalized like extras.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(super) enum DependencyGroupEntry {
+    Requirement(Requirement),
+    #[serde(rename_all = "kebab-case")]
+    IncludeGroup {
+        include_group: Extra,
+    },
+}
+
+/// The `[project]` table as specified by
+/// [PEP 621](https://peps.python.org/pep-0621/).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(super) struct Project {
+    pub(super) name: Option<PackageName>,
+
+    #[serde(default)]
+    dependencies: Vec<Requirement>,
+
+    #[serde(default)]
+    pub(super) optional_dependencies: IndexMap<Extra, Vec<Requirement>>,
+}
+
+/// An error that can occur when reading the requirements of a project.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ProjectRequirementsError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    InvalidPyProject(PathBuf, #[source] toml::de::Error),
+
+    #[error("{0} does not contain a [project] table")]
+    MissingProjectTable(PathBuf),
+
+    #[error("the project in {0} does not have an extra named '{1}'")]
+    UnknownExtra(PathBuf, Extra),
+
+    #[error("{0} does not have a dependency group named '{1}'")]
+    UnknownDependencyGroup(PathBuf, Extra),
+
+    #[error("the dependency group '{1}' in {0} includes itself")]
+    CyclicDependencyGroup(PathBuf, Extra),
+
+    #[error("the [project] table in {0} does not specify a name")]
+    MissingProjectName(PathBuf),
+
+    #[error(transparent)]
+    InvalidRequirement(#[from] RequirementBuilderError),
+}
+
+/// Reads and parses the `pyproject.toml` file at `pyproject`.
+pub(super) fn read_pyproject(pyproject: &Path) -> Result<PyProject, ProjectRequirementsError> {
+    let contents = fs::read_to_string(pyproject)
+        .map_err(|e| ProjectRequirementsError::IoError(pyproject.to_path_buf(), e))?;
+    toml::from_str(&contents)
+        .map_err(|e| ProjectRequirementsError::InvalidPyProject(pyproject.to_path_buf(), e))
+}
+
+/// Reads the `[project]` table of the `pyproject.toml` file at `pyproject`.
+fn read_project(pyproject: &Path) -> Result<Project, ProjectRequirementsError> {
+    read_pyproject(pyproject)?
+        .project
+        .ok_or_else(|| ProjectRequirementsError::MissingProjectTable(pyproject.to_path_buf()))
+}
+
+/// Reads the dependencies of the project that is described by the `pyproject.toml` file at
+/// `pyproject`, together with the optional dependencies of the selected `extras`.
+pub fn project_requirements(
+    pyproject: &Path,
+    extras: &[Extra],
+) -> Result<Vec<Requirement>, ProjectRequirementsError> {
+    let project = read_project(pyproject)?;
+
+    let mut requirements = project.dependencies;
+    for extra in extras {
+        let optional = project.optional_dependencies.get(extra).ok_or_else(|| {
+            ProjectRequirementsError::UnknownExtra(pyproject.to_path_buf(), extra.clone())
+        })?;
+        requirements.extend(optional.iter().cloned());
+    }
+    Ok(requirements)
+}
+
+/// Reads the requirements of the dependency `groups` in the `pyproject.toml` file at `pyproject`,
+/// including the requirements of the groups they include. Unlike the other functions in this
+/// module this does not require a `[project]` table.
+pub fn dependency_group_requirements(
+    pyproject: &Path,
+    groups: &[Extra],
+) -> Result<Vec<Requirement>, ProjectRequirementsError> {
+    let dependency_groups = read_pyproject(pyproject)?.dependency_groups;
+
+    fn collect(
+        pyproject: &Path,
+        dependency_groups: &IndexMap<Extra, Vec<DependencyGroupEntry>>,
+        group: &Extra,
+        parents: &mut Vec<Extra>,
+        requirements: &mut Vec<Requirement>,
+    ) -> Result<(), ProjectRequirementsError> {
+        if parents.contains(group) {
+            return Err(ProjectRequirementsError::CyclicDependencyGroup(
+                pyproject.to_path_buf(),
+                group.clone(),
+            ));
+        }
+        let entries = dependency_groups.get(group).ok_or_else(|| {
+            ProjectRequirementsError::UnknownDependencyGroup(pyproject.to_path_buf(), group.clone())
+        })?;
+        parents.push(group.clone());
+        for entry in entries {
+            match entry {
+                DependencyGroupEntry::Requirement(requirement) => {
+                    requirements.push(requirement.clone())
+                }
+                DependencyGroupEntry::IncludeGroup { include_group } => collect(
+                    pyproject,
+                    dependency_groups,
+                    include_group,
+                    parents,
+                    requirements,
+                )?,
+            }
+        }
+        parents.pop();
+        Ok(())
+    }
+
+    let mut requirements = Vec::new();
+    for group in groups {
+        collect(
+            pyproject,
+            &dependency_groups,
+            group,
+            &mut Vec::new(),
+            &mut requirements,
+        )?;
+    }
+    Ok(requirements)
+}
+
+/// Returns a requirement on the project in `project_dir` itself, e.g.
+/// `demo[test] @ file:///path/to/demo`. Installing this requirement builds the project from
+/// source. The name of the project is read from its `pyproject.toml` file.
+pub fn project_requirement(
+    project_dir: &Path,
+    extras: &[Extra],
+) -> Result<Requirement, ProjectRequirementsError> {
+    let project_dir = dunce::canonicalize(project_dir)
+        .map_err(|e| ProjectRequirementsError::IoError(project_dir.to_path_buf(), e))?;
+    let pyproject = project_dir.join("pyproject.toml");
+    let name = read_project(&pyproject)?
+        .name
+        .ok_or_else(|| ProjectRequirementsError::MissingProjectName(pyproject.clone()))?;
+    let url =
+        Url::from_directory_path(&project_dir).expect("a canonicalized path is always absolute");
+
+    Ok(RequirementBuilder::new(name)
+        .with_extras(extras.iter().cloned())
+        .with_url(url)
+        .build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_project_requirements() {
+        let dir = tempfile::tempdir().unwrap();
+        let pyproject = dir.path().join("pyproject.toml");
+        fs::write(
+            &pyproject,
+            r#"
+[project]
+name = "demo"
+dependencies = ["click>=8"]
+
+[project.optional-dependencies]
+Test_Extra = ["pytest"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            project_requirements(&pyproject, &[]).unwrap(),
+            vec![Requirement::from_str("click>=8").unwrap()]
+        );
+
+        // Extras are compared after normalization
+        assert_eq!(
+            project_requirements(&pyproject, &[Extra::from_str("test-extra").unwrap()]).unwrap(),
+            vec![
+                Requirement::from_str("click>=8").unwrap(),
+                Requirement::from_str("pytest").unwrap()
+            ]
+        );
+
+        assert!(matches!(
+            project_requirements(&pyproject, &[Extra::from_str("docs").unwrap()]),
+            Err(ProjectRequirementsError::UnknownExtra(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_dependency_group_requirements() {
+        let dir = tempfile::tempdir().unwrap();
+        let pyproject = dir.path().join("pyproject.toml");
+        fs::write(
+            &pyproject,
+            r#"
+[dependency-groups]
+Test = ["pytest"]
+dev = ["ruff", { include-group = "test" }]
+cycle = [{ include-group = "cycle" }]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            dependency_group_requirements(&pyproject, &[Extra::from_str("dev").unwrap()]).unwrap(),
+            vec![
+                Requirement::from_str("ruff").unwrap(),
+                Requirement::from_str("pytest").unwrap()
+            ]
+        );
+        assert!(matches!(
+            dependency_group_requirements(&pyproject, &[Extra::from_str("docs").unwrap()]),
+            Err(ProjectRequirementsError::UnknownDependencyGroup(_, _))
+        ));
+        assert!(matches!(
+            dependency_group_requirements(&pyproject, &[Extra::from_str("cycle").unwrap()]),
+            Err(ProjectRequirementsError::CyclicDependencyGroup(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_project_requirement() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            "[project]\nname = \"Demo\"\n",
+        )
+        .unwrap();
+
+        let requirement =
+            project_requirement(dir.path(), &[Extra::from_str("test").unwrap()]).unwrap();
+        let url = Url::from_directory_path(dunce::canonicalize(dir.path()).unwrap()).unwrap();
+        assert_eq!(
+            requirement,
+            Requirement::from_str(&format!("Demo[test] @ {url}")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::types::DirectUrlHashes;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use url::Url;
+
+/// Specifies the `provenance_url.json` format of [PEP 710](https://peps.python.org/pep-0710/),
+/// which records the index a distribution was installed from and the exact artifact that was
+/// downloaded from it. Distributions that were installed from a direct url record their source in
+/// a [`crate::types::DirectUrlJson`] instead.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceUrlJson {
+    /// Url of the artifact that was downloaded.
+    pub url: Url,
+    /// Url of the index that listed the artifact.
+    pub index_url: Url,
+    /// Information about the artifact.
+    pub archive_info: ProvenanceArchiveInfo,
+}
+
+/// The `archive_info` of a [`ProvenanceUrlJson`].
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProvenanceArchiveInfo {
+    /// Hashes of the artifact.
+    pub hashes: Option<DirectUrlHashes>,
+}
+
+impl ProvenanceUrlJson {
+    /// The name of the file in the `.dist-info` directory that holds this information.
+    pub const FILE_NAME: &'static str = "provenance_url.json";
+
+    /// Reads the `provenance_url.json` file from a `.dist-info` directory. Returns `Ok(None)` if
+    /// the provenance of the distribution was not recorded.
+    pub fn from_dist_info(dist_info: &Path) -> serde_json::Result<Option<Self>> {
+        let path = dist_info.join(Self::FILE_NAME);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::from_reader(fs_err::File::open(&path).map_err(serde_json::Error::io)?).map(Some)
+    }
+
+    /// Reads the contents of a `provenance_url.json` file from a reader.
+    pub fn from_reader(reader: impl Read) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let contents = r#"{"url":"https://files.pythonhosted.org/packages/pip-23.3.1-py3-none-any.whl","index_url":"https://pypi.org/simple","archive_info":{"hashes":{"sha256":"55eb67bb6171d37447e82213be585b75fe2b12b359e993773aca4de9247a052b"}}}"#;
+        let provenance = ProvenanceUrlJson::from_reader(contents.as_bytes()).unwrap();
+        assert_eq!(provenance.index_url.as_str(), "https://pypi.org/simple");
+        assert_eq!(
+            provenance.archive_info.hashes.as_ref().unwrap().sha256,
+            "55eb67bb6171d37447e82213be585b75fe2b12b359e993773aca4de9247a052b"
+        );
+        assert_eq!(serde_json::to_string(&provenance).unwrap(), contents);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::types::{format_requirement, Extra, NormalizedPackageName, PackageName};
+use fs_err as fs;
+use pep508_rs::Requirement;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+use toml_edit::{Array, Document, Item, Table, TableLike, Value};
+
+/// A list of dependencies in a `pyproject.toml` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyList {
+    /// The `dependencies` of the `[project]` table
+    Project,
+    /// The dependencies of an extra in `[project.optional-dependencies]`
+    Optional(Extra),
+    /// A dependency group in the `[dependency-groups]` table as specified by
+    /// [PEP 735](https://peps.python.org/pep-0735/)
+    Group(Extra),
+}
+
+impl Display for DependencyList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyList::Project => write!(f, "the project dependencies"),
+            DependencyList::Optional(extra) => write!(f, "the optional dependencies of '{extra}'"),
+            DependencyList::Group(group) => write!(f, "the dependency group '{group}'"),
+        }
+    }
+}
+
+/// An error that can occur when editing a `pyproject.toml` file.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum PyProjectEditError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    InvalidPyProject(PathBuf, #[source] toml_edit::TomlError),
+
+    #[error("{0} does not contain a [project] table")]
+    MissingProjectTable(PathBuf),
+
+    #[error("{1} in {0} are not a list of requirements")]
+    InvalidDependencyList(PathBuf, DependencyList),
+}
+
+/// Edits the dependencies in a `pyproject.toml` file. Only the edited lists are changed, the
+/// formatting and comments of the rest of the file are preserved.
+#[derive(Debug, Clone)]
+pub struct PyProjectEditor {
+    path: PathBuf,
+    document: Document,
+}
+
+impl PyProjectEditor {
+    /// Reads the `pyproject.toml` file at `path`.
+    pub fn from_path(path: &Path) -> Result<Self, PyProjectEditError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| PyProjectEditError::IoError(path.to_path_buf(), e))?;
+        let document = contents
+            .parse()
+            .map_err(|e| PyProjectEditError::InvalidPyProject(path.to_path_buf(), e))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            document,
+        })
+    }
+
+    /// Returns the path of the `pyproject.toml` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Adds `requirement` to the dependency `list`, the list is created if it does not exist. An
+    /// existing requirement on the same package is replaced and returned.
+    pub fn add_dependency(
+        &mut self,
+        requirement: &Requirement,
+        list: &DependencyList,
+    ) -> Result<Option<Requirement>, PyProjectEditError> {
+        let array = self
+            .dependency_array(list, true)?
+            .expect("the array is created if it does not exist");
+        let name = normalized_name(&requirement.name);
+        match find_requirement(array, name.as_ref()) {
+            Some((index, existing)) => {
+                array.replace(index, format_requirement(requirement));
+                Ok(Some(existing))
+            }
+            None => {
+                push_formatted(array, requirement);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Removes the requirement on the package `name` from the dependency `list`. Returns the
+    /// removed requirement or `None` if the list does not contain the package.
+    pub fn remove_dependency(
+        &mut self,
+        name: &NormalizedPackageName,
+        list: &DependencyList,
+    ) -> Result<Option<Requirement>, PyProjectEditError> {
+        let Some(array) = self.dependency_array(list, false)? else {
+            return Ok(None);
+        };
+        Ok(
+            find_requirement(array, Some(name)).map(|(index, existing)| {
+                array.remove(index);
+                existing
+            }),
+        )
+    }
+
+    /// Returns the extras in `[project.optional-dependencies]`.
+    pub fn extras(&self) -> Vec<Extra> {
+        let optional = self
+            .document
+            .get("project")
+            .and_then(|project| project.get("optional-dependencies"));
+        table_keys(optional)
+    }
+
+    /// Returns the dependency groups in `[dependency-groups]`.
+    pub fn dependency_groups(&self) -> Vec<Extra> {
+        table_keys(self.document.get("dependency-groups"))
+    }
+
+    /// Writes the edited file back to disk.
+    pub fn write(&self) -> Result<(), PyProjectEditError> {
+        fs::write(&self.path, self.document.to_string())
+            .map_err(|e| PyProjectEditError::IoError(self.path.clone(), e))
+    }
+
+    /// Returns the array that holds the dependency `list`. If the array does not exist it is
+    /// created when `create` is true, otherwise `None` is returned.
+    fn dependency_array(
+        &mut self,
+        list: &DependencyList,
+        create: bool,
+    ) -> Result<Option<&mut Array>, PyProjectEditError> {
+        let invalid = || PyProjectEditError::InvalidDependencyList(self.path.clone(), list.clone());
+        let (table, key): (&mut dyn TableLike, String) = match list {
+            DependencyList::Project => {
+                let project = self
+                    .document
+                    .get_mut("project")
+                    .and_then(Item::as_table_like_mut)
+                    .ok_or_else(|| PyProjectEditError::MissingProjectTable(self.path.clone()))?;
+                (project, String::from("dependencies"))
+            }
+            DependencyList::Optional(extra) => {
+                let Some(project) = self
+                    .document
+                    .get_mut("project")
+                    .and_then(Item::as_table_like_mut)
+                else {
+                    return Err(PyProjectEditError::MissingProjectTable(self.path.clone()));
+                };
+                if !create && !project.contains_key("optional-dependencies") {
+                    return Ok(None);
+                }
+                let optional = project
+                    .entry("optional-dependencies")
+                    .or_insert(Item::Table(Table::new()))
+                    .as_table_like_mut()
+                    .ok_or_else(invalid)?;
+                let key = matching_key(optional, extra);
+                (optional, key)
+            }
+            DependencyList::Group(group) => {
+                if !create && !self.document.contains_key("dependency-groups") {
+                    return Ok(None);
+                }
+                let groups = self
+                    .document
+                    .entry("dependency-groups")
+                    .or_insert(Item::Table(Table::new()))
+                    .as_table_like_mut()
+                    .ok_or_else(invalid)?;
+                let key = matching_key(groups, group);
+                (groups, key)
+            }
+        };
+
+        if !table.contains_key(&key) {
+            if !create {
+                return Ok(None);
+            }
+            table.insert(&key, Item::Value(Value::Array(Array::new())));
+        }
+        table
+            .get_mut(&key)
+            .and_then(Item::as_array_mut)
+            .map(Some)
+            .ok_or_else(|| {
+                PyProjectEditError::InvalidDependencyList(self.path.clone(), list.clone())
+            })
+    }
+}
+
+impl Display for PyProjectEditor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+/// Returns the key in `table` that matches `name` after normalization, or `name` itself if there
+/// is no such key.
+fn matching_key(table: &dyn TableLike, name: &Extra) -> String {
+    table
+        .iter()
+        .map(|(key, _)| key)
+        .find(|key| Extra::from_str(key).is_ok_and(|key| &key == name))
+        .unwrap_or(name.as_source_str())
+        .to_string()
+}
+
+/// Returns the keys of the table in `item` that are valid names.
+fn table_keys(item: Option<&Item>) -> Vec<Extra> {
+    item.and_then(Item::as_table_like)
+        .map(|table| {
+            table
+                .iter()
+                .filter_map(|(key, _)| Extra::from_str(key).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn normalized_name(name: &str) -> Option<NormalizedPackageName> {
+    PackageName::from_str(name)
+        .ok()
+        .map(NormalizedPackageName::from)
+}
+
+/// Finds the requirement on the package `name` in `array`. Entries that are not requirements,
+/// like the `include-group` tables of dependency groups, are skipped.
+fn find_requirement(
+    array: &Array,
+    name: Option<&NormalizedPackageName>,
+) -> Option<(usize, Requirement)> {
+    let name = name?;
+    array.iter().enumerate().find_map(|(index, value)| {
+        let requirement = Requirement::from_str(value.as_str()?).ok()?;
+        (normalized_name(&requirement.name).as_ref() == Some(name)).then_some((index, requirement))
+    })
+}
+
+/// Appends `requirement` to `array` with the same formatting as the last element, this keeps
+/// arrays that have an element per line formatted that way.
+fn push_formatted(array: &mut Array, requirement: &Requirement) {
+    let decor = array
+        .iter()
+        .last()
+        .map(|value| value.decor().clone())
+        .filter(|decor| {
+            decor
+                .prefix()
+                .is_some_and(|prefix| prefix.as_str() != Some(""))
+        });
+    array.push(format_requirement(requirement));
+    if let (Some(decor), Some(value)) = (decor, array.iter_mut().last()) {
+        *value.decor_mut() = decor;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PYPROJECT: &str = r#"[project]
+name = "demo"
+# The runtime dependencies
+dependencies = [
+    "click>=8",
+    "Requests",
+]
+
+[dependency-groups]
+Dev = ["ruff", { include-group = "test" }]
+"#;
+
+    fn editor() -> (tempfile::TempDir, PyProjectEditor) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pyproject.toml");
+        fs::write(&path, PYPROJECT).unwrap();
+        let editor = PyProjectEditor::from_path(&path).unwrap();
+        (dir, editor)
+    }
+
+    fn requirement(requirement: &str) -> Requirement {
+        Requirement::from_str(requirement).unwrap()
+    }
+
+    fn name(name: &str) -> NormalizedPackageName {
+        PackageName::from_str(name).unwrap().into()
+    }
+
+    #[test]
+    fn test_add_dependency() {
+        let (_dir, mut editor) = editor();
+
+        assert_eq!(
+            editor
+                .add_dependency(&requirement("flask>=3"), &DependencyList::Project)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            editor
+                .add_dependency(&requirement("requests<3"), &DependencyList::Project)
+                .unwrap(),
+            Some(requirement("Requests"))
+        );
+        editor
+            .add_dependency(
+                &requirement("pytest"),
+                &DependencyList::Optional(Extra::from_str("test").unwrap()),
+            )
+            .unwrap();
+        editor
+            .add_dependency(
+                &requirement("mypy"),
+                &DependencyList::Group(Extra::from_str("dev").unwrap()),
+            )
+            .unwrap();
+
+        insta::assert_snapshot!(editor.to_string());
+    }
+
+    #[test]
+    fn test_remove_dependency() {
+        let (_dir, mut editor) = editor();
+
+        assert_eq!(
+            editor
+                .remove_dependency(&name("click"), &DependencyList::Project)
+                .unwrap(),
+            Some(requirement("click>=8"))
+        );
+        assert_eq!(
+            editor
+                .remove_dependency(
+                    &name("ruff"),
+                    &DependencyList::Group(Extra::from_str("dev").unwrap())
+                )
+                .unwrap(),
+            Some(requirement("ruff"))
+        );
+        assert_eq!(
+            editor
+                .remove_dependency(
+                    &name("pytest"),
+                    &DependencyList::Optional(Extra::from_str("test").unwrap())
+                )
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            editor.dependency_groups(),
+            vec![Extra::from_str("dev").unwrap()]
+        );
+        assert!(editor.extras().is_empty());
+
+        editor.write().unwrap();
+        let written = fs::read_to_string(editor.path()).unwrap();
+        assert!(written.contains("# The runtime dependencies"));
+        assert!(!written.contains("click"));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::types::{ArtifactHashes, NormalizedPackageName};
+use std::collections::HashMap;
+
+/// The hashes that the artifacts of packages are required to match, e.g. the `--hash` options of a
+/// requirements file. When hashes are required every package must have at least one hash, an
+/// artifact is accepted if it matches any of the hashes of its package.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequiredHashes {
+    hashes: HashMap<NormalizedPackageName, Vec<ArtifactHashes>>,
+}
+
+impl RequiredHashes {
+    /// Adds `hashes` to the accepted hashes of the package `name`.
+    pub fn insert(&mut self, name: NormalizedPackageName, hashes: ArtifactHashes) {
+        let accepted = self.hashes.entry(name).or_default();
+        if !accepted.contains(&hashes) {
+            accepted.push(hashes);
+        }
+    }
+
+    /// Adds the hashes of `other` to this instance.
+    pub fn extend(&mut self, other: RequiredHashes) {
+        for (name, hashes) in other.hashes {
+            for hashes in hashes {
+                self.insert(name.clone(), hashes);
+            }
+        }
+    }
+
+    /// Returns the accepted hashes of the package `name`, or `None` if no hashes were specified.
+    pub fn get(&self, name: &NormalizedPackageName) -> Option<&[ArtifactHashes]> {
+        self.hashes.get(name).map(Vec::as_slice)
+    }
+
+    /// Returns true if hashes were specified for the package `name`.
+    pub fn contains(&self, name: &NormalizedPackageName) -> bool {
+        self.hashes.contains_key(name)
+    }
+
+    /// Returns true if no hashes were specified at all.
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Returns true if the `hashes` of an artifact of the package `name` match one of the accepted
+    /// hashes. This is never the case for packages without accepted hashes or artifacts without
+    /// hashes.
+    pub fn matches(&self, name: &NormalizedPackageName, hashes: Option<&ArtifactHashes>) -> bool {
+        let (Some(accepted), Some(hashes)) = (self.get(name), hashes) else {
+            return false;
+        };
+        accepted
+            .iter()
+            .any(|accepted| accepted.sha256.is_some() && accepted.sha256 == hashes.sha256)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::PackageName;
+    use rattler_digest::{parse_digest_from_hex, Sha256};
+    use std::str::FromStr;
+
+    fn hashes(hex: &str) -> ArtifactHashes {
+        ArtifactHashes {
+            sha256: parse_digest_from_hex::<Sha256>(hex),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let name: NormalizedPackageName = PackageName::from_str("Flask").unwrap().into();
+        let other: NormalizedPackageName = PackageName::from_str("click").unwrap().into();
+        let good = hashes("58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f");
+        let bad = hashes("aca0cab64ad4e6a9f4830ca8922d6229aa9baed0e0544dab7af1b229a3813c89");
+
+        let mut required = RequiredHashes::default();
+        required.insert(name.clone(), good.clone());
+
+        assert!(required.matches(&name, Some(&good)));
+        assert!(!required.matches(&name, Some(&bad)));
+        assert!(!required.matches(&name, None));
+        assert!(!required.matches(&other, Some(&good)));
+    }
+}
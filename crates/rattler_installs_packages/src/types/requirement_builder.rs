@@ -0,0 +1,249 @@
+use crate::types::{Extra, NormalizedPackageName, PackageName};
+use itertools::Itertools;
+use miette::Diagnostic;
+use pep440_rs::{Operator, Version, VersionSpecifier, VersionSpecifiers};
+use pep508_rs::{MarkerTree, Requirement, VersionOrUrl};
+use thiserror::Error;
+use url::Url;
+
+/// Error that can occur when building a [`Requirement`] with a [`RequirementBuilder`].
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[allow(missing_docs)]
+pub enum RequirementBuilderError {
+    #[error("requirement '{0}' cannot have both version specifiers and a direct url")]
+    ConflictingVersionAndUrl(NormalizedPackageName),
+
+    #[error("the direct url '{1}' of requirement '{0}' cannot be a base")]
+    InvalidUrl(NormalizedPackageName, Url),
+}
+
+/// "Builder" pattern for creating a PEP 508 [`Requirement`] from its typed parts without having
+/// to format and re-parse a requirement string.
+///
+/// ```
+/// # use std::str::FromStr;
+/// # use rattler_installs_packages::types::{Extra, PackageName, Requirement, RequirementBuilder, Version};
+/// let requirement = RequirementBuilder::new(PackageName::from_str("Flask").unwrap())
+///     .with_extra(Extra::from_str("async").unwrap())
+///     .with_minimum_version(Version::from_str("2.0").unwrap())
+///     .build()
+///     .unwrap();
+/// assert_eq!(requirement, Requirement::from_str("Flask[async]>=2.0").unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequirementBuilder {
+    name: PackageName,
+    extras: Vec<Extra>,
+    specifiers: Vec<VersionSpecifier>,
+    url: Option<Url>,
+    marker: Option<MarkerTree>,
+}
+
+impl RequirementBuilder {
+    /// Start building a requirement on the package with the given name.
+    pub fn new(name: PackageName) -> Self {
+        Self {
+            name,
+            extras: Vec::new(),
+            specifiers: Vec::new(),
+            url: None,
+            marker: None,
+        }
+    }
+
+    /// Add an extra to the requirement. Extras that are already present are ignored.
+    pub fn with_extra(mut self, extra: Extra) -> Self {
+        if !self.extras.contains(&extra) {
+            self.extras.push(extra);
+        }
+        self
+    }
+
+    /// Add multiple extras to the requirement.
+    pub fn with_extras(self, extras: impl IntoIterator<Item = Extra>) -> Self {
+        extras.into_iter().fold(self, Self::with_extra)
+    }
+
+    /// Add a version specifier to the requirement. Multiple specifiers must all match.
+    pub fn with_specifier(mut self, specifier: VersionSpecifier) -> Self {
+        self.specifiers.push(specifier);
+        self
+    }
+
+    /// Add multiple version specifiers to the requirement.
+    pub fn with_specifiers(
+        mut self,
+        specifiers: impl IntoIterator<Item = VersionSpecifier>,
+    ) -> Self {
+        self.specifiers.extend(specifiers);
+        self
+    }
+
+    /// Require exactly the given version (`==version`).
+    pub fn with_exact_version(self, version: Version) -> Self {
+        self.with_specifier(
+            VersionSpecifier::new(Operator::Equal, version, false)
+                .expect("an equality specifier is always valid"),
+        )
+    }
+
+    /// Require at least the given version (`>=version`).
+    pub fn with_minimum_version(self, version: Version) -> Self {
+        self.with_specifier(
+            VersionSpecifier::new(Operator::GreaterThanEqual, version, false)
+                .expect("a greater than or equal specifier is always valid"),
+        )
+    }
+
+    /// Require the package to be installed from the given url instead of from an index.
+    pub fn with_url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// Only apply the requirement if the given environment marker evaluates to true. If a marker
+    /// was already set both markers have to evaluate to true.
+    pub fn with_marker(mut self, marker: MarkerTree) -> Self {
+        self.marker = Some(match self.marker.take() {
+            None => marker,
+            Some(MarkerTree::And(mut markers)) => {
+                markers.push(marker);
+                MarkerTree::And(markers)
+            }
+            Some(existing) => MarkerTree::And(vec![existing, marker]),
+        });
+        self
+    }
+
+    /// Finalize the builder and create a [`Requirement`].
+    pub fn build(&self) -> Result<Requirement, RequirementBuilderError> {
+        let version_or_url = match (&self.url, self.specifiers.is_empty()) {
+            (Some(_), false) => {
+                return Err(RequirementBuilderError::ConflictingVersionAndUrl(
+                    self.name.clone().into(),
+                ))
+            }
+            (Some(url), true) if url.cannot_be_a_base() => {
+                return Err(RequirementBuilderError::InvalidUrl(
+                    self.name.clone().into(),
+                    url.clone(),
+                ))
+            }
+            (Some(url), true) => Some(VersionOrUrl::Url(url.clone())),
+            (None, false) => Some(VersionOrUrl::VersionSpecifier(
+                VersionSpecifiers::from_iter(self.specifiers.iter().cloned()),
+            )),
+            (None, true) => None,
+        };
+
+        Ok(Requirement {
+            name: self.name.as_source_str().to_owned(),
+            extras: (!self.extras.is_empty()).then(|| {
+                self.extras
+                    .iter()
+                    .map(|extra| extra.as_source_str().to_owned())
+                    .collect()
+            }),
+            version_or_url,
+            marker: self.marker.clone(),
+        })
+    }
+}
+
+/// Formats a [`Requirement`] the way it is written in requirements files and `pyproject.toml`,
+/// e.g. `requests[socks]>=2.8.1,<3; python_version >= "3.8"`. Unlike the `Display` implementation
+/// of [`Requirement`] this does not put a space between the name and the version specifiers.
+pub fn format_requirement(requirement: &Requirement) -> String {
+    let mut result = requirement.name.clone();
+    if let Some(extras) = requirement
+        .extras
+        .as_ref()
+        .filter(|extras| !extras.is_empty())
+    {
+        result.push_str(&format!("[{}]", extras.join(",")));
+    }
+    match &requirement.version_or_url {
+        Some(VersionOrUrl::VersionSpecifier(specifiers)) => {
+            result.push_str(&specifiers.iter().map(ToString::to_string).join(","));
+        }
+        Some(VersionOrUrl::Url(url)) => {
+            result.push_str(" @ ");
+            result.push_str(url.as_str());
+        }
+        None => {}
+    }
+    if let Some(marker) = &requirement.marker {
+        // The marker has to be separated from a url by a space, otherwise it is part of the url
+        match &requirement.version_or_url {
+            Some(VersionOrUrl::Url(_)) => result.push_str(" ; "),
+            _ => result.push_str("; "),
+        }
+        result.push_str(&marker.to_string());
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn builder(name: &str) -> RequirementBuilder {
+        RequirementBuilder::new(PackageName::from_str(name).unwrap())
+    }
+
+    #[test]
+    fn test_build_roundtrips() {
+        let requirement = builder("requests")
+            .with_extras(["security", "socks", "Security"].map(|e| Extra::from_str(e).unwrap()))
+            .with_minimum_version(Version::from_str("2.8.1").unwrap())
+            .with_specifier(VersionSpecifier::from_str("<3").unwrap())
+            .with_marker(MarkerTree::from_str("python_version >= '3.8'").unwrap())
+            .with_marker(MarkerTree::from_str("sys_platform == 'linux'").unwrap())
+            .build()
+            .unwrap();
+
+        let parsed = Requirement::from_str(&requirement.to_string()).unwrap();
+        assert_eq!(parsed, requirement);
+        assert_eq!(
+            requirement.extras,
+            Some(vec![String::from("security"), String::from("socks")])
+        );
+    }
+
+    #[test]
+    fn test_build_url() {
+        let requirement = builder("pip")
+            .with_url(Url::parse("https://github.com/pypa/pip/archive/1.3.1.zip").unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            Requirement::from_str(&requirement.to_string()).unwrap(),
+            requirement
+        );
+
+        assert!(matches!(
+            builder("pip")
+                .with_url(Url::parse("https://github.com/pypa/pip/archive/1.3.1.zip").unwrap())
+                .with_exact_version(Version::from_str("1.3.1").unwrap())
+                .build(),
+            Err(RequirementBuilderError::ConflictingVersionAndUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_requirement() {
+        let format =
+            |requirement: &str| format_requirement(&Requirement::from_str(requirement).unwrap());
+        assert_eq!(format("click == 7.1.2"), "click==7.1.2");
+        assert_eq!(
+            format("requests [socks, security] >=2.8.1, <3 ; python_version >= '3.8'"),
+            "requests[socks,security]>=2.8.1,<3; python_version >= '3.8'"
+        );
+        assert_eq!(
+            format("pip @ https://github.com/pypa/pip/archive/1.3.1.zip ; sys_platform == 'linux'"),
+            "pip @ https://github.com/pypa/pip/archive/1.3.1.zip ; sys_platform == 'linux'"
+        );
+        assert_eq!(format("flask"), "flask");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::types::{ArtifactHashes, NormalizedPackageName, PackageName, RequiredHashes};
+use fs_err as fs;
+use pep508_rs::{Pep508Error, Requirement};
+use rattler_digest::{parse_digest_from_hex, Sha256};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Options of pip that only influence where packages are downloaded from. These are ignored when
+/// reading a requirements file because the package sources are configured separately.
+const IGNORED_OPTIONS: [&str; 10] = [
+    "-i",
+    "--index-url",
+    "--extra-index-url",
+    "--no-index",
+    "-f",
+    "--find-links",
+    "--trusted-host",
+    "--pre",
+    "--prefer-binary",
+    "--only-binary",
+];
+
+/// An error that can occur when reading a requirements file.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum RequirementsFileError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("invalid requirement on line {1} of {0}")]
+    InvalidRequirement(PathBuf, usize, #[source] Box<Pep508Error>),
+
+    #[error("unsupported option '{2}' on line {1} of {0}")]
+    UnsupportedOption(PathBuf, usize, String),
+
+    #[error("invalid hash '{2}' on line {1} of {0}, expected a sha256 hash like `sha256:<hex>`")]
+    InvalidHash(PathBuf, usize, String),
+}
+
+/// The contents of a pip requirements file.
+#[derive(Debug, Clone, Default)]
+pub struct RequirementsFile {
+    /// The requirements in the file and in the files it includes.
+    pub requirements: Vec<Requirement>,
+
+    /// The hashes of the `--hash` options of the requirements.
+    pub hashes: RequiredHashes,
+
+    /// True if the file, or a file it includes, contains the `--require-hashes` option.
+    pub require_hashes: bool,
+}
+
+impl RequirementsFile {
+    /// Reads a requirements file, see [`read_requirements_file`].
+    pub fn from_path(path: &Path) -> Result<Self, RequirementsFileError> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| RequirementsFileError::IoError(path.to_path_buf(), e))?;
+        Self::parse(&source, path)
+    }
+
+    /// Parses the contents of the requirements file at `path`, see [`read_requirements_file`].
+    pub fn parse(source: &str, path: &Path) -> Result<Self, RequirementsFileError> {
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        let mut file = RequirementsFile::default();
+        for (line_number, line) in logical_lines(source) {
+            let unsupported = || {
+                RequirementsFileError::UnsupportedOption(
+                    path.to_path_buf(),
+                    line_number,
+                    line.clone(),
+                )
+            };
+
+            if line.starts_with('-') {
+                let (name, value) = split_option(&line);
+                match name {
+                    "-r" | "--requirement" => {
+                        let value = value.ok_or_else(unsupported)?;
+                        let included = Self::from_path(&base_dir.join(value))?;
+                        file.requirements.extend(included.requirements);
+                        file.hashes.extend(included.hashes);
+                        file.require_hashes |= included.require_hashes;
+                    }
+                    "--require-hashes" if value.is_none() => file.require_hashes = true,
+                    name if IGNORED_OPTIONS.contains(&name) => {
+                        tracing::warn!("ignoring '{line}' in {}", path.display());
+                    }
+                    _ => return Err(unsupported()),
+                }
+                continue;
+            }
+
+            let (requirement, hashes) = match line.find(" --hash") {
+                Some(index) => (&line[..index], &line[index..]),
+                None => (line.as_str(), ""),
+            };
+            let requirement = Requirement::from_str(requirement.trim()).map_err(|e| {
+                RequirementsFileError::InvalidRequirement(
+                    path.to_path_buf(),
+                    line_number,
+                    Box::new(e),
+                )
+            })?;
+            let hashes = parse_hashes(hashes).map_err(|hash| {
+                RequirementsFileError::InvalidHash(path.to_path_buf(), line_number, hash)
+            })?;
+            if let Ok(name) = PackageName::from_str(&requirement.name) {
+                let name = NormalizedPackageName::from(name);
+                for hashes in hashes {
+                    file.hashes.insert(name.clone(), hashes);
+                }
+            }
+            file.requirements.push(requirement);
+        }
+        Ok(file)
+    }
+}
+
+/// Reads the requirements from a pip requirements file, e.g. `requirements.txt`.
+///
+/// Comments, line continuations and `--hash` options are supported. Other requirements files that
+/// are included with `-r` are read relative to the directory of the including file. Options that
+/// only configure the package index, like `--index-url`, are ignored. Use
+/// [`RequirementsFile::from_path`] to also read the hashes.
+pub fn read_requirements_file(path: &Path) -> Result<Vec<Requirement>, RequirementsFileError> {
+    Ok(RequirementsFile::from_path(path)?.requirements)
+}
+
+/// Parses the contents of the requirements file at `path`. See [`read_requirements_file`].
+pub fn parse_requirements_file(
+    source: &str,
+    path: &Path,
+) -> Result<Vec<Requirement>, RequirementsFileError> {
+    Ok(RequirementsFile::parse(source, path)?.requirements)
+}
+
+/// Parses the `--hash` options that follow a requirement, e.g.
+/// `--hash=sha256:<hex> --hash sha256:<hex>`. Returns the offending hash if it cannot be parsed.
+fn parse_hashes(options: &str) -> Result<Vec<ArtifactHashes>, String> {
+    let mut tokens = options.split_whitespace();
+    let mut hashes = Vec::new();
+    while let Some(token) = tokens.next() {
+        let hash = match token.strip_prefix("--hash") {
+            Some("") => tokens.next().unwrap_or_default(),
+            Some(hash) => hash.strip_prefix('=').unwrap_or(hash),
+            None => return Err(token.to_string()),
+        };
+        let sha256 = hash
+            .strip_prefix("sha256:")
+            .and_then(parse_digest_from_hex::<Sha256>)
+            .ok_or_else(|| hash.to_string())?;
+        hashes.push(ArtifactHashes {
+            sha256: Some(sha256),
+        });
+    }
+    Ok(hashes)
+}
+
+/// Splits an option like `--requirement=base.txt` or `-r base.txt` into its name and value.
+fn split_option(option: &str) -> (&str, Option<&str>) {
+    match option.find(|c: char| c == '=' || c.is_whitespace()) {
+        Some(index) => {
+            let value = option[index + 1..].trim();
+            (&option[..index], (!value.is_empty()).then_some(value))
+        }
+        None => (option, None),
+    }
+}
+
+/// Returns the non-empty lines of the file with comments removed and continuation lines joined,
+/// together with the line number on which they start.
+fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let line = strip_comment(line);
+        let (line, continues) = match line.strip_suffix('\\') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let (_, text) = current.get_or_insert_with(|| (index + 1, String::new()));
+        text.push_str(line);
+        if !text.is_empty() && !text.ends_with(' ') {
+            text.push(' ');
+        }
+
+        if !continues {
+            if let Some((number, text)) = current.take() {
+                let text = text.trim().to_string();
+                if !text.is_empty() {
+                    lines.push((number, text));
+                }
+            }
+        }
+    }
+    if let Some((number, text)) = current {
+        let text = text.trim().to_string();
+        if !text.is_empty() {
+            lines.push((number, text));
+        }
+    }
+    lines
+}
+
+/// Removes a comment from a line. Comments start with a `#` at the start of the line or after
+/// whitespace, a `#` within a URL fragment is not a comment.
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #").or_else(|| line.find("\t#")) {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirements_file() {
+        let source = r#"
+# The web framework
+flask>=2 # comment after a requirement
+requests[socks]==2.31.0 \
+    --hash=sha256:58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f
+--index-url https://pypi.org/simple
+
+package @ https://example.com/package-1.0-py3-none-any.whl#sha256=abc
+"#;
+        let requirements = parse_requirements_file(source, Path::new("requirements.txt")).unwrap();
+        assert_eq!(
+            requirements,
+            vec![
+                Requirement::from_str("flask>=2").unwrap(),
+                Requirement::from_str("requests[socks]==2.31.0").unwrap(),
+                Requirement::from_str(
+                    "package @ https://example.com/package-1.0-py3-none-any.whl#sha256=abc"
+                )
+                .unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_requirements_file_hashes() {
+        let source = r#"
+--require-hashes
+requests==2.31.0 \
+    --hash=sha256:58cd2187c01e70e6e26505bca751777aa9f2ee0b7f4300988b709f44e013003f \
+    --hash sha256:aca0cab64ad4e6a9f4830ca8922d6229aa9baed0e0544dab7af1b229a3813c89
+click==8.1.7
+"#;
+        let file = RequirementsFile::parse(source, Path::new("requirements.txt")).unwrap();
+        assert!(file.require_hashes);
+        assert_eq!(file.requirements.len(), 2);
+
+        let requests = PackageName::from_str("requests").unwrap().into();
+        let click = PackageName::from_str("click").unwrap().into();
+        assert_eq!(file.hashes.get(&requests).map(<[_]>::len), Some(2));
+        assert!(!file.hashes.contains(&click));
+
+        assert!(matches!(
+            RequirementsFile::parse("click --hash=md5:abc\n", Path::new("requirements.txt")),
+            Err(RequirementsFileError::InvalidHash(_, 1, _))
+        ));
+    }
+
+    #[test]
+    fn test_included_requirements_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("base.txt"), "click\n").unwrap();
+        fs::write(
+            dir.path().join("requirements.txt"),
+            "-r base.txt\n--requirement=base.txt\nblack\n",
+        )
+        .unwrap();
+
+        let requirements = read_requirements_file(&dir.path().join("requirements.txt")).unwrap();
+        assert_eq!(
+            requirements,
+            vec![
+                Requirement::from_str("click").unwrap(),
+                Requirement::from_str("click").unwrap(),
+                Requirement::from_str("black").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_requirements_file() {
+        assert!(matches!(
+            parse_requirements_file("-e .\n", Path::new("requirements.txt")),
+            Err(RequirementsFileError::UnsupportedOption(_, 1, _))
+        ));
+        assert!(matches!(
+            parse_requirements_file("flask\nflask >= >= 2\n", Path::new("requirements.txt")),
+            Err(RequirementsFileError::InvalidRequirement(_, 2, _))
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+use fs_err as fs;
+use pep440_rs::VersionSpecifiers;
+use pep508_rs::Requirement;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// The metadata that is embedded in a single-file python script, as specified by
+/// [PEP 723](https://peps.python.org/pep-0723/).
+///
+/// The metadata is stored as TOML in a comment block of the script:
+///
+/// ```python
+/// # /// script
+/// # requires-python = ">=3.11"
+/// # dependencies = [
+/// #   "requests<3",
+/// #   "rich",
+/// # ]
+/// # ///
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScriptMetadata {
+    /// The versions of python that the script is compatible with.
+    pub requires_python: Option<VersionSpecifiers>,
+
+    /// The packages that the script depends on.
+    #[serde(default)]
+    pub dependencies: Vec<Requirement>,
+}
+
+/// An error that can occur when reading the metadata of a script.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum ScriptMetadataError {
+    #[error("failed to read {0}")]
+    IoError(String, #[source] io::Error),
+
+    #[error("the '{0}' metadata block is not closed with '# ///'")]
+    UnclosedBlock(String),
+
+    #[error("the script contains more than one '{0}' metadata block")]
+    DuplicateBlock(String),
+
+    #[error("the script metadata is not valid")]
+    InvalidToml(#[from] toml::de::Error),
+}
+
+impl ScriptMetadata {
+    /// Reads the metadata from the script at `path`. Returns `None` if the script does not
+    /// contain a `script` metadata block.
+    pub fn from_path(path: &Path) -> Result<Option<Self>, ScriptMetadataError> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| ScriptMetadataError::IoError(path.display().to_string(), e))?;
+        Self::from_source(&source)
+    }
+
+    /// Parses the metadata from the source code of a script. Returns `None` if the script does not
+    /// contain a `script` metadata block.
+    pub fn from_source(source: &str) -> Result<Option<Self>, ScriptMetadataError> {
+        match metadata_block(source, "script")? {
+            Some(content) => Ok(Some(toml::from_str(&content)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns the content of the metadata block of type `block_type` with the comment prefixes
+/// removed, or `None` if the source does not contain such a block.
+fn metadata_block(source: &str, block_type: &str) -> Result<Option<String>, ScriptMetadataError> {
+    let start = format!("# /// {block_type}");
+    let lines = source.lines().collect::<Vec<_>>();
+
+    let mut block = None;
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index].trim_end() != start {
+            index += 1;
+            continue;
+        }
+        if block.is_some() {
+            return Err(ScriptMetadataError::DuplicateBlock(block_type.to_string()));
+        }
+
+        // The block consists of consecutive comment lines and is closed by the last `# ///` line
+        // among them.
+        let comments = lines[index + 1..]
+            .iter()
+            .take_while(|line| **line == "#" || line.starts_with("# "))
+            .collect::<Vec<_>>();
+        let end = comments
+            .iter()
+            .rposition(|line| line.trim_end() == "# ///")
+            .ok_or_else(|| ScriptMetadataError::UnclosedBlock(block_type.to_string()))?;
+
+        let content = comments[..end]
+            .iter()
+            .map(|line| line.strip_prefix("# ").unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        block = Some(content);
+        index += end + 2;
+    }
+
+    Ok(block)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_script_metadata() {
+        let source = r##"#!/usr/bin/env python
+# /// script
+# requires-python = ">=3.11"
+# dependencies = [
+#   "requests<3",
+#   "rich",
+# ]
+#
+# [tool.rip]
+# comment = "# /// is allowed inside strings"
+# ///
+
+import requests
+"##;
+        let metadata = ScriptMetadata::from_source(source).unwrap().unwrap();
+        assert_eq!(
+            metadata.requires_python,
+            Some(VersionSpecifiers::from_str(">=3.11").unwrap())
+        );
+        assert_eq!(
+            metadata.dependencies,
+            vec![
+                Requirement::from_str("requests<3").unwrap(),
+                Requirement::from_str("rich").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_script_metadata() {
+        assert_eq!(
+            ScriptMetadata::from_source("import sys\n# /// other\n# ///\n").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_script_metadata() {
+        assert!(matches!(
+            ScriptMetadata::from_source("# /// script\n# dependencies = []\n\nprint()\n"),
+            Err(ScriptMetadataError::UnclosedBlock(_))
+        ));
+        assert!(matches!(
+            ScriptMetadata::from_source("# /// script\n# ///\n\n# /// script\n# ///\n"),
+            Err(ScriptMetadataError::DuplicateBlock(_))
+        ));
+    }
+}
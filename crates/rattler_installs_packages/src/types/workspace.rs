@@ -0,0 +1,441 @@
+//! Workspaces of interdependent local projects, e.g. the packages of a monorepo.
+//!
+//! The root `pyproject.toml` of a workspace lists the directories of its members:
+//!
+//! ```toml
+//! [tool.rip.workspace]
+//! members = ["packages/*"]
+//! exclude = ["packages/legacy"]
+//! ```
+//!
+//! The workspace is resolved as a whole: the [`Workspace::requirements`] of all members are
+//! passed to the resolver together with the [`Workspace::local_packages`], so a requirement of
+//! one member on another resolves to the source tree of that member instead of the index.
+use super::project_requirements::{read_pyproject, ProjectRequirementsError};
+use crate::types::{
+    dependency_group_requirements, Extra, NormalizedPackageName, PackageName, RequirementBuilder,
+    RequirementBuilderError,
+};
+use fs_err as fs;
+use pep508_rs::Requirement;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use url::Url;
+
+/// The parts of the root `pyproject.toml` file that describe the workspace.
+#[derive(Debug, Deserialize)]
+struct WorkspacePyProject {
+    tool: Option<Tool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Tool {
+    rip: Option<RipTool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RipTool {
+    workspace: Option<WorkspaceTable>,
+}
+
+/// The `[tool.rip.workspace]` table.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct WorkspaceTable {
+    /// Glob patterns of the directories of the members, relative to the root.
+    members: Vec<String>,
+
+    /// Glob patterns of directories that match `members` but are not part of the workspace.
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// An error that can occur when reading a workspace.
+#[derive(Debug, Error)]
+#[allow(missing_docs)]
+pub enum WorkspaceError {
+    #[error("failed to read {0}")]
+    IoError(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse {0}")]
+    InvalidPyProject(PathBuf, #[source] toml::de::Error),
+
+    #[error("{0} does not contain a [tool.rip.workspace] table")]
+    MissingWorkspaceTable(PathBuf),
+
+    #[error("invalid workspace member pattern '{0}'")]
+    InvalidPattern(String, #[source] glob::PatternError),
+
+    #[error("the workspace members in {1} and {2} are both named '{0}'")]
+    DuplicateMember(NormalizedPackageName, PathBuf, PathBuf),
+
+    #[error("none of the workspace members has an extra named '{0}'")]
+    UnknownExtra(Extra),
+
+    #[error("none of the projects in the workspace has a dependency group named '{0}'")]
+    UnknownDependencyGroup(Extra),
+
+    #[error(transparent)]
+    Project(#[from] ProjectRequirementsError),
+
+    #[error(transparent)]
+    InvalidRequirement(#[from] RequirementBuilderError),
+}
+
+/// A project that is part of a [`Workspace`].
+#[derive(Debug, Clone)]
+pub struct WorkspaceMember {
+    name: PackageName,
+    path: PathBuf,
+    extras: Vec<Extra>,
+    dependency_groups: Vec<Extra>,
+}
+
+impl WorkspaceMember {
+    /// Reads the member in the directory `path`, which must be canonicalized.
+    fn from_dir(path: PathBuf) -> Result<Self, ProjectRequirementsError> {
+        let pyproject = path.join("pyproject.toml");
+        let contents = read_pyproject(&pyproject)?;
+        let project = contents
+            .project
+            .ok_or_else(|| ProjectRequirementsError::MissingProjectTable(pyproject.clone()))?;
+        let name = project
+            .name
+            .ok_or_else(|| ProjectRequirementsError::MissingProjectName(pyproject.clone()))?;
+        Ok(Self {
+            name,
+            path,
+            extras: project.optional_dependencies.into_keys().collect(),
+            dependency_groups: contents.dependency_groups.into_keys().collect(),
+        })
+    }
+
+    /// The name of the project.
+    pub fn name(&self) -> &PackageName {
+        &self.name
+    }
+
+    /// The directory of the project.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The `pyproject.toml` file of the project.
+    pub fn pyproject(&self) -> PathBuf {
+        self.path.join("pyproject.toml")
+    }
+
+    /// The `file://` url of the source tree of the project.
+    pub fn url(&self) -> Url {
+        Url::from_directory_path(&self.path).expect("a canonicalized path is always absolute")
+    }
+
+    /// The extras that the project declares.
+    pub fn extras(&self) -> &[Extra] {
+        &self.extras
+    }
+
+    /// The dependency groups that the project declares.
+    pub fn dependency_groups(&self) -> &[Extra] {
+        &self.dependency_groups
+    }
+}
+
+/// A set of local projects that is resolved together, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pyproject: PathBuf,
+    members: Vec<WorkspaceMember>,
+}
+
+impl Workspace {
+    /// Reads the workspace that is described by the root `pyproject.toml` file at `pyproject`.
+    /// If the root file has a `[project]` table the root project is a member too.
+    pub fn from_path(pyproject: &Path) -> Result<Self, WorkspaceError> {
+        let pyproject = dunce::canonicalize(pyproject)
+            .map_err(|e| WorkspaceError::IoError(pyproject.to_path_buf(), e))?;
+        let root = pyproject
+            .parent()
+            .expect("a canonicalized file always has a parent")
+            .to_path_buf();
+        let contents = fs::read_to_string(&pyproject)
+            .map_err(|e| WorkspaceError::IoError(pyproject.clone(), e))?;
+        let table = toml::from_str::<WorkspacePyProject>(&contents)
+            .map_err(|e| WorkspaceError::InvalidPyProject(pyproject.clone(), e))?
+            .tool
+            .and_then(|tool| tool.rip)
+            .and_then(|rip| rip.workspace)
+            .ok_or_else(|| WorkspaceError::MissingWorkspaceTable(pyproject.clone()))?;
+
+        let mut directories = Vec::new();
+        if read_pyproject(&pyproject)?.project.is_some() {
+            directories.push(root.clone());
+        }
+        let exclude = table
+            .exclude
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .map_err(|e| WorkspaceError::InvalidPattern(pattern.clone(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for pattern in &table.members {
+            let absolute = format!(
+                "{}/{pattern}",
+                glob::Pattern::escape(&root.to_string_lossy())
+            );
+            let paths = glob::glob(&absolute)
+                .map_err(|e| WorkspaceError::InvalidPattern(pattern.clone(), e))?;
+            for path in paths {
+                let path = path.map_err(|e| {
+                    let path = e.path().to_path_buf();
+                    WorkspaceError::IoError(path, e.into_error())
+                })?;
+                let relative = path.strip_prefix(&root).unwrap_or(&path);
+                if !path.is_dir() || exclude.iter().any(|e| e.matches_path(relative)) {
+                    continue;
+                }
+                let path = dunce::canonicalize(&path)
+                    .map_err(|e| WorkspaceError::IoError(path.clone(), e))?;
+                if !directories.contains(&path) {
+                    directories.push(path);
+                }
+            }
+        }
+
+        let mut members: Vec<WorkspaceMember> = Vec::with_capacity(directories.len());
+        for directory in directories {
+            let member = WorkspaceMember::from_dir(directory)?;
+            if let Some(existing) = members.iter().find(|m| m.name == member.name) {
+                return Err(WorkspaceError::DuplicateMember(
+                    member.name.into(),
+                    existing.path.clone(),
+                    member.path,
+                ));
+            }
+            members.push(member);
+        }
+
+        Ok(Self { pyproject, members })
+    }
+
+    /// The root `pyproject.toml` file of the workspace.
+    pub fn pyproject(&self) -> &Path {
+        &self.pyproject
+    }
+
+    /// The members of the workspace, the root project first if it is a member.
+    pub fn members(&self) -> &[WorkspaceMember] {
+        &self.members
+    }
+
+    /// The `pyproject.toml` files of the root and of all members.
+    pub fn pyprojects(&self) -> Vec<PathBuf> {
+        let mut pyprojects = vec![self.pyproject.clone()];
+        for member in &self.members {
+            let pyproject = member.pyproject();
+            if !pyprojects.contains(&pyproject) {
+                pyprojects.push(pyproject);
+            }
+        }
+        pyprojects
+    }
+
+    /// Returns the urls of the source trees of the members by their name, pass these as the
+    /// [`crate::resolve::solve_options::ResolveOptions::local_packages`] to resolve the
+    /// requirements of the members on each other to their source trees.
+    pub fn local_packages(&self) -> HashMap<NormalizedPackageName, Url> {
+        self.members
+            .iter()
+            .map(|member| (member.name.clone().into(), member.url()))
+            .collect()
+    }
+
+    /// Returns the requirements to resolve the whole workspace: a requirement on every member,
+    /// e.g. `demo[test] @ file:///path/to/demo`, and the requirements of the dependency `groups`.
+    ///
+    /// Each of the `extras` and `groups` is selected for the members that declare it, the root
+    /// `pyproject.toml` can declare dependency groups without being a member. It is an error if
+    /// none of them declares it.
+    pub fn requirements(
+        &self,
+        extras: &[Extra],
+        groups: &[Extra],
+    ) -> Result<Vec<Requirement>, WorkspaceError> {
+        if let Some(extra) = extras
+            .iter()
+            .find(|extra| !self.members.iter().any(|m| m.extras.contains(extra)))
+        {
+            return Err(WorkspaceError::UnknownExtra(extra.clone()));
+        }
+
+        let mut requirements = Vec::new();
+        for member in &self.members {
+            let member_extras = extras.iter().filter(|extra| member.extras.contains(extra));
+            requirements.push(
+                RequirementBuilder::new(member.name.clone())
+                    .with_extras(member_extras.cloned())
+                    .with_url(member.url())
+                    .build()?,
+            );
+        }
+
+        let mut found = vec![false; groups.len()];
+        for pyproject in self.pyprojects() {
+            let declared = read_pyproject(&pyproject)?.dependency_groups;
+            let selected = groups
+                .iter()
+                .zip(found.iter_mut())
+                .filter(|(group, _)| declared.contains_key(*group))
+                .map(|(group, found)| {
+                    *found = true;
+                    group.clone()
+                })
+                .collect::<Vec<_>>();
+            requirements.extend(dependency_group_requirements(&pyproject, &selected)?);
+        }
+        if let Some((group, _)) = groups.iter().zip(found).find(|(_, found)| !found) {
+            return Err(WorkspaceError::UnknownDependencyGroup(group.clone()));
+        }
+
+        Ok(requirements)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dunce::canonicalize(dir.path()).unwrap();
+        write(
+            &root.join("pyproject.toml"),
+            r#"
+[project]
+name = "app"
+dependencies = ["core>=1", "click"]
+
+[project.optional-dependencies]
+test = ["pytest", "core[testing]"]
+
+[dependency-groups]
+dev = ["ruff"]
+
+[tool.rip.workspace]
+members = ["packages/*"]
+exclude = ["packages/legacy"]
+"#,
+        );
+        write(
+            &root.join("packages/core/pyproject.toml"),
+            r#"
+[project]
+name = "Core"
+
+[project.optional-dependencies]
+testing = ["hypothesis"]
+
+[dependency-groups]
+lint = ["mypy"]
+"#,
+        );
+        write(
+            &root.join("packages/legacy/pyproject.toml"),
+            "[project]\nname = \"legacy\"\n",
+        );
+        write(&root.join("packages/README.md"), "not a member");
+
+        let workspace = Workspace::from_path(&root.join("pyproject.toml")).unwrap();
+        let names = workspace
+            .members()
+            .iter()
+            .map(|member| member.name().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["app", "core"]);
+        assert_eq!(
+            workspace.pyprojects(),
+            vec![
+                root.join("pyproject.toml"),
+                root.join("packages/core/pyproject.toml")
+            ]
+        );
+
+        let core_url = Url::from_directory_path(root.join("packages/core")).unwrap();
+        assert_eq!(
+            workspace.local_packages(),
+            HashMap::from([
+                (
+                    NormalizedPackageName::from_str("app").unwrap(),
+                    Url::from_directory_path(&root).unwrap()
+                ),
+                (NormalizedPackageName::from_str("core").unwrap(), core_url)
+            ])
+        );
+
+        // Extras and groups are selected for the members that declare them
+        let extra = |s: &str| Extra::from_str(s).unwrap();
+        let requirements = workspace
+            .requirements(&[extra("test")], &[extra("dev"), extra("lint")])
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requirements,
+            vec![
+                format!("app[test] @ {}", Url::from_directory_path(&root).unwrap()),
+                format!(
+                    "Core @ {}",
+                    Url::from_directory_path(root.join("packages/core")).unwrap()
+                ),
+                String::from("ruff"),
+                String::from("mypy"),
+            ]
+        );
+
+        assert!(matches!(
+            workspace.requirements(&[extra("docs")], &[]),
+            Err(WorkspaceError::UnknownExtra(_))
+        ));
+        assert!(matches!(
+            workspace.requirements(&[], &[extra("docs")]),
+            Err(WorkspaceError::UnknownDependencyGroup(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let pyproject = dir.path().join("pyproject.toml");
+        write(&pyproject, "[project]\nname = \"app\"\n");
+        assert!(matches!(
+            Workspace::from_path(&pyproject),
+            Err(WorkspaceError::MissingWorkspaceTable(_))
+        ));
+
+        // Two members with the same name
+        write(
+            &pyproject,
+            "[project]\nname = \"app\"\n\n[tool.rip.workspace]\nmembers = [\"app\"]\n",
+        );
+        write(
+            &dir.path().join("app/pyproject.toml"),
+            "[project]\nname = \"App\"\n",
+        );
+        assert!(matches!(
+            Workspace::from_path(&pyproject),
+            Err(WorkspaceError::DuplicateMember(_, _, _))
+        ));
+    }
+}
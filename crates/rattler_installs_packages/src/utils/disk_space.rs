@@ -0,0 +1,114 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// How many times larger the contents of an sdist are estimated to be than the archive itself,
+/// including the files that are created while building a wheel from it.
+#[cfg(feature = "sdist")]
+pub(crate) const SDIST_BUILD_SPACE_FACTOR: u64 = 8;
+
+/// The space that is reserved for the virtual environment in which a wheel is built.
+#[cfg(feature = "sdist")]
+pub(crate) const BUILD_ENVIRONMENT_SPACE: u64 = 64 * 1024 * 1024;
+
+/// There is not enough free space on a volume to perform an operation, this is detected before
+/// the operation is started.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+#[error(
+    "not enough free disk space in '{}' to {operation}, about {} is required but only {} is available",
+    path.display(),
+    Bytes(*required),
+    Bytes(*available)
+)]
+pub struct InsufficientDiskSpace {
+    /// A directory on the volume that does not have enough space
+    pub path: PathBuf,
+
+    /// A description of the operation, e.g. `download numpy-1.26.4.tar.gz`
+    pub operation: String,
+
+    /// The estimated number of bytes that the operation requires
+    pub required: u64,
+
+    /// The number of bytes that are available on the volume
+    pub available: u64,
+}
+
+/// Returns an error if less than `required` bytes are available on the volume that contains
+/// `path`. The path does not need to exist yet, the closest existing ancestor is checked instead.
+/// If the available space cannot be determined the operation is assumed to fit.
+pub(crate) fn ensure_available_space(
+    path: &Path,
+    required: u64,
+    operation: impl FnOnce() -> String,
+) -> Result<(), InsufficientDiskSpace> {
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return Ok(());
+    };
+    let available = match fs4::available_space(existing) {
+        Ok(available) => available,
+        Err(err) => {
+            tracing::debug!(
+                "could not determine the available space in '{}': {err}",
+                existing.display()
+            );
+            return Ok(());
+        }
+    };
+
+    if available < required {
+        return Err(InsufficientDiskSpace {
+            path: path.to_path_buf(),
+            operation: operation(),
+            required,
+            available,
+        });
+    }
+    Ok(())
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `1.5 GiB`.
+struct Bytes(u64);
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ensure_available_space() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_available_space(dir.path(), 1, || String::from("write a byte")).unwrap();
+
+        // The directory does not need to exist yet
+        let path = dir.path().join("does/not/exist");
+        let err =
+            ensure_available_space(&path, u64::MAX, || String::from("install numpy")).unwrap_err();
+        assert_eq!(err.path, path);
+        assert_eq!(err.required, u64::MAX);
+        assert!(err
+            .to_string()
+            .contains("to install numpy, about 16.0 EiB is required"));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(Bytes(12).to_string(), "12 B");
+        assert_eq!(Bytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(Bytes(3 * 1024 * 1024 * 1024).to_string(), "3.0 GiB");
+    }
+}
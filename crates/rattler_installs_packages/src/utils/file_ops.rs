@@ -0,0 +1,150 @@
+//! File operations that are robust against files that are in use on Windows.
+//!
+//! On Windows a file that is opened by another process, like the interpreter of the environment or
+//! a DLL that is loaded by a running python process, cannot be overwritten or deleted. Often this
+//! is temporary, e.g. when a virus scanner inspects a freshly written file, so operations are
+//! retried with a backoff first. Files that remain in use can still be renamed, so they are moved
+//! out of the way and removed during a later installation instead.
+
+use fs_err as fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The delays between subsequent attempts of an operation on a file that is in use.
+const RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+];
+
+/// The extension of files that were moved out of the way because they could not be removed.
+pub(crate) const PENDING_DELETE_EXTENSION: &str = "rip-deleteme";
+
+/// Returns true if the error indicates that the file is in use by another process.
+pub(crate) fn is_file_in_use(err: &io::Error) -> bool {
+    // ERROR_ACCESS_DENIED, ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    cfg!(windows) && matches!(err.raw_os_error(), Some(5 | 32 | 33))
+}
+
+/// Calls `f` until it succeeds or fails with an error that does not indicate that a file is in use,
+/// waiting a little longer between every attempt.
+pub(crate) fn retry_in_use<T>(mut f: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    for delay in RETRY_DELAYS {
+        match f() {
+            Err(err) if is_file_in_use(&err) => {
+                tracing::debug!("file is in use ({err}), retrying in {delay:?}");
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+    f()
+}
+
+/// Removes the file at `path`. If the file is still in use after retrying, it is renamed instead
+/// so that its location can be reused. The renamed file is removed by
+/// [`remove_pending_deletes`].
+pub(crate) fn remove_file(path: &Path) -> io::Result<()> {
+    match retry_in_use(|| fs::remove_file(path)) {
+        Err(err) if is_file_in_use(&err) => move_aside(path).map_err(|_| err),
+        result => result,
+    }
+}
+
+/// Renames the file at `path` to a unique name in the same directory and tries to remove it.
+fn move_aside(path: &Path) -> io::Result<()> {
+    let pending = pending_delete_path(path);
+    fs::rename(path, &pending)?;
+
+    // The file might have been released in the meantime.
+    if fs::remove_file(&pending).is_err() {
+        tracing::warn!(
+            "{} is in use by another process, it will be removed during a later installation",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Returns a unique path next to `path` to move the file to when it cannot be removed.
+fn pending_delete_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(
+        ".{}-{}.{PENDING_DELETE_EXTENSION}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(file_name)
+}
+
+/// Recursively removes the files in `dir` that were moved out of the way by [`remove_file`]
+/// because they were in use. Files that are still in use are left alone.
+pub(crate) fn remove_pending_deletes(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            remove_pending_deletes(&path)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == PENDING_DELETE_EXTENSION)
+        {
+            if let Err(err) = fs::remove_file(&path) {
+                tracing::debug!("failed to remove {}: {err}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remove_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "hello").unwrap();
+        remove_file(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            remove_file(&path).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_remove_pending_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let pending = pending_delete_path(&dir.path().join("nested/python3.dll"));
+        assert_ne!(
+            pending,
+            pending_delete_path(&dir.path().join("nested/python3.dll"))
+        );
+        fs::create_dir_all(pending.parent().unwrap()).unwrap();
+        fs::write(&pending, "").unwrap();
+        fs::write(dir.path().join("nested/keep.dll"), "").unwrap();
+
+        remove_pending_deletes(dir.path()).unwrap();
+        assert!(!pending.exists());
+        assert!(dir.path().join("nested/keep.dll").exists());
+    }
+
+    #[test]
+    fn test_retry_in_use() {
+        let mut attempts = 0;
+        let result: io::Result<()> = retry_in_use(|| {
+            attempts += 1;
+            Err(io::Error::new(io::ErrorKind::Other, "failed"))
+        });
+        assert!(result.is_err());
+        // Errors that do not indicate a file is in use are not retried
+        assert_eq!(attempts, 1);
+    }
+}
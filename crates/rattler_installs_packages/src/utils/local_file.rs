@@ -0,0 +1,109 @@
+use fs_err as fs;
+use memmap2::Mmap;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Files that are at least this large are memory-mapped by [`LocalFile`].
+pub const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// A file on disk that is read through a memory map if it is large enough, e.g. a wheel in a
+/// find-links directory or in the cache.
+///
+/// Reading the entries of a large zip archive from a plain file requires many small seeks and
+/// reads. With a memory map these are served from the page cache without a system call. If the
+/// file cannot be mapped it is read as a plain file.
+///
+/// Only files that are not modified while they are read should be opened this way, which is the
+/// case for the files in the caches because they are replaced instead of overwritten.
+pub enum LocalFile {
+    /// The file is memory-mapped
+    Mapped(Cursor<Mmap>),
+
+    /// The file is too small to be mapped, or mapping it failed
+    File(fs::File),
+}
+
+impl LocalFile {
+    /// Opens the file at `path`.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_file(fs::File::open(path)?)
+    }
+
+    /// Maps `file` if it is at least [`MMAP_THRESHOLD`] bytes large. The current position of the
+    /// file is not preserved, reading starts at the beginning of the file.
+    pub fn from_file(mut file: fs::File) -> io::Result<Self> {
+        if file.metadata()?.len() < MMAP_THRESHOLD {
+            file.rewind()?;
+            return Ok(Self::File(file));
+        }
+
+        // SAFETY: The files that are opened this way are never modified in place, see the
+        // documentation of this type.
+        match unsafe { Mmap::map(file.file()) } {
+            Ok(map) => Ok(Self::Mapped(Cursor::new(map))),
+            Err(err) => {
+                tracing::debug!("failed to memory-map file, reading it instead: {err}");
+                file.rewind()?;
+                Ok(Self::File(file))
+            }
+        }
+    }
+}
+
+impl Read for LocalFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Mapped(map) => map.read(buf),
+            Self::File(file) => file.read(buf),
+        }
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        match self {
+            Self::Mapped(map) => map.read_exact(buf),
+            Self::File(file) => file.read_exact(buf),
+        }
+    }
+}
+
+impl Seek for LocalFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Mapped(map) => map.seek(pos),
+            Self::File(file) => file.seek(pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_local_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let small = dir.path().join("small");
+        fs::write(&small, b"hello world").unwrap();
+        let mut file = LocalFile::open(&small).unwrap();
+        assert!(matches!(file, LocalFile::File(_)));
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello world");
+
+        let large = dir.path().join("large");
+        let mut writer = fs::File::create(&large).unwrap();
+        writer
+            .write_all(&vec![0u8; MMAP_THRESHOLD as usize])
+            .unwrap();
+        writer.write_all(b"tail").unwrap();
+        drop(writer);
+        let mut file = LocalFile::open(&large).unwrap();
+        assert!(matches!(file, LocalFile::Mapped(_)));
+        file.seek(SeekFrom::End(-4)).unwrap();
+        let mut tail = String::new();
+        file.read_to_string(&mut tail).unwrap();
+        assert_eq!(tail, "tail");
+    }
+}
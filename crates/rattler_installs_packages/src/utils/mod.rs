@@ -1,3 +1,6 @@
+mod disk_space;
+mod file_ops;
+mod local_file;
 mod read_and_seek;
 mod streaming_or_local;
 
@@ -8,6 +11,12 @@ use std::path::{Component, Path, PathBuf};
 use include_dir::{include_dir, Dir};
 use url::Url;
 
+pub(crate) use disk_space::ensure_available_space;
+pub use disk_space::InsufficientDiskSpace;
+#[cfg(feature = "sdist")]
+pub(crate) use disk_space::{BUILD_ENVIRONMENT_SPACE, SDIST_BUILD_SPACE_FACTOR};
+pub(crate) use file_ops::{is_file_in_use, remove_file, remove_pending_deletes, retry_in_use};
+pub(crate) use local_file::LocalFile;
 pub use read_and_seek::ReadAndSeek;
 pub use streaming_or_local::StreamingOrLocal;
 
@@ -0,0 +1,349 @@
+//! Building wheels from sdists and source trees with their build backend. This is only available
+//! with the `sdist` feature.
+
+use super::build_environment::BuildEnvironment;
+use super::{WheelBuildError, WheelBuilder};
+use crate::artifacts::{STree, Wheel};
+use crate::resolve::solve_options::OnWheelBuildFailure;
+use crate::types::{
+    ArtifactFromSource, NormalizedPackageName, PackageName, SourceArtifactName, WheelCoreMetadata,
+    WheelFilename,
+};
+use fs_err as fs;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Weak};
+use tokio::sync::broadcast;
+
+type OptionalBuildEnv = Option<Arc<BuildEnvironment>>;
+type BuildEnvironmentSender = broadcast::Sender<OptionalBuildEnv>;
+type BuildEnvironmentReceiver = broadcast::Receiver<OptionalBuildEnv>;
+
+/// The build environments that were set up by a [`WheelBuilder`].
+#[derive(Default)]
+pub(super) struct BuildEnvironments {
+    /// A cache for virtualenvs that might be reused later in the process
+    venv_cache: Mutex<HashMap<SourceArtifactName, Arc<BuildEnvironment>>>,
+
+    /// A cache for in-flight virtualenvs
+    in_setup_venv: Mutex<HashMap<SourceArtifactName, Weak<BuildEnvironmentSender>>>,
+}
+
+impl WheelBuilder {
+    /// Get the metadata of an sdist from its build backend. This function uses the
+    /// `prepare_metadata_for_build_wheel` entry point of the build backend.
+    pub(super) async fn metadata_from_source<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
+        let build_environment = self.setup_build_venv(sdist).await?;
+
+        // Capture the result of the build
+        // to handle different failure modes
+        let result = self
+            .get_sdist_metadata_internal(&build_environment, sdist)
+            .await;
+        self.handle_build_failure(result, &build_environment)
+    }
+
+    /// Build a wheel from an sdist with its build backend. This function uses the `build_wheel`
+    /// entry point of the build backend.
+    pub(super) async fn wheel_from_source<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<Wheel, WheelBuildError> {
+        // Setup a new virtualenv for building the wheel or use an existing
+        let build_environment = self.setup_build_venv(sdist).await?;
+        // Capture the result of the build
+        // to handle different failure modes
+        let result = self.build_wheel_internal(&build_environment, sdist).await;
+
+        self.handle_build_failure(result, &build_environment)
+    }
+
+    /// Build an sdist from a source tree with its build backend and write it to `output_dir`.
+    /// This function uses the `build_sdist` entry point of the build backend.
+    pub(super) async fn sdist_from_source(
+        &self,
+        stree: &STree,
+        output_dir: &Path,
+    ) -> Result<PathBuf, WheelBuildError> {
+        let build_environment = self.setup_build_venv(stree).await?;
+        let result = self
+            .build_sdist_internal(&build_environment, output_dir)
+            .await;
+
+        self.handle_build_failure(result, &build_environment)
+    }
+
+    /// Get a prepared virtualenv for building a wheel (or extracting metadata) from an `[SDist]`
+    /// This function also caches the virtualenvs, so that they can be reused later.
+    #[tracing::instrument(name = "setup_build_env", skip_all, fields(package = %sdist.distribution_name()))]
+    async fn setup_build_venv(
+        &self,
+        sdist: &impl ArtifactFromSource,
+    ) -> Result<Arc<BuildEnvironment>, WheelBuildError> {
+        // Don't start new builds after the operations have been interrupted
+        if self.package_db.cancellation_token().is_cancelled() {
+            return Err(WheelBuildError::Interrupted);
+        }
+
+        // Either we have the venv cached or not yet
+        let name = sdist.artifact_name();
+        if let Some(venv) = self.build_environments.venv_cache.lock().get(&name) {
+            tracing::debug!(
+                "using cached virtual env for: {:?}",
+                sdist.distribution_name()
+            );
+            return Ok(venv.clone());
+        }
+
+        // Even though there is no build environment yet.
+        // Check if another task is already setting up the build environment
+        // if so wait for it to finish
+        enum BuildEnvState {
+            // No build environment yet
+            New(Arc<BuildEnvironmentSender>),
+            // Currently setting up the build environment
+            SettingUp(BuildEnvironmentReceiver),
+        }
+
+        // Check if we are inflight
+        let state = {
+            let mut lock = self.build_environments.in_setup_venv.lock();
+            match lock.get(&name) {
+                // We are setting up lets wait for the broadcast
+                Some(notify) => {
+                    // If the notify is still alive, we are setting up
+                    if let Some(sender) = notify.upgrade() {
+                        BuildEnvState::SettingUp(sender.subscribe())
+                    } else {
+                        // Otherwise a panic happened, so we need to re-setup
+                        let (tx, _) = broadcast::channel(1);
+                        let tx = Arc::new(tx);
+                        lock.insert(name.clone(), Arc::downgrade(&tx));
+                        BuildEnvState::New(tx)
+                    }
+                }
+                // We are the first one here, so lets tell the other tasks to wait
+                None => {
+                    let (tx, _) = broadcast::channel(1);
+                    let tx = Arc::new(tx);
+                    lock.insert(name.clone(), Arc::downgrade(&tx));
+                    BuildEnvState::New(tx)
+                }
+            }
+        };
+        // Drop the lock to allow other tasks to continue getting to this point
+
+        // If we are SettingUp wait for the response
+        let tx = match state {
+            BuildEnvState::SettingUp(mut rx) => {
+                tracing::debug!(
+                    "waiting for in-flight virtual env for: {:?}",
+                    sdist.distribution_name()
+                );
+                // Wait for a value to return
+                // If the .recv() has an error all senders have been dropped
+                // this implies that the setup has panicked
+                return if let Some(build_env) = rx.recv().await.map_err(|_| {
+                    WheelBuildError::Error(
+                        "panic during setup of original build environment".to_string(),
+                    )
+                })? {
+                    Ok(build_env)
+                } else {
+                    // Error while setting up a build env
+                    // but not a panic
+                    Err(WheelBuildError::Error(
+                        "error during setup of original build environment".to_string(),
+                    ))
+                };
+            }
+            BuildEnvState::New(notify) => notify,
+        };
+
+        // Otherwise we need to do the work
+        tracing::debug!("creating virtual env for: {:?}", sdist.distribution_name());
+
+        // Wrap this in a future to capture the result
+        let future = || async {
+            let mut build_environment = BuildEnvironment::setup(sdist, self).await?;
+            build_environment.install_build_files(sdist)?;
+            // Install extra requirements if any
+            build_environment.install_extra_requirements(self).await?;
+            Ok(build_environment)
+        };
+
+        match future().await {
+            Ok(build_environment) => {
+                let build_environment = Arc::new(build_environment);
+                // Insert into the venv cache
+                self.build_environments
+                    .venv_cache
+                    .lock()
+                    .insert(sdist.artifact_name().clone(), build_environment.clone());
+
+                // Notify others that a result is available
+                let _ = tx.send(Some(build_environment.clone()));
+
+                Ok(build_environment)
+            }
+            Err(e) => {
+                // Notify others that a result is available
+                // It's fine that its none because the error is also propagated
+                let _ = tx.send(None);
+                Err(e)
+            }
+        }
+    }
+
+    /// Handle's a build failure by either saving the build environment or deleting it. A build
+    /// that was interrupted is handled like any other failure.
+    fn handle_build_failure<T>(
+        &self,
+        result: Result<T, WheelBuildError>,
+        build_environment: &BuildEnvironment,
+    ) -> Result<T, WheelBuildError> {
+        // A build backend that is stopped by the interrupt fails as well, report why it failed
+        let result = match result {
+            Err(_) if self.package_db.cancellation_token().is_cancelled() => {
+                Err(WheelBuildError::Interrupted)
+            }
+            result => result,
+        };
+        if self.resolve_options.on_wheel_build_failure != OnWheelBuildFailure::SaveBuildEnv {
+            return result;
+        }
+        if let Err(e) = result {
+            // Persist the build environment
+            build_environment.persist();
+
+            // Save the information for later usage
+            let path = build_environment.work_dir();
+            tracing::info!("saved build environment is available at: {:?}", &path);
+            self.saved_build_envs
+                .lock()
+                .insert(build_environment.work_dir());
+            Err(e)
+        } else {
+            result
+        }
+    }
+
+    async fn get_sdist_metadata_internal<S: ArtifactFromSource>(
+        &self,
+        build_environment: &BuildEnvironment,
+        sdist: &S,
+    ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
+        let output_dir = tempfile::tempdir()?;
+        let output = build_environment.run_command("WheelMetadata", output_dir.path())?;
+        if !output.status.success() {
+            if output.status.code() == Some(50) {
+                tracing::warn!("SDist build backend does not support metadata generation");
+                // build wheel instead
+                let wheel = self.build_wheel(sdist).await?;
+                return wheel.metadata().map_err(|e| {
+                    WheelBuildError::Error(format!("Could not parse wheel metadata: {}", e))
+                });
+            }
+            let stdout = String::from_utf8_lossy(&output.stderr);
+            return Err(WheelBuildError::Error(stdout.to_string()));
+        }
+
+        // Read the outputted file
+        let result = fs::read_to_string(output_dir.path().join("metadata_result"))?;
+        let folder = PathBuf::from(result.trim());
+        let path = folder.join("METADATA");
+
+        // Read the metadata
+        let metadata = fs::read(path)?;
+        let wheel_metadata = WheelCoreMetadata::try_from(metadata.as_slice())?;
+        Ok((metadata, wheel_metadata))
+    }
+
+    async fn build_wheel_internal<S: ArtifactFromSource>(
+        &self,
+        build_environment: &BuildEnvironment,
+        sdist: &S,
+    ) -> Result<Wheel, WheelBuildError> {
+        let output_dir = tempfile::tempdir()?;
+        // Run the wheel stage
+        let output = build_environment.run_command("Wheel", output_dir.path())?;
+
+        // Check for success
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stderr);
+            return Err(WheelBuildError::Error(stdout.to_string()));
+        }
+
+        // This is where the wheel file is located
+        let wheel_file: PathBuf = fs::read_to_string(output_dir.path().join("wheel_result"))?
+            .trim()
+            .into();
+
+        // Get the name of the package
+        let package_name: NormalizedPackageName = PackageName::from_str(&sdist.distribution_name())
+            .unwrap()
+            .into();
+
+        // Save the wheel into the cache
+        let key = self.wheel_cache_key(sdist)?;
+
+        // Reconstruction of the wheel filename
+        let file_component = wheel_file
+            .file_name()
+            .and_then(|f| f.to_str())
+            .ok_or_else(|| {
+                WheelBuildError::Error(format!(
+                    "Could not get extract file component from {}",
+                    wheel_file.display()
+                ))
+            })?;
+        let wheel_file_name = WheelFilename::from_filename(file_component, &package_name)?;
+
+        // Associate the wheel with the key which is the hashed sdist
+        self.package_db.local_wheel_cache().associate_wheel(
+            &key,
+            wheel_file_name,
+            &mut fs::File::open(&wheel_file)?,
+        )?;
+
+        // Reconstruct wheel from the path
+        let wheel = Wheel::from_path(&wheel_file, &package_name)
+            .map_err(|e| WheelBuildError::Error(format!("Could not build wheel: {}", e)))?;
+
+        Ok(wheel)
+    }
+
+    async fn build_sdist_internal(
+        &self,
+        build_environment: &BuildEnvironment,
+        output_dir: &Path,
+    ) -> Result<PathBuf, WheelBuildError> {
+        let work_dir = tempfile::tempdir()?;
+        let output = build_environment.run_command("SDist", work_dir.path())?;
+        if !output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stderr);
+            return Err(WheelBuildError::Error(stdout.to_string()));
+        }
+
+        // Move the sdist from the temporary directory to the output directory
+        let sdist_file: PathBuf = fs::read_to_string(work_dir.path().join("sdist_result"))?
+            .trim()
+            .into();
+        let file_name = sdist_file.file_name().ok_or_else(|| {
+            WheelBuildError::Error(format!(
+                "Could not get extract file component from {}",
+                sdist_file.display()
+            ))
+        })?;
+        fs::create_dir_all(output_dir)?;
+        let destination = output_dir.join(file_name);
+        fs::copy(&sdist_file, &destination)?;
+
+        Ok(destination)
+    }
+}
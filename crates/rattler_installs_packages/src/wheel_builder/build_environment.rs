@@ -1,9 +1,12 @@
 use crate::artifacts::wheel::UnpackWheelOptions;
-use crate::types::ArtifactFromSource;
+use crate::types::{ArtifactFromSource, SourceArtifactName};
 
 use crate::python_env::{PythonLocation, VEnv};
+use crate::reporter::Reporter;
 use crate::resolve::{resolve, PinnedPackage};
-use crate::utils::normalize_path;
+use crate::utils::{
+    ensure_available_space, normalize_path, BUILD_ENVIRONMENT_SPACE, SDIST_BUILD_SPACE_FACTOR,
+};
 use crate::wheel_builder::{WheelBuildError, WheelBuilder};
 use fs_err as fs;
 use fs_err::read_dir;
@@ -11,11 +14,13 @@ use parking_lot::RwLock;
 use pep508_rs::Requirement;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read};
 
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
 use std::str::FromStr;
+use std::sync::{mpsc, Arc};
 
 #[derive(Debug)]
 enum DeleteOrPersist {
@@ -89,7 +94,6 @@ const BUILD_FRONTEND_PY: &str = include_str!("./wheel_builder_frontend.py");
 pub(crate) struct BuildEnvironment {
     work_dir: TempBuildEnvironment,
     package_dir: PathBuf,
-    #[allow(dead_code)]
     build_system: pyproject_toml::BuildSystem,
     entry_point: String,
     build_requirements: Vec<Requirement>,
@@ -99,6 +103,8 @@ pub(crate) struct BuildEnvironment {
     clean_env: bool,
     #[allow(dead_code)]
     python_location: PythonLocation,
+    name: SourceArtifactName,
+    reporter: Option<Arc<dyn Reporter>>,
 }
 
 fn normalize_backend_path(
@@ -132,7 +138,7 @@ impl BuildEnvironment {
     pub(crate) fn install_build_files(
         &mut self,
         sdist: &(impl ArtifactFromSource + ?Sized),
-    ) -> std::io::Result<()> {
+    ) -> Result<(), WheelBuildError> {
         // Extract the sdist to the work folder
         // extract to a specific package dir
         let work_dir = self.work_dir.path();
@@ -146,11 +152,10 @@ impl BuildEnvironment {
         // which is not actually true
         // so after extracting or moving
         // we map correct package location
-        // when URL is actually a git version
-        // it is extracted in work_dir
+        // when the artifact is a source tree (a local directory
+        // or a git checkout) it is extracted in work_dir
         // so we map package_dir to work_dir
-
-        if sdist.version().is_git() {
+        if matches!(sdist.artifact_name(), SourceArtifactName::STree(_)) {
             self.package_dir = self.work_dir.path();
         } else if let Some(package_dir_name) = self.package_dir.file_name() {
             let actual_package_dir = work_dir.join(package_dir_name);
@@ -168,8 +173,20 @@ impl BuildEnvironment {
             }
         }
 
+        // insert env var for the backend path that will be used by the build frontend, the
+        // backend path is relative to the package dir so this is done after it is mapped
+        if let Some(backend_path) = &self.build_system.backend_path {
+            self.env_variables.insert(
+                "PEP517_BACKEND_PATH".into(),
+                std::env::join_paths(normalize_backend_path(backend_path, &self.package_dir)?)?
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+
         // Write the python frontend to the work folder
-        fs::write(work_dir.join("build_frontend.py"), BUILD_FRONTEND_PY)
+        fs::write(work_dir.join("build_frontend.py"), BUILD_FRONTEND_PY)?;
+        Ok(())
     }
 
     /// Get the path to the work directory
@@ -246,7 +263,7 @@ impl BuildEnvironment {
                 wheel_builder.wheel_tags.clone(),
                 locked_packages,
                 favored_packages,
-                wheel_builder.resolve_options.clone(),
+                wheel_builder.build_resolve_options(),
                 self.env_variables.clone(),
             )
             .await
@@ -287,6 +304,7 @@ impl BuildEnvironment {
     }
 
     /// Run a command in the build environment
+    #[tracing::instrument(name = "run_build_backend", skip(self, output_dir), fields(package = %self.name, exit_code))]
     pub(crate) fn run_command(
         &self,
         stage: &str,
@@ -341,8 +359,61 @@ impl BuildEnvironment {
             .arg(&self.entry_point)
             // Building Wheel or Metadata
             .arg(stage)
-            .output()
-            .map_err(|e| WheelBuildError::CouldNotRunCommand(stage.into(), e))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = base_command
+            .spawn()
+            .map_err(|e| WheelBuildError::CouldNotRunCommand(stage.into(), e))?;
+
+        // Forward the output of the build backend line by line while it runs and also collect it
+        // for the error message if the build fails
+        let streams: [(bool, Box<dyn Read + Send>); 2] = [
+            (
+                false,
+                Box::new(child.stdout.take().expect("stdout is piped")),
+            ),
+            (
+                true,
+                Box::new(child.stderr.take().expect("stderr is piped")),
+            ),
+        ];
+        let (stdout, stderr) = std::thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            for (is_stderr, stream) in streams {
+                let tx = tx.clone();
+                scope.spawn(move || {
+                    for line in BufReader::new(stream).split(b'\n').map_while(Result::ok) {
+                        if tx.send((is_stderr, line)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            for (is_stderr, line) in rx {
+                if let Some(reporter) = &self.reporter {
+                    reporter.on_build_output(&self.name, String::from_utf8_lossy(&line).trim_end());
+                }
+                let output = if is_stderr { &mut stderr } else { &mut stdout };
+                output.extend_from_slice(&line);
+                output.push(b'\n');
+            }
+            (stdout, stderr)
+        });
+
+        let status = child
+            .wait()
+            .map_err(|e| WheelBuildError::CouldNotRunCommand(stage.into(), e))?;
+        if let Some(exit_code) = status.code() {
+            tracing::Span::current().record("exit_code", exit_code);
+        }
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
     }
 
     fn default_build_system() -> pyproject_toml::BuildSystem {
@@ -373,6 +444,14 @@ impl BuildEnvironment {
     ) -> Result<BuildEnvironment, WheelBuildError> {
         // Setup a work directory and a new env dir
         let work_dir = tempfile::tempdir()?;
+
+        // Fail before anything is extracted or installed if the build will not fit
+        let required = sdist.archive_size().unwrap_or_default() * SDIST_BUILD_SPACE_FACTOR
+            + BUILD_ENVIRONMENT_SPACE;
+        ensure_available_space(work_dir.path(), required, || {
+            format!("build a wheel for {}", sdist.distribution_name())
+        })?;
+
         let venv = VEnv::create(
             &work_dir.path().join("venv"),
             wheel_builder.resolve_options.python_location.clone(),
@@ -413,7 +492,7 @@ impl BuildEnvironment {
             wheel_builder.wheel_tags.clone(),
             HashMap::default(),
             HashMap::default(),
-            wheel_builder.resolve_options.clone(),
+            wheel_builder.build_resolve_options(),
             Default::default(),
         )
         .await
@@ -450,19 +529,11 @@ impl BuildEnvironment {
                 .path()
                 .join(format!("{}-{}", sdist.distribution_name(), sdist.version(),));
 
-        let env_variables = if let Some(backend_path) = &build_system.backend_path {
-            let mut env_variables = wheel_builder.env_variables.clone();
-            // insert env var for the backend path that will be used by the build frontend
-            env_variables.insert(
-                "PEP517_BACKEND_PATH".into(),
-                std::env::join_paths(normalize_backend_path(backend_path, &package_dir)?)?
-                    .to_string_lossy()
-                    .to_string(),
-            );
-            env_variables
-        } else {
-            wheel_builder.env_variables.clone()
-        };
+        let mut env_variables = wheel_builder.env_variables.clone();
+        if let Some(config_settings) = wheel_builder.config_settings_json() {
+            // the config settings are passed to the hooks of the build backend
+            env_variables.insert("PEP517_CONFIG_SETTINGS".into(), config_settings);
+        }
 
         Ok(BuildEnvironment {
             work_dir: TempBuildEnvironment::new(work_dir),
@@ -475,6 +546,8 @@ impl BuildEnvironment {
             env_variables,
             clean_env: wheel_builder.resolve_options.clean_env,
             python_location: wheel_builder.resolve_options.python_location.clone(),
+            name: sdist.artifact_name(),
+            reporter: wheel_builder.reporter().cloned(),
         })
     }
 }
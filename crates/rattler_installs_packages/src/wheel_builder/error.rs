@@ -1,6 +1,8 @@
 use crate::artifacts::wheel::UnpackError;
 use crate::python_env::VEnvError;
 use crate::types::{ParseArtifactNameError, WheelCoreMetaDataError};
+use crate::utils::InsufficientDiskSpace;
+#[cfg(feature = "sdist")]
 use crate::wheel_builder::wheel_cache;
 use pep508_rs::Requirement;
 use std::path::PathBuf;
@@ -33,6 +35,7 @@ pub enum WheelBuildError {
     #[error("could not get artifact: {0}")]
     CouldNotGetArtifact(miette::Report),
 
+    #[cfg(feature = "sdist")]
     #[error("could not get artifact from cache: {0}")]
     CacheError(#[from] wheel_cache::WheelCacheError),
 
@@ -52,4 +55,13 @@ pub enum WheelBuildError {
 
     #[error("could not join path: {0}")]
     CouldNotJoinPath(#[from] std::env::JoinPathsError),
+
+    #[error("cannot build a wheel for '{0}', building from source is not available because the `sdist` feature is disabled")]
+    SDistBuildingDisabled(String),
+
+    #[error("the build was interrupted")]
+    Interrupted,
+
+    #[error(transparent)]
+    InsufficientDiskSpace(#[from] InsufficientDiskSpace),
 }
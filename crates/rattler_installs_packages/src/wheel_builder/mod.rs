@@ -1,42 +1,43 @@
 //! Turn an sdist into a wheel by creating a virtualenv and building the sdist in it
+//!
+//! Building wheels, and caching the wheels that were built, requires the `sdist` feature. Without
+//! it sdists and source trees cannot be used at all.
 
+#[cfg(feature = "sdist")]
+mod build;
+#[cfg(feature = "sdist")]
 mod build_environment;
 mod error;
+#[cfg(feature = "sdist")]
 mod wheel_cache;
 
-use fs_err as fs;
-
 use std::collections::HashSet;
-use std::str::FromStr;
 
-use std::sync::{Arc, Weak};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
 use std::{collections::HashMap, path::PathBuf};
 
 use parking_lot::Mutex;
 use pep508_rs::MarkerEnvironment;
 
+use crate::artifacts::STree;
 use crate::python_env::{ParsePythonInterpreterVersionError, PythonInterpreterVersion};
-use crate::resolve::solve_options::{OnWheelBuildFailure, ResolveOptions};
-use crate::types::ArtifactFromSource;
-use crate::types::{NormalizedPackageName, PackageName, SourceArtifactName, WheelFilename};
-use crate::wheel_builder::build_environment::BuildEnvironment;
-pub use crate::wheel_builder::wheel_cache::{WheelCache, WheelCacheKey};
+use crate::reporter::Reporter;
+use crate::resolve::solve_options::ResolveOptions;
+use crate::types::{ArtifactFromSource, PackageName};
+#[cfg(feature = "sdist")]
+pub use crate::wheel_builder::wheel_cache::{WheelCache, WheelCacheError, WheelCacheKey};
 use crate::{artifacts::Wheel, index::PackageDb, python_env::WheelTags, types::WheelCoreMetadata};
 pub use error::WheelBuildError;
-use tokio::sync::broadcast;
-
-type BuildCache = Mutex<HashMap<SourceArtifactName, Arc<BuildEnvironment>>>;
-type OptionalBuildEnv = Option<Arc<BuildEnvironment>>;
-type BuildEnvironmentSender = broadcast::Sender<OptionalBuildEnv>;
-type BuildEnvironmentReceiver = broadcast::Receiver<OptionalBuildEnv>;
 
 /// A builder for wheels
+#[cfg_attr(not(feature = "sdist"), allow(dead_code))]
 pub struct WheelBuilder {
-    /// A cache for virtualenvs that might be reused later in the process
-    venv_cache: BuildCache,
-
-    /// A cache for in-flight virtualenvs
-    in_setup_venv: Mutex<HashMap<SourceArtifactName, Weak<BuildEnvironmentSender>>>,
+    /// The build environments that were set up by this instance
+    #[cfg(feature = "sdist")]
+    build_environments: build::BuildEnvironments,
 
     /// The package database to use
     package_db: Arc<PackageDb>,
@@ -62,6 +63,16 @@ pub struct WheelBuilder {
 
     /// Python interpreter version
     python_version: PythonInterpreterVersion,
+
+    /// Whether the wheels are built for a free-threaded interpreter
+    free_threaded: bool,
+
+    /// The config settings that are passed to the build backend
+    config_settings: Vec<(String, String)>,
+
+    /// Receives the progress and the output of builds, instead of the reporter of the package
+    /// database
+    reporter: Option<Arc<dyn Reporter>>,
 }
 
 impl WheelBuilder {
@@ -76,10 +87,13 @@ impl WheelBuilder {
         let resolve_options = resolve_options.clone();
 
         let python_version = resolve_options.python_location.version()?;
+        let free_threaded = wheel_tags
+            .as_ref()
+            .is_some_and(|wheel_tags| wheel_tags.is_free_threaded());
 
         Ok(Self {
-            venv_cache: Mutex::new(HashMap::new()),
-            in_setup_venv: Mutex::new(HashMap::new()),
+            #[cfg(feature = "sdist")]
+            build_environments: Default::default(),
             package_db,
             env_markers,
             wheel_tags,
@@ -87,127 +101,93 @@ impl WheelBuilder {
             env_variables,
             saved_build_envs: Mutex::new(HashSet::new()),
             python_version,
+            free_threaded,
+            config_settings: Vec::new(),
+            reporter: None,
         })
     }
 
+    /// Sets the config settings that are passed to the hooks of the build backend, similar to
+    /// `--config-setting` of `python -m build`. A key that occurs multiple times is passed as a
+    /// list of values.
+    pub fn with_config_settings(
+        mut self,
+        config_settings: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        self.config_settings = config_settings.into_iter().collect();
+        self
+    }
+
+    /// Sets the reporter that is notified about builds and their output. By default the reporter of
+    /// the package database is used.
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = Some(reporter);
+        self
+    }
+
+    /// Returns the reporter that is notified about builds, if any.
+    pub fn reporter(&self) -> Option<&Arc<dyn Reporter>> {
+        self.reporter
+            .as_ref()
+            .or_else(|| self.package_db.reporter())
+    }
+
+    /// Returns the config settings as a JSON object, or `None` if there are no config settings.
+    #[cfg(feature = "sdist")]
+    fn config_settings_json(&self) -> Option<String> {
+        config_settings_json(&self.config_settings)
+    }
+
+    /// Returns the options to resolve build requirements with. Like pip, hashes are only required
+    /// for the packages that are installed, not for the build requirements of sdists.
+    #[cfg(feature = "sdist")]
+    pub(crate) fn build_resolve_options(&self) -> ResolveOptions {
+        ResolveOptions {
+            required_hashes: None,
+            ..self.resolve_options.clone()
+        }
+    }
+
     /// Get the python interpreter version
     pub fn python_version(&self) -> &PythonInterpreterVersion {
         &self.python_version
     }
 
-    /// Get a prepared virtualenv for building a wheel (or extracting metadata) from an `[SDist]`
-    /// This function also caches the virtualenvs, so that they can be reused later.
-    async fn setup_build_venv(
-        &self,
-        sdist: &impl ArtifactFromSource,
-    ) -> Result<Arc<BuildEnvironment>, WheelBuildError> {
-        // Either we have the venv cached or not yet
-        let name = sdist.artifact_name();
-        if let Some(venv) = self.venv_cache.lock().get(&name) {
-            tracing::debug!(
-                "using cached virtual env for: {:?}",
-                sdist.distribution_name()
-            );
-            return Ok(venv.clone());
+    /// Returns the key under which the wheel that is built from `sdist` is cached.
+    #[cfg(feature = "sdist")]
+    fn wheel_cache_key(&self, sdist: &impl ArtifactFromSource) -> std::io::Result<WheelCacheKey> {
+        let mut key = WheelCacheKey::from_sdist(sdist, &self.python_version)?;
+        if self.free_threaded {
+            key = key.free_threaded();
         }
-
-        // Even though there is no build environment yet.
-        // Check if another task is already setting up the build environment
-        // if so wait for it to finish
-        enum BuildEnvState {
-            // No build environment yet
-            New(Arc<BuildEnvironmentSender>),
-            // Currently setting up the build environment
-            SettingUp(BuildEnvironmentReceiver),
+        if let Some(config_settings) = self.config_settings_json() {
+            key = key.with_config_settings(&config_settings);
         }
+        Ok(key)
+    }
 
-        // Check if we are inflight
-        let state = {
-            let mut lock = self.in_setup_venv.lock();
-            match lock.get(&name) {
-                // We are setting up lets wait for the broadcast
-                Some(notify) => {
-                    // If the notify is still alive, we are setting up
-                    if let Some(sender) = notify.upgrade() {
-                        BuildEnvState::SettingUp(sender.subscribe())
-                    } else {
-                        // Otherwise a panic happened, so we need to re-setup
-                        let (tx, _) = broadcast::channel(1);
-                        let tx = Arc::new(tx);
-                        lock.insert(name.clone(), Arc::downgrade(&tx));
-                        BuildEnvState::New(tx)
-                    }
-                }
-                // We are the first one here, so lets tell the other tasks to wait
-                None => {
-                    let (tx, _) = broadcast::channel(1);
-                    let tx = Arc::new(tx);
-                    lock.insert(name.clone(), Arc::downgrade(&tx));
-                    BuildEnvState::New(tx)
-                }
-            }
-        };
-        // Drop the lock to allow other tasks to continue getting to this point
-
-        // If we are SettingUp wait for the response
-        let tx = match state {
-            BuildEnvState::SettingUp(mut rx) => {
-                tracing::debug!(
-                    "waiting for in-flight virtual env for: {:?}",
-                    sdist.distribution_name()
-                );
-                // Wait for a value to return
-                // If the .recv() has an error all senders have been dropped
-                // this implies that the setup has panicked
-                return if let Some(build_env) = rx.recv().await.map_err(|_| {
-                    WheelBuildError::Error(
-                        "panic during setup of original build environment".to_string(),
-                    )
-                })? {
-                    Ok(build_env)
-                } else {
-                    // Error while setting up a build env
-                    // but not a panic
-                    Err(WheelBuildError::Error(
-                        "error during setup of original build environment".to_string(),
-                    ))
-                };
-            }
-            BuildEnvState::New(notify) => notify,
-        };
-
-        // Otherwise we need to do the work
-        tracing::debug!("creating virtual env for: {:?}", sdist.distribution_name());
-
-        // Wrap this in a future to capture the result
-        let future = || async {
-            let mut build_environment = BuildEnvironment::setup(sdist, self).await?;
-            build_environment.install_build_files(sdist)?;
-            // Install extra requirements if any
-            build_environment.install_extra_requirements(self).await?;
-            Ok(build_environment)
+    /// Returns a wheel that was built from `sdist` before, unless it is not compatible with the
+    /// wheel tags of the target environment.
+    #[cfg(feature = "sdist")]
+    fn cached_wheel(
+        &self,
+        sdist: &impl ArtifactFromSource,
+    ) -> Result<Option<Wheel>, WheelBuildError> {
+        let key = self.wheel_cache_key(sdist)?;
+        let Some(wheel) = self.package_db.local_wheel_cache().wheel_for_key(&key)? else {
+            return Ok(None);
         };
-
-        match future().await {
-            Ok(build_environment) => {
-                let build_environment = Arc::new(build_environment);
-                // Insert into the venv cache
-                self.venv_cache
-                    .lock()
-                    .insert(sdist.artifact_name().clone(), build_environment.clone());
-
-                // Notify others that a result is available
-                let _ = tx.send(Some(build_environment.clone()));
-
-                Ok(build_environment)
-            }
-            Err(e) => {
-                // Notify others that a result is available
-                // It's fine that its none because the error is also propagated
-                let _ = tx.send(None);
-                Err(e)
+        if let Some(wheel_tags) = &self.wheel_tags {
+            if !wheel
+                .name
+                .all_tags_iter()
+                .any(|tag| wheel_tags.is_compatible(&tag))
+            {
+                tracing::debug!("ignoring incompatible cached wheel {}", wheel.name);
+                return Ok(None);
             }
         }
+        Ok(Some(wheel))
     }
 
     /// Get the paths to the saved build environments
@@ -215,166 +195,151 @@ impl WheelBuilder {
         self.saved_build_envs.lock().clone()
     }
 
-    /// Handle's a build failure by either saving the build environment or deleting it
-    fn handle_build_failure<T>(
-        &self,
-        result: Result<T, WheelBuildError>,
-        build_environment: &BuildEnvironment,
-    ) -> Result<T, WheelBuildError> {
-        if self.resolve_options.on_wheel_build_failure != OnWheelBuildFailure::SaveBuildEnv {
-            return result;
-        }
-        if let Err(e) = result {
-            // Persist the build environment
-            build_environment.persist();
-
-            // Save the information for later usage
-            let path = build_environment.work_dir();
-            tracing::info!("saved build environment is available at: {:?}", &path);
-            self.saved_build_envs
-                .lock()
-                .insert(build_environment.work_dir());
-            Err(e)
-        } else {
-            result
-        }
-    }
-
     /// Get the metadata for a given sdist by using the build_backend in a virtual env
     /// This function uses the `prepare_metadata_for_build_wheel` entry point of the build backend.
-    #[tracing::instrument(skip_all, fields(name = % sdist.distribution_name(), version = % sdist.version()))]
+    #[tracing::instrument(name = "build_metadata", skip_all, fields(package = % sdist.distribution_name(), version = % sdist.version()))]
     pub async fn get_sdist_metadata<S: ArtifactFromSource>(
         &self,
         sdist: &S,
     ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
         // See if we have a locally built wheel for this sdist
         // use that metadata instead
-        let key: WheelCacheKey = WheelCacheKey::from_sdist(sdist, &self.python_version)?;
-        if let Some(wheel) = self.package_db.local_wheel_cache().wheel_for_key(&key)? {
+        if let Some(wheel) = self.cached_wheel(sdist)? {
             return wheel.metadata().map_err(|e| {
                 WheelBuildError::Error(format!("Could not parse wheel metadata: {}", e))
             });
         }
 
-        let build_environment = self.setup_build_venv(sdist).await?;
-
-        // Capture the result of the build
-        // to handle different failure modes
-        let result = self
-            .get_sdist_metadata_internal(&build_environment, sdist)
-            .await;
-        self.handle_build_failure(result, &build_environment)
-    }
-
-    async fn get_sdist_metadata_internal<S: ArtifactFromSource>(
-        &self,
-        build_environment: &BuildEnvironment,
-        sdist: &S,
-    ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
-        let output_dir = tempfile::tempdir()?;
-        let output = build_environment.run_command("WheelMetadata", output_dir.path())?;
-        if !output.status.success() {
-            if output.status.code() == Some(50) {
-                tracing::warn!("SDist build backend does not support metadata generation");
-                // build wheel instead
-                let wheel = self.build_wheel(sdist).await?;
-                return wheel.metadata().map_err(|e| {
-                    WheelBuildError::Error(format!("Could not parse wheel metadata: {}", e))
-                });
-            }
-            let stdout = String::from_utf8_lossy(&output.stderr);
-            return Err(WheelBuildError::Error(stdout.to_string()));
+        let start = Instant::now();
+        let result = self.metadata_from_source(sdist).await;
+        let name = PackageName::from_str(&sdist.distribution_name());
+        if let (Some(profiler), Ok(name)) = (&self.resolve_options.profiler, name) {
+            profiler.record_metadata_build(&name.into(), start.elapsed());
         }
-
-        // Read the outputted file
-        let result = fs::read_to_string(output_dir.path().join("metadata_result"))?;
-        let folder = PathBuf::from(result.trim());
-        let path = folder.join("METADATA");
-
-        // Read the metadata
-        let metadata = fs::read(path)?;
-        let wheel_metadata = WheelCoreMetadata::try_from(metadata.as_slice())?;
-        Ok((metadata, wheel_metadata))
+        result
     }
 
     /// Build a wheel from an sdist by using the build_backend in a virtual env.
     /// This function uses the `build_wheel` entry point of the build backend.
-    #[tracing::instrument(skip_all, fields(name = % sdist.distribution_name(), version = % sdist.version()))]
+    #[tracing::instrument(name = "build_wheel", skip_all, fields(package = % sdist.distribution_name(), version = % sdist.version()))]
     pub async fn build_wheel<S: ArtifactFromSource>(
         &self,
         sdist: &S,
     ) -> Result<Wheel, WheelBuildError> {
         // Check if we have already built this wheel locally and use that instead
-        let key = WheelCacheKey::from_sdist(sdist, &self.python_version)?;
-        if let Some(wheel) = self.package_db.local_wheel_cache().wheel_for_key(&key)? {
+        if let Some(wheel) = self.cached_wheel(sdist)? {
             return Ok(wheel);
         }
 
-        // Setup a new virtualenv for building the wheel or use an existing
-        let build_environment = self.setup_build_venv(sdist).await?;
-        // Capture the result of the build
-        // to handle different failure modes
-        let result = self.build_wheel_internal(&build_environment, sdist).await;
+        self.reported_wheel_from_source(sdist).await
+    }
 
-        self.handle_build_failure(result, &build_environment)
+    /// Build a wheel from a source tree, without reusing a wheel that was built from it before.
+    /// This is useful for a local project whose sources might have changed since the last build.
+    #[tracing::instrument(name = "build_wheel", skip_all, fields(package = % sdist.distribution_name(), version = % sdist.version()))]
+    pub async fn rebuild_wheel<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<Wheel, WheelBuildError> {
+        self.reported_wheel_from_source(sdist).await
     }
 
-    async fn build_wheel_internal<S: ArtifactFromSource>(
+    /// Builds a wheel from source and reports the build to the reporter.
+    async fn reported_wheel_from_source<S: ArtifactFromSource>(
         &self,
-        build_environment: &BuildEnvironment,
         sdist: &S,
     ) -> Result<Wheel, WheelBuildError> {
-        let output_dir = tempfile::tempdir()?;
-        // Run the wheel stage
-        let output = build_environment.run_command("Wheel", output_dir.path())?;
-
-        // Check for success
-        if !output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stderr);
-            return Err(WheelBuildError::Error(stdout.to_string()));
+        let reporter = self.reporter();
+        let index = reporter.map(|reporter| reporter.on_build_start(&sdist.artifact_name()));
+        let start = Instant::now();
+        let result = self.wheel_from_source(sdist).await;
+        if let (Some(reporter), Some(index)) = (reporter, index) {
+            reporter.on_build_complete(index);
         }
+        let metrics = self.package_db.metrics();
+        metrics.build_seconds.record(start.elapsed());
+        match &result {
+            Ok(_) => metrics.wheels_built.inc(),
+            Err(_) => metrics.build_failures.inc(),
+        }
+        result
+    }
 
-        // This is where the wheel file is located
-        let wheel_file: PathBuf = fs::read_to_string(output_dir.path().join("wheel_result"))?
-            .trim()
-            .into();
+    /// Build an sdist from a source tree by using the build_backend in a virtual env and write it
+    /// to `output_dir`. This function uses the `build_sdist` entry point of the build backend.
+    /// Returns the path of the sdist.
+    #[tracing::instrument(skip_all, fields(package = % stree.distribution_name()))]
+    pub async fn build_sdist(
+        &self,
+        stree: &STree,
+        output_dir: &Path,
+    ) -> Result<PathBuf, WheelBuildError> {
+        self.sdist_from_source(stree, output_dir).await
+    }
+}
 
-        // Get the name of the package
-        let package_name: NormalizedPackageName = PackageName::from_str(&sdist.distribution_name())
-            .unwrap()
-            .into();
-
-        // Save the wheel into the cache
-        let key = WheelCacheKey::from_sdist(sdist, &self.python_version)?;
-
-        // Reconstruction of the wheel filename
-        let file_component = wheel_file
-            .file_name()
-            .and_then(|f| f.to_str())
-            .ok_or_else(|| {
-                WheelBuildError::Error(format!(
-                    "Could not get extract file component from {}",
-                    wheel_file.display()
-                ))
-            })?;
-        let wheel_file_name = WheelFilename::from_filename(file_component, &package_name)?;
-
-        // Associate the wheel with the key which is the hashed sdist
-        self.package_db.local_wheel_cache().associate_wheel(
-            &key,
-            wheel_file_name,
-            &mut fs::File::open(&wheel_file)?,
-        )?;
-
-        // Reconstruct wheel from the path
-        let wheel = Wheel::from_path(&wheel_file, &package_name)
-            .map_err(|e| WheelBuildError::Error(format!("Could not build wheel: {}", e)))?;
-
-        Ok(wheel)
+/// Converts config settings into a JSON object, or `None` if there are no config settings. A key
+/// that occurs multiple times is converted into a list of values.
+#[cfg(feature = "sdist")]
+fn config_settings_json(config_settings: &[(String, String)]) -> Option<String> {
+    if config_settings.is_empty() {
+        return None;
+    }
+
+    let mut settings = serde_json::Map::new();
+    for (key, value) in config_settings {
+        let value = serde_json::Value::String(value.clone());
+        match settings.get_mut(key) {
+            None => {
+                settings.insert(key.clone(), value);
+            }
+            Some(serde_json::Value::Array(values)) => values.push(value),
+            Some(existing) => *existing = serde_json::Value::Array(vec![existing.take(), value]),
+        }
+    }
+    Some(serde_json::Value::Object(settings).to_string())
+}
+
+/// Without the `sdist` feature wheels cannot be built and there are no cached wheels.
+#[cfg(not(feature = "sdist"))]
+impl WheelBuilder {
+    fn cached_wheel<S: ArtifactFromSource>(
+        &self,
+        _sdist: &S,
+    ) -> Result<Option<Wheel>, WheelBuildError> {
+        Ok(None)
+    }
+
+    async fn metadata_from_source<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<(Vec<u8>, WheelCoreMetadata), WheelBuildError> {
+        Err(WheelBuildError::SDistBuildingDisabled(
+            sdist.distribution_name(),
+        ))
+    }
+
+    async fn wheel_from_source<S: ArtifactFromSource>(
+        &self,
+        sdist: &S,
+    ) -> Result<Wheel, WheelBuildError> {
+        Err(WheelBuildError::SDistBuildingDisabled(
+            sdist.distribution_name(),
+        ))
+    }
+
+    async fn sdist_from_source(
+        &self,
+        stree: &STree,
+        _output_dir: &Path,
+    ) -> Result<PathBuf, WheelBuildError> {
+        Err(WheelBuildError::SDistBuildingDisabled(
+            stree.distribution_name(),
+        ))
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "sdist"))]
 mod tests {
     use crate::artifacts::SDist;
     use crate::index::{PackageDb, PackageSourcesBuilder};
@@ -598,4 +563,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    pub fn config_settings_json() {
+        let settings = [
+            ("--build-option", "--plat-name"),
+            ("editable_mode", "compat"),
+            ("--build-option", "win_amd64"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        assert_eq!(super::config_settings_json(&[]), None);
+        assert_eq!(
+            super::config_settings_json(&settings).unwrap(),
+            r#"{"--build-option":["--plat-name","win_amd64"],"editable_mode":"compat"}"#
+        );
+    }
 }
@@ -46,16 +46,25 @@ def get_backend_from_entry_point(entrypoint: str) -> ModuleType:
     return backend
 
 
+def get_config_settings():
+    """
+    Returns the config settings that are passed to the hooks of the build backend.
+    """
+    config_settings = os.environ.get("PEP517_CONFIG_SETTINGS")
+    if config_settings:
+        return loads(config_settings)
+    return None
+
 def get_requires_for_build_wheel(backend: ModuleType, work_dir: Path) -> [str]:
     """
     Returns a list of requirements. This is only necessary if we do not
     have a pyproject.toml file.
     """
-    f = getattr(backend, "get_requires_for_build_wheel")
+    f = getattr(backend, "get_requires_for_build_wheel", None)
     if f is None:
         result = []
     else:
-        result = f()
+        result = f(config_settings=get_config_settings())
 
     j = json.dumps(result)
     out_json_file = work_dir / "extra_requirements.json"
@@ -76,7 +85,10 @@ def prepare_metadata_for_build_wheel(backend: ModuleType, work_dir: Path):
         # Create the metadata output directory
         d = metadata_dirs(work_dir)
         d.mkdir()
-        dist_info = backend.prepare_metadata_for_build_wheel(str(d))
+        dist_info = backend.prepare_metadata_for_build_wheel(
+            str(d),
+            config_settings=get_config_settings(),
+        )
         # Path to the dist-info directory
         result = str(d / dist_info)
         # Write the path to the dist-info directory to a file
@@ -102,11 +114,28 @@ def build_wheel(backend: ModuleType, work_dir: Path):
     wheel_dir.mkdir()
     wheel_basename = backend.build_wheel(
         str(wheel_dir),
+        config_settings=get_config_settings(),
         metadata_directory=metadata_dir,
     )
 
     result_file.write_text(str(wheel_dir / wheel_basename))
 
+def sdist_dirs(work_dir: Path):
+    return work_dir / "sdist"
+
+def build_sdist(backend: ModuleType, work_dir: Path):
+    """Take a source tree and build an sdist from it."""
+    sdist_dir = sdist_dirs(work_dir)
+    result_file = work_dir / "sdist_result"
+
+    sdist_dir.mkdir()
+    sdist_basename = backend.build_sdist(
+        str(sdist_dir),
+        config_settings=get_config_settings(),
+    )
+
+    result_file.write_text(str(sdist_dir / sdist_basename))
+
 if __name__ == "__main__":
     work_dir, entry_point, goal = sys.argv[1:]
 
@@ -126,5 +155,7 @@ if __name__ == "__main__":
         prepare_metadata_for_build_wheel(backend, work_dir)
     elif goal == "Wheel":
         build_wheel(backend, work_dir)
+    elif goal == "SDist":
+        build_sdist(backend, work_dir)
 
     exit(0)
@@ -31,11 +31,13 @@ use crate::python_env::PythonInterpreterVersion;
 use crate::types::ArtifactFromSource;
 use crate::types::{ArtifactFromBytes, WheelFilename};
 use cacache::{Integrity, WriteOpts};
-use rattler_digest::Sha256;
+use rattler_digest::{digest::Digest, Sha256};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::io::{Cursor, Read};
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 
 /// Wrapper around an API built on top of cacache
 /// This is used to store wheels that are built from sdists
@@ -75,13 +77,27 @@ impl WheelCacheKey {
         Self(format!("{}:{}", prefix.as_ref(), key.as_ref()))
     }
 
+    /// Returns the key for a wheel that is built for a free-threaded interpreter. These wheels
+    /// are not compatible with regular builds of the same python version and vice versa.
+    pub fn free_threaded(self) -> Self {
+        Self(format!("{}t", self.0))
+    }
+
+    /// Returns the key for a wheel that is built with config settings for the build backend.
+    /// Different config settings can result in different wheels.
+    pub fn with_config_settings(self, config_settings: &str) -> Self {
+        let hash = rattler_digest::compute_bytes_digest::<Sha256>(config_settings);
+        Self(format!("{}:{:x}", self.0, hash))
+    }
+
     /// Create a WheelCacheKey from an sdist and the python interpreter version
     pub fn from_sdist(
         sdist: &impl ArtifactFromSource,
         python_interpreter_version: &PythonInterpreterVersion,
     ) -> Result<WheelCacheKey, std::io::Error> {
-        let hash = sdist.try_get_bytes()?;
-        let hash = rattler_digest::compute_bytes_digest::<Sha256>(&hash);
+        let mut hasher = Sha256::default();
+        sdist.write_bytes_to(&mut hasher)?;
+        let hash = hasher.finalize();
 
         // Hash python version
         Ok(WheelCacheKey::new(
@@ -94,7 +110,9 @@ impl WheelCacheKey {
     }
 }
 
+/// An error that can occur when accessing the [`WheelCache`].
 #[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
 pub enum WheelCacheError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -185,6 +203,67 @@ impl WheelCache {
             Ok(None)
         }
     }
+
+    /// Removes the wheels for which `predicate` returns true. The predicate is called with the
+    /// filename of the wheel and the time at which it was added to the cache. Returns the number
+    /// of removed entries.
+    pub fn remove_wheels(
+        &self,
+        mut predicate: impl FnMut(&WheelFilename, SystemTime) -> bool,
+    ) -> Result<usize, WheelCacheError> {
+        if !self.path.is_dir() {
+            return Ok(0);
+        }
+
+        let mut removed = Vec::new();
+        let mut retained = HashSet::new();
+        for entry in cacache::index::ls(&self.path) {
+            let entry = entry?;
+            let metadata: WheelKeyMetadata = serde_json::from_value(entry.metadata)?;
+            let time = SystemTime::UNIX_EPOCH + Duration::from_millis(entry.time as u64);
+            if predicate(&metadata.wheel_filename, time) {
+                removed.push((entry.key, metadata.integrity));
+            } else {
+                retained.insert(metadata.integrity);
+            }
+        }
+
+        // Multiple keys can refer to the same wheel, its contents are only removed when none of
+        // the retained keys refer to it.
+        let mut removed_contents = HashSet::new();
+        for (key, integrity) in &removed {
+            cacache::remove_sync(&self.path, key)?;
+            if !retained.contains(integrity) && removed_contents.insert(integrity) {
+                let integrity =
+                    Integrity::from_str(integrity).map_err(cacache::Error::IntegrityError)?;
+                cacache::remove_hash_sync(&self.path, &integrity)?;
+            }
+        }
+
+        Ok(removed.len())
+    }
+
+    /// Checks that the contents of every wheel in the cache match their recorded integrity.
+    /// Returns the filenames of the wheels that are corrupted or missing.
+    pub fn verify(&self) -> Result<Vec<WheelFilename>, WheelCacheError> {
+        let mut corrupted = Vec::new();
+        if !self.path.is_dir() {
+            return Ok(corrupted);
+        }
+
+        for entry in cacache::index::ls(&self.path) {
+            let metadata: WheelKeyMetadata = serde_json::from_value(entry?.metadata)?;
+            let valid = Integrity::from_str(&metadata.integrity)
+                .ok()
+                .map_or(false, |integrity| {
+                    cacache::read_hash_sync(&self.path, &integrity).is_ok()
+                });
+            if !valid {
+                corrupted.push(metadata.wheel_filename);
+            }
+        }
+        Ok(corrupted)
+    }
 }
 
 #[cfg(test)]
@@ -231,5 +310,11 @@ mod tests {
         cache.wheel_for_key(&key).unwrap().unwrap();
 
         assert_eq!(cache.wheels().count(), 1);
+        assert!(cache.verify().unwrap().is_empty());
+
+        // Removing the wheel also removes its contents
+        assert_eq!(cache.remove_wheels(|_, _| true).unwrap(), 1);
+        assert!(cache.wheel_for_key(&key).unwrap().is_none());
+        assert_eq!(cache.wheels().count(), 0);
     }
 }
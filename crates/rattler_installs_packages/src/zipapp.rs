@@ -0,0 +1,227 @@
+//! Bundles a resolved set of pure-python packages into a single executable
+//! [PEP 441](https://peps.python.org/pep-0441/) zipapp (`.pyz`).
+//!
+//! The zipapp contains the contents of the wheels that would end up in `site-packages` (including
+//! their `.dist-info` directories so `importlib.metadata` keeps working) and a `__main__.py` that
+//! invokes the selected entry point.
+
+use crate::artifacts::wheel::UnpackError;
+use crate::artifacts::Wheel;
+use crate::index::PackageDb;
+use crate::resolve::PinnedPackage;
+use crate::types::{EntryPoint, NormalizedPackageName, WheelFilename};
+use crate::wheel_builder::WheelBuilder;
+use fs_err as fs;
+use miette::Diagnostic;
+use std::collections::HashSet;
+use std::io::{Seek, Write};
+use std::path::Path;
+use thiserror::Error;
+use zip::{result::ZipError, write::FileOptions, ZipWriter};
+
+/// The interpreter that is used in the shebang of a zipapp if none is specified.
+pub const DEFAULT_ZIPAPP_INTERPRETER: &str = "/usr/bin/env python3";
+
+/// Determines which code is executed when the zipapp is run.
+#[derive(Debug, Clone)]
+pub enum ZipAppEntryPoint {
+    /// Run the console script with the given name as defined by one of the bundled packages.
+    ConsoleScript(String),
+
+    /// Run the specified entry point.
+    EntryPoint(EntryPoint),
+}
+
+/// Options to pass to [`build_zipapp`].
+#[derive(Debug, Clone)]
+pub struct ZipAppOptions {
+    /// The entry point of the zipapp
+    pub entry_point: ZipAppEntryPoint,
+
+    /// The interpreter to put in the shebang of the zipapp, e.g. `/usr/bin/env python3`. If this
+    /// is `None` no shebang is added.
+    pub interpreter: Option<String>,
+}
+
+impl ZipAppOptions {
+    /// Constructs options to run the console script with the given name using the
+    /// [`DEFAULT_ZIPAPP_INTERPRETER`].
+    pub fn for_console_script(script_name: impl Into<String>) -> Self {
+        Self {
+            entry_point: ZipAppEntryPoint::ConsoleScript(script_name.into()),
+            interpreter: Some(String::from(DEFAULT_ZIPAPP_INTERPRETER)),
+        }
+    }
+}
+
+/// An error that can occur when building a zipapp
+#[derive(Debug, Error, Diagnostic)]
+#[allow(missing_docs)]
+pub enum ZipAppError {
+    #[error("package '{0}' does not have any artifacts")]
+    NoArtifacts(NormalizedPackageName),
+
+    #[error("'{0}' is not a pure-python wheel and cannot be included in a zipapp")]
+    #[diagnostic(help("zipapps can only contain packages without compiled extensions"))]
+    NotPurePython(String),
+
+    #[error("none of the packages define a console script named '{0}'")]
+    ConsoleScriptNotFound(String),
+
+    #[error("failed to copy the contents of '{0}'")]
+    FailedToCopyWheel(String, #[source] Box<UnpackError>),
+
+    #[error("failed to write the zipapp")]
+    ZipError(#[from] ZipError),
+
+    #[error("failed to write the zipapp")]
+    IoError(#[from] std::io::Error),
+}
+
+/// Downloads (or builds) the wheels of the `packages` and bundles them into a zipapp at `output`.
+///
+/// Only pure-python wheels can be bundled in a zipapp, an error is returned for any package that
+/// requires a platform specific wheel.
+pub async fn build_zipapp(
+    package_db: &PackageDb,
+    packages: &[PinnedPackage],
+    wheel_builder: Option<&WheelBuilder>,
+    options: &ZipAppOptions,
+    output: &Path,
+) -> miette::Result<()> {
+    let mut wheels = Vec::with_capacity(packages.len());
+    for package in packages {
+        let artifact_info = package
+            .artifacts
+            .first()
+            .ok_or_else(|| ZipAppError::NoArtifacts(package.name.clone()))?;
+        let (wheel, _) = package_db.get_wheel(artifact_info, wheel_builder).await?;
+        wheels.push(wheel);
+    }
+
+    let file = fs::File::create(output).map_err(ZipAppError::IoError)?;
+    write_zipapp(&wheels, options, file)?;
+
+    // Make the zipapp executable
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if options.interpreter.is_some() {
+            fs::set_permissions(output, std::fs::Permissions::from_mode(0o755))
+                .map_err(ZipAppError::IoError)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a zipapp that contains the specified wheels to `writer`.
+pub fn write_zipapp<W: Write + Seek>(
+    wheels: &[Wheel],
+    options: &ZipAppOptions,
+    mut writer: W,
+) -> Result<(), ZipAppError> {
+    if let Some(interpreter) = &options.interpreter {
+        writeln!(writer, "#!{interpreter}")?;
+    }
+
+    let mut zip = ZipWriter::new(writer);
+    let mut written = HashSet::new();
+    let mut console_scripts = Vec::new();
+    for wheel in wheels {
+        if !is_pure_python(&wheel.name) {
+            return Err(ZipAppError::NotPurePython(wheel.name.to_string()));
+        }
+
+        let scripts = wheel
+            .copy_site_packages_to_zip(&mut zip, &mut written)
+            .map_err(|e| ZipAppError::FailedToCopyWheel(wheel.name.to_string(), Box::new(e)))?;
+        console_scripts.extend(scripts);
+    }
+
+    let entry_point = match &options.entry_point {
+        ZipAppEntryPoint::EntryPoint(entry_point) => entry_point.clone(),
+        ZipAppEntryPoint::ConsoleScript(name) => console_scripts
+            .into_iter()
+            .find(|script| &script.script_name == name)
+            .ok_or_else(|| ZipAppError::ConsoleScriptNotFound(name.clone()))?,
+    };
+
+    zip.start_file("__main__.py", FileOptions::default())?;
+    zip.write_all(entry_point.launch_script().as_bytes())?;
+    zip.finish()?;
+
+    Ok(())
+}
+
+/// Returns true if the wheel does not contain any platform specific code.
+fn is_pure_python(name: &WheelFilename) -> bool {
+    name.abi_tags.iter().all(|abi| abi == "none") && name.arch_tags.iter().all(|arch| arch == "any")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use zip::ZipArchive;
+
+    fn test_wheel(filename: &str, name: &str) -> Wheel {
+        Wheel::from_path(
+            &Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../test-data/wheels")
+                .join(filename),
+            &name.parse().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_write_zipapp() {
+        let wheel = test_wheel("miniblack-23.1.0-py3-none-any.whl", "miniblack");
+        let mut bytes = Cursor::new(Vec::new());
+        write_zipapp(
+            &[wheel],
+            &ZipAppOptions::for_console_script("black"),
+            &mut bytes,
+        )
+        .unwrap();
+
+        let bytes = bytes.into_inner();
+        assert!(bytes.starts_with(b"#!/usr/bin/env python3\n"));
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let names = archive.file_names().collect::<HashSet<_>>();
+        assert!(names.contains("black/__init__.py"));
+        assert!(names.contains("miniblack-23.1.0.dist-info/METADATA"));
+
+        let mut main = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("__main__.py").unwrap(), &mut main)
+            .unwrap();
+        assert!(main.contains("from black import patched_main"));
+    }
+
+    #[test]
+    fn test_missing_console_script() {
+        let wheel = test_wheel("miniblack-23.1.0-py3-none-any.whl", "miniblack");
+        let result = write_zipapp(
+            &[wheel],
+            &ZipAppOptions::for_console_script("white"),
+            Cursor::new(Vec::new()),
+        );
+        assert!(matches!(result, Err(ZipAppError::ConsoleScriptNotFound(_))));
+    }
+
+    #[test]
+    fn test_reject_platform_wheels() {
+        let wheel = test_wheel(
+            "purelib_and_platlib-1.0.0-cp38-cp38-linux_x86_64.whl",
+            "purelib_and_platlib",
+        );
+        let result = write_zipapp(
+            &[wheel],
+            &ZipAppOptions::for_console_script("pure"),
+            Cursor::new(Vec::new()),
+        );
+        assert!(matches!(result, Err(ZipAppError::NotPurePython(_))));
+    }
+}
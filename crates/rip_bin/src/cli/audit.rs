@@ -0,0 +1,126 @@
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::audit::{AuditPackage, AuditReport, Finding, OsvClient, Severity};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::lock::{Lockfile, LOCKFILE_NAME};
+use rattler_installs_packages::python_env::{find_distributions_in_venv, PythonInterpreterVersion};
+use reqwest::Client;
+use reqwest_middleware::ClientWithMiddleware;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The lockfile to audit
+    #[clap(long, short, default_value = LOCKFILE_NAME)]
+    lockfile: PathBuf,
+
+    /// Audit the packages that are installed in this virtual environment instead of a lockfile
+    #[clap(long)]
+    installed: Option<PathBuf>,
+
+    /// Only fail for vulnerabilities of at least this severity. By default every vulnerability
+    /// fails the audit, including those without a known severity. One of `low`, `medium`, `high`
+    /// or `critical`
+    #[clap(long)]
+    fail_on: Option<Severity>,
+}
+
+pub async fn audit(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let packages = match &args.installed {
+        Some(venv) => installed_packages(venv)?,
+        None => locked_packages(&args.lockfile)?,
+    };
+    let report = if packages.is_empty() {
+        AuditReport::default()
+    } else {
+        OsvClient::new(ClientWithMiddleware::from(Client::new()))
+            .with_cache_dir(package_db.cache_dir())
+            .audit(&packages)
+            .await
+            .into_diagnostic()?
+    };
+
+    match output_format() {
+        OutputFormat::Human if packages.is_empty() => println!(
+            "{}",
+            console::style("There are no packages to audit").bold()
+        ),
+        OutputFormat::Human => print_findings(&report.vulnerabilities, report.audited),
+        OutputFormat::Json => print_json("audit", &report)?,
+    }
+
+    let failing = report.findings_at_least(args.fail_on).count();
+    if failing > 0 {
+        miette::bail!("found {failing} vulnerabilities in the audited packages");
+    }
+    Ok(())
+}
+
+fn print_findings(findings: &[Finding], audited: usize) {
+    for finding in findings {
+        let fix = match &finding.fixed_version {
+            Some(fixed) => format!("upgrade to {fixed}"),
+            None => String::from("no fix available"),
+        };
+        println!(
+            "{} {} {} ({}{}, {})",
+            console::style(&finding.name).bold(),
+            finding.version,
+            console::style(&finding.id).red(),
+            finding.severity.map_or_else(
+                || String::from("unknown severity"),
+                |severity| severity.to_string()
+            ),
+            if finding.aliases.is_empty() {
+                String::new()
+            } else {
+                format!(", {}", finding.aliases.join(", "))
+            },
+            fix
+        );
+        if let Some(summary) = &finding.summary {
+            println!("  {summary}");
+        }
+    }
+
+    if findings.is_empty() {
+        println!(
+            "{}",
+            console::style(format!(
+                "No known vulnerabilities found in {audited} package(s)"
+            ))
+            .bold()
+            .green()
+        );
+    }
+}
+
+/// Returns the packages of all targets in the lockfile.
+fn locked_packages(lockfile: &Path) -> miette::Result<Vec<AuditPackage>> {
+    let lockfile = Lockfile::from_path(lockfile)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read {}", lockfile.display()))?;
+    Ok(AuditPackage::from_lockfile(&lockfile))
+}
+
+/// Returns the packages that are installed in the virtual environment at `venv`.
+fn installed_packages(venv: &Path) -> miette::Result<Vec<AuditPackage>> {
+    let python = venv.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", venv.display());
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let distributions =
+        find_distributions_in_venv(venv, &InstallPaths::for_venv(version, cfg!(windows)))
+            .into_diagnostic()?;
+    Ok(AuditPackage::from_distributions(&distributions))
+}
@@ -0,0 +1,148 @@
+use crate::build_config_settings;
+use clap::Parser;
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::STree;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::python_env::{Pep508EnvMakers, PythonLocation, WheelTags};
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::types::{project_requirement, PackageName, STreeFilename, Version};
+use rattler_installs_packages::wheel_builder::WheelBuilder;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The directory of the project to build
+    #[clap(default_value = ".")]
+    source: PathBuf,
+
+    /// Build an sdist. If neither `--sdist` nor `--wheel` is specified both are built
+    #[clap(long)]
+    sdist: bool,
+
+    /// Build a wheel. If neither `--sdist` nor `--wheel` is specified both are built
+    #[clap(long)]
+    wheel: bool,
+
+    /// The directory to write the built artifacts to
+    #[clap(long, short, default_value = "dist")]
+    outdir: PathBuf,
+
+    /// A setting that is passed to the build backend in the form `KEY=VALUE`, can be specified
+    /// multiple times
+    #[clap(long = "config-setting", short = 'C', value_parser = parse_config_setting)]
+    config_settings: Vec<(String, String)>,
+
+    /// Path to the python interpreter to build with
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+}
+
+/// Parses a `KEY=VALUE` config setting.
+fn parse_config_setting(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid config setting '{s}', expected KEY=VALUE"))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+pub async fn build(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let (build_sdist, build_wheel) = match (args.sdist, args.wheel) {
+        (false, false) => (true, true),
+        selected => selected,
+    };
+
+    let source = fs::canonicalize(&args.source)
+        .into_diagnostic()
+        .wrap_err("could not find the project to build")?;
+
+    // The real name and version of the project are determined by the build backend, the name from
+    // the `pyproject.toml` is only used for reporting.
+    let distribution = match project_requirement(&source, &[]) {
+        Ok(requirement) => PackageName::from_str(&requirement.name).into_diagnostic()?,
+        Err(_) => source
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| PackageName::from_str(name).ok())
+            .ok_or_else(|| {
+                miette::miette!("could not determine the name of {}", source.display())
+            })?,
+    };
+    let stree = STree::new(
+        STreeFilename {
+            distribution,
+            version: Version::from_str("0.0.0").expect("0.0.0 version should always be parseable"),
+            url: Url::from_directory_path(&source)
+                .expect("a canonicalized path is always absolute"),
+        },
+        source.clone(),
+    );
+
+    let python_location = match args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+    let python = python_location.executable().into_diagnostic()?;
+    let env_markers = Pep508EnvMakers::from_python(&python)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            format!(
+                "failed to determine environment markers (could not run Python in path: {})",
+                python.display()
+            )
+        })?
+        .0;
+    let wheel_tags = WheelTags::from_python(&python).await.into_diagnostic()?;
+
+    let wheel_builder = WheelBuilder::new(
+        package_db,
+        Arc::new(env_markers),
+        Some(Arc::new(wheel_tags)),
+        ResolveOptions {
+            python_location,
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .into_diagnostic()?
+    .with_config_settings(build_config_settings(args.config_settings));
+
+    fs::create_dir_all(&args.outdir).into_diagnostic()?;
+
+    if build_sdist {
+        let path = wheel_builder
+            .build_sdist(&stree, &args.outdir)
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to build an sdist from {}", source.display()))?;
+        println!(
+            "{} {}",
+            console::style("Built").bold().green(),
+            path.display()
+        );
+    }
+
+    if build_wheel {
+        // The sources of a local project might have changed since it was last built, so never
+        // reuse a cached wheel.
+        let wheel = wheel_builder
+            .rebuild_wheel(&stree)
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to build a wheel from {}", source.display()))?;
+        let path = args.outdir.join(wheel.name.to_string());
+        wheel.write_to(&path).into_diagnostic()?;
+        println!(
+            "{} {}",
+            console::style("Built").bold().green(),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,317 @@
+use crate::{output_format, print_json, OutputFormat};
+use clap::{Parser, Subcommand};
+use indicatif::HumanBytes;
+use miette::IntoDiagnostic;
+use rattler_installs_packages::cache::{cache_info, clean_cache, verify_cache, CleanOptions};
+use rattler_installs_packages::index::{PackageDb, WarmOptions, WarmRequest};
+use rattler_installs_packages::lock::Lockfile;
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use serde::Serialize;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Print the location of the cache directory
+    Dir,
+
+    /// Show the disk usage of the cache
+    Info,
+
+    /// Remove entries from the cache, without any options everything except the standalone
+    /// python interpreters and the wheel files that are linked into environments is removed
+    Clean {
+        /// Only remove entries that were last modified longer ago than this, e.g. `30d`, `12h`
+        /// or `2w`
+        #[clap(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+
+        /// Only remove the built and unpacked wheels of this package
+        #[clap(long)]
+        package: Option<PackageName>,
+
+        /// Also remove the files of wheels that are linked into environments, environments that
+        /// were installed with `--link-mode symlink` break when they are removed
+        #[clap(long)]
+        wheel_files: bool,
+    },
+
+    /// Check that the cached wheels have not been modified or corrupted
+    Verify,
+
+    /// Prefetch the index pages and metadata of packages into the cache, so later resolves are
+    /// fast and also work with `--offline`
+    Warm {
+        /// The packages to prefetch, the latest release of each is used
+        #[clap(required_unless_present = "lockfile", conflicts_with = "lockfile")]
+        packages: Vec<PackageName>,
+
+        /// Prefetch the locked packages of this lockfile instead
+        #[clap(long)]
+        lockfile: Option<PathBuf>,
+
+        /// Also download the wheels and sdists, so the packages can be installed offline
+        #[clap(long)]
+        artifacts: bool,
+
+        /// The maximum number of packages to prefetch at the same time
+        #[clap(long, default_value = "10")]
+        concurrency: NonZeroUsize,
+    },
+}
+
+/// The JSON output of `rip cache dir`.
+#[derive(Serialize)]
+struct DirOutput<'a> {
+    path: &'a Path,
+}
+
+/// The JSON output of `rip cache info`.
+#[derive(Serialize)]
+struct InfoOutput {
+    buckets: Vec<BucketOutput>,
+    files: u64,
+    /// The total size of the cache in bytes
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct BucketOutput {
+    name: &'static str,
+    path: PathBuf,
+    files: u64,
+    size: u64,
+}
+
+/// The JSON output of `rip cache clean`.
+#[derive(Serialize)]
+struct CleanOutput {
+    /// The number of removed files
+    files: u64,
+    /// The total size of the removed files in bytes
+    size: u64,
+}
+
+/// The JSON output of `rip cache verify`.
+#[derive(Serialize)]
+struct VerifyOutput {
+    corrupted: Vec<CorruptedOutput>,
+}
+
+#[derive(Serialize)]
+struct CorruptedOutput {
+    bucket: &'static str,
+    entry: String,
+}
+
+/// The JSON output of `rip cache warm`.
+#[derive(Serialize)]
+struct WarmOutput {
+    packages: usize,
+    metadata: usize,
+    artifacts: usize,
+}
+
+pub async fn cache(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let cache_dir = package_db.cache_dir();
+    match args.command {
+        Commands::Dir => match output_format() {
+            OutputFormat::Human => {
+                println!("{}", cache_dir.display());
+                Ok(())
+            }
+            OutputFormat::Json => print_json("cache dir", &DirOutput { path: cache_dir }),
+        },
+        Commands::Info => info(cache_dir),
+        Commands::Clean {
+            older_than,
+            package,
+            wheel_files,
+        } => {
+            let summary = clean_cache(
+                cache_dir,
+                &CleanOptions {
+                    older_than,
+                    package: package.map(NormalizedPackageName::from),
+                    wheel_files,
+                },
+            )
+            .into_diagnostic()?;
+            match output_format() {
+                OutputFormat::Human => {
+                    println!(
+                        "Removed {} files ({})",
+                        summary.files,
+                        console::style(HumanBytes(summary.size)).bold()
+                    );
+                    Ok(())
+                }
+                OutputFormat::Json => print_json(
+                    "cache clean",
+                    &CleanOutput {
+                        files: summary.files,
+                        size: summary.size,
+                    },
+                ),
+            }
+        }
+        Commands::Verify => {
+            let corrupted = verify_cache(cache_dir).into_diagnostic()?;
+            match output_format() {
+                OutputFormat::Human if corrupted.is_empty() => {
+                    println!("{}", console::style("The cache is intact").bold());
+                }
+                OutputFormat::Human => {
+                    for entry in &corrupted {
+                        println!(
+                            "{} {}",
+                            console::style(entry.bucket.dir_name()).red(),
+                            entry.entry
+                        );
+                    }
+                }
+                OutputFormat::Json => print_json(
+                    "cache verify",
+                    &VerifyOutput {
+                        corrupted: corrupted
+                            .iter()
+                            .map(|entry| CorruptedOutput {
+                                bucket: entry.bucket.dir_name(),
+                                entry: entry.entry.clone(),
+                            })
+                            .collect(),
+                    },
+                )?,
+            }
+            if corrupted.is_empty() {
+                return Ok(());
+            }
+            miette::bail!(
+                "found {} corrupted cache entries, remove them with `rip cache clean`",
+                corrupted.len()
+            )
+        }
+        Commands::Warm {
+            packages,
+            lockfile,
+            artifacts,
+            concurrency,
+        } => {
+            let request = match lockfile {
+                Some(path) => {
+                    WarmRequest::from_lockfile(&Lockfile::from_path(&path).into_diagnostic()?)
+                        .into_diagnostic()?
+                }
+                None => WarmRequest::Names(
+                    packages
+                        .into_iter()
+                        .map(NormalizedPackageName::from)
+                        .collect(),
+                ),
+            };
+            let summary = package_db
+                .warm(
+                    request,
+                    &WarmOptions::default()
+                        .with_concurrency(concurrency)
+                        .with_artifacts(artifacts),
+                )
+                .await?;
+            match output_format() {
+                OutputFormat::Human => {
+                    println!(
+                        "Warmed {} packages ({} metadata, {} artifacts)",
+                        console::style(summary.packages).bold(),
+                        summary.metadata,
+                        summary.artifacts
+                    );
+                    Ok(())
+                }
+                OutputFormat::Json => print_json(
+                    "cache warm",
+                    &WarmOutput {
+                        packages: summary.packages,
+                        metadata: summary.metadata,
+                        artifacts: summary.artifacts,
+                    },
+                ),
+            }
+        }
+    }
+}
+
+fn info(cache_dir: &Path) -> miette::Result<()> {
+    let buckets = cache_info(cache_dir).into_diagnostic()?;
+    if output_format() == OutputFormat::Json {
+        return print_json(
+            "cache info",
+            &InfoOutput {
+                files: buckets.iter().map(|bucket| bucket.files).sum(),
+                size: buckets.iter().map(|bucket| bucket.size).sum(),
+                buckets: buckets
+                    .into_iter()
+                    .map(|bucket| BucketOutput {
+                        name: bucket.bucket.dir_name(),
+                        path: bucket.path,
+                        files: bucket.files,
+                        size: bucket.size,
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    let mut tabbed_stdout = tabwriter::TabWriter::new(std::io::stdout());
+    for bucket in &buckets {
+        writeln!(
+            tabbed_stdout,
+            "{}\t{} files\t{}",
+            console::style(bucket.bucket.dir_name()).bold(),
+            bucket.files,
+            HumanBytes(bucket.size)
+        )
+        .into_diagnostic()?;
+    }
+    writeln!(
+        tabbed_stdout,
+        "{}\t{} files\t{}",
+        console::style("total").bold(),
+        buckets.iter().map(|bucket| bucket.files).sum::<u64>(),
+        HumanBytes(buckets.iter().map(|bucket| bucket.size).sum())
+    )
+    .into_diagnostic()?;
+    tabbed_stdout.flush().into_diagnostic()
+}
+
+/// Parses a duration like `90s`, `30m`, `12h`, `7d` or `2w`.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing a unit in '{s}', e.g. 7d"))?;
+    let (amount, unit) = s.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}'"))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unknown unit '{unit}', expected one of s, m, h, d or w"
+            ))
+        }
+    };
+    Ok(Duration::from_secs(amount * seconds))
+}
@@ -0,0 +1,245 @@
+use crate::build_config_settings;
+use crate::cli::lock::targets;
+use crate::cli::resolve::{
+    collect_requirements, pre_release_resolution, RequirementFileArgs, SDistResolutionArgs, Spec,
+};
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use fs_err as fs;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::{SDist, Wheel};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::python_env::{PlatformTag, PythonLocation};
+use rattler_installs_packages::resolve::resolve;
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::types::{ArtifactInfo, Version};
+use rattler_installs_packages::wheel_builder::WheelBuilder;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(num_args = 1.., required_unless_present = "requirement")]
+    /// The specs to download. A path to a project directory, optionally followed by extras like
+    /// `.[test]`, downloads the dependencies of the project
+    specs: Vec<Spec>,
+
+    #[clap(flatten)]
+    requirement_files: RequirementFileArgs,
+
+    /// The directory to download the artifacts into
+    #[clap(long, short, default_value = ".")]
+    dest: PathBuf,
+
+    /// The platform to download for, e.g. `manylinux_2_17_x86_64` or `win_amd64`. Can be
+    /// specified multiple times, defaults to the platform of the python interpreter
+    #[clap(long)]
+    platform: Vec<PlatformTag>,
+
+    /// The python version to download for, e.g. `3.11`. Defaults to the version of the python
+    /// interpreter
+    #[clap(long)]
+    python_version: Option<Version>,
+
+    /// Build wheels from the sdists of packages without a compatible wheel instead of downloading
+    /// the sdists. Wheels are built with the python interpreter, so this is only useful when
+    /// downloading for the platform of the interpreter
+    #[clap(long)]
+    build: bool,
+
+    /// How to handle SDists
+    #[clap(flatten)]
+    sdist_resolution: SDistResolutionArgs,
+
+    /// Path to the python interpreter to use for building sdists and to determine the default
+    /// target
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Prefer pre-releases to normal releases
+    #[clap(long)]
+    pre: bool,
+}
+
+/// The JSON output of `rip download`.
+#[derive(Serialize)]
+struct DownloadOutput {
+    /// The targets the requirements were resolved for
+    targets: Vec<String>,
+    /// The artifacts that were written to `dest`, artifacts shared by targets are listed once
+    artifacts: Vec<DownloadedArtifact>,
+    dest: PathBuf,
+}
+
+#[derive(Serialize)]
+struct DownloadedArtifact {
+    name: String,
+    version: String,
+    filename: String,
+    /// True if the wheel was built from an sdist with `--build`
+    built: bool,
+}
+
+pub async fn download(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let requirements = collect_requirements(&args.specs, &args.requirement_files.requirement)?;
+
+    let python_location = match args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+    let targets = targets(
+        &python_location,
+        &args.platform,
+        args.python_version.as_ref(),
+    )
+    .await?;
+
+    let pre_release_resolution = pre_release_resolution(args.pre, &requirements);
+    let resolve_options = ResolveOptions {
+        sdist_resolution: args.sdist_resolution.into(),
+        python_location,
+        pre_release_resolution,
+        ..Default::default()
+    };
+
+    fs::create_dir_all(&args.dest).into_diagnostic()?;
+
+    // Artifacts that are shared between targets are only downloaded once
+    let mut downloaded = HashSet::new();
+    let mut output = DownloadOutput {
+        targets: Vec::new(),
+        artifacts: Vec::new(),
+        dest: args.dest.clone(),
+    };
+    for target in targets {
+        let env_markers = Arc::new(target.env_markers().into_diagnostic()?.0);
+        let wheel_tags = Arc::new(target.wheel_tags());
+        let packages = resolve(
+            package_db.clone(),
+            &requirements,
+            env_markers.clone(),
+            Some(wheel_tags.clone()),
+            HashMap::default(),
+            HashMap::default(),
+            resolve_options.clone(),
+            HashMap::default(),
+        )
+        .await
+        .wrap_err_with(|| format!("could not resolve the requirements for {target}"))?;
+
+        if output_format() == OutputFormat::Human {
+            println!(
+                "{} {}",
+                console::style("Resolved").bold().green(),
+                console::style(&target).bold()
+            );
+        }
+        output.targets.push(target.to_string());
+
+        let wheel_builder = if args.build {
+            Some(
+                WheelBuilder::new(
+                    package_db.clone(),
+                    env_markers,
+                    Some(wheel_tags),
+                    resolve_options.clone(),
+                    Default::default(),
+                )
+                .into_diagnostic()?
+                .with_config_settings(build_config_settings(Vec::new())),
+            )
+        } else {
+            None
+        };
+
+        for package in packages.iter().sorted_by(|a, b| a.name.cmp(&b.name)) {
+            if package.url.is_some() {
+                tracing::warn!(
+                    "{} is a direct url requirement and is not downloaded",
+                    package.name
+                );
+                continue;
+            }
+
+            // The artifacts are ordered by compatibility, so prefer the first wheel
+            let artifact = package
+                .artifacts
+                .iter()
+                .find(|artifact| artifact.is::<Wheel>())
+                .or_else(|| {
+                    package
+                        .artifacts
+                        .iter()
+                        .find(|artifact| artifact.is::<SDist>())
+                })
+                .ok_or_else(|| {
+                    miette::miette!(
+                        "there are no artifacts to download for {} {}",
+                        package.name,
+                        package.version
+                    )
+                })?;
+            if !downloaded.insert(artifact.filename.to_string()) {
+                continue;
+            }
+
+            let sdist_builder = wheel_builder.as_ref().filter(|_| artifact.is::<SDist>());
+            let filename = match sdist_builder {
+                Some(wheel_builder) => {
+                    build_wheel(&package_db, wheel_builder, artifact, &args.dest).await?
+                }
+                None => {
+                    let filename = artifact.filename.to_string();
+                    package_db
+                        .download_artifact(artifact, &args.dest.join(&filename))
+                        .await
+                        .wrap_err_with(|| format!("failed to download {filename}"))?;
+                    filename
+                }
+            };
+            if output_format() == OutputFormat::Human {
+                println!("- {filename}");
+            }
+            output.artifacts.push(DownloadedArtifact {
+                name: package.name.to_string(),
+                version: package.version.to_string(),
+                filename,
+                built: sdist_builder.is_some(),
+            });
+        }
+    }
+
+    match output_format() {
+        OutputFormat::Human => {
+            println!(
+                "Downloaded {} artifact(s) to {}, use them with `--find-links {}`",
+                downloaded.len(),
+                console::style(args.dest.display()).bold(),
+                args.dest.display()
+            );
+            Ok(())
+        }
+        OutputFormat::Json => print_json("download", &output),
+    }
+}
+
+/// Builds a wheel from the sdist and writes it to the `dest` directory. Returns the filename of
+/// the wheel.
+async fn build_wheel(
+    package_db: &PackageDb,
+    wheel_builder: &WheelBuilder,
+    artifact: &ArtifactInfo,
+    dest: &Path,
+) -> miette::Result<String> {
+    let (wheel, _) = package_db
+        .get_wheel(artifact, Some(wheel_builder))
+        .await
+        .wrap_err_with(|| format!("failed to build a wheel from {}", artifact.filename))?;
+    let filename = wheel.name.to_string();
+    wheel.write_to(&dest.join(&filename)).into_diagnostic()?;
+    Ok(filename)
+}
@@ -0,0 +1,163 @@
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use fs_err as fs;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::index::{ArtifactRequest, PackageDb};
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, freeze_distributions, Distribution, PythonInterpreterVersion,
+};
+use rattler_installs_packages::resolve::PypiVersion;
+use rattler_installs_packages::types::format_requirement;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The virtual environment to freeze
+    target: PathBuf,
+
+    /// Do not include packages that are installed in editable mode
+    #[clap(long)]
+    exclude_editable: bool,
+
+    /// Include the sha256 hashes of the artifacts of every package, so the output can be
+    /// installed with `--require-hashes`. The hashes of packages from the index are retrieved from
+    /// the index
+    #[clap(long)]
+    hashes: bool,
+
+    /// Write the requirements to this file instead of printing them
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+/// The JSON output of `rip freeze`.
+#[derive(Serialize)]
+struct FreezeOutput {
+    packages: Vec<FrozenPackage>,
+    /// The requirements file that was written with `--output`
+    written: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct FrozenPackage {
+    name: String,
+    version: String,
+    /// The line of the package in the requirements file, without the hashes
+    requirement: String,
+    editable: bool,
+    hashes: Vec<String>,
+}
+
+pub async fn freeze(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let python = args.target.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", args.target.display());
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let mut distributions = find_distributions_in_venv(
+        &args.target,
+        &InstallPaths::for_venv(version, cfg!(windows)),
+    )
+    .into_diagnostic()?;
+    if args.exclude_editable {
+        distributions.retain(|distribution| !distribution.is_editable());
+    }
+
+    // The requirements are sorted by name, sort the distributions the same way so they line up
+    distributions.sort_by(|a, b| a.name.cmp(&b.name));
+    let requirements = freeze_distributions(&distributions)?;
+
+    let mut frozen = Vec::new();
+    for (distribution, requirement) in distributions.iter().zip(requirements) {
+        let requirement = match &distribution.direct_url {
+            Some(direct_url) if distribution.is_editable() => format!("-e {}", direct_url.url),
+            _ => format_requirement(&requirement),
+        };
+        let hashes = if args.hashes {
+            artifact_hashes(&package_db, distribution).await?
+        } else {
+            Vec::new()
+        };
+        frozen.push(FrozenPackage {
+            name: distribution.name.to_string(),
+            version: distribution.version.to_string(),
+            requirement,
+            editable: distribution.is_editable(),
+            hashes,
+        });
+    }
+
+    let mut output = String::new();
+    for package in &frozen {
+        output.push_str(&package.requirement);
+        for hash in &package.hashes {
+            write!(output, " \\\n    --hash=sha256:{hash}")
+                .expect("writing to a string cannot fail");
+        }
+        output.push('\n');
+    }
+
+    if let Some(path) = &args.output {
+        fs::write(path, &output)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write {}", path.display()))?;
+    }
+    match output_format() {
+        OutputFormat::Human => {
+            if args.output.is_none() {
+                print!("{output}");
+            }
+            Ok(())
+        }
+        OutputFormat::Json => print_json(
+            "freeze",
+            &FreezeOutput {
+                packages: frozen,
+                written: args.output,
+            },
+        ),
+    }
+}
+
+/// Returns the sha256 hashes of the artifacts the distribution could have been installed from.
+/// For a distribution from the index these are the hashes of all artifacts of its version, for an
+/// archive url it is the hash recorded in its `direct_url.json`.
+async fn artifact_hashes(
+    package_db: &PackageDb,
+    distribution: &Distribution,
+) -> miette::Result<Vec<String>> {
+    if let Some(direct_url) = &distribution.direct_url {
+        return Ok(direct_url
+            .archive_sha256()
+            .map(ToString::to_string)
+            .into_iter()
+            .collect());
+    }
+
+    let available = package_db
+        .available_artifacts(ArtifactRequest::FromIndex(distribution.name.clone()))
+        .await
+        .wrap_err_with(|| format!("failed to retrieve the artifacts of {}", distribution.name))?;
+    Ok(available
+        .iter()
+        .filter(|(version, _)| {
+            matches!(version, PypiVersion::Version { version, .. } if version == &distribution.version)
+        })
+        .flat_map(|(_, artifacts)| artifacts.iter())
+        .filter_map(|artifact| artifact.hashes.as_ref()?.sha256)
+        .map(|sha256| format!("{sha256:x}"))
+        .sorted()
+        .dedup()
+        .collect())
+}
@@ -0,0 +1,126 @@
+use crate::cli::lock::{print_target, targets, LockOutput};
+use crate::{output_format, print_json, OutputFormat};
+use clap::{Parser, ValueEnum};
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::lock::{
+    PipfileLock, PoetryLock, UvLock, LOCKFILE_NAME, PIPFILE_LOCK_NAME, POETRY_LOCK_NAME,
+    UV_LOCK_NAME,
+};
+use rattler_installs_packages::python_env::{PlatformTag, PythonLocation};
+use rattler_installs_packages::types::Version;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The lockfile of another tool to import, e.g. a `poetry.lock`, `uv.lock` or `Pipfile.lock`
+    path: PathBuf,
+
+    /// The format of the lockfile, detected from its file name by default
+    #[clap(long, value_enum)]
+    from: Option<ImportFormat>,
+
+    /// Also import the packages of the `develop` section of a `Pipfile.lock`
+    #[clap(long)]
+    dev: bool,
+
+    /// The lockfile to write
+    #[clap(long, short, default_value = LOCKFILE_NAME)]
+    output: PathBuf,
+
+    /// The platform to import the packages for, e.g. `manylinux_2_17_x86_64` or `win_amd64`. Can
+    /// be specified multiple times, defaults to the platform of the python interpreter
+    #[clap(long)]
+    platform: Vec<PlatformTag>,
+
+    /// The python version to import the packages for, e.g. `3.11`. Defaults to the version of the
+    /// python interpreter
+    #[clap(long)]
+    python_version: Option<Version>,
+
+    /// Path to the python interpreter that determines the default target
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    /// A `poetry.lock` file written by Poetry
+    Poetry,
+    /// A `uv.lock` file written by uv
+    Uv,
+    /// A `Pipfile.lock` file written by Pipenv
+    Pipfile,
+}
+
+impl ImportFormat {
+    fn detect(path: &Path) -> Option<Self> {
+        match path.file_name()?.to_str()? {
+            POETRY_LOCK_NAME => Some(ImportFormat::Poetry),
+            UV_LOCK_NAME => Some(ImportFormat::Uv),
+            PIPFILE_LOCK_NAME => Some(ImportFormat::Pipfile),
+            _ => None,
+        }
+    }
+}
+
+pub async fn import(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let Some(format) = args.from.or_else(|| ImportFormat::detect(&args.path)) else {
+        miette::bail!(
+            "cannot determine the format of {}, specify it with --from",
+            args.path.display()
+        );
+    };
+
+    let python_location = match args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+    let targets = targets(
+        &python_location,
+        &args.platform,
+        args.python_version.as_ref(),
+    )
+    .await?;
+
+    let lockfile = match format {
+        ImportFormat::Poetry => {
+            PoetryLock::from_path(&args.path)
+                .into_diagnostic()?
+                .to_lockfile(&package_db, &targets)
+                .await
+        }
+        ImportFormat::Pipfile => {
+            PipfileLock::from_path(&args.path)
+                .into_diagnostic()?
+                .with_develop(args.dev)
+                .to_lockfile(&package_db, &targets)
+                .await
+        }
+        ImportFormat::Uv => {
+            UvLock::from_path(&args.path)
+                .into_diagnostic()?
+                .to_lockfile(&package_db, &targets)
+                .await
+        }
+    }
+    .wrap_err_with(|| format!("failed to import {}", args.path.display()))?;
+    lockfile.to_path(&args.output).into_diagnostic()?;
+
+    let mut output = LockOutput::new(&lockfile)?;
+    output.written.push(args.output);
+    match output_format() {
+        OutputFormat::Human => {
+            for locked in &lockfile.targets {
+                print_target(&locked.target, &locked.pinned_packages().into_diagnostic()?);
+            }
+            for path in &output.written {
+                println!("Wrote {}", console::style(path.display()).bold());
+            }
+            Ok(())
+        }
+        OutputFormat::Json => print_json("import", &output),
+    }
+}
@@ -0,0 +1,128 @@
+use crate::cli::sbom::{directory_name, installed_sbom, locked_packages};
+use crate::{global_config, output_format, print_json, OutputFormat};
+use clap::Parser;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::license_policy::{LicensePolicy, LicenseViolation};
+use rattler_installs_packages::lock::LOCKFILE_NAME;
+use rattler_installs_packages::resolve::PinnedPackage;
+use rattler_installs_packages::sbom::Sbom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The lockfile to check
+    #[clap(long, short, default_value = LOCKFILE_NAME)]
+    lockfile: PathBuf,
+
+    /// Check the packages that are installed in this virtual environment instead of a lockfile
+    #[clap(long)]
+    installed: Option<PathBuf>,
+
+    /// Allow a license in addition to the `allow-licenses` of the configuration, can be
+    /// specified multiple times
+    #[clap(long = "allow", value_name = "LICENSE")]
+    allow: Vec<String>,
+
+    /// Deny a license in addition to the `deny-licenses` of the configuration, can be specified
+    /// multiple times
+    #[clap(long = "deny", value_name = "LICENSE")]
+    deny: Vec<String>,
+}
+
+pub async fn licenses(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let policy = args.allow.into_iter().fold(
+        global_config().policy.license_policy(),
+        LicensePolicy::with_allowed,
+    );
+    let policy = args
+        .deny
+        .into_iter()
+        .fold(policy, LicensePolicy::with_denied);
+    if policy.is_empty() {
+        miette::bail!(
+            "there is no license policy, configure `allow-licenses` or `deny-licenses` in the \
+            [policy] table or pass --allow or --deny"
+        );
+    }
+
+    let sbom = match &args.installed {
+        Some(venv) => installed_sbom(directory_name(venv), venv).await?,
+        None => {
+            let name = directory_name(args.lockfile.parent().unwrap_or(Path::new(".")));
+            let packages = locked_packages(&args.lockfile)?;
+            Sbom::from_resolution(name, &package_db, &packages).await
+        }
+    };
+    let report = policy.check(&sbom);
+
+    match output_format() {
+        OutputFormat::Human => {
+            for violation in &report.violations {
+                println!("{}", format_violation(violation));
+            }
+            if report.violations.is_empty() {
+                println!(
+                    "{}",
+                    console::style(format!(
+                        "The licenses of {} package(s) comply with the license policy",
+                        report.checked
+                    ))
+                    .bold()
+                    .green()
+                );
+            }
+        }
+        OutputFormat::Json => print_json("licenses", &report)?,
+    }
+
+    if !report.violations.is_empty() {
+        miette::bail!(
+            "{} package(s) violate the license policy",
+            report.violations.len()
+        );
+    }
+    Ok(())
+}
+
+/// Checks the licenses of `packages` against the license policy of the configuration before they
+/// are installed. Violations are printed as warnings, and fail the installation if the policy is
+/// enforced.
+pub(crate) async fn check_license_policy(
+    package_db: &PackageDb,
+    packages: &[PinnedPackage],
+) -> miette::Result<()> {
+    let policy = global_config().policy.license_policy();
+    if policy.is_empty() {
+        return Ok(());
+    }
+
+    let report = policy.check(&Sbom::from_resolution("", package_db, packages).await);
+    if report.violations.is_empty() {
+        return Ok(());
+    }
+    eprintln!(
+        "{}: the licenses of these packages violate the license policy:",
+        console::style("warning").yellow().bold()
+    );
+    for violation in &report.violations {
+        eprintln!("  {}", format_violation(violation));
+    }
+    if policy.is_enforced() {
+        miette::bail!(
+            "{} package(s) violate the license policy",
+            report.violations.len()
+        );
+    }
+    Ok(())
+}
+
+fn format_violation(violation: &LicenseViolation) -> String {
+    format!(
+        "{} {} ({})",
+        console::style(&violation.name).bold(),
+        violation.version,
+        console::style(&violation.reason).red()
+    )
+}
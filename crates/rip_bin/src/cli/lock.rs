@@ -0,0 +1,475 @@
+use crate::cli::resolve::{pre_release_resolution, RequirementFileArgs, SDistResolutionArgs};
+use crate::{output_format, print_json, OutputFormat};
+use clap::{ArgGroup, Parser, ValueEnum};
+use fs_err as fs;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::lock::{
+    signature_path, LockTarget, Lockfile, SecretKey, LOCKFILE_NAME, UV_LOCK_NAME,
+};
+use rattler_installs_packages::python_env::{PlatformTag, PythonLocation, WheelTagTarget};
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::resolve::{resolve, PinnedPackage};
+use rattler_installs_packages::types::{
+    dependency_group_requirements, project_requirement, project_requirements,
+    read_requirements_file, Extra, NormalizedPackageName, PackageName, Requirement, Version,
+    Workspace,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The file name of a lockfile in the `pylock.toml` format.
+const PYLOCK_NAME: &str = "pylock.toml";
+
+/// The file name of the pip packages of a conda-lock lockfile.
+const CONDA_LOCK_PIP_NAME: &str = "conda-lock.pip.yml";
+
+/// The file name of a pinned requirements file.
+const REQUIREMENTS_TXT_NAME: &str = "requirements.txt";
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("project").args(["pyproject", "workspace"])))]
+pub struct Args {
+    /// The specs to lock, in addition to the ones read from requirements files or pyproject.toml
+    specs: Vec<Requirement>,
+
+    #[clap(flatten)]
+    requirement_files: RequirementFileArgs,
+
+    /// Read the dependencies of the project from a pyproject.toml file
+    #[clap(long)]
+    pyproject: Option<PathBuf>,
+
+    /// Lock the members of the workspace that is described by the `[tool.rip.workspace]` table
+    /// of this pyproject.toml file together. Requirements of the members on each other resolve to
+    /// their local source trees
+    #[clap(long)]
+    workspace: Option<PathBuf>,
+
+    /// Include the optional dependencies of an extra of the project, or of every workspace member
+    /// that declares it. Can be specified multiple times
+    #[clap(long, requires = "project")]
+    extra: Vec<Extra>,
+
+    /// Include the requirements of a dependency group of the project, or of every project in the
+    /// workspace that declares it. Can be specified multiple times
+    #[clap(long, requires = "project")]
+    group: Vec<Extra>,
+
+    /// The lockfile to write, defaults to `rip.lock`, `pylock.toml`, `uv.lock`,
+    /// `conda-lock.pip.yml` or `requirements.txt` depending on the format
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// The format of the lockfile. With `both` the pylock.toml file is written next to the output
+    #[clap(long, value_enum, default_value_t = LockFormat::Native)]
+    lock_format: LockFormat,
+
+    /// The platform to lock for, e.g. `manylinux_2_17_x86_64` or `win_amd64`. Can be specified
+    /// multiple times, defaults to the platform of the python interpreter
+    #[clap(long)]
+    platform: Vec<PlatformTag>,
+
+    /// The python version to lock for, e.g. `3.11`. Defaults to the version of the python
+    /// interpreter
+    #[clap(long)]
+    python_version: Option<Version>,
+
+    /// Ignore the versions in an existing lockfile and lock the newest versions of all packages
+    #[clap(long, short = 'U')]
+    upgrade: bool,
+
+    /// Lock the newest version of a specific package, can be specified multiple times
+    #[clap(long, short = 'P')]
+    upgrade_package: Vec<PackageName>,
+
+    /// How to handle SDists
+    #[clap(flatten)]
+    sdist_resolution: SDistResolutionArgs,
+
+    /// Path to the python interpreter to use for building sdists and to determine the default
+    /// target
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Prefer pre-releases to normal releases
+    #[clap(long)]
+    pre: bool,
+
+    /// Sign the lockfile with this minisign secret key, the signature is written next to the
+    /// lockfile with a `.minisig` extension. The key must not be protected by a password
+    #[clap(long)]
+    sign: Option<PathBuf>,
+}
+
+/// The JSON output of `rip lock` and `rip import`.
+#[derive(Serialize)]
+pub(crate) struct LockOutput {
+    targets: Vec<LockedTarget>,
+    pub(crate) written: Vec<PathBuf>,
+}
+
+impl LockOutput {
+    pub(crate) fn new(lockfile: &Lockfile) -> miette::Result<Self> {
+        Ok(Self {
+            targets: lockfile
+                .targets
+                .iter()
+                .map(|locked| {
+                    Ok(LockedTarget {
+                        target: locked.target.to_string(),
+                        packages: locked
+                            .pinned_packages()
+                            .into_diagnostic()?
+                            .iter()
+                            .sorted_by(|a, b| a.name.cmp(&b.name))
+                            .map(LockedPackage::from)
+                            .collect(),
+                    })
+                })
+                .collect::<miette::Result<_>>()?,
+            written: Vec::new(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct LockedTarget {
+    target: String,
+    packages: Vec<LockedPackage>,
+}
+
+#[derive(Serialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+impl From<&PinnedPackage> for LockedPackage {
+    fn from(package: &PinnedPackage) -> Self {
+        Self {
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum LockFormat {
+    /// The native `rip.lock` format
+    Native,
+    /// The standardized `pylock.toml` format (PEP 751)
+    Pylock,
+    /// Both formats
+    Both,
+    /// The `uv.lock` format of uv, requires `--pyproject`
+    Uv,
+    /// The pip packages of a conda-lock lockfile, one platform per target
+    CondaLock,
+    /// A pinned `requirements.txt` with hashes and `# via` annotations like pip-compile writes,
+    /// for a single target
+    Requirements,
+}
+
+pub async fn lock(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    // Gather all requirements, and the files they were read from to annotate requirements files
+    let mut requirements = args.specs.clone();
+    let mut origins: HashMap<NormalizedPackageName, Vec<String>> = HashMap::new();
+    let mut add_origin = |requirements: &[Requirement], origin: &str| {
+        for requirement in requirements {
+            if let Ok(name) = NormalizedPackageName::from_str(&requirement.name) {
+                let origins = origins.entry(name).or_default();
+                if !origins.iter().any(|existing| existing == origin) {
+                    origins.push(origin.to_string());
+                }
+            }
+        }
+    };
+    for path in &args.requirement_files.requirement {
+        let file_requirements = read_requirements_file(path).into_diagnostic()?;
+        add_origin(&file_requirements, &format!("-r {}", path.display()));
+        requirements.extend(file_requirements);
+    }
+    if let Some(pyproject) = &args.pyproject {
+        let mut project = project_requirements(pyproject, &args.extra).into_diagnostic()?;
+        project.extend(dependency_group_requirements(pyproject, &args.group).into_diagnostic()?);
+        let origin = match project_name(pyproject) {
+            Ok(name) => format!("{} ({})", name.as_str(), pyproject.display()),
+            Err(_) => pyproject.display().to_string(),
+        };
+        add_origin(&project, &origin);
+        requirements.extend(project);
+    }
+    let workspace = match &args.workspace {
+        Some(pyproject) => Some(Workspace::from_path(pyproject).into_diagnostic()?),
+        None => None,
+    };
+    if let Some(workspace) = &workspace {
+        let members = workspace
+            .requirements(&args.extra, &args.group)
+            .into_diagnostic()?;
+        add_origin(
+            &members,
+            &format!("workspace ({})", workspace.pyproject().display()),
+        );
+        requirements.extend(members);
+    }
+    if requirements.is_empty() {
+        miette::bail!("no requirements to lock, specify them on the command line, with --requirement, with --pyproject or with --workspace");
+    }
+    if args.sign.is_some() && !matches!(args.lock_format, LockFormat::Native | LockFormat::Both) {
+        miette::bail!("only the native lockfile format can be signed");
+    }
+    let secret_key = match &args.sign {
+        Some(path) => Some(
+            fs::read_to_string(path)
+                .into_diagnostic()?
+                .parse::<SecretKey>()
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to read the secret key {}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    // A uv.lock file describes a project, the name of the project is its virtual root package
+    let uv_project = match (args.lock_format, &args.pyproject) {
+        (LockFormat::Uv, Some(pyproject)) => Some(project_name(pyproject)?),
+        (LockFormat::Uv, None) => miette::bail!("the uv.lock format requires --pyproject"),
+        _ => None,
+    };
+
+    let python_location = match args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+
+    // Determine the targets to lock for, defaults to the current interpreter
+    let targets = targets(
+        &python_location,
+        &args.platform,
+        args.python_version.as_ref(),
+    )
+    .await?;
+    if args.lock_format == LockFormat::Requirements && targets.len() != 1 {
+        miette::bail!("the requirements format can only be written for a single target");
+    }
+
+    let (native_path, export_path) = match (args.lock_format, args.output) {
+        (LockFormat::Native, output) => (output.unwrap_or(LOCKFILE_NAME.into()), None),
+        (LockFormat::Pylock, output) => (
+            PathBuf::from(LOCKFILE_NAME),
+            Some(output.unwrap_or(PYLOCK_NAME.into())),
+        ),
+        (LockFormat::Both, output) => {
+            let native_path = output.unwrap_or(LOCKFILE_NAME.into());
+            let pylock_path = native_path.with_file_name(PYLOCK_NAME);
+            (native_path, Some(pylock_path))
+        }
+        (LockFormat::Uv, output) => (
+            PathBuf::from(LOCKFILE_NAME),
+            Some(output.unwrap_or(UV_LOCK_NAME.into())),
+        ),
+        (LockFormat::CondaLock, output) => (
+            PathBuf::from(LOCKFILE_NAME),
+            Some(output.unwrap_or(CONDA_LOCK_PIP_NAME.into())),
+        ),
+        (LockFormat::Requirements, output) => (
+            PathBuf::from(LOCKFILE_NAME),
+            Some(output.unwrap_or(REQUIREMENTS_TXT_NAME.into())),
+        ),
+    };
+
+    // Read the existing lockfile to keep the versions that are already locked
+    let existing = if native_path.exists() && !args.upgrade {
+        Some(Lockfile::from_path(&native_path).into_diagnostic()?)
+    } else {
+        None
+    };
+    let upgrade = args
+        .upgrade_package
+        .iter()
+        .cloned()
+        .map(NormalizedPackageName::from)
+        .collect::<HashSet<_>>();
+
+    let pre_release_resolution = pre_release_resolution(args.pre, &requirements);
+    let resolve_options = ResolveOptions {
+        sdist_resolution: args.sdist_resolution.into(),
+        python_location,
+        pre_release_resolution,
+        local_packages: workspace
+            .as_ref()
+            .map(Workspace::local_packages)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let index_url = package_db.sources().default_index_url();
+    let mut lockfile = resolve_lockfile(
+        package_db,
+        requirements,
+        targets,
+        existing.as_ref(),
+        &upgrade,
+        &resolve_options,
+    )
+    .await?;
+    let workspace_inputs = workspace.as_ref().map(Workspace::pyprojects);
+    for input in args
+        .requirement_files
+        .requirement
+        .iter()
+        .chain(&args.pyproject)
+        .chain(workspace_inputs.iter().flatten())
+    {
+        lockfile.add_input(input, &native_path).into_diagnostic()?;
+    }
+    let mut output = LockOutput::new(&lockfile)?;
+
+    // Write the lockfile in the requested formats
+    if matches!(args.lock_format, LockFormat::Native | LockFormat::Both) {
+        let signature = match &secret_key {
+            Some(secret_key) => {
+                lockfile
+                    .to_path_signed(&native_path, secret_key)
+                    .into_diagnostic()?;
+                Some(signature_path(&native_path))
+            }
+            None => {
+                lockfile.to_path(&native_path).into_diagnostic()?;
+                None
+            }
+        };
+        output.written.push(native_path);
+        output.written.extend(signature);
+    }
+    if let Some(export_path) = export_path {
+        let exported = match (args.lock_format, &uv_project) {
+            (LockFormat::Uv, Some(project)) => lockfile.to_uv_lock(project, &index_url),
+            (LockFormat::CondaLock, _) => lockfile.to_conda_lock_pip(),
+            (LockFormat::Requirements, _) => Ok(lockfile.targets[0].to_requirements_txt(&origins)),
+            _ => lockfile.to_pylock(),
+        }
+        .into_diagnostic()?;
+        fs::write(&export_path, exported).into_diagnostic()?;
+        output.written.push(export_path);
+    }
+
+    match output_format() {
+        OutputFormat::Human => {
+            for path in &output.written {
+                println!("Wrote {}", console::style(path.display()).bold());
+            }
+            Ok(())
+        }
+        OutputFormat::Json => print_json("lock", &output),
+    }
+}
+
+/// Reads the name of the project from its `pyproject.toml` file.
+fn project_name(pyproject: &Path) -> miette::Result<NormalizedPackageName> {
+    let project_dir = pyproject
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let requirement = project_requirement(project_dir, &[]).into_diagnostic()?;
+    NormalizedPackageName::from_str(&requirement.name).into_diagnostic()
+}
+
+/// Resolves the `requirements` for every target and returns the lockfile. The versions in the
+/// `existing` lockfile are preferred, except for the packages in `upgrade`.
+pub(crate) async fn resolve_lockfile(
+    package_db: Arc<PackageDb>,
+    requirements: Vec<Requirement>,
+    targets: Vec<LockTarget>,
+    existing: Option<&Lockfile>,
+    upgrade: &HashSet<NormalizedPackageName>,
+    resolve_options: &ResolveOptions,
+) -> miette::Result<Lockfile> {
+    let mut lockfile = Lockfile::new(requirements.clone());
+    for target in targets {
+        let favored_packages = match existing.and_then(|lock| lock.target(&target)) {
+            Some(locked) => locked
+                .pinned_packages()
+                .into_diagnostic()?
+                .into_iter()
+                .filter(|package| !upgrade.contains(&package.name))
+                .map(|package| (package.name.clone(), package))
+                .collect(),
+            None => HashMap::default(),
+        };
+
+        let env_markers = Arc::new(target.env_markers().into_diagnostic()?.0);
+        let packages = resolve(
+            package_db.clone(),
+            &requirements,
+            env_markers,
+            Some(Arc::new(target.wheel_tags())),
+            HashMap::default(),
+            favored_packages,
+            resolve_options.clone(),
+            HashMap::default(),
+        )
+        .await
+        .wrap_err_with(|| format!("could not lock the requirements for {target}"))?;
+
+        if output_format() == OutputFormat::Human {
+            print_target(&target, &packages);
+        }
+        lockfile.add_target(target, &packages);
+    }
+    Ok(lockfile)
+}
+
+/// Returns the combinations of the `platforms` and the `python_version`. Without platforms or
+/// without a python version, the platform or version of the python interpreter is used.
+pub(crate) async fn targets(
+    python_location: &PythonLocation,
+    platforms: &[PlatformTag],
+    python_version: Option<&Version>,
+) -> miette::Result<Vec<LockTarget>> {
+    if let (false, Some(python_version)) = (platforms.is_empty(), python_version) {
+        return Ok(platforms
+            .iter()
+            .map(|platform| LockTarget::new(python_version.clone(), platform.clone()))
+            .collect());
+    }
+
+    let python = python_location.executable().into_diagnostic()?;
+    let host = WheelTagTarget::from_python(&python)
+        .await
+        .into_diagnostic()
+        .wrap_err("failed to determine the platform of the python interpreter")?;
+    let python_version = python_version.cloned().unwrap_or_else(|| {
+        Version::from_str(&format!(
+            "{}.{}",
+            host.python_version.0, host.python_version.1
+        ))
+        .expect("a version formatted from integers is always valid")
+    });
+    let platforms = if platforms.is_empty() {
+        vec![host.platform]
+    } else {
+        platforms.to_vec()
+    };
+    Ok(platforms
+        .into_iter()
+        .map(|platform| LockTarget::new(python_version.clone(), platform))
+        .collect())
+}
+
+pub(crate) fn print_target(target: &LockTarget, packages: &[PinnedPackage]) {
+    println!(
+        "{} {}",
+        console::style("Locked").bold().green(),
+        console::style(target).bold()
+    );
+    for package in packages.iter().sorted_by(|a, b| a.name.cmp(&b.name)) {
+        println!("- {} {}", package.name.as_str(), package.version);
+    }
+}
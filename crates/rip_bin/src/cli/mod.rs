@@ -1,3 +1,41 @@
 pub mod resolve;
 
 pub mod wheels;
+
+pub mod tools;
+
+pub mod lock;
+
+pub mod import;
+
+pub mod sync;
+
+pub mod venv;
+
+pub mod cache;
+
+pub mod tree;
+
+pub mod show;
+
+pub mod download;
+
+pub mod build;
+
+pub mod uninstall;
+
+pub mod freeze;
+
+pub mod audit;
+
+pub mod why;
+
+pub mod search;
+
+pub mod run;
+
+pub mod project;
+
+pub mod sbom;
+
+pub mod licenses;
@@ -0,0 +1,225 @@
+use crate::cli::lock::{resolve_lockfile, targets};
+use crate::cli::resolve::pre_release_resolution;
+use crate::cli::sync::sync_environment;
+use clap::Parser;
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::install::InstallOptions;
+use rattler_installs_packages::lock::{Lockfile, LOCKFILE_NAME};
+use rattler_installs_packages::python_env::PythonLocation;
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::types::{
+    dependency_group_requirements, format_requirement, project_requirements, DependencyList, Extra,
+    NormalizedPackageName, PackageName, PyProjectEditor, Requirement,
+};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct AddArgs {
+    /// The requirements to add, a requirement on a package that is already a dependency replaces
+    /// it
+    #[clap(num_args = 1.., required = true)]
+    requirements: Vec<Requirement>,
+
+    #[clap(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct RemoveArgs {
+    /// The packages to remove
+    #[clap(num_args = 1.., required = true)]
+    packages: Vec<PackageName>,
+
+    #[clap(flatten)]
+    project: ProjectArgs,
+}
+
+#[derive(Parser)]
+pub struct ProjectArgs {
+    /// Edit the optional dependencies of an extra instead of the project dependencies
+    #[clap(long, conflicts_with = "group")]
+    optional: Option<Extra>,
+
+    /// Edit a dependency group (PEP 735) instead of the project dependencies
+    #[clap(long)]
+    group: Option<Extra>,
+
+    /// The directory of the project that contains the pyproject.toml file
+    #[clap(long, default_value = ".")]
+    project: PathBuf,
+
+    /// The lockfile to update, defaults to `rip.lock` in the project directory
+    #[clap(long, short)]
+    lockfile: Option<PathBuf>,
+
+    /// Synchronize the virtual environment at this path with the updated lockfile
+    #[clap(long)]
+    sync: Option<PathBuf>,
+
+    /// Path to the python interpreter to use for building sdists and to determine the platform to
+    /// lock for if there is no lockfile yet
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+}
+
+impl ProjectArgs {
+    fn dependency_list(&self) -> DependencyList {
+        match (&self.optional, &self.group) {
+            (Some(extra), _) => DependencyList::Optional(extra.clone()),
+            (None, Some(group)) => DependencyList::Group(group.clone()),
+            (None, None) => DependencyList::Project,
+        }
+    }
+}
+
+pub async fn add(package_db: Arc<PackageDb>, args: AddArgs) -> miette::Result<()> {
+    let pyproject = args.project.project.join("pyproject.toml");
+    let mut editor = PyProjectEditor::from_path(&pyproject).into_diagnostic()?;
+    let list = args.project.dependency_list();
+
+    let mut upgrade = HashSet::new();
+    for requirement in &args.requirements {
+        match editor
+            .add_dependency(requirement, &list)
+            .into_diagnostic()?
+        {
+            Some(existing) => println!(
+                "{} {} -> {}",
+                console::style("~").yellow(),
+                format_requirement(&existing),
+                console::style(format_requirement(requirement)).bold()
+            ),
+            None => println!(
+                "{} {}",
+                console::style("+").green(),
+                console::style(format_requirement(requirement)).bold()
+            ),
+        }
+
+        // The locked version of an added package may not satisfy the new requirement
+        if let Ok(name) = PackageName::from_str(&requirement.name) {
+            upgrade.insert(NormalizedPackageName::from(name));
+        }
+    }
+
+    update_project(package_db, &args.project, &editor, &upgrade).await
+}
+
+pub async fn remove(package_db: Arc<PackageDb>, args: RemoveArgs) -> miette::Result<()> {
+    let pyproject = args.project.project.join("pyproject.toml");
+    let mut editor = PyProjectEditor::from_path(&pyproject).into_diagnostic()?;
+    let list = args.project.dependency_list();
+
+    for package in args.packages {
+        let name = NormalizedPackageName::from(package);
+        let Some(removed) = editor.remove_dependency(&name, &list).into_diagnostic()? else {
+            miette::bail!("{name} is not in {list} of {}", pyproject.display());
+        };
+        println!(
+            "{} {}",
+            console::style("-").red(),
+            console::style(format_requirement(&removed)).bold()
+        );
+    }
+
+    update_project(package_db, &args.project, &editor, &HashSet::new()).await
+}
+
+/// Writes the edited pyproject.toml, locks the requirements of the project with all of its extras
+/// and dependency groups and synchronizes the environment if requested. The pyproject.toml is
+/// restored if the requirements cannot be locked.
+async fn update_project(
+    package_db: Arc<PackageDb>,
+    args: &ProjectArgs,
+    editor: &PyProjectEditor,
+    upgrade: &HashSet<NormalizedPackageName>,
+) -> miette::Result<()> {
+    let pyproject = editor.path();
+    let original = fs::read_to_string(pyproject).into_diagnostic()?;
+    editor.write().into_diagnostic()?;
+
+    let lockfile_path = args
+        .lockfile
+        .clone()
+        .unwrap_or_else(|| args.project.join(LOCKFILE_NAME));
+    let python_location = match &args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter.clone()),
+        None => PythonLocation::System,
+    };
+
+    let lock = async {
+        let mut requirements =
+            project_requirements(pyproject, &editor.extras()).into_diagnostic()?;
+        requirements.extend(
+            dependency_group_requirements(pyproject, &editor.dependency_groups())
+                .into_diagnostic()?,
+        );
+
+        // Lock for the same targets as before, or for the interpreter if there is no lockfile
+        let existing = if lockfile_path.exists() {
+            Some(Lockfile::from_path(&lockfile_path).into_diagnostic()?)
+        } else {
+            None
+        };
+        let targets = match &existing {
+            Some(existing) if !existing.targets.is_empty() => existing
+                .targets
+                .iter()
+                .map(|locked| locked.target.clone())
+                .collect(),
+            _ => targets(&python_location, &[], None).await?,
+        };
+
+        let resolve_options = ResolveOptions {
+            python_location: python_location.clone(),
+            pre_release_resolution: pre_release_resolution(false, &requirements),
+            ..Default::default()
+        };
+        resolve_lockfile(
+            package_db.clone(),
+            requirements,
+            targets,
+            existing.as_ref(),
+            upgrade,
+            &resolve_options,
+        )
+        .await
+    };
+    let mut lockfile = match lock.await {
+        Ok(lockfile) => lockfile,
+        Err(err) => {
+            fs::write(pyproject, original).into_diagnostic()?;
+            return Err(err).wrap_err(format!("{} was left unchanged", pyproject.display()));
+        }
+    };
+    lockfile
+        .add_input(pyproject, &lockfile_path)
+        .into_diagnostic()?;
+    lockfile.to_path(&lockfile_path).into_diagnostic()?;
+    println!(
+        "Updated {} and {}",
+        console::style(pyproject.display()).bold(),
+        console::style(lockfile_path.display()).bold()
+    );
+
+    if let Some(venv) = &args.sync {
+        sync_environment(
+            package_db,
+            venv,
+            &lockfile_path,
+            args.python_interpreter.clone(),
+            false,
+            InstallOptions::default(),
+            None,
+        )
+        .await?;
+    }
+    Ok(())
+}
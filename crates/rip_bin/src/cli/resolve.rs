@@ -1,27 +1,49 @@
-use clap::{Parser, Subcommand};
+use crate::cli::licenses::check_license_policy;
+use crate::{build_config_settings, global_config, output_format, print_json, OutputFormat};
+use clap::{Parser, Subcommand, ValueEnum};
 use fs_err as fs;
 use itertools::Itertools;
 use miette::{Context, IntoDiagnostic};
-use rattler_installs_packages::artifacts::wheel::UnpackWheelOptions;
-use rattler_installs_packages::index::PackageDb;
-use rattler_installs_packages::python_env::{Pep508EnvMakers, PythonLocation, WheelTags};
+use rattler_installs_packages::artifacts::wheel::{staged_path, InstallPaths, UnpackWheelOptions};
+use rattler_installs_packages::cache::CacheBucket;
+use rattler_installs_packages::index::{CacheSetting, PackageDb};
+use rattler_installs_packages::install::{
+    execute_sync_plan, favored_installed_packages, InstallOptions, InstallReport, InstallTarget,
+    LinkStrategy, SyncPlan, SyncPlanOptions, UnpackedWheelCache, UpgradeStrategy, WheelFileCache,
+};
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, find_interpreter, ByteCodeCompiler, CondaEnvironment, Distribution,
+    Implementation, ManagedPythons, Pep508EnvMakers, PythonLocation, TargetDirectory, UserSite,
+    VEnv, VEnvOptions, WheelTags,
+};
+use rattler_installs_packages::resolve::profile::ResolutionProfiler;
 use rattler_installs_packages::resolve::solve_options::{
-    OnWheelBuildFailure, PreReleaseResolution, ResolveOptions, SDistResolution,
+    Abi3Resolution, OnWheelBuildFailure, PreReleaseResolution, ResolveOptions, SDistResolution,
 };
 use rattler_installs_packages::resolve::PinnedPackage;
-use rattler_installs_packages::types::Requirement;
+use rattler_installs_packages::types::{
+    project_requirement, read_requirements_file, Extra, NormalizedPackageName, PackageName,
+    RequiredHashes, Requirement, RequirementsFile, VersionSpecifiers,
+};
 use rattler_installs_packages::wheel_builder::WheelBuilder;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::Write;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 
+/// The JSON output of `rip resolve` and `rip install`.
 #[derive(Serialize, Debug)]
 struct Solution {
     resolved: bool,
-    packages: HashMap<String, String>,
+    packages: BTreeMap<String, String>,
     error: Option<String>,
+    /// The changes that were made to the environment by `rip install`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<InstallReport>,
 }
 
 #[derive(Subcommand)]
@@ -30,26 +52,60 @@ pub enum Commands {
     #[clap(alias = "r")]
     Resolve(ResolveArgs),
 
-    /// Resolve and install a set of requirements
+    /// Resolve and install a set of requirements into the target directory that is passed as the
+    /// last argument, or into the user site-packages with `--user`
     #[clap(alias = "i")]
     Install(InstallArgs),
 }
 
+impl Commands {
+    /// Returns true if the packages are installed with `--require-hashes`, or if one of the
+    /// requirements files requires hashes.
+    pub fn require_hashes(&self) -> miette::Result<bool> {
+        match self {
+            Commands::Resolve(_) => Ok(false),
+            Commands::Install(args) => Ok(args.resolve_args.required_hashes()?.is_some()),
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct ResolveArgs {
-    #[clap(num_args = 1.., required = true)]
-    /// The specs to resolve
-    specs: Vec<Requirement>,
+    #[clap(num_args = 1.., required_unless_present = "requirement")]
+    /// The specs to resolve. A path to a project directory, optionally followed by extras like
+    /// `.[test]`, resolves the project itself
+    specs: Vec<Spec>,
+
+    #[clap(flatten)]
+    requirement_files: RequirementFileArgs,
 
     /// How to handle SDists
     #[clap(flatten)]
     sdist_resolution: SDistResolutionArgs,
 
+    /// How to handle abi3 wheels
+    #[clap(flatten)]
+    abi3_resolution: Abi3ResolutionArgs,
+
     /// Path to the python interpreter to use for resolving environment markers and creating venvs
     #[clap(long, short)]
     python_interpreter: Option<PathBuf>,
 
+    /// Select the best interpreter on the PATH that matches this version specifier, e.g.
+    /// ">=3.10,<3.13", instead of specifying its path
+    #[clap(long, conflicts_with = "python_interpreter")]
+    python_version: Option<VersionSpecifiers>,
+
+    /// The implementation of the interpreter to select with `--python-version`
+    #[clap(long, default_value = "any", requires = "python_version")]
+    python_implementation: Implementation,
+
+    /// Download a standalone python build if no interpreter on the PATH matches
+    /// `--python-version`, or if there is no system interpreter at all
+    #[clap(long, conflicts_with = "python_interpreter")]
+    managed_python: bool,
+
     /// Disable inheritance of env variables.
     #[arg(short = 'c', long)]
     clean_env: bool,
@@ -62,19 +118,278 @@ pub struct ResolveArgs {
     #[clap(long)]
     pre: bool,
 
-    /// Output the result as json
+    /// Require a sha256 hash for every package, including dependencies, from the `--hash` options
+    /// in the requirements files. Only artifacts that match are selected and their contents are
+    /// verified before installing, also when they are cached. Implied if a requirements file
+    /// contains `--require-hashes`
+    #[clap(long)]
+    require_hashes: bool,
+
+    /// Print where the time of the resolution went after resolving, ranked by package
+    #[clap(long)]
+    profile: bool,
+
+    /// Output the result as json, the same as `--format json`
     #[clap(long)]
     json: bool,
 }
 
+/// A requirement or a local project that is passed on the command line.
+#[derive(Clone, Debug)]
+pub enum Spec {
+    /// A PEP 508 requirement, e.g. `flask>=2`
+    Requirement(Requirement),
+    /// A directory that contains a python project together with the extras to select, e.g.
+    /// `./project[test]`
+    Project { path: PathBuf, extras: Vec<Extra> },
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let requirement_error = match Requirement::from_str(s) {
+            Ok(requirement) => return Ok(Spec::Requirement(requirement)),
+            Err(e) => e,
+        };
+
+        // Anything that does not parse as a requirement but looks like a path is a project
+        if !(s.starts_with('.') || s.contains(['/', '\\'])) {
+            return Err(requirement_error.to_string());
+        }
+        let (path, extras) = match s.strip_suffix(']').and_then(|s| s.split_once('[')) {
+            Some((path, extras)) => (
+                path,
+                extras
+                    .split(',')
+                    .map(|extra| Extra::from_str(extra.trim()).map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => (s, Vec::new()),
+        };
+        Ok(Spec::Project {
+            path: PathBuf::from(path),
+            extras,
+        })
+    }
+}
+
+impl Spec {
+    /// Interprets the spec as a directory, this is how the install target is passed.
+    fn into_directory(self) -> Option<PathBuf> {
+        match self {
+            Spec::Project { path, extras } if extras.is_empty() => Some(path),
+            Spec::Requirement(Requirement {
+                name,
+                extras: None,
+                version_or_url: None,
+                marker: None,
+            }) => Some(PathBuf::from(name)),
+            _ => None,
+        }
+    }
+}
+
+impl ResolveArgs {
+    /// Returns the requirements from the command line and from the requirements files.
+    fn requirements(&self) -> miette::Result<Vec<Requirement>> {
+        collect_requirements(&self.specs, &self.requirement_files.requirement)
+    }
+
+    /// Returns the hashes from the requirements files if hashes are required, either with
+    /// `--require-hashes` or by one of the files.
+    fn required_hashes(&self) -> miette::Result<Option<RequiredHashes>> {
+        let mut require_hashes = self.require_hashes;
+        let mut hashes = RequiredHashes::default();
+        for path in &self.requirement_files.requirement {
+            let file = RequirementsFile::from_path(path).into_diagnostic()?;
+            require_hashes |= file.require_hashes;
+            hashes.extend(file.hashes);
+        }
+        Ok(require_hashes.then_some(hashes))
+    }
+}
+
+/// Returns the requirements of the specs on the command line together with the requirements in
+/// the `requirement_files`.
+pub(crate) fn collect_requirements(
+    specs: &[Spec],
+    requirement_files: &[PathBuf],
+) -> miette::Result<Vec<Requirement>> {
+    let mut requirements = Vec::new();
+    for spec in specs {
+        requirements.push(match spec {
+            Spec::Requirement(requirement) => requirement.clone(),
+            Spec::Project { path, extras } => {
+                project_requirement(path, extras)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to read the project in {}", path.display()))?
+            }
+        });
+    }
+    for path in requirement_files {
+        requirements.extend(read_requirements_file(path).into_diagnostic()?);
+    }
+    Ok(requirements)
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct InstallArgs {
     #[clap(flatten)]
     resolve_args: ResolveArgs,
 
-    /// The target directory to install into
-    target: PathBuf,
+    #[clap(flatten)]
+    install_target: InstallTargetArgs,
+}
+
+impl InstallArgs {
+    /// Moves the target directory, the last positional argument, from the specs to the install
+    /// target unless installing into the user site-packages.
+    fn take_target(&mut self) -> miette::Result<()> {
+        if self.install_target.user {
+            return Ok(());
+        }
+        let Some(target) = self.resolve_args.specs.pop() else {
+            miette::bail!("the target directory to install into is missing");
+        };
+        let Some(target) = target.into_directory() else {
+            miette::bail!("the last argument must be the target directory to install into");
+        };
+        if self.resolve_args.specs.is_empty()
+            && self.resolve_args.requirement_files.requirement.is_empty()
+        {
+            miette::bail!("no requirements to install into {}", target.display());
+        }
+        self.install_target.target = Some(target);
+        Ok(())
+    }
+}
+
+#[derive(Parser)]
+pub struct InstallTargetArgs {
+    /// The target directory to install into. If this is an existing conda environment the
+    /// packages are installed into it, otherwise a virtual environment is created.
+    ///
+    /// Clap cannot parse an optional positional after the variadic specs so this is taken from
+    /// the last spec, see [`InstallArgs::take_target`].
+    #[clap(skip)]
+    target: Option<PathBuf>,
+
+    /// How to place the files of cached wheels into the environment
+    #[clap(long, value_enum, default_value_t = LinkMode::Hardlink)]
+    link_mode: LinkMode,
+
+    /// Keep a cache of unpacked wheels and place them into the environment as a whole. This
+    /// speeds up repeated installs, especially when copying files on Windows
+    #[clap(long)]
+    cache_unpacked: bool,
+
+    /// The maximum number of wheels to install concurrently, defaults to the number of CPUs
+    #[clap(long, short)]
+    jobs: Option<NonZeroUsize>,
+
+    /// Compile the installed python files to bytecode
+    #[clap(long)]
+    compile: bool,
+
+    /// Write a JSON report of the installation to the specified file
+    #[clap(long)]
+    report: Option<PathBuf>,
+
+    /// Install the packages directly into the target directory instead of creating a virtual
+    /// environment, similar to `pip install --target`
+    #[clap(long)]
+    flat: bool,
+
+    /// The directory to write scripts to when using `--flat`, relative to the target directory
+    #[clap(long, requires = "flat")]
+    scripts_dir: Option<PathBuf>,
+
+    /// Install the packages into the user site-packages directory of the interpreter, similar to
+    /// `pip install --user`
+    #[clap(long, conflicts_with = "flat")]
+    user: bool,
+
+    /// Reinstall packages even if the installed version already satisfies the resolution
+    #[clap(long)]
+    reinstall: bool,
+
+    /// Create a virtual environment and scripts that do not refer to the absolute location of
+    /// the environment, so it can be moved after installation
+    #[clap(long, conflicts_with = "user")]
+    relocatable: bool,
+
+    /// Stage the files below this directory instead of writing them to the target directly,
+    /// similar to `DESTDIR`. The installed files still refer to the target directory
+    #[clap(long, requires = "flat")]
+    destdir: Option<PathBuf>,
+
+    /// Record the index that each package was downloaded from in a PEP 710
+    /// `provenance_url.json` file
+    #[clap(long)]
+    record_provenance: bool,
+
+    /// Upgrade the requested packages to the newest available versions instead of keeping the
+    /// installed versions
+    #[clap(long, short = 'U')]
+    upgrade: bool,
+
+    /// Upgrade a specific package, can be specified multiple times
+    #[clap(long, short = 'P')]
+    upgrade_package: Vec<PackageName>,
+
+    /// Determines which dependencies of upgraded packages are upgraded as well
+    #[clap(long, value_enum, default_value_t = UpgradeStrategyArg::OnlyIfNeeded)]
+    upgrade_strategy: UpgradeStrategyArg,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum UpgradeStrategyArg {
+    /// Only upgrade dependencies if the installed version does not satisfy the requirements
+    OnlyIfNeeded,
+    /// Upgrade all dependencies of the upgraded packages
+    Eager,
+}
+
+impl From<UpgradeStrategyArg> for UpgradeStrategy {
+    fn from(value: UpgradeStrategyArg) -> Self {
+        match value {
+            UpgradeStrategyArg::OnlyIfNeeded => UpgradeStrategy::OnlyIfNeeded,
+            UpgradeStrategyArg::Eager => UpgradeStrategy::Eager,
+        }
+    }
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+pub enum LinkMode {
+    /// Hardlink files from the cache, falls back to copying
+    Hardlink,
+    /// Copy files from the cache
+    Copy,
+    /// Clone files from the cache on filesystems that support it, falls back to copying
+    Reflink,
+    /// Symlink files from the cache, falls back to copying
+    Symlink,
+}
+
+impl From<LinkMode> for LinkStrategy {
+    fn from(value: LinkMode) -> Self {
+        match value {
+            LinkMode::Hardlink => LinkStrategy::Hardlink,
+            LinkMode::Copy => LinkStrategy::Copy,
+            LinkMode::Reflink => LinkStrategy::Reflink,
+            LinkMode::Symlink => LinkStrategy::Symlink,
+        }
+    }
+}
+
+/// The requirements files to read requirements from, in addition to the specs on the command line.
+#[derive(Parser)]
+pub struct RequirementFileArgs {
+    /// Read requirements from a requirements file, can be specified multiple times
+    #[clap(long, short = 'r')]
+    pub(crate) requirement: Vec<PathBuf>,
 }
 
 #[derive(Parser)]
@@ -97,6 +412,20 @@ pub struct SDistResolutionArgs {
     only_sdists: bool,
 }
 
+/// Returns how pre-releases are resolved, `pre` is the value of the `--pre` flag. Pre-releases are
+/// allowed if the flag or the configuration allows them, otherwise only for packages whose
+/// requirements refer to a pre-release.
+pub(crate) fn pre_release_resolution(
+    pre: bool,
+    requirements: &[Requirement],
+) -> PreReleaseResolution {
+    if pre || global_config().pre == Some(true) {
+        PreReleaseResolution::Allow
+    } else {
+        PreReleaseResolution::from_specs(requirements)
+    }
+}
+
 impl From<SDistResolutionArgs> for SDistResolution {
     fn from(value: SDistResolutionArgs) -> Self {
         if value.only_sdists {
@@ -108,16 +437,88 @@ impl From<SDistResolutionArgs> for SDistResolution {
         } else if value.prefer_wheels {
             SDistResolution::PreferWheels
         } else {
-            SDistResolution::Normal
+            global_config().sdist_resolution.unwrap_or_default()
+        }
+    }
+}
+
+#[derive(Parser)]
+#[group(multiple = false)]
+pub struct Abi3ResolutionArgs {
+    /// Prefer abi3 wheels over wheels built for a specific python version
+    #[clap(long)]
+    prefer_abi3: bool,
+
+    /// Never select wheels built for a specific python version, only abi3 or pure wheels
+    #[clap(long)]
+    require_abi3: bool,
+}
+
+impl From<Abi3ResolutionArgs> for Abi3Resolution {
+    fn from(value: Abi3ResolutionArgs) -> Self {
+        if value.require_abi3 {
+            Abi3Resolution::RequireAbi3
+        } else if value.prefer_abi3 {
+            Abi3Resolution::PreferAbi3
+        } else {
+            Abi3Resolution::Normal
         }
     }
 }
 
 pub async fn execute(package_db: Arc<PackageDb>, commands: Commands) -> miette::Result<()> {
-    let (args, target) = match commands {
+    let (mut args, install_target) = match commands {
         Commands::Resolve(args) => (args, None),
-        Commands::Install(args) => (args.resolve_args, Some(args.target)),
+        Commands::Install(mut args) => {
+            args.take_target()?;
+            (args.resolve_args, Some(args.install_target))
+        }
+    };
+    let command = if install_target.is_some() {
+        "install"
+    } else {
+        "resolve"
     };
+    let json = args.json || output_format() == OutputFormat::Json;
+    let requirements = args.requirements()?;
+    let required_hashes = args.required_hashes()?;
+    let require_hashes = required_hashes.is_some();
+
+    // Select an interpreter that matches the requested version
+    if let Some(python_version) = args.python_version.take() {
+        let interpreter = match find_interpreter(&python_version, args.python_implementation) {
+            Ok(interpreter) => interpreter,
+            Err(_) if args.managed_python => managed_pythons(&package_db)
+                .find_or_install(&python_version)
+                .await
+                .into_diagnostic()?,
+            Err(err) => return Err(err).into_diagnostic(),
+        };
+        tracing::info!(
+            "selected python {}.{}.{} at {}",
+            interpreter.version.major,
+            interpreter.version.minor,
+            interpreter.version.patch,
+            interpreter.path.display()
+        );
+        args.python_interpreter = Some(interpreter.path);
+    } else if args.managed_python
+        && args.python_interpreter.is_none()
+        && PythonLocation::System.executable().is_err()
+    {
+        let interpreter = managed_pythons(&package_db)
+            .find_or_install(&VersionSpecifiers::from_str(">=3").expect("valid specifiers"))
+            .await
+            .into_diagnostic()?;
+        tracing::info!(
+            "no system python found, using python {}.{}.{} at {}",
+            interpreter.version.major,
+            interpreter.version.minor,
+            interpreter.version.patch,
+            interpreter.path.display()
+        );
+        args.python_interpreter = Some(interpreter.path);
+    }
 
     // Determine the environment markers for the current machine
     let env_markers = Arc::new(match args.python_interpreter {
@@ -156,50 +557,86 @@ pub async fn execute(package_db: Arc<PackageDb>, commands: Commands) -> miette::
         compatible_tags.tags().format(", ")
     );
 
-    let on_wheel_build_failure = if args.save_on_failure {
-        OnWheelBuildFailure::SaveBuildEnv
-    } else {
-        OnWheelBuildFailure::DeleteBuildEnv
-    };
+    let build_config = &global_config().build;
+    let on_wheel_build_failure =
+        if args.save_on_failure || build_config.save_on_failure == Some(true) {
+            OnWheelBuildFailure::SaveBuildEnv
+        } else {
+            OnWheelBuildFailure::DeleteBuildEnv
+        };
 
-    let pre_release_resolution = if args.pre {
-        PreReleaseResolution::Allow
-    } else {
-        PreReleaseResolution::from_specs(&args.specs)
-    };
+    let pre_release_resolution = pre_release_resolution(args.pre, &requirements);
 
     let resolve_opts = ResolveOptions {
         sdist_resolution: args.sdist_resolution.into(),
+        abi3_resolution: args.abi3_resolution.into(),
         python_location: python_location.clone(),
-        clean_env: args.clean_env,
+        clean_env: args.clean_env || build_config.clean_env == Some(true),
         on_wheel_build_failure,
         pre_release_resolution,
+        required_hashes: required_hashes.map(Arc::new),
+        profiler: args.profile.then(|| Arc::new(ResolutionProfiler::new())),
         ..Default::default()
     };
 
+    // Prefer the versions that are already installed in the target, unless they are upgraded
+    let favored_packages = match &install_target {
+        Some(install_target) => {
+            let (root, installed) = installed_distributions(install_target, &python_location)?;
+            let mut upgrade = install_target
+                .upgrade_package
+                .iter()
+                .cloned()
+                .map(NormalizedPackageName::from)
+                .collect::<HashSet<_>>();
+            if install_target.upgrade {
+                upgrade.extend(
+                    requirements
+                        .iter()
+                        .filter_map(|spec| PackageName::from_str(&spec.name).ok())
+                        .map(NormalizedPackageName::from),
+                );
+            }
+            favored_installed_packages(
+                &root,
+                &installed,
+                &upgrade,
+                install_target.upgrade_strategy.into(),
+            )
+        }
+        None => HashMap::default(),
+    };
+
     // Solve the environment
-    let blueprint = match rattler_installs_packages::resolve::resolve(
+    let blueprint = rattler_installs_packages::resolve::resolve(
         package_db.clone(),
-        &args.specs,
+        &requirements,
         env_markers.clone(),
         Some(compatible_tags.clone()),
         HashMap::default(),
-        HashMap::default(),
+        favored_packages,
         resolve_opts.clone(),
         HashMap::default(),
     )
-    .await
-    {
+    .await;
+    if let Some(profiler) = &resolve_opts.profiler {
+        eprintln!("{}", profiler.report());
+    }
+    let blueprint = match blueprint {
         Ok(blueprint) => blueprint,
         Err(err) => {
-            return if args.json {
+            return if json {
                 let solution = Solution {
                     resolved: false,
-                    packages: HashMap::default(),
+                    packages: BTreeMap::default(),
                     error: Some(format!("{}", err)),
+                    report: None,
                 };
-                println!("{}", serde_json::to_string_pretty(&solution).unwrap());
-                return Ok(());
+                print_json(command, &solution)?;
+                if install_target.is_some() {
+                    miette::bail!("Could not solve for requested requirements");
+                }
+                Ok(())
             } else {
                 Err(err.wrap_err("Could not solve for requested requirements"))
             }
@@ -207,11 +644,72 @@ pub async fn execute(package_db: Arc<PackageDb>, commands: Commands) -> miette::
     };
 
     // Output the selected versions
+    if !json {
+        print_solution(&requirements, &blueprint)?;
+    }
+
+    let packages = blueprint
+        .iter()
+        .map(|p| (p.name.to_string(), p.version.to_string()))
+        .collect();
+
+    // Install if requested
+    let report = if let Some(install_target) = install_target {
+        check_license_policy(&package_db, &blueprint).await?;
+
+        let wheel_builder = WheelBuilder::new(
+            package_db.clone(),
+            env_markers,
+            Some(compatible_tags),
+            resolve_opts,
+            Default::default(),
+        )
+        .into_diagnostic()?
+        .with_config_settings(build_config_settings(Vec::new()));
+
+        // The packages that were explicitly requested, as opposed to their dependencies
+        let requested = requirements
+            .iter()
+            .filter_map(|spec| PackageName::from_str(&spec.name).ok())
+            .map(NormalizedPackageName::from)
+            .collect();
+
+        Some(
+            install_packages(
+                package_db,
+                wheel_builder,
+                blueprint,
+                requested,
+                python_location,
+                install_target,
+                require_hashes,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    if json {
+        let solution = Solution {
+            resolved: true,
+            packages,
+            error: None,
+            report,
+        };
+        print_json(command, &solution)?;
+    }
+
+    Ok(())
+}
+
+/// Prints the requirements and the versions they were resolved to.
+fn print_solution(requirements: &[Requirement], blueprint: &[PinnedPackage]) -> miette::Result<()> {
     println!(
         "{}:",
         console::style("Successfully resolved environment").bold()
     );
-    for spec in args.specs.iter() {
+    for spec in requirements.iter() {
         println!("- {}", spec);
     }
 
@@ -242,106 +740,280 @@ pub async fn execute(package_db: Arc<PackageDb>, commands: Commands) -> miette::
         )
         .into_diagnostic()?;
     }
-    tabbed_stdout.flush().into_diagnostic()?;
+    tabbed_stdout.flush().into_diagnostic()
+}
 
-    if args.json {
-        let solution = Solution {
-            resolved: true,
-            packages: blueprint
-                .iter()
-                .map(|p| (p.name.to_string(), p.version.to_string()))
-                .collect(),
-            error: None,
-        };
-        println!("{}", serde_json::to_string_pretty(&solution).unwrap());
+/// Returns the directory of standalone python builds that are downloaded on demand.
+pub(crate) fn managed_pythons(package_db: &PackageDb) -> ManagedPythons {
+    ManagedPythons::new(
+        CacheBucket::Pythons.path(package_db.cache_dir()),
+        package_db.client().clone(),
+    )
+    .with_offline(package_db.cache_setting() == CacheSetting::Offline)
+}
+
+/// Returns the root of the target and the distributions that are already installed in it.
+fn installed_distributions(
+    install_target: &InstallTargetArgs,
+    python_location: &PythonLocation,
+) -> miette::Result<(PathBuf, Vec<Distribution>)> {
+    if install_target.user {
+        let python_executable = python_location.executable().into_diagnostic()?;
+        let user_site = UserSite::from_python(&python_executable).into_diagnostic()?;
+        let installed = find_distributions_in_venv(user_site.root(), user_site.install_paths())
+            .into_diagnostic()?;
+        return Ok((user_site.root().to_path_buf(), installed));
     }
 
-    // Install if requested
-    if let Some(target) = target {
-        let wheel_builder = WheelBuilder::new(
-            package_db.clone(),
-            env_markers,
-            Some(compatible_tags),
-            resolve_opts,
-            Default::default(),
-        )
-        .into_diagnostic()?;
+    let Some(target) = install_target.target.clone() else {
+        miette::bail!("the target directory to install into is missing");
+    };
+    let target = match &install_target.destdir {
+        Some(destdir) => staged_path(destdir, &target),
+        None => target,
+    };
+    if !target.exists() {
+        return Ok((target, Vec::new()));
+    }
 
-        install_packages(
-            package_db,
-            wheel_builder,
-            blueprint,
-            python_location,
-            target,
+    let installed = if !install_target.flat && CondaEnvironment::is_conda_environment(&target) {
+        CondaEnvironment::from_prefix(&target)
+            .into_diagnostic()?
+            .installed_distributions()
+    } else if install_target.flat {
+        let scripts_dir = install_target
+            .scripts_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(if cfg!(windows) { "Scripts" } else { "bin" }));
+        find_distributions_in_venv(
+            &target,
+            &InstallPaths::for_target_dir(scripts_dir, cfg!(windows)),
         )
-        .await?
+    } else {
+        let version = python_location.version().into_diagnostic()?;
+        find_distributions_in_venv(&target, &InstallPaths::for_venv(version, cfg!(windows)))
     }
-
-    Ok(())
+    .into_diagnostic()?;
+    Ok((target, installed))
 }
 
-/// Install resolved packages into a virtual environment
+/// The name that is written to the `INSTALLER` file of every installed distribution.
+pub(crate) const INSTALLER: &str = "rip";
+
+/// Install resolved packages into a virtual environment or a plain directory and returns a report
+/// of the changes.
 pub async fn install_packages(
     package_db: Arc<PackageDb>,
     wheel_builder: WheelBuilder,
     pinned_packages: Vec<PinnedPackage>,
+    requested: HashSet<NormalizedPackageName>,
     python_location: PythonLocation,
-    target: PathBuf,
-) -> miette::Result<()> {
-    println!(
-        "\n\nInstalling into: {}",
-        console::style(target.display()).bold()
+    install_target: InstallTargetArgs,
+    require_hashes: bool,
+) -> miette::Result<InstallReport> {
+    let start = Instant::now();
+    let human = output_format() == OutputFormat::Human;
+    let InstallTargetArgs {
+        target,
+        link_mode,
+        cache_unpacked,
+        jobs,
+        compile,
+        report: report_path,
+        flat,
+        scripts_dir,
+        user,
+        reinstall,
+        relocatable,
+        destdir,
+        record_provenance,
+        upgrade: _,
+        upgrade_package: _,
+        upgrade_strategy: _,
+    } = install_target;
+
+    // Python packages that are managed by conda must not be touched
+    let mut externally_managed = HashSet::new();
+
+    let (install_target, python_executable): (Box<dyn InstallTarget + Sync>, _) = if user {
+        let python_executable = python_location.executable().into_diagnostic()?;
+        let user_site = UserSite::from_python(&python_executable).into_diagnostic()?;
+        if human {
+            println!(
+                "\n\nInstalling into: {}",
+                console::style(user_site.site_packages().display()).bold()
+            );
+        }
+        (Box::new(user_site), python_executable)
+    } else {
+        let Some(target) = target else {
+            miette::bail!("the target directory to install into is missing");
+        };
+        if human {
+            println!(
+                "\n\nInstalling into: {}",
+                console::style(target.display()).bold()
+            );
+        }
+        let staged_target = match &destdir {
+            Some(destdir) => staged_path(destdir, &target),
+            None => target.clone(),
+        };
+        if !staged_target.exists() {
+            std::fs::create_dir_all(&staged_target).into_diagnostic()?;
+        }
+        if !flat && CondaEnvironment::is_conda_environment(&target) {
+            let conda_env = CondaEnvironment::from_prefix(&target).into_diagnostic()?;
+            externally_managed.extend(
+                conda_env
+                    .conda_managed_distributions()
+                    .into_diagnostic()?
+                    .into_iter()
+                    .map(|distribution| distribution.name),
+            );
+            let python_executable = conda_env.python_executable();
+            (Box::new(conda_env), python_executable)
+        } else if flat {
+            let python_executable = python_location.executable().into_diagnostic()?;
+            let mut target_dir = match scripts_dir {
+                Some(scripts_dir) => {
+                    TargetDirectory::with_scripts_dir(&target, scripts_dir, &python_executable)
+                }
+                None => TargetDirectory::new(&target, &python_executable),
+            };
+            if let Some(destdir) = destdir {
+                target_dir = target_dir.with_destdir(destdir);
+            }
+            (Box::new(target_dir), python_executable)
+        } else {
+            let venv = VEnv::create_with_options(
+                &target,
+                python_location,
+                &VEnvOptions {
+                    relocatable,
+                    ..VEnvOptions::default()
+                },
+            )
+            .into_diagnostic()?;
+            let python_executable = venv.python_executable();
+            (Box::new(venv), python_executable)
+        }
+    };
+
+    let install_options = InstallOptions {
+        link_strategy: link_mode.into(),
+        file_cache: Some(WheelFileCache::new(
+            CacheBucket::WheelFiles.path(package_db.cache_dir()),
+        )),
+        unpacked_cache: cache_unpacked.then(|| {
+            UnpackedWheelCache::new(CacheBucket::UnpackedWheels.path(package_db.cache_dir()))
+        }),
+        parallelism: jobs,
+        hooks: None,
+        reporter: package_db.reporter().cloned(),
+        metrics: Some(package_db.metrics().clone()),
+        require_hashes,
+        record_provenance,
+    };
+
+    // Determine which packages are not already satisfied by the target
+    let installed =
+        find_distributions_in_venv(install_target.root(), install_target.install_paths())
+            .into_diagnostic()?;
+    let plan = SyncPlan::with_options(
+        installed,
+        &pinned_packages,
+        SyncPlanOptions {
+            keep_extraneous: true,
+            reinstall,
+            requested,
+            externally_managed,
+            ..SyncPlanOptions::default()
+        },
     );
-    if !target.exists() {
-        std::fs::create_dir_all(&target).into_diagnostic()?;
+
+    let byte_code_compiler = if compile {
+        Some(ByteCodeCompiler::new(&python_executable).into_diagnostic()?)
+    } else {
+        None
+    };
+
+    let to_install = plan
+        .install
+        .iter()
+        .chain(plan.reinstall.iter().map(|(_, package)| package))
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect::<Vec<_>>();
+    if human {
+        print_to_install(&to_install, pinned_packages.len() - to_install.len())?;
     }
 
-    let venv = rattler_installs_packages::python_env::VEnv::create(&target, python_location)
-        .into_diagnostic()?;
+    let options = UnpackWheelOptions {
+        installer: Some(String::from(INSTALLER)),
+        byte_code_compiler: byte_code_compiler.as_ref(),
+        install_options,
+        relocatable,
+        ..Default::default()
+    };
+    let mut report = execute_sync_plan(
+        install_target.as_ref(),
+        &package_db,
+        Some(&wheel_builder),
+        &plan,
+        &options,
+    )
+    .await?;
+    drop(options);
+
+    if let Some(byte_code_compiler) = byte_code_compiler {
+        let compile_start = Instant::now();
+        byte_code_compiler.wait().into_diagnostic()?;
+        report.timings.install += compile_start.elapsed();
+    }
+    report.timings.total = start.elapsed();
+
+    if let Some(report_path) = report_path {
+        let report = serde_json::to_string_pretty(&report).into_diagnostic()?;
+        fs::write(report_path, report).into_diagnostic()?;
+    }
 
-    let longest = pinned_packages
+    if human {
+        println!(
+            "\n{}",
+            console::style("Successfully installed environment!").bold()
+        );
+    }
+
+    Ok(report)
+}
+
+/// Prints the packages that are about to be installed.
+fn print_to_install(to_install: &[&PinnedPackage], satisfied: usize) -> miette::Result<()> {
+    let longest = to_install
         .iter()
         .map(|p| p.name.as_str().len())
         .max()
         .unwrap_or_default();
     let mut tabbed_stdout = tabwriter::TabWriter::new(std::io::stdout()).minwidth(longest);
-
-    for pinned_package in pinned_packages
-        .clone()
-        .into_iter()
-        .sorted_by(|a, b| a.name.cmp(&b.name))
-    {
+    for package in to_install {
         writeln!(
             tabbed_stdout,
             "{name}\t{version}",
-            name = console::style(pinned_package.name).bold().green(),
-            version = console::style(pinned_package.version).italic()
-        )
-        .into_diagnostic()?;
-        tabbed_stdout.flush().into_diagnostic()?;
-        // println!(
-        //     "\ninstalling: {} - {}",
-        //     console::style(pinned_package.name).bold().green(),
-        //     console::style(pinned_package.version).italic()
-        // );
-        let artifact_info = pinned_package.artifacts.first().unwrap();
-        let (artifact, direct_url_json) = package_db
-            .get_wheel(artifact_info, Some(&wheel_builder))
-            .await?;
-        venv.install_wheel(
-            &artifact,
-            &UnpackWheelOptions {
-                direct_url_json,
-                ..Default::default()
-            },
+            name = console::style(&package.name).bold().green(),
+            version = console::style(&package.version).italic()
         )
         .into_diagnostic()?;
     }
+    tabbed_stdout.flush().into_diagnostic()?;
 
-    println!(
-        "\n{}",
-        console::style("Successfully installed environment!").bold()
-    );
-
+    if satisfied > 0 {
+        println!(
+            "{}",
+            console::style(format!(
+                "{satisfied} package(s) already satisfied, use --reinstall to reinstall them"
+            ))
+            .dim()
+        );
+    }
     Ok(())
 }
@@ -0,0 +1,129 @@
+use crate::cli::resolve::managed_pythons;
+use clap::Parser;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::cache::CacheBucket;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::install::EphemeralEnvironments;
+use rattler_installs_packages::python_env::{find_interpreter, Implementation, PythonLocation};
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::types::{Requirement, Version, VersionSpecifiers};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The python interpreter to run with, either a version like `3.12`, version specifiers like
+    /// `>=3.10,<3.13` or the path to an interpreter
+    #[clap(long, short)]
+    python: Option<String>,
+
+    /// Download a standalone python build if no interpreter on the PATH matches `--python`
+    #[clap(long, requires = "python")]
+    managed_python: bool,
+
+    /// A requirement to install into the environment, can be specified multiple times
+    #[clap(long = "with", short = 'w')]
+    with: Vec<Requirement>,
+
+    /// The command to run followed by its arguments. The dependencies that a python script
+    /// declares in its inline metadata (PEP 723) are installed as well
+    #[clap(num_args = 1.., required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<OsString>,
+}
+
+/// How the user selected the python interpreter.
+enum PythonRequest {
+    Path(PathBuf),
+    Version(VersionSpecifiers),
+}
+
+impl PythonRequest {
+    fn parse(python: &str) -> Self {
+        if let Ok(version) = Version::from_str(python) {
+            let specifiers = VersionSpecifiers::from_str(&format!("=={version}.*"))
+                .expect("a version with a wildcard is a valid specifier");
+            PythonRequest::Version(specifiers)
+        } else if let Ok(specifiers) = VersionSpecifiers::from_str(python) {
+            PythonRequest::Version(specifiers)
+        } else {
+            PythonRequest::Path(PathBuf::from(python))
+        }
+    }
+}
+
+pub async fn run(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let python_location = match args.python.as_deref().map(PythonRequest::parse) {
+        None => PythonLocation::System,
+        Some(PythonRequest::Path(path)) => PythonLocation::Custom(path),
+        Some(PythonRequest::Version(specifiers)) => {
+            let interpreter = match find_interpreter(&specifiers, Implementation::Any) {
+                Ok(interpreter) => interpreter,
+                Err(_) if args.managed_python => managed_pythons(&package_db)
+                    .find_or_install(&specifiers)
+                    .await
+                    .into_diagnostic()?,
+                Err(err) => return Err(err).into_diagnostic(),
+            };
+            PythonLocation::CustomWithVersion(interpreter.path, interpreter.version)
+        }
+    };
+    let resolve_options = ResolveOptions {
+        python_location,
+        ..Default::default()
+    };
+
+    let (program, program_args) = args
+        .command
+        .split_first()
+        .expect("clap requires at least one argument");
+
+    // Run python scripts, also when they are passed to python explicitly, with their inline
+    // dependencies
+    let script = [Some(program), program_args.first()]
+        .into_iter()
+        .flatten()
+        .map(Path::new)
+        .find(|path| path.extension().is_some_and(|ext| ext == "py") && path.is_file());
+
+    let environments =
+        EphemeralEnvironments::new(CacheBucket::Environments.path(package_db.cache_dir()));
+    let environment = match script {
+        Some(script) => {
+            environments
+                .provision_script(package_db.clone(), script, &args.with, resolve_options)
+                .await?
+        }
+        None => {
+            environments
+                .provision(package_db.clone(), &args.with, resolve_options)
+                .await?
+        }
+    };
+    tracing::debug!(
+        "running in the environment at {}",
+        environment.venv().root().display()
+    );
+
+    let mut command = if Path::new(program)
+        .extension()
+        .is_some_and(|ext| ext == "py")
+    {
+        environment.python_command(program)
+    } else {
+        environment.command(program)
+    };
+    let status = command
+        .args(program_args)
+        .status()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to run {}", program.to_string_lossy()))?;
+
+    // Forward the exit code of the command
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use clap::Parser;
+use fs_err as fs;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::lock::{Lockfile, LOCKFILE_NAME};
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, Pep508EnvMakers, PythonInterpreterVersion,
+};
+use rattler_installs_packages::resolve::PinnedPackage;
+use rattler_installs_packages::sbom::Sbom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The lockfile to describe
+    #[clap(long, short, default_value = LOCKFILE_NAME)]
+    lockfile: PathBuf,
+
+    /// Describe the packages that are installed in this virtual environment instead of a lockfile
+    #[clap(long)]
+    installed: Option<PathBuf>,
+
+    /// Write an SPDX 2.3 document instead of a CycloneDX 1.5 document
+    #[clap(long)]
+    spdx: bool,
+
+    /// The name of the described project or environment, defaults to the name of its directory
+    #[clap(long)]
+    name: Option<String>,
+
+    /// Write the document to this file instead of to stdout
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub async fn sbom(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let sbom = match &args.installed {
+        Some(venv) => {
+            let name = args.name.clone().unwrap_or_else(|| directory_name(venv));
+            installed_sbom(name, venv).await?
+        }
+        None => {
+            let name = args.name.clone().unwrap_or_else(|| {
+                directory_name(args.lockfile.parent().unwrap_or(Path::new(".")))
+            });
+            let packages = locked_packages(&args.lockfile)?;
+            Sbom::from_resolution(name, &package_db, &packages).await
+        }
+    };
+
+    let document = if args.spdx {
+        sbom.to_spdx_json()
+    } else {
+        sbom.to_cyclonedx_json()
+    };
+    match &args.output {
+        Some(output) => {
+            fs::write(output, document).into_diagnostic()?;
+            eprintln!(
+                "Wrote the bill of materials of {} package(s) to {}",
+                sbom.components.len(),
+                console::style(output.display()).bold()
+            );
+        }
+        None => println!("{document}"),
+    }
+    Ok(())
+}
+
+/// Returns the packages of all targets in the lockfile, packages that are locked to the same
+/// version for several targets are only included once.
+pub(crate) fn locked_packages(lockfile: &Path) -> miette::Result<Vec<PinnedPackage>> {
+    let lockfile = Lockfile::from_path(lockfile)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to read {}", lockfile.display()))?;
+    let mut packages: Vec<PinnedPackage> = Vec::new();
+    for target in &lockfile.targets {
+        for package in target.pinned_packages().into_diagnostic()? {
+            if !packages
+                .iter()
+                .any(|other| other.name == package.name && other.version == package.version)
+            {
+                packages.push(package);
+            }
+        }
+    }
+    Ok(packages)
+}
+
+/// Returns the bill of materials of the virtual environment at `venv`.
+pub(crate) async fn installed_sbom(name: String, venv: &Path) -> miette::Result<Sbom> {
+    let python = venv.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", venv.display());
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let distributions =
+        find_distributions_in_venv(venv, &InstallPaths::for_venv(version, cfg!(windows)))
+            .into_diagnostic()?;
+    let env_markers = Pep508EnvMakers::from_python(&python)
+        .await
+        .into_diagnostic()?
+        .0;
+    Ok(Sbom::from_environment(
+        name,
+        venv,
+        &distributions,
+        &env_markers,
+    ))
+}
+
+pub(crate) fn directory_name(path: &Path) -> String {
+    let path = if path.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        path
+    };
+    path.canonicalize()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| String::from("environment"))
+}
@@ -0,0 +1,137 @@
+use crate::cli::show::index_metadata;
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use itertools::Itertools;
+use miette::Context;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The (partial) name of the package to search for
+    query: String,
+
+    /// The maximum number of packages to show
+    #[clap(long, short, default_value_t = 10)]
+    limit: usize,
+}
+
+/// The JSON output of `rip search`.
+#[derive(Serialize)]
+struct SearchOutput {
+    query: String,
+    packages: Vec<SearchHit>,
+}
+
+#[derive(Serialize)]
+struct SearchHit {
+    name: String,
+    /// The newest release, unless its metadata could not be retrieved
+    version: Option<String>,
+    summary: Option<String>,
+}
+
+/// The minimal Jaro-Winkler similarity of a name that does not contain the query to still be
+/// considered a match, this allows for small typos.
+const MIN_SIMILARITY: f64 = 0.85;
+
+pub async fn search(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let query = normalize(&args.query);
+
+    // The list of projects is cached by the index, so this is only slow the first time
+    let names = package_db
+        .get_package_names()
+        .await
+        .wrap_err("failed to retrieve the packages on the index")?;
+
+    let hits = names
+        .iter()
+        .filter_map(|name| {
+            let normalized = normalize(name);
+            let similarity = strsim::jaro_winkler(&query, &normalized);
+            let rank = if normalized == query {
+                0
+            } else if normalized.starts_with(&query) {
+                1
+            } else if normalized.contains(&query) {
+                2
+            } else if similarity >= MIN_SIMILARITY {
+                3
+            } else {
+                return None;
+            };
+            Some((rank, similarity, name))
+        })
+        .sorted_by(
+            |(rank_a, similarity_a, name_a), (rank_b, similarity_b, name_b)| {
+                rank_a
+                    .cmp(rank_b)
+                    .then(similarity_b.total_cmp(similarity_a))
+                    .then(name_a.len().cmp(&name_b.len()))
+                    .then(name_a.cmp(name_b))
+            },
+        )
+        .map(|(_, _, name)| name)
+        .take(args.limit)
+        .collect_vec();
+
+    // Retrieve the newest release and the summary of the top hits
+    let mut packages = Vec::new();
+    for name in hits {
+        let Ok(package_name) = PackageName::from_str(name) else {
+            continue;
+        };
+        let normalized = NormalizedPackageName::from(package_name);
+        packages.push(match index_metadata(&package_db, &normalized, None).await {
+            Ok((_, metadata, description)) => SearchHit {
+                name: metadata.name.as_source_str().to_string(),
+                version: Some(metadata.version.to_string()),
+                summary: description.summary.filter(|summary| !summary.is_empty()),
+            },
+            Err(err) => {
+                tracing::debug!("failed to retrieve the metadata of {name}: {err}");
+                SearchHit {
+                    name: name.clone(),
+                    version: None,
+                    summary: None,
+                }
+            }
+        });
+    }
+
+    match output_format() {
+        OutputFormat::Human => {
+            if packages.is_empty() {
+                println!("No packages found matching '{}'", args.query);
+            }
+            for package in packages {
+                match package.version {
+                    Some(version) => {
+                        println!("{} {version}", console::style(&package.name).bold())
+                    }
+                    None => println!("{}", console::style(&package.name).bold()),
+                }
+                if let Some(summary) = package.summary {
+                    println!("  {summary}");
+                }
+            }
+            Ok(())
+        }
+        OutputFormat::Json => print_json(
+            "search",
+            &SearchOutput {
+                query: args.query,
+                packages,
+            },
+        ),
+    }
+}
+
+/// Normalizes a name for comparison like package names are normalized.
+fn normalize(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
@@ -0,0 +1,365 @@
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use fs_err as fs;
+use itertools::Itertools;
+use miette::IntoDiagnostic;
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::index::{ArtifactRequest, PackageDb};
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, Distribution, Pep508EnvMakers, PythonInterpreterVersion,
+};
+use rattler_installs_packages::resolve::{DependencyGraph, PypiVersion};
+use rattler_installs_packages::types::{
+    ArtifactInfo, NormalizedPackageName, PackageDescription, PackageName, Record, Version,
+    WheelCoreMetadata,
+};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, about, long_about = None, disable_version_flag = true)]
+pub struct Args {
+    /// The package to show
+    package: PackageName,
+
+    /// The version to show, defaults to the installed version or the newest version on the index
+    #[clap(long)]
+    version: Option<Version>,
+
+    /// Show the package as it is installed in this virtual environment
+    #[clap(long)]
+    installed: Option<PathBuf>,
+
+    /// List the files that were installed by the package
+    #[clap(long, requires = "installed")]
+    files: bool,
+}
+
+/// The JSON output of `rip show`.
+#[derive(Serialize)]
+struct ShowOutput {
+    name: String,
+    version: String,
+    summary: Option<String>,
+    home_page: Option<String>,
+    project_urls: Vec<ProjectUrl>,
+    author: Option<String>,
+    license: Option<String>,
+    requires_python: Option<String>,
+    requires: Vec<String>,
+    /// The artifact on the index the metadata was read from
+    index: Option<IndexArtifact>,
+    /// The installed distribution with `--installed`
+    installed: Option<InstalledPackage>,
+}
+
+#[derive(Serialize)]
+struct ProjectUrl {
+    label: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct IndexArtifact {
+    url: String,
+    filename: String,
+    hash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct InstalledPackage {
+    required_by: Vec<String>,
+    url: Option<String>,
+    location: PathBuf,
+    installer: Option<String>,
+    tags: Option<Vec<String>>,
+    files: Option<Vec<String>>,
+}
+
+/// A package that is installed in a virtual environment.
+struct Installed {
+    root: PathBuf,
+    distribution: Distribution,
+    graph: DependencyGraph,
+}
+
+pub async fn show(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let name = NormalizedPackageName::from(args.package);
+
+    let installed = match &args.installed {
+        Some(venv) => Some(find_installed(venv, &name).await?),
+        None => None,
+    };
+
+    // Packages that were installed from a direct url are not looked up on the index
+    let version = args.version.or_else(|| {
+        installed
+            .as_ref()
+            .map(|installed| installed.distribution.version.clone())
+    });
+    let from_index = installed.as_ref().map_or(true, |installed| {
+        installed.distribution.direct_url.is_none()
+    });
+    let index = if from_index {
+        match index_metadata(&package_db, &name, version.as_ref()).await {
+            Ok(index) => Some(index),
+            Err(err) if installed.is_some() => {
+                tracing::warn!("failed to retrieve {name} from the index: {err}");
+                None
+            }
+            Err(err) => return Err(err),
+        }
+    } else {
+        None
+    };
+
+    // Prefer the metadata of the installed distribution
+    let (metadata, description) = match &installed {
+        Some(installed) => {
+            let bytes = fs::read(
+                installed
+                    .root
+                    .join(&installed.distribution.dist_info)
+                    .join("METADATA"),
+            )
+            .into_diagnostic()?;
+            (
+                WheelCoreMetadata::try_from(bytes.as_slice()).into_diagnostic()?,
+                PackageDescription::from_bytes(&bytes).into_diagnostic()?,
+            )
+        }
+        None => {
+            let (_, metadata, description) = index.as_ref().expect("either installed or index");
+            (metadata.clone(), description.clone())
+        }
+    };
+
+    let mut output = ShowOutput {
+        name: metadata.name.as_source_str().to_string(),
+        version: metadata.version.to_string(),
+        summary: description.summary,
+        home_page: description.home_page,
+        project_urls: description
+            .project_urls
+            .into_iter()
+            .map(|(label, url)| ProjectUrl { label, url })
+            .collect(),
+        author: description.author,
+        license: description.license,
+        requires_python: metadata.requires_python.map(|spec| spec.to_string()),
+        requires: metadata
+            .requires_dist
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        index: None,
+        installed: None,
+    };
+
+    // Where the package comes from
+    if let Some((artifact, _, _)) = &index {
+        output.index = Some(IndexArtifact {
+            url: artifact.url.to_string(),
+            filename: artifact.filename.to_string(),
+            hash: artifact
+                .hashes
+                .as_ref()
+                .and_then(|hashes| hashes.sha256)
+                .map(|sha256| format!("sha256:{sha256:x}")),
+        });
+    }
+    if let Some(installed) = &installed {
+        let files = if args.files {
+            let record = Record::from_path(
+                &installed
+                    .root
+                    .join(&installed.distribution.dist_info)
+                    .join("RECORD"),
+            )
+            .into_diagnostic()?;
+            Some(
+                record
+                    .iter()
+                    .map(|entry| entry.path.clone())
+                    .sorted()
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        output.installed = Some(InstalledPackage {
+            required_by: installed
+                .graph
+                .dependents(&name)
+                .map(|(dependent, _)| dependent.name.to_string())
+                .unique()
+                .collect(),
+            url: installed
+                .distribution
+                .direct_url
+                .as_ref()
+                .map(|direct_url| direct_url.url.to_string()),
+            location: installed
+                .root
+                .join(&installed.distribution.dist_info)
+                .parent()
+                .unwrap_or(&installed.root)
+                .to_path_buf(),
+            installer: installed.distribution.installer.clone(),
+            tags: installed
+                .distribution
+                .tags
+                .as_ref()
+                .map(|tags| tags.iter().map(ToString::to_string).collect()),
+            files,
+        });
+    }
+
+    match output_format() {
+        OutputFormat::Human => {
+            print_human(&output);
+            Ok(())
+        }
+        OutputFormat::Json => print_json("show", &output),
+    }
+}
+
+fn print_human(output: &ShowOutput) {
+    field("Name", &output.name);
+    field("Version", &output.version);
+    if let Some(summary) = &output.summary {
+        field("Summary", summary);
+    }
+    if let Some(home_page) = &output.home_page {
+        field("Home-page", home_page);
+    }
+    for ProjectUrl { label, url } in &output.project_urls {
+        field(label, url);
+    }
+    if let Some(author) = &output.author {
+        field("Author", author);
+    }
+    if let Some(license) = &output.license {
+        // Some packages include the complete text of the license
+        field("License", license.lines().next().unwrap_or_default());
+    }
+    if let Some(requires_python) = &output.requires_python {
+        field("Requires-Python", requires_python);
+    }
+    field("Requires", &output.requires.join(", "));
+    if let Some(installed) = &output.installed {
+        field("Required-by", &installed.required_by.join(", "));
+    }
+
+    if let Some(artifact) = &output.index {
+        field("Url", &artifact.url);
+        field("Filename", &artifact.filename);
+        if let Some(hash) = &artifact.hash {
+            field("Hash", hash);
+        }
+    }
+    if let Some(installed) = &output.installed {
+        if let Some(url) = &installed.url {
+            field("Url", url);
+        }
+        field("Location", &installed.location.display().to_string());
+        if let Some(installer) = &installed.installer {
+            field("Installer", installer);
+        }
+        if let Some(tags) = &installed.tags {
+            field("Tags", &tags.join(", "));
+        }
+        if let Some(files) = &installed.files {
+            println!("{}:", console::style("Files").bold());
+            for file in files {
+                println!("  {file}");
+            }
+        }
+    }
+}
+
+fn field(name: &str, value: &str) {
+    println!("{} {}", console::style(format!("{name}:")).bold(), value);
+}
+
+/// Finds the distribution of the package in the virtual environment at `venv`.
+async fn find_installed(venv: &Path, name: &NormalizedPackageName) -> miette::Result<Installed> {
+    let python = venv.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", venv.display());
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let distributions =
+        find_distributions_in_venv(venv, &InstallPaths::for_venv(version, cfg!(windows)))
+            .into_diagnostic()?;
+    let env_markers = Pep508EnvMakers::from_python(&python)
+        .await
+        .into_diagnostic()?
+        .0;
+    let graph = DependencyGraph::from_installed(venv, &distributions, &env_markers);
+
+    let distribution = distributions
+        .into_iter()
+        .find(|distribution| &distribution.name == name)
+        .ok_or_else(|| miette::miette!("{name} is not installed in {}", venv.display()))?;
+    Ok(Installed {
+        root: venv.to_path_buf(),
+        distribution,
+        graph,
+    })
+}
+
+/// Retrieves the metadata of a version of the package from the index, together with the artifact
+/// it was read from. Without a version the newest release is used.
+pub(crate) async fn index_metadata(
+    package_db: &PackageDb,
+    name: &NormalizedPackageName,
+    version: Option<&Version>,
+) -> miette::Result<(Arc<ArtifactInfo>, WheelCoreMetadata, PackageDescription)> {
+    let available = package_db
+        .available_artifacts(ArtifactRequest::FromIndex(name.clone()))
+        .await?;
+
+    // The versions are sorted in descending order
+    let versions = available.iter().filter_map(|(pypi_version, artifacts)| {
+        let PypiVersion::Version { version, .. } = pypi_version else {
+            return None;
+        };
+        Some((version, artifacts))
+    });
+    let artifacts = match version {
+        Some(requested) => versions
+            .into_iter()
+            .find(|(version, _)| *version == requested)
+            .map(|(_, artifacts)| artifacts)
+            .ok_or_else(|| miette::miette!("{name} {requested} is not available on the index"))?,
+        None => {
+            let versions = versions.collect_vec();
+            versions
+                .iter()
+                .find(|(version, _)| !version.any_prerelease())
+                .or_else(|| versions.first())
+                .map(|(_, artifacts)| *artifacts)
+                .ok_or_else(|| miette::miette!("{name} is not available on the index"))?
+        }
+    };
+
+    let (artifact, metadata) =
+        package_db
+            .get_metadata(artifacts, None)
+            .await?
+            .ok_or_else(|| {
+                miette::miette!("the metadata of {name} is not available without building it")
+            })?;
+    let description = match package_db.cached_metadata_bytes(artifact).await {
+        Some(bytes) => PackageDescription::from_bytes(&bytes).into_diagnostic()?,
+        None => PackageDescription::default(),
+    };
+    Ok((artifact.clone(), metadata, description))
+}
@@ -0,0 +1,300 @@
+use crate::build_config_settings;
+use crate::cli::licenses::check_license_policy;
+use crate::cli::resolve::INSTALLER;
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::{InstallPaths, UnpackWheelOptions};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::install::{
+    execute_sync_plan, InstallOptions, InstallReport, SyncPlan, SyncPlanOptions,
+};
+use rattler_installs_packages::lock::{Lockfile, PublicKey, LOCKFILE_NAME};
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, Pep508EnvMakers, PythonInterpreterVersion, PythonLocation, VEnv,
+    WheelTagTarget, WheelTags,
+};
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use rattler_installs_packages::wheel_builder::WheelBuilder;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The virtual environment to synchronize, it is created if it does not exist
+    target: PathBuf,
+
+    /// The lockfile to read the packages from
+    #[clap(long, short, default_value = LOCKFILE_NAME)]
+    lockfile: PathBuf,
+
+    /// Path to the python interpreter to use when the virtual environment is created
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Only print the changes that would be made to the environment
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Refuse to install packages that were locked without a sha256 hash. Downloaded artifacts are
+    /// always verified against their hash, with this option cached artifacts are verified as well
+    #[clap(long)]
+    require_hashes: bool,
+
+    /// Refuse to install from a lockfile that is not signed by this minisign public key, or whose
+    /// requirements files changed after it was locked. Either the key itself or the path of a
+    /// public key file
+    #[clap(long)]
+    verify_key: Option<String>,
+
+    /// Record the index that each package was downloaded from in a PEP 710
+    /// `provenance_url.json` file
+    #[clap(long)]
+    record_provenance: bool,
+}
+
+/// The JSON output of `rip sync`.
+#[derive(Serialize)]
+struct SyncOutput {
+    /// True if the changes were only planned and not made
+    dry_run: bool,
+    report: InstallReport,
+}
+
+impl Args {
+    /// Returns true if the packages are installed with `--require-hashes`.
+    pub fn require_hashes(&self) -> bool {
+        self.require_hashes
+    }
+}
+
+pub async fn sync(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let verify_key = match &args.verify_key {
+        Some(key) => Some(public_key(key)?),
+        None => None,
+    };
+    sync_environment(
+        package_db,
+        &args.target,
+        &args.lockfile,
+        args.python_interpreter,
+        args.dry_run,
+        InstallOptions {
+            require_hashes: args.require_hashes,
+            record_provenance: args.record_provenance,
+            ..Default::default()
+        },
+        verify_key.as_ref(),
+    )
+    .await
+}
+
+/// Parses a public key that is either given directly or as the path of a public key file.
+fn public_key(key: &str) -> miette::Result<PublicKey> {
+    let path = Path::new(key);
+    let key = if path.is_file() {
+        fs_err::read_to_string(path).into_diagnostic()?
+    } else {
+        key.to_string()
+    };
+    key.parse::<PublicKey>()
+        .into_diagnostic()
+        .wrap_err("failed to read the public key to verify the lockfile with")
+}
+
+/// Synchronizes the virtual environment at `target` with the packages in the lockfile at
+/// `lockfile_path`, the environment is created if it does not exist. With a `verify_key` the
+/// signature of the lockfile and the hashes of its inputs are verified first. The packages are
+/// installed with the given `install_options`.
+pub(crate) async fn sync_environment(
+    package_db: Arc<PackageDb>,
+    target: &Path,
+    lockfile_path: &Path,
+    python_interpreter: Option<PathBuf>,
+    dry_run: bool,
+    install_options: InstallOptions,
+    verify_key: Option<&PublicKey>,
+) -> miette::Result<()> {
+    let lockfile = match verify_key {
+        Some(verify_key) => {
+            let lockfile =
+                Lockfile::from_path_verified(lockfile_path, verify_key).into_diagnostic()?;
+            lockfile.verify_inputs(lockfile_path).into_diagnostic()?;
+            eprintln!(
+                "{} {} is signed by key {}",
+                console::style("Verified").bold().green(),
+                lockfile_path.display(),
+                verify_key.key_id()
+            );
+            lockfile
+        }
+        None => Lockfile::from_path(lockfile_path).into_diagnostic()?,
+    };
+
+    // Use the interpreter of the environment if it already exists
+    let python_location = match python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+    let venv_python = target.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    let python = if venv_python.is_file() {
+        venv_python
+    } else {
+        python_location.executable().into_diagnostic()?
+    };
+
+    // Select the packages that were locked for the interpreter
+    let interpreter = WheelTagTarget::from_python(&python)
+        .await
+        .into_diagnostic()
+        .wrap_err("failed to determine the platform of the python interpreter")?;
+    let locked = lockfile.select_target(&interpreter).ok_or_else(|| {
+        miette::miette!(
+            "{} does not contain packages for python {}.{} on {}, it was locked for: {}",
+            lockfile_path.display(),
+            interpreter.python_version.0,
+            interpreter.python_version.1,
+            interpreter.platform,
+            lockfile
+                .targets
+                .iter()
+                .map(|locked| locked.target.to_string())
+                .join(", ")
+        )
+    })?;
+    let packages = locked.pinned_packages().into_diagnostic()?;
+
+    // Determine the changes that are required to match the lockfile
+    let installed = if target.exists() {
+        let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+        find_distributions_in_venv(target, &InstallPaths::for_venv(version, cfg!(windows)))
+            .into_diagnostic()?
+    } else {
+        Vec::new()
+    };
+    let requested = lockfile
+        .requirements
+        .iter()
+        .filter_map(|requirement| PackageName::from_str(&requirement.name).ok())
+        .map(NormalizedPackageName::from)
+        .collect();
+    let plan = SyncPlan::with_options(
+        installed,
+        &packages,
+        SyncPlanOptions {
+            installer: Some(String::from(INSTALLER)),
+            requested,
+            ..SyncPlanOptions::default()
+        },
+    );
+
+    if output_format() == OutputFormat::Human {
+        print_plan(&plan);
+        if plan.is_empty() {
+            println!("{}", console::style("The environment is up to date").bold());
+        }
+    }
+    if !plan.is_empty() {
+        check_license_policy(&package_db, &packages).await?;
+    }
+    if plan.is_empty() || dry_run {
+        return match output_format() {
+            OutputFormat::Human => Ok(()),
+            OutputFormat::Json => print_json(
+                "sync",
+                &SyncOutput {
+                    dry_run,
+                    report: InstallReport::from_plan(&plan),
+                },
+            ),
+        };
+    }
+
+    let venv = VEnv::create(target, python_location).into_diagnostic()?;
+    let env_markers = Arc::new(
+        Pep508EnvMakers::from_python(&venv.python_executable())
+            .await
+            .into_diagnostic()?
+            .0,
+    );
+    let wheel_tags = Arc::new(WheelTags::from_target(&interpreter));
+    let wheel_builder = WheelBuilder::new(
+        package_db.clone(),
+        env_markers,
+        Some(wheel_tags),
+        ResolveOptions {
+            python_location: PythonLocation::Custom(venv.python_executable()),
+            ..Default::default()
+        },
+        Default::default(),
+    )
+    .into_diagnostic()?
+    .with_config_settings(build_config_settings(Vec::new()));
+
+    let options = UnpackWheelOptions {
+        installer: Some(String::from(INSTALLER)),
+        install_options,
+        ..Default::default()
+    };
+    let report =
+        execute_sync_plan(&venv, &package_db, Some(&wheel_builder), &plan, &options).await?;
+
+    match output_format() {
+        OutputFormat::Human => {
+            println!(
+                "\n{}",
+                console::style("Successfully synchronized environment!").bold()
+            );
+            Ok(())
+        }
+        OutputFormat::Json => print_json(
+            "sync",
+            &SyncOutput {
+                dry_run: false,
+                report,
+            },
+        ),
+    }
+}
+
+/// Prints the operations of the plan, one line per package.
+fn print_plan(plan: &SyncPlan) {
+    for package in plan.install.iter().sorted_by(|a, b| a.name.cmp(&b.name)) {
+        println!(
+            "{} {} {}",
+            console::style("+").green(),
+            console::style(&package.name).bold(),
+            package.version
+        );
+    }
+    for (distribution, package) in plan
+        .reinstall
+        .iter()
+        .sorted_by(|a, b| a.1.name.cmp(&b.1.name))
+    {
+        println!(
+            "{} {} {} -> {}",
+            console::style("~").yellow(),
+            console::style(&package.name).bold(),
+            distribution.version,
+            package.version
+        );
+    }
+    for distribution in plan.remove.iter().sorted_by(|a, b| a.name.cmp(&b.name)) {
+        println!(
+            "{} {} {}",
+            console::style("-").red(),
+            console::style(&distribution.name).bold(),
+            distribution.version
+        );
+    }
+}
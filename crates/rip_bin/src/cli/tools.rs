@@ -0,0 +1,183 @@
+use crate::cli::resolve::{self, InstallArgs};
+use clap::{Parser, Subcommand};
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::python_env::{PythonLocation, VEnv};
+use rattler_installs_packages::tools::{ToolReceipt, ToolsDirectory};
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName, Requirement};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+
+    /// The directory that contains the environments of the tools
+    #[clap(long, global = true)]
+    tools_dir: Option<PathBuf>,
+
+    /// The directory into which the scripts of the tools are linked, this should be on the PATH
+    #[clap(long, global = true)]
+    bin_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Install an application into its own environment and link its scripts into the bin directory
+    Install(ToolInstallArgs),
+
+    /// List the installed tools
+    List,
+
+    /// Upgrade an installed tool to the newest available version
+    Upgrade {
+        /// The name of the tool to upgrade
+        name: PackageName,
+    },
+
+    /// Remove an installed tool and its scripts
+    Uninstall {
+        /// The name of the tool to remove
+        name: PackageName,
+    },
+}
+
+#[derive(Parser)]
+pub struct ToolInstallArgs {
+    /// The application to install followed by any additional requirements to install into its
+    /// environment
+    #[clap(num_args = 1.., required = true)]
+    specs: Vec<Requirement>,
+
+    /// Path to the python interpreter to create the environment of the tool with
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Reinstall the tool if it is already installed
+    #[clap(long)]
+    force: bool,
+}
+
+pub async fn tools(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let tools = tools_directory(args.tools_dir, args.bin_dir)?;
+    match args.command {
+        Commands::Install(args) => install_tool(package_db, &tools, args).await,
+        Commands::List => list_tools(&tools),
+        Commands::Upgrade { name } => upgrade_tool(package_db, &tools, name.into()).await,
+        Commands::Uninstall { name } => {
+            let receipt = tools.remove(&name.into()).into_diagnostic()?;
+            println!("removed {}", receipt.name);
+            Ok(())
+        }
+    }
+}
+
+/// Determines the tools directory from the arguments or the default locations of the platform.
+fn tools_directory(
+    tools_dir: Option<PathBuf>,
+    bin_dir: Option<PathBuf>,
+) -> miette::Result<ToolsDirectory> {
+    let tools_dir = match tools_dir {
+        Some(tools_dir) => tools_dir,
+        None => dirs::data_dir()
+            .ok_or_else(|| miette::miette!("failed to determine the data directory"))?
+            .join("rattler/pypi-tools"),
+    };
+    let bin_dir = match bin_dir {
+        Some(bin_dir) => bin_dir,
+        None => dirs::executable_dir()
+            .or_else(|| dirs::home_dir().map(|home| home.join(".local/bin")))
+            .ok_or_else(|| miette::miette!("failed to determine the bin directory"))?,
+    };
+    Ok(ToolsDirectory::new(tools_dir, bin_dir))
+}
+
+async fn install_tool(
+    package_db: Arc<PackageDb>,
+    tools: &ToolsDirectory,
+    args: ToolInstallArgs,
+) -> miette::Result<()> {
+    let name =
+        NormalizedPackageName::from(PackageName::from_str(&args.specs[0].name).into_diagnostic()?);
+    if !args.force && tools.receipt(&name).into_diagnostic()?.is_some() {
+        miette::bail!("{name} is already installed, use `rip tool upgrade {name}` to upgrade it");
+    }
+
+    let mut receipt = ToolReceipt {
+        name,
+        requirements: args.specs.iter().map(ToString::to_string).collect(),
+        python: args.python_interpreter,
+        scripts: Vec::new(),
+    };
+    if let Some(existing) = tools.receipt(&receipt.name).into_diagnostic()? {
+        receipt.scripts = existing.scripts;
+    }
+
+    install_into_tool_env(package_db, tools, &mut receipt, false).await
+}
+
+async fn upgrade_tool(
+    package_db: Arc<PackageDb>,
+    tools: &ToolsDirectory,
+    name: NormalizedPackageName,
+) -> miette::Result<()> {
+    let mut receipt = tools
+        .receipt(&name)
+        .into_diagnostic()?
+        .ok_or_else(|| miette::miette!("{name} is not installed"))?;
+    install_into_tool_env(package_db, tools, &mut receipt, true).await
+}
+
+/// Installs the requirements of the tool into its environment, links its scripts and stores the
+/// receipt.
+async fn install_into_tool_env(
+    package_db: Arc<PackageDb>,
+    tools: &ToolsDirectory,
+    receipt: &mut ToolReceipt,
+    upgrade: bool,
+) -> miette::Result<()> {
+    let tool_dir = tools.tool_dir(&receipt.name);
+
+    let mut install_args: Vec<OsString> = vec!["install".into()];
+    install_args.extend(receipt.requirements.iter().map(OsString::from));
+    install_args.push(tool_dir.clone().into());
+    if let Some(python) = &receipt.python {
+        install_args.extend(["--python-interpreter".into(), python.clone().into()]);
+    }
+    if upgrade {
+        install_args.push("--upgrade".into());
+    }
+    let install_args = InstallArgs::try_parse_from(install_args).into_diagnostic()?;
+    resolve::execute(package_db, resolve::Commands::Install(install_args)).await?;
+
+    let python = match &receipt.python {
+        Some(python) => PythonLocation::Custom(python.clone()),
+        None => PythonLocation::System,
+    };
+    let venv = VEnv::create(&tool_dir, python).into_diagnostic()?;
+    let linked = tools
+        .link_scripts(receipt, &venv)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to link the scripts of {}", receipt.name))?;
+    tools.write_receipt(receipt).into_diagnostic()?;
+
+    for script in linked {
+        println!("linked {}", script.display());
+    }
+    Ok(())
+}
+
+fn list_tools(tools: &ToolsDirectory) -> miette::Result<()> {
+    for receipt in tools.list().into_diagnostic()? {
+        println!(
+            "{} ({}): {}",
+            console::style(&receipt.name).bold().green(),
+            receipt.requirements.join(", "),
+            receipt.scripts.join(", ")
+        );
+    }
+    Ok(())
+}
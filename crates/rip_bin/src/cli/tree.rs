@@ -0,0 +1,225 @@
+use crate::cli::resolve::{
+    collect_requirements, pre_release_resolution, RequirementFileArgs, SDistResolutionArgs, Spec,
+};
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, Pep508EnvMakers, PythonInterpreterVersion, PythonLocation,
+    WheelTags,
+};
+use rattler_installs_packages::resolve::solve_options::ResolveOptions;
+use rattler_installs_packages::resolve::{resolve, DependencyGraph};
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(flatten)]
+    graph: GraphArgs,
+
+    /// Show the packages that depend on this package instead of the dependencies
+    #[clap(long)]
+    invert: Option<PackageName>,
+}
+
+/// The JSON output of `rip tree`: the packages of the graph and the packages the tree starts at.
+#[derive(Serialize)]
+struct TreeOutput {
+    roots: Vec<String>,
+    inverted: bool,
+    packages: Vec<TreePackage>,
+}
+
+#[derive(Serialize)]
+struct TreePackage {
+    name: String,
+    version: String,
+    extras: Vec<String>,
+    dependencies: Vec<TreeDependency>,
+}
+
+#[derive(Serialize)]
+struct TreeDependency {
+    requirement: String,
+    /// The package that satisfies the requirement, if it is part of the graph
+    name: Option<String>,
+}
+
+/// The arguments that select the packages of a dependency graph, either by resolving requirements
+/// or from an environment.
+#[derive(Parser)]
+pub struct GraphArgs {
+    /// The specs to resolve. A path to a project directory, optionally followed by extras like
+    /// `.[test]`, resolves the project itself
+    #[clap(required_unless_present_any = ["requirement", "installed"])]
+    specs: Vec<Spec>,
+
+    #[clap(flatten)]
+    requirement_files: RequirementFileArgs,
+
+    /// Use the packages that are installed in this virtual environment instead of resolving
+    /// requirements
+    #[clap(long, conflicts_with_all = ["specs", "requirement"])]
+    installed: Option<PathBuf>,
+
+    /// How to handle SDists
+    #[clap(flatten)]
+    sdist_resolution: SDistResolutionArgs,
+
+    /// Path to the python interpreter to use for resolving environment markers
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Prefer pre-releases to normal releases
+    #[clap(long)]
+    pre: bool,
+}
+
+pub async fn tree(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let invert = args.invert.map(NormalizedPackageName::from);
+    let (graph, roots) = dependency_graph(package_db, args.graph).await?;
+
+    let roots = match &invert {
+        Some(name) => {
+            if graph.node(name).is_none() {
+                miette::bail!("{} is not part of the environment", name);
+            }
+            vec![name]
+        }
+        None => roots.iter().collect(),
+    };
+
+    match output_format() {
+        OutputFormat::Human => {
+            print!("{}", graph.render(&roots, invert.is_some()));
+            Ok(())
+        }
+        OutputFormat::Json => {
+            let output = TreeOutput {
+                roots: roots.iter().map(ToString::to_string).collect(),
+                inverted: invert.is_some(),
+                packages: graph
+                    .nodes()
+                    .map(|node| TreePackage {
+                        name: node.name.to_string(),
+                        version: node.version.to_string(),
+                        extras: node.extras.iter().map(|e| e.as_str().to_string()).collect(),
+                        dependencies: graph
+                            .dependencies(&node.name)
+                            .map(|(requirement, dependency)| TreeDependency {
+                                requirement: requirement.to_string(),
+                                name: dependency.map(|dependency| dependency.name.to_string()),
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            };
+            print_json("tree", &output)
+        }
+    }
+}
+
+/// Returns the dependency graph that is selected by the arguments, together with its roots: the
+/// requested packages or, for an environment, the packages that no other package depends on.
+pub(crate) async fn dependency_graph(
+    package_db: Arc<PackageDb>,
+    mut args: GraphArgs,
+) -> miette::Result<(DependencyGraph, Vec<NormalizedPackageName>)> {
+    match args.installed.take() {
+        Some(venv) => installed_graph(&venv).await,
+        None => resolved_graph(package_db, args).await,
+    }
+}
+
+/// Resolves the requirements and returns the graph of the result, together with the requested
+/// packages.
+async fn resolved_graph(
+    package_db: Arc<PackageDb>,
+    args: GraphArgs,
+) -> miette::Result<(DependencyGraph, Vec<NormalizedPackageName>)> {
+    let requirements = collect_requirements(&args.specs, &args.requirement_files.requirement)?;
+
+    let python_location = match args.python_interpreter {
+        Some(python_interpreter) => PythonLocation::Custom(python_interpreter),
+        None => PythonLocation::System,
+    };
+    let python = python_location.executable().into_diagnostic()?;
+    let env_markers = Arc::new(
+        Pep508EnvMakers::from_python(&python)
+            .await
+            .into_diagnostic()
+            .wrap_err("failed to determine the environment markers of the python interpreter")?
+            .0,
+    );
+    let compatible_tags = Arc::new(WheelTags::from_python(&python).await.into_diagnostic()?);
+
+    let pre_release_resolution = pre_release_resolution(args.pre, &requirements);
+    let resolve_options = ResolveOptions {
+        sdist_resolution: args.sdist_resolution.into(),
+        python_location,
+        pre_release_resolution,
+        ..Default::default()
+    };
+
+    let packages = resolve(
+        package_db,
+        &requirements,
+        env_markers,
+        Some(compatible_tags),
+        HashMap::default(),
+        HashMap::default(),
+        resolve_options,
+        HashMap::default(),
+    )
+    .await
+    .wrap_err("Could not solve for requested requirements")?;
+
+    let roots = requirements
+        .iter()
+        .filter_map(|requirement| PackageName::from_str(&requirement.name).ok())
+        .map(NormalizedPackageName::from)
+        .unique()
+        .collect();
+    Ok((DependencyGraph::from_pinned(&packages), roots))
+}
+
+/// Returns the graph of the packages that are installed in the virtual environment at `venv`,
+/// together with the packages that no other package depends on.
+async fn installed_graph(
+    venv: &Path,
+) -> miette::Result<(DependencyGraph, Vec<NormalizedPackageName>)> {
+    let python = venv.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", venv.display());
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let distributions =
+        find_distributions_in_venv(venv, &InstallPaths::for_venv(version, cfg!(windows)))
+            .into_diagnostic()?;
+    let env_markers = Pep508EnvMakers::from_python(&python)
+        .await
+        .into_diagnostic()?
+        .0;
+
+    let graph = DependencyGraph::from_installed(venv, &distributions, &env_markers);
+    let roots = graph
+        .roots()
+        .into_iter()
+        .map(|node| node.name.clone())
+        .collect();
+    Ok((graph, roots))
+}
@@ -0,0 +1,187 @@
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
+use rattler_installs_packages::artifacts::wheel::InstallPaths;
+use rattler_installs_packages::install::RemoveReportItem;
+use rattler_installs_packages::python_env::{
+    find_distributions_in_venv, uninstall_distribution, Distribution, Pep508EnvMakers,
+    PythonInterpreterVersion,
+};
+use rattler_installs_packages::resolve::DependencyGraph;
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The virtual environment to remove the packages from
+    target: PathBuf,
+
+    /// The packages to remove
+    #[clap(num_args = 1.., required = true)]
+    packages: Vec<PackageName>,
+
+    /// Also remove the dependencies of the packages that are no longer required by any other
+    /// package and that were not explicitly installed
+    #[clap(long)]
+    orphans: bool,
+
+    /// Only print the packages that would be removed
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Remove the packages without asking for confirmation
+    #[clap(long, short)]
+    yes: bool,
+}
+
+/// The JSON output of `rip uninstall`.
+#[derive(Serialize)]
+struct UninstallOutput {
+    /// True if the packages were only listed and not removed
+    dry_run: bool,
+    removed: Vec<RemoveReportItem>,
+}
+
+pub async fn uninstall(args: Args) -> miette::Result<()> {
+    let python = args.target.join(if cfg!(windows) {
+        "Scripts/python.exe"
+    } else {
+        "bin/python"
+    });
+    if !python.is_file() {
+        miette::bail!("{} is not a virtual environment", args.target.display());
+    }
+    let json = output_format() == OutputFormat::Json;
+    if json && !(args.yes || args.dry_run) {
+        miette::bail!("`--format json` cannot ask for confirmation, pass `--yes` or `--dry-run`");
+    }
+
+    let version = PythonInterpreterVersion::from_path(&python).into_diagnostic()?;
+    let distributions = find_distributions_in_venv(
+        &args.target,
+        &InstallPaths::for_venv(version, cfg!(windows)),
+    )
+    .into_diagnostic()?;
+    let env_markers = Pep508EnvMakers::from_python(&python)
+        .await
+        .into_diagnostic()?
+        .0;
+    let graph = DependencyGraph::from_installed(&args.target, &distributions, &env_markers);
+
+    let mut removed = Vec::new();
+    for name in args.packages.into_iter().map(NormalizedPackageName::from) {
+        if !distributions
+            .iter()
+            .any(|distribution| distribution.name == name)
+        {
+            miette::bail!("{name} is not installed in {}", args.target.display());
+        }
+        if !removed.contains(&name) {
+            removed.push(name);
+        }
+    }
+    if args.orphans {
+        let orphans = graph
+            .orphans(&removed, |node| {
+                distributions
+                    .iter()
+                    .any(|distribution| distribution.name == node.name && distribution.requested)
+            })
+            .into_iter()
+            .map(|node| node.name.clone())
+            .collect_vec();
+        removed.extend(orphans);
+    }
+
+    // Packages that remain in the environment but depend on a removed package are broken
+    // afterwards, warn about them before anything is removed.
+    for name in &removed {
+        let dependents = graph
+            .dependents(name)
+            .map(|(dependent, _)| &dependent.name)
+            .filter(|dependent| !removed.contains(dependent))
+            .unique()
+            .join(", ");
+        if !dependents.is_empty() {
+            tracing::warn!("{name} is required by {dependents}");
+        }
+    }
+
+    let removed = removed
+        .iter()
+        .filter_map(|name| {
+            distributions
+                .iter()
+                .find(|distribution| &distribution.name == name)
+        })
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect_vec();
+    if !json {
+        for distribution in &removed {
+            println!(
+                "{} {} {}",
+                console::style("-").red(),
+                console::style(&distribution.name).bold(),
+                distribution.version
+            );
+        }
+    }
+    if args.dry_run || !(args.yes || confirm()?) {
+        return print_output(&removed, true);
+    }
+
+    for distribution in &removed {
+        // The paths in the RECORD file are relative to the site-packages directory
+        let dist_info = args.target.join(&distribution.dist_info);
+        let (Some(site_packages), Some(dist_info_dir)) =
+            (dist_info.parent(), dist_info.file_name())
+        else {
+            continue;
+        };
+        uninstall_distribution(site_packages, Path::new(dist_info_dir))
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to uninstall {}", distribution.name))?;
+    }
+
+    print_output(&removed, false)
+}
+
+/// Prints the distributions that were, or with `dry_run` would be, removed.
+fn print_output(removed: &[&Distribution], dry_run: bool) -> miette::Result<()> {
+    match output_format() {
+        OutputFormat::Human if dry_run => Ok(()),
+        OutputFormat::Human => {
+            println!(
+                "\n{}",
+                console::style(format!("Successfully removed {} package(s)", removed.len())).bold()
+            );
+            Ok(())
+        }
+        OutputFormat::Json => print_json(
+            "uninstall",
+            &UninstallOutput {
+                dry_run,
+                removed: removed
+                    .iter()
+                    .map(|distribution| RemoveReportItem {
+                        name: distribution.name.clone(),
+                        version: distribution.version.clone(),
+                    })
+                    .collect(),
+            },
+        ),
+    }
+}
+
+/// Asks the user whether the packages should be removed.
+fn confirm() -> miette::Result<bool> {
+    print!("Proceed (y/N)? ");
+    std::io::stdout().flush().into_diagnostic()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).into_diagnostic()?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
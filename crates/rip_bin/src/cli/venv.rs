@@ -0,0 +1,225 @@
+use crate::cli::resolve::{managed_pythons, INSTALLER};
+use clap::{Parser, Subcommand};
+use fs_err as fs;
+use miette::IntoDiagnostic;
+use rattler_installs_packages::artifacts::wheel::UnpackWheelOptions;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::install::{seed_venv, DEFAULT_SEED_PACKAGES};
+use rattler_installs_packages::python_env::{
+    find_interpreter, Implementation, PythonLocation, VEnv, VEnvOptions,
+};
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName, VersionSpecifiers};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub struct Args {
+    #[clap(subcommand)]
+    command: Commands,
+
+    /// The directory that contains the environments that are referred to by name
+    #[clap(long, global = true)]
+    venvs_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Create a virtual environment
+    Create(CreateArgs),
+
+    /// List the virtual environments in the environments directory
+    List,
+
+    /// Remove a virtual environment
+    Remove {
+        /// The name of the environment in the environments directory or a path to an environment
+        env: String,
+    },
+}
+
+#[derive(Parser)]
+pub struct CreateArgs {
+    /// The name of the environment in the environments directory or a path to an environment
+    env: String,
+
+    /// Path to the python interpreter to create the environment with
+    #[clap(long, short)]
+    python_interpreter: Option<PathBuf>,
+
+    /// Select the best interpreter on the PATH that matches this version specifier, e.g.
+    /// ">=3.10,<3.13", instead of specifying its path
+    #[clap(long, conflicts_with = "python_interpreter")]
+    python_version: Option<VersionSpecifiers>,
+
+    /// The implementation of the interpreter to select with `--python-version`
+    #[clap(long, default_value = "any", requires = "python_version")]
+    python_implementation: Implementation,
+
+    /// Download a standalone python build if no interpreter on the PATH matches
+    /// `--python-version`
+    #[clap(long, requires = "python_version")]
+    managed_python: bool,
+
+    /// The prompt that is displayed when the environment is activated, defaults to the name of
+    /// the environment
+    #[clap(long)]
+    prompt: Option<String>,
+
+    /// Install pip, setuptools and wheel into the environment
+    #[clap(long)]
+    seed: bool,
+
+    /// The packages to install with `--seed`, can be specified multiple times
+    #[clap(long, requires = "seed")]
+    seed_package: Vec<PackageName>,
+
+    /// Give the environment access to the packages of the base interpreter
+    #[clap(long)]
+    system_site_packages: bool,
+
+    /// Copy the interpreter into the environment instead of symlinking it
+    #[clap(long)]
+    copies: bool,
+
+    /// Upgrade the interpreter of an existing environment
+    #[clap(long)]
+    upgrade: bool,
+}
+
+pub async fn venv(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let venvs_dir = match args.venvs_dir {
+        Some(venvs_dir) => venvs_dir,
+        None => dirs::data_dir()
+            .ok_or_else(|| miette::miette!("failed to determine the data directory"))?
+            .join("rattler/pypi-venvs"),
+    };
+    match args.command {
+        Commands::Create(args) => create(package_db, &venvs_dir, args).await,
+        Commands::List => list(&venvs_dir),
+        Commands::Remove { env } => remove(&venv_path(&venvs_dir, &env)),
+    }
+}
+
+/// Environments are referred to by their name in the environments directory, unless a path is
+/// specified.
+fn venv_path(venvs_dir: &Path, env: &str) -> PathBuf {
+    if env.starts_with('.') || env.contains(['/', '\\']) {
+        PathBuf::from(env)
+    } else {
+        venvs_dir.join(env)
+    }
+}
+
+async fn create(
+    package_db: Arc<PackageDb>,
+    venvs_dir: &Path,
+    args: CreateArgs,
+) -> miette::Result<()> {
+    let path = venv_path(venvs_dir, &args.env);
+
+    let python_location = match (args.python_interpreter, args.python_version) {
+        (Some(python_interpreter), _) => PythonLocation::Custom(python_interpreter),
+        (None, Some(python_version)) => {
+            let interpreter = match find_interpreter(&python_version, args.python_implementation) {
+                Ok(interpreter) => interpreter,
+                Err(_) if args.managed_python => managed_pythons(&package_db)
+                    .find_or_install(&python_version)
+                    .await
+                    .into_diagnostic()?,
+                Err(err) => return Err(err).into_diagnostic(),
+            };
+            PythonLocation::CustomWithVersion(interpreter.path, interpreter.version)
+        }
+        (None, None) => PythonLocation::System,
+    };
+
+    let venv = VEnv::create_with_options(
+        &path,
+        python_location,
+        &VEnvOptions {
+            prompt: args.prompt,
+            system_site_packages: args.system_site_packages,
+            copies: args.copies,
+            upgrade: args.upgrade,
+            ..VEnvOptions::default()
+        },
+    )
+    .into_diagnostic()?;
+
+    if args.seed {
+        let packages = if args.seed_package.is_empty() {
+            DEFAULT_SEED_PACKAGES
+                .iter()
+                .map(|name| {
+                    NormalizedPackageName::from_str(name).expect("seed packages are valid names")
+                })
+                .collect()
+        } else {
+            args.seed_package
+                .into_iter()
+                .map(NormalizedPackageName::from)
+                .collect::<Vec<_>>()
+        };
+        let options = UnpackWheelOptions {
+            installer: Some(String::from(INSTALLER)),
+            ..Default::default()
+        };
+        seed_venv(&venv, &package_db, &packages, &options).await?;
+    }
+
+    println!(
+        "Created virtual environment at {}",
+        console::style(venv.root().display()).bold()
+    );
+    Ok(())
+}
+
+fn list(venvs_dir: &Path) -> miette::Result<()> {
+    if !venvs_dir.is_dir() {
+        return Ok(());
+    }
+
+    let mut venvs = fs::read_dir(venvs_dir)
+        .into_diagnostic()?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let version = python_version(&entry.path())?;
+            Some((entry.file_name().to_string_lossy().into_owned(), version))
+        })
+        .collect::<Vec<_>>();
+    venvs.sort();
+
+    let mut tabbed_stdout = tabwriter::TabWriter::new(std::io::stdout());
+    for (name, version) in venvs {
+        writeln!(
+            tabbed_stdout,
+            "{}\t{}",
+            console::style(name).bold(),
+            version
+        )
+        .into_diagnostic()?;
+    }
+    tabbed_stdout.flush().into_diagnostic()
+}
+
+fn remove(path: &Path) -> miette::Result<()> {
+    if python_version(path).is_none() {
+        miette::bail!("{} is not a virtual environment", path.display());
+    }
+    fs::remove_dir_all(path).into_diagnostic()?;
+    println!("Removed {}", path.display());
+    Ok(())
+}
+
+/// Returns the python version that is recorded in the `pyvenv.cfg` file of the environment at
+/// `path`, or `None` if `path` is not a virtual environment.
+fn python_version(path: &Path) -> Option<String> {
+    let cfg = fs::read_to_string(path.join("pyvenv.cfg")).ok()?;
+    let version = cfg.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "version").then(|| value.trim().to_string())
+    });
+    Some(version.unwrap_or_default())
+}
@@ -1,4 +1,5 @@
 use clap::{Parser, Subcommand};
+#[cfg(feature = "sdist")]
 use miette::IntoDiagnostic;
 use rattler_installs_packages::index::PackageDb;
 use std::sync::Arc;
@@ -23,6 +24,7 @@ pub fn wheels(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
     }
 }
 
+#[cfg(feature = "sdist")]
 fn list_wheels(package_db: Arc<PackageDb>) -> miette::Result<()> {
     let wheels = package_db.local_wheel_cache().wheels();
     for wheel in wheels {
@@ -31,3 +33,9 @@ fn list_wheels(package_db: Arc<PackageDb>) -> miette::Result<()> {
 
     Ok(())
 }
+
+/// Without the `sdist` feature no wheels are built, so there are none to list.
+#[cfg(not(feature = "sdist"))]
+fn list_wheels(_package_db: Arc<PackageDb>) -> miette::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use crate::cli::tree::{dependency_graph, GraphArgs};
+use crate::{output_format, print_json, OutputFormat};
+use clap::Parser;
+use rattler_installs_packages::index::PackageDb;
+use rattler_installs_packages::types::{NormalizedPackageName, PackageName};
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Args {
+    /// The package to explain
+    package: PackageName,
+
+    #[clap(flatten)]
+    graph: GraphArgs,
+}
+
+/// The JSON output of `rip why`.
+#[derive(Serialize)]
+struct WhyOutput {
+    name: String,
+    version: String,
+    /// Every chain of dependents from a top-level package down to the package, an empty chain
+    /// means the package is a top-level package itself
+    chains: Vec<Vec<ChainLink>>,
+}
+
+#[derive(Serialize)]
+struct ChainLink {
+    name: String,
+    version: String,
+    /// The requirement of this package on the next package of the chain
+    requirement: String,
+}
+
+pub async fn why(package_db: Arc<PackageDb>, args: Args) -> miette::Result<()> {
+    let name = NormalizedPackageName::from(args.package);
+    let (graph, roots) = dependency_graph(package_db, args.graph).await?;
+    let Some(node) = graph.node(&name) else {
+        miette::bail!("{name} is not part of the environment");
+    };
+
+    let roots = roots.iter().collect::<Vec<_>>();
+    let chains = graph.requirement_chains(&name, &roots);
+    if output_format() == OutputFormat::Json {
+        return print_json(
+            "why",
+            &WhyOutput {
+                name: node.name.to_string(),
+                version: node.version.to_string(),
+                chains: chains
+                    .into_iter()
+                    .map(|chain| {
+                        chain
+                            .into_iter()
+                            .map(|(dependent, requirement)| ChainLink {
+                                name: dependent.name.to_string(),
+                                version: dependent.version.to_string(),
+                                requirement: requirement.to_string(),
+                            })
+                            .collect()
+                    })
+                    .collect(),
+            },
+        );
+    }
+
+    println!(
+        "{} {} is required by:",
+        console::style(&node.name).bold(),
+        node.version
+    );
+    for chain in chains {
+        if chain.is_empty() {
+            println!("- it is a top-level package");
+            continue;
+        }
+        let chain = chain
+            .into_iter()
+            .map(|(dependent, requirement)| {
+                format!(
+                    "{} {} ({})",
+                    console::style(&dependent.name).bold(),
+                    dependent.version,
+                    requirement
+                )
+            })
+            .collect::<Vec<_>>();
+        println!("- {} -> {}", chain.join(" -> "), node.name);
+    }
+    Ok(())
+}
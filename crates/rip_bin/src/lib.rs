@@ -1,9 +1,98 @@
+use clap::ValueEnum;
 use indicatif::{MultiProgress, ProgressDrawTarget};
+use miette::IntoDiagnostic;
+use rattler_installs_packages::config::Config;
+#[cfg(feature = "git")]
+use rattler_installs_packages::index::GitCredentialCallback;
+use serde::Serialize;
 use std::io;
+#[cfg(feature = "git")]
+use std::path::Path;
 use std::sync::OnceLock;
 use tracing_subscriber::fmt::MakeWriter;
 
 pub mod cli;
+pub mod progress;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Returns the configuration that was loaded from the configuration files and environment
+/// variables, see [`set_global_config`]. The settings of command line flags take precedence.
+pub fn global_config() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Sets the configuration that is returned by [`global_config`]. This can only be done once,
+/// before the configuration is used.
+pub fn set_global_config(config: Config) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("the configuration was already initialized");
+    }
+}
+
+/// Returns the config settings for build backends from the configuration, followed by the
+/// settings in `overrides`. Settings in `overrides` replace those with the same key.
+pub fn build_config_settings(overrides: Vec<(String, String)>) -> Vec<(String, String)> {
+    let mut settings = global_config()
+        .build
+        .config_settings
+        .iter()
+        .flatten()
+        .filter(|(key, _)| !overrides.iter().any(|(k, _)| k == *key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<Vec<_>>();
+    settings.extend(overrides);
+    settings
+}
+
+/// The format in which commands print their results to stdout.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Text that is meant to be read by humans
+    #[default]
+    Human,
+    /// A JSON document that is meant to be read by programs
+    Json,
+}
+
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Returns the format in which commands print their results, see [`set_output_format`].
+pub fn output_format() -> OutputFormat {
+    *OUTPUT_FORMAT.get_or_init(OutputFormat::default)
+}
+
+/// Sets the format that is returned by [`output_format`]. This can only be done once.
+pub fn set_output_format(format: OutputFormat) {
+    if OUTPUT_FORMAT.set(format).is_err() {
+        tracing::warn!("the output format was already initialized");
+    }
+}
+
+/// The version of the schemas of the JSON output. It is increased when fields are removed or
+/// change their meaning, adding fields is not considered a breaking change.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct JsonOutput<'a, T> {
+    schema_version: u32,
+    command: &'a str,
+    #[serde(flatten)]
+    output: &'a T,
+}
+
+/// Prints the output of a command as JSON to stdout. The fields of `output` are preceded by the
+/// `schema_version` and the name of the `command` so consumers can tell the schemas apart.
+pub fn print_json<T: Serialize>(command: &str, output: &T) -> miette::Result<()> {
+    let json = serde_json::to_string_pretty(&JsonOutput {
+        schema_version: JSON_SCHEMA_VERSION,
+        command,
+        output,
+    })
+    .into_diagnostic()?;
+    println!("{json}");
+    Ok(())
+}
 
 /// Returns a global instance of [`indicatif::MultiProgress`].
 ///
@@ -49,3 +138,27 @@ impl<'a> MakeWriter<'a> for IndicatifWriter {
         self.clone()
     }
 }
+
+/// Asks for the passphrase of an encrypted ssh key of a git dependency. The passphrase is taken
+/// from `RIP_GIT_SSH_KEY_PASSPHRASE` if it is set, otherwise it is read from the terminal.
+#[cfg(feature = "git")]
+pub struct SshPassphrasePrompt;
+
+#[cfg(feature = "git")]
+impl GitCredentialCallback for SshPassphrasePrompt {
+    fn ssh_key_passphrase(&self, key: &Path) -> Option<String> {
+        if let Ok(passphrase) = std::env::var("RIP_GIT_SSH_KEY_PASSPHRASE") {
+            return Some(passphrase);
+        }
+
+        let term = console::Term::stderr();
+        if !term.is_term() {
+            return None;
+        }
+        global_multi_progress().suspend(|| {
+            term.write_str(&format!("Enter passphrase for key '{}': ", key.display()))
+                .ok()?;
+            term.read_secure_line().ok()
+        })
+    }
+}
@@ -1,22 +1,41 @@
-use rip_bin::{cli, global_multi_progress, IndicatifWriter};
+use rip_bin::{
+    cli, global_multi_progress, set_global_config, set_output_format, IndicatifWriter, OutputFormat,
+};
 
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 
-use clap::{Parser, Subcommand};
-use miette::Context;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use miette::{Context, IntoDiagnostic};
 use tracing_subscriber::filter::Directive;
+use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use rattler_installs_packages::index::PackageSourcesBuilder;
+use rattler_installs_packages::config::ConfigLoader;
+use rattler_installs_packages::index::{
+    CacheSetting, ConcurrencyLimits, ForgeTokens, PackageSourcesBuilder, TufRepository,
+    TyposquattingCheck,
+};
+#[cfg(feature = "git")]
+use rattler_installs_packages::{config::GitConfig, index::GitAuth};
+#[cfg(feature = "git")]
+use rip_bin::SshPassphrasePrompt;
 
 use rattler_installs_packages::normalize_index_url;
 use reqwest::Client;
 use reqwest_middleware::ClientWithMiddleware;
 use rip_bin::cli::wheels::wheels;
+use rip_bin::progress::ProgressReporter;
+use tokio_util::sync::CancellationToken;
 use tracing::metadata::LevelFilter;
 use url::Url;
 
+/// The index that is used if no other index is configured.
+const DEFAULT_INDEX_URL: &str = "https://pypi.org/simple/";
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -31,8 +50,65 @@ struct Cli {
 
     /// Base URL of the Python Package Index (default <https://pypi.org/simple>). This should point
     /// to a repository compliant with PEP 503 (the simple repository API).
-    #[clap(default_value = "https://pypi.org/simple/", long, global = true)]
-    index_url: Url,
+    #[clap(long, global = true)]
+    index_url: Option<Url>,
+
+    /// A local directory with artifacts to use next to the index, e.g. one that was created with
+    /// `rip download`. Can be specified multiple times
+    #[clap(long, global = true)]
+    find_links: Vec<PathBuf>,
+
+    /// Ignore the index and only use the artifacts from the `--find-links` directories
+    #[clap(long, global = true)]
+    no_index: bool,
+
+    /// The directory in which downloads, metadata and built wheels are cached
+    #[clap(long, global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Never access the network, index pages and artifacts are only taken from the cache
+    #[clap(long, global = true, conflicts_with_all = ["refresh", "no_cache"])]
+    offline: bool,
+
+    /// Revalidate all cached index pages and artifacts with the server
+    #[clap(long, global = true, conflicts_with = "no_cache")]
+    refresh: bool,
+
+    /// Don't read from or write to the HTTP cache
+    #[clap(long, global = true)]
+    no_cache: bool,
+
+    /// The maximum number of requests to the index, including downloads, that are performed at the
+    /// same time
+    #[clap(long, global = true)]
+    max_connections: Option<NonZeroUsize>,
+
+    /// The maximum number of requests to a single host that are performed at the same time, e.g.
+    /// to avoid rate limits of the CDN of an index
+    #[clap(long, global = true)]
+    max_connections_per_host: Option<NonZeroUsize>,
+
+    /// Warn about requested packages whose name looks like the name of a popular package, e.g.
+    /// `reqeusts` instead of `requests`
+    #[clap(long, global = true)]
+    typosquatting_check: bool,
+
+    /// Log when each span of the library closes and how long it took, see the `telemetry` module
+    /// of the library for the spans that are emitted
+    #[clap(long, global = true)]
+    log_spans: bool,
+
+    /// Write the metrics of the command, like the number of requests, downloaded bytes and how
+    /// long builds and installs took, as JSON to this file when the command finishes
+    #[clap(long, global = true)]
+    metrics_file: Option<PathBuf>,
+
+    /// The format of the output. `json` is supported by the commands that report on packages:
+    /// `install`, `resolve`, `lock`, `import`, `sync`, `uninstall`, `download`, `cache`, `tree`,
+    /// `show`, `why`, `search`, `freeze`, `audit` and `licenses`. `run`, `tool`, `venv`, `add`,
+    /// `remove`, `build`, `wheels` and `sbom` (which writes its own JSON document) refuse it
+    #[clap(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
 }
 
 #[derive(Subcommand)]
@@ -40,53 +116,313 @@ enum Commands {
     /// Options w.r.t locally built wheels
     Wheels(cli::wheels::Args),
 
+    /// Install python applications into isolated environments
+    Tool(cli::tools::Args),
+
+    /// Resolve requirements and write the result to a lockfile
+    Lock(cli::lock::Args),
+
+    /// Convert the lockfile of another tool, like a poetry.lock, into a lockfile without
+    /// resolving again
+    Import(cli::import::Args),
+
+    /// Make a virtual environment exactly match a lockfile
+    Sync(cli::sync::Args),
+
+    /// Add dependencies to the pyproject.toml of a project and update its lockfile
+    Add(cli::project::AddArgs),
+
+    /// Remove dependencies from the pyproject.toml of a project and update its lockfile
+    Remove(cli::project::RemoveArgs),
+
+    /// Create, list and remove virtual environments
+    Venv(cli::venv::Args),
+
+    /// Inspect, clean, verify and warm the cache
+    Cache(cli::cache::Args),
+
+    /// Show the dependency graph of resolved requirements or of an environment as a tree
+    Tree(cli::tree::Args),
+
+    /// Show the metadata of a package from the index or as it is installed in an environment
+    Show(cli::show::Args),
+
+    /// Download the artifacts of resolved requirements into a directory for offline use
+    Download(cli::download::Args),
+
+    /// Build an sdist and/or a wheel of a local project
+    Build(cli::build::Args),
+
+    /// Remove packages from a virtual environment
+    Uninstall(cli::uninstall::Args),
+
+    /// Print the packages that are installed in a virtual environment as pinned requirements
+    Freeze(cli::freeze::Args),
+
+    /// Check the packages of a lockfile or environment for known vulnerabilities
+    Audit(cli::audit::Args),
+
+    /// Write a software bill of materials of a lockfile or environment
+    Sbom(cli::sbom::Args),
+
+    /// Check the licenses of a lockfile or environment against the license policy
+    Licenses(cli::licenses::Args),
+
+    /// Explain why a package is part of the resolved requirements or of an environment
+    Why(cli::why::Args),
+
+    /// Search the index for packages by name
+    Search(cli::search::Args),
+
+    /// Run a command in a cached environment with the requested packages
+    Run(cli::run::Args),
+
     #[command(flatten)]
     InstallOrResolve(cli::resolve::Commands),
 }
 
-async fn actual_main() -> miette::Result<()> {
-    let args = Cli::parse();
+impl Commands {
+    /// Returns true if the command can print its output as JSON.
+    fn supports_json(&self) -> bool {
+        matches!(
+            self,
+            Commands::InstallOrResolve(_)
+                | Commands::Lock(_)
+                | Commands::Import(_)
+                | Commands::Sync(_)
+                | Commands::Uninstall(_)
+                | Commands::Download(_)
+                | Commands::Cache(_)
+                | Commands::Tree(_)
+                | Commands::Show(_)
+                | Commands::Why(_)
+                | Commands::Search(_)
+                | Commands::Freeze(_)
+                | Commands::Audit(_)
+                | Commands::Licenses(_)
+        )
+    }
+
+    /// Returns true if the command installs packages whose contents must always be verified
+    /// against their hashes.
+    fn require_hashes(&self) -> miette::Result<bool> {
+        Ok(match self {
+            Commands::InstallOrResolve(cmds) => cmds.require_hashes()?,
+            Commands::Sync(args) => args.require_hashes(),
+            _ => false,
+        })
+    }
+}
+
+async fn actual_main(cancellation: CancellationToken) -> miette::Result<()> {
+    let mut command = Cli::command();
+    let args = Cli::from_arg_matches(&command.get_matches_mut()).unwrap_or_else(|e| e.exit());
+    if args.format == OutputFormat::Json && !args.command.supports_json() {
+        command
+            .error(
+                ErrorKind::ArgumentConflict,
+                "`--format json` is not supported by this command",
+            )
+            .exit();
+    }
 
     // Setup tracing subscriber
     tracing_subscriber::registry()
-        .with(fmt::layer().with_writer(IndicatifWriter::new(global_multi_progress())))
+        .with(
+            fmt::layer()
+                .with_writer(IndicatifWriter::new(global_multi_progress()))
+                .with_span_events(if args.log_spans {
+                    FmtSpan::CLOSE
+                } else {
+                    FmtSpan::NONE
+                }),
+        )
         .with(
             EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| get_default_env_filter(args.verbose)),
         )
         .init();
 
+    set_output_format(args.format);
+
+    // Load the configuration files, the command line flags take precedence
+    let current_dir = std::env::current_dir().into_diagnostic()?;
+    let config = ConfigLoader::new()
+        .with_project_dir(current_dir)
+        .load()
+        .into_diagnostic()
+        .wrap_err("failed to load the configuration")?;
+    set_global_config(config.clone());
+
     // Determine cache directory
-    let cache_dir = dirs::cache_dir()
-        .ok_or_else(|| miette::miette!("failed to determine cache directory"))?
-        .join("rattler/pypi");
+    let cache_dir = match args.cache_dir.or(config.cache_dir) {
+        Some(cache_dir) => cache_dir,
+        None => dirs::cache_dir()
+            .ok_or_else(|| miette::miette!("failed to determine cache directory"))?
+            .join("rattler/pypi"),
+    };
     tracing::info!("cache directory: {}", cache_dir.display());
 
     // Construct a package database
-    let index_url = normalize_index_url(args.index_url.clone());
-    let sources = PackageSourcesBuilder::new(index_url).build()?;
+    let index_url = args
+        .index_url
+        .or(config.index_url)
+        .unwrap_or_else(|| Url::parse(DEFAULT_INDEX_URL).expect("the default index url is valid"));
+    let find_links = if args.find_links.is_empty() {
+        config.find_links.unwrap_or_default()
+    } else {
+        args.find_links
+    };
+    let no_index = args.no_index || config.no_index == Some(true);
+    if no_index && find_links.is_empty() {
+        miette::bail!("--no-index requires at least one --find-links directory");
+    }
+
+    let mut sources = PackageSourcesBuilder::new(normalize_index_url(index_url.clone()));
+    for find_links in &find_links {
+        sources = sources.with_find_links(find_links);
+    }
+    if no_index {
+        sources = sources.without_index();
+    }
+    let sources = sources.build()?;
+
+    // The cache setting applies to every request, also those to resolve build dependencies
+    let cache_setting = if args.no_cache {
+        CacheSetting::NoCache
+    } else if args.refresh {
+        CacheSetting::Refresh
+    } else if args.offline || config.offline == Some(true) {
+        CacheSetting::Offline
+    } else {
+        CacheSetting::Default
+    };
+
+    // Verify the index with TUF metadata if the repository is configured to serve it
+    let tuf_repository = match (config.tuf.root, config.tuf.metadata_url) {
+        (Some(root), Some(metadata_url)) => Some(
+            config
+                .tuf
+                .target_urls
+                .unwrap_or_default()
+                .into_iter()
+                .fold(TufRepository::new(metadata_url, root), |repository, url| {
+                    repository.with_target_base_url(url)
+                }),
+        ),
+        (None, None) => None,
+        _ => miette::bail!("the [tuf] configuration requires both `root` and `metadata-url`"),
+    };
+
+    // Show the progress of downloads, builds and installs
+    let reporter = Arc::new(ProgressReporter::new(global_multi_progress()));
 
     let client = ClientWithMiddleware::from(Client::new());
-    let package_db = Arc::new(
+    let mut package_db =
         rattler_installs_packages::index::PackageDb::new(sources, client, &cache_dir)
             .wrap_err_with(|| {
                 format!(
                     "failed to construct package database for index {}",
-                    args.index_url
+                    index_url
                 )
-            })?,
-    );
+            })?
+            .with_cache_setting(cache_setting)
+            .with_concurrency_limits(ConcurrencyLimits {
+                total: args.max_connections.or(config.max_connections),
+                per_host: args
+                    .max_connections_per_host
+                    .or(config.max_connections_per_host),
+            })
+            .with_forge_tokens(ForgeTokens::from_env())
+            .with_reporter(reporter.clone())
+            .with_cancellation_token(cancellation)
+            .with_require_hashes(args.command.require_hashes()?);
+    #[cfg(feature = "git")]
+    {
+        package_db = package_db.with_git_auth(git_auth(&config.git));
+    }
+    if let Some(tuf_repository) = tuf_repository {
+        package_db = package_db.with_tuf_repository(tuf_repository);
+    }
+    if args.typosquatting_check || config.typosquatting_check == Some(true) {
+        package_db = package_db.with_typosquatting_check(TyposquattingCheck::default());
+    }
+    let package_rules = config.policy.package_rules();
+    if !package_rules.is_empty() {
+        package_db = package_db.with_package_policy(Arc::new(package_rules));
+    }
+    let package_db = Arc::new(package_db);
 
-    match args.command {
+    let result = match args.command {
         Commands::InstallOrResolve(cmds) => cli::resolve::execute(package_db.clone(), cmds).await,
         Commands::Wheels(args) => wheels(package_db.clone(), args),
+        Commands::Tool(args) => cli::tools::tools(package_db.clone(), args).await,
+        Commands::Lock(args) => cli::lock::lock(package_db.clone(), args).await,
+        Commands::Import(args) => cli::import::import(package_db.clone(), args).await,
+        Commands::Sync(args) => cli::sync::sync(package_db.clone(), args).await,
+        Commands::Add(args) => cli::project::add(package_db.clone(), args).await,
+        Commands::Remove(args) => cli::project::remove(package_db.clone(), args).await,
+        Commands::Venv(args) => cli::venv::venv(package_db.clone(), args).await,
+        Commands::Cache(args) => cli::cache::cache(package_db.clone(), args).await,
+        Commands::Tree(args) => cli::tree::tree(package_db.clone(), args).await,
+        Commands::Show(args) => cli::show::show(package_db.clone(), args).await,
+        Commands::Download(args) => cli::download::download(package_db.clone(), args).await,
+        Commands::Build(args) => cli::build::build(package_db.clone(), args).await,
+        Commands::Uninstall(args) => cli::uninstall::uninstall(args).await,
+        Commands::Freeze(args) => cli::freeze::freeze(package_db.clone(), args).await,
+        Commands::Audit(args) => cli::audit::audit(package_db.clone(), args).await,
+        Commands::Sbom(args) => cli::sbom::sbom(package_db.clone(), args).await,
+        Commands::Licenses(args) => cli::licenses::licenses(package_db.clone(), args).await,
+        Commands::Why(args) => cli::why::why(package_db.clone(), args).await,
+        Commands::Search(args) => cli::search::search(package_db.clone(), args).await,
+        Commands::Run(args) => cli::run::run(package_db.clone(), args).await,
+    };
+
+    reporter.finish();
+    if let Some(metrics_file) = &args.metrics_file {
+        let metrics =
+            serde_json::to_vec_pretty(&package_db.metrics().snapshot()).into_diagnostic()?;
+        std::fs::write(metrics_file, metrics)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write metrics to {}", metrics_file.display()))?;
+    }
+    result
+}
+
+/// Constructs the authentication of the `git` commands from the `[git]` configuration.
+#[cfg(feature = "git")]
+fn git_auth(config: &GitConfig) -> GitAuth {
+    let git_auth = GitAuth::default()
+        .with_ssh_agent(config.ssh_agent.unwrap_or(true))
+        .with_credential_callback(Arc::new(SshPassphrasePrompt));
+    match &config.ssh_key {
+        Some(ssh_key) => git_auth.with_ssh_key(ssh_key),
+        None => git_auth,
     }
 }
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = actual_main().await {
+    let cancellation = CancellationToken::new();
+    tokio::spawn(cancel_on_interrupt(cancellation.clone()));
+    if let Err(e) = actual_main(cancellation.clone()).await {
         eprintln!("{e:?}");
+        std::process::exit(if cancellation.is_cancelled() { 130 } else { 1 });
+    }
+}
+
+/// Cancels `cancellation` when the process is interrupted, so the operations that are in flight
+/// can stop and clean up after themselves. A second interrupt exits immediately.
+async fn cancel_on_interrupt(cancellation: CancellationToken) {
+    if tokio::signal::ctrl_c().await.is_err() {
+        return;
+    }
+    eprintln!(
+        "interrupted, stopping the running operations (press Ctrl-C again to exit immediately)"
+    );
+    cancellation.cancel();
+    if tokio::signal::ctrl_c().await.is_ok() {
+        std::process::exit(130);
     }
 }
 
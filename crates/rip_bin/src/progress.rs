@@ -0,0 +1,218 @@
+//! Renders the progress events of the library as progress bars.
+
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use parking_lot::Mutex;
+use rattler_installs_packages::reporter::Reporter;
+use rattler_installs_packages::types::{SourceArtifactName, WheelFilename};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// A [`Reporter`] that shows every download and build as a separate progress bar, and all
+/// installs as a single bar. When the command finishes [`ProgressReporter::finish`] prints a
+/// summary.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    multi_progress: MultiProgress,
+    start: Instant,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The bars of the downloads and builds by their index
+    bars: Vec<Option<ProgressBar>>,
+    /// The index of the bar of the builds that are running, by the name of the artifact
+    build_bars: HashMap<SourceArtifactName, usize>,
+    install_bar: Option<ProgressBar>,
+    downloads: usize,
+    bytes_downloaded: u64,
+    builds: usize,
+    installs: usize,
+    removals: usize,
+}
+
+impl State {
+    fn add_bar(&mut self, bar: ProgressBar) -> usize {
+        self.bars.push(Some(bar));
+        self.bars.len() - 1
+    }
+}
+
+impl ProgressReporter {
+    /// Constructs a new instance that adds its bars to `multi_progress`.
+    pub fn new(multi_progress: MultiProgress) -> Self {
+        Self {
+            multi_progress,
+            start: Instant::now(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Removes the remaining bars and prints a summary of what was done, if anything.
+    pub fn finish(&self) {
+        let mut state = self.state.lock();
+        for bar in state.bars.iter_mut().filter_map(Option::take) {
+            bar.finish_and_clear();
+        }
+        if let Some(bar) = state.install_bar.take() {
+            bar.finish_and_clear();
+        }
+
+        let mut parts = Vec::new();
+        if state.downloads > 0 {
+            parts.push(format!(
+                "downloaded {} artifact(s) ({})",
+                state.downloads,
+                HumanBytes(state.bytes_downloaded)
+            ));
+        }
+        if state.builds > 0 {
+            parts.push(format!("built {} wheel(s)", state.builds));
+        }
+        if state.removals > 0 {
+            parts.push(format!("removed {} package(s)", state.removals));
+        }
+        if state.installs > 0 {
+            parts.push(format!("installed {} package(s)", state.installs));
+        }
+        let Some((last, rest)) = parts.split_last() else {
+            return;
+        };
+        let summary = if rest.is_empty() {
+            last.clone()
+        } else {
+            format!("{} and {last}", rest.join(", "))
+        };
+        let _ = self.multi_progress.println(format!(
+            "{} in {}",
+            console::style(capitalize(&summary)).bold(),
+            HumanDuration(self.start.elapsed())
+        ));
+    }
+}
+
+impl Reporter for ProgressReporter {
+    fn on_download_start(&self, url: &Url, total_bytes: Option<u64>) -> usize {
+        let filename = url
+            .path_segments()
+            .and_then(|segments| segments.last())
+            .unwrap_or(url.as_str())
+            .to_string();
+        let bar = self.multi_progress.add(match total_bytes {
+            Some(total_bytes) => ProgressBar::new(total_bytes).with_style(download_style()),
+            None => ProgressBar::new_spinner().with_style(download_spinner_style()),
+        });
+        bar.set_prefix(filename);
+        bar.enable_steady_tick(Duration::from_millis(100));
+        self.state.lock().add_bar(bar)
+    }
+
+    fn on_download_progress(&self, index: usize, bytes_downloaded: u64) {
+        if let Some(Some(bar)) = self.state.lock().bars.get(index) {
+            bar.set_position(bytes_downloaded);
+        }
+    }
+
+    fn on_download_complete(&self, index: usize) {
+        let mut state = self.state.lock();
+        if let Some(bar) = state.bars.get_mut(index).and_then(Option::take) {
+            state.downloads += 1;
+            state.bytes_downloaded += bar.position();
+            bar.finish_and_clear();
+        }
+    }
+
+    fn on_build_start(&self, name: &SourceArtifactName) -> usize {
+        let bar = self
+            .multi_progress
+            .add(ProgressBar::new_spinner().with_style(build_style()));
+        bar.set_prefix(name.to_string());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        let mut state = self.state.lock();
+        let index = state.add_bar(bar);
+        state.build_bars.insert(name.clone(), index);
+        index
+    }
+
+    fn on_build_output(&self, name: &SourceArtifactName, line: &str) {
+        let state = self.state.lock();
+        let bar = state
+            .build_bars
+            .get(name)
+            .and_then(|index| state.bars.get(*index))
+            .and_then(Option::as_ref);
+        if let Some(bar) = bar {
+            if !line.trim().is_empty() {
+                bar.set_message(line.trim().to_string());
+            }
+        }
+    }
+
+    fn on_build_complete(&self, index: usize) {
+        let mut state = self.state.lock();
+        state
+            .build_bars
+            .retain(|_, build_index| *build_index != index);
+        if let Some(bar) = state.bars.get_mut(index).and_then(Option::take) {
+            state.builds += 1;
+            bar.finish_and_clear();
+        }
+    }
+
+    fn on_install_start(&self, name: &WheelFilename) -> usize {
+        let mut state = self.state.lock();
+        let bar = state.install_bar.get_or_insert_with(|| {
+            self.multi_progress
+                .add(ProgressBar::new(0).with_style(install_style()))
+        });
+        bar.inc_length(1);
+        bar.set_message(name.distribution.as_source_str().to_string());
+        0
+    }
+
+    fn on_install_complete(&self, _index: usize) {
+        let mut state = self.state.lock();
+        state.installs += 1;
+        if let Some(bar) = &state.install_bar {
+            bar.inc(1);
+        }
+    }
+
+    fn on_uninstall_complete(&self, _index: usize) {
+        self.state.lock().removals += 1;
+    }
+}
+
+fn download_style() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{spinner:.green} {prefix:40!} [{bar:20}] {bytes:>10}/{total_bytes:<10} {bytes_per_sec}",
+    )
+    .expect("valid template")
+    .progress_chars("=> ")
+}
+
+fn download_spinner_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green} {prefix:40!} {bytes:>10} {bytes_per_sec}")
+        .expect("valid template")
+}
+
+fn build_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green} building {prefix} ({elapsed}) {wide_msg:.dim}")
+        .expect("valid template")
+}
+
+fn install_style() -> ProgressStyle {
+    ProgressStyle::with_template("{spinner:.green} installing [{bar:20}] {pos}/{len} {msg}")
+        .expect("valid template")
+        .progress_chars("=> ")
+}
+
+/// Makes the first character of `text` uppercase.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
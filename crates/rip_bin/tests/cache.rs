@@ -0,0 +1,76 @@
+//! Runs the `rip cache` commands against a cache that was filled by an install.
+
+mod common;
+
+use common::{rip_failure, rip_json, rip_success};
+use serde_json::Value;
+
+/// Returns the number of files in a bucket of the `rip cache info` output.
+fn bucket_files(info: &Value, name: &str) -> u64 {
+    info["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|bucket| bucket["name"] == name)
+        .unwrap_or_else(|| panic!("no bucket named {name}"))["files"]
+        .as_u64()
+        .unwrap()
+}
+
+#[test]
+fn test_cache_clean() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let venv = dir.path().join("venv");
+    rip_success(
+        &cache_dir,
+        &["install", "wordle_python", venv.to_str().unwrap()],
+    );
+
+    let json = rip_json(&cache_dir, &["cache", "dir"]);
+    assert_eq!(json["command"], "cache dir");
+    assert_eq!(json["path"], cache_dir.to_str().unwrap());
+
+    let info = rip_json(&cache_dir, &["cache", "info"]);
+    assert_eq!(info["command"], "cache info");
+    assert!(bucket_files(&info, "wheel-files") > 0);
+    let total = info["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket["files"].as_u64().unwrap())
+        .sum::<u64>();
+    assert_eq!(info["files"], total);
+
+    // Nothing in the fresh cache is older than a day
+    let json = rip_json(&cache_dir, &["cache", "clean", "--older-than", "1d"]);
+    assert_eq!(json["command"], "cache clean");
+    assert_eq!(json["files"], 0);
+    assert_eq!(json["size"], 0);
+
+    // The files of wheels that are linked into environments are kept by default
+    let json = rip_json(&cache_dir, &["cache", "clean"]);
+    assert!(json["files"].as_u64().unwrap() > 0);
+    let info = rip_json(&cache_dir, &["cache", "info"]);
+    assert!(bucket_files(&info, "wheel-files") > 0);
+
+    rip_json(&cache_dir, &["cache", "clean", "--wheel-files"]);
+    let info = rip_json(&cache_dir, &["cache", "info"]);
+    assert_eq!(bucket_files(&info, "wheel-files"), 0);
+
+    let json = rip_json(&cache_dir, &["cache", "verify"]);
+    assert_eq!(json["corrupted"], Value::Array(Vec::new()));
+}
+
+#[test]
+fn test_cache_clean_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+
+    let stderr = rip_failure(&cache_dir, &["cache", "clean", "--older-than", "30"]);
+    assert!(stderr.contains("missing a unit in '30'"), "{stderr}");
+    let stderr = rip_failure(&cache_dir, &["cache", "clean", "--older-than", "2y"]);
+    assert!(stderr.contains("unknown unit 'y'"), "{stderr}");
+    let stderr = rip_failure(&cache_dir, &["cache", "clean", "--package", "not a name"]);
+    assert!(stderr.contains("--package"), "{stderr}");
+}
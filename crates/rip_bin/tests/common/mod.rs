@@ -0,0 +1,95 @@
+//! Helpers to run the `rip` binary against the local project and the wheels in `test-data`, so no
+//! network access is needed.
+
+// Every test binary only uses some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+pub fn test_data() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test-data")
+}
+
+/// Runs `rip` with the given arguments from the directory of the local project. Packages are only
+/// taken from the wheels in `test-data`.
+pub fn rip(cache_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rip"))
+        .current_dir(test_data().join("stree/local_project"))
+        .arg("--no-index")
+        .arg("--find-links")
+        .arg(test_data().join("wheels"))
+        .arg("--cache-dir")
+        .arg(cache_dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs `rip` like [`rip`] and panics if it fails.
+pub fn rip_success(cache_dir: &Path, args: &[&str]) -> Output {
+    let output = rip(cache_dir, args);
+    assert!(
+        output.status.success(),
+        "rip {} failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// Runs `rip --format json` with the given arguments and returns the parsed document.
+pub fn rip_json(cache_dir: &Path, args: &[&str]) -> serde_json::Value {
+    let args = [&["--format", "json"], args].concat();
+    let output = rip_success(cache_dir, &args);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_else(|err| {
+        panic!(
+            "rip {} did not print JSON: {err}\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout)
+        )
+    });
+    assert_eq!(json["schema_version"], 1);
+    json
+}
+
+/// Returns the stderr of a failed `rip` invocation.
+pub fn rip_failure(cache_dir: &Path, args: &[&str]) -> String {
+    let output = rip(cache_dir, args);
+    assert!(
+        !output.status.success(),
+        "rip {} unexpectedly succeeded",
+        args.join(" ")
+    );
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Returns the site-packages directory of the virtual environment at `venv`.
+pub fn site_packages(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        return venv.join("Lib").join("site-packages");
+    }
+    let lib = std::fs::read_dir(venv.join("lib"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| {
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("python")
+        })
+        .unwrap();
+    lib.join("site-packages")
+}
+
+/// Returns the names of the packages in a list of `{ "name": ..., "version": ... }` objects.
+pub fn names(packages: &serde_json::Value) -> Vec<&str> {
+    let mut names = packages
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|package| package["name"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
@@ -0,0 +1,71 @@
+//! Runs `rip install` against a local project. The project is built with an in-tree build backend
+//! and its dependencies are taken from the wheels in `test-data`, so no network access is needed.
+
+mod common;
+
+use common::{names, rip_failure, rip_json, rip_success, site_packages};
+use std::path::Path;
+
+#[test]
+fn test_install_local_project() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let venv = dir.path().join("venv");
+    let venv = venv.to_str().unwrap();
+
+    rip_success(&cache_dir, &["install", ".", venv]);
+    let site_packages = site_packages(Path::new(venv));
+    assert!(site_packages.join("local_project.py").is_file());
+    assert!(site_packages.join("local_project-0.1.0.dist-info").is_dir());
+    assert!(!site_packages
+        .join("wordle_python-2.3.32.dist-info")
+        .exists());
+
+    // Selecting an extra of the project installs its optional dependencies
+    rip_success(&cache_dir, &["install", ".[words]", venv]);
+    assert!(site_packages
+        .join("wordle_python-2.3.32.dist-info")
+        .is_dir());
+}
+
+#[test]
+fn test_install_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let venv = dir.path().join("venv");
+
+    let json = rip_json(
+        &cache_dir,
+        &["install", "wordle_python", venv.to_str().unwrap()],
+    );
+    assert_eq!(json["command"], "install");
+    assert_eq!(json["resolved"], true);
+    assert_eq!(json["packages"]["wordle-python"], "2.3.32");
+    assert_eq!(names(&json["report"]["install"]), ["wordle-python"]);
+    assert_eq!(names(&json["report"]["remove"]), Vec::<&str>::new());
+}
+
+#[test]
+fn test_install_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+
+    // The last argument is the target, so a single argument leaves nothing to install
+    let stderr = rip_failure(&cache_dir, &["install", "venv"]);
+    assert!(
+        stderr.contains("no requirements to install into venv"),
+        "{stderr}"
+    );
+
+    // A requirement with a version cannot be the target
+    let stderr = rip_failure(&cache_dir, &["install", ".", "wordle_python==2.3.32"]);
+    assert!(
+        stderr.contains("the last argument must be the target directory"),
+        "{stderr}"
+    );
+
+    // With --user there is no target, all arguments are specs so this fails to resolve `venv`
+    let stderr = rip_failure(&cache_dir, &["install", "--user", "venv"]);
+    assert!(!stderr.contains("install into"), "{stderr}");
+    assert!(!stderr.contains("target directory"), "{stderr}");
+}
@@ -0,0 +1,49 @@
+//! Runs `rip lock` and `rip sync` with the wheels in `test-data`.
+
+mod common;
+
+use common::{names, rip_json, rip_success, site_packages};
+
+#[test]
+fn test_sync_dry_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let lockfile = dir.path().join("rip.lock");
+    let lockfile = lockfile.to_str().unwrap();
+    let venv = dir.path().join("venv");
+    let venv_str = venv.to_str().unwrap();
+
+    let json = rip_json(&cache_dir, &["lock", "wordle_python", "-o", lockfile]);
+    assert_eq!(json["command"], "lock");
+    assert_eq!(json["written"][0], lockfile);
+    assert_eq!(names(&json["targets"][0]["packages"]), ["wordle-python"]);
+
+    // A dry run reports the changes without creating the environment
+    let json = rip_json(
+        &cache_dir,
+        &["sync", venv_str, "--lockfile", lockfile, "--dry-run"],
+    );
+    assert_eq!(json["command"], "sync");
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(names(&json["report"]["install"]), ["wordle-python"]);
+    assert_eq!(json["report"]["install"][0]["version"], "2.3.32");
+    assert!(!venv.exists());
+
+    let json = rip_json(&cache_dir, &["sync", venv_str, "--lockfile", lockfile]);
+    assert_eq!(json["dry_run"], false);
+    assert_eq!(names(&json["report"]["install"]), ["wordle-python"]);
+    assert!(site_packages(&venv)
+        .join("wordle_python-2.3.32.dist-info")
+        .is_dir());
+
+    // Once synchronized there is nothing left to do
+    let json = rip_json(
+        &cache_dir,
+        &["sync", venv_str, "--lockfile", lockfile, "--dry-run"],
+    );
+    assert_eq!(names(&json["report"]["install"]), Vec::<&str>::new());
+    assert_eq!(names(&json["report"]["remove"]), Vec::<&str>::new());
+
+    let output = rip_success(&cache_dir, &["sync", venv_str, "--lockfile", lockfile]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("The environment is up to date"));
+}
@@ -0,0 +1,99 @@
+//! Runs `rip uninstall` against an environment with the local project and its optional
+//! dependencies installed.
+
+mod common;
+
+use common::{names, rip_failure, rip_json, rip_success, site_packages, test_data};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Copies the local project to `dir` and makes `wordle_python` a required dependency of it. The
+/// dependencies of an extra are not known once installed, so they are never orphaned.
+fn project_with_dependency(dir: &Path) -> PathBuf {
+    let source = test_data().join("stree/local_project");
+    let project = dir.join("project");
+    fs::create_dir(&project).unwrap();
+    for file in ["backend.py", "local_project.py", "pyproject.toml"] {
+        let contents = fs::read_to_string(source.join(file))
+            .unwrap()
+            .replace("wordle_python ; extra == \"words\"", "wordle_python")
+            .replace("dependencies = []", "dependencies = [\"wordle_python\"]");
+        fs::write(project.join(file), contents).unwrap();
+    }
+    project
+}
+
+#[test]
+fn test_uninstall_orphans() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let venv = dir.path().join("venv");
+    let venv_str = venv.to_str().unwrap();
+    let project = project_with_dependency(dir.path());
+    rip_success(
+        &cache_dir,
+        &["install", project.to_str().unwrap(), venv_str],
+    );
+    let site_packages = site_packages(&venv);
+    let installed = |name: &str| site_packages.join(name).is_dir();
+
+    // Without `--orphans` only the requested package is removed
+    let json = rip_json(
+        &cache_dir,
+        &["uninstall", venv_str, "local-project", "--dry-run"],
+    );
+    assert_eq!(json["command"], "uninstall");
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(names(&json["removed"]), ["local-project"]);
+
+    // The dependency that is no longer required is removed with `--orphans`
+    let json = rip_json(
+        &cache_dir,
+        &[
+            "uninstall",
+            venv_str,
+            "local-project",
+            "--orphans",
+            "--dry-run",
+        ],
+    );
+    assert_eq!(names(&json["removed"]), ["local-project", "wordle-python"]);
+    assert_eq!(json["removed"][1]["version"], "2.3.32");
+    assert!(installed("local_project-0.1.0.dist-info"));
+    assert!(installed("wordle_python-2.3.32.dist-info"));
+
+    let json = rip_json(
+        &cache_dir,
+        &["uninstall", venv_str, "local-project", "--orphans", "--yes"],
+    );
+    assert_eq!(json["dry_run"], false);
+    assert_eq!(names(&json["removed"]), ["local-project", "wordle-python"]);
+    assert!(!installed("local_project-0.1.0.dist-info"));
+    assert!(!installed("wordle_python-2.3.32.dist-info"));
+    assert!(!site_packages.join("local_project.py").exists());
+}
+
+#[test]
+fn test_uninstall_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let venv = dir.path().join("venv");
+    let venv_str = venv.to_str().unwrap();
+
+    let stderr = rip_failure(&cache_dir, &["uninstall", venv_str, "wordle-python"]);
+    assert!(stderr.contains("is not a virtual environment"), "{stderr}");
+
+    rip_success(&cache_dir, &["install", "wordle_python", venv_str]);
+    let stderr = rip_failure(&cache_dir, &["uninstall", venv_str, "miniblack", "--yes"]);
+    assert!(stderr.contains("miniblack is not installed"), "{stderr}");
+
+    // JSON output cannot be combined with the confirmation prompt
+    let stderr = rip_failure(
+        &cache_dir,
+        &["--format", "json", "uninstall", venv_str, "wordle-python"],
+    );
+    assert!(stderr.contains("pass `--yes` or `--dry-run`"), "{stderr}");
+    assert!(site_packages(Path::new(venv_str))
+        .join("wordle_python-2.3.32.dist-info")
+        .is_dir());
+}
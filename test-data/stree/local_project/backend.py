@@ -0,0 +1,42 @@
+"""A minimal PEP 517 build backend that packages `local_project.py` without any dependencies."""
+
+import base64
+import hashlib
+import zipfile
+
+NAME = "local_project"
+VERSION = "0.1.0"
+METADATA = """Metadata-Version: 2.1
+Name: local-project
+Version: 0.1.0
+Provides-Extra: words
+Requires-Dist: wordle_python ; extra == "words"
+"""
+WHEEL = """Wheel-Version: 1.0
+Generator: backend
+Root-Is-Purelib: true
+Tag: py3-none-any
+"""
+
+
+def _record_line(path, contents):
+    digest = base64.urlsafe_b64encode(hashlib.sha256(contents).digest()).rstrip(b"=")
+    return f"{path},sha256={digest.decode()},{len(contents)}"
+
+
+def build_wheel(wheel_directory, config_settings=None, metadata_directory=None):
+    dist_info = f"{NAME}-{VERSION}.dist-info"
+    files = {
+        f"{NAME}.py": open(f"{NAME}.py", "rb").read(),
+        f"{dist_info}/METADATA": METADATA.encode(),
+        f"{dist_info}/WHEEL": WHEEL.encode(),
+    }
+    wheel_name = f"{NAME}-{VERSION}-py3-none-any.whl"
+    with zipfile.ZipFile(f"{wheel_directory}/{wheel_name}", "w") as wheel:
+        record = []
+        for path, contents in files.items():
+            wheel.writestr(path, contents)
+            record.append(_record_line(path, contents))
+        record.append(f"{dist_info}/RECORD,,")
+        wheel.writestr(f"{dist_info}/RECORD", "\n".join(record) + "\n")
+    return wheel_name